@@ -1,10 +1,12 @@
 #![cfg_attr(
-    not(any(feature = "vulkan", feature = "dx12", feature = "metal")),
+    not(any(feature = "vulkan", feature = "dx11", feature = "dx12", feature = "metal")),
     allow(dead_code, unused_extern_crates, unused_imports)
 )]
 
 extern crate env_logger;
 extern crate gfx_hal as hal;
+#[cfg(feature = "dx11")]
+extern crate gfx_backend_dx11 as back;
 #[cfg(feature = "dx12")]
 extern crate gfx_backend_dx12 as back;
 #[cfg(feature = "vulkan")]
@@ -19,7 +21,7 @@ use hal::{
 };
 use hal::{queue, pso, memory, buffer, pool, command};
 
-#[cfg(any(feature = "vulkan", feature = "dx12", feature = "metal"))]
+#[cfg(any(feature = "vulkan", feature = "dx11", feature = "dx12", feature = "metal"))]
 fn main() {
     env_logger::init();
 
@@ -31,10 +33,10 @@ fn main() {
         .collect();
     let stride = std::mem::size_of::<u32>() as u64;
 
-    #[cfg(any(feature = "vulkan", feature = "dx12", feature = "metal"))]
+    #[cfg(any(feature = "vulkan", feature = "dx11", feature = "dx12", feature = "metal"))]
     let instance = back::Instance::create("gfx-rs compute", 1);
 
-    let adapter = instance.enumerate_adapters().into_iter()
+    let adapter = instance.enumerate_adapters_compute_only().into_iter()
         .find(|a| a.queue_families
             .iter()
             .any(|family| family.supports_compute())
@@ -55,6 +57,9 @@ fn main() {
                     ty: pso::DescriptorType::StorageBuffer,
                     count: 1,
                     stage_flags: pso::ShaderStageFlags::COMPUTE,
+                    update_frequency: None,
+                    rasterizer_ordered: false,
+                    immutable_samplers: Vec::new(),
                 }
             ],
         );
@@ -64,6 +69,9 @@ fn main() {
         let pipeline = device
             .create_compute_pipeline(&pso::ComputePipelineDesc::new(entry_point, &pipeline_layout))
             .expect("Error creating compute pipeline!");
+        if let Some(statistics) = device.get_compute_pipeline_statistics(&pipeline) {
+            println!("Pipeline statistics: {:?}", statistics);
+        }
 
         let desc_pool = device.create_descriptor_pool(
             1,
@@ -73,6 +81,7 @@ fn main() {
                     count: 1,
                 },
             ],
+            pso::DescriptorPoolCreateFlags::empty(),
         );
         (pipeline_layout, pipeline, set_layout, desc_pool)
     };
@@ -123,7 +132,8 @@ fn main() {
             memory::Dependencies::empty(),
             Some(memory::Barrier::Buffer {
                 states: buffer::Access::TRANSFER_WRITE .. buffer::Access::SHADER_READ | buffer::Access::SHADER_WRITE,
-                target: &device_buffer
+                target: &device_buffer,
+                families: None,
             }),
         );
         command_buffer.bind_compute_pipeline(&pipeline);
@@ -134,7 +144,8 @@ fn main() {
             memory::Dependencies::empty(),
             Some(memory::Barrier::Buffer {
                 states: buffer::Access::SHADER_READ | buffer::Access::SHADER_WRITE .. buffer::Access::TRANSFER_READ,
-                target: &device_buffer
+                target: &device_buffer,
+                families: None,
             }),
         );
         command_buffer.copy_buffer(&device_buffer, &staging_buffer, &[command::BufferCopy { src: 0, dst: 0, size: stride * numbers.len() as u64}]);
@@ -189,7 +200,7 @@ fn create_buffer<B: Backend>(
     (memory, buffer)
 }
 
-#[cfg(not(any(feature = "vulkan", feature = "dx12", feature = "metal")))]
+#[cfg(not(any(feature = "vulkan", feature = "dx11", feature = "dx12", feature = "metal")))]
 fn main() {
-    println!("You need to enable one of the next-gen API feature (vulkan, dx12, metal) to run this example.");
+    println!("You need to enable one of the next-gen API feature (vulkan, dx12, dx11, metal) to run this example.");
 }
@@ -1,10 +1,12 @@
 #![cfg_attr(
-    not(any(feature = "vulkan", feature = "dx12", feature = "metal", feature = "gl")),
+    not(any(feature = "vulkan", feature = "dx11", feature = "dx12", feature = "metal", feature = "gl")),
     allow(dead_code, unused_extern_crates, unused_imports)
 )]
 
 extern crate env_logger;
 extern crate gfx_hal as hal;
+#[cfg(feature = "dx11")]
+extern crate gfx_backend_dx11 as back;
 #[cfg(feature = "dx12")]
 extern crate gfx_backend_dx12 as back;
 #[cfg(feature = "vulkan")]
@@ -55,7 +57,7 @@ const COLOR_RANGE: i::SubresourceRange = i::SubresourceRange {
     layers: 0 .. 1,
 };
 
-#[cfg(any(feature = "vulkan", feature = "dx12", feature = "metal", feature = "gl"))]
+#[cfg(any(feature = "vulkan", feature = "dx11", feature = "dx12", feature = "metal", feature = "gl"))]
 fn main() {
     env_logger::init();
 
@@ -67,7 +69,7 @@ fn main() {
     let wb = winit::WindowBuilder::new()
         .with_dimensions(1024, 768)
         .with_title("quad".to_string());
-    #[cfg(any(feature = "vulkan", feature = "dx12", feature = "metal"))]
+    #[cfg(any(feature = "vulkan", feature = "dx11", feature = "dx12", feature = "metal"))]
     let window = wb
         .build(&events_loop)
         .unwrap();
@@ -86,7 +88,7 @@ fn main() {
     let pixel_height = window_size.1 as u16;
 
     // instantiate backend
-    #[cfg(any(feature = "vulkan", feature = "dx12", feature = "metal"))]
+    #[cfg(any(feature = "vulkan", feature = "dx11", feature = "dx12", feature = "metal"))]
     let (_instance, mut adapters, mut surface) = {
         let instance = back::Instance::create("gfx-rs quad", 1);
         let surface = instance.create_surface(&window);
@@ -141,7 +143,8 @@ fn main() {
     let swap_config = SwapchainConfig::new()
         .with_color(surface_format)
         .with_image_usage(i::Usage::COLOR_ATTACHMENT);
-    let (mut swap_chain, backbuffer) = device.create_swapchain(&mut surface, swap_config);
+    let (mut swap_chain, backbuffer) = device.create_swapchain(&mut surface, swap_config)
+        .expect("Can't create swapchain");
 
     // Setup renderpass and pipeline
     let set_layout = device.create_descriptor_set_layout(&[
@@ -150,12 +153,18 @@ fn main() {
                 ty: pso::DescriptorType::SampledImage,
                 count: 1,
                 stage_flags: ShaderStageFlags::FRAGMENT,
+                update_frequency: None,
+                rasterizer_ordered: false,
+                immutable_samplers: Vec::new(),
             },
             pso::DescriptorSetLayoutBinding {
                 binding: 1,
                 ty: pso::DescriptorType::Sampler,
                 count: 1,
                 stage_flags: ShaderStageFlags::FRAGMENT,
+                update_frequency: None,
+                rasterizer_ordered: false,
+                immutable_samplers: Vec::new(),
             },
         ],
     );
@@ -180,6 +189,8 @@ fn main() {
             depth_stencil: None,
             inputs: &[],
             preserves: &[],
+            view_mask: 0,
+            correlation_mask: 0,
         };
 
         let dependency = pass::SubpassDependency {
@@ -320,6 +331,7 @@ fn main() {
                 count: 1,
             },
         ],
+        pso::DescriptorPoolCreateFlags::empty(),
     );
     let desc_set = desc_pool.allocate_set(&set_layout).unwrap();
 
@@ -480,6 +492,7 @@ fn main() {
                         (i::Access::TRANSFER_WRITE, i::Layout::TransferDstOptimal),
                 target: &image_logo,
                 range: COLOR_RANGE.clone(),
+                families: None,
             };
             cmd_buffer.pipeline_barrier(
                 PipelineStage::TOP_OF_PIPE .. PipelineStage::TRANSFER,
@@ -509,6 +522,7 @@ fn main() {
                         (i::Access::SHADER_READ, i::Layout::ShaderReadOnlyOptimal),
                 target: &image_logo,
                 range: COLOR_RANGE.clone(),
+                families: None,
             };
             cmd_buffer.pipeline_barrier(
                 PipelineStage::TRANSFER .. PipelineStage::FRAGMENT_SHADER,
@@ -617,7 +631,7 @@ fn main() {
     device.destroy_swapchain(swap_chain);
 }
 
-#[cfg(not(any(feature = "vulkan", feature = "dx12", feature = "metal", feature = "gl")))]
+#[cfg(not(any(feature = "vulkan", feature = "dx11", feature = "dx12", feature = "metal", feature = "gl")))]
 fn main() {
     println!("You need to enable the native API feature (vulkan/metal) in order to test the LL");
 }
@@ -0,0 +1,239 @@
+//! Runtime backend selection for gfx-hal.
+//!
+//! `Instance` creates the instances of all backends compiled into this crate
+//! which are available on the system, and enumerates their adapters in order
+//! of preference: D3D12 on Windows and Metal on macOS first, then Vulkan and
+//! finally OpenGL. Applications pick one of the adapters at runtime and
+//! dispatch on its backend, instead of selecting the backend with `cfg`
+//! attributes at compile time.
+//!
+//! OpenGL adapters are tied to a context, which is passed in with
+//! `Instance::set_gl` from either a window surface or a headless context.
+//!
+//! ```no_run
+//! extern crate gfx_backend_auto as auto;
+//!
+//! # fn main() {
+//! let instance = auto::Instance::create("app", 1);
+//! for adapter in instance.enumerate_adapters_all() {
+//!     println!("{}: {}", adapter.backend().name(), adapter.info().name);
+//! }
+//! # }
+//! ```
+
+#![deny(missing_docs)]
+
+extern crate gfx_hal as hal;
+#[macro_use]
+extern crate log;
+
+/// The D3D12 backend.
+#[cfg(windows)]
+pub extern crate gfx_backend_dx12 as dx12;
+/// The Metal backend.
+#[cfg(target_os = "macos")]
+pub extern crate gfx_backend_metal as metal;
+/// The Vulkan backend.
+#[cfg(feature = "vulkan")]
+pub extern crate gfx_backend_vulkan as vulkan;
+/// The OpenGL backend.
+#[cfg(feature = "gl")]
+pub extern crate gfx_backend_gl as gl;
+
+use hal::Instance as HalInstance;
+
+/// Backends which can be selected at runtime, in order of preference.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BackendKind {
+    /// D3D12, only available on Windows.
+    Dx12,
+    /// Metal, only available on macOS.
+    Metal,
+    /// Vulkan.
+    Vulkan,
+    /// OpenGL, only available with a context passed to `Instance::set_gl`.
+    Gl,
+}
+
+impl BackendKind {
+    /// Human readable name of the backend.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            BackendKind::Dx12 => "D3D12",
+            BackendKind::Metal => "Metal",
+            BackendKind::Vulkan => "Vulkan",
+            BackendKind::Gl => "OpenGL",
+        }
+    }
+}
+
+/// Adapter of one of the backends, tagged with its backend.
+pub enum Adapter {
+    /// D3D12 adapter.
+    #[cfg(windows)]
+    Dx12(hal::Adapter<dx12::Backend>),
+    /// Metal adapter.
+    #[cfg(target_os = "macos")]
+    Metal(hal::Adapter<metal::Backend>),
+    /// Vulkan adapter.
+    #[cfg(feature = "vulkan")]
+    Vulkan(hal::Adapter<vulkan::Backend>),
+    /// OpenGL adapter.
+    #[cfg(feature = "gl")]
+    Gl(hal::Adapter<gl::Backend>),
+}
+
+impl Adapter {
+    /// Backend of the adapter.
+    pub fn backend(&self) -> BackendKind {
+        match *self {
+            #[cfg(windows)]
+            Adapter::Dx12(_) => BackendKind::Dx12,
+            #[cfg(target_os = "macos")]
+            Adapter::Metal(_) => BackendKind::Metal,
+            #[cfg(feature = "vulkan")]
+            Adapter::Vulkan(_) => BackendKind::Vulkan,
+            #[cfg(feature = "gl")]
+            Adapter::Gl(_) => BackendKind::Gl,
+        }
+    }
+
+    /// General information about the adapter.
+    pub fn info(&self) -> &hal::AdapterInfo {
+        match *self {
+            #[cfg(windows)]
+            Adapter::Dx12(ref adapter) => &adapter.info,
+            #[cfg(target_os = "macos")]
+            Adapter::Metal(ref adapter) => &adapter.info,
+            #[cfg(feature = "vulkan")]
+            Adapter::Vulkan(ref adapter) => &adapter.info,
+            #[cfg(feature = "gl")]
+            Adapter::Gl(ref adapter) => &adapter.info,
+        }
+    }
+}
+
+/// Instances of all backends available on the system.
+pub struct Instance {
+    #[cfg(windows)]
+    dx12: Option<dx12::Instance>,
+    #[cfg(target_os = "macos")]
+    metal: Option<metal::Instance>,
+    #[cfg(feature = "vulkan")]
+    vulkan: Option<vulkan::Instance>,
+    #[cfg(feature = "gl")]
+    gl: Option<Box<HalInstance<Backend = gl::Backend>>>,
+}
+
+impl Instance {
+    /// Create the instances of all backends available on the system.
+    ///
+    /// Backends failing to initialize, e.g. Vulkan without an installed
+    /// loader, are skipped and logged.
+    pub fn create(name: &str, version: u32) -> Self {
+        let instance = Instance {
+            #[cfg(windows)]
+            dx12: dx12::Instance::try_create(name, version),
+            #[cfg(target_os = "macos")]
+            metal: Some(metal::Instance::create(name, version)),
+            #[cfg(feature = "vulkan")]
+            vulkan: vulkan::Instance::try_create(name, version),
+            #[cfg(feature = "gl")]
+            gl: None,
+        };
+        info!("Available backends: {:?}", instance.backends());
+        instance
+    }
+
+    /// Add the OpenGL backend, enumerating the adapter of a window surface
+    /// or headless context.
+    #[cfg(feature = "gl")]
+    pub fn set_gl<I>(&mut self, instance: I)
+    where
+        I: HalInstance<Backend = gl::Backend>,
+    {
+        self.gl = Some(Box::new(instance));
+    }
+
+    /// Backends which were created successfully, in order of preference.
+    pub fn backends(&self) -> Vec<BackendKind> {
+        let mut backends = Vec::new();
+        #[cfg(windows)]
+        {
+            if self.dx12.is_some() {
+                backends.push(BackendKind::Dx12);
+            }
+        }
+        #[cfg(target_os = "macos")]
+        {
+            if self.metal.is_some() {
+                backends.push(BackendKind::Metal);
+            }
+        }
+        #[cfg(feature = "vulkan")]
+        {
+            if self.vulkan.is_some() {
+                backends.push(BackendKind::Vulkan);
+            }
+        }
+        #[cfg(feature = "gl")]
+        {
+            if self.gl.is_some() {
+                backends.push(BackendKind::Gl);
+            }
+        }
+        backends
+    }
+
+    /// Enumerate the adapters of all backends, in order of preference of the backends.
+    ///
+    /// Each backend only reports adapters it supports, so the first adapter
+    /// is a good default choice. The D3D12 backend skips adapters without
+    /// D3D12 support, which are exposed by the Vulkan or OpenGL backends instead.
+    pub fn enumerate_adapters_all(&self) -> Vec<Adapter> {
+        let mut adapters = Vec::new();
+        #[cfg(windows)]
+        {
+            if let Some(ref instance) = self.dx12 {
+                adapters.extend(instance.enumerate_adapters().into_iter().map(Adapter::Dx12));
+            }
+        }
+        #[cfg(target_os = "macos")]
+        {
+            if let Some(ref instance) = self.metal {
+                adapters.extend(instance.enumerate_adapters().into_iter().map(Adapter::Metal));
+            }
+        }
+        #[cfg(feature = "vulkan")]
+        {
+            if let Some(ref instance) = self.vulkan {
+                adapters.extend(instance.enumerate_adapters().into_iter().map(Adapter::Vulkan));
+            }
+        }
+        #[cfg(feature = "gl")]
+        {
+            if let Some(ref instance) = self.gl {
+                adapters.extend(instance.enumerate_adapters().into_iter().map(Adapter::Gl));
+            }
+        }
+        adapters
+    }
+
+    /// The D3D12 instance, if available, e.g. for creating surfaces.
+    #[cfg(windows)]
+    pub fn dx12(&self) -> Option<&dx12::Instance> {
+        self.dx12.as_ref()
+    }
+
+    /// The Metal instance, if available, e.g. for creating surfaces.
+    #[cfg(target_os = "macos")]
+    pub fn metal(&self) -> Option<&metal::Instance> {
+        self.metal.as_ref()
+    }
+
+    /// The Vulkan instance, if available, e.g. for creating surfaces.
+    #[cfg(feature = "vulkan")]
+    pub fn vulkan(&self) -> Option<&vulkan::Instance> {
+        self.vulkan.as_ref()
+    }
+}
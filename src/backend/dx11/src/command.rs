@@ -1,396 +1,1922 @@
-#![allow(missing_docs)]
-
-use std::ptr;
-use winapi::{FLOAT, INT, UINT, UINT8, DXGI_FORMAT,
-             DXGI_FORMAT_R16_UINT, DXGI_FORMAT_R32_UINT,
-             D3D11_CLEAR_FLAG, D3D11_PRIMITIVE_TOPOLOGY, D3D11_VIEWPORT, D3D11_RECT,
-             ID3D11RasterizerState, ID3D11DepthStencilState, ID3D11BlendState};
-use core::{command, pso, shade, state, target, texture as tex};
-use core::{IndexType, VertexCount};
-use core::{MAX_VERTEX_ATTRIBUTES, MAX_CONSTANT_BUFFERS,
-           MAX_RESOURCE_VIEWS, MAX_UNORDERED_VIEWS,
-           MAX_SAMPLERS, MAX_COLOR_TARGETS};
-use {native, Backend, CommandList, Resources, InputLayout, Buffer, Texture, Pipeline, Program};
+use hal::{accel_struct, buffer, command as com, image, indirect, memory, pass, pso, query};
+use hal::{IndexCount, IndexType, InstanceCount, VertexCount, VertexOffset, WorkGroupCount};
+use hal::format::{Aspects, ChannelType, Format};
 
-#[derive(Clone)]
-pub struct SubmitInfo<P> {
-    pub parser: P,
+use std::{cmp, mem, ptr, slice, thread};
+use std::borrow::Borrow;
+use std::ops::Range;
+
+use winapi::shared::{dxgiformat, dxgitype};
+use winapi::shared::minwindef::{FALSE, TRUE, UINT};
+use winapi::shared::winerror;
+use winapi::um::{d3d11, d3d11_1};
+use wio::com::ComPtr;
+
+use device::{Device, ViewInfo};
+use {conv, native as n, shader, Backend, MAX_VERTEX_BUFFERS};
+
+// Number of UAV slots available to fragment and compute shaders.
+const MAX_UAV_SLOTS: usize = d3d11::D3D11_PS_CS_UAV_REGISTER_COUNT as usize;
+
+#[derive(Clone, Copy)]
+struct AttachmentClear {
+    subpass_id: Option<pass::SubpassId>,
+    value: Option<com::ClearValueRaw>,
+    stencil_value: Option<u32>,
 }
 
-/// The place of some data in the data buffer.
-#[derive(Clone, Copy, PartialEq, Debug)]
-pub struct DataPointer {
-    offset: u32,
-    size: u32,
+struct RenderPassCache {
+    render_pass: n::RenderPass,
+    framebuffer: n::Framebuffer,
+    attachment_clears: Vec<AttachmentClear>,
+    target_rect: d3d11::D3D11_RECT,
 }
 
-#[derive(Clone)]
-pub struct DataBuffer(Vec<u8>);
-impl DataBuffer {
-    /// Create a new empty data buffer.
-    pub fn new() -> DataBuffer {
-        DataBuffer(Vec::new())
+fn get_rect(rect: &pso::Rect) -> d3d11::D3D11_RECT {
+    d3d11::D3D11_RECT {
+        left: rect.x as i32,
+        top: rect.y as i32,
+        right: (rect.x + rect.w) as i32,
+        bottom: (rect.y + rect.h) as i32,
     }
-    /// Reset the contents.
-    pub fn reset(&mut self) {
-        self.0.clear();
+}
+
+fn get_viewport(viewport: &pso::Viewport) -> d3d11::D3D11_VIEWPORT {
+    d3d11::D3D11_VIEWPORT {
+        TopLeftX: viewport.rect.x as _,
+        TopLeftY: viewport.rect.y as _,
+        Width: viewport.rect.w as _,
+        Height: viewport.rect.h as _,
+        MinDepth: viewport.depth.start,
+        MaxDepth: viewport.depth.end,
     }
-    /// Copy a given vector slice into the buffer.
-    pub fn add(&mut self, data: &[u8]) -> DataPointer {
-        self.0.extend_from_slice(data);
-        DataPointer {
-            offset: (self.0.len() - data.len()) as u32,
-            size: data.len() as u32,
+}
+
+/// `ClearView` takes floating point values, which are converted to the view format.
+fn get_clear_color(format: Option<Format>, value: com::ClearColorRaw) -> [f32; 4] {
+    let channel = format.map(|format| format.base_format().1);
+    unsafe {
+        match channel {
+            Some(ChannelType::Uint) => {
+                let v = value.uint32;
+                [v[0] as f32, v[1] as f32, v[2] as f32, v[3] as f32]
+            }
+            Some(ChannelType::Int) => {
+                let v = value.int32;
+                [v[0] as f32, v[1] as f32, v[2] as f32, v[3] as f32]
+            }
+            _ => value.float32,
         }
     }
-    /// Return a reference to a stored data object.
-    pub fn get(&self, ptr: DataPointer) -> &[u8] {
-        &self.0[ptr.offset as usize .. (ptr.offset + ptr.size) as usize]
-    }
 }
 
-///Serialized device command.
-#[derive(Clone, Copy, Debug)]
-pub enum Command {
-    // states
-    BindProgram(Program),
-    BindInputLayout(InputLayout),
-    BindIndex(Buffer, DXGI_FORMAT),
-    BindVertexBuffers([native::Buffer; MAX_VERTEX_ATTRIBUTES], [UINT; MAX_VERTEX_ATTRIBUTES], [UINT; MAX_VERTEX_ATTRIBUTES]),
-    BindConstantBuffers(shade::Stage, [native::Buffer; MAX_CONSTANT_BUFFERS]),
-    BindShaderResources(shade::Stage, [native::Srv; MAX_RESOURCE_VIEWS]),
-    BindSamplers(shade::Stage, [native::Sampler; MAX_SAMPLERS]),
-    BindPixelTargets([native::Rtv; MAX_COLOR_TARGETS], native::Dsv),
-    SetPrimitive(D3D11_PRIMITIVE_TOPOLOGY),
-    SetViewport(D3D11_VIEWPORT),
-    SetScissor(D3D11_RECT),
-    SetRasterizer(*const ID3D11RasterizerState),
-    SetDepthStencil(*const ID3D11DepthStencilState, UINT),
-    SetBlend(*const ID3D11BlendState, [FLOAT; 4], UINT),
-    CopyBuffer(Buffer, Buffer, UINT, UINT, UINT),
-    // resource updates
-    UpdateBuffer(Buffer, DataPointer, usize),
-    UpdateTexture(Texture, tex::Kind, Option<tex::CubeFace>, DataPointer, tex::RawImageInfo),
-    GenerateMips(native::Srv),
-    // drawing
-    ClearColor(native::Rtv, [f32; 4]),
-    ClearDepthStencil(native::Dsv, D3D11_CLEAR_FLAG, FLOAT, UINT8),
-    Draw(UINT, UINT),
-    DrawInstanced(UINT, UINT, UINT, UINT),
-    DrawIndexed(UINT, UINT, INT),
-    DrawIndexedInstanced(UINT, UINT, UINT, INT, UINT),
+fn get_depth_stencil_flags(aspects: Aspects) -> UINT {
+    let mut flags = 0;
+    if aspects.contains(Aspects::DEPTH) {
+        flags |= d3d11::D3D11_CLEAR_DEPTH;
+    }
+    if aspects.contains(Aspects::STENCIL) {
+        flags |= d3d11::D3D11_CLEAR_STENCIL;
+    }
+    flags
 }
 
-unsafe impl Send for Command {}
-
-struct Cache {
-    attrib_strides: [Option<pso::ElemStride>; MAX_VERTEX_ATTRIBUTES],
-    rasterizer: *const ID3D11RasterizerState,
-    depth_stencil: *const ID3D11DepthStencilState,
-    stencil_ref: UINT,
-    blend: *const ID3D11BlendState,
-    blend_ref: [FLOAT; 4],
-}
-unsafe impl Send for Cache {}
-
-impl Cache {
-    fn new() -> Cache {
-        Cache {
-            attrib_strides: [None; MAX_VERTEX_ATTRIBUTES],
-            rasterizer: ptr::null(),
-            depth_stencil: ptr::null(),
-            stencil_ref: 0,
-            blend: ptr::null(),
-            blend_ref: [0.0; 4],
+/// Write the query results of `query` into `data`, returns false if not available yet.
+unsafe fn get_query_data<T>(
+    context: &ComPtr<d3d11_1::ID3D11DeviceContext1>,
+    query: &ComPtr<d3d11::ID3D11Query>,
+    data: &mut T,
+    wait: bool,
+) -> bool {
+    loop {
+        let hr = context.GetData(
+            query.as_raw() as *mut _,
+            data as *mut T as *mut _,
+            mem::size_of::<T>() as _,
+            0,
+        );
+        match hr {
+            winerror::S_OK => return true,
+            winerror::S_FALSE if wait => thread::yield_now(),
+            winerror::S_FALSE => return false,
+            _ => {
+                error!("error on query data retrieval: {:x}", hr);
+                return false;
+            }
         }
     }
 }
 
-pub struct RawCommandBuffer<P> {
-    pub parser: P,
-    cache: Cache,
+/// Write `data` into the buffer region starting at `offset`.
+unsafe fn write_buffer(
+    context: &ComPtr<d3d11_1::ID3D11DeviceContext1>,
+    buffer: &ComPtr<d3d11::ID3D11Buffer>,
+    offset: buffer::Offset,
+    data: &[u8],
+) {
+    let dst_box = d3d11::D3D11_BOX {
+        left: offset as _,
+        top: 0,
+        front: 0,
+        right: (offset + data.len() as u64) as _,
+        bottom: 1,
+        back: 1,
+    };
+    context.UpdateSubresource(buffer.as_raw() as *mut _, 0, &dst_box, data.as_ptr() as *const _, 0, 0);
 }
 
-impl command::CommandBuffer<Backend> for RawCommandBuffer<CommandList> {
-    unsafe fn end(&mut self) -> SubmitInfo<CommandList> {
-        SubmitInfo {
-            parser: self.parser.clone(), // TODO: slow
+/// Work executed on the immediate context after the command list of a segment.
+///
+/// Deferred contexts can't read back any data, copies into buffers which
+/// depend on resource contents or query results are done on submission.
+#[derive(Clone)]
+pub(crate) enum Readback {
+    /// Copy the texels of a staging texture, filled by the command list,
+    /// into a buffer.
+    Image {
+        staging: ComPtr<d3d11::ID3D11Resource>,
+        buffer: ComPtr<d3d11::ID3D11Buffer>,
+        offset: buffer::Offset,
+        // Size of a row of texel blocks.
+        row_size: u32,
+        // Number of block rows and depth slices.
+        rows: u32,
+        slices: u32,
+        // Layout of the rows and slices in the buffer.
+        row_pitch: u32,
+        slice_pitch: u32,
+    },
+    /// Copy query results into a buffer.
+    Queries {
+        kind: n::QueryKind,
+        queries: Vec<ComPtr<d3d11::ID3D11Query>>,
+        disjoint: Vec<ComPtr<d3d11::ID3D11Query>>,
+        buffer: ComPtr<d3d11::ID3D11Buffer>,
+        offset: buffer::Offset,
+        stride: buffer::Offset,
+        flags: query::ResultFlags,
+    },
+}
+
+impl Readback {
+    pub(crate) unsafe fn execute(&self, context: &ComPtr<d3d11_1::ID3D11DeviceContext1>) {
+        match *self {
+            Readback::Image { ref staging, ref buffer, offset, row_size, rows, slices, row_pitch, slice_pitch } => {
+                let mut mapped = mem::zeroed::<d3d11::D3D11_MAPPED_SUBRESOURCE>();
+                let hr = context.Map(staging.as_raw(), 0, d3d11::D3D11_MAP_READ, 0, &mut mapped);
+                if !winerror::SUCCEEDED(hr) {
+                    error!("error on staging texture mapping: {:x}", hr);
+                    return;
+                }
+                for slice in 0 .. slices {
+                    for row in 0 .. rows {
+                        let src = (mapped.pData as *const u8)
+                            .offset((slice * mapped.DepthPitch + row * mapped.RowPitch) as isize);
+                        let dst_offset = offset + (slice * slice_pitch + row * row_pitch) as u64;
+                        // Rows are written one by one to keep the buffer contents in between.
+                        write_buffer(context, buffer, dst_offset, slice::from_raw_parts(src, row_size as usize));
+                    }
+                }
+                context.Unmap(staging.as_raw(), 0);
+            }
+            Readback::Queries { kind, ref queries, ref disjoint, ref buffer, offset, stride, flags } => {
+                let wait = flags.contains(query::ResultFlags::WAIT);
+                let value_size = if flags.contains(query::ResultFlags::BITS_64) { 8 } else { 4 };
+                // Values are stored as little endian integers of `value_size` bytes.
+                let encode = |values: &[u64]| {
+                    values
+                        .iter()
+                        .flat_map(|&value| (0 .. value_size).map(move |byte| (value >> (byte * 8)) as u8))
+                        .collect::<Vec<_>>()
+                };
+                let mut values = Vec::new();
+                for (i, query) in queries.iter().enumerate() {
+                    values.clear();
+                    let available = match kind {
+                        n::QueryKind::Occlusion => {
+                            let mut samples = 0u64;
+                            let available = get_query_data(context, query, &mut samples, wait);
+                            values.push(samples);
+                            available
+                        }
+                        n::QueryKind::BinaryOcclusion => {
+                            let mut passed = FALSE;
+                            let available = get_query_data(context, query, &mut passed, wait);
+                            values.push(passed as u64);
+                            available
+                        }
+                        n::QueryKind::PipelineStatistics(statistics) => {
+                            let mut data = mem::zeroed::<d3d11::D3D11_QUERY_DATA_PIPELINE_STATISTICS>();
+                            let available = get_query_data(context, query, &mut data, wait);
+                            // The statistics are written in the order of the flag bits.
+                            let all = [
+                                data.IAVertices, data.IAPrimitives, data.VSInvocations,
+                                data.GSInvocations, data.GSPrimitives, data.CInvocations,
+                                data.CPrimitives, data.PSInvocations, data.HSInvocations,
+                                data.DSInvocations, data.CSInvocations,
+                            ];
+                            values.extend(all
+                                .iter()
+                                .enumerate()
+                                .filter(|&(bit, _)| statistics.bits() & (1 << bit) != 0)
+                                .map(|(_, &value)| value));
+                            available
+                        }
+                        n::QueryKind::Timestamp => {
+                            let mut ticks = 0u64;
+                            let mut frequency = mem::zeroed::<d3d11::D3D11_QUERY_DATA_TIMESTAMP_DISJOINT>();
+                            let available = get_query_data(context, query, &mut ticks, wait) &&
+                                get_query_data(context, &disjoint[i], &mut frequency, wait) &&
+                                frequency.Disjoint == FALSE;
+                            // Timestamps are reported in nanoseconds.
+                            let nanos = if available && frequency.Frequency != 0 {
+                                (ticks as f64 * 1_000_000_000.0 / frequency.Frequency as f64) as u64
+                            } else {
+                                0
+                            };
+                            values.push(nanos);
+                            available
+                        }
+                    };
+
+                    // Unavailable results are only written if partial results are requested,
+                    // the availability follows the results in any case.
+                    let slot_offset = offset + i as u64 * stride;
+                    if available || flags.contains(query::ResultFlags::PARTIAL) {
+                        write_buffer(context, buffer, slot_offset, &encode(&values));
+                    }
+                    if flags.contains(query::ResultFlags::WITH_AVAILABILITY) {
+                        let availability_offset = slot_offset + values.len() as u64 * value_size;
+                        write_buffer(context, buffer, availability_offset, &encode(&[available as u64]));
+                    }
+                }
+            }
         }
     }
 }
-pub trait Parser: Sized + Send {
-    fn reset(&mut self);
-    fn parse(&mut self, cmd: Command);
-    fn update_buffer(&mut self, buf: Buffer, data: &[u8], offset: usize);
-    fn update_texture(&mut self, tex: Texture, kind: tex::Kind, face: Option<tex::CubeFace>, data: &[u8], image: tex::RawImageInfo);
+
+/// Commands recorded into a command list, followed by an optional readback.
+#[derive(Clone)]
+pub(crate) struct Segment {
+    pub(crate) list: ComPtr<d3d11::ID3D11CommandList>,
+    pub(crate) readback: Option<Readback>,
 }
 
-impl<P: Parser> From<P> for RawCommandBuffer<P> {
-    fn from(parser: P) -> RawCommandBuffer<P> {
-        RawCommandBuffer {
-            parser: parser,
-            cache: Cache::new(),
-        }
-    }
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct CommandBuffer {
+    // Deferred context recording the commands.
+    #[derivative(Debug="ignore")]
+    context: ComPtr<d3d11_1::ID3D11DeviceContext1>,
+    // Command lists of the finished parts of the recording.
+    #[derivative(Debug="ignore")]
+    segments: Vec<Segment>,
+    finished: bool,
+    #[derivative(Debug="ignore")]
+    device: ComPtr<d3d11_1::ID3D11Device1>,
+
+    // Push constants of all stages, bound to `shader::PUSH_CONSTANT_REGISTER`.
+    #[derivative(Debug="ignore")]
+    push_constant_buffer: ComPtr<d3d11::ID3D11Buffer>,
+    push_constants: [u32; (shader::PUSH_CONSTANT_SIZE / 4) as usize],
+    push_constants_dirty: bool,
+
+    // Vertex buffers are bound together with the strides of the current pipeline.
+    #[derivative(Debug="ignore")]
+    vertex_buffers: [*mut d3d11::ID3D11Buffer; MAX_VERTEX_BUFFERS],
+    vertex_offsets: [UINT; MAX_VERTEX_BUFFERS],
+    vertex_strides: [UINT; MAX_VERTEX_BUFFERS],
+    num_vertex_buffers: usize,
+
+    // UAVs of the graphics pipeline are set all at once.
+    #[derivative(Debug="ignore")]
+    graphics_uavs: [*mut d3d11::ID3D11UnorderedAccessView; MAX_UAV_SLOTS],
+
+    #[derivative(Debug="ignore")]
+    viewports: Vec<d3d11::D3D11_VIEWPORT>,
+    #[derivative(Debug="ignore")]
+    scissors: Vec<d3d11::D3D11_RECT>,
+
+    // Dynamic states are baked into the state objects of the pipeline.
+    #[derivative(Debug="ignore")]
+    blend_state: Option<ComPtr<d3d11::ID3D11BlendState>>,
+    blend_constants: pso::ColorValue,
+    #[derivative(Debug="ignore")]
+    depth_stencil_state: Option<ComPtr<d3d11::ID3D11DepthStencilState>>,
+    stencil_ref: UINT,
+
+    // Active render pass.
+    #[derivative(Debug="ignore")]
+    pass_cache: Option<RenderPassCache>,
+    cur_subpass: usize,
 }
 
-impl<P: Parser> RawCommandBuffer<P> {
-    fn flush(&mut self) {
-        let sample_mask = !0; //TODO
-        self.parser.parse(Command::SetDepthStencil(self.cache.depth_stencil, self.cache.stencil_ref));
-        self.parser.parse(Command::SetBlend(self.cache.blend, self.cache.blend_ref, sample_mask));
+unsafe impl Send for CommandBuffer { }
+unsafe impl Sync for CommandBuffer { }
+
+impl CommandBuffer {
+    pub(crate) fn new(
+        device: ComPtr<d3d11_1::ID3D11Device1>,
+        context: ComPtr<d3d11_1::ID3D11DeviceContext1>,
+    ) -> Self {
+        let desc = d3d11::D3D11_BUFFER_DESC {
+            ByteWidth: shader::PUSH_CONSTANT_SIZE,
+            Usage: d3d11::D3D11_USAGE_DEFAULT,
+            BindFlags: d3d11::D3D11_BIND_CONSTANT_BUFFER,
+            CPUAccessFlags: 0,
+            MiscFlags: 0,
+            StructureByteStride: 0,
+        };
+        let mut push_constant_buffer = ptr::null_mut();
+        let hr = unsafe {
+            device.CreateBuffer(&desc, ptr::null(), &mut push_constant_buffer as *mut *mut _)
+        };
+        assert!(winerror::SUCCEEDED(hr), "error on push constant buffer creation: {:x}", hr);
+
+        CommandBuffer {
+            context,
+            segments: Vec::new(),
+            finished: false,
+            device,
+            push_constant_buffer: unsafe { ComPtr::from_raw(push_constant_buffer) },
+            push_constants: [0; (shader::PUSH_CONSTANT_SIZE / 4) as usize],
+            push_constants_dirty: false,
+            vertex_buffers: [ptr::null_mut(); MAX_VERTEX_BUFFERS],
+            vertex_offsets: [0; MAX_VERTEX_BUFFERS],
+            vertex_strides: [0; MAX_VERTEX_BUFFERS],
+            num_vertex_buffers: 0,
+            graphics_uavs: [ptr::null_mut(); MAX_UAV_SLOTS],
+            viewports: Vec::new(),
+            scissors: Vec::new(),
+            blend_state: None,
+            blend_constants: [0.0; 4],
+            depth_stencil_state: None,
+            stencil_ref: 0,
+            pass_cache: None,
+            cur_subpass: !0,
+        }
     }
-}
 
-impl<P: 'static + Parser> command::Buffer<Resources> for RawCommandBuffer<P> {
     fn reset(&mut self) {
-        self.parser.reset();
-        self.cache = Cache::new();
+        unsafe { self.context.ClearState(); }
+        self.segments.clear();
+        self.finished = false;
+        self.push_constants_dirty = false;
+        self.vertex_buffers = [ptr::null_mut(); MAX_VERTEX_BUFFERS];
+        self.vertex_offsets = [0; MAX_VERTEX_BUFFERS];
+        self.vertex_strides = [0; MAX_VERTEX_BUFFERS];
+        self.num_vertex_buffers = 0;
+        self.graphics_uavs = [ptr::null_mut(); MAX_UAV_SLOTS];
+        self.viewports.clear();
+        self.scissors.clear();
+        self.blend_state = None;
+        self.blend_constants = [0.0; 4];
+        self.depth_stencil_state = None;
+        self.stencil_ref = 0;
+        self.pass_cache = None;
+        self.cur_subpass = !0;
+
+        // The push constant buffer stays bound to all stages for the whole recording.
+        let buffer = self.push_constant_buffer.as_raw();
+        let slot = shader::PUSH_CONSTANT_REGISTER;
+        unsafe {
+            self.context.VSSetConstantBuffers(slot, 1, &buffer);
+            self.context.HSSetConstantBuffers(slot, 1, &buffer);
+            self.context.DSSetConstantBuffers(slot, 1, &buffer);
+            self.context.GSSetConstantBuffers(slot, 1, &buffer);
+            self.context.PSSetConstantBuffers(slot, 1, &buffer);
+            self.context.CSSetConstantBuffers(slot, 1, &buffer);
+        }
     }
 
-    fn bind_pipeline_state(&mut self, pso: Pipeline) {
-        self.parser.parse(Command::SetPrimitive(pso.topology));
-        for (stride, ad_option) in self.cache.attrib_strides.iter_mut().zip(pso.attributes.iter()) {
-            *stride = ad_option.map(|(buf_id, _)| match pso.vertex_buffers[buf_id as usize] {
-                Some(ref bdesc) => bdesc.stride,
-                None => {
-                    error!("Unexpected use of buffer id {}", buf_id);
-                    0
-                },
+    /// Recorded segments of a finished command buffer.
+    pub(crate) fn segments(&self) -> Option<&[Segment]> {
+        if self.finished {
+            Some(&self.segments)
+        } else {
+            None
+        }
+    }
+
+    /// End the current command list, `readback` is executed after it on submission.
+    fn split(&mut self, readback: Readback) {
+        // The state of the deferred context is kept for the next command list.
+        let mut list = ptr::null_mut();
+        let hr = unsafe {
+            self.context.FinishCommandList(TRUE, &mut list as *mut *mut _)
+        };
+        if winerror::SUCCEEDED(hr) {
+            self.segments.push(Segment {
+                list: unsafe { ComPtr::from_raw(list) },
+                readback: Some(readback),
             });
+        } else {
+            error!("error on command list creation: {:x}", hr);
+        }
+    }
+
+    fn flush_push_constants(&mut self) {
+        if self.push_constants_dirty {
+            unsafe {
+                self.context.UpdateSubresource(
+                    self.push_constant_buffer.as_raw() as *mut _,
+                    0,
+                    ptr::null(),
+                    self.push_constants.as_ptr() as *const _,
+                    0,
+                    0,
+                );
+            }
+            self.push_constants_dirty = false;
+        }
+    }
+
+    /// Check the usage and range of `count` indirect arguments of `size` bytes,
+    /// which are `step` bytes apart. Out of bounds reads aren't reported by
+    /// the runtime and may hang the device.
+    fn check_indirect(
+        &self,
+        command: &str,
+        buffer: &n::Buffer,
+        offset: buffer::Offset,
+        count: u32,
+        step: u32,
+        size: u32,
+    ) -> bool {
+        if !buffer.usage.contains(buffer::Usage::INDIRECT) {
+            error!("`{}`: Buffer needs to be created with indirect usage", command);
+            return false;
+        }
+        if offset % 4 != 0 {
+            error!("`{}`: Offset {} must be a multiple of 4", command, offset);
+            return false;
         }
-        if self.cache.rasterizer != pso.rasterizer {
-            self.cache.rasterizer = pso.rasterizer;
-            self.parser.parse(Command::SetRasterizer(pso.rasterizer));
-        }
-        self.cache.depth_stencil = pso.depth_stencil;
-        self.cache.blend = pso.blend;
-        self.parser.parse(Command::BindInputLayout(pso.layout));
-        self.parser.parse(Command::BindProgram(pso.program));
-    }
-
-    fn bind_vertex_buffers(&mut self, vbs: pso::VertexBufferSet<Resources>) {
-        //Note: assumes `bind_pipeline_state` is called prior
-        let mut buffers = [native::Buffer(ptr::null_mut()); MAX_VERTEX_ATTRIBUTES];
-        let mut strides = [0; MAX_VERTEX_ATTRIBUTES];
-        let mut offsets = [0; MAX_VERTEX_ATTRIBUTES];
-        for i in 0 .. MAX_VERTEX_ATTRIBUTES {
-            match (vbs.0[i], self.cache.attrib_strides[i]) {
-                (None, Some(stride)) => {
-                    error!("No vertex input provided for slot {} with stride {}", i, stride)
-                },
-                (Some((buffer, offset)), Some(stride)) => {
-                    buffers[i] = buffer.0;
-                    strides[i] = stride as UINT;
-                    offsets[i] = offset as UINT;
-                },
-                (_, None) => (),
-            }
-        }
-        self.parser.parse(Command::BindVertexBuffers(buffers, strides, offsets));
-    }
-
-    fn bind_constant_buffers(&mut self, cbs: &[pso::ConstantBufferParam<Resources>]) {
-        for &stage in shade::STAGES.iter() {
-            let mut buffers = [native::Buffer(ptr::null_mut()); MAX_CONSTANT_BUFFERS];
-            let mask = stage.into();
-            let mut count = 0;
-            for cbuf in cbs.iter() {
-                if cbuf.1.contains(mask) {
-                    buffers[cbuf.2 as usize] = (cbuf.0).0;
-                    count += 1;
+        if count != 0 && offset + (count - 1) as u64 * step as u64 + size as u64 > buffer.size {
+            error!("`{}`: {} arguments at offset {} exceed the buffer size {}", command, count, offset, buffer.size);
+            return false;
+        }
+        true
+    }
+
+    fn update_push_constants(&mut self, offset: u32, constants: &[u32]) {
+        let offset = offset as usize;
+        if offset + constants.len() > self.push_constants.len() {
+            error!("Push constants exceed the supported size: {}", offset + constants.len());
+            return;
+        }
+        self.push_constants[offset .. offset + constants.len()].copy_from_slice(constants);
+        self.push_constants_dirty = true;
+    }
+
+    fn flush_vertex_buffers(&mut self) {
+        if self.num_vertex_buffers == 0 {
+            return;
+        }
+        unsafe {
+            self.context.IASetVertexBuffers(
+                0,
+                self.num_vertex_buffers as _,
+                self.vertex_buffers.as_ptr(),
+                self.vertex_strides.as_ptr(),
+                self.vertex_offsets.as_ptr(),
+            );
+        }
+    }
+
+    fn flush_graphics_uavs(&mut self) {
+        let first = match self.graphics_uavs.iter().position(|uav| !uav.is_null()) {
+            Some(first) => first,
+            None => return,
+        };
+        let last = self.graphics_uavs.iter().rposition(|uav| !uav.is_null()).unwrap();
+        unsafe {
+            self.context.OMSetRenderTargetsAndUnorderedAccessViews(
+                d3d11::D3D11_KEEP_RENDER_TARGETS_AND_DEPTH_STENCIL,
+                ptr::null(),
+                ptr::null_mut(),
+                first as _,
+                (last + 1 - first) as _,
+                self.graphics_uavs[first ..].as_ptr(),
+                ptr::null(),
+            );
+        }
+    }
+
+    fn flush_depth_stencil_state(&mut self) {
+        let state = self.depth_stencil_state.as_ref().map_or(ptr::null_mut(), |state| state.as_raw());
+        unsafe { self.context.OMSetDepthStencilState(state, self.stencil_ref); }
+    }
+
+    fn flush_blend_state(&mut self) {
+        let state = self.blend_state.as_ref().map_or(ptr::null_mut(), |state| state.as_raw());
+        unsafe { self.context.OMSetBlendState(state, &self.blend_constants, !0); }
+    }
+
+    fn bind_constant_buffer(
+        &mut self, stages: pso::ShaderStageFlags, slot: UINT,
+        buffer: *mut d3d11::ID3D11Buffer, first_constant: UINT, num_constants: UINT,
+    ) {
+        let ctx = &self.context;
+        unsafe {
+            if stages.contains(pso::ShaderStageFlags::VERTEX) {
+                ctx.VSSetConstantBuffers1(slot, 1, &buffer, &first_constant, &num_constants);
+            }
+            if stages.contains(pso::ShaderStageFlags::HULL) {
+                ctx.HSSetConstantBuffers1(slot, 1, &buffer, &first_constant, &num_constants);
+            }
+            if stages.contains(pso::ShaderStageFlags::DOMAIN) {
+                ctx.DSSetConstantBuffers1(slot, 1, &buffer, &first_constant, &num_constants);
+            }
+            if stages.contains(pso::ShaderStageFlags::GEOMETRY) {
+                ctx.GSSetConstantBuffers1(slot, 1, &buffer, &first_constant, &num_constants);
+            }
+            if stages.contains(pso::ShaderStageFlags::FRAGMENT) {
+                ctx.PSSetConstantBuffers1(slot, 1, &buffer, &first_constant, &num_constants);
+            }
+            if stages.contains(pso::ShaderStageFlags::COMPUTE) {
+                ctx.CSSetConstantBuffers1(slot, 1, &buffer, &first_constant, &num_constants);
+            }
+        }
+    }
+
+    fn bind_shader_resource(
+        &mut self, stages: pso::ShaderStageFlags, slot: UINT, view: *mut d3d11::ID3D11ShaderResourceView,
+    ) {
+        let ctx = &self.context;
+        unsafe {
+            if stages.contains(pso::ShaderStageFlags::VERTEX) {
+                ctx.VSSetShaderResources(slot, 1, &view);
+            }
+            if stages.contains(pso::ShaderStageFlags::HULL) {
+                ctx.HSSetShaderResources(slot, 1, &view);
+            }
+            if stages.contains(pso::ShaderStageFlags::DOMAIN) {
+                ctx.DSSetShaderResources(slot, 1, &view);
+            }
+            if stages.contains(pso::ShaderStageFlags::GEOMETRY) {
+                ctx.GSSetShaderResources(slot, 1, &view);
+            }
+            if stages.contains(pso::ShaderStageFlags::FRAGMENT) {
+                ctx.PSSetShaderResources(slot, 1, &view);
+            }
+            if stages.contains(pso::ShaderStageFlags::COMPUTE) {
+                ctx.CSSetShaderResources(slot, 1, &view);
+            }
+        }
+    }
+
+    fn bind_sampler(
+        &mut self, stages: pso::ShaderStageFlags, slot: UINT, sampler: *mut d3d11::ID3D11SamplerState,
+    ) {
+        let ctx = &self.context;
+        unsafe {
+            if stages.contains(pso::ShaderStageFlags::VERTEX) {
+                ctx.VSSetSamplers(slot, 1, &sampler);
+            }
+            if stages.contains(pso::ShaderStageFlags::HULL) {
+                ctx.HSSetSamplers(slot, 1, &sampler);
+            }
+            if stages.contains(pso::ShaderStageFlags::DOMAIN) {
+                ctx.DSSetSamplers(slot, 1, &sampler);
+            }
+            if stages.contains(pso::ShaderStageFlags::GEOMETRY) {
+                ctx.GSSetSamplers(slot, 1, &sampler);
+            }
+            if stages.contains(pso::ShaderStageFlags::FRAGMENT) {
+                ctx.PSSetSamplers(slot, 1, &sampler);
+            }
+            if stages.contains(pso::ShaderStageFlags::COMPUTE) {
+                ctx.CSSetSamplers(slot, 1, &sampler);
+            }
+        }
+    }
+
+    /// Unordered access views are only available in fragment and compute shaders.
+    fn bind_unordered_access(
+        &mut self, stages: pso::ShaderStageFlags, slot: UINT, view: *mut d3d11::ID3D11UnorderedAccessView,
+    ) {
+        if stages.intersects(
+            pso::ShaderStageFlags::VERTEX | pso::ShaderStageFlags::HULL |
+            pso::ShaderStageFlags::DOMAIN | pso::ShaderStageFlags::GEOMETRY
+        ) {
+            error!("Storage resources are only supported in fragment and compute shaders");
+        }
+        if slot as usize >= MAX_UAV_SLOTS {
+            error!("Storage resource register {} exceeds the available slots", slot);
+            return;
+        }
+        if stages.contains(pso::ShaderStageFlags::FRAGMENT) {
+            self.graphics_uavs[slot as usize] = view;
+        }
+        if stages.contains(pso::ShaderStageFlags::COMPUTE) {
+            unsafe { self.context.CSSetUnorderedAccessViews(slot, 1, &view, ptr::null()); }
+        }
+    }
+
+    fn bind_descriptor_sets<T>(
+        &mut self,
+        compute: bool,
+        layout: &n::PipelineLayout,
+        first_set: usize,
+        sets: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<n::DescriptorSet>,
+    {
+        for (set_index, set) in sets.into_iter().enumerate() {
+            let set = set.borrow();
+            let descriptors = set.descriptors.lock().unwrap();
+            let layout_set = &layout.sets[first_set + set_index];
+
+            for (&(ref binding, registers), descriptors) in layout_set.iter().zip(descriptors.iter()) {
+                let stages = if compute {
+                    pso::ShaderStageFlags::COMPUTE
+                } else {
+                    binding.stage_flags & pso::ShaderStageFlags::GRAPHICS
+                };
+
+                for (i, descriptor) in descriptors.iter().enumerate() {
+                    let i = i as UINT;
+                    match *descriptor {
+                        n::Descriptor::Empty => {}
+                        n::Descriptor::ConstantBuffer { raw, first_constant, num_constants } => {
+                            self.bind_constant_buffer(stages, registers.c + i, raw, first_constant, num_constants);
+                        }
+                        n::Descriptor::ShaderResource(srv) => {
+                            self.bind_shader_resource(stages, registers.t + i, srv);
+                        }
+                        n::Descriptor::UnorderedAccess(uav) => {
+                            self.bind_unordered_access(stages, registers.u + i, uav);
+                        }
+                        n::Descriptor::StorageBuffer(srv, uav) => {
+                            // Storage buffers are writable in fragment and compute shaders,
+                            // other stages can only read them.
+                            let uav_stages = stages & (pso::ShaderStageFlags::FRAGMENT | pso::ShaderStageFlags::COMPUTE);
+                            if !uav_stages.is_empty() {
+                                self.bind_unordered_access(uav_stages, registers.u + i, uav);
+                            }
+                            if uav_stages != stages {
+                                self.bind_shader_resource(stages - uav_stages, registers.t + i, srv);
+                            }
+                        }
+                        n::Descriptor::Sampler(sampler) => {
+                            self.bind_sampler(stages, registers.s + i, sampler);
+                        }
+                        n::Descriptor::CombinedImageSampler(srv, sampler) => {
+                            self.bind_shader_resource(stages, registers.t + i, srv);
+                            self.bind_sampler(stages, registers.s + i, sampler);
+                        }
+                    }
                 }
             }
-            if count != 0 {
-                self.parser.parse(Command::BindConstantBuffers(stage, buffers));
+        }
+
+        if !compute {
+            self.flush_graphics_uavs();
+        }
+    }
+
+    /// Create a texture of the given extent, which can be read by the host
+    /// and holds a single subresource of `image`.
+    fn create_staging_texture(
+        &self, image: &n::Image, extent: image::Extent,
+    ) -> Option<ComPtr<d3d11::ID3D11Resource>> {
+        let mut raw: *mut d3d11::ID3D11Resource = ptr::null_mut();
+        let hr = match image.kind {
+            image::Kind::D1(..) => {
+                let desc = d3d11::D3D11_TEXTURE1D_DESC {
+                    Width: extent.width,
+                    MipLevels: 1,
+                    ArraySize: 1,
+                    Format: image.dxgi_format,
+                    Usage: d3d11::D3D11_USAGE_STAGING,
+                    BindFlags: 0,
+                    CPUAccessFlags: d3d11::D3D11_CPU_ACCESS_READ,
+                    MiscFlags: 0,
+                };
+                unsafe {
+                    self.device.CreateTexture1D(&desc, ptr::null(), &mut raw as *mut *mut _ as *mut *mut _)
+                }
+            }
+            image::Kind::D2(..) => {
+                let desc = d3d11::D3D11_TEXTURE2D_DESC {
+                    Width: extent.width,
+                    Height: extent.height,
+                    MipLevels: 1,
+                    ArraySize: 1,
+                    Format: image.dxgi_format,
+                    SampleDesc: dxgitype::DXGI_SAMPLE_DESC {
+                        Count: 1,
+                        Quality: 0,
+                    },
+                    Usage: d3d11::D3D11_USAGE_STAGING,
+                    BindFlags: 0,
+                    CPUAccessFlags: d3d11::D3D11_CPU_ACCESS_READ,
+                    MiscFlags: 0,
+                };
+                unsafe {
+                    self.device.CreateTexture2D(&desc, ptr::null(), &mut raw as *mut *mut _ as *mut *mut _)
+                }
+            }
+            image::Kind::D3(..) => {
+                let desc = d3d11::D3D11_TEXTURE3D_DESC {
+                    Width: extent.width,
+                    Height: extent.height,
+                    Depth: extent.depth,
+                    MipLevels: 1,
+                    Format: image.dxgi_format,
+                    Usage: d3d11::D3D11_USAGE_STAGING,
+                    BindFlags: 0,
+                    CPUAccessFlags: d3d11::D3D11_CPU_ACCESS_READ,
+                    MiscFlags: 0,
+                };
+                unsafe {
+                    self.device.CreateTexture3D(&desc, ptr::null(), &mut raw as *mut *mut _ as *mut *mut _)
+                }
             }
+        };
+
+        if winerror::SUCCEEDED(hr) {
+            Some(unsafe { ComPtr::from_raw(raw) })
+        } else {
+            error!("error on staging texture creation: {:x}", hr);
+            None
         }
     }
 
-    fn bind_global_constant(&mut self, _: shade::Location, _: shade::UniformValue) {
-        error!("Global constants are not supported");
+    fn view_info(image: &n::Image, format: dxgiformat::DXGI_FORMAT, level: image::Level, layers: Range<image::Layer>) -> ViewInfo {
+        let (view_kind, layers) = match image.kind {
+            image::Kind::D1(..) => (image::ViewKind::D1Array, layers),
+            image::Kind::D2(..) => (image::ViewKind::D2Array, layers),
+            // Depth slices are addressed like layers.
+            image::Kind::D3(..) => (image::ViewKind::D3, 0 .. image.kind.level_extent(level).depth as image::Layer),
+        };
+        ViewInfo {
+            resource: image.raw.as_raw(),
+            kind: image.kind,
+            view_kind,
+            format,
+            range: image::SubresourceRange {
+                aspects: Aspects::empty(),
+                levels: level .. level + 1,
+                layers,
+            },
+        }
     }
 
-    fn bind_resource_views(&mut self, rvs: &[pso::ResourceViewParam<Resources>]) {
-        for &stage in shade::STAGES.iter() {
-            let mut views = [native::Srv(ptr::null_mut()); MAX_RESOURCE_VIEWS];
-            let mask = stage.into();
-            let mut count = 0;
-            for view in rvs.iter() {
-                if view.1.contains(mask) {
-                    views[view.2 as usize] = view.0;
-                    count += 1;
+    fn bind_targets(&mut self) {
+        let state = self.pass_cache.as_ref().unwrap();
+        let subpass = &state.render_pass.subpasses[self.cur_subpass];
+
+        let color_views = subpass.color_attachments
+            .iter()
+            .map(|&id| {
+                state.framebuffer.attachments[id].rtv
+                    .as_ref()
+                    .map_or(ptr::null_mut(), |rtv| rtv.as_raw())
+            })
+            .collect::<Vec<_>>();
+        let ds_view = subpass.depth_stencil_attachment
+            .and_then(|id| state.framebuffer.attachments[id].dsv.as_ref())
+            .map_or(ptr::null_mut(), |dsv| dsv.as_raw());
+
+        unsafe {
+            self.context.OMSetRenderTargets(color_views.len() as _, color_views.as_ptr(), ds_view);
+        }
+
+        // Clear the attachments on their first usage in the render pass.
+        for (i, (view, clear)) in state.framebuffer.attachments.iter()
+            .zip(state.attachment_clears.iter())
+            .enumerate()
+        {
+            if clear.subpass_id != Some(self.cur_subpass) {
+                continue;
+            }
+
+            if let Some(ref rtv) = view.rtv {
+                if let Some(value) = clear.value {
+                    let color = get_clear_color(state.render_pass.attachments[i].format, unsafe { value.color });
+                    unsafe {
+                        self.context.ClearView(rtv.as_raw() as *mut _, &color, &state.target_rect, 1);
+                    }
                 }
             }
-            if count != 0 {
-                self.parser.parse(Command::BindShaderResources(stage, views));
+
+            if let Some(ref dsv) = view.dsv {
+                let mut flags = 0;
+                let (mut depth, mut stencil) = (0.0, 0);
+                if let Some(value) = clear.value {
+                    flags |= d3d11::D3D11_CLEAR_DEPTH;
+                    depth = unsafe { value.depth_stencil.depth };
+                }
+                if let Some(value) = clear.stencil_value {
+                    flags |= d3d11::D3D11_CLEAR_STENCIL;
+                    stencil = value;
+                }
+                if flags != 0 {
+                    // Depth stencil clears always cover the whole view.
+                    unsafe {
+                        self.context.ClearDepthStencilView(dsv.as_raw(), flags, depth, stencil as u8);
+                    }
+                }
             }
         }
     }
+}
+
+impl com::RawCommandBuffer<Backend> for CommandBuffer {
+    fn begin(&mut self, _flags: com::CommandBufferFlags, _info: com::CommandBufferInheritanceInfo<Backend>) {
+        // TODO: Implement flags and inheritance of the render pass for secondary command buffers.
+        self.reset();
+    }
+
+    fn finish(&mut self) -> Result<(), com::RecordingError> {
+        let mut list = ptr::null_mut();
+        let hr = unsafe {
+            self.context.FinishCommandList(FALSE, &mut list as *mut *mut _)
+        };
+        if winerror::SUCCEEDED(hr) {
+            self.segments.push(Segment {
+                list: unsafe { ComPtr::from_raw(list) },
+                readback: None,
+            });
+            self.finished = true;
+        } else {
+            error!("error on command list creation: {:x}", hr);
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self, _release_resources: bool) {
+        self.reset();
+    }
+
+    fn begin_render_pass_raw<T>(
+        &mut self,
+        render_pass: &n::RenderPass,
+        framebuffer: &n::Framebuffer,
+        target_rect: pso::Rect,
+        clear_values: T,
+        _first_subpass: com::SubpassContents,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<com::ClearValueRaw>,
+    {
+        assert_eq!(framebuffer.attachments.len(), render_pass.attachments.len());
+
+        let mut clear_iter = clear_values.into_iter();
+        let attachment_clears = render_pass.attachments
+            .iter()
+            .enumerate()
+            .map(|(i, attachment)| {
+                let cv = if attachment.ops.load == pass::AttachmentLoadOp::Clear || attachment.stencil_ops.load == pass::AttachmentLoadOp::Clear {
+                    Some(*clear_iter.next().unwrap().borrow())
+                } else {
+                    None
+                };
+
+                AttachmentClear {
+                    subpass_id: render_pass.subpasses.iter().position(|sp| sp.is_using(i)),
+                    value: if attachment.ops.load == pass::AttachmentLoadOp::Clear {
+                        assert!(cv.is_some());
+                        cv
+                    } else {
+                        None
+                    },
+                    stencil_value: if attachment.stencil_ops.load == pass::AttachmentLoadOp::Clear {
+                        Some(unsafe { cv.unwrap().depth_stencil.stencil })
+                    } else {
+                        None
+                    },
+                }
+            }).collect();
+
+        self.pass_cache = Some(RenderPassCache {
+            render_pass: render_pass.clone(),
+            framebuffer: framebuffer.clone(),
+            attachment_clears,
+            target_rect: get_rect(&target_rect),
+        });
+        self.cur_subpass = 0;
+        self.bind_targets();
+    }
+
+    fn next_subpass(&mut self, _contents: com::SubpassContents) {
+        self.cur_subpass += 1;
+        self.bind_targets();
+    }
+
+    fn end_render_pass(&mut self) {
+        unsafe { self.context.OMSetRenderTargets(0, ptr::null(), ptr::null_mut()); }
+        self.pass_cache = None;
+        self.cur_subpass = !0;
+    }
+
+    fn begin_rendering<'a, T>(
+        &mut self,
+        render_area: pso::Rect,
+        colors: T,
+        depth_stencil: Option<pass::RenderingAttachment<'a, Backend>>,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<pass::RenderingAttachment<'a, Backend>>,
+    {
+        // Rendering is recorded as a render pass with a single subpass.
+        let mut attachments = Vec::new();
+        let mut views = Vec::new();
+        let mut clear_values = Vec::new();
+        let (color_attachments, depth_stencil_attachment) = {
+            let mut add_attachment = |attachment: &pass::RenderingAttachment<Backend>| {
+                let id = attachments.len();
+                attachments.push(pass::Attachment {
+                    format: Some(attachment.format),
+                    ops: attachment.ops,
+                    stencil_ops: attachment.stencil_ops,
+                    layouts: attachment.layout .. attachment.layout,
+                });
+                views.push(attachment.view.clone());
+                if attachment.ops.load == pass::AttachmentLoadOp::Clear || attachment.stencil_ops.load == pass::AttachmentLoadOp::Clear {
+                    clear_values.push(com::ClearValueRaw::from(attachment.clear_value));
+                }
+                id
+            };
+            let colors = colors
+                .into_iter()
+                .map(|attachment| add_attachment(attachment.borrow()))
+                .collect::<Vec<_>>();
+            (colors, depth_stencil.as_ref().map(add_attachment))
+        };
+
+        let render_pass = n::RenderPass {
+            attachments,
+            subpasses: vec![n::SubpassDesc {
+                color_attachments,
+                depth_stencil_attachment,
+            }],
+        };
+        let framebuffer = n::Framebuffer {
+            attachments: views,
+            layers: 1,
+        };
+        self.begin_render_pass_raw(
+            &render_pass,
+            &framebuffer,
+            render_area,
+            clear_values,
+            com::SubpassContents::Inline,
+        );
+    }
+
+    fn end_rendering(&mut self) {
+        if self.pass_cache.is_none() {
+            error!("`end_rendering` called outside of rendering");
+            return;
+        }
+        self.end_render_pass();
+    }
 
-    fn bind_unordered_views(&mut self, uvs: &[pso::UnorderedViewParam<Resources>]) {
-        let mut views = [(); MAX_UNORDERED_VIEWS];
-        let mut count = 0;
-        for view in uvs.iter() {
-            views[view.2 as usize] = view.0;
-            count += 1;
+    fn pipeline_barrier<'a, T>(
+        &mut self,
+        _stages: Range<pso::PipelineStage>,
+        _dependencies: memory::Dependencies,
+        _barriers: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<memory::Barrier<'a, Backend>>,
+    {
+        // Hazards between commands are tracked by the runtime.
+    }
+
+    fn clear_color_image_raw(
+        &mut self,
+        image: &n::Image,
+        _: image::Layout,
+        range: image::SubresourceRange,
+        value: com::ClearColorRaw,
+    ) {
+        assert_eq!(range.aspects, Aspects::COLOR);
+
+        let color = get_clear_color(Some(image.format), value);
+        for level in range.levels.clone() {
+            let info = Self::view_info(image, image.dxgi_format, level, range.layers.clone());
+            if image.usage.contains(image::Usage::COLOR_ATTACHMENT) {
+                if let Ok(rtv) = Device::view_image_as_render_target(&self.device, &info) {
+                    unsafe { self.context.ClearView(rtv.as_raw() as *mut _, &color, ptr::null(), 0); }
+                }
+            } else if image.usage.contains(image::Usage::STORAGE) {
+                if let Ok(uav) = Device::view_image_as_storage(&self.device, &info) {
+                    unsafe { self.context.ClearView(uav.as_raw() as *mut _, &color, ptr::null(), 0); }
+                }
+            } else {
+                error!("Color image clears require color attachment or storage usage");
+                return;
+            }
         }
-        if count != 0 {
-            unimplemented!()
-            //self.parser.parse(Command::BindUnorderedAccess(stage, views));
+    }
+
+    fn clear_depth_stencil_image_raw(
+        &mut self,
+        image: &n::Image,
+        _layout: image::Layout,
+        range: image::SubresourceRange,
+        value: com::ClearDepthStencilRaw,
+    ) {
+        assert!((Aspects::DEPTH | Aspects::STENCIL).contains(range.aspects));
+
+        let format = match conv::map_format_dsv(image.format.base_format().0) {
+            Some(format) => format,
+            None => {
+                error!("Unable to clear image with format {:?}", image.format);
+                return;
+            }
+        };
+        let flags = get_depth_stencil_flags(range.aspects);
+        for level in range.levels.clone() {
+            let info = Self::view_info(image, format, level, range.layers.clone());
+            if let Ok(dsv) = Device::view_image_as_depth_stencil(&self.device, &info) {
+                unsafe {
+                    self.context.ClearDepthStencilView(dsv.as_raw(), flags, value.depth, value.stencil as u8);
+                }
+            }
         }
     }
 
-    fn bind_samplers(&mut self, ss: &[pso::SamplerParam<Resources>]) {
-        for &stage in shade::STAGES.iter() {
-            let mut samplers = [native::Sampler(ptr::null_mut()); MAX_SAMPLERS];
-            let mask = stage.into();
-            let mut count = 0;
-            for sm in ss.iter() {
-                if sm.1.contains(mask) {
-                    samplers[sm.2 as usize] = sm.0;
-                    count += 1;
+    fn clear_attachments<T, U>(&mut self, clears: T, rects: U)
+    where
+        T: IntoIterator,
+        T::Item: Borrow<com::AttachmentClear>,
+        U: IntoIterator,
+        U::Item: Borrow<pso::ClearRect>,
+    {
+        let rects = rects
+            .into_iter()
+            .map(|rect| {
+                let rect = rect.borrow();
+                // TODO: restrict to the given layers
+                if rect.layers != (0 .. 1) {
+                    warn!("Attachment clears always cover all layers");
+                }
+                get_rect(&rect.rect)
+            })
+            .collect::<Vec<_>>();
+        let state = self.pass_cache.as_ref().expect("Attachments can only be cleared inside a render pass");
+        let subpass = &state.render_pass.subpasses[self.cur_subpass];
+
+        for clear in clears {
+            let (depth, stencil, flags) = match *clear.borrow() {
+                com::AttachmentClear::Color(index, value) => {
+                    let id = subpass.color_attachments[index];
+                    let rtv = match state.framebuffer.attachments[id].rtv {
+                        Some(ref rtv) => rtv,
+                        None => continue,
+                    };
+                    let color = match value {
+                        com::ClearColor::Float(v) => v,
+                        com::ClearColor::Int(v) => [v[0] as f32, v[1] as f32, v[2] as f32, v[3] as f32],
+                        com::ClearColor::Uint(v) => [v[0] as f32, v[1] as f32, v[2] as f32, v[3] as f32],
+                    };
+                    unsafe {
+                        self.context.ClearView(rtv.as_raw() as *mut _, &color, rects.as_ptr(), rects.len() as _);
+                    }
+                    continue;
+                }
+                com::AttachmentClear::Depth(depth) => (depth, 0, d3d11::D3D11_CLEAR_DEPTH),
+                com::AttachmentClear::Stencil(stencil) => (0.0, stencil, d3d11::D3D11_CLEAR_STENCIL),
+                com::AttachmentClear::DepthStencil(com::ClearDepthStencil(depth, stencil)) => {
+                    (depth, stencil, d3d11::D3D11_CLEAR_DEPTH | d3d11::D3D11_CLEAR_STENCIL)
                 }
+            };
+
+            let dsv = match subpass.depth_stencil_attachment
+                .and_then(|id| state.framebuffer.attachments[id].dsv.as_ref())
+            {
+                Some(dsv) => dsv,
+                None => continue,
+            };
+            // TODO: restrict to the given rectangles
+            if rects.iter().any(|rect| *rect != state.target_rect) {
+                warn!("Depth stencil attachment clears always cover the whole attachment");
             }
-            if count != 0 {
-                self.parser.parse(Command::BindSamplers(stage, samplers));
+            unsafe {
+                self.context.ClearDepthStencilView(dsv.as_raw(), flags, depth, stencil as u8);
             }
         }
     }
 
-    fn bind_pixel_targets(&mut self, pts: pso::PixelTargetSet<Resources>) {
-        if let (Some(ref d), Some(ref s)) = (pts.depth, pts.stencil) {
-            if d != s {
-                error!("Depth and stencil views have to be the same");
+    fn resolve_image<T>(
+        &mut self,
+        src: &n::Image,
+        _src_layout: image::Layout,
+        dst: &n::Image,
+        _dst_layout: image::Layout,
+        regions: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<com::ImageResolve>,
+    {
+        for region in regions {
+            let r = region.borrow();
+            if r.src_offset != image::Offset::ZERO || r.dst_offset != image::Offset::ZERO {
+                warn!("Resolve offsets are not supported, resolving the whole subresource");
+            }
+            let num_layers = r.src_subresource.layers.end - r.src_subresource.layers.start;
+            for layer in 0 .. num_layers {
+                let src_sub = src.calc_subresource(
+                    r.src_subresource.level as _,
+                    (r.src_subresource.layers.start + layer) as _,
+                );
+                let dst_sub = dst.calc_subresource(
+                    r.dst_subresource.level as _,
+                    (r.dst_subresource.layers.start + layer) as _,
+                );
+                unsafe {
+                    self.context.ResolveSubresource(
+                        dst.raw.as_raw(),
+                        dst_sub,
+                        src.raw.as_raw(),
+                        src_sub,
+                        dst.dxgi_format,
+                    );
+                }
             }
         }
+    }
 
-        let view = pts.get_view();
-        let viewport = D3D11_VIEWPORT {
-            TopLeftX: 0.0,
-            TopLeftY: 0.0,
-            Width: view.0 as f32,
-            Height: view.1 as f32,
-            MinDepth: 0.0,
-            MaxDepth: 1.0,
-        };
+    fn blit_image<T>(
+        &mut self,
+        src: &n::Image,
+        _src_layout: image::Layout,
+        dst: &n::Image,
+        _dst_layout: image::Layout,
+        _filter: image::Filter,
+        regions: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<com::ImageBlit>,
+    {
+        // Blits are executed as copies, which requires matching extents and texel formats.
+        // Formats only differing in the sRGB encoding produce the same values.
+        let (src_surface, src_channel) = src.format.base_format();
+        let (dst_surface, dst_channel) = dst.format.base_format();
+        let srgb_pair = |a, b| a == ChannelType::Srgb && b == ChannelType::Unorm;
+        if src_surface != dst_surface ||
+            !(src_channel == dst_channel || srgb_pair(src_channel, dst_channel) || srgb_pair(dst_channel, src_channel))
+        {
+            error!("Blits between formats {:?} and {:?} are not supported", src.format, dst.format);
+            return;
+        }
 
-        let mut colors = [native::Rtv(ptr::null_mut()); MAX_COLOR_TARGETS];
-        for i in 0 .. MAX_COLOR_TARGETS {
-            if let Some(c) = pts.colors[i] {
-                colors[i] = c;
+        for region in regions {
+            let r = region.borrow();
+            let (src_start, src_end) = (r.src_bounds.start, r.src_bounds.end);
+            let (dst_start, dst_end) = (r.dst_bounds.start, r.dst_bounds.end);
+            if src_end.x - src_start.x != dst_end.x - dst_start.x ||
+                src_end.y - src_start.y != dst_end.y - dst_start.y ||
+                src_end.z - src_start.z != dst_end.z - dst_start.z
+            {
+                error!("Scaling blits are not supported");
+                continue;
+            }
+            if src_end.x < src_start.x || src_end.y < src_start.y || src_end.z < src_start.z {
+                error!("Mirroring blits are not supported");
+                continue;
+            }
+
+            let src_box = d3d11::D3D11_BOX {
+                left: src_start.x as _,
+                top: src_start.y as _,
+                front: src_start.z as _,
+                right: src_end.x as _,
+                bottom: src_end.y as _,
+                back: src_end.z as _,
+            };
+            let num_layers = r.src_subresource.layers.end - r.src_subresource.layers.start;
+            for layer in 0 .. num_layers {
+                let src_sub = src.calc_subresource(
+                    r.src_subresource.level as _,
+                    (r.src_subresource.layers.start + layer) as _,
+                );
+                let dst_sub = dst.calc_subresource(
+                    r.dst_subresource.level as _,
+                    (r.dst_subresource.layers.start + layer) as _,
+                );
+                unsafe {
+                    self.context.CopySubresourceRegion(
+                        dst.raw.as_raw(),
+                        dst_sub,
+                        dst_start.x as _,
+                        dst_start.y as _,
+                        dst_start.z as _,
+                        src.raw.as_raw(),
+                        src_sub,
+                        &src_box,
+                    );
+                }
             }
         }
-        let ds = pts.depth.unwrap_or(native::Dsv(ptr::null_mut()));
-        self.parser.parse(Command::BindPixelTargets(colors, ds));
-        self.parser.parse(Command::SetViewport(viewport));
     }
 
-    fn bind_index(&mut self, buf: Buffer, itype: IndexType) {
-        let format = match itype {
-            IndexType::U16 => DXGI_FORMAT_R16_UINT,
-            IndexType::U32 => DXGI_FORMAT_R32_UINT,
+    fn bind_index_buffer(&mut self, ibv: buffer::IndexBufferView<Backend>) {
+        let format = match ibv.index_type {
+            IndexType::U16 => dxgiformat::DXGI_FORMAT_R16_UINT,
+            IndexType::U32 => dxgiformat::DXGI_FORMAT_R32_UINT,
         };
-        self.parser.parse(Command::BindIndex(buf, format));
+        unsafe {
+            self.context.IASetIndexBuffer(ibv.buffer.raw.as_raw(), format, ibv.offset as _);
+        }
+    }
+
+    fn bind_vertex_buffers(&mut self, vbs: pso::VertexBufferSet<Backend>) {
+        assert!(vbs.0.len() <= MAX_VERTEX_BUFFERS);
+        for (i, &(buffer, offset)) in vbs.0.iter().enumerate() {
+            self.vertex_buffers[i] = buffer.raw.as_raw();
+            self.vertex_offsets[i] = offset as _;
+        }
+        self.num_vertex_buffers = vbs.0.len();
+        self.flush_vertex_buffers();
+    }
+
+    fn set_viewports<T>(&mut self, first_viewport: u32, viewports: T)
+    where
+        T: IntoIterator,
+        T::Item: Borrow<pso::Viewport>,
+    {
+        // All viewports are set at once, keep the ones outside of the updated range.
+        for (i, viewport) in viewports.into_iter().enumerate() {
+            let index = first_viewport as usize + i;
+            if index >= self.viewports.len() {
+                self.viewports.resize(index + 1, unsafe { mem::zeroed() });
+            }
+            self.viewports[index] = get_viewport(viewport.borrow());
+        }
+        unsafe {
+            self.context.RSSetViewports(self.viewports.len() as _, self.viewports.as_ptr());
+        }
+    }
+
+    fn set_scissors<T>(&mut self, first_scissor: u32, scissors: T)
+    where
+        T: IntoIterator,
+        T::Item: Borrow<pso::Rect>,
+    {
+        for (i, scissor) in scissors.into_iter().enumerate() {
+            let index = first_scissor as usize + i;
+            if index >= self.scissors.len() {
+                self.scissors.resize(index + 1, unsafe { mem::zeroed() });
+            }
+            self.scissors[index] = get_rect(scissor.borrow());
+        }
+        unsafe {
+            self.context.RSSetScissorRects(self.scissors.len() as _, self.scissors.as_ptr());
+        }
     }
 
-    fn set_scissor(&mut self, rect: target::Rect) {
-        self.parser.parse(Command::SetScissor(D3D11_RECT {
-            left: rect.x as INT,
-            top: rect.y as INT,
-            right: (rect.x + rect.w) as INT,
-            bottom: (rect.y + rect.h) as INT,
-        }));
+    fn set_blend_constants(&mut self, color: pso::ColorValue) {
+        self.blend_constants = color;
+        self.flush_blend_state();
+    }
+
+    fn set_stencil_reference(&mut self, front: pso::StencilValue, back: pso::StencilValue) {
+        if front != back {
+            error!(
+                "Unable to set different stencil ref values for front ({}) and back ({})",
+                front,
+                back,
+            );
+        }
+        self.stencil_ref = front as _;
+        self.flush_depth_stencil_state();
+    }
+
+    fn set_depth_bounds(&mut self, _bounds: Range<f32>) {
+        warn!("Depth bounds test is not supported");
+    }
+
+    fn set_line_width(&mut self, width: f32) {
+        if width != 1.0 {
+            warn!("Wide lines are not supported: {}", width);
+        }
     }
 
-    fn set_ref_values(&mut self, rv: state::RefValues) {
-        if rv.stencil.0 != rv.stencil.1 {
-            error!("Unable to set different stencil ref values for front ({}) and back ({})",
-                rv.stencil.0, rv.stencil.1);
+    fn bind_graphics_pipeline(&mut self, pipeline: &n::GraphicsPipeline) {
+        unsafe {
+            self.context.IASetPrimitiveTopology(pipeline.topology);
+            self.context.IASetInputLayout(
+                pipeline.input_layout.as_ref().map_or(ptr::null_mut(), |layout| layout.as_raw()),
+            );
+            self.context.VSSetShader(pipeline.vs.as_raw(), ptr::null(), 0);
+            self.context.HSSetShader(pipeline.hs.as_ref().map_or(ptr::null_mut(), |hs| hs.as_raw()), ptr::null(), 0);
+            self.context.DSSetShader(pipeline.ds.as_ref().map_or(ptr::null_mut(), |ds| ds.as_raw()), ptr::null(), 0);
+            self.context.GSSetShader(pipeline.gs.as_ref().map_or(ptr::null_mut(), |gs| gs.as_raw()), ptr::null(), 0);
+            self.context.PSSetShader(pipeline.ps.as_ref().map_or(ptr::null_mut(), |ps| ps.as_raw()), ptr::null(), 0);
+            self.context.RSSetState(pipeline.rasterizer_state.as_raw());
+        }
+
+        self.blend_state = Some(pipeline.blend_state.clone());
+        self.depth_stencil_state = pipeline.depth_stencil_state.clone();
+        if let Some(color) = pipeline.baked_states.blend_color {
+            self.blend_constants = color;
+        }
+        self.flush_blend_state();
+        self.flush_depth_stencil_state();
+
+        // Viewports and scissors beyond the count of the pipeline are discarded,
+        // baked states replace the dynamic ones.
+        let viewport_count = if pipeline.baked_states.viewport.is_some() { 0 } else { pipeline.viewport_count };
+        if self.viewports.len() > viewport_count {
+            self.viewports.truncate(viewport_count);
+            unsafe {
+                self.context.RSSetViewports(self.viewports.len() as _, self.viewports.as_ptr());
+            }
+        }
+        let scissor_count = if pipeline.baked_states.scissor.is_some() { 0 } else { pipeline.viewport_count };
+        if self.scissors.len() > scissor_count {
+            self.scissors.truncate(scissor_count);
+            unsafe {
+                self.context.RSSetScissorRects(self.scissors.len() as _, self.scissors.as_ptr());
+            }
+        }
+        if let Some(ref viewport) = pipeline.baked_states.viewport {
+            self.set_viewports(0, &[viewport.clone()]);
+        }
+        if let Some(ref rect) = pipeline.baked_states.scissor {
+            self.set_scissors(0, &[rect.clone()]);
+        }
+        if pipeline.baked_states.depth_bounds.is_some() {
+            warn!("Depth bounds test is not supported");
+        }
+
+        if self.vertex_strides != pipeline.vertex_strides {
+            self.vertex_strides = pipeline.vertex_strides;
+            self.flush_vertex_buffers();
         }
-        self.cache.stencil_ref = rv.stencil.0 as UINT;
-        self.cache.blend_ref = rv.blend;
     }
 
-    fn copy_buffer(&mut self, src: Buffer, dst: Buffer,
-                   src_offset_bytes: usize, dst_offset_bytes: usize,
-                   size_bytes: usize) {
-        self.parser.parse(Command::CopyBuffer(src, dst,
-                                              src_offset_bytes as UINT,
-                                              dst_offset_bytes as UINT,
-                                              size_bytes as UINT));
+    fn bind_graphics_descriptor_sets<T>(
+        &mut self,
+        layout: &n::PipelineLayout,
+        first_set: usize,
+        sets: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<n::DescriptorSet>,
+    {
+        self.bind_descriptor_sets(false, layout, first_set, sets);
+    }
+
+    fn bind_compute_pipeline(&mut self, pipeline: &n::ComputePipeline) {
+        unsafe { self.context.CSSetShader(pipeline.cs.as_raw(), ptr::null(), 0); }
+    }
+
+    fn bind_compute_descriptor_sets<T>(
+        &mut self,
+        layout: &n::PipelineLayout,
+        first_set: usize,
+        sets: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<n::DescriptorSet>,
+    {
+        self.bind_descriptor_sets(true, layout, first_set, sets);
     }
 
-    #[allow(unused_variables)]
-    fn copy_buffer_to_texture(&mut self, src: Buffer, src_offset_bytes: usize,
-                              dst: Texture,
-                              kind: tex::Kind,
-                              face: Option<tex::CubeFace>,
-                              img: tex::RawImageInfo) {
+    fn dispatch(&mut self, count: WorkGroupCount) {
+        self.flush_push_constants();
+        unsafe { self.context.Dispatch(count[0], count[1], count[2]); }
+    }
+
+    fn dispatch_indirect(&mut self, buffer: &n::Buffer, offset: buffer::Offset, dispatch_count: u32) {
+        if !self.check_indirect("dispatch_indirect", buffer, offset, dispatch_count, 12, 12) {
+            return;
+        }
+        self.flush_push_constants();
+        for i in 0 .. dispatch_count {
+            unsafe { self.context.DispatchIndirect(buffer.raw.as_raw(), offset as UINT + i * 12); }
+        }
+    }
+
+    fn build_acceleration_structure<'a>(
+        &mut self,
+        _desc: &accel_struct::BuildDesc<'a, Backend>,
+        _dst: &n::AccelerationStructure,
+        _scratch: (&n::Buffer, buffer::Offset),
+    ) {
         unimplemented!()
     }
 
-    #[allow(unused_variables)]
-    fn copy_texture_to_buffer(&mut self,
-                              src: Texture,
-                              kind: tex::Kind,
-                              face: Option<tex::CubeFace>,
-                              img: tex::RawImageInfo,
-                              dst: Buffer, dst_offset_bytes: usize) {
+    fn bind_ray_tracing_pipeline(&mut self, _pipeline: &n::RayTracingPipeline) {
         unimplemented!()
     }
 
-    fn update_buffer(&mut self, buf: Buffer, data: &[u8], offset: usize) {
-        self.parser.update_buffer(buf, data, offset);
+    fn bind_ray_tracing_descriptor_sets<T>(
+        &mut self,
+        _layout: &n::PipelineLayout,
+        _first_set: usize,
+        _sets: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<n::DescriptorSet>,
+    {
+        unimplemented!()
     }
 
-    fn update_texture(&mut self, tex: Texture, kind: tex::Kind, face: Option<tex::CubeFace>,
-                      data: &[u8], image: tex::RawImageInfo) {
-        self.parser.update_texture(tex, kind, face, data, image);
+    fn trace_rays<'a>(
+        &mut self,
+        _tables: &accel_struct::ShaderBindingTables<'a, Backend>,
+        _count: [u32; 3],
+    ) {
+        unimplemented!()
     }
 
-    fn generate_mipmap(&mut self, srv: native::Srv) {
-        self.parser.parse(Command::GenerateMips(srv));
+    fn fill_buffer(&mut self, buffer: &n::Buffer, range: Range<buffer::Offset>, data: u32) {
+        let uav = match buffer.uav {
+            Some(ref uav) => uav,
+            None => {
+                error!("Buffer fills require storage usage");
+                return;
+            }
+        };
+        if range.start != 0 || range.end < buffer.size {
+            // TODO: create a view for the given range
+            warn!("Buffer fills always cover the whole buffer");
+        }
+        unsafe {
+            self.context.ClearUnorderedAccessViewUint(uav.as_raw(), &[data; 4]);
+        }
     }
 
-    fn clear_color(&mut self, target: native::Rtv, value: command::ClearColor) {
-        match value {
-            command::ClearColor::Float(data) => {
-                self.parser.parse(Command::ClearColor(target, data));
-            },
-            _ => {
-                error!("Unable to clear int/uint target");
-            },
+    fn update_buffer(&mut self, buffer: &n::Buffer, offset: buffer::Offset, data: &[u8]) {
+        if buffer.constant {
+            // Constant buffers can only be updated as a whole.
+            if offset != 0 || (data.len() as u64) < buffer.size {
+                error!("Partial updates of uniform buffers are not supported");
+                return;
+            }
+            unsafe {
+                self.context.UpdateSubresource(
+                    buffer.raw.as_raw() as *mut _,
+                    0,
+                    ptr::null(),
+                    data.as_ptr() as *const _,
+                    0,
+                    0,
+                );
+            }
+        } else {
+            let dst_box = d3d11::D3D11_BOX {
+                left: offset as _,
+                top: 0,
+                front: 0,
+                right: (offset + data.len() as u64) as _,
+                bottom: 1,
+                back: 1,
+            };
+            unsafe {
+                self.context.UpdateSubresource(
+                    buffer.raw.as_raw() as *mut _,
+                    0,
+                    &dst_box,
+                    data.as_ptr() as *const _,
+                    0,
+                    0,
+                );
+            }
         }
     }
 
-    fn clear_depth_stencil(&mut self, target: native::Dsv, depth: Option<target::Depth>,
-                           stencil: Option<target::Stencil>) {
-        let flags = //warning: magic constants ahead
-            D3D11_CLEAR_FLAG(if depth.is_some() {1} else {0}) |
-            D3D11_CLEAR_FLAG(if stencil.is_some() {2} else {0});
-        self.parser.parse(Command::ClearDepthStencil(target, flags,
-                      depth.unwrap_or_default() as FLOAT,
-                      stencil.unwrap_or_default() as UINT8
-        ));
+    fn copy_buffer<T>(&mut self, src: &n::Buffer, dst: &n::Buffer, regions: T)
+    where
+        T: IntoIterator,
+        T::Item: Borrow<com::BufferCopy>,
+    {
+        for region in regions {
+            let region = region.borrow();
+            if src.constant || dst.constant {
+                // Constant buffers can only be copied as a whole.
+                if region.src != 0 || region.dst != 0 || src.size != dst.size || region.size < src.size {
+                    error!("Partial copies of uniform buffers are not supported");
+                    continue;
+                }
+                unsafe {
+                    self.context.CopyResource(dst.raw.as_raw() as *mut _, src.raw.as_raw() as *mut _);
+                }
+                continue;
+            }
+
+            let src_box = d3d11::D3D11_BOX {
+                left: region.src as _,
+                top: 0,
+                front: 0,
+                right: (region.src + region.size) as _,
+                bottom: 1,
+                back: 1,
+            };
+            unsafe {
+                self.context.CopySubresourceRegion(
+                    dst.raw.as_raw() as *mut _,
+                    0,
+                    region.dst as _,
+                    0,
+                    0,
+                    src.raw.as_raw() as *mut _,
+                    0,
+                    &src_box,
+                );
+            }
+        }
     }
 
-    fn call_draw(&mut self, start: VertexCount, count: VertexCount, instances: Option<command::InstanceParams>) {
-        self.flush();
-        self.parser.parse(match instances {
-            Some((ninst, offset)) => Command::DrawInstanced(
-                count as UINT, ninst as UINT, start as UINT, offset as UINT),
-            None => Command::Draw(count as UINT, start as UINT),
-        });
+    fn copy_image<T>(
+        &mut self,
+        src: &n::Image,
+        _: image::Layout,
+        dst: &n::Image,
+        _: image::Layout,
+        regions: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<com::ImageCopy>,
+    {
+        for region in regions {
+            let r = region.borrow();
+            let num_layers = r.src_subresource.layers.end - r.src_subresource.layers.start;
+            let src_box = d3d11::D3D11_BOX {
+                left: r.src_offset.x as _,
+                top: r.src_offset.y as _,
+                front: r.src_offset.z as _,
+                right: (r.src_offset.x + r.extent.width as i32) as _,
+                bottom: (r.src_offset.y + r.extent.height as i32) as _,
+                back: (r.src_offset.z + r.extent.depth as i32) as _,
+            };
+
+            for layer in 0 .. num_layers {
+                let src_sub = src.calc_subresource(
+                    r.src_subresource.level as _,
+                    (r.src_subresource.layers.start + layer) as _,
+                );
+                let dst_sub = dst.calc_subresource(
+                    r.dst_subresource.level as _,
+                    (r.dst_subresource.layers.start + layer) as _,
+                );
+                unsafe {
+                    self.context.CopySubresourceRegion(
+                        dst.raw.as_raw(),
+                        dst_sub,
+                        r.dst_offset.x as _,
+                        r.dst_offset.y as _,
+                        r.dst_offset.z as _,
+                        src.raw.as_raw(),
+                        src_sub,
+                        &src_box,
+                    );
+                }
+            }
+        }
+    }
+
+    fn copy_buffer_to_image<T>(
+        &mut self,
+        buffer: &n::Buffer,
+        image: &n::Image,
+        _: image::Layout,
+        regions: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<com::BufferImageCopy>,
+    {
+        // The data is taken from the host copy of the buffer at recording time.
+        if buffer.host_ptr.is_null() {
+            error!("Copies from buffers in device local memory are not supported");
+            return;
+        }
+
+        let desc = image.format.base_format().0.desc();
+        let (block_width, block_height) = (desc.dim.0 as u32, desc.dim.1 as u32);
+        let block_size = (desc.bits / 8) as u32;
+
+        for region in regions {
+            let r = region.borrow();
+            let buffer_width = if r.buffer_width == 0 { r.image_extent.width } else { r.buffer_width };
+            let buffer_height = if r.buffer_height == 0 { r.image_extent.height } else { r.buffer_height };
+            let row_pitch = (buffer_width + block_width - 1) / block_width * block_size;
+            let slice_pitch = (buffer_height + block_height - 1) / block_height * row_pitch;
+            let layer_size = slice_pitch as u64 * r.image_extent.depth as u64;
+
+            let dst_box = d3d11::D3D11_BOX {
+                left: r.image_offset.x as _,
+                top: r.image_offset.y as _,
+                front: r.image_offset.z as _,
+                right: (r.image_offset.x + r.image_extent.width as i32) as _,
+                bottom: (r.image_offset.y + r.image_extent.height as i32) as _,
+                back: (r.image_offset.z + r.image_extent.depth as i32) as _,
+            };
+
+            let num_layers = r.image_layers.layers.end - r.image_layers.layers.start;
+            for layer in 0 .. num_layers {
+                let subresource = image.calc_subresource(
+                    r.image_layers.level as _,
+                    (r.image_layers.layers.start + layer) as _,
+                );
+                let offset = r.buffer_offset + layer as u64 * layer_size;
+                unsafe {
+                    self.context.UpdateSubresource(
+                        image.raw.as_raw(),
+                        subresource,
+                        &dst_box,
+                        buffer.host_ptr.offset(offset as isize) as *const _,
+                        row_pitch,
+                        slice_pitch,
+                    );
+                }
+            }
+        }
+    }
+
+    fn copy_image_to_buffer<T>(
+        &mut self,
+        image: &n::Image,
+        _: image::Layout,
+        buffer: &n::Buffer,
+        regions: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<com::BufferImageCopy>,
+    {
+        // The texels are copied into a staging texture for each layer,
+        // which is read back into the buffer on submission.
+        if buffer.constant {
+            error!("Images can't be copied into uniform buffers");
+            return;
+        }
+
+        let desc = image.format.base_format().0.desc();
+        let (block_width, block_height) = (desc.dim.0 as u32, desc.dim.1 as u32);
+        let block_size = (desc.bits / 8) as u32;
+
+        for region in regions {
+            let r = region.borrow();
+            if r.image_layers.aspects != Aspects::COLOR {
+                error!("Copies of depth and stencil aspects into buffers are not supported");
+                continue;
+            }
+
+            let buffer_width = if r.buffer_width == 0 { r.image_extent.width } else { r.buffer_width };
+            let buffer_height = if r.buffer_height == 0 { r.image_extent.height } else { r.buffer_height };
+            let row_pitch = (buffer_width + block_width - 1) / block_width * block_size;
+            let slice_pitch = (buffer_height + block_height - 1) / block_height * row_pitch;
+            let layer_size = slice_pitch as u64 * r.image_extent.depth as u64;
+
+            // Staging textures of compressed formats cover whole blocks.
+            let extent = image::Extent {
+                width: (r.image_extent.width + block_width - 1) / block_width * block_width,
+                height: (r.image_extent.height + block_height - 1) / block_height * block_height,
+                depth: r.image_extent.depth,
+            };
+            let src_box = d3d11::D3D11_BOX {
+                left: r.image_offset.x as _,
+                top: r.image_offset.y as _,
+                front: r.image_offset.z as _,
+                right: (r.image_offset.x + r.image_extent.width as i32) as _,
+                bottom: (r.image_offset.y + r.image_extent.height as i32) as _,
+                back: (r.image_offset.z + r.image_extent.depth as i32) as _,
+            };
+
+            let num_layers = r.image_layers.layers.end - r.image_layers.layers.start;
+            for layer in 0 .. num_layers {
+                let staging = match self.create_staging_texture(image, extent) {
+                    Some(staging) => staging,
+                    None => return,
+                };
+                let subresource = image.calc_subresource(
+                    r.image_layers.level as _,
+                    (r.image_layers.layers.start + layer) as _,
+                );
+                unsafe {
+                    self.context.CopySubresourceRegion(
+                        staging.as_raw(),
+                        0,
+                        0,
+                        0,
+                        0,
+                        image.raw.as_raw(),
+                        subresource,
+                        &src_box,
+                    );
+                }
+                self.split(Readback::Image {
+                    staging,
+                    buffer: buffer.raw.clone(),
+                    offset: r.buffer_offset + layer as u64 * layer_size,
+                    row_size: (r.image_extent.width + block_width - 1) / block_width * block_size,
+                    rows: (r.image_extent.height + block_height - 1) / block_height,
+                    slices: r.image_extent.depth,
+                    row_pitch,
+                    slice_pitch,
+                });
+            }
+        }
+    }
+
+    fn draw(&mut self, vertices: Range<VertexCount>, instances: Range<InstanceCount>) {
+        self.flush_push_constants();
+        unsafe {
+            self.context.DrawInstanced(
+                vertices.end - vertices.start,
+                instances.end - instances.start,
+                vertices.start,
+                instances.start,
+            );
+        }
+    }
+
+    fn draw_indexed(
+        &mut self,
+        indices: Range<IndexCount>,
+        base_vertex: VertexOffset,
+        instances: Range<InstanceCount>,
+    ) {
+        self.flush_push_constants();
+        unsafe {
+            self.context.DrawIndexedInstanced(
+                indices.end - indices.start,
+                instances.end - instances.start,
+                indices.start,
+                base_vertex,
+                instances.start,
+            );
+        }
     }
 
-    fn call_draw_indexed(&mut self, start: VertexCount, count: VertexCount,
-                         base: VertexCount, instances: Option<command::InstanceParams>) {
-        self.flush();
-        self.parser.parse(match instances {
-            Some((ninst, offset)) => Command::DrawIndexedInstanced(
-                count as UINT, ninst as UINT, start as UINT, base as INT, offset as UINT),
-            None => Command::DrawIndexed(count as UINT, start as UINT, base as INT),
+    fn draw_indirect(&mut self, buffer: &n::Buffer, offset: buffer::Offset, draw_count: u32, stride: u32) {
+        let stride = cmp::max(stride, 16);
+        if !self.check_indirect("draw_indirect", buffer, offset, draw_count, stride, 16) {
+            return;
+        }
+        self.flush_push_constants();
+        // There is no multi draw indirect, issue the draws one by one.
+        for i in 0 .. draw_count {
+            let offset = offset + (i * stride) as u64;
+            unsafe { self.context.DrawInstancedIndirect(buffer.raw.as_raw(), offset as _); }
+        }
+    }
+
+    fn draw_indexed_indirect(
+        &mut self,
+        buffer: &n::Buffer,
+        offset: buffer::Offset,
+        draw_count: u32,
+        stride: u32,
+    ) {
+        let stride = cmp::max(stride, 20);
+        if !self.check_indirect("draw_indexed_indirect", buffer, offset, draw_count, stride, 20) {
+            return;
+        }
+        self.flush_push_constants();
+        for i in 0 .. draw_count {
+            let offset = offset + (i * stride) as u64;
+            unsafe { self.context.DrawIndexedInstancedIndirect(buffer.raw.as_raw(), offset as _); }
+        }
+    }
+
+    fn execute_indirect(
+        &mut self,
+        signature: &n::CommandSignature,
+        buffer: &n::Buffer,
+        offset: buffer::Offset,
+        count: u32,
+    ) {
+        self.flush_push_constants();
+        for i in 0 .. count {
+            let offset = (offset + (i * signature.stride) as u64) as UINT;
+            unsafe {
+                match signature.command {
+                    indirect::Argument::Draw =>
+                        self.context.DrawInstancedIndirect(buffer.raw.as_raw(), offset),
+                    indirect::Argument::DrawIndexed =>
+                        self.context.DrawIndexedInstancedIndirect(buffer.raw.as_raw(), offset),
+                    indirect::Argument::Dispatch =>
+                        self.context.DispatchIndirect(buffer.raw.as_raw(), offset),
+                    _ => unreachable!(),
+                }
+            }
+        }
+    }
+
+    fn begin_query(&mut self, query: query::Query<Backend>, _flags: query::QueryControl) {
+        // Occlusion queries are always precise.
+        if query.pool.kind == n::QueryKind::Timestamp {
+            error!("Timestamp queries can only be written with `write_timestamp`");
+            return;
+        }
+        match query.pool.queries.get(query.id as usize) {
+            Some(raw) => unsafe { self.context.Begin(raw.as_raw() as *mut _) },
+            None => error!("Query {} doesn't exist in the pool", query.id),
+        }
+    }
+
+    fn end_query(&mut self, query: query::Query<Backend>) {
+        if query.pool.kind == n::QueryKind::Timestamp {
+            error!("Timestamp queries can only be written with `write_timestamp`");
+            return;
+        }
+        match query.pool.queries.get(query.id as usize) {
+            Some(raw) => unsafe { self.context.End(raw.as_raw() as *mut _) },
+            None => error!("Query {} doesn't exist in the pool", query.id),
+        }
+    }
+
+    fn reset_query_pool(&mut self, _pool: &n::QueryPool, _queries: Range<query::QueryId>) {
+        // Queries are reset when they are issued again.
+    }
+
+    fn write_timestamp(&mut self, _: pso::PipelineStage, query: query::Query<Backend>) {
+        let id = query.id as usize;
+        if query.pool.kind != n::QueryKind::Timestamp {
+            error!("Timestamps can only be written to timestamp query pools");
+            return;
+        }
+        if id >= query.pool.queries.len() {
+            error!("Query {} doesn't exist in the pool", query.id);
+            return;
+        }
+        let (raw, disjoint) = (&query.pool.queries[id], &query.pool.disjoint[id]);
+        unsafe {
+            self.context.Begin(disjoint.as_raw() as *mut _);
+            self.context.End(raw.as_raw() as *mut _);
+            self.context.End(disjoint.as_raw() as *mut _);
+        }
+    }
+
+    fn copy_query_pool_results(
+        &mut self,
+        pool: &n::QueryPool,
+        queries: Range<query::QueryId>,
+        buffer: &n::Buffer,
+        offset: buffer::Offset,
+        stride: buffer::Offset,
+        flags: query::ResultFlags,
+    ) {
+        if buffer.constant {
+            error!("Query results can't be copied into uniform buffers");
+            return;
+        }
+        let range = queries.start as usize .. queries.end as usize;
+        if range.end > pool.queries.len() {
+            error!("Queries {:?} don't exist in the pool", queries);
+            return;
+        }
+        // Results are only available on the immediate context.
+        self.split(Readback::Queries {
+            kind: pool.kind,
+            queries: pool.queries[range.clone()].to_vec(),
+            disjoint: pool.disjoint.get(range).map_or(Vec::new(), |disjoint| disjoint.to_vec()),
+            buffer: buffer.raw.clone(),
+            offset,
+            stride,
+            flags,
         });
     }
+
+    fn push_graphics_constants(
+        &mut self,
+        _layout: &n::PipelineLayout,
+        _stages: pso::ShaderStageFlags,
+        offset: u32,
+        constants: &[u32],
+    ) {
+        // All stages share the push constant buffer.
+        self.update_push_constants(offset, constants);
+    }
+
+    fn push_compute_constants(
+        &mut self,
+        _layout: &n::PipelineLayout,
+        offset: u32,
+        constants: &[u32],
+    ) {
+        self.update_push_constants(offset, constants);
+    }
+
+    fn execute_commands<I>(&mut self, buffers: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<CommandBuffer>,
+    {
+        for buffer in buffers {
+            let segments = match buffer.borrow().segments() {
+                Some(segments) => segments.to_vec(),
+                None => {
+                    error!("Executed command buffer has not been finished");
+                    continue;
+                }
+            };
+            for segment in segments {
+                // Keep the state of the primary command buffer.
+                unsafe { self.context.ExecuteCommandList(segment.list.as_raw(), TRUE); }
+                if let Some(readback) = segment.readback {
+                    self.split(readback);
+                }
+            }
+        }
+    }
 }
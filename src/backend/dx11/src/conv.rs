@@ -0,0 +1,455 @@
+use std::mem;
+use spirv_cross::spirv;
+
+use winapi::shared::basetsd::UINT8;
+use winapi::shared::dxgiformat::*;
+use winapi::shared::minwindef::{FALSE, INT, TRUE, UINT};
+use winapi::um::d3d11::*;
+use winapi::um::d3dcommon::*;
+
+use hal::format::{Format, SurfaceType};
+use hal::{buffer, image, pso, Primitive};
+
+pub fn map_format(format: Format) -> Option<DXGI_FORMAT> {
+    use hal::format::Format::*;
+
+    // TODO: list may be incomplete or partially wrong..
+    let format = match format {
+        R5g6b5Unorm => DXGI_FORMAT_B5G6R5_UNORM,
+        R5g5b5a1Unorm => DXGI_FORMAT_B5G5R5A1_UNORM,
+        R8Unorm => DXGI_FORMAT_R8_UNORM,
+        R8Inorm => DXGI_FORMAT_R8_SNORM,
+        R8Uint => DXGI_FORMAT_R8_UINT,
+        R8Int => DXGI_FORMAT_R8_SINT,
+        Rg8Unorm => DXGI_FORMAT_R8G8_UNORM,
+        Rg8Inorm => DXGI_FORMAT_R8G8_SNORM,
+        Rg8Uint => DXGI_FORMAT_R8G8_UINT,
+        Rg8Int => DXGI_FORMAT_R8G8_SINT,
+        Rgba8Unorm => DXGI_FORMAT_R8G8B8A8_UNORM,
+        Rgba8Inorm => DXGI_FORMAT_R8G8B8A8_SNORM,
+        Rgba8Uint => DXGI_FORMAT_R8G8B8A8_UINT,
+        Rgba8Int => DXGI_FORMAT_R8G8B8A8_SINT,
+        Rgba8Srgb => DXGI_FORMAT_R8G8B8A8_UNORM_SRGB,
+        Bgra8Unorm => DXGI_FORMAT_B8G8R8A8_UNORM,
+        Bgra8Srgb => DXGI_FORMAT_B8G8R8A8_UNORM_SRGB,
+        A2b10g10r10Unorm => DXGI_FORMAT_R10G10B10A2_UNORM,
+        A2b10g10r10Uint => DXGI_FORMAT_R10G10B10A2_UINT,
+        R16Unorm => DXGI_FORMAT_R16_UNORM,
+        R16Inorm => DXGI_FORMAT_R16_SNORM,
+        R16Uint => DXGI_FORMAT_R16_UINT,
+        R16Int => DXGI_FORMAT_R16_SINT,
+        R16Float => DXGI_FORMAT_R16_FLOAT,
+        Rg16Unorm => DXGI_FORMAT_R16G16_UNORM,
+        Rg16Inorm => DXGI_FORMAT_R16G16_SNORM,
+        Rg16Uint => DXGI_FORMAT_R16G16_UINT,
+        Rg16Int => DXGI_FORMAT_R16G16_SINT,
+        Rg16Float => DXGI_FORMAT_R16G16_FLOAT,
+        Rgba16Unorm => DXGI_FORMAT_R16G16B16A16_UNORM,
+        Rgba16Inorm => DXGI_FORMAT_R16G16B16A16_SNORM,
+        Rgba16Uint => DXGI_FORMAT_R16G16B16A16_UINT,
+        Rgba16Int => DXGI_FORMAT_R16G16B16A16_SINT,
+        Rgba16Float => DXGI_FORMAT_R16G16B16A16_FLOAT,
+        R32Uint => DXGI_FORMAT_R32_UINT,
+        R32Int => DXGI_FORMAT_R32_SINT,
+        R32Float => DXGI_FORMAT_R32_FLOAT,
+        Rg32Uint => DXGI_FORMAT_R32G32_UINT,
+        Rg32Int => DXGI_FORMAT_R32G32_SINT,
+        Rg32Float => DXGI_FORMAT_R32G32_FLOAT,
+        Rgb32Uint => DXGI_FORMAT_R32G32B32_UINT,
+        Rgb32Int => DXGI_FORMAT_R32G32B32_SINT,
+        Rgb32Float => DXGI_FORMAT_R32G32B32_FLOAT,
+        Rgba32Uint => DXGI_FORMAT_R32G32B32A32_UINT,
+        Rgba32Int => DXGI_FORMAT_R32G32B32A32_SINT,
+        Rgba32Float => DXGI_FORMAT_R32G32B32A32_FLOAT,
+        B10g11r11Ufloat => DXGI_FORMAT_R11G11B10_FLOAT,
+        E5b9g9r9Ufloat => DXGI_FORMAT_R9G9B9E5_SHAREDEXP,
+        D16Unorm => DXGI_FORMAT_D16_UNORM,
+        D24UnormS8Uint => DXGI_FORMAT_D24_UNORM_S8_UINT,
+        D32Float => DXGI_FORMAT_D32_FLOAT,
+        D32FloatS8Uint => DXGI_FORMAT_D32_FLOAT_S8X24_UINT,
+        Bc1RgbUnorm => DXGI_FORMAT_BC1_UNORM,
+        Bc1RgbSrgb => DXGI_FORMAT_BC1_UNORM_SRGB,
+        Bc2Unorm => DXGI_FORMAT_BC2_UNORM,
+        Bc2Srgb => DXGI_FORMAT_BC2_UNORM_SRGB,
+        Bc3Unorm => DXGI_FORMAT_BC3_UNORM,
+        Bc3Srgb => DXGI_FORMAT_BC3_UNORM_SRGB,
+        Bc4Unorm => DXGI_FORMAT_BC4_UNORM,
+        Bc4Inorm => DXGI_FORMAT_BC4_SNORM,
+        Bc5Unorm => DXGI_FORMAT_BC5_UNORM,
+        Bc5Inorm => DXGI_FORMAT_BC5_SNORM,
+        Bc6hUfloat => DXGI_FORMAT_BC6H_UF16,
+        Bc6hFloat => DXGI_FORMAT_BC6H_SF16,
+        Bc7Unorm => DXGI_FORMAT_BC7_UNORM,
+        Bc7Srgb => DXGI_FORMAT_BC7_UNORM_SRGB,
+
+        _ => return None,
+    };
+
+    Some(format)
+}
+
+pub fn map_format_dsv(surface: SurfaceType) -> Option<DXGI_FORMAT> {
+    Some(match surface {
+        SurfaceType::D16    => DXGI_FORMAT_D16_UNORM,
+        SurfaceType::X8D24 |
+        SurfaceType::D24_S8 => DXGI_FORMAT_D24_UNORM_S8_UINT,
+        SurfaceType::D32    => DXGI_FORMAT_D32_FLOAT,
+        SurfaceType::D32_S8 => DXGI_FORMAT_D32_FLOAT_S8X24_UINT,
+        _ => return None,
+    })
+}
+
+/// Typeless format of depth-stencil resources, which allows creating
+/// depth stencil views as well as shader resource views.
+pub fn map_format_typeless(surface: SurfaceType) -> Option<DXGI_FORMAT> {
+    Some(match surface {
+        SurfaceType::D16    => DXGI_FORMAT_R16_TYPELESS,
+        SurfaceType::X8D24 |
+        SurfaceType::D24_S8 => DXGI_FORMAT_R24G8_TYPELESS,
+        SurfaceType::D32    => DXGI_FORMAT_R32_TYPELESS,
+        SurfaceType::D32_S8 => DXGI_FORMAT_R32G8X24_TYPELESS,
+        _ => return None,
+    })
+}
+
+/// Format of shader resource views reading the depth aspect of a depth-stencil resource.
+pub fn map_format_depth_srv(surface: SurfaceType) -> Option<DXGI_FORMAT> {
+    Some(match surface {
+        SurfaceType::D16    => DXGI_FORMAT_R16_UNORM,
+        SurfaceType::X8D24 |
+        SurfaceType::D24_S8 => DXGI_FORMAT_R24_UNORM_X8_TYPELESS,
+        SurfaceType::D32    => DXGI_FORMAT_R32_FLOAT,
+        SurfaceType::D32_S8 => DXGI_FORMAT_R32_FLOAT_X8X24_TYPELESS,
+        _ => return None,
+    })
+}
+
+pub fn map_topology(primitive: Primitive) -> D3D11_PRIMITIVE_TOPOLOGY {
+    use hal::Primitive::*;
+    match primitive {
+        PointList              => D3D_PRIMITIVE_TOPOLOGY_POINTLIST,
+        LineList               => D3D_PRIMITIVE_TOPOLOGY_LINELIST,
+        LineListAdjacency      => D3D_PRIMITIVE_TOPOLOGY_LINELIST_ADJ,
+        LineStrip              => D3D_PRIMITIVE_TOPOLOGY_LINESTRIP,
+        LineStripAdjacency     => D3D_PRIMITIVE_TOPOLOGY_LINESTRIP_ADJ,
+        TriangleList           => D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST,
+        TriangleListAdjacency  => D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST_ADJ,
+        TriangleStrip          => D3D_PRIMITIVE_TOPOLOGY_TRIANGLESTRIP,
+        TriangleStripAdjacency => D3D_PRIMITIVE_TOPOLOGY_TRIANGLESTRIP_ADJ,
+        PatchList(num) => { assert!(num != 0);
+            D3D_PRIMITIVE_TOPOLOGY_1_CONTROL_POINT_PATCHLIST + (num as u32) - 1
+        },
+    }
+}
+
+pub fn map_rasterizer(rasterizer: &pso::Rasterizer) -> D3D11_RASTERIZER_DESC {
+    use hal::pso::PolygonMode::*;
+    use hal::pso::CullFace::*;
+    use hal::pso::FrontFace::*;
+
+    if rasterizer.conservative {
+        error!("Conservative rasterization is not supported");
+    }
+
+    D3D11_RASTERIZER_DESC {
+        FillMode: match rasterizer.polygon_mode {
+            Point => {
+                error!("Point rasterization is not supported");
+                D3D11_FILL_WIREFRAME
+            },
+            Line(pso::State::Static(width)) if width != 1.0 => {
+                error!("Unsupported line width: {:?}", width);
+                D3D11_FILL_WIREFRAME
+            },
+            Line(_) => D3D11_FILL_WIREFRAME,
+            Fill => D3D11_FILL_SOLID,
+        },
+        CullMode: match rasterizer.cull_face {
+            None => D3D11_CULL_NONE,
+            Some(Front) => D3D11_CULL_FRONT,
+            Some(Back) => D3D11_CULL_BACK,
+        },
+        FrontCounterClockwise: match rasterizer.front_face {
+            Clockwise => FALSE,
+            CounterClockwise => TRUE,
+        },
+        DepthBias: rasterizer.depth_bias.map_or(0, |bias| bias.const_factor as INT),
+        DepthBiasClamp: rasterizer.depth_bias.map_or(0.0, |bias| bias.clamp),
+        SlopeScaledDepthBias: rasterizer.depth_bias.map_or(0.0, |bias| bias.slope_factor),
+        // Depth values are always clamped to the viewport depth range.
+        DepthClipEnable: if rasterizer.depth_clipping { TRUE } else { FALSE },
+        // Scissor testing can't be disabled in Vulkan.
+        ScissorEnable: TRUE,
+        MultisampleEnable: FALSE, // TODO: currently not supported
+        AntialiasedLineEnable: FALSE, // TODO: currently not supported
+    }
+}
+
+fn map_factor(factor: pso::Factor) -> D3D11_BLEND {
+    use hal::pso::Factor::*;
+    match factor {
+        Zero => D3D11_BLEND_ZERO,
+        One => D3D11_BLEND_ONE,
+        SrcColor => D3D11_BLEND_SRC_COLOR,
+        OneMinusSrcColor => D3D11_BLEND_INV_SRC_COLOR,
+        DstColor => D3D11_BLEND_DEST_COLOR,
+        OneMinusDstColor => D3D11_BLEND_INV_DEST_COLOR,
+        SrcAlpha => D3D11_BLEND_SRC_ALPHA,
+        OneMinusSrcAlpha => D3D11_BLEND_INV_SRC_ALPHA,
+        DstAlpha => D3D11_BLEND_DEST_ALPHA,
+        OneMinusDstAlpha => D3D11_BLEND_INV_DEST_ALPHA,
+        ConstColor | ConstAlpha => D3D11_BLEND_BLEND_FACTOR,
+        OneMinusConstColor | OneMinusConstAlpha => D3D11_BLEND_INV_BLEND_FACTOR,
+        SrcAlphaSaturate => D3D11_BLEND_SRC_ALPHA_SAT,
+        Src1Color => D3D11_BLEND_SRC1_COLOR,
+        OneMinusSrc1Color => D3D11_BLEND_INV_SRC1_COLOR,
+        Src1Alpha => D3D11_BLEND_SRC1_ALPHA,
+        OneMinusSrc1Alpha => D3D11_BLEND_INV_SRC1_ALPHA,
+    }
+}
+
+fn map_blend_op(operation: pso::BlendOp) -> (D3D11_BLEND_OP, D3D11_BLEND, D3D11_BLEND) {
+    use hal::pso::BlendOp::*;
+    match operation {
+        Add    { src, dst } => (D3D11_BLEND_OP_ADD,          map_factor(src), map_factor(dst)),
+        Sub    { src, dst } => (D3D11_BLEND_OP_SUBTRACT,     map_factor(src), map_factor(dst)),
+        RevSub { src, dst } => (D3D11_BLEND_OP_REV_SUBTRACT, map_factor(src), map_factor(dst)),
+        Min => (D3D11_BLEND_OP_MIN, D3D11_BLEND_ZERO, D3D11_BLEND_ZERO),
+        Max => (D3D11_BLEND_OP_MAX, D3D11_BLEND_ZERO, D3D11_BLEND_ZERO),
+    }
+}
+
+pub fn map_blend_desc(desc: &pso::BlendDesc) -> D3D11_BLEND_DESC {
+    if desc.logic_op.is_some() {
+        // Requires `D3D11_BLEND_DESC1` of the 11.1 runtime.
+        error!("Logic operations are not supported");
+    }
+
+    let dummy_target = D3D11_RENDER_TARGET_BLEND_DESC {
+        BlendEnable: FALSE,
+        SrcBlend: D3D11_BLEND_ZERO,
+        DestBlend: D3D11_BLEND_ZERO,
+        BlendOp: D3D11_BLEND_OP_ADD,
+        SrcBlendAlpha: D3D11_BLEND_ZERO,
+        DestBlendAlpha: D3D11_BLEND_ZERO,
+        BlendOpAlpha: D3D11_BLEND_OP_ADD,
+        RenderTargetWriteMask: 0,
+    };
+    let mut targets = [dummy_target; 8];
+
+    for (target, &pso::ColorBlendDesc(mask, blend)) in targets.iter_mut().zip(desc.targets.iter()) {
+        target.RenderTargetWriteMask = mask.bits() as UINT8;
+        if let pso::BlendState::On { color, alpha } = blend {
+            let (color_op, color_src, color_dst) = map_blend_op(color);
+            let (alpha_op, alpha_src, alpha_dst) = map_blend_op(alpha);
+            target.BlendEnable = TRUE;
+            target.BlendOp = color_op;
+            target.SrcBlend = color_src;
+            target.DestBlend = color_dst;
+            target.BlendOpAlpha = alpha_op;
+            target.SrcBlendAlpha = alpha_src;
+            target.DestBlendAlpha = alpha_dst;
+        }
+    }
+
+    D3D11_BLEND_DESC {
+        AlphaToCoverageEnable: if desc.alpha_coverage { TRUE } else { FALSE },
+        IndependentBlendEnable: TRUE,
+        RenderTarget: targets,
+    }
+}
+
+pub fn map_depth_stencil(dsi: &pso::DepthStencilDesc) -> D3D11_DEPTH_STENCIL_DESC {
+    if dsi.depth_bounds {
+        error!("Depth bounds testing is not supported");
+    }
+
+    let (depth_on, depth_write, depth_func) = match dsi.depth {
+        pso::DepthTest::On { fun, write } => (TRUE, write, map_comparison(fun)),
+        pso::DepthTest::Off => unsafe { mem::zeroed() },
+    };
+
+    let (stencil_on, front, back, read_mask, write_mask) = match dsi.stencil {
+        pso::StencilTest::On { ref front, ref back } => {
+            if front.mask_read != back.mask_read || front.mask_write != back.mask_write {
+                error!("Different masks on stencil front ({:?}) and back ({:?}) are not supported", front, back);
+            }
+            (TRUE, map_stencil_side(front), map_stencil_side(back), front.mask_read, front.mask_write)
+        },
+        pso::StencilTest::Off => unsafe { mem::zeroed() },
+    };
+
+    D3D11_DEPTH_STENCIL_DESC {
+        DepthEnable: depth_on,
+        DepthWriteMask: if depth_write {D3D11_DEPTH_WRITE_MASK_ALL} else {D3D11_DEPTH_WRITE_MASK_ZERO},
+        DepthFunc: depth_func,
+        StencilEnable: stencil_on,
+        StencilReadMask: read_mask as _,
+        StencilWriteMask: write_mask as _,
+        FrontFace: front,
+        BackFace: back,
+    }
+}
+
+pub fn map_comparison(func: pso::Comparison) -> D3D11_COMPARISON_FUNC {
+    use hal::pso::Comparison::*;
+    match func {
+        Never => D3D11_COMPARISON_NEVER,
+        Less => D3D11_COMPARISON_LESS,
+        LessEqual => D3D11_COMPARISON_LESS_EQUAL,
+        Equal => D3D11_COMPARISON_EQUAL,
+        GreaterEqual => D3D11_COMPARISON_GREATER_EQUAL,
+        Greater => D3D11_COMPARISON_GREATER,
+        NotEqual => D3D11_COMPARISON_NOT_EQUAL,
+        Always => D3D11_COMPARISON_ALWAYS,
+    }
+}
+
+fn map_stencil_op(op: pso::StencilOp) -> D3D11_STENCIL_OP {
+    use hal::pso::StencilOp::*;
+    match op {
+        Keep => D3D11_STENCIL_OP_KEEP,
+        Zero => D3D11_STENCIL_OP_ZERO,
+        Replace => D3D11_STENCIL_OP_REPLACE,
+        IncrementClamp => D3D11_STENCIL_OP_INCR_SAT,
+        IncrementWrap => D3D11_STENCIL_OP_INCR,
+        DecrementClamp => D3D11_STENCIL_OP_DECR_SAT,
+        DecrementWrap => D3D11_STENCIL_OP_DECR,
+        Invert => D3D11_STENCIL_OP_INVERT,
+    }
+}
+
+fn map_stencil_side(side: &pso::StencilFace) -> D3D11_DEPTH_STENCILOP_DESC {
+    D3D11_DEPTH_STENCILOP_DESC {
+        StencilFailOp: map_stencil_op(side.op_fail),
+        StencilDepthFailOp: map_stencil_op(side.op_depth_fail),
+        StencilPassOp: map_stencil_op(side.op_pass),
+        StencilFunc: map_comparison(side.fun),
+    }
+}
+
+pub fn map_wrap(wrap: image::WrapMode) -> D3D11_TEXTURE_ADDRESS_MODE {
+    use hal::image::WrapMode::*;
+    match wrap {
+        Tile   => D3D11_TEXTURE_ADDRESS_WRAP,
+        Mirror => D3D11_TEXTURE_ADDRESS_MIRROR,
+        Clamp  => D3D11_TEXTURE_ADDRESS_CLAMP,
+        Border => D3D11_TEXTURE_ADDRESS_BORDER,
+    }
+}
+
+fn map_filter_type(filter: image::Filter) -> D3D11_FILTER_TYPE {
+    match filter {
+        image::Filter::Nearest => D3D11_FILTER_TYPE_POINT,
+        image::Filter::Linear => D3D11_FILTER_TYPE_LINEAR,
+    }
+}
+
+pub fn map_filter(
+    mag_filter: image::Filter,
+    min_filter: image::Filter,
+    mip_filter: image::Filter,
+    anisotropic: image::Anisotropic,
+    reduction: D3D11_FILTER_REDUCTION_TYPE,
+) -> D3D11_FILTER {
+    if let image::Anisotropic::On(_) = anisotropic {
+        return match reduction {
+            D3D11_FILTER_REDUCTION_TYPE_COMPARISON => D3D11_FILTER_COMPARISON_ANISOTROPIC,
+            _ => D3D11_FILTER_ANISOTROPIC,
+        };
+    }
+
+    let mag = map_filter_type(mag_filter);
+    let min = map_filter_type(min_filter);
+    let mip = map_filter_type(mip_filter);
+
+    (min & D3D11_FILTER_TYPE_MASK) << D3D11_MIN_FILTER_SHIFT |
+    (mag & D3D11_FILTER_TYPE_MASK) << D3D11_MAG_FILTER_SHIFT |
+    (mip & D3D11_FILTER_TYPE_MASK) << D3D11_MIP_FILTER_SHIFT |
+    (reduction & D3D11_FILTER_REDUCTION_TYPE_MASK) << D3D11_FILTER_REDUCTION_TYPE_SHIFT
+}
+
+/// Bind flags of a buffer.
+///
+/// Constant buffers can't be combined with any other bind flag,
+/// which is rejected on buffer creation.
+pub fn map_buffer_bind_flags(usage: buffer::Usage) -> UINT {
+    use self::buffer::Usage;
+
+    if usage.contains(Usage::UNIFORM) {
+        return D3D11_BIND_CONSTANT_BUFFER;
+    }
+
+    let mut flags = 0;
+    if usage.contains(Usage::VERTEX) {
+        flags |= D3D11_BIND_VERTEX_BUFFER;
+    }
+    if usage.contains(Usage::INDEX) {
+        flags |= D3D11_BIND_INDEX_BUFFER;
+    }
+    if usage.contains(Usage::UNIFORM_TEXEL) {
+        flags |= D3D11_BIND_SHADER_RESOURCE;
+    }
+    if usage.contains(Usage::STORAGE_TEXEL) {
+        flags |= D3D11_BIND_UNORDERED_ACCESS;
+    }
+    if usage.contains(Usage::STORAGE) {
+        flags |= D3D11_BIND_SHADER_RESOURCE | D3D11_BIND_UNORDERED_ACCESS;
+    }
+
+    flags
+}
+
+pub fn map_buffer_misc_flags(usage: buffer::Usage) -> UINT {
+    let mut flags = 0;
+    if usage.contains(buffer::Usage::STORAGE) {
+        flags |= D3D11_RESOURCE_MISC_BUFFER_ALLOW_RAW_VIEWS;
+    }
+    if usage.contains(buffer::Usage::INDIRECT) {
+        flags |= D3D11_RESOURCE_MISC_DRAWINDIRECT_ARGS;
+    }
+    flags
+}
+
+pub fn map_image_bind_flags(usage: image::Usage) -> UINT {
+    use self::image::Usage;
+    let mut flags = 0;
+
+    if usage.intersects(Usage::SAMPLED | Usage::INPUT_ATTACHMENT) {
+        flags |= D3D11_BIND_SHADER_RESOURCE;
+    }
+    if usage.contains(Usage::COLOR_ATTACHMENT) {
+        flags |= D3D11_BIND_RENDER_TARGET;
+    }
+    if usage.contains(Usage::DEPTH_STENCIL_ATTACHMENT) {
+        flags |= D3D11_BIND_DEPTH_STENCIL;
+    }
+    if usage.contains(Usage::STORAGE) {
+        flags |= D3D11_BIND_UNORDERED_ACCESS;
+    }
+
+    flags
+}
+
+pub fn map_execution_model(model: spirv::ExecutionModel) -> pso::Stage {
+    match model {
+        spirv::ExecutionModel::Vertex => pso::Stage::Vertex,
+        spirv::ExecutionModel::Fragment => pso::Stage::Fragment,
+        spirv::ExecutionModel::Geometry => pso::Stage::Geometry,
+        spirv::ExecutionModel::GlCompute => pso::Stage::Compute,
+        spirv::ExecutionModel::TessellationControl => pso::Stage::Hull,
+        spirv::ExecutionModel::TessellationEvaluation => pso::Stage::Domain,
+        spirv::ExecutionModel::Kernel => panic!("Kernel is not a valid execution model."),
+    }
+}
+
+pub fn map_stage(stage: pso::Stage) -> spirv::ExecutionModel {
+    match stage {
+        pso::Stage::Vertex => spirv::ExecutionModel::Vertex,
+        pso::Stage::Fragment => spirv::ExecutionModel::Fragment,
+        pso::Stage::Geometry => spirv::ExecutionModel::Geometry,
+        pso::Stage::Compute => spirv::ExecutionModel::GlCompute,
+        pso::Stage::Hull => spirv::ExecutionModel::TessellationControl,
+        pso::Stage::Domain => spirv::ExecutionModel::TessellationEvaluation,
+    }
+}
@@ -1,984 +1,1836 @@
-use std::{cmp, ptr, slice};
-use std::collections::BTreeMap as Map;
-use std::os::raw::c_void;
-use std::sync::Arc;
-use winapi;
-use core::{self, device as d, buffer, texture, mapping};
-use core::memory::{self, Bind, Typed};
-use core::handle::{self as h, Producer};
-use {Resources as R, Share, Buffer, Fence, Texture, Pipeline, Program, Shader};
-use command::RawCommandBuffer;
-use {CommandList, DeferredContext, ShaderModel};
-use native;
+use std::borrow::Borrow;
+use std::cell::UnsafeCell;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::{cmp, mem, ptr, thread, time};
+
+use winapi::Interface;
+use winapi::shared::dxgiformat::{self, DXGI_FORMAT};
+use winapi::shared::minwindef::{TRUE, UINT};
+use winapi::shared::{dxgi, dxgitype, winerror};
+use winapi::um::{d3d11, d3d11_1, d3dcommon};
+use winapi::um::unknwnbase::IUnknown;
 use wio::com::ComPtr;
 
-
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub struct MappingGate {
-    pointer: *mut c_void,
-}
-
-unsafe impl Send for MappingGate {}
-unsafe impl Sync for MappingGate {}
-
-impl core::mapping::Gate<R> for MappingGate {
-    unsafe fn set<T>(&self, index: usize, val: T) {
-        *(self.pointer as *mut T).offset(index as isize) = val;
+use hal::{self, accel_struct, buffer, device as d, error, format, image, indirect, mapping, memory, pass, pso, query};
+use hal::memory::Requirements;
+use hal::pool::CommandPoolCreateFlags;
+use hal::queue::QueueFamilyId;
+use hal::range::RangeArg;
+
+use {conv, native as n, shader, window as w, Backend as B, MAX_VERTEX_BUFFERS, MEMORY_TYPE_HOST_VISIBLE};
+use pool::RawCommandPool;
+
+// Buffers can be bound to device local and host visible memory,
+// images are restricted to device local memory.
+const BUFFER_TYPE_MASK: u64 = 0b11;
+const IMAGE_TYPE_MASK: u64 = 0b01;
+
+/// Block until the GPU finished all work submitted to the context.
+pub(crate) fn wait_for_gpu(context: &ComPtr<d3d11_1::ID3D11DeviceContext1>) {
+    let fence = n::Fence::new(false);
+    fence.signal_on(context);
+    while !fence.is_signaled(context) {
+        thread::yield_now();
     }
+}
 
-    unsafe fn slice<'a, 'b, T>(&'a self, len: usize) -> &'b [T] {
-        slice::from_raw_parts(self.pointer as *const T, len)
-    }
+macro_rules! create_shader {
+    ($device:expr, $method:ident, $blob:expr) => {{
+        let mut shader = ptr::null_mut();
+        let hr = unsafe {
+            $device.$method(
+                $blob.GetBufferPointer(),
+                $blob.GetBufferSize(),
+                ptr::null_mut(),
+                &mut shader as *mut *mut _,
+            )
+        };
+        if winerror::SUCCEEDED(hr) {
+            Ok(unsafe { ComPtr::from_raw(shader) })
+        } else {
+            error!("error on shader creation: {:x}", hr);
+            Err(pso::CreationError::Other)
+        }
+    }};
+}
 
-    unsafe fn mut_slice<'a, 'b, T>(&'a self, len: usize) -> &'b mut [T] {
-        slice::from_raw_parts_mut(self.pointer as *mut T, len)
-    }
+#[derive(Debug)]
+pub struct UnboundBuffer {
+    size: u64,
+    usage: buffer::Usage,
+    requirements: Requirements,
 }
 
 #[derive(Debug)]
-struct TextureParam {
-    levels: winapi::UINT,
-    format: winapi::DXGI_FORMAT,
-    bytes_per_texel: winapi::UINT,
-    bind: winapi::D3D11_BIND_FLAG,
-    usage: winapi::D3D11_USAGE,
-    cpu_access: winapi::D3D11_CPU_ACCESS_FLAG,
+pub struct UnboundImage {
+    kind: image::Kind,
+    mip_levels: image::Level,
+    format: format::Format,
+    usage: image::Usage,
+    flags: image::StorageFlags,
+    requirements: Requirements,
 }
 
-pub struct Device {
-    device: ComPtr<winapi::ID3D11Device>,
-    share: Arc<Share>,
-    frame_handles: h::Manager<R>,
-    vs_cache: Map<u64, Vec<u8>>,
-    /// Create typed surface formats for the textures. This is useful for debugging
-    /// with PIX, since it doesn't understand typeless formats. This may also prevent
-    /// some valid views to be created because the typed formats can't be reinterpret.
-    use_texture_format_hint: bool,
-    sub_data_array: Vec<winapi::D3D11_SUBRESOURCE_DATA>,
-    feature_level: winapi::D3D_FEATURE_LEVEL,
+/// Description of an image view to create.
+pub(crate) struct ViewInfo {
+    pub(crate) resource: *mut d3d11::ID3D11Resource,
+    pub(crate) kind: image::Kind,
+    pub(crate) view_kind: image::ViewKind,
+    pub(crate) format: DXGI_FORMAT,
+    pub(crate) range: image::SubresourceRange,
 }
 
-impl Clone for Device {
-    fn clone(&self) -> Device {
-        Device::new(self.device.clone(), self.feature_level, self.share.clone())
-    }
+pub struct Device {
+    pub(crate) raw: ComPtr<d3d11_1::ID3D11Device1>,
+    // Immediate context, shared with the command queue.
+    pub(crate) context: Arc<Mutex<ComPtr<d3d11_1::ID3D11DeviceContext1>>>,
 }
 
+unsafe impl Send for Device {}
+unsafe impl Sync for Device {}
+
 impl Device {
-    /// Create a new `Device`.
-    pub fn new(device: ComPtr<winapi::ID3D11Device>, feature_level: winapi::D3D_FEATURE_LEVEL, share: Arc<Share>) -> Device {
-        Device {
-            device: device,
-            share: share,
-            frame_handles: h::Manager::new(),
-            vs_cache: Map::new(),
-            use_texture_format_hint: false,
-            sub_data_array: Vec::new(),
-            feature_level: feature_level,
-        }
-    }
-
-    #[doc(hidden)]
-    pub fn wrap_back_buffer(&mut self, back_buffer: *mut winapi::ID3D11Texture2D, info: texture::Info,
-                            desc: texture::RenderDesc) -> h::RawRenderTargetView<R> {
-        use core::Device;
-        let raw_tex = Texture(native::Texture::D2(back_buffer));
-        let color_tex = self.share.handles.borrow_mut().make_texture(raw_tex, info);
-        self.view_texture_as_render_target_raw(&color_tex, desc).unwrap()
-    }
-
-    /// Return the maximum supported shader model.
-    pub fn shader_model(&self) -> ShaderModel {
-        match self.feature_level {
-            winapi::D3D_FEATURE_LEVEL_10_0 => 40,
-            winapi::D3D_FEATURE_LEVEL_10_1 => 41,
-            winapi::D3D_FEATURE_LEVEL_11_0 => 50,
-            winapi::D3D_FEATURE_LEVEL_11_1 => 51,
-            _ => {
-                error!("Unknown feature level {:?}", self.feature_level);
-                0
-            },
-        }
+    pub(crate) fn new(
+        raw: ComPtr<d3d11_1::ID3D11Device1>,
+        context: Arc<Mutex<ComPtr<d3d11_1::ID3D11DeviceContext1>>>,
+    ) -> Self {
+        Device { raw, context }
     }
 
-    pub fn create_command_buffer(&self) -> RawCommandBuffer<CommandList> {
-        CommandList::new().into()
+    /// Create a shader module from HLSL with a single entry point
+    pub fn create_shader_module_from_source(
+        &self,
+        stage: pso::Stage,
+        hlsl_entry: &str,
+        entry_point: &str,
+        code: &[u8],
+    ) -> Result<n::ShaderModule, d::ShaderError> {
+        let mut shader_map = BTreeMap::new();
+        let blob = shader::compile_hlsl(stage, hlsl_entry, code)?;
+        shader_map.insert(entry_point.into(), blob);
+        Ok(n::ShaderModule::Compiled(shader_map))
     }
 
-    pub fn create_command_buffer_native(&mut self) -> RawCommandBuffer<DeferredContext> {
-        let mut dc = unsafe { ComPtr::<winapi::ID3D11DeviceContext>::new(ptr::null_mut()) };
+    pub(crate) fn view_image_as_shader_resource(
+        device: &ComPtr<d3d11_1::ID3D11Device1>, info: &ViewInfo,
+    ) -> Result<ComPtr<d3d11::ID3D11ShaderResourceView>, image::ViewError> {
+        #![allow(non_snake_case)]
+
+        let mut desc = d3d11::D3D11_SHADER_RESOURCE_VIEW_DESC {
+            Format: info.format,
+            ViewDimension: 0,
+            u: unsafe { mem::zeroed() },
+        };
+
+        let MostDetailedMip = info.range.levels.start as _;
+        let MipLevels = (info.range.levels.end - info.range.levels.start) as _;
+        let FirstArraySlice = info.range.layers.start as _;
+        let ArraySize = (info.range.layers.end - info.range.layers.start) as _;
+        let is_msaa = info.kind.num_samples() > 1;
+
+        match info.view_kind {
+            image::ViewKind::D1 => {
+                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURE1D;
+                *unsafe { desc.u.Texture1D_mut() } = d3d11::D3D11_TEX1D_SRV {
+                    MostDetailedMip,
+                    MipLevels,
+                }
+            }
+            image::ViewKind::D1Array => {
+                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURE1DARRAY;
+                *unsafe { desc.u.Texture1DArray_mut() } = d3d11::D3D11_TEX1D_ARRAY_SRV {
+                    MostDetailedMip,
+                    MipLevels,
+                    FirstArraySlice,
+                    ArraySize,
+                }
+            }
+            image::ViewKind::D2 if is_msaa => {
+                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURE2DMS;
+                *unsafe { desc.u.Texture2DMS_mut() } = d3d11::D3D11_TEX2DMS_SRV {
+                    UnusedField_NothingToDefine: 0,
+                }
+            }
+            image::ViewKind::D2 => {
+                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURE2D;
+                *unsafe { desc.u.Texture2D_mut() } = d3d11::D3D11_TEX2D_SRV {
+                    MostDetailedMip,
+                    MipLevels,
+                }
+            }
+            image::ViewKind::D2Array if is_msaa => {
+                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURE2DMSARRAY;
+                *unsafe { desc.u.Texture2DMSArray_mut() } = d3d11::D3D11_TEX2DMS_ARRAY_SRV {
+                    FirstArraySlice,
+                    ArraySize,
+                }
+            }
+            image::ViewKind::D2Array => {
+                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURE2DARRAY;
+                *unsafe { desc.u.Texture2DArray_mut() } = d3d11::D3D11_TEX2D_ARRAY_SRV {
+                    MostDetailedMip,
+                    MipLevels,
+                    FirstArraySlice,
+                    ArraySize,
+                }
+            }
+            image::ViewKind::D3 => {
+                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURE3D;
+                *unsafe { desc.u.Texture3D_mut() } = d3d11::D3D11_TEX3D_SRV {
+                    MostDetailedMip,
+                    MipLevels,
+                }
+            }
+            image::ViewKind::Cube => {
+                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURECUBE;
+                *unsafe { desc.u.TextureCube_mut() } = d3d11::D3D11_TEXCUBE_SRV {
+                    MostDetailedMip,
+                    MipLevels,
+                }
+            }
+            image::ViewKind::CubeArray => {
+                assert_eq!(0, ArraySize % 6);
+                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURECUBEARRAY;
+                *unsafe { desc.u.TextureCubeArray_mut() } = d3d11::D3D11_TEXCUBE_ARRAY_SRV {
+                    MostDetailedMip,
+                    MipLevels,
+                    First2DArrayFace: FirstArraySlice,
+                    NumCubes: ArraySize / 6,
+                }
+            }
+        }
+
+        let mut view = ptr::null_mut();
         let hr = unsafe {
-            self.device.CreateDeferredContext(0, &mut dc.as_mut() as *mut &mut _ as *mut *mut _)
+            device.CreateShaderResourceView(info.resource, &desc, &mut view as *mut *mut _)
         };
-        if winapi::SUCCEEDED(hr) {
-            DeferredContext::new(dc).into()
-        }else {
-            panic!("Failed to create a deferred context")
+        if winerror::SUCCEEDED(hr) {
+            Ok(unsafe { ComPtr::from_raw(view) })
+        } else {
+            error!("error on SRV creation: {:x}", hr);
+            Err(image::ViewError::Unsupported)
         }
     }
 
-    fn create_buffer_internal(&mut self, info: buffer::Info, raw_data: Option<*const c_void>)
-                              -> Result<h::RawBuffer<R>, buffer::CreationError> {
-        use winapi::d3d11::*;
-        use data::{map_bind, map_usage};
+    pub(crate) fn view_image_as_render_target(
+        device: &ComPtr<d3d11_1::ID3D11Device1>, info: &ViewInfo,
+    ) -> Result<ComPtr<d3d11::ID3D11RenderTargetView>, image::ViewError> {
+        #![allow(non_snake_case)]
 
-        let (subind, size) = match info.role {
-            buffer::Role::Vertex   =>
-                (D3D11_BIND_VERTEX_BUFFER, info.size),
-            buffer::Role::Index    => {
-                if info.stride != 2 && info.stride != 4 {
-                    error!("Only U16 and U32 index buffers are allowed");
-                    return Err(buffer::CreationError::Other);
-                }
-                (D3D11_BIND_INDEX_BUFFER, info.size)
-            },
-            buffer::Role::Constant  => // 16 bit alignment
-                (D3D11_BIND_CONSTANT_BUFFER, (info.size + 0xF) & !0xF),
-            buffer::Role::Staging =>
-                (D3D11_BIND_FLAG(0), info.size)
-        };
-
-        assert!(size >= info.size);
-        let (usage, cpu) = map_usage(info.usage, info.bind);
-        let bind = map_bind(info.bind) | subind;
-        if info.bind.contains(memory::RENDER_TARGET) | info.bind.contains(memory::DEPTH_STENCIL) {
-            return Err(buffer::CreationError::UnsupportedBind(info.bind))
-        }
-        let native_desc = D3D11_BUFFER_DESC {
-            ByteWidth: size as winapi::UINT,
-            Usage: usage,
-            BindFlags: bind.0,
-            CPUAccessFlags: cpu.0,
-            MiscFlags: 0,
-            StructureByteStride: 0, //TODO
-        };
-        let mut sub = D3D11_SUBRESOURCE_DATA {
-            pSysMem: ptr::null(),
-            SysMemPitch: 0,
-            SysMemSlicePitch: 0,
-        };
-        let sub_raw = match raw_data {
-            Some(data) => {
-                sub.pSysMem = data;
-                &sub as *const _
-            },
-            None => ptr::null(),
+        let mut desc = d3d11::D3D11_RENDER_TARGET_VIEW_DESC {
+            Format: info.format,
+            ViewDimension: 0,
+            u: unsafe { mem::zeroed() },
         };
 
-        debug!("Creating Buffer with info {:?} and sub-data {:?}", info, sub);
-        let mut raw_buf = native::Buffer(ptr::null_mut());
-        let hr = unsafe {
-            self.device.CreateBuffer(&native_desc, sub_raw, &mut raw_buf.0)
-        };
-        if winapi::SUCCEEDED(hr) {
-            let buf = Buffer(raw_buf);
+        let MipSlice = info.range.levels.start as _;
+        let FirstArraySlice = info.range.layers.start as _;
+        let ArraySize = (info.range.layers.end - info.range.layers.start) as _;
+        let is_msaa = info.kind.num_samples() > 1;
 
-            use core::memory::Usage::*;
-            let mapping = match info.usage {
-                Data | Dynamic => None,
-                Upload | Download => Some(MappingGate { pointer: ptr::null_mut() }),
-            };
+        match info.view_kind {
+            image::ViewKind::D1 => {
+                desc.ViewDimension = d3d11::D3D11_RTV_DIMENSION_TEXTURE1D;
+                *unsafe { desc.u.Texture1D_mut() } = d3d11::D3D11_TEX1D_RTV {
+                    MipSlice,
+                }
+            }
+            image::ViewKind::D1Array => {
+                desc.ViewDimension = d3d11::D3D11_RTV_DIMENSION_TEXTURE1DARRAY;
+                *unsafe { desc.u.Texture1DArray_mut() } = d3d11::D3D11_TEX1D_ARRAY_RTV {
+                    MipSlice,
+                    FirstArraySlice,
+                    ArraySize,
+                }
+            }
+            image::ViewKind::D2 if is_msaa => {
+                desc.ViewDimension = d3d11::D3D11_RTV_DIMENSION_TEXTURE2DMS;
+                *unsafe { desc.u.Texture2DMS_mut() } = d3d11::D3D11_TEX2DMS_RTV {
+                    UnusedField_NothingToDefine: 0,
+                }
+            }
+            image::ViewKind::D2 => {
+                desc.ViewDimension = d3d11::D3D11_RTV_DIMENSION_TEXTURE2D;
+                *unsafe { desc.u.Texture2D_mut() } = d3d11::D3D11_TEX2D_RTV {
+                    MipSlice,
+                }
+            }
+            image::ViewKind::D2Array if is_msaa => {
+                desc.ViewDimension = d3d11::D3D11_RTV_DIMENSION_TEXTURE2DMSARRAY;
+                *unsafe { desc.u.Texture2DMSArray_mut() } = d3d11::D3D11_TEX2DMS_ARRAY_RTV {
+                    FirstArraySlice,
+                    ArraySize,
+                }
+            }
+            image::ViewKind::D2Array |
+            image::ViewKind::Cube |
+            image::ViewKind::CubeArray => {
+                desc.ViewDimension = d3d11::D3D11_RTV_DIMENSION_TEXTURE2DARRAY;
+                *unsafe { desc.u.Texture2DArray_mut() } = d3d11::D3D11_TEX2D_ARRAY_RTV {
+                    MipSlice,
+                    FirstArraySlice,
+                    ArraySize,
+                }
+            }
+            image::ViewKind::D3 => {
+                desc.ViewDimension = d3d11::D3D11_RTV_DIMENSION_TEXTURE3D;
+                *unsafe { desc.u.Texture3D_mut() } = d3d11::D3D11_TEX3D_RTV {
+                    MipSlice,
+                    FirstWSlice: FirstArraySlice,
+                    WSize: ArraySize,
+                }
+            }
+        }
 
-            Ok(self.share.handles.borrow_mut().make_buffer(buf, info, mapping))
+        let mut view = ptr::null_mut();
+        let hr = unsafe {
+            device.CreateRenderTargetView(info.resource, &desc, &mut view as *mut *mut _)
+        };
+        if winerror::SUCCEEDED(hr) {
+            Ok(unsafe { ComPtr::from_raw(view) })
         } else {
-            error!("Failed to create a buffer with desc {:#?}, error {:x}", native_desc, hr);
-            Err(buffer::CreationError::Other)
+            error!("error on RTV creation: {:x}", hr);
+            Err(image::ViewError::Unsupported)
         }
     }
 
-    fn update_sub_data(&mut self, w: texture::Size, h: texture::Size, bpt: winapi::UINT)
-                       -> *const winapi::D3D11_SUBRESOURCE_DATA {
-        use winapi::UINT;
-        for sub in self.sub_data_array.iter_mut() {
-            sub.SysMemPitch = w as UINT * bpt;
-            sub.SysMemSlicePitch = (h as UINT) * sub.SysMemPitch;
+    pub(crate) fn view_image_as_depth_stencil(
+        device: &ComPtr<d3d11_1::ID3D11Device1>, info: &ViewInfo,
+    ) -> Result<ComPtr<d3d11::ID3D11DepthStencilView>, image::ViewError> {
+        #![allow(non_snake_case)]
+
+        let mut desc = d3d11::D3D11_DEPTH_STENCIL_VIEW_DESC {
+            Format: info.format,
+            ViewDimension: 0,
+            Flags: 0,
+            u: unsafe { mem::zeroed() },
+        };
+
+        let MipSlice = info.range.levels.start as _;
+        let FirstArraySlice = info.range.layers.start as _;
+        let ArraySize = (info.range.layers.end - info.range.layers.start) as _;
+        let is_msaa = info.kind.num_samples() > 1;
+
+        match info.view_kind {
+            image::ViewKind::D1 => {
+                desc.ViewDimension = d3d11::D3D11_DSV_DIMENSION_TEXTURE1D;
+                *unsafe { desc.u.Texture1D_mut() } = d3d11::D3D11_TEX1D_DSV {
+                    MipSlice,
+                }
+            }
+            image::ViewKind::D1Array => {
+                desc.ViewDimension = d3d11::D3D11_DSV_DIMENSION_TEXTURE1DARRAY;
+                *unsafe { desc.u.Texture1DArray_mut() } = d3d11::D3D11_TEX1D_ARRAY_DSV {
+                    MipSlice,
+                    FirstArraySlice,
+                    ArraySize,
+                }
+            }
+            image::ViewKind::D2 if is_msaa => {
+                desc.ViewDimension = d3d11::D3D11_DSV_DIMENSION_TEXTURE2DMS;
+                *unsafe { desc.u.Texture2DMS_mut() } = d3d11::D3D11_TEX2DMS_DSV {
+                    UnusedField_NothingToDefine: 0,
+                }
+            }
+            image::ViewKind::D2 => {
+                desc.ViewDimension = d3d11::D3D11_DSV_DIMENSION_TEXTURE2D;
+                *unsafe { desc.u.Texture2D_mut() } = d3d11::D3D11_TEX2D_DSV {
+                    MipSlice,
+                }
+            }
+            image::ViewKind::D2Array if is_msaa => {
+                desc.ViewDimension = d3d11::D3D11_DSV_DIMENSION_TEXTURE2DMSARRAY;
+                *unsafe { desc.u.Texture2DMSArray_mut() } = d3d11::D3D11_TEX2DMS_ARRAY_DSV {
+                    FirstArraySlice,
+                    ArraySize,
+                }
+            }
+            image::ViewKind::D2Array |
+            image::ViewKind::Cube |
+            image::ViewKind::CubeArray => {
+                desc.ViewDimension = d3d11::D3D11_DSV_DIMENSION_TEXTURE2DARRAY;
+                *unsafe { desc.u.Texture2DArray_mut() } = d3d11::D3D11_TEX2D_ARRAY_DSV {
+                    MipSlice,
+                    FirstArraySlice,
+                    ArraySize,
+                }
+            }
+            image::ViewKind::D3 => {
+                error!("3D images can't be viewed as depth stencil");
+                return Err(image::ViewError::BadKind)
+            }
         }
-        self.sub_data_array.as_ptr()
-    }
 
-    fn create_texture_1d(&mut self, size: texture::Size, array: texture::Layer,
-                         tp: TextureParam, misc: winapi::D3D11_RESOURCE_MISC_FLAG)
-                         -> Result<native::Texture, winapi::HRESULT>
-    {
-        use winapi::UINT;
-        let native_desc = winapi::D3D11_TEXTURE1D_DESC {
-            Width: size as UINT,
-            MipLevels: tp.levels,
-            ArraySize: array as UINT,
-            Format: tp.format,
-            Usage: tp.usage,
-            BindFlags: tp.bind.0,
-            CPUAccessFlags: tp.cpu_access.0,
-            MiscFlags: misc.0,
-        };
-        let sub_data = if self.sub_data_array.len() > 0 {
-            let num_data = array as usize * cmp::max(1, tp.levels) as usize;
-            if num_data != self.sub_data_array.len() {
-                error!("Texture1D with {} slices and {} levels is given {} data chunks",
-                    array, tp.levels, self.sub_data_array.len());
-                return Err(winapi::S_OK)
-            }
-            self.update_sub_data(size, 0, tp.bytes_per_texel)
-        }else {
-            ptr::null()
-        };
-
-        debug!("Creating Texture1D with size {:?}, layer {}, param {:?}, and sub-data {:p}",
-            size, array, tp, sub_data);
-        let mut raw = ptr::null_mut();
+        let mut view = ptr::null_mut();
         let hr = unsafe {
-            (*self.device).CreateTexture1D(&native_desc, sub_data, &mut raw)
+            device.CreateDepthStencilView(info.resource, &desc, &mut view as *mut *mut _)
         };
-        if winapi::SUCCEEDED(hr) {
-            Ok(native::Texture::D1(raw))
-        }else {
-            error!("CreateTexture1D failed on {:#?} with error {:x}", native_desc, hr);
-            Err(hr)
+        if winerror::SUCCEEDED(hr) {
+            Ok(unsafe { ComPtr::from_raw(view) })
+        } else {
+            error!("error on DSV creation: {:x}", hr);
+            Err(image::ViewError::Unsupported)
         }
     }
 
-    fn create_texture_2d(&mut self, size: [texture::Size; 2], array: texture::Layer, aa: texture::AaMode,
-                         tp: TextureParam, misc: winapi::D3D11_RESOURCE_MISC_FLAG)
-                         -> Result<native::Texture, winapi::HRESULT>
-    {
-        use winapi::UINT;
-        use data::map_anti_alias;
-
-        let native_desc = winapi::D3D11_TEXTURE2D_DESC {
-            Width: size[0] as UINT,
-            Height: size[1] as UINT,
-            MipLevels: tp.levels,
-            ArraySize: array as UINT,
-            Format: tp.format,
-            SampleDesc: map_anti_alias(aa),
-            Usage: tp.usage,
-            BindFlags: tp.bind.0,
-            CPUAccessFlags: tp.cpu_access.0,
-            MiscFlags: misc.0,
-        };
-        let sub_data = if self.sub_data_array.len() > 0 {
-            let num_data = array as usize * cmp::max(1, tp.levels) as usize;
-            if num_data != self.sub_data_array.len() {
-                error!("Texture2D with {} slices and {} levels is given {} data chunks",
-                    array, tp.levels, self.sub_data_array.len());
-                return Err(winapi::S_OK)
-            }
-            self.update_sub_data(size[0], size[1], tp.bytes_per_texel)
-        }else {
-            ptr::null()
-        };
-
-        debug!("Creating Texture2D with size {:?}, layer {}, param {:?}, and sub-data {:p}",
-            size, array, tp, sub_data);
-        let mut raw = ptr::null_mut();
+    pub(crate) fn view_image_as_storage(
+        device: &ComPtr<d3d11_1::ID3D11Device1>, info: &ViewInfo,
+    ) -> Result<ComPtr<d3d11::ID3D11UnorderedAccessView>, image::ViewError> {
+        #![allow(non_snake_case)]
+
+        let mut desc = d3d11::D3D11_UNORDERED_ACCESS_VIEW_DESC {
+            Format: info.format,
+            ViewDimension: 0,
+            u: unsafe { mem::zeroed() },
+        };
+
+        let MipSlice = info.range.levels.start as _;
+        let FirstArraySlice = info.range.layers.start as _;
+        let ArraySize = (info.range.layers.end - info.range.layers.start) as _;
+
+        if info.kind.num_samples() > 1 {
+            error!("MSAA images can't be viewed as UAV");
+            return Err(image::ViewError::Unsupported);
+        }
+
+        match info.view_kind {
+            image::ViewKind::D1 => {
+                desc.ViewDimension = d3d11::D3D11_UAV_DIMENSION_TEXTURE1D;
+                *unsafe { desc.u.Texture1D_mut() } = d3d11::D3D11_TEX1D_UAV {
+                    MipSlice,
+                }
+            }
+            image::ViewKind::D1Array => {
+                desc.ViewDimension = d3d11::D3D11_UAV_DIMENSION_TEXTURE1DARRAY;
+                *unsafe { desc.u.Texture1DArray_mut() } = d3d11::D3D11_TEX1D_ARRAY_UAV {
+                    MipSlice,
+                    FirstArraySlice,
+                    ArraySize,
+                }
+            }
+            image::ViewKind::D2 => {
+                desc.ViewDimension = d3d11::D3D11_UAV_DIMENSION_TEXTURE2D;
+                *unsafe { desc.u.Texture2D_mut() } = d3d11::D3D11_TEX2D_UAV {
+                    MipSlice,
+                }
+            }
+            image::ViewKind::D2Array |
+            image::ViewKind::Cube |
+            image::ViewKind::CubeArray => {
+                desc.ViewDimension = d3d11::D3D11_UAV_DIMENSION_TEXTURE2DARRAY;
+                *unsafe { desc.u.Texture2DArray_mut() } = d3d11::D3D11_TEX2D_ARRAY_UAV {
+                    MipSlice,
+                    FirstArraySlice,
+                    ArraySize,
+                }
+            }
+            image::ViewKind::D3 => {
+                desc.ViewDimension = d3d11::D3D11_UAV_DIMENSION_TEXTURE3D;
+                *unsafe { desc.u.Texture3D_mut() } = d3d11::D3D11_TEX3D_UAV {
+                    MipSlice,
+                    FirstWSlice: FirstArraySlice,
+                    WSize: ArraySize,
+                }
+            }
+        }
+
+        let mut view = ptr::null_mut();
         let hr = unsafe {
-            (*self.device).CreateTexture2D(&native_desc, sub_data, &mut raw)
+            device.CreateUnorderedAccessView(info.resource, &desc, &mut view as *mut *mut _)
         };
-        if winapi::SUCCEEDED(hr) {
-            Ok(native::Texture::D2(raw))
-        }else {
-            error!("CreateTexture2D failed on {:#?} with error {:x}", native_desc, hr);
-            Err(hr)
+        if winerror::SUCCEEDED(hr) {
+            Ok(unsafe { ComPtr::from_raw(view) })
+        } else {
+            error!("error on UAV creation: {:x}", hr);
+            Err(image::ViewError::Unsupported)
         }
     }
 
-    fn create_texture_3d(&mut self, size: [texture::Size; 3],
-                         tp: TextureParam, misc: winapi::D3D11_RESOURCE_MISC_FLAG)
-                         -> Result<native::Texture, winapi::HRESULT>
-    {
-        use winapi::UINT;
-        let native_desc = winapi::D3D11_TEXTURE3D_DESC {
-            Width: size[0] as UINT,
-            Height: size[1] as UINT,
-            Depth: size[2] as UINT,
-            MipLevels: tp.levels,
-            Format: tp.format,
-            Usage: tp.usage,
-            BindFlags: tp.bind.0,
-            CPUAccessFlags: tp.cpu_access.0,
-            MiscFlags: misc.0,
-        };
-        let sub_data = if self.sub_data_array.len() > 0 {
-            if cmp::max(1, tp.levels) as usize != self.sub_data_array.len() {
-                error!("Texture3D with {} levels is given {} data chunks",
-                    tp.levels, self.sub_data_array.len());
-                return Err(winapi::S_OK)
-            }
-            self.update_sub_data(size[0], size[1], tp.bytes_per_texel)
-        }else {
-            ptr::null()
-        };
-
-        debug!("Creating Texture3D with size {:?}, param {:?}, and sub-data {:p}",
-            size, tp, sub_data);
-        let mut raw = ptr::null_mut();
+    /// Create a raw (`ByteAddressBuffer`) view of a storage buffer.
+    fn view_buffer_as_raw(
+        &self, buffer: &ComPtr<d3d11::ID3D11Buffer>, size: u64,
+    ) -> (Option<ComPtr<d3d11::ID3D11ShaderResourceView>>, Option<ComPtr<d3d11::ID3D11UnorderedAccessView>>) {
+        let num_elements = (size / 4) as UINT;
+
+        let mut srv_desc = d3d11::D3D11_SHADER_RESOURCE_VIEW_DESC {
+            Format: dxgiformat::DXGI_FORMAT_R32_TYPELESS,
+            ViewDimension: d3dcommon::D3D11_SRV_DIMENSION_BUFFEREX,
+            u: unsafe { mem::zeroed() },
+        };
+        *unsafe { srv_desc.u.BufferEx_mut() } = d3d11::D3D11_BUFFEREX_SRV {
+            FirstElement: 0,
+            NumElements: num_elements,
+            Flags: d3d11::D3D11_BUFFEREX_SRV_FLAG_RAW,
+        };
+        let mut srv = ptr::null_mut();
         let hr = unsafe {
-            (*self.device).CreateTexture3D(&native_desc, sub_data, &mut raw)
+            self.raw.CreateShaderResourceView(buffer.as_raw() as *mut _, &srv_desc, &mut srv as *mut *mut _)
+        };
+        let srv = if winerror::SUCCEEDED(hr) {
+            Some(unsafe { ComPtr::from_raw(srv) })
+        } else {
+            error!("error on raw buffer SRV creation: {:x}", hr);
+            None
         };
-        if winapi::SUCCEEDED(hr) {
-            Ok(native::Texture::D3(raw))
-        }else {
-            error!("CreateTexture3D failed on {:#?} with error {:x}", native_desc, hr);
-            Err(hr)
+
+        let mut uav_desc = d3d11::D3D11_UNORDERED_ACCESS_VIEW_DESC {
+            Format: dxgiformat::DXGI_FORMAT_R32_TYPELESS,
+            ViewDimension: d3d11::D3D11_UAV_DIMENSION_BUFFER,
+            u: unsafe { mem::zeroed() },
+        };
+        *unsafe { uav_desc.u.Buffer_mut() } = d3d11::D3D11_BUFFER_UAV {
+            FirstElement: 0,
+            NumElements: num_elements,
+            Flags: d3d11::D3D11_BUFFER_UAV_FLAG_RAW,
+        };
+        let mut uav = ptr::null_mut();
+        let hr = unsafe {
+            self.raw.CreateUnorderedAccessView(buffer.as_raw() as *mut _, &uav_desc, &mut uav as *mut *mut _)
+        };
+        let uav = if winerror::SUCCEEDED(hr) {
+            Some(unsafe { ComPtr::from_raw(uav) })
+        } else {
+            error!("error on raw buffer UAV creation: {:x}", hr);
+            None
+        };
+
+        (srv, uav)
+    }
+}
+
+impl d::Device<B> for Device {
+    fn allocate_memory(
+        &self,
+        mem_type: hal::MemoryTypeId,
+        size: u64,
+    ) -> Result<n::Memory, d::OutOfMemory> {
+        let (properties, host) = if mem_type.0 == MEMORY_TYPE_HOST_VISIBLE {
+            (
+                memory::Properties::CPU_VISIBLE | memory::Properties::CPU_CACHED,
+                Some(UnsafeCell::new(vec![0; size as usize])),
+            )
+        } else {
+            (memory::Properties::DEVICE_LOCAL, None)
+        };
+
+        Ok(n::Memory {
+            properties,
+            size,
+            host,
+            buffers: Mutex::new(Vec::new()),
+        })
+    }
+
+    fn create_command_pool(
+        &self, _family: QueueFamilyId, _create_flags: CommandPoolCreateFlags
+    ) -> RawCommandPool {
+        RawCommandPool {
+            device: self.raw.clone(),
         }
     }
 
-    pub fn cleanup(&mut self) {
-        self.frame_handles.clear();
+    fn destroy_command_pool(&self, _pool: RawCommandPool) {
+        // automatic
     }
-}
 
-impl core::Device<R> for Device {
-    fn get_capabilities(&self) -> &core::Capabilities {
-        &self.share.capabilities
+    fn create_render_pass<'a, IA, IS, ID>(
+        &self,
+        attachments: IA,
+        subpasses: IS,
+        _dependencies: ID,
+    ) -> n::RenderPass
+    where
+        IA: IntoIterator,
+        IA::Item: Borrow<pass::Attachment>,
+        IS: IntoIterator,
+        IS::Item: Borrow<pass::SubpassDesc<'a>>,
+        ID: IntoIterator,
+        ID::Item: Borrow<pass::SubpassDependency>,
+    {
+        // Subpasses are executed in order, dependencies don't require any work.
+        let subpasses = subpasses
+            .into_iter()
+            .map(|sub| {
+                let sub = sub.borrow();
+                if sub.view_mask != 0 {
+                    error!("Multiview is not supported");
+                }
+                n::SubpassDesc {
+                    color_attachments: sub.colors.iter().map(|&(id, _)| id).collect(),
+                    depth_stencil_attachment: sub.depth_stencil.map(|&(id, _)| id),
+                }
+            })
+            .collect();
+
+        n::RenderPass {
+            attachments: attachments.into_iter().map(|att| att.borrow().clone()).collect(),
+            subpasses,
+        }
     }
 
-    fn create_buffer_raw(&mut self, info: buffer::Info) -> Result<h::RawBuffer<R>, buffer::CreationError> {
-        self.create_buffer_internal(info, None)
+    fn create_pipeline_layout<IS, IR>(
+        &self,
+        sets: IS,
+        push_constant_ranges: IR,
+    ) -> n::PipelineLayout
+    where
+        IS: IntoIterator,
+        IS::Item: Borrow<n::DescriptorSetLayout>,
+        IR: IntoIterator,
+        IR::Item: Borrow<(pso::ShaderStageFlags, Range<u32>)>,
+    {
+        use hal::pso::DescriptorType::*;
+
+        // Next free register of each type, shared by all shader stages.
+        let mut registers = n::RegisterData::<UINT>::default();
+        let sets = sets
+            .into_iter()
+            .map(|set| {
+                set.borrow().bindings
+                    .iter()
+                    .map(|binding| {
+                        let count = binding.count as UINT;
+                        let mut start = registers;
+                        match binding.ty {
+                            Sampler => registers.s += count,
+                            // Texture and sampler of a combined descriptor share the register index.
+                            CombinedImageSampler => {
+                                let base = cmp::max(registers.t, registers.s);
+                                start.t = base;
+                                start.s = base;
+                                registers.t = base + count;
+                                registers.s = base + count;
+                            }
+                            SampledImage | UniformTexelBuffer | InputAttachment => registers.t += count,
+                            StorageImage | StorageTexelBuffer | UniformImageDynamic => registers.u += count,
+                            // Read-only storage buffers are translated into SRVs,
+                            // both views share the register index.
+                            StorageBuffer => {
+                                let base = cmp::max(registers.t, registers.u);
+                                start.t = base;
+                                start.u = base;
+                                registers.t = base + count;
+                                registers.u = base + count;
+                            }
+                            UniformBuffer | UniformBufferDynamic => registers.c += count,
+                            AccelerationStructure => error!("Acceleration structures are not supported"),
+                        }
+                        (binding.clone(), start)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        if registers.c > shader::PUSH_CONSTANT_REGISTER {
+            error!("Too many uniform buffers in the pipeline layout: {}", registers.c);
+        }
+        for constant in push_constant_ranges {
+            let &(_, ref range) = constant.borrow();
+            if range.end * 4 > shader::PUSH_CONSTANT_SIZE {
+                error!("Push constant range {:?} exceeds the supported size", range);
+            }
+        }
+
+        n::PipelineLayout { sets }
     }
 
-    fn create_buffer_immutable_raw(&mut self, data: &[u8], stride: usize, role: buffer::Role, bind: Bind)
-                                -> Result<h::RawBuffer<R>, buffer::CreationError> {
-        let info = buffer::Info {
-            role: role,
-            usage: memory::Usage::Data,
-            bind: bind,
-            size: data.len(),
-            stride: stride,
+    fn create_graphics_pipeline<'a>(
+        &self,
+        desc: &pso::GraphicsPipelineDesc<'a, B>,
+    ) -> Result<n::GraphicsPipeline, pso::CreationError> {
+        let build_shader = |stage: pso::Stage, source: Option<&pso::EntryPoint<'a, B>>| {
+            match source {
+                Some(source) => shader::compile_entry_point(stage, source, desc.layout)
+                    .map(Some)
+                    .map_err(pso::CreationError::Shader),
+                None => Ok(None),
+            }
         };
-        self.create_buffer_internal(info, Some(data.as_ptr() as *const c_void))
-    }
 
-    fn create_shader(&mut self, stage: core::shade::Stage, code: &[u8])
-                     -> Result<h::Shader<R>, core::shade::CreateShaderError> {
-        use winapi::ID3D11DeviceChild;
-        use core::shade::{CreateShaderError, Stage};
-        use mirror::reflect_shader;
+        let vs_blob = build_shader(pso::Stage::Vertex, Some(&desc.shaders.vertex))?.unwrap();
+        let hs_blob = build_shader(pso::Stage::Hull, desc.shaders.hull.as_ref())?;
+        let ds_blob = build_shader(pso::Stage::Domain, desc.shaders.domain.as_ref())?;
+        let gs_blob = build_shader(pso::Stage::Geometry, desc.shaders.geometry.as_ref())?;
+        let ps_blob = build_shader(pso::Stage::Fragment, desc.shaders.fragment.as_ref())?;
 
-        let dev = &mut self.device;
-        let len = code.len() as winapi::SIZE_T;
-        let (hr, object) = match stage {
-            Stage::Vertex => {
-                let mut ret = ptr::null_mut();
-                let hr = unsafe {
-                    dev.CreateVertexShader(code.as_ptr() as *const c_void, len, ptr::null_mut(), &mut ret)
-                };
-                (hr, ret as *mut ID3D11DeviceChild)
-            },
-            Stage::Hull => {
-                let mut ret = ptr::null_mut();
-                let hr = unsafe {
-                    dev.CreateHullShader(code.as_ptr() as *const c_void, len, ptr::null_mut(), &mut ret)
-                };
-                (hr, ret as *mut ID3D11DeviceChild)
-            },
-            Stage::Domain => {
-                let mut ret = ptr::null_mut();
-                let hr = unsafe {
-                    dev.CreateDomainShader(code.as_ptr() as *const c_void, len, ptr::null_mut(), &mut ret)
-                };
-                (hr, ret as *mut ID3D11DeviceChild)
-            },
-            Stage::Geometry => {
-                let mut ret = ptr::null_mut();
-                let hr = unsafe {
-                    dev.CreateGeometryShader(code.as_ptr() as *const c_void, len, ptr::null_mut(), &mut ret)
+        let vs = create_shader!(self.raw, CreateVertexShader, vs_blob)?;
+        let hs = match hs_blob {
+            Some(blob) => Some(create_shader!(self.raw, CreateHullShader, blob)?),
+            None => None,
+        };
+        let ds = match ds_blob {
+            Some(blob) => Some(create_shader!(self.raw, CreateDomainShader, blob)?),
+            None => None,
+        };
+        let gs = match gs_blob {
+            Some(blob) => Some(create_shader!(self.raw, CreateGeometryShader, blob)?),
+            None => None,
+        };
+        let ps = match ps_blob {
+            Some(blob) => Some(create_shader!(self.raw, CreatePixelShader, blob)?),
+            None => None,
+        };
+
+        // Define input element descriptions
+        let input_element_descs = desc.attributes
+            .iter()
+            .map(|attrib| {
+                let buffer_desc = match desc.vertex_buffers.get(attrib.binding as usize) {
+                    Some(buffer_desc) => buffer_desc,
+                    None => {
+                        error!("Couldn't find associated vertex buffer description {:?}", attrib.binding);
+                        return Err(pso::CreationError::Other);
+                    }
                 };
-                (hr, ret as *mut ID3D11DeviceChild)
-            },
-            Stage::Pixel => {
-                let mut ret = ptr::null_mut();
-                let hr = unsafe {
-                    dev.CreatePixelShader(code.as_ptr() as *const c_void, len, ptr::null_mut(), &mut ret)
+
+                let slot_class = match buffer_desc.rate {
+                    0 => d3d11::D3D11_INPUT_PER_VERTEX_DATA,
+                    _ => d3d11::D3D11_INPUT_PER_INSTANCE_DATA,
                 };
-                (hr, ret as *mut ID3D11DeviceChild)
-            },
-            //_ => return Err(CreateShaderError::StageNotSupported(stage))
+                let format = attrib.element.format;
+
+                Ok(d3d11::D3D11_INPUT_ELEMENT_DESC {
+                    SemanticName: "TEXCOORD\0".as_ptr() as *const _, // Semantic name used by SPIRV-Cross
+                    SemanticIndex: attrib.location,
+                    Format: match conv::map_format(format) {
+                        Some(fm) => fm,
+                        None => {
+                            error!("Unable to find DXGI format for {:?}", format);
+                            return Err(pso::CreationError::Other);
+                        }
+                    },
+                    InputSlot: attrib.binding as _,
+                    AlignedByteOffset: attrib.element.offset,
+                    InputSlotClass: slot_class,
+                    InstanceDataStepRate: buffer_desc.rate as _,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let input_layout = if input_element_descs.is_empty() {
+            None
+        } else {
+            let mut layout = ptr::null_mut();
+            let hr = unsafe {
+                self.raw.CreateInputLayout(
+                    input_element_descs.as_ptr(),
+                    input_element_descs.len() as _,
+                    vs_blob.GetBufferPointer(),
+                    vs_blob.GetBufferSize(),
+                    &mut layout as *mut *mut _,
+                )
+            };
+            if !winerror::SUCCEEDED(hr) {
+                error!("error on input layout creation: {:x}", hr);
+                return Err(pso::CreationError::Other);
+            }
+            Some(unsafe { ComPtr::from_raw(layout) })
         };
 
-        if winapi::SUCCEEDED(hr) {
-            let reflection = reflect_shader(code);
-            let hash = {
-                use std::collections::hash_map::DefaultHasher;
-                use std::hash::{Hash, Hasher};
-                let mut hasher = DefaultHasher::new();
-                code.hash(&mut hasher);
-                hasher.finish()
+        // Input slots
+        let mut vertex_strides = [0; MAX_VERTEX_BUFFERS];
+        for (stride, buffer) in vertex_strides.iter_mut().zip(desc.vertex_buffers.iter()) {
+            *stride = buffer.stride;
+        }
+
+        let rasterizer_state = {
+            let rasterizer_desc = conv::map_rasterizer(&desc.rasterizer);
+            let mut state = ptr::null_mut();
+            let hr = unsafe {
+                self.raw.CreateRasterizerState(&rasterizer_desc, &mut state as *mut *mut _)
             };
-            if stage == Stage::Vertex {
-                self.vs_cache.insert(hash, code.to_owned());
+            if !winerror::SUCCEEDED(hr) {
+                error!("error on rasterizer state creation: {:x}", hr);
+                return Err(pso::CreationError::Other);
             }
-            let shader = Shader {
-                object: object,
-                reflection: reflection,
-                code_hash: hash,
+            unsafe { ComPtr::from_raw(state) }
+        };
+
+        let blend_state = {
+            let blend_desc = conv::map_blend_desc(&desc.blender);
+            let mut state = ptr::null_mut();
+            let hr = unsafe {
+                self.raw.CreateBlendState(&blend_desc, &mut state as *mut *mut _)
             };
-            Ok(self.share.handles.borrow_mut().make_shader(shader))
-        }else {
-            Err(CreateShaderError::CompilationFailed(format!("code {}", hr)))
-        }
-    }
-
-    fn create_program(&mut self, shader_set: &core::ShaderSet<R>)
-                      -> Result<h::Program<R>, core::shade::CreateProgramError> {
-        use winapi::{ID3D11VertexShader, ID3D11HullShader, ID3D11DomainShader, ID3D11GeometryShader, ID3D11PixelShader};
-        use core::shade::{ProgramInfo, Stage};
-        use mirror::populate_info;
-
-        let mut info = ProgramInfo {
-            vertex_attributes: Vec::new(),
-            globals: Vec::new(),
-            constant_buffers: Vec::new(),
-            textures: Vec::new(),
-            unordereds: Vec::new(),
-            samplers: Vec::new(),
-            outputs: Vec::new(),
-            output_depth: false,
-            knows_outputs: true,
-        };
-        let fh = &mut self.frame_handles;
-        let prog = match shader_set {
-            &core::ShaderSet::Simple(ref vs, ref ps) => {
-                let (vs, ps) = (vs.reference(fh), ps.reference(fh));
-                populate_info(&mut info, Stage::Vertex, vs.reflection);
-                populate_info(&mut info, Stage::Pixel,  ps.reflection);
-                unsafe { (*vs.object).AddRef(); (*ps.object).AddRef(); }
-                Program {
-                    vs: vs.object as *mut ID3D11VertexShader,
-                    hs: ptr::null_mut(),
-                    ds: ptr::null_mut(),
-                    gs: ptr::null_mut(),
-                    ps: ps.object as *mut ID3D11PixelShader,
-                    vs_hash: vs.code_hash,
-                }
-            },
-            &core::ShaderSet::Geometry(ref vs, ref gs, ref ps) => {
-                let (vs, gs, ps) = (vs.reference(fh), gs.reference(fh), ps.reference(fh));
-                populate_info(&mut info, Stage::Vertex,   vs.reflection);
-                populate_info(&mut info, Stage::Geometry, gs.reflection);
-                populate_info(&mut info, Stage::Pixel,    ps.reflection);
-                unsafe { (*vs.object).AddRef(); (*gs.object).AddRef(); (*ps.object).AddRef(); }
-                Program {
-                    vs: vs.object as *mut ID3D11VertexShader,
-                    hs: ptr::null_mut(),
-                    ds: ptr::null_mut(),
-                    gs: gs.object as *mut ID3D11GeometryShader,
-                    ps: ps.object as *mut ID3D11PixelShader,
-                    vs_hash: vs.code_hash,
+            if !winerror::SUCCEEDED(hr) {
+                error!("error on blend state creation: {:x}", hr);
+                return Err(pso::CreationError::Other);
+            }
+            unsafe { ComPtr::from_raw(state) }
+        };
+
+        let depth_stencil_state = match desc.depth_stencil {
+            Some(ref depth_stencil) => {
+                let ds_desc = conv::map_depth_stencil(depth_stencil);
+                let mut state = ptr::null_mut();
+                let hr = unsafe {
+                    self.raw.CreateDepthStencilState(&ds_desc, &mut state as *mut *mut _)
+                };
+                if !winerror::SUCCEEDED(hr) {
+                    error!("error on depth stencil state creation: {:x}", hr);
+                    return Err(pso::CreationError::Other);
                 }
-            },
-            &core::ShaderSet::Tessellated(ref vs, ref hs, ref ds, ref ps) => {
-                let (vs, hs, ds, ps) = (vs.reference(fh), hs.reference(fh), ds.reference(fh), ps.reference(fh));
-
-                populate_info(&mut info, Stage::Vertex, vs.reflection);
-                populate_info(&mut info, Stage::Hull,   hs.reflection);
-                populate_info(&mut info, Stage::Domain, ds.reflection);
-                populate_info(&mut info, Stage::Pixel,  ps.reflection);
-                unsafe { (*vs.object).AddRef(); (*hs.object).AddRef(); (*ds.object).AddRef(); (*ps.object).AddRef(); }
-                Program {
-                    vs: vs.object as *mut ID3D11VertexShader,
-                    hs: hs.object as *mut ID3D11HullShader,
-                    ds: ds.object as *mut ID3D11DomainShader,
-                    gs: ptr::null_mut(),
-                    ps: ps.object as *mut ID3D11PixelShader,
-                    vs_hash: vs.code_hash,
-                }
-            }
-        };
-        Ok(self.share.handles.borrow_mut().make_program(prog, info))
-    }
-
-    fn create_pipeline_state_raw(&mut self, program: &h::Program<R>, desc: &core::pso::Descriptor)
-                                 -> Result<h::RawPipelineState<R>, core::pso::CreationError> {
-        use winapi::d3dcommon::*;
-        use core::Primitive::*;
-        use data::map_format;
-        use state;
-
-        let mut layouts = Vec::new();
-        let mut charbuf = [0; 256];
-        let mut charpos = 0;
-        for (attrib, at_desc) in program.get_info().vertex_attributes.iter().zip(desc.attributes.iter()) {
-            use winapi::UINT;
-            let (bdesc, elem) = match at_desc {
-                &Some((buf_id, ref el)) => match desc.vertex_buffers[buf_id as usize] {
-                    Some(ref bd) => (bd, el),
-                    None => return Err(core::pso::CreationError),
-                },
-                &None => continue,
-            };
-            if elem.offset & 1 != 0 {
-                error!("Vertex attribute {} must be aligned to 2 bytes, has offset {}",
-                    attrib.name, elem.offset);
-                return Err(core::pso::CreationError);
-            }
-            layouts.push(winapi::D3D11_INPUT_ELEMENT_DESC {
-                SemanticName: &charbuf[charpos],
-                SemanticIndex: 0,
-                Format: match map_format(elem.format, false) {
-                    Some(fm) => fm,
-                    None => {
-                        error!("Unable to find DXGI format for {:?}", elem.format);
-                        return Err(core::pso::CreationError);
-                    }
-                },
-                InputSlot: attrib.slot as UINT,
-                AlignedByteOffset: elem.offset as UINT,
-                InputSlotClass: if bdesc.rate == 0 {
-                    winapi::D3D11_INPUT_PER_VERTEX_DATA
-                }else {
-                    winapi::D3D11_INPUT_PER_INSTANCE_DATA
-                },
-                InstanceDataStepRate: bdesc.rate as UINT,
-            });
-            for (out, inp) in charbuf[charpos..].iter_mut().zip(attrib.name.as_bytes().iter()) {
-                *out = *inp as i8;
+                Some(unsafe { ComPtr::from_raw(state) })
             }
-            charpos += attrib.name.as_bytes().len() + 1;
+            None => None,
+        };
+
+        Ok(n::GraphicsPipeline {
+            vs,
+            hs,
+            ds,
+            gs,
+            ps,
+            input_layout,
+            rasterizer_state,
+            blend_state,
+            depth_stencil_state,
+            topology: conv::map_topology(desc.input_assembler.primitive),
+            vertex_strides,
+            baked_states: desc.baked_states.clone(),
+            viewport_count: desc.viewport_count,
+        })
+    }
+
+    fn create_compute_pipeline<'a>(
+        &self,
+        desc: &pso::ComputePipelineDesc<'a, B>,
+    ) -> Result<n::ComputePipeline, pso::CreationError> {
+        let cs_blob = shader::compile_entry_point(pso::Stage::Compute, &desc.shader, desc.layout)
+            .map_err(pso::CreationError::Shader)?;
+        let cs = create_shader!(self.raw, CreateComputeShader, cs_blob)?;
+
+        Ok(n::ComputePipeline { cs })
+    }
+
+    fn create_ray_tracing_pipeline<'a>(
+        &self, _desc: &pso::RayTracingPipelineDesc<'a, B>,
+    ) -> Result<n::RayTracingPipeline, pso::CreationError> {
+        unimplemented!()
+    }
+
+    fn destroy_ray_tracing_pipeline(&self, _pipeline: n::RayTracingPipeline) {
+        unimplemented!()
+    }
+
+    fn get_ray_tracing_shader_group_handles(
+        &self, _pipeline: &n::RayTracingPipeline, _groups: Range<u32>,
+    ) -> Vec<u8> {
+        unimplemented!()
+    }
+
+    fn get_acceleration_structure_prebuild_info<'a>(
+        &self, _desc: &accel_struct::BuildDesc<'a, B>,
+    ) -> accel_struct::PrebuildInfo {
+        unimplemented!()
+    }
+
+    fn create_acceleration_structure<'a>(
+        &self, _desc: &accel_struct::Desc<'a, B>,
+    ) -> Result<n::AccelerationStructure, accel_struct::CreationError> {
+        unimplemented!()
+    }
+
+    fn get_acceleration_structure_address(&self, _accel: &n::AccelerationStructure) -> u64 {
+        unimplemented!()
+    }
+
+    fn destroy_acceleration_structure(&self, _accel: n::AccelerationStructure) {
+        unimplemented!()
+    }
+
+    fn create_framebuffer<I>(
+        &self,
+        _renderpass: &n::RenderPass,
+        attachments: I,
+        extent: image::Extent,
+    ) -> Result<n::Framebuffer, d::FramebufferError>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<n::ImageView>,
+    {
+        Ok(n::Framebuffer {
+            attachments: attachments.into_iter().map(|att| att.borrow().clone()).collect(),
+            layers: extent.depth as _,
+        })
+    }
+
+    fn create_shader_module(&self, raw_data: &[u8]) -> Result<n::ShaderModule, d::ShaderError> {
+        Ok(n::ShaderModule::Spirv(raw_data.into()))
+    }
+
+    fn create_buffer(
+        &self,
+        size: u64,
+        usage: buffer::Usage,
+    ) -> Result<UnboundBuffer, buffer::CreationError> {
+        // Constant buffers can't be bound as any other buffer type.
+        if usage.contains(buffer::Usage::UNIFORM) &&
+            usage.intersects(
+                buffer::Usage::UNIFORM_TEXEL | buffer::Usage::STORAGE_TEXEL |
+                buffer::Usage::STORAGE | buffer::Usage::INDEX | buffer::Usage::VERTEX
+            )
+        {
+            return Err(buffer::CreationError::Usage(usage));
         }
 
-        let prog = *self.frame_handles.ref_program(program);
-        let vs_bin = match self.vs_cache.get(&prog.vs_hash) {
-            Some(ref code) => &code[..],
-            None => {
-                error!("VS hash {} is not found in the device cache", prog.vs_hash);
-                return Err(core::pso::CreationError);
-            }
+        // Constant buffers and raw views require a multiple of 16 bytes.
+        let requirements = Requirements {
+            size: (size + 15) & !15,
+            alignment: 16,
+            type_mask: BUFFER_TYPE_MASK,
         };
 
-        let dev = &mut self.device;
-        let mut vertex_layout = ptr::null_mut();
-        let hr = unsafe {
-            dev.CreateInputLayout(
-                layouts.as_ptr(), layouts.len() as winapi::UINT,
-                vs_bin.as_ptr() as *const c_void, vs_bin.len() as winapi::SIZE_T,
-                &mut vertex_layout)
-        };
-        if !winapi::SUCCEEDED(hr) {
-            error!("Failed to create input layout from {:#?}, error {:x}", layouts, hr);
-            return Err(core::pso::CreationError);
-        }
-        let dummy_dsi = core::pso::DepthStencilInfo { depth: None, front: None, back: None };
-        //TODO: cache rasterizer, depth-stencil, and blend states
-        let caps = &self.share.capabilities;
-
-        let pso = Pipeline {
-            topology: match desc.primitive {
-                PointList       => D3D11_PRIMITIVE_TOPOLOGY_POINTLIST,
-                LineList        => D3D11_PRIMITIVE_TOPOLOGY_LINELIST,
-                LineStrip       => D3D11_PRIMITIVE_TOPOLOGY_LINESTRIP,
-                TriangleList    => D3D11_PRIMITIVE_TOPOLOGY_TRIANGLELIST,
-                TriangleStrip   => D3D11_PRIMITIVE_TOPOLOGY_TRIANGLESTRIP,
-                LineListAdjacency        => D3D11_PRIMITIVE_TOPOLOGY_LINELIST_ADJ,
-                LineStripAdjacency       => D3D11_PRIMITIVE_TOPOLOGY_LINESTRIP_ADJ,
-                TriangleListAdjacency    => D3D11_PRIMITIVE_TOPOLOGY_TRIANGLELIST_ADJ,
-                TriangleStripAdjacency   => D3D11_PRIMITIVE_TOPOLOGY_TRIANGLESTRIP_ADJ,
-                PatchList(num)  => {
-                    if num == 0 || num > caps.max_patch_size {
-                        return Err(core::pso::CreationError)
-                    }
-                    D3D_PRIMITIVE_TOPOLOGY(D3D11_PRIMITIVE_TOPOLOGY_1_CONTROL_POINT_PATCHLIST.0 + (num as u32) - 1)
-                },
-            },
-            layout: vertex_layout,
-            vertex_buffers: desc.vertex_buffers,
-            attributes: desc.attributes,
-            program: prog,
-            rasterizer: state::make_rasterizer(dev, &desc.rasterizer, desc.scissor),
-            depth_stencil: state::make_depth_stencil(dev, match desc.depth_stencil {
-                Some((_, ref dsi)) => dsi,
-                None => &dummy_dsi,
-            }),
-            blend: state::make_blend(dev, &desc.color_targets),
-        };
-        Ok(self.share.handles.borrow_mut().make_pso(pso, program))
-    }
-
-    fn create_texture_raw(&mut self, desc: texture::Info, hint: Option<core::format::ChannelType>,
-                          data_opt: Option<&[&[u8]]>) -> Result<h::RawTexture<R>, texture::CreationError> {
-        use core::texture::{AaMode, CreationError, Kind};
-        use data::{map_bind, map_usage, map_surface, map_format};
-
-        let (usage, cpu_access) = map_usage(desc.usage, desc.bind);
-        let tparam = TextureParam {
-            levels: desc.levels as winapi::UINT,
-            format: match hint {
-                Some(channel) if self.use_texture_format_hint && !desc.bind.contains(memory::DEPTH_STENCIL) => {
-                    match map_format(core::format::Format(desc.format, channel), true) {
-                        Some(f) => f,
-                        None => return Err(CreationError::Format(desc.format, Some(channel)))
-                    }
-                },
-                _ => match map_surface(desc.format) {
-                    Some(f) => f,
-                    None => return Err(CreationError::Format(desc.format, None))
-                },
-            },
-            bytes_per_texel: (desc.format.get_total_bits() >> 3) as winapi::UINT,
-            bind: map_bind(desc.bind),
-            usage: usage,
-            cpu_access: cpu_access,
-        };
-
-        self.sub_data_array.clear();
-        if let Some(data) = data_opt {
-            for sub in data.iter() {
-                self.sub_data_array.push(winapi::D3D11_SUBRESOURCE_DATA {
-                    pSysMem: sub.as_ptr() as *const c_void,
-                    SysMemPitch: 0,
-                    SysMemSlicePitch: 0,
-                });
-            }
-        };
-        let misc = if usage != winapi::D3D11_USAGE_IMMUTABLE &&
-            desc.bind.contains(memory::RENDER_TARGET | memory::SHADER_RESOURCE) &&
-            desc.levels > 1 && data_opt.is_none() {
-            winapi::D3D11_RESOURCE_MISC_GENERATE_MIPS
-        }else {
-            winapi::D3D11_RESOURCE_MISC_FLAG(0)
-        };
-
-        let texture_result = match desc.kind {
-            Kind::D1(w) =>
-                self.create_texture_1d(w, 1, tparam, misc),
-            Kind::D1Array(w, d) =>
-                self.create_texture_1d(w, d, tparam, misc),
-            Kind::D2(w, h, aa) =>
-                self.create_texture_2d([w,h], 1, aa, tparam, misc),
-            Kind::D2Array(w, h, d, aa) =>
-                self.create_texture_2d([w,h], d, aa, tparam, misc),
-            Kind::D3(w, h, d) =>
-                self.create_texture_3d([w,h,d], tparam, misc),
-            Kind::Cube(w) =>
-                self.create_texture_2d([w,w], 6*1, AaMode::Single, tparam, misc | winapi::D3D11_RESOURCE_MISC_TEXTURECUBE),
-            Kind::CubeArray(w, d) =>
-                self.create_texture_2d([w,w], 6*d, AaMode::Single, tparam, misc | winapi::D3D11_RESOURCE_MISC_TEXTURECUBE),
-        };
-
-        match texture_result {
-            Ok(native) => {
-                let tex = Texture(native);
-                Ok(self.share.handles.borrow_mut().make_texture(tex, desc))
-            },
-            Err(_) => Err(CreationError::Kind),
-        }
-    }
-
-    fn view_buffer_as_shader_resource_raw(&mut self, _hbuf: &h::RawBuffer<R>, _: core::format::Format)
-                                      -> Result<h::RawShaderResourceView<R>, d::ResourceViewError> {
-        Err(d::ResourceViewError::Unsupported) //TODO
-    }
-
-    fn view_buffer_as_unordered_access_raw(&mut self, _hbuf: &h::RawBuffer<R>)
-                                       -> Result<h::RawUnorderedAccessView<R>, d::ResourceViewError> {
-        Err(d::ResourceViewError::Unsupported) //TODO
-    }
-
-    fn view_texture_as_shader_resource_raw(&mut self, htex: &h::RawTexture<R>, desc: texture::ResourceDesc)
-                                       -> Result<h::RawShaderResourceView<R>, d::ResourceViewError> {
-        use winapi::UINT;
-        use core::texture::{AaMode, Kind};
-        use data::map_format;
-        //TODO: support desc.layer parsing
-
-        let (dim, layers, has_levels) = match htex.get_info().kind {
-            Kind::D1(_) =>
-                (winapi::D3D11_SRV_DIMENSION_TEXTURE1D, 1, true),
-            Kind::D1Array(_, d) =>
-                (winapi::D3D11_SRV_DIMENSION_TEXTURE1DARRAY, d, true),
-            Kind::D2(_, _, AaMode::Single) =>
-                (winapi::D3D11_SRV_DIMENSION_TEXTURE2D, 1, true),
-            Kind::D2(_, _, _) =>
-                (winapi::D3D11_SRV_DIMENSION_TEXTURE2DMS, 1, false),
-            Kind::D2Array(_, _, d, AaMode::Single) =>
-                (winapi::D3D11_SRV_DIMENSION_TEXTURE2DARRAY, d, true),
-            Kind::D2Array(_, _, d, _) =>
-                (winapi::D3D11_SRV_DIMENSION_TEXTURE2DMSARRAY, d, false),
-            Kind::D3(_, _, _) =>
-                (winapi::D3D11_SRV_DIMENSION_TEXTURE3D, 1, true),
-            Kind::Cube(_) =>
-                (winapi::D3D11_SRV_DIMENSION_TEXTURECUBE, 1, true),
-            Kind::CubeArray(_, d) =>
-                (winapi::D3D11_SRV_DIMENSION_TEXTURECUBEARRAY, d, true),
-        };
-
-        let format = core::format::Format(htex.get_info().format, desc.channel);
-        let native_desc = winapi::D3D11_SHADER_RESOURCE_VIEW_DESC {
-            Format: match map_format(format, false) {
-                Some(fm) => fm,
-                None => return Err(d::ResourceViewError::Channel(desc.channel)),
-            },
-            ViewDimension: dim,
-            u: if has_levels {
-                assert!(desc.max >= desc.min);
-                [desc.min as UINT, (desc.max + 1 - desc.min) as UINT, 0, layers as UINT]
-            }else {
-                [0, layers as UINT, 0, 0]
-            },
+        Ok(UnboundBuffer {
+            size,
+            usage,
+            requirements,
+        })
+    }
+
+    fn get_buffer_requirements(&self, buffer: &UnboundBuffer) -> Requirements {
+        buffer.requirements
+    }
+
+    fn bind_buffer_memory(
+        &self,
+        memory: &n::Memory,
+        offset: u64,
+        buffer: UnboundBuffer,
+    ) -> Result<n::Buffer, d::BindError> {
+        let size = buffer.requirements.size;
+        if offset + size > memory.size {
+            return Err(d::BindError::OutOfBounds);
+        }
+
+        let constant = buffer.usage.contains(buffer::Usage::UNIFORM);
+        let desc = d3d11::D3D11_BUFFER_DESC {
+            ByteWidth: size as _,
+            Usage: d3d11::D3D11_USAGE_DEFAULT,
+            BindFlags: conv::map_buffer_bind_flags(buffer.usage),
+            CPUAccessFlags: 0,
+            MiscFlags: conv::map_buffer_misc_flags(buffer.usage),
+            StructureByteStride: 0,
         };
 
-        let mut raw_view = ptr::null_mut();
-        let raw_tex = self.frame_handles.ref_texture(htex).as_resource();
+        // Buffers in host visible memory start off with the current host copy.
+        let host_ptr = memory.host_ptr(offset);
+        let initial_data = d3d11::D3D11_SUBRESOURCE_DATA {
+            pSysMem: host_ptr as *const _,
+            SysMemPitch: 0,
+            SysMemSlicePitch: 0,
+        };
+
+        let mut raw = ptr::null_mut();
         let hr = unsafe {
-            (*self.device).CreateShaderResourceView(raw_tex, &native_desc, &mut raw_view)
+            self.raw.CreateBuffer(
+                &desc,
+                if host_ptr.is_null() { ptr::null() } else { &initial_data },
+                &mut raw as *mut *mut _,
+            )
         };
-        if !winapi::SUCCEEDED(hr) {
-            error!("Failed to create SRV from {:#?}, error {:x}", native_desc, hr);
-            return Err(d::ResourceViewError::Unsupported);
+        if !winerror::SUCCEEDED(hr) {
+            error!("error on buffer creation: {:x}", hr);
+            return Err(d::BindError::WrongMemory);
+        }
+        let raw = unsafe { ComPtr::<d3d11::ID3D11Buffer>::from_raw(raw) };
+
+        let (srv, uav) = if buffer.usage.contains(buffer::Usage::STORAGE) {
+            self.view_buffer_as_raw(&raw, size)
+        } else {
+            (None, None)
+        };
+
+        if !host_ptr.is_null() {
+            memory.buffers.lock().unwrap().push(n::BoundBuffer {
+                range: offset .. offset + size,
+                raw: raw.clone(),
+                constant,
+            });
         }
-        Ok(self.share.handles.borrow_mut().make_texture_srv(native::Srv(raw_view), htex))
-    }
 
-    fn view_texture_as_unordered_access_raw(&mut self, _htex: &h::RawTexture<R>)
-                                        -> Result<h::RawUnorderedAccessView<R>, d::ResourceViewError> {
-        Err(d::ResourceViewError::Unsupported) //TODO
+        Ok(n::Buffer {
+            raw,
+            srv,
+            uav,
+            size: buffer.size,
+            usage: buffer.usage,
+            constant,
+            host_ptr,
+        })
     }
 
-    fn view_texture_as_render_target_raw(&mut self, htex: &h::RawTexture<R>, desc: texture::RenderDesc)
-                                         -> Result<h::RawRenderTargetView<R>, d::TargetViewError>
-    {
-        use winapi::UINT;
-        use core::texture::{AaMode, Kind};
-        use data::map_format;
-
-        let level = desc.level as UINT;
-        let (dim, extra) = match (htex.get_info().kind, desc.layer) {
-            (Kind::D1(..), None) =>
-                (winapi::D3D11_RTV_DIMENSION_TEXTURE1D, [level, 0, 0]),
-            (Kind::D1Array(_, nlayers), Some(lid)) if lid < nlayers =>
-                (winapi::D3D11_RTV_DIMENSION_TEXTURE1DARRAY, [level, lid as UINT, 1+lid as UINT]),
-            (Kind::D1Array(_, nlayers), None) =>
-                (winapi::D3D11_RTV_DIMENSION_TEXTURE1DARRAY, [level, 0, nlayers as UINT]),
-            (Kind::D2(_, _, AaMode::Single), None) =>
-                (winapi::D3D11_RTV_DIMENSION_TEXTURE2D, [level, 0, 0]),
-            (Kind::D2(_, _, _), None) if level == 0 =>
-                (winapi::D3D11_RTV_DIMENSION_TEXTURE2DMS, [0, 0, 0]),
-            (Kind::D2Array(_, _, nlayers, AaMode::Single), None) =>
-                (winapi::D3D11_RTV_DIMENSION_TEXTURE2DARRAY, [level, 0, nlayers as UINT]),
-            (Kind::D2Array(_, _, nlayers, AaMode::Single), Some(lid)) if lid < nlayers =>
-                (winapi::D3D11_RTV_DIMENSION_TEXTURE2DARRAY, [level, lid as UINT, 1+lid as UINT]),
-            (Kind::D2Array(_, _, nlayers, _), None) if level == 0 =>
-                (winapi::D3D11_RTV_DIMENSION_TEXTURE2DMSARRAY, [0, nlayers as UINT, 0]),
-            (Kind::D2Array(_, _, nlayers, _), Some(lid)) if level == 0 && lid < nlayers =>
-                (winapi::D3D11_RTV_DIMENSION_TEXTURE2DMSARRAY, [lid as UINT, 1+lid as UINT, 0]),
-            (Kind::D3(_, _, depth), None) =>
-                (winapi::D3D11_RTV_DIMENSION_TEXTURE3D, [level, 0, depth as UINT]),
-            (Kind::D3(_, _, depth), Some(lid)) if lid < depth =>
-                (winapi::D3D11_RTV_DIMENSION_TEXTURE3D, [level, lid as UINT, 1+lid as UINT]),
-            (Kind::Cube(..), None) =>
-                (winapi::D3D11_RTV_DIMENSION_TEXTURE2DARRAY, [level, 0, 6]),
-            (Kind::Cube(..), Some(lid)) if lid < 6 =>
-                (winapi::D3D11_RTV_DIMENSION_TEXTURE2DARRAY, [level, lid as UINT, 1+lid as UINT]),
-            (Kind::CubeArray(_, nlayers), None) =>
-                (winapi::D3D11_RTV_DIMENSION_TEXTURE2DARRAY, [level, 0, 6 * nlayers as UINT]),
-            (Kind::CubeArray(_, nlayers), Some(lid)) if lid < nlayers =>
-                (winapi::D3D11_RTV_DIMENSION_TEXTURE2DARRAY, [level, 6 * lid as UINT, 6 * (1+lid) as UINT]),
-            (_, None) => return Err(d::TargetViewError::Level(desc.level)),
-            (_, Some(lid)) => return Err(d::TargetViewError::Layer(texture::LayerError::OutOfBounds(lid, 0))), //TODO
-        };
-        let format = core::format::Format(htex.get_info().format, desc.channel);
-        let native_desc = winapi::D3D11_RENDER_TARGET_VIEW_DESC {
-            Format: match map_format(format, true) {
-                Some(fm) => fm,
-                None => return Err(d::TargetViewError::Channel(desc.channel)),
-            },
-            ViewDimension: dim,
-            u: extra,
+    fn create_buffer_view<R: RangeArg<u64>>(
+        &self,
+        buffer: &n::Buffer,
+        format: Option<format::Format>,
+        range: R,
+    ) -> Result<n::BufferView, buffer::ViewError> {
+        let format = match format {
+            Some(format) => format,
+            None => return Err(buffer::ViewError::Unsupported),
         };
-        let mut raw_view = ptr::null_mut();
-        let raw_tex = self.frame_handles.ref_texture(htex).as_resource();
-        let hr = unsafe {
-            (*self.device).CreateRenderTargetView(raw_tex, &native_desc, &mut raw_view)
+        let dxgi_format = conv::map_format(format).ok_or(buffer::ViewError::Unsupported)?;
+        let element_size = (format.base_format().0.desc().bits / 8) as u64;
+        let start = *range.start().unwrap_or(&0);
+        let end = *range.end().unwrap_or(&buffer.size);
+        let first_element = (start / element_size) as UINT;
+        let num_elements = ((end - start) / element_size) as UINT;
+
+        let srv = if buffer.usage.contains(buffer::Usage::UNIFORM_TEXEL) {
+            let mut desc = d3d11::D3D11_SHADER_RESOURCE_VIEW_DESC {
+                Format: dxgi_format,
+                ViewDimension: d3dcommon::D3D11_SRV_DIMENSION_BUFFEREX,
+                u: unsafe { mem::zeroed() },
+            };
+            *unsafe { desc.u.BufferEx_mut() } = d3d11::D3D11_BUFFEREX_SRV {
+                FirstElement: first_element,
+                NumElements: num_elements,
+                Flags: 0,
+            };
+            let mut view = ptr::null_mut();
+            let hr = unsafe {
+                self.raw.CreateShaderResourceView(buffer.raw.as_raw() as *mut _, &desc, &mut view as *mut *mut _)
+            };
+            if !winerror::SUCCEEDED(hr) {
+                error!("error on buffer SRV creation: {:x}", hr);
+                return Err(buffer::ViewError::Unsupported);
+            }
+            Some(unsafe { ComPtr::from_raw(view) })
+        } else {
+            None
         };
-        if !winapi::SUCCEEDED(hr) {
-            error!("Failed to create RTV from {:#?}, error {:x}", native_desc, hr);
-            return Err(d::TargetViewError::Unsupported);
-        }
-        let size = htex.get_info().kind.level_dimensions(desc.level);
-        Ok(self.share.handles.borrow_mut().make_rtv(native::Rtv(raw_view), htex, size))
+
+        let uav = if buffer.usage.contains(buffer::Usage::STORAGE_TEXEL) {
+            let mut desc = d3d11::D3D11_UNORDERED_ACCESS_VIEW_DESC {
+                Format: dxgi_format,
+                ViewDimension: d3d11::D3D11_UAV_DIMENSION_BUFFER,
+                u: unsafe { mem::zeroed() },
+            };
+            *unsafe { desc.u.Buffer_mut() } = d3d11::D3D11_BUFFER_UAV {
+                FirstElement: first_element,
+                NumElements: num_elements,
+                Flags: 0,
+            };
+            let mut view = ptr::null_mut();
+            let hr = unsafe {
+                self.raw.CreateUnorderedAccessView(buffer.raw.as_raw() as *mut _, &desc, &mut view as *mut *mut _)
+            };
+            if !winerror::SUCCEEDED(hr) {
+                error!("error on buffer UAV creation: {:x}", hr);
+                return Err(buffer::ViewError::Unsupported);
+            }
+            Some(unsafe { ComPtr::from_raw(view) })
+        } else {
+            None
+        };
+
+        Ok(n::BufferView { srv, uav })
     }
 
-    fn view_texture_as_depth_stencil_raw(&mut self, htex: &h::RawTexture<R>, desc: texture::DepthStencilDesc)
-                                         -> Result<h::RawDepthStencilView<R>, d::TargetViewError>
-    {
-        use winapi::UINT;
-        use core::texture::{AaMode, Kind};
-        use data::{map_format, map_dsv_flags};
-
-        let level = desc.level as UINT;
-        let (dim, extra) = match (htex.get_info().kind, desc.layer) {
-            (Kind::D1(..), None) =>
-                (winapi::D3D11_DSV_DIMENSION_TEXTURE1D, [level, 0, 0]),
-            (Kind::D1Array(_, nlayers), Some(lid)) if lid < nlayers =>
-                (winapi::D3D11_DSV_DIMENSION_TEXTURE1DARRAY, [level, lid as UINT, 1+lid as UINT]),
-            (Kind::D1Array(_, nlayers), None) =>
-                (winapi::D3D11_DSV_DIMENSION_TEXTURE1DARRAY, [level, 0, nlayers as UINT]),
-            (Kind::D2(_, _, AaMode::Single), None) =>
-                (winapi::D3D11_DSV_DIMENSION_TEXTURE2D, [level, 0, 0]),
-            (Kind::D2(_, _, _), None) if level == 0 =>
-                (winapi::D3D11_DSV_DIMENSION_TEXTURE2DMS, [0, 0, 0]),
-            (Kind::D2Array(_, _, nlayers, AaMode::Single), None) =>
-                (winapi::D3D11_DSV_DIMENSION_TEXTURE2DARRAY, [level, 0, nlayers as UINT]),
-            (Kind::D2Array(_, _, nlayers, AaMode::Single), Some(lid)) if lid < nlayers =>
-                (winapi::D3D11_DSV_DIMENSION_TEXTURE2DARRAY, [level, lid as UINT, 1+lid as UINT]),
-            (Kind::D2Array(_, _, nlayers, _), None) if level == 0 =>
-                (winapi::D3D11_DSV_DIMENSION_TEXTURE2DMSARRAY, [0, nlayers as UINT, 0]),
-            (Kind::D2Array(_, _, nlayers, _), Some(lid)) if level == 0 && lid < nlayers =>
-                (winapi::D3D11_DSV_DIMENSION_TEXTURE2DMSARRAY, [lid as UINT, 1+lid as UINT, 0]),
-            (Kind::D3(..), _) => return Err(d::TargetViewError::Unsupported),
-            (Kind::Cube(..), None) =>
-                (winapi::D3D11_DSV_DIMENSION_TEXTURE2DARRAY, [level, 0, 6]),
-            (Kind::Cube(..), Some(lid)) if lid < 6 =>
-                (winapi::D3D11_DSV_DIMENSION_TEXTURE2DARRAY, [level, lid as UINT, 1+lid as UINT]),
-            (Kind::CubeArray(_, nlayers), None) =>
-                (winapi::D3D11_DSV_DIMENSION_TEXTURE2DARRAY, [level, 0, 6 * nlayers as UINT]),
-            (Kind::CubeArray(_, nlayers), Some(lid)) if lid < nlayers =>
-                (winapi::D3D11_DSV_DIMENSION_TEXTURE2DARRAY, [level, 6 * lid as UINT, 6 * (1+lid) as UINT]),
-            (_, None) => return Err(d::TargetViewError::Level(desc.level)),
-            (_, Some(lid)) => return Err(d::TargetViewError::Layer(texture::LayerError::OutOfBounds(lid, 0))), //TODO
-        };
-
-        let channel = core::format::ChannelType::Uint; //doesn't matter
-        let format = core::format::Format(htex.get_info().format, channel);
-        let native_desc = winapi::D3D11_DEPTH_STENCIL_VIEW_DESC {
-            Format: match map_format(format, true) {
-                Some(fm) => fm,
-                None => return Err(d::TargetViewError::Channel(channel)),
+    fn create_image(
+        &self,
+        kind: image::Kind,
+        mip_levels: image::Level,
+        format: format::Format,
+        tiling: image::Tiling,
+        usage: image::Usage,
+        flags: image::StorageFlags,
+    ) -> Result<UnboundImage, image::CreationError> {
+        if tiling != image::Tiling::Optimal {
+            error!("Linear images are not supported");
+            return Err(image::CreationError::Usage(usage));
+        }
+        if conv::map_format(format).is_none() {
+            return Err(image::CreationError::Format(format));
+        }
+
+        // The actual layout is opaque, the size is only used to sub-allocate device local memory.
+        let desc = format.base_format().0.desc();
+        let (block_width, block_height) = (desc.dim.0 as u32, desc.dim.1 as u32);
+        let size = (0 .. mip_levels)
+            .map(|level| {
+                let extent = kind.level_extent(level);
+                let blocks_x = (extent.width + block_width - 1) / block_width;
+                let blocks_y = (extent.height + block_height - 1) / block_height;
+                blocks_x as u64 * blocks_y as u64 * extent.depth as u64 * (desc.bits / 8) as u64
+            })
+            .sum::<u64>() * kind.num_layers() as u64 * kind.num_samples() as u64;
+
+        Ok(UnboundImage {
+            kind,
+            mip_levels,
+            format,
+            usage,
+            flags,
+            requirements: Requirements {
+                size,
+                alignment: 256,
+                type_mask: IMAGE_TYPE_MASK,
             },
-            ViewDimension: dim,
-            Flags: map_dsv_flags(desc.flags).0,
-            u: extra,
+        })
+    }
+
+    fn get_image_requirements(&self, image: &UnboundImage) -> Requirements {
+        image.requirements
+    }
+
+    fn bind_image_memory(
+        &self,
+        memory: &n::Memory,
+        offset: u64,
+        image: UnboundImage,
+    ) -> Result<n::Image, d::BindError> {
+        if memory.host.is_some() {
+            return Err(d::BindError::WrongMemory);
+        }
+        if offset + image.requirements.size > memory.size {
+            return Err(d::BindError::OutOfBounds);
+        }
+
+        let dxgi_format = conv::map_format(image.format).unwrap();
+        // Sampled depth images need a typeless resource to create both, depth stencil and shader resource views.
+        let resource_format = if image.format.is_depth() &&
+            image.usage.intersects(image::Usage::SAMPLED | image::Usage::INPUT_ATTACHMENT)
+        {
+            conv::map_format_typeless(image.format.base_format().0).unwrap_or(dxgi_format)
+        } else {
+            dxgi_format
         };
+        let bind_flags = conv::map_image_bind_flags(image.usage);
+        let mut misc_flags = 0;
+        if image.flags.contains(image::StorageFlags::CUBE_VIEW) {
+            misc_flags |= d3d11::D3D11_RESOURCE_MISC_TEXTURECUBE;
+        }
 
-        let mut raw_view = ptr::null_mut();
-        let raw_tex = self.frame_handles.ref_texture(htex).as_resource();
-        let hr = unsafe {
-            (*self.device).CreateDepthStencilView(raw_tex, &native_desc, &mut raw_view)
+        let mut raw: *mut d3d11::ID3D11Resource = ptr::null_mut();
+        let hr = match image.kind {
+            image::Kind::D1(width, layers) => {
+                let desc = d3d11::D3D11_TEXTURE1D_DESC {
+                    Width: width,
+                    MipLevels: image.mip_levels as _,
+                    ArraySize: layers as _,
+                    Format: resource_format,
+                    Usage: d3d11::D3D11_USAGE_DEFAULT,
+                    BindFlags: bind_flags,
+                    CPUAccessFlags: 0,
+                    MiscFlags: misc_flags,
+                };
+                unsafe {
+                    self.raw.CreateTexture1D(&desc, ptr::null(), &mut raw as *mut *mut _ as *mut *mut _)
+                }
+            }
+            image::Kind::D2(width, height, layers, samples) => {
+                let desc = d3d11::D3D11_TEXTURE2D_DESC {
+                    Width: width,
+                    Height: height,
+                    MipLevels: image.mip_levels as _,
+                    ArraySize: layers as _,
+                    Format: resource_format,
+                    SampleDesc: dxgitype::DXGI_SAMPLE_DESC {
+                        Count: samples as _,
+                        Quality: 0,
+                    },
+                    Usage: d3d11::D3D11_USAGE_DEFAULT,
+                    BindFlags: bind_flags,
+                    CPUAccessFlags: 0,
+                    MiscFlags: misc_flags,
+                };
+                unsafe {
+                    self.raw.CreateTexture2D(&desc, ptr::null(), &mut raw as *mut *mut _ as *mut *mut _)
+                }
+            }
+            image::Kind::D3(width, height, depth) => {
+                let desc = d3d11::D3D11_TEXTURE3D_DESC {
+                    Width: width,
+                    Height: height,
+                    Depth: depth,
+                    MipLevels: image.mip_levels as _,
+                    Format: resource_format,
+                    Usage: d3d11::D3D11_USAGE_DEFAULT,
+                    BindFlags: bind_flags,
+                    CPUAccessFlags: 0,
+                    MiscFlags: misc_flags,
+                };
+                unsafe {
+                    self.raw.CreateTexture3D(&desc, ptr::null(), &mut raw as *mut *mut _ as *mut *mut _)
+                }
+            }
         };
-        if !winapi::SUCCEEDED(hr) {
-            error!("Failed to create DSV from {:#?}, error {:x}", native_desc, hr);
-            return Err(d::TargetViewError::Unsupported);
+
+        if !winerror::SUCCEEDED(hr) {
+            error!("error on texture creation: {:x}", hr);
+            return Err(d::BindError::WrongMemory);
         }
-        let dim = htex.get_info().kind.level_dimensions(desc.level);
-        Ok(self.share.handles.borrow_mut().make_dsv(native::Dsv(raw_view), htex, dim))
+
+        Ok(n::Image {
+            raw: unsafe { ComPtr::from_raw(raw) },
+            kind: image.kind,
+            format: image.format,
+            dxgi_format,
+            usage: image.usage,
+            mip_levels: image.mip_levels,
+        })
     }
 
-    fn create_sampler(&mut self, info: texture::SamplerInfo) -> h::Sampler<R> {
-        use core::texture::FilterMethod;
-        use data::{FilterOp, map_function, map_filter, map_wrap};
+    fn create_image_view(
+        &self,
+        image: &n::Image,
+        view_kind: image::ViewKind,
+        format: format::Format,
+        _swizzle: format::Swizzle,
+        range: image::SubresourceRange,
+    ) -> Result<n::ImageView, image::ViewError> {
+        let surface = format.base_format().0;
+        let info = |format| ViewInfo {
+            resource: image.raw.as_raw(),
+            kind: image.kind,
+            view_kind,
+            format,
+            range: range.clone(),
+        };
+
+        let srv = if image.usage.intersects(image::Usage::SAMPLED | image::Usage::INPUT_ATTACHMENT) {
+            let format = if format.is_depth() {
+                conv::map_format_depth_srv(surface)
+            } else {
+                conv::map_format(format)
+            };
+            let format = format.ok_or(image::ViewError::BadFormat)?;
+            Some(Self::view_image_as_shader_resource(&self.raw, &info(format))?)
+        } else {
+            None
+        };
 
-        let op = if info.comparison.is_some() {FilterOp::Comparison} else {FilterOp::Product};
-        let native_desc = winapi::D3D11_SAMPLER_DESC {
-            Filter: map_filter(info.filter, op),
-            AddressU: map_wrap(info.wrap_mode.0),
-            AddressV: map_wrap(info.wrap_mode.1),
-            AddressW: map_wrap(info.wrap_mode.2),
+        let rtv = if image.usage.contains(image::Usage::COLOR_ATTACHMENT) {
+            let format = conv::map_format(format).ok_or(image::ViewError::BadFormat)?;
+            Some(Self::view_image_as_render_target(&self.raw, &info(format))?)
+        } else {
+            None
+        };
+
+        let dsv = if image.usage.contains(image::Usage::DEPTH_STENCIL_ATTACHMENT) {
+            let format = conv::map_format_dsv(surface).ok_or(image::ViewError::BadFormat)?;
+            Some(Self::view_image_as_depth_stencil(&self.raw, &info(format))?)
+        } else {
+            None
+        };
+
+        let uav = if image.usage.contains(image::Usage::STORAGE) {
+            let format = conv::map_format(format).ok_or(image::ViewError::BadFormat)?;
+            Some(Self::view_image_as_storage(&self.raw, &info(format))?)
+        } else {
+            None
+        };
+
+        Ok(n::ImageView { srv, rtv, dsv, uav })
+    }
+
+    fn create_sampler(&self, info: image::SamplerInfo) -> n::Sampler {
+        let op = match info.comparison {
+            Some(_) => d3d11::D3D11_FILTER_REDUCTION_TYPE_COMPARISON,
+            None => d3d11::D3D11_FILTER_REDUCTION_TYPE_STANDARD,
+        };
+        let desc = d3d11::D3D11_SAMPLER_DESC {
+            Filter: conv::map_filter(info.mag_filter, info.min_filter, info.mip_filter, info.anisotropic, op),
+            AddressU: conv::map_wrap(info.wrap_mode.0),
+            AddressV: conv::map_wrap(info.wrap_mode.1),
+            AddressW: conv::map_wrap(info.wrap_mode.2),
             MipLODBias: info.lod_bias.into(),
-            MaxAnisotropy: match info.filter {
-                FilterMethod::Anisotropic(max) => max as winapi::UINT,
-                _ => 0,
+            MaxAnisotropy: match info.anisotropic {
+                image::Anisotropic::On(max) => max as _,
+                image::Anisotropic::Off => 0,
             },
-            ComparisonFunc: map_function(info.comparison.unwrap_or(core::state::Comparison::Always)),
+            ComparisonFunc: conv::map_comparison(info.comparison.unwrap_or(pso::Comparison::Always)),
             BorderColor: info.border.into(),
-            MinLOD: info.lod_range.0.into(),
-            MaxLOD: info.lod_range.1.into(),
+            MinLOD: info.lod_range.start.into(),
+            MaxLOD: info.lod_range.end.into(),
         };
 
-        let mut raw_sampler = ptr::null_mut();
+        let mut raw = ptr::null_mut();
         let hr = unsafe {
-            (*self.device).CreateSamplerState(&native_desc, &mut raw_sampler)
+            self.raw.CreateSamplerState(&desc, &mut raw as *mut *mut _)
         };
-        if winapi::SUCCEEDED(hr) {
-            self.share.handles.borrow_mut().make_sampler(native::Sampler(raw_sampler), info)
-        } else {
-            error!("Unable to create a sampler with desc {:#?}, error {:x}", info, hr);
-            unimplemented!()
+        assert_eq!(winerror::S_OK, hr);
+
+        n::Sampler {
+            raw: unsafe { ComPtr::from_raw(raw) },
         }
     }
 
-    fn create_semaphore(&mut self) -> h::Semaphore<R> {
-        self.share.handles.borrow_mut().make_semaphore(())
+    fn create_descriptor_pool<I>(
+        &self,
+        _max_sets: usize,
+        _descriptor_pools: I,
+        _flags: pso::DescriptorPoolCreateFlags,
+    ) -> n::DescriptorPool
+    where
+        I: IntoIterator,
+        I::Item: Borrow<pso::DescriptorRangeDesc>,
+    {
+        n::DescriptorPool
     }
 
-    fn create_fence(&mut self, _signalled: bool) -> h::Fence<R> {
-        self.share.handles.borrow_mut().make_fence(Fence)
+    fn create_descriptor_set_layout<I>(
+        &self,
+        bindings: I,
+    ) -> n::DescriptorSetLayout
+    where
+        I: IntoIterator,
+        I::Item: Borrow<pso::DescriptorSetLayoutBinding>,
+    {
+        n::DescriptorSetLayout {
+            bindings: bindings.into_iter().map(|binding| binding.borrow().clone()).collect(),
+        }
     }
 
-    fn reset_fences(&mut self, fences: &[&h::Fence<R>]) {
-        // TODO: noop?
+    fn write_descriptor_sets<'a, I, J>(&self, write_iter: I)
+    where
+        I: IntoIterator<Item = pso::DescriptorSetWrite<'a, B, J>>,
+        J: IntoIterator,
+        J::Item: Borrow<pso::Descriptor<'a, B>>,
+    {
+        fn raw<T>(view: &Option<ComPtr<T>>) -> *mut T {
+            view.as_ref().map_or(ptr::null_mut(), |view| view.as_raw())
+        }
+
+        for write in write_iter {
+            let set = write.set;
+            let mut descriptors = set.descriptors.lock().unwrap();
+            let mut binding_index = match set.binding_index(write.binding) {
+                Some(index) => index,
+                None => {
+                    error!("Descriptor binding {} is missing in the set layout", write.binding);
+                    continue;
+                }
+            };
+            let mut offset = write.array_offset;
+
+            for descriptor in write.descriptors {
+                // spill over the writes onto the next binding
+                while offset >= descriptors[binding_index].len() {
+                    binding_index += 1;
+                    offset = 0;
+                }
+                let ty = set.bindings[binding_index].ty;
+
+                descriptors[binding_index][offset] = match *descriptor.borrow() {
+                    pso::Descriptor::Sampler(sampler) => n::Descriptor::Sampler(sampler.raw.as_raw()),
+                    pso::Descriptor::Image(view, _layout) => match ty {
+                        pso::DescriptorType::StorageImage => n::Descriptor::UnorderedAccess(raw(&view.uav)),
+                        _ => n::Descriptor::ShaderResource(raw(&view.srv)),
+                    },
+                    pso::Descriptor::CombinedImageSampler(view, _layout, sampler) => {
+                        n::Descriptor::CombinedImageSampler(raw(&view.srv), sampler.raw.as_raw())
+                    }
+                    pso::Descriptor::Buffer(buffer, ref range) => {
+                        let start = range.start.unwrap_or(0);
+                        let end = range.end.unwrap_or(buffer.size);
+                        match ty {
+                            pso::DescriptorType::UniformBuffer |
+                            pso::DescriptorType::UniformBufferDynamic => {
+                                // Offsets and sizes are specified in 16 byte constants,
+                                // in multiples of 16 constants.
+                                n::Descriptor::ConstantBuffer {
+                                    raw: buffer.raw.as_raw(),
+                                    first_constant: (start / 16) as _,
+                                    num_constants: (((end - start + 255) & !255) / 16) as _,
+                                }
+                            }
+                            _ => {
+                                if start != 0 {
+                                    error!("Storage buffer offsets are not supported: {}", start);
+                                }
+                                n::Descriptor::StorageBuffer(raw(&buffer.srv), raw(&buffer.uav))
+                            }
+                        }
+                    }
+                    pso::Descriptor::TexelBuffer(view) => match ty {
+                        pso::DescriptorType::StorageTexelBuffer => n::Descriptor::UnorderedAccess(raw(&view.uav)),
+                        _ => n::Descriptor::ShaderResource(raw(&view.srv)),
+                    },
+                    pso::Descriptor::AccelerationStructure(_) => {
+                        error!("Acceleration structures are not supported");
+                        n::Descriptor::Empty
+                    }
+                };
+                offset += 1;
+            }
+        }
     }
 
-    fn wait_for_fences(&mut self, _fences: &[&h::Fence<R>], _wait: d::WaitFor, _timeout_ms: u32) -> bool {
-        // TODO: noop?
-        true
+    fn copy_descriptor_sets<'a, I>(&self, copy_iter: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<pso::DescriptorSetCopy<'a, B>>,
+    {
+        // Walk `count` descriptors from the given binding and offset, spilling over
+        // onto the following bindings.
+        fn slots(
+            descriptors: &[Vec<n::Descriptor>], mut binding: usize, mut offset: usize, count: usize,
+        ) -> Vec<(usize, usize)> {
+            let mut slots = Vec::with_capacity(count);
+            while slots.len() < count {
+                if offset >= descriptors[binding].len() {
+                    binding += 1;
+                    offset = 0;
+                    continue;
+                }
+                slots.push((binding, offset));
+                offset += 1;
+            }
+            slots
+        }
+
+        for copy in copy_iter {
+            let copy = copy.borrow();
+            let (src_binding, dst_binding) = match (
+                copy.src_set.binding_index(copy.src_binding),
+                copy.dst_set.binding_index(copy.dst_binding),
+            ) {
+                (Some(src), Some(dst)) => (src, dst),
+                _ => {
+                    error!("Descriptor binding is missing in the set layout");
+                    continue;
+                }
+            };
+
+            // Read out the source first, the sets may be identical.
+            let values = {
+                let src = copy.src_set.descriptors.lock().unwrap();
+                slots(&src, src_binding, copy.src_array_offset, copy.count)
+                    .into_iter()
+                    .map(|(binding, offset)| src[binding][offset])
+                    .collect::<Vec<_>>()
+            };
+
+            let mut dst = copy.dst_set.descriptors.lock().unwrap();
+            for ((binding, offset), value) in slots(&dst, dst_binding, copy.dst_array_offset, copy.count)
+                .into_iter()
+                .zip(values)
+            {
+                dst[binding][offset] = value;
+            }
+        }
     }
 
-    fn read_mapping<'a, 'b, T>(&'a mut self, buf: &'b h::Buffer<R, T>)
-                               -> Result<mapping::Reader<'b, R, T>,
-                                         mapping::Error>
-        where T: Copy
+    fn map_memory<R>(&self, memory: &n::Memory, range: R) -> Result<*mut u8, mapping::Error>
+    where
+        R: RangeArg<u64>,
     {
-        unsafe {
-            mapping::read(buf.raw(), |mut m| {
-                ensure_mapped(&mut m, buf.raw(), winapi::d3d11::D3D11_MAP_READ, self)
-            })
+        let start = *range.start().unwrap_or(&0);
+        let end = *range.end().unwrap_or(&memory.size);
+        assert!(start <= end);
+        if end > memory.size {
+            return Err(mapping::Error::OutOfBounds);
+        }
+
+        let ptr = memory.host_ptr(start);
+        if ptr.is_null() {
+            Err(mapping::Error::InvalidAccess)
+        } else {
+            Ok(ptr)
         }
     }
 
-    fn write_mapping<'a, 'b, T>(&'a mut self, buf: &'b h::Buffer<R, T>)
-                               -> Result<mapping::Writer<'b, R, T>,
-                                         mapping::Error>
-        where T: Copy
+    fn unmap_memory(&self, _memory: &n::Memory) {
+        // The host copy stays accessible.
+    }
+
+    fn flush_mapped_memory_ranges<'a, I, R>(&self, ranges: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<(&'a n::Memory, R)>,
+        R: RangeArg<u64>,
     {
-        unsafe {
-            mapping::write(buf.raw(), |mut m| {
-                // not MAP_WRITE_DISCARD because we are STAGING
-                ensure_mapped(&mut m, buf.raw(), winapi::d3d11::D3D11_MAP_WRITE, self)
-            })
+        let context = self.context.lock().unwrap();
+        for range in ranges {
+            let &(ref memory, ref range) = range.borrow();
+            let start = *range.start().unwrap_or(&0);
+            let end = *range.end().unwrap_or(&memory.size);
+
+            for bound in memory.buffers.lock().unwrap().iter() {
+                let lo = cmp::max(start, bound.range.start);
+                let hi = cmp::min(end, bound.range.end);
+                if lo >= hi {
+                    continue;
+                }
+
+                if bound.constant {
+                    // Constant buffers can only be updated as a whole.
+                    unsafe {
+                        context.UpdateSubresource(
+                            bound.raw.as_raw() as *mut _,
+                            0,
+                            ptr::null(),
+                            memory.host_ptr(bound.range.start) as *const _,
+                            0,
+                            0,
+                        );
+                    }
+                } else {
+                    let dst_box = d3d11::D3D11_BOX {
+                        left: (lo - bound.range.start) as _,
+                        top: 0,
+                        front: 0,
+                        right: (hi - bound.range.start) as _,
+                        bottom: 1,
+                        back: 1,
+                    };
+                    unsafe {
+                        context.UpdateSubresource(
+                            bound.raw.as_raw() as *mut _,
+                            0,
+                            &dst_box,
+                            memory.host_ptr(lo) as *const _,
+                            0,
+                            0,
+                        );
+                    }
+                }
+            }
         }
     }
-}
 
-pub fn ensure_mapped(mapping: &mut MappingGate,
-                     buffer: &h::RawBuffer<R>,
-                     map_type: winapi::d3d11::D3D11_MAP,
-                     device: &mut Device) {
-    if mapping.pointer.is_null() {
-        let raw_handle = *buffer.resource();
-        let mut ctx = ptr::null_mut();
+    fn invalidate_mapped_memory_ranges<'a, I, R>(&self, ranges: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<(&'a n::Memory, R)>,
+        R: RangeArg<u64>,
+    {
+        let context = self.context.lock().unwrap();
+        for range in ranges {
+            let &(ref memory, ref range) = range.borrow();
+            let start = *range.start().unwrap_or(&0);
+            let end = *range.end().unwrap_or(&memory.size);
+
+            for bound in memory.buffers.lock().unwrap().iter() {
+                let lo = cmp::max(start, bound.range.start);
+                let hi = cmp::min(end, bound.range.end);
+                if lo >= hi {
+                    continue;
+                }
 
-        unsafe {
-            device.device.GetImmediateContext(&mut ctx);
+                // Read back through a staging copy of the whole buffer.
+                let size = bound.range.end - bound.range.start;
+                let desc = d3d11::D3D11_BUFFER_DESC {
+                    ByteWidth: size as _,
+                    Usage: d3d11::D3D11_USAGE_STAGING,
+                    BindFlags: 0,
+                    CPUAccessFlags: d3d11::D3D11_CPU_ACCESS_READ,
+                    MiscFlags: 0,
+                    StructureByteStride: 0,
+                };
+                let mut staging = ptr::null_mut();
+                let hr = unsafe {
+                    self.raw.CreateBuffer(&desc, ptr::null(), &mut staging as *mut *mut _)
+                };
+                if !winerror::SUCCEEDED(hr) {
+                    error!("error on staging buffer creation: {:x}", hr);
+                    continue;
+                }
+                let staging = unsafe { ComPtr::<d3d11::ID3D11Buffer>::from_raw(staging) };
+
+                unsafe {
+                    context.CopyResource(staging.as_raw() as *mut _, bound.raw.as_raw() as *mut _);
+                    let mut mapped = mem::zeroed::<d3d11::D3D11_MAPPED_SUBRESOURCE>();
+                    let hr = context.Map(staging.as_raw() as *mut _, 0, d3d11::D3D11_MAP_READ, 0, &mut mapped);
+                    if !winerror::SUCCEEDED(hr) {
+                        error!("error on staging buffer mapping: {:x}", hr);
+                        continue;
+                    }
+                    ptr::copy_nonoverlapping(
+                        (mapped.pData as *const u8).offset((lo - bound.range.start) as isize),
+                        memory.host_ptr(lo),
+                        (hi - lo) as usize,
+                    );
+                    context.Unmap(staging.as_raw() as *mut _, 0);
+                }
+            }
         }
+    }
+
+    fn create_semaphore(&self) -> n::Semaphore {
+        n::Semaphore
+    }
+
+    fn create_fence(&self, signaled: bool) -> n::Fence {
+        n::Fence::new(signaled)
+    }
 
-        let mut sres = winapi::d3d11::D3D11_MAPPED_SUBRESOURCE {
-            pData: ptr::null_mut(),
-            RowPitch: 0,
-            DepthPitch: 0,
+    fn reset_fence(&self, fence: &n::Fence) {
+        *fence.state.lock().unwrap() = n::FenceState::Unsignaled;
+    }
+
+    fn wait_for_fence(&self, fence: &n::Fence, timeout_ms: u32) -> bool {
+        let start = time::Instant::now();
+        loop {
+            if self.get_fence_status(fence) {
+                return true;
+            }
+            let elapsed = start.elapsed();
+            if elapsed.as_secs() * 1000 + (elapsed.subsec_nanos() / 1_000_000) as u64 >= timeout_ms as u64 {
+                return false;
+            }
+            thread::yield_now();
+        }
+    }
+
+    fn get_fence_status(&self, fence: &n::Fence) -> bool {
+        let context = self.context.lock().unwrap();
+        fence.is_signaled(&context)
+    }
+
+    fn free_memory(&self, _memory: n::Memory) {
+        // automatic
+    }
+
+    fn create_query_pool(
+        &self,
+        query_ty: query::QueryType,
+        count: u32,
+        flags: query::QueryPoolCreateFlags,
+    ) -> n::QueryPool {
+        let kind = match query_ty {
+            query::QueryType::Occlusion if flags.contains(query::QueryPoolCreateFlags::PREDICATION) =>
+                n::QueryKind::BinaryOcclusion,
+            query::QueryType::Occlusion => n::QueryKind::Occlusion,
+            query::QueryType::PipelineStatistics(statistics) => n::QueryKind::PipelineStatistics(statistics),
+            query::QueryType::Timestamp => n::QueryKind::Timestamp,
+        };
+        let query = match kind {
+            n::QueryKind::Occlusion => d3d11::D3D11_QUERY_OCCLUSION,
+            n::QueryKind::BinaryOcclusion => d3d11::D3D11_QUERY_OCCLUSION_PREDICATE,
+            n::QueryKind::PipelineStatistics(_) => d3d11::D3D11_QUERY_PIPELINE_STATISTICS,
+            n::QueryKind::Timestamp => d3d11::D3D11_QUERY_TIMESTAMP,
         };
 
-        let dst = raw_handle.as_resource() as *mut winapi::d3d11::ID3D11Resource;
-        let hr = unsafe {
-            (*ctx).Map(dst, 0, map_type, 0, &mut sres)
+        let create_query = |query| {
+            let desc = d3d11::D3D11_QUERY_DESC {
+                Query: query,
+                MiscFlags: 0,
+            };
+            let mut raw = ptr::null_mut();
+            let hr = unsafe { self.raw.CreateQuery(&desc, &mut raw) };
+            if winerror::SUCCEEDED(hr) {
+                Some(unsafe { ComPtr::<d3d11::ID3D11Query>::from_raw(raw) })
+            } else {
+                error!("error on query creation: {:x}", hr);
+                None
+            }
         };
 
-        if winapi::SUCCEEDED(hr) {
-            mapping.pointer = sres.pData;
-        } else {
-            panic!("Unable to map a buffer {:?}, error {:x}", buffer, hr);
+        // Queries which failed to be created are reported when used.
+        let mut pool = n::QueryPool {
+            kind,
+            queries: Vec::with_capacity(count as usize),
+            disjoint: Vec::new(),
+        };
+        for _ in 0 .. count {
+            let disjoint = if kind == n::QueryKind::Timestamp {
+                match create_query(d3d11::D3D11_QUERY_TIMESTAMP_DISJOINT) {
+                    Some(disjoint) => Some(disjoint),
+                    None => break,
+                }
+            } else {
+                None
+            };
+            match create_query(query) {
+                Some(query) => pool.queries.push(query),
+                None => break,
+            }
+            pool.disjoint.extend(disjoint);
         }
+        pool
     }
-}
 
-pub fn ensure_unmapped(mapping: &mut MappingGate,
-                       buffer: &buffer::Raw<R>,
-                       context: &mut ComPtr<winapi::ID3D11DeviceContext>) {
-    if !mapping.pointer.is_null() {
-        let raw_handle = *buffer.resource();
-        unsafe {
-            context.Unmap(raw_handle.as_resource() as *mut winapi::d3d11::ID3D11Resource, 0);
+    fn destroy_query_pool(&self, _pool: n::QueryPool) {
+        // automatic
+    }
+
+    fn create_command_signature(
+        &self,
+        arguments: &[indirect::Argument],
+        stride: u32,
+        layout: Option<&n::PipelineLayout>,
+    ) -> Result<n::CommandSignature, indirect::CreationError> {
+        indirect::validate_sequence(arguments, stride, layout.is_some())?;
+        if arguments.len() > 1 {
+            return Err(indirect::CreationError::UnsupportedArgument(0));
         }
+        Ok(n::CommandSignature {
+            command: arguments[0].clone(),
+            stride,
+        })
+    }
+
+    fn destroy_command_signature(&self, _signature: n::CommandSignature) {
+        // automatic
+    }
+
+    fn destroy_shader_module(&self, _shader: n::ShaderModule) {
+        // automatic
+    }
+
+    fn destroy_render_pass(&self, _rp: n::RenderPass) {
+        // automatic
+    }
+
+    fn destroy_pipeline_layout(&self, _layout: n::PipelineLayout) {
+        // automatic
+    }
+
+    fn destroy_graphics_pipeline(&self, _pipeline: n::GraphicsPipeline) {
+        // automatic
+    }
+
+    fn destroy_compute_pipeline(&self, _pipeline: n::ComputePipeline) {
+        // automatic
+    }
+
+    fn destroy_framebuffer(&self, _fb: n::Framebuffer) {
+        // automatic
+    }
+
+    fn get_buffer_address(&self, _buffer: &n::Buffer) -> u64 {
+        // `Features::BUFFER_DEVICE_ADDRESS` is not supported.
+        error!("Buffer device addresses are not supported");
+        0
+    }
+
+    fn destroy_buffer(&self, _buffer: n::Buffer) {
+        // The D3D11 buffer is released once the memory is freed.
+    }
+
+    fn destroy_buffer_view(&self, _view: n::BufferView) {
+        // automatic
+    }
+
+    fn destroy_image(&self, _image: n::Image) {
+        // automatic
+    }
+
+    fn destroy_image_view(&self, _view: n::ImageView) {
+        // automatic
+    }
+
+    fn destroy_sampler(&self, _sampler: n::Sampler) {
+        // automatic
+    }
+
+    fn destroy_descriptor_pool(&self, _pool: n::DescriptorPool) {
+        // automatic
+    }
+
+    fn destroy_descriptor_set_layout(&self, _layout: n::DescriptorSetLayout) {
+        // automatic
+    }
+
+    fn destroy_fence(&self, _fence: n::Fence) {
+        // automatic
+    }
+
+    fn destroy_semaphore(&self, _semaphore: n::Semaphore) {
+        // automatic
+    }
+
+    fn create_swapchain(
+        &self,
+        surface: &mut w::Surface,
+        config: hal::SwapchainConfig,
+    ) -> Result<(w::Swapchain, hal::Backbuffer<B>), hal::window::CreationError> {
+        let format = conv::map_format(config.color_format)
+            .ok_or(hal::window::CreationError::UnsupportedFormat(config.color_format))?;
+
+        let mut buffer_usage = dxgitype::DXGI_USAGE_RENDER_TARGET_OUTPUT;
+        if config.image_usage.contains(image::Usage::SAMPLED) {
+            buffer_usage |= dxgitype::DXGI_USAGE_SHADER_INPUT;
+        }
+
+        // The discard swap effect only exposes the first buffer,
+        // which allows sRGB formats in contrast to the flip model.
+        let mut desc = dxgi::DXGI_SWAP_CHAIN_DESC {
+            BufferDesc: dxgitype::DXGI_MODE_DESC {
+                Width: surface.width,
+                Height: surface.height,
+                RefreshRate: dxgitype::DXGI_RATIONAL {
+                    Numerator: 0,
+                    Denominator: 1,
+                },
+                Format: format,
+                ScanlineOrdering: dxgitype::DXGI_MODE_SCANLINE_ORDER_UNSPECIFIED,
+                Scaling: dxgitype::DXGI_MODE_SCALING_UNSPECIFIED,
+            },
+            SampleDesc: dxgitype::DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            BufferUsage: buffer_usage,
+            BufferCount: 1,
+            OutputWindow: surface.wnd_handle,
+            Windowed: TRUE,
+            SwapEffect: dxgi::DXGI_SWAP_EFFECT_DISCARD,
+            Flags: 0,
+        };
+
+        let mut swap_chain = ptr::null_mut();
+        let hr = unsafe {
+            surface.factory.CreateSwapChain(
+                self.raw.as_raw() as *mut IUnknown,
+                &mut desc,
+                &mut swap_chain as *mut *mut _,
+            )
+        };
+
+        if !winerror::SUCCEEDED(hr) {
+            error!("error on swapchain creation 0x{:x}", hr);
+            return Err(if hr == winerror::E_OUTOFMEMORY {
+                hal::window::CreationError::OutOfMemory
+            } else {
+                hal::window::CreationError::InitializationFailed
+            });
+        }
+
+        let swap_chain = unsafe { ComPtr::<dxgi::IDXGISwapChain>::from_raw(swap_chain) };
+
+        let mut resource: *mut d3d11::ID3D11Texture2D = ptr::null_mut();
+        let hr = unsafe {
+            swap_chain.GetBuffer(
+                0,
+                &d3d11::ID3D11Texture2D::uuidof(),
+                &mut resource as *mut *mut _ as *mut *mut _,
+            )
+        };
+        if !winerror::SUCCEEDED(hr) {
+            error!("error on swapchain buffer access 0x{:x}", hr);
+            return Err(hal::window::CreationError::InitializationFailed);
+        }
+
+        let image = n::Image {
+            raw: unsafe { ComPtr::from_raw(resource as *mut d3d11::ID3D11Resource) },
+            kind: image::Kind::D2(surface.width, surface.height, 1, 1),
+            format: config.color_format,
+            dxgi_format: format,
+            usage: config.image_usage,
+            mip_levels: 1,
+        };
+
+        let swapchain = w::Swapchain {
+            inner: swap_chain,
+        };
+
+        Ok((swapchain, hal::Backbuffer::Images(vec![image])))
+    }
+
+    fn destroy_swapchain(&self, _swapchain: w::Swapchain) {
+        // automatic
+    }
 
-        mapping.pointer = ptr::null_mut();
+    fn wait_idle(&self) -> Result<(), error::HostExecutionError> {
+        let context = self.context.lock().unwrap();
+        wait_for_gpu(&context);
+        Ok(())
     }
 }
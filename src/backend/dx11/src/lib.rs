@@ -1,500 +1,538 @@
-//#[deny(missing_docs)]
-
+#[macro_use]
+extern crate derivative;
+extern crate gfx_hal as hal;
 #[macro_use]
 extern crate log;
-extern crate gfx_core as core;
-extern crate d3d11;
-extern crate d3dcompiler;
-extern crate dxgi;
-extern crate dxguid;
+extern crate smallvec;
+extern crate spirv_cross;
 extern crate winapi;
+#[cfg(feature = "winit")]
+extern crate winit;
 extern crate wio;
 
-pub use self::data::map_format;
-pub use self::device::Device;
-
 mod command;
-pub mod data;
-mod execute;
+mod conv;
 mod device;
-mod mirror;
-pub mod native;
+mod native;
 mod pool;
-mod state;
+mod shader;
+mod window;
 
-use core::{command as com, handle};
+use hal::{error, format as f, image, memory, Features, Limits, QueueType};
+use hal::queue::{QueueFamilyId, Queues};
+
+use winapi::Interface;
+use winapi::shared::{dxgi, winerror};
+use winapi::um::{d3d11, d3d11_1, d3dcommon};
 use wio::com::ComPtr;
-use std::cell::RefCell;
-use std::ptr;
-use std::sync::Arc;
-use core::{handle as h, texture as tex};
-use core::{QueueType, SubmissionResult};
-use core::command::{AccessInfo, AccessGuard};
-use std::os::raw::c_void;
-
-static FEATURE_LEVELS: [winapi::D3D_FEATURE_LEVEL; 3] = [
-    winapi::D3D_FEATURE_LEVEL_11_0,
-    winapi::D3D_FEATURE_LEVEL_10_1,
-    winapi::D3D_FEATURE_LEVEL_10_0,
-];
 
-#[doc(hidden)]
-pub struct Instance(pub ComPtr<winapi::IDXGIFactory2>);
+use std::{mem, ptr};
+use std::borrow::{Borrow, BorrowMut};
+use std::ffi::OsString;
+use std::os::windows::ffi::OsStringExt;
+use std::sync::{Arc, Mutex};
 
-impl Instance {
-    #[doc(hidden)]
-    pub fn create() -> Self {
-        // Create DXGI factory
-        let mut dxgi_factory: *mut winapi::IDXGIFactory2 = ptr::null_mut();
-        let hr = unsafe {
-            dxgi::CreateDXGIFactory1(
-                &dxguid::IID_IDXGIFactory2,
-                &mut dxgi_factory as *mut *mut _ as *mut *mut c_void)
+pub use device::Device;
+
+// Feature levels accepted on device creation, in order of preference.
+// 11_1 is only known to the runtime shipped with Windows 8 and newer.
+static FEATURE_LEVELS: [d3dcommon::D3D_FEATURE_LEVEL; 2] = [
+    d3dcommon::D3D_FEATURE_LEVEL_11_1,
+    d3dcommon::D3D_FEATURE_LEVEL_11_0,
+];
+
+// https://msdn.microsoft.com/en-us/library/windows/desktop/ff476876(v=vs.85).aspx
+const MAX_VERTEX_BUFFERS: usize = d3d11::D3D11_IA_VERTEX_INPUT_RESOURCE_SLOT_COUNT as usize;
+
+// Memory type indices, see `Instance::enumerate_adapters`.
+const MEMORY_TYPE_DEVICE_LOCAL: usize = 0;
+const MEMORY_TYPE_HOST_VISIBLE: usize = 1;
+
+/// Create a D3D11 device and its immediate context for the given adapter.
+fn create_device(
+    adapter: &ComPtr<dxgi::IDXGIAdapter1>,
+) -> Result<(ComPtr<d3d11::ID3D11Device>, ComPtr<d3d11::ID3D11DeviceContext>, d3dcommon::D3D_FEATURE_LEVEL), winerror::HRESULT> {
+    let mut hr = winerror::E_FAIL;
+    // Runtimes without 11.1 support reject the whole list if it contains 11_1,
+    // retry with the remaining feature levels in that case.
+    for levels in &[&FEATURE_LEVELS[..], &FEATURE_LEVELS[1..]] {
+        let mut device = ptr::null_mut();
+        let mut context = ptr::null_mut();
+        let mut feature_level = 0;
+        hr = unsafe {
+            d3d11::D3D11CreateDevice(
+                adapter.as_raw() as *mut _,
+                d3dcommon::D3D_DRIVER_TYPE_UNKNOWN,
+                ptr::null_mut(),
+                0,
+                levels.as_ptr(),
+                levels.len() as _,
+                d3d11::D3D11_SDK_VERSION,
+                &mut device as *mut *mut _,
+                &mut feature_level,
+                &mut context as *mut *mut _,
+            )
         };
 
-        if !winapi::SUCCEEDED(hr) {
-            error!("Failed on dxgi factory creation: {:?}", hr);
+        if winerror::SUCCEEDED(hr) {
+            return Ok(unsafe {
+                (ComPtr::from_raw(device), ComPtr::from_raw(context), feature_level)
+            });
+        }
+        if hr != winerror::E_INVALIDARG {
+            break;
         }
-
-        Instance(unsafe { ComPtr::new(dxgi_factory) })
     }
 
-    #[doc(hidden)]
-    pub fn enumerate_adapters(&mut self) -> Vec<Adapter> {
-        use std::ffi::OsString;
-        use std::os::windows::ffi::OsStringExt;
+    Err(hr)
+}
 
-        let mut cur_index = 0;
-        let mut adapters = Vec::new();
-        loop {
-            let mut adapter = {
-                let mut adapter: *mut winapi::IDXGIAdapter1 = ptr::null_mut();
-                let hr = unsafe {
-                    self.0.EnumAdapters1(
-                        cur_index,
-                        &mut adapter as *mut *mut _)
-                };
+/// D3D11 only exposes a single immediate context, which executes all work.
+#[derive(Debug, Clone, Copy)]
+pub struct QueueFamily;
 
-                if hr == winapi::DXGI_ERROR_NOT_FOUND {
-                    break;
-                }
+impl hal::QueueFamily for QueueFamily {
+    fn queue_type(&self) -> QueueType { QueueType::General }
+    fn max_queues(&self) -> usize { 1 }
+    fn id(&self) -> QueueFamilyId { QueueFamilyId(0) }
+}
 
-                unsafe { ComPtr::new(adapter) }
-            };
+pub struct PhysicalDevice {
+    adapter: ComPtr<dxgi::IDXGIAdapter1>,
+    // Device only used for capability queries.
+    query_device: ComPtr<d3d11::ID3D11Device>,
+    features: hal::Features,
+    limits: hal::Limits,
+    memory_properties: hal::MemoryProperties,
+}
 
-            // We have found a possible adapter
-            // acquire the device information
-            let mut desc: winapi::DXGI_ADAPTER_DESC1 = unsafe { std::mem::uninitialized() };
-            unsafe { adapter.GetDesc1(&mut desc); }
+unsafe impl Send for PhysicalDevice { }
+unsafe impl Sync for PhysicalDevice { }
 
-            let device_name = {
-                let len = desc.Description.iter().take_while(|&&c| c != 0).count();
-                let name = <OsString as OsStringExt>::from_wide(&desc.Description[..len]);
-                name.to_string_lossy().into_owned()
-            };
+impl hal::PhysicalDevice<Backend> for PhysicalDevice {
+    fn open(
+        &self, families: &[(&QueueFamily, &[hal::QueuePriority])]
+    ) -> Result<hal::Gpu<Backend>, error::DeviceCreationError> {
+        let (device, context, _) = create_device(&self.adapter)
+            .map_err(|hr| {
+                error!("error on device creation: {:x}", hr);
+                error::DeviceCreationError::InitializationFailed
+            })?;
+
+        // Command lists with constant buffer offsets require the 11.1 interfaces.
+        let device = device.cast::<d3d11_1::ID3D11Device1>()
+            .map_err(|_| error::DeviceCreationError::InitializationFailed)?;
+        let context = context.cast::<d3d11_1::ID3D11DeviceContext1>()
+            .map_err(|_| error::DeviceCreationError::InitializationFailed)?;
+        let context = Arc::new(Mutex::new(context));
+
+        let queue_groups = families
+            .into_iter()
+            .map(|&(&family, priorities)| {
+                let mut group = hal::backend::RawQueueGroup::new(family);
+                for _ in 0 .. priorities.len() {
+                    group.add_queue(CommandQueue {
+                        context: context.clone(),
+                    });
+                }
+                (family.id(), group)
+            })
+            .collect();
+
+        Ok(hal::Gpu {
+            device: Device::new(device, context),
+            queues: Queues::new(queue_groups),
+        })
+    }
 
-            let info = core::AdapterInfo {
-                name: device_name,
-                vendor: desc.VendorId as usize,
-                device: desc.DeviceId as usize,
-                software_rendering: false, // TODO
-            };
+    fn format_properties(&self, fmt: Option<f::Format>) -> f::Properties {
+        let mut properties = f::Properties {
+            linear_tiling: f::ImageFeature::empty(),
+            optimal_tiling: f::ImageFeature::empty(),
+            buffer_features: f::BufferFeature::empty(),
+        };
 
-            adapters.push(
-                Adapter {
-                    adapter: adapter,
-                    info: info,
-                    queue_family: [(QueueFamily, QueueType::General)],
-                }
-            );
+        let format = match fmt.and_then(conv::map_format) {
+            Some(format) => format,
+            None => return properties,
+        };
 
-            cur_index += 1;
+        let mut support = 0;
+        let hr = unsafe { self.query_device.CheckFormatSupport(format, &mut support) };
+        if !winerror::SUCCEEDED(hr) {
+            return properties;
         }
 
-        adapters
-    }
-}
+        let has = |flag: d3d11::D3D11_FORMAT_SUPPORT| support & flag != 0;
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
-pub struct Buffer(native::Buffer);
-impl Buffer {
-    pub fn as_resource(&self) -> *mut winapi::ID3D11Resource {
-        type Res = *mut winapi::ID3D11Resource;
-        match self.0 {
-            native::Buffer(t) => t as Res,
+        if has(d3d11::D3D11_FORMAT_SUPPORT_SHADER_LOAD) {
+            properties.optimal_tiling |= f::ImageFeature::SAMPLED;
+        }
+        if has(d3d11::D3D11_FORMAT_SUPPORT_SHADER_SAMPLE) {
+            properties.optimal_tiling |= f::ImageFeature::SAMPLED | f::ImageFeature::SAMPLED_LINEAR;
+        }
+        if has(d3d11::D3D11_FORMAT_SUPPORT_RENDER_TARGET) {
+            properties.optimal_tiling |= f::ImageFeature::COLOR_ATTACHMENT;
+        }
+        if has(d3d11::D3D11_FORMAT_SUPPORT_BLENDABLE) {
+            properties.optimal_tiling |= f::ImageFeature::COLOR_ATTACHMENT_BLEND;
+        }
+        if has(d3d11::D3D11_FORMAT_SUPPORT_DEPTH_STENCIL) {
+            properties.optimal_tiling |= f::ImageFeature::DEPTH_STENCIL_ATTACHMENT;
+        }
+        if has(d3d11::D3D11_FORMAT_SUPPORT_TYPED_UNORDERED_ACCESS_VIEW) {
+            properties.optimal_tiling |= f::ImageFeature::STORAGE;
         }
-    }
-}
-
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
-pub struct Texture(native::Texture);
-impl Texture {
-    #[doc(hidden)]
-    pub fn new(tex: native::Texture) -> Self {
-        Texture(tex)
-    }
 
-    pub fn as_resource(&self) -> *mut winapi::ID3D11Resource {
-        type Res = *mut winapi::ID3D11Resource;
-        match self.0 {
-            native::Texture::D1(t) => t as Res,
-            native::Texture::D2(t) => t as Res,
-            native::Texture::D3(t) => t as Res,
+        if has(d3d11::D3D11_FORMAT_SUPPORT_IA_VERTEX_BUFFER) {
+            properties.buffer_features |= f::BufferFeature::VERTEX;
+        }
+        if has(d3d11::D3D11_FORMAT_SUPPORT_BUFFER) {
+            if has(d3d11::D3D11_FORMAT_SUPPORT_SHADER_LOAD) {
+                properties.buffer_features |= f::BufferFeature::UNIFORM_TEXEL;
+            }
+            if has(d3d11::D3D11_FORMAT_SUPPORT_TYPED_UNORDERED_ACCESS_VIEW) {
+                properties.buffer_features |= f::BufferFeature::STORAGE_TEXEL;
+            }
         }
+
+        properties
     }
-}
 
+    fn image_format_properties(
+        &self, _format: f::Format, dimensions: u8, tiling: image::Tiling,
+        usage: image::Usage, storage_flags: image::StorageFlags,
+    ) -> Option<image::FormatProperties> {
+        // Linear images can't be mapped, they are not supported at all.
+        if tiling != image::Tiling::Optimal {
+            return None;
+        }
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
-pub struct Shader {
-    object: *mut winapi::ID3D11DeviceChild,
-    reflection: *mut winapi::ID3D11ShaderReflection,
-    code_hash: u64,
-}
-unsafe impl Send for Shader {}
-unsafe impl Sync for Shader {}
-
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
-pub struct Program {
-    vs: *mut winapi::ID3D11VertexShader,
-    hs: *mut winapi::ID3D11HullShader,
-    ds: *mut winapi::ID3D11DomainShader,
-    gs: *mut winapi::ID3D11GeometryShader,
-    ps: *mut winapi::ID3D11PixelShader,
-    vs_hash: u64,
-}
-unsafe impl Send for Program {}
-unsafe impl Sync for Program {}
-
-pub type InputLayout = *mut winapi::ID3D11InputLayout;
-
-#[derive(Clone, PartialEq, Eq, Hash, Debug)]
-pub struct Pipeline {
-    topology: winapi::D3D11_PRIMITIVE_TOPOLOGY,
-    layout: InputLayout,
-    vertex_buffers: [Option<core::pso::VertexBufferDesc>; core::pso::MAX_VERTEX_BUFFERS],
-    attributes: [Option<core::pso::AttributeDesc>; core::MAX_VERTEX_ATTRIBUTES],
-    program: Program,
-    rasterizer: *const winapi::ID3D11RasterizerState,
-    depth_stencil: *const winapi::ID3D11DepthStencilState,
-    blend: *const winapi::ID3D11BlendState,
-}
-unsafe impl Send for Pipeline {}
-unsafe impl Sync for Pipeline {}
+        Some(image::FormatProperties {
+            max_extent: match dimensions {
+                1 => image::Extent {
+                    width: d3d11::D3D11_REQ_TEXTURE1D_U_DIMENSION,
+                    height: 1,
+                    depth: 1,
+                },
+                2 => image::Extent {
+                    width: d3d11::D3D11_REQ_TEXTURE2D_U_OR_V_DIMENSION,
+                    height: d3d11::D3D11_REQ_TEXTURE2D_U_OR_V_DIMENSION,
+                    depth: 1,
+                },
+                3 => image::Extent {
+                    width: d3d11::D3D11_REQ_TEXTURE3D_U_V_OR_W_DIMENSION,
+                    height: d3d11::D3D11_REQ_TEXTURE3D_U_V_OR_W_DIMENSION,
+                    depth: d3d11::D3D11_REQ_TEXTURE3D_U_V_OR_W_DIMENSION,
+                },
+                _ => return None,
+            },
+            max_levels: d3d11::D3D11_REQ_MIP_LEVELS as _,
+            max_layers: match dimensions {
+                1 => d3d11::D3D11_REQ_TEXTURE1D_ARRAY_AXIS_DIMENSION as _,
+                2 => d3d11::D3D11_REQ_TEXTURE2D_ARRAY_AXIS_DIMENSION as _,
+                _ => 1,
+            },
+            sample_count_mask: if dimensions == 2 &&
+                !storage_flags.contains(image::StorageFlags::CUBE_VIEW) &&
+                (usage.contains(image::Usage::COLOR_ATTACHMENT) | usage.contains(image::Usage::DEPTH_STENCIL_ATTACHMENT))
+            {
+                0x1F //TODO: use CheckMultisampleQualityLevels
+            } else {
+                0x1
+            },
+            max_resource_size: (d3d11::D3D11_REQ_RESOURCE_SIZE_IN_MEGABYTES_EXPRESSION_A_TERM as usize) << 20,
+        })
+    }
 
-#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
-pub enum Backend {}
-impl core::Backend for Backend {
-    type Adapter = Adapter;
-    type Resources = Resources;
-    type CommandQueue = CommandQueue;
-    type RawCommandBuffer = command::RawCommandBuffer<CommandList>; // TODO: deferred?
-    type SubmitInfo = command::SubmitInfo<CommandList>;
-    type Device = Device;
-    type QueueFamily = QueueFamily;
+    fn memory_properties(&self) -> hal::MemoryProperties {
+        self.memory_properties.clone()
+    }
 
-    type RawCommandPool = pool::RawCommandPool;
+    fn features(&self) -> Features { self.features }
+    fn limits(&self) -> Limits { self.limits }
 }
 
-#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
-pub enum Resources {}
-
-impl core::Resources for Resources {
-    type Buffer              = Buffer;
-    type Shader              = Shader;
-    type Program             = Program;
-    type PipelineStateObject = Pipeline;
-    type Texture             = Texture;
-    type RenderTargetView    = native::Rtv;
-    type DepthStencilView    = native::Dsv;
-    type ShaderResourceView  = native::Srv;
-    type UnorderedAccessView = ();
-    type Sampler             = native::Sampler;
-    type Fence               = Fence;
-    type Semaphore           = (); // TODO
-    type Mapping             = device::MappingGate;
+#[derive(Clone)]
+pub struct CommandQueue {
+    // Immediate context, shared with the device.
+    pub(crate) context: Arc<Mutex<ComPtr<d3d11_1::ID3D11DeviceContext1>>>,
 }
 
-/// Internal struct of shared data between the device and its factories.
-#[doc(hidden)]
-pub struct Share {
-    capabilities: core::Capabilities,
-    handles: RefCell<h::Manager<Resources>>,
-}
+unsafe impl Send for CommandQueue {}
+unsafe impl Sync for CommandQueue {}
 
-pub type ShaderModel = u16;
+impl hal::queue::RawCommandQueue<Backend> for CommandQueue {
+    unsafe fn submit_raw<IC>(
+        &mut self,
+        submission: hal::queue::RawSubmission<Backend, IC>,
+        fence: Option<&native::Fence>,
+    ) where
+        IC: IntoIterator,
+        IC::Item: Borrow<command::CommandBuffer>,
+    {
+        // Semaphores don't need any handling as there is only a single queue,
+        // executing the submissions in order.
+        let context = self.context.lock().unwrap();
+        for cmd_buffer in submission.cmd_buffers {
+            let segments = match cmd_buffer.borrow().segments() {
+                Some(segments) => segments,
+                None => {
+                    error!("Submitted command buffer has not been finished");
+                    continue;
+                }
+            };
+            for segment in segments {
+                context.ExecuteCommandList(segment.list.as_raw(), 0);
+                if let Some(ref readback) = segment.readback {
+                    readback.execute(&context);
+                }
+            }
+        }
 
-#[derive(Clone)]
-pub struct CommandList(Vec<command::Command>, command::DataBuffer);
-impl CommandList {
-    pub fn new() -> CommandList {
-        CommandList(Vec::new(), command::DataBuffer::new())
-    }
-}
-impl command::Parser for CommandList {
-    fn reset(&mut self) {
-        self.0.clear();
-        self.1.reset();
-    }
-    fn parse(&mut self, com: command::Command) {
-        self.0.push(com);
-    }
-    fn update_buffer(&mut self, buf: Buffer, data: &[u8], offset: usize) {
-        let ptr = self.1.add(data);
-        self.0.push(command::Command::UpdateBuffer(buf, ptr, offset));
+        if let Some(fence) = fence {
+            fence.signal_on(&context);
+        }
     }
-    fn update_texture(&mut self, tex: Texture, kind: tex::Kind, face: Option<tex::CubeFace>, data: &[u8], image: tex::RawImageInfo) {
-        let ptr = self.1.add(data);
-        self.0.push(command::Command::UpdateTexture(tex, kind, face, ptr, image));
+
+    fn present<IS, IW>(&mut self, swapchains: IS, _wait_semaphores: IW)
+    where
+        IS: IntoIterator,
+        IS::Item: BorrowMut<window::Swapchain>,
+        IW: IntoIterator,
+        IW::Item: Borrow<native::Semaphore>,
+    {
+        // Presenting uses the immediate context internally.
+        let _context = self.context.lock().unwrap();
+        for swapchain in swapchains {
+            unsafe { swapchain.borrow().inner.Present(1, 0); }
+        }
     }
-}
 
-pub struct DeferredContext(ComPtr<winapi::ID3D11DeviceContext>, Option<*mut winapi::ID3D11CommandList>);
-unsafe impl Send for DeferredContext {}
-impl DeferredContext {
-    pub fn new(dc: ComPtr<winapi::ID3D11DeviceContext>) -> DeferredContext {
-        DeferredContext(dc, None)
+    fn wait_idle(&self) -> Result<(), error::HostExecutionError> {
+        let context = self.context.lock().unwrap();
+        device::wait_for_gpu(&context);
+        Ok(())
     }
 }
-impl Drop for DeferredContext {
-    fn drop(&mut self) {
-        unsafe { self.0.Release() };
-    }
+
+pub struct Instance {
+    pub(crate) factory: ComPtr<dxgi::IDXGIFactory1>,
 }
-impl command::Parser for DeferredContext {
-    fn reset(&mut self) {
-        if let Some(cl) = self.1 {
-            unsafe { (*cl).Release() };
-            self.1 = None;
-        }
-        unsafe {
-            self.0.ClearState()
+
+unsafe impl Send for Instance { }
+unsafe impl Sync for Instance { }
+
+impl Instance {
+    pub fn create(_: &str, _: u32) -> Instance {
+        let mut factory: *mut dxgi::IDXGIFactory1 = ptr::null_mut();
+        let hr = unsafe {
+            dxgi::CreateDXGIFactory1(
+                &dxgi::IDXGIFactory1::uuidof(),
+                &mut factory as *mut *mut _ as *mut *mut _,
+            )
         };
-    }
-    fn parse(&mut self, com: command::Command) {
-        let db = command::DataBuffer::new(); //not used
-        execute::process(&mut self.0, &com, &db);
-    }
-    fn update_buffer(&mut self, buf: Buffer, data: &[u8], offset: usize) {
-        execute::update_buffer(&mut self.0, &buf, data, offset);
-    }
-    fn update_texture(&mut self, tex: Texture, kind: tex::Kind, face: Option<tex::CubeFace>, data: &[u8], image: tex::RawImageInfo) {
-        execute::update_texture(&mut self.0, &tex, kind, face, data, &image);
-    }
-}
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub struct Fence;
+        if !winerror::SUCCEEDED(hr) {
+            error!("Failed on dxgi factory creation: {:?}", hr);
+        }
 
-pub struct Adapter {
-    adapter: ComPtr<winapi::IDXGIAdapter1>,
-    info: core::AdapterInfo,
-    queue_family: [(QueueFamily, QueueType); 1],
+        Instance {
+            factory: unsafe { ComPtr::from_raw(factory) },
+        }
+    }
 }
 
-impl core::Adapter<Backend> for Adapter {
-    fn open(&self, queue_descs: &[(&QueueFamily, QueueType, u32)]) -> core::Gpu<Backend> {
-        // Only support a single queue
-        assert_eq!(queue_descs.len(), 1);
-        assert!(queue_descs[0].2 <= 1);
-
-        // Create D3D11 device
-        let mut feature_level = winapi::D3D_FEATURE_LEVEL_10_0;
-
-        let (mut dev, context) = {
-            let mut device: *mut winapi::ID3D11Device         = ptr::null_mut();
-            let mut context: *mut winapi::ID3D11DeviceContext = ptr::null_mut();
-
-            let hr = unsafe {
-                d3d11::D3D11CreateDevice(
-                    self.adapter.as_mut() as *mut _ as *mut winapi::IDXGIAdapter,
-                    winapi::D3D_DRIVER_TYPE_UNKNOWN,
-                    ptr::null_mut(),
-                    0, // TODO
-                    &FEATURE_LEVELS[0],
-                    FEATURE_LEVELS.len() as winapi::UINT,
-                    winapi::D3D11_SDK_VERSION,
-                    &mut device as *mut *mut winapi::ID3D11Device,
-                    &mut feature_level as *mut _,
-                    &mut context as *mut *mut winapi::ID3D11DeviceContext,
-                )
-            };
+impl hal::Instance for Instance {
+    type Backend = Backend;
 
-            if !winapi::SUCCEEDED(hr) {
-                error!("error on device creation: {:x}", hr);
-            }
+    fn enumerate_adapters(&self) -> Vec<hal::Adapter<Backend>> {
+        use self::memory::Properties;
 
-            unsafe { (ComPtr::new(device), ComPtr::new(context)) }
-        };
+        let mut cur_index = 0;
+        let mut adapters = Vec::new();
+        loop {
+            let adapter = {
+                let mut adapter: *mut dxgi::IDXGIAdapter1 = ptr::null_mut();
+                let hr = unsafe {
+                    self.factory.EnumAdapters1(
+                        cur_index,
+                        &mut adapter as *mut *mut _)
+                };
 
-        let share = Arc::new(Share {
-            capabilities: core::Capabilities {
-                max_texture_size: 0,
-                max_patch_size: 32, //hard-coded in D3D11
-                instance_base_supported: false,
-                instance_call_supported: false,
-                instance_rate_supported: false,
-                vertex_base_supported: false,
-                srgb_color_supported: false,
-                constant_buffer_supported: true,
-                unordered_access_view_supported: false,
-                separate_blending_slots_supported: false,
-                copy_buffer_supported: true,
-            },
-            handles: RefCell::new(h::Manager::new()),
-        });
-
-        let device = Device::new(dev.clone(), feature_level, share.clone());
-        let mut gpu = core::Gpu {
-            device,
-            general_queues: Vec::new(),
-            graphics_queues: Vec::new(),
-            compute_queues: Vec::new(),
-            transfer_queues: Vec::new(),
-            heap_types: Vec::new(),
-            memory_heaps: Vec::new(),
-        };
+                if hr == winerror::DXGI_ERROR_NOT_FOUND {
+                    break;
+                }
 
-        let raw_queue = || {
-            CommandQueue {
-                device: dev.clone(),
-                context: context.clone(),
-                share: share.clone(),
-                frame_handles: handle::Manager::new(),
-                max_resource_count: Some(999999),
-            }
-        };
+                unsafe { ComPtr::from_raw(adapter) }
+            };
 
-        let (_, queue_type, num_queues) = queue_descs[0];
-        for _ in 0..num_queues {
-            unsafe {
-                match queue_type {
-                    QueueType::General => {
-                        gpu.general_queues.push(core::GeneralQueue::new(raw_queue()));
-                    }
-                    QueueType::Graphics => {
-                        gpu.graphics_queues.push(core::GraphicsQueue::new(raw_queue()));
-                    }
-                    QueueType::Compute => {
-                        gpu.compute_queues.push(core::ComputeQueue::new(raw_queue()));
-                    }
-                    QueueType::Transfer => {
-                        gpu.transfer_queues.push(core::TransferQueue::new(raw_queue()));
-                    }
-                }
-            }
-        }
+            cur_index += 1;
 
-        gpu
-    }
+            // Check for D3D11 support by creating a temporary device.
+            let (device, feature_level) = match create_device(&adapter) {
+                Ok((device, _, feature_level)) => (device, feature_level),
+                Err(_) => continue,
+            };
 
-    fn info(&self) -> &core::AdapterInfo {
-        &self.info
-    }
+            let mut desc: dxgi::DXGI_ADAPTER_DESC1 = unsafe { mem::zeroed() };
+            unsafe { adapter.GetDesc1(&mut desc); }
 
-    fn queue_families(&self) -> &[(QueueFamily, QueueType)] {
-        &self.queue_family
-    }
-}
+            let device_name = {
+                let len = desc.Description.iter().take_while(|&&c| c != 0).count();
+                let name = <OsString as OsStringExt>::from_wide(&desc.Description[..len]);
+                name.to_string_lossy().into_owned()
+            };
 
-pub struct CommandQueue {
-    #[doc(hidden)]
-    pub device: ComPtr<winapi::ID3D11Device>,
-    context: ComPtr<winapi::ID3D11DeviceContext>,
-    share: Arc<Share>,
-    frame_handles: handle::Manager<Resources>,
-    max_resource_count: Option<usize>,
-}
+            let info = hal::AdapterInfo {
+                name: device_name,
+                vendor: desc.VendorId as usize,
+                device: desc.DeviceId as usize,
+                software_rendering: desc.Flags & dxgi::DXGI_ADAPTER_FLAG_SOFTWARE != 0,
+            };
 
-impl CommandQueue {
-    pub fn before_submit<'a>(&mut self, gpu_access: &'a AccessInfo<Resources>)
-                             -> core::SubmissionResult<AccessGuard<'a, Resources>> {
-        let mut gpu_access = try!(gpu_access.take_accesses());
-        for (buffer, mut mapping) in gpu_access.access_mapped() {
-            device::ensure_unmapped(&mut mapping, buffer, &mut self.context);
-        }
-        Ok(gpu_access)
-    }
-}
+            // Host visible memory is emulated by the backend, see `native::Memory`.
+            let memory_properties = hal::MemoryProperties {
+                memory_types: vec![
+                    hal::MemoryType {
+                        properties: Properties::DEVICE_LOCAL,
+                        heap_index: 0,
+                    },
+                    hal::MemoryType {
+                        properties: Properties::CPU_VISIBLE | Properties::CPU_CACHED,
+                        heap_index: 1,
+                    },
+                ],
+                memory_heaps: vec![
+                    desc.DedicatedVideoMemory as _,
+                    desc.SharedSystemMemory as _,
+                ],
+            };
 
-impl core::CommandQueue<Backend> for CommandQueue {
-    unsafe fn submit_raw<'a, I>(
-        &mut self,
-        submit_infos: I,
-        fence: Option<&h::Fence<Resources>>,
-        access: &com::AccessInfo<Resources>,
-    ) where I: Iterator<Item=core::RawSubmission<'a, Backend>> {
-        let _guard = self.before_submit(access).unwrap();
-        for submit in submit_infos {
-            for cb in submit.cmd_buffers {
-                unsafe { self.context.ClearState(); }
-                for com in &cb.parser.0 {
-                    execute::process(&mut self.context, com, &cb.parser.1);
-                }
-            }
-        }
+            let logic_op = if feature_level >= d3dcommon::D3D_FEATURE_LEVEL_11_1 {
+                Features::LOGIC_OP
+            } else {
+                Features::empty()
+            };
 
-        // TODO: handle sync
-    }
+            let physical_device = PhysicalDevice {
+                adapter,
+                query_device: device,
+                features:
+                    Features::ROBUST_BUFFER_ACCESS |
+                    Features::IMAGE_CUBE_ARRAY |
+                    Features::INDEPENDENT_BLENDING |
+                    Features::GEOMETRY_SHADER |
+                    Features::TESSELLATION_SHADER |
+                    Features::DUAL_SRC_BLENDING |
+                    Features::MULTI_DRAW_INDIRECT |
+                    Features::DRAW_INDIRECT_FIRST_INSTANCE |
+                    Features::DRAW_FIRST_INSTANCE |
+                    Features::DRAW_BASE_VERTEX |
+                    Features::DEPTH_CLAMP |
+                    Features::DEPTH_CLIP_CONTROL |
+                    Features::DEPTH_BIAS_CLAMP |
+                    Features::NON_FILL_POLYGON_MODE |
+                    Features::MULTI_VIEWPORTS |
+                    Features::SAMPLER_ANISOTROPY |
+                    Features::SAMPLER_BORDER_COLOR |
+                    Features::FORMAT_BC |
+                    Features::INSTANCE_RATE |
+                    Features::PRECISE_OCCLUSION_QUERY |
+                    Features::PIPELINE_STATISTICS_QUERY |
+                    Features::DYNAMIC_RENDERING |
+                    // Resource states are managed by the driver.
+                    Features::TRACKED_RESOURCES |
+                    logic_op,
+                limits: Limits {
+                    max_texture_size: d3d11::D3D11_REQ_TEXTURE2D_U_OR_V_DIMENSION as _,
+                    max_texture_3d_size: d3d11::D3D11_REQ_TEXTURE3D_U_V_OR_W_DIMENSION as _,
+                    max_texture_array_layers: d3d11::D3D11_REQ_TEXTURE2D_ARRAY_AXIS_DIMENSION as _,
+                    max_patch_size: d3d11::D3D11_IA_PATCH_MAX_CONTROL_POINT_COUNT as _,
+                    max_viewports: d3d11::D3D11_VIEWPORT_AND_SCISSORRECT_OBJECT_COUNT_PER_PIPELINE as _,
+                    max_compute_group_count: [
+                        d3d11::D3D11_CS_DISPATCH_MAX_THREAD_GROUPS_PER_DIMENSION,
+                        d3d11::D3D11_CS_DISPATCH_MAX_THREAD_GROUPS_PER_DIMENSION,
+                        d3d11::D3D11_CS_DISPATCH_MAX_THREAD_GROUPS_PER_DIMENSION,
+                    ],
+                    max_compute_group_size: [
+                        d3d11::D3D11_CS_THREAD_GROUP_MAX_X,
+                        d3d11::D3D11_CS_THREAD_GROUP_MAX_Y,
+                        d3d11::D3D11_CS_THREAD_GROUP_MAX_Z,
+                    ],
+                    // Push constants are emulated with a constant buffer.
+                    max_push_constants_size: shader::PUSH_CONSTANT_SIZE as _,
+                    max_bound_descriptor_sets: 8, // TODO
+                    max_per_stage_descriptor_samplers: d3d11::D3D11_COMMONSHADER_SAMPLER_SLOT_COUNT as _,
+                    max_per_stage_descriptor_uniform_buffers: d3d11::D3D11_COMMONSHADER_CONSTANT_BUFFER_API_SLOT_COUNT as _,
+                    max_per_stage_descriptor_storage_buffers: d3d11::D3D11_1_UAV_SLOT_COUNT as _,
+                    max_per_stage_descriptor_sampled_images: d3d11::D3D11_COMMONSHADER_INPUT_RESOURCE_SLOT_COUNT as _,
+                    max_per_stage_descriptor_storage_images: d3d11::D3D11_1_UAV_SLOT_COUNT as _,
+                    max_texel_elements: 1 << d3d11::D3D11_REQ_BUFFER_RESOURCE_TEXEL_COUNT_2_TO_EXP,
+                    min_buffer_copy_offset_alignment: 1,
+                    min_buffer_copy_pitch_alignment: 1,
+                    // Constant buffer offsets are specified in multiples of 16 constants.
+                    min_uniform_buffer_offset_alignment: 256,
+                    non_coherent_atom_size: 1,
+                    shader_group_handle_size: 0,
+                    max_ray_recursion_depth: 0,
+                    min_shader_table_alignment: 0,
+                    min_shader_record_alignment: 0,
+                    max_multiview_view_count: 0,
+                },
+                memory_properties,
+            };
 
-    fn pin_submitted_resources(&mut self, man: &handle::Manager<Resources>) {
-        self.frame_handles.extend(man);
-        match self.max_resource_count {
-            Some(c) if self.frame_handles.count() > c => {
-                error!("Way too many resources in the current frame. Did you call Device::cleanup()?");
-                self.max_resource_count = None;
-            },
-            _ => (),
+            adapters.push(hal::Adapter {
+                info,
+                physical_device,
+                queue_families: vec![QueueFamily],
+            });
         }
-    }
-
-    fn cleanup(&mut self) {
-        use core::handle::Producer;
-
-        self.frame_handles.clear();
-        self.share.handles.borrow_mut().clean_with(&mut self.context,
-            |ctx, buffer| {
-                buffer.mapping().map(|raw| {
-                    // we have exclusive access because it's the last reference
-                    let mut mapping = unsafe { raw.use_access() };
-                    device::ensure_unmapped(&mut mapping, buffer, ctx);
-                });
-                unsafe { (*(buffer.resource().0).0).Release(); }
-            },
-            |_, s| unsafe { //shader
-                (*s.object).Release();
-                (*s.reflection).Release();
-            },
-            |_, program| unsafe {
-                let p = program.resource();
-                if p.vs != ptr::null_mut() { (*p.vs).Release(); }
-                if p.hs != ptr::null_mut() { (*p.hs).Release(); }
-                if p.ds != ptr::null_mut() { (*p.ds).Release(); }
-                if p.gs != ptr::null_mut() { (*p.gs).Release(); }
-                if p.ps != ptr::null_mut() { (*p.ps).Release(); }
-            },
-            |_, v| unsafe { //PSO
-                type Child = *mut winapi::ID3D11DeviceChild;
-                (*v.layout).Release();
-                (*(v.rasterizer as Child)).Release();
-                (*(v.depth_stencil as Child)).Release();
-                (*(v.blend as Child)).Release();
-            },
-            |_, texture| unsafe { (*texture.resource().as_resource()).Release(); },
-            |_, v| unsafe { (*v.0).Release(); }, //SRV
-            |_, _| {}, //UAV
-            |_, v| unsafe { (*v.0).Release(); }, //RTV
-            |_, v| unsafe { (*v.0).Release(); }, //DSV
-            |_, v| unsafe { (*v.0).Release(); }, //sampler
-            |_, _fence| {},
-            |_, _| {}, // Semaphore
-        );
+        adapters
     }
 }
 
-///
-#[derive(Debug)]
-pub struct QueueFamily;
-impl core::QueueFamily for QueueFamily {
-    fn num_queues(&self) -> u32 { 1 }
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Backend {}
+impl hal::Backend for Backend {
+    type PhysicalDevice = PhysicalDevice;
+    type Device = Device;
+
+    type Surface = window::Surface;
+    type Swapchain = window::Swapchain;
+
+    type QueueFamily = QueueFamily;
+    type CommandQueue = CommandQueue;
+    type CommandBuffer = command::CommandBuffer;
+
+    type Memory = native::Memory;
+    type CommandPool = pool::RawCommandPool;
+
+    type ShaderModule = native::ShaderModule;
+    type RenderPass = native::RenderPass;
+    type Framebuffer = native::Framebuffer;
+
+    type UnboundBuffer = device::UnboundBuffer;
+    type Buffer = native::Buffer;
+    type BufferView = native::BufferView;
+    type UnboundImage = device::UnboundImage;
+    type Image = native::Image;
+    type ImageView = native::ImageView;
+    type Sampler = native::Sampler;
+
+    type ComputePipeline = native::ComputePipeline;
+    type GraphicsPipeline = native::GraphicsPipeline;
+    type PipelineLayout = native::PipelineLayout;
+    type DescriptorSetLayout = native::DescriptorSetLayout;
+    type DescriptorPool = native::DescriptorPool;
+    type DescriptorSet = native::DescriptorSet;
+
+    type Fence = native::Fence;
+    type Semaphore = native::Semaphore;
+    type QueryPool = native::QueryPool;
+    type CommandSignature = native::CommandSignature;
+
+    type AccelerationStructure = native::AccelerationStructure;
+    type RayTracingPipeline = native::RayTracingPipeline;
 }
@@ -1,35 +1,415 @@
-use winapi::*;
-
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
-pub struct Buffer(pub *mut ID3D11Buffer);
-unsafe impl Send for Buffer {}
-unsafe impl Sync for Buffer {}
-
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
-pub enum Texture {
-    D1(*mut ID3D11Texture1D),
-    D2(*mut ID3D11Texture2D),
-    D3(*mut ID3D11Texture3D),
-}
-unsafe impl Send for Texture {}
-unsafe impl Sync for Texture {}
-
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
-pub struct Rtv(pub *mut ID3D11RenderTargetView);
-unsafe impl Send for Rtv {}
-unsafe impl Sync for Rtv {}
-
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
-pub struct Dsv(pub *mut ID3D11DepthStencilView);
-unsafe impl Send for Dsv {}
-unsafe impl Sync for Dsv {}
-
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
-pub struct Srv(pub *mut ID3D11ShaderResourceView);
-unsafe impl Send for Srv {}
-unsafe impl Sync for Srv {}
-
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
-pub struct Sampler(pub *mut ID3D11SamplerState);
-unsafe impl Send for Sampler {}
-unsafe impl Sync for Sampler {}
+use winapi::shared::dxgiformat::DXGI_FORMAT;
+use winapi::shared::minwindef::UINT;
+use winapi::shared::winerror;
+use winapi::um::{d3d11, d3d11_1, d3dcommon};
+use wio::com::ComPtr;
+
+use hal::{buffer, format, image, indirect, memory, pass, pso, query, DescriptorPool as HalDescriptorPool, QueryPool as HalQueryPool};
+use {Backend, MAX_VERTEX_BUFFERS};
+
+use std::cell::UnsafeCell;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::ptr;
+use std::sync::Mutex;
+
+// ShaderModule is either precompiled if the source comes from HLSL or
+// a SPIR-V module, which is translated on pipeline creation as the
+// resource bindings depend on the pipeline layout.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub enum ShaderModule {
+    Compiled(#[derivative(Debug="ignore")] BTreeMap<String, ComPtr<d3dcommon::ID3DBlob>>),
+    Spirv(Vec<u8>),
+}
+unsafe impl Send for ShaderModule { }
+unsafe impl Sync for ShaderModule { }
+
+#[derive(Debug, Clone)]
+pub struct SubpassDesc {
+    pub(crate) color_attachments: Vec<pass::AttachmentId>,
+    pub(crate) depth_stencil_attachment: Option<pass::AttachmentId>,
+}
+
+impl SubpassDesc {
+    /// Check if an attachment is used by this sub-pass.
+    pub(crate) fn is_using(&self, at_id: pass::AttachmentId) -> bool {
+        self.color_attachments.iter()
+            .chain(self.depth_stencil_attachment.iter())
+            .any(|&id| id == at_id)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderPass {
+    pub(crate) attachments: Vec<pass::Attachment>,
+    pub(crate) subpasses: Vec<SubpassDesc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Framebuffer {
+    pub(crate) attachments: Vec<ImageView>,
+    pub(crate) layers: image::Layer,
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct GraphicsPipeline {
+    #[derivative(Debug="ignore")]
+    pub(crate) vs: ComPtr<d3d11::ID3D11VertexShader>,
+    #[derivative(Debug="ignore")]
+    pub(crate) hs: Option<ComPtr<d3d11::ID3D11HullShader>>,
+    #[derivative(Debug="ignore")]
+    pub(crate) ds: Option<ComPtr<d3d11::ID3D11DomainShader>>,
+    #[derivative(Debug="ignore")]
+    pub(crate) gs: Option<ComPtr<d3d11::ID3D11GeometryShader>>,
+    #[derivative(Debug="ignore")]
+    pub(crate) ps: Option<ComPtr<d3d11::ID3D11PixelShader>>,
+    #[derivative(Debug="ignore")]
+    pub(crate) input_layout: Option<ComPtr<d3d11::ID3D11InputLayout>>,
+    #[derivative(Debug="ignore")]
+    pub(crate) rasterizer_state: ComPtr<d3d11::ID3D11RasterizerState>,
+    #[derivative(Debug="ignore")]
+    pub(crate) blend_state: ComPtr<d3d11::ID3D11BlendState>,
+    #[derivative(Debug="ignore")]
+    pub(crate) depth_stencil_state: Option<ComPtr<d3d11::ID3D11DepthStencilState>>,
+    pub(crate) topology: d3d11::D3D11_PRIMITIVE_TOPOLOGY,
+    pub(crate) vertex_strides: [UINT; MAX_VERTEX_BUFFERS],
+    pub(crate) baked_states: pso::BakedStates,
+    pub(crate) viewport_count: usize,
+}
+unsafe impl Send for GraphicsPipeline { }
+unsafe impl Sync for GraphicsPipeline { }
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct ComputePipeline {
+    #[derivative(Debug="ignore")]
+    pub(crate) cs: ComPtr<d3d11::ID3D11ComputeShader>,
+}
+unsafe impl Send for ComputePipeline { }
+unsafe impl Sync for ComputePipeline { }
+
+#[derive(Debug)]
+pub struct RayTracingPipeline;
+
+// Command sequences are executed with the plain indirect commands,
+// state arguments are not supported.
+#[derive(Debug)]
+pub struct CommandSignature {
+    pub(crate) command: indirect::Argument,
+    pub(crate) stride: u32,
+}
+
+/// Shader register indices, one for each register type of D3D11.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RegisterData<T> {
+    // Constant buffers (`b#`)
+    pub(crate) c: T,
+    // Shader resource views (`t#`)
+    pub(crate) t: T,
+    // Samplers (`s#`)
+    pub(crate) s: T,
+    // Unordered access views (`u#`)
+    pub(crate) u: T,
+}
+
+#[derive(Debug)]
+pub struct DescriptorSetLayout {
+    pub(crate) bindings: Vec<pso::DescriptorSetLayoutBinding>,
+}
+
+#[derive(Debug)]
+pub struct PipelineLayout {
+    // First shader registers of each binding, for each descriptor set.
+    // The bindings are stored in the order of the descriptor set layout.
+    pub(crate) sets: Vec<Vec<(pso::DescriptorSetLayoutBinding, RegisterData<UINT>)>>,
+}
+
+impl PipelineLayout {
+    /// Look up the registers of a descriptor binding.
+    pub(crate) fn registers(
+        &self, set: usize, binding: pso::DescriptorBinding,
+    ) -> Option<RegisterData<UINT>> {
+        self.sets
+            .get(set)?
+            .iter()
+            .find(|&&(ref layout_binding, _)| layout_binding.binding == binding)
+            .map(|&(_, registers)| registers)
+    }
+}
+
+/// A single descriptor of a descriptor set.
+///
+/// Descriptors don't hold a reference to the underlying views,
+/// the resources need to outlive the usage of the descriptor set.
+#[derive(Clone, Copy, Debug)]
+pub enum Descriptor {
+    Empty,
+    ConstantBuffer {
+        raw: *mut d3d11::ID3D11Buffer,
+        // Range of the bound buffer in units of 16 byte constants.
+        first_constant: UINT,
+        num_constants: UINT,
+    },
+    ShaderResource(*mut d3d11::ID3D11ShaderResourceView),
+    UnorderedAccess(*mut d3d11::ID3D11UnorderedAccessView),
+    StorageBuffer(*mut d3d11::ID3D11ShaderResourceView, *mut d3d11::ID3D11UnorderedAccessView),
+    Sampler(*mut d3d11::ID3D11SamplerState),
+    CombinedImageSampler(*mut d3d11::ID3D11ShaderResourceView, *mut d3d11::ID3D11SamplerState),
+}
+
+#[derive(Debug)]
+pub struct DescriptorSet {
+    pub(crate) bindings: Vec<pso::DescriptorSetLayoutBinding>,
+    // Descriptors for each binding, in the order of `bindings`.
+    pub(crate) descriptors: Mutex<Vec<Vec<Descriptor>>>,
+}
+unsafe impl Send for DescriptorSet { }
+unsafe impl Sync for DescriptorSet { }
+
+impl DescriptorSet {
+    pub(crate) fn new(layout: &DescriptorSetLayout) -> Self {
+        DescriptorSet {
+            bindings: layout.bindings.clone(),
+            descriptors: Mutex::new(layout.bindings
+                .iter()
+                .map(|binding| vec![Descriptor::Empty; binding.count])
+                .collect()),
+        }
+    }
+
+    /// Index of the binding in the set, if existing.
+    pub(crate) fn binding_index(&self, binding: pso::DescriptorBinding) -> Option<usize> {
+        self.bindings.iter().position(|b| b.binding == binding)
+    }
+}
+
+// Descriptor sets are plain host memory, the pool only hands them out.
+#[derive(Debug)]
+pub struct DescriptorPool;
+
+impl HalDescriptorPool<Backend> for DescriptorPool {
+    fn allocate_set(&mut self, layout: &DescriptorSetLayout) -> Result<DescriptorSet, pso::AllocationError> {
+        Ok(DescriptorSet::new(layout))
+    }
+
+    fn reset(&mut self) { }
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct Buffer {
+    #[derivative(Debug="ignore")]
+    pub(crate) raw: ComPtr<d3d11::ID3D11Buffer>,
+    // Raw views, only available for storage buffers.
+    #[derivative(Debug="ignore")]
+    pub(crate) srv: Option<ComPtr<d3d11::ID3D11ShaderResourceView>>,
+    #[derivative(Debug="ignore")]
+    pub(crate) uav: Option<ComPtr<d3d11::ID3D11UnorderedAccessView>>,
+    pub(crate) size: u64,
+    pub(crate) usage: buffer::Usage,
+    // Constant buffers can only be updated as a whole.
+    pub(crate) constant: bool,
+    // Host copy of the buffer contents, null if bound to device local memory.
+    pub(crate) host_ptr: *mut u8,
+}
+unsafe impl Send for Buffer { }
+unsafe impl Sync for Buffer { }
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct BufferView {
+    #[derivative(Debug="ignore")]
+    pub(crate) srv: Option<ComPtr<d3d11::ID3D11ShaderResourceView>>,
+    #[derivative(Debug="ignore")]
+    pub(crate) uav: Option<ComPtr<d3d11::ID3D11UnorderedAccessView>>,
+}
+unsafe impl Send for BufferView { }
+unsafe impl Sync for BufferView { }
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct Image {
+    #[derivative(Debug="ignore")]
+    pub(crate) raw: ComPtr<d3d11::ID3D11Resource>,
+    pub(crate) kind: image::Kind,
+    pub(crate) format: format::Format,
+    // Typed format of the image. The resource itself may be typeless.
+    pub(crate) dxgi_format: DXGI_FORMAT,
+    pub(crate) usage: image::Usage,
+    pub(crate) mip_levels: image::Level,
+}
+unsafe impl Send for Image { }
+unsafe impl Sync for Image { }
+
+impl Image {
+    pub(crate) fn calc_subresource(&self, mip_level: UINT, layer: UINT) -> UINT {
+        mip_level + layer * self.mip_levels as UINT
+    }
+}
+
+#[derive(Derivative, Clone)]
+#[derivative(Debug)]
+pub struct ImageView {
+    #[derivative(Debug="ignore")]
+    pub(crate) srv: Option<ComPtr<d3d11::ID3D11ShaderResourceView>>,
+    #[derivative(Debug="ignore")]
+    pub(crate) rtv: Option<ComPtr<d3d11::ID3D11RenderTargetView>>,
+    #[derivative(Debug="ignore")]
+    pub(crate) dsv: Option<ComPtr<d3d11::ID3D11DepthStencilView>>,
+    #[derivative(Debug="ignore")]
+    pub(crate) uav: Option<ComPtr<d3d11::ID3D11UnorderedAccessView>>,
+}
+unsafe impl Send for ImageView { }
+unsafe impl Sync for ImageView { }
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct Sampler {
+    #[derivative(Debug="ignore")]
+    pub(crate) raw: ComPtr<d3d11::ID3D11SamplerState>,
+}
+unsafe impl Send for Sampler { }
+unsafe impl Sync for Sampler { }
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub(crate) struct BoundBuffer {
+    pub(crate) range: Range<u64>,
+    #[derivative(Debug="ignore")]
+    pub(crate) raw: ComPtr<d3d11::ID3D11Buffer>,
+    pub(crate) constant: bool,
+}
+
+/// Device memory.
+///
+/// D3D11 resources are not placed into explicit heaps, buffers and images are
+/// created on binding instead. Host visible memory keeps a host copy of its contents,
+/// which gets uploaded to the bound buffers on flush and read back on invalidation.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct Memory {
+    pub(crate) properties: memory::Properties,
+    pub(crate) size: u64,
+    #[derivative(Debug="ignore")]
+    pub(crate) host: Option<UnsafeCell<Vec<u8>>>,
+    pub(crate) buffers: Mutex<Vec<BoundBuffer>>,
+}
+unsafe impl Send for Memory { }
+unsafe impl Sync for Memory { }
+
+impl Memory {
+    /// Pointer to the host copy at the given offset, null for device local memory.
+    pub(crate) fn host_ptr(&self, offset: u64) -> *mut u8 {
+        match self.host {
+            Some(ref host) => unsafe { (*host.get()).as_mut_ptr().offset(offset as isize) },
+            None => ptr::null_mut(),
+        }
+    }
+}
+
+pub(crate) enum FenceState {
+    Unsignaled,
+    Signaled,
+    // Event query issued after the last submission associated with the fence.
+    Pending(ComPtr<d3d11::ID3D11Query>),
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct Fence {
+    #[derivative(Debug="ignore")]
+    pub(crate) state: Mutex<FenceState>,
+}
+unsafe impl Send for Fence { }
+unsafe impl Sync for Fence { }
+
+impl Fence {
+    pub(crate) fn new(signaled: bool) -> Self {
+        Fence {
+            state: Mutex::new(if signaled { FenceState::Signaled } else { FenceState::Unsignaled }),
+        }
+    }
+
+    /// Signal the fence once the GPU finished all work submitted to the context so far.
+    pub(crate) fn signal_on(&self, context: &ComPtr<d3d11_1::ID3D11DeviceContext1>) {
+        let device = unsafe {
+            let mut device = ptr::null_mut();
+            context.GetDevice(&mut device);
+            ComPtr::from_raw(device)
+        };
+        let desc = d3d11::D3D11_QUERY_DESC {
+            Query: d3d11::D3D11_QUERY_EVENT,
+            MiscFlags: 0,
+        };
+        let mut query = ptr::null_mut();
+        let hr = unsafe { device.CreateQuery(&desc, &mut query) };
+        if !winerror::SUCCEEDED(hr) {
+            // Fall back to a signaled fence instead of blocking waiters forever.
+            error!("error on event query creation: {:x}", hr);
+            unsafe { context.Flush(); }
+            *self.state.lock().unwrap() = FenceState::Signaled;
+            return;
+        }
+
+        let query = unsafe { ComPtr::<d3d11::ID3D11Query>::from_raw(query) };
+        unsafe { context.End(query.as_raw() as *mut _); }
+        *self.state.lock().unwrap() = FenceState::Pending(query);
+    }
+
+    /// Check if the fence has been signaled, polling the pending query if any.
+    pub(crate) fn is_signaled(&self, context: &ComPtr<d3d11_1::ID3D11DeviceContext1>) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let done = match *state {
+            FenceState::Unsignaled => false,
+            FenceState::Signaled => true,
+            FenceState::Pending(ref query) => unsafe {
+                context.GetData(query.as_raw() as *mut _, ptr::null_mut(), 0, 0) == winerror::S_OK
+            },
+        };
+        if done {
+            *state = FenceState::Signaled;
+        }
+        done
+    }
+}
+
+// Submissions are executed in order on the single queue, semaphores are no-ops.
+#[derive(Debug)]
+pub struct Semaphore;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum QueryKind {
+    Occlusion,
+    // Occlusion query only reporting if any sample passed.
+    BinaryOcclusion,
+    PipelineStatistics(query::PipelineStatistic),
+    Timestamp,
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct QueryPool {
+    pub(crate) kind: QueryKind,
+    #[derivative(Debug="ignore")]
+    pub(crate) queries: Vec<ComPtr<d3d11::ID3D11Query>>,
+    // Timestamps are only valid inside of a disjoint query, which also
+    // provides the frequency. Each timestamp query gets its own one.
+    #[derivative(Debug="ignore")]
+    pub(crate) disjoint: Vec<ComPtr<d3d11::ID3D11Query>>,
+}
+unsafe impl Send for QueryPool { }
+unsafe impl Sync for QueryPool { }
+
+impl HalQueryPool for QueryPool {
+    fn result_stride(&self) -> buffer::Offset {
+        match self.kind {
+            QueryKind::PipelineStatistics(statistics) => statistics.bits().count_ones() as buffer::Offset * 8,
+            _ => 8,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AccelerationStructure;
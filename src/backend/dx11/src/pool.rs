@@ -1,47 +1,46 @@
-use core::{self, pool};
-use core::command::{Buffer, GeneralCommandBuffer, GraphicsCommandBuffer, ComputeCommandBuffer, TransferCommandBuffer, Encoder};
-use core::queue::{GeneralQueue, GraphicsQueue, ComputeQueue, TransferQueue};
-use command::{self, RawCommandBuffer};
-use {Backend, CommandQueue, CommandList, Resources};
+use std::ptr;
 
+use winapi::shared::winerror;
+use winapi::um::d3d11_1;
+use wio::com::ComPtr;
+
+use hal::{command, pool};
+
+use command::CommandBuffer;
+use Backend;
+
+// Each command buffer records into its own deferred context,
+// there is no shared memory to manage in the pool.
+#[derive(Derivative)]
+#[derivative(Debug)]
 pub struct RawCommandPool {
-    command_buffers: Vec<RawCommandBuffer<CommandList>>,
-    next_buffer: usize,
+    #[derivative(Debug="ignore")]
+    pub(crate) device: ComPtr<d3d11_1::ID3D11Device1>,
 }
+unsafe impl Send for RawCommandPool { }
+unsafe impl Sync for RawCommandPool { }
 
 impl pool::RawCommandPool<Backend> for RawCommandPool {
     fn reset(&mut self) {
-        self.next_buffer = 0;
-        for cb in &mut self.command_buffers {
-            cb.reset();
-        }
+        // Command buffers are reset individually on `begin`.
     }
 
-    fn reserve(&mut self, additional: usize) {
-        for _ in 0..additional {
-            self.command_buffers.push(CommandList::new().into());
-        }
+    fn allocate(&mut self, num: usize, _level: command::RawLevel) -> Vec<CommandBuffer> {
+        // Secondary command buffers are recorded into deferred contexts as well.
+        (0 .. num)
+            .map(|_| {
+                let mut context = ptr::null_mut();
+                let hr = unsafe {
+                    self.device.CreateDeferredContext1(0, &mut context as *mut *mut _)
+                };
+                assert!(winerror::SUCCEEDED(hr), "error on deferred context creation: {:x}", hr);
+                CommandBuffer::new(self.device.clone(), unsafe { ComPtr::from_raw(context) })
+            })
+            .collect()
     }
 
-    unsafe fn from_queue<'a, Q>(mut _queue: Q, capacity: usize) -> Self
-        where Q: AsRef<CommandQueue>
-    {
-        let buffers = (0..capacity).map(|_| CommandList::new().into())
-                                    .collect();
-        RawCommandPool {
-            command_buffers: buffers,
-            next_buffer: 0,
-        }
-    }
-
-    unsafe fn acquire_command_buffer(&mut self) -> &mut RawCommandBuffer<CommandList> {
-        let available_buffers = self.command_buffers.len() as isize - self.next_buffer as isize;
-        if available_buffers <= 0 {
-            self.reserve((-available_buffers) as usize + 1);
-        }
-
-        let buffer = &mut self.command_buffers[self.next_buffer];
-        self.next_buffer += 1;
-        buffer
+    unsafe fn free(&mut self, cbufs: Vec<CommandBuffer>) {
+        // Deferred contexts and recorded lists are released on drop.
+        drop(cbufs);
     }
 }
@@ -0,0 +1,250 @@
+use std::{ffi, mem, ptr, slice};
+
+use spirv_cross::{hlsl, spirv, ErrorCode as SpirvErrorCode};
+
+use winapi::shared::winerror;
+use winapi::um::{d3dcommon, d3dcompiler};
+use wio::com::ComPtr;
+
+use hal::{device as d, pso};
+
+use {conv, native as n, Backend as B};
+
+/// Constant buffer register holding the push constants of all stages.
+///
+/// Lower registers are handed out to the uniform buffers of the pipeline layout.
+pub(crate) const PUSH_CONSTANT_REGISTER: u32 = 13;
+/// Size of the push constant buffer in bytes.
+pub(crate) const PUSH_CONSTANT_SIZE: u32 = 256;
+
+/// Emit error during shader module creation. Used if we don't expect an error
+/// but might panic due to an exception in SPIRV-Cross.
+fn gen_unexpected_error(err: SpirvErrorCode) -> d::ShaderError {
+    let msg = match err {
+        SpirvErrorCode::CompilationError(msg) => msg,
+        SpirvErrorCode::Unhandled => "Unexpected error".into(),
+    };
+    d::ShaderError::CompilationFailed(msg)
+}
+
+/// Emit error during shader module creation. Used if we execute an query command.
+fn gen_query_error(err: SpirvErrorCode) -> d::ShaderError {
+    let msg = match err {
+        SpirvErrorCode::CompilationError(msg) => msg,
+        SpirvErrorCode::Unhandled => "Unknown query error".into(),
+    };
+    d::ShaderError::CompilationFailed(msg)
+}
+
+/// Compile a single shader entry point from a HLSL text shader.
+pub(crate) fn compile_hlsl(
+    stage: pso::Stage,
+    entry: &str,
+    code: &[u8],
+) -> Result<ComPtr<d3dcommon::ID3DBlob>, d::ShaderError> {
+    let target = match stage {
+        pso::Stage::Vertex => "vs_5_0\0",
+        pso::Stage::Hull => "hs_5_0\0",
+        pso::Stage::Domain => "ds_5_0\0",
+        pso::Stage::Geometry => "gs_5_0\0",
+        pso::Stage::Fragment => "ps_5_0\0",
+        pso::Stage::Compute => "cs_5_0\0",
+    };
+
+    let mut blob = ptr::null_mut();
+    let mut error = ptr::null_mut();
+    let entry = ffi::CString::new(entry).unwrap();
+    let hr = unsafe {
+        d3dcompiler::D3DCompile(
+            code.as_ptr() as *const _,
+            code.len(),
+            ptr::null(),
+            ptr::null(),
+            ptr::null_mut(),
+            entry.as_ptr() as *const _,
+            target.as_ptr() as *const i8,
+            1,
+            0,
+            &mut blob as *mut *mut _,
+            &mut error as *mut *mut _)
+    };
+    if !winerror::SUCCEEDED(hr) {
+        error!("D3DCompile error {:x}", hr);
+        let error = unsafe { ComPtr::<d3dcommon::ID3DBlob>::from_raw(error) };
+        let message = unsafe {
+            let pointer = error.GetBufferPointer();
+            let size = error.GetBufferSize();
+            let slice = slice::from_raw_parts(pointer as *const u8, size as usize);
+            String::from_utf8_lossy(slice).into_owned()
+        };
+        Err(d::ShaderError::CompilationFailed(message))
+    } else {
+        Ok(unsafe { ComPtr::from_raw(blob) })
+    }
+}
+
+fn parse_spirv(raw_data: &[u8]) -> Result<spirv::Ast<hlsl::Target>, d::ShaderError> {
+    // spec requires "codeSize must be a multiple of 4"
+    assert_eq!(raw_data.len() & 3, 0);
+
+    let module = spirv::Module::from_words(unsafe {
+        slice::from_raw_parts(
+            raw_data.as_ptr() as *const u32,
+            raw_data.len() / mem::size_of::<u32>(),
+        )
+    });
+
+    spirv::Ast::parse(&module)
+        .map_err(|err| {
+            let msg =  match err {
+                SpirvErrorCode::CompilationError(msg) => msg,
+                SpirvErrorCode::Unhandled => "Unknown parsing error".into(),
+            };
+            d::ShaderError::CompilationFailed(msg)
+        })
+}
+
+/// Register types a SPIR-V resource may be bound to.
+#[derive(Clone, Copy)]
+enum Register {
+    C,
+    T,
+    S,
+    U,
+}
+
+fn patch_spirv_resources(
+    ast: &mut spirv::Ast<hlsl::Target>,
+    layout: &n::PipelineLayout,
+) -> Result<(), d::ShaderError> {
+    // Shader model 5.0 has no register spaces, all descriptor sets are flattened
+    // into the registers assigned by the pipeline layout.
+    let shader_resources = ast.get_shader_resources().map_err(gen_query_error)?;
+    let resources = shader_resources.uniform_buffers.iter().map(|res| (res, Register::C))
+        // Read-only storage buffers are translated to SRVs, the pipeline layout
+        // assigns matching `t` and `u` registers to storage buffers.
+        .chain(shader_resources.storage_buffers.iter().map(|res| (res, Register::U)))
+        .chain(shader_resources.storage_images.iter().map(|res| (res, Register::U)))
+        .chain(shader_resources.sampled_images.iter().map(|res| (res, Register::T)))
+        .chain(shader_resources.separate_images.iter().map(|res| (res, Register::T)))
+        .chain(shader_resources.separate_samplers.iter().map(|res| (res, Register::S)));
+
+    for (resource, register) in resources {
+        let set = ast.get_decoration(resource.id, spirv::Decoration::DescriptorSet).map_err(gen_query_error)?;
+        let binding = ast.get_decoration(resource.id, spirv::Decoration::Binding).map_err(gen_query_error)?;
+        let registers = layout
+            .registers(set as usize, binding)
+            .ok_or_else(|| d::ShaderError::InterfaceMismatch(
+                format!("Resource binding (set {}, binding {}) is missing in the pipeline layout", set, binding)
+            ))?;
+        let index = match register {
+            Register::C => registers.c,
+            Register::T => registers.t,
+            Register::S => registers.s,
+            Register::U => registers.u,
+        };
+
+        ast.set_decoration(resource.id, spirv::Decoration::DescriptorSet, 0)
+            .map_err(gen_unexpected_error)?;
+        ast.set_decoration(resource.id, spirv::Decoration::Binding, index)
+            .map_err(gen_unexpected_error)?;
+    }
+
+    Ok(())
+}
+
+fn translate_spirv(
+    ast: &mut spirv::Ast<hlsl::Target>,
+) -> Result<String, d::ShaderError> {
+    let mut compile_options = hlsl::CompilerOptions::default();
+    compile_options.shader_model = hlsl::ShaderModel::V5_0;
+    compile_options.vertex.invert_y = true;
+
+    // Push constants of all stages share a single constant buffer.
+    let root_constant_layout = vec![
+        hlsl::RootConstant {
+            start: 0,
+            end: PUSH_CONSTANT_SIZE,
+            binding: PUSH_CONSTANT_REGISTER,
+            space: 0,
+        },
+    ];
+    ast.set_compiler_options(&compile_options)
+        .map_err(gen_unexpected_error)?;
+    ast.set_root_constant_layout(root_constant_layout)
+        .map_err(gen_unexpected_error)?;
+    ast.compile()
+        .map_err(|err| {
+            let msg =  match err {
+                SpirvErrorCode::CompilationError(msg) => msg,
+                SpirvErrorCode::Unhandled => "Unknown compile error".into(),
+            };
+            d::ShaderError::CompilationFailed(msg)
+        })
+}
+
+/// Extract entry point from shader module on pipeline creation.
+///
+/// SPIR-V modules are translated to HLSL and compiled, as the shader registers
+/// depend on the pipeline layout.
+pub(crate) fn compile_entry_point(
+    stage: pso::Stage,
+    source: &pso::EntryPoint<B>,
+    layout: &n::PipelineLayout,
+) -> Result<ComPtr<d3dcommon::ID3DBlob>, d::ShaderError> {
+    match *source.module {
+        n::ShaderModule::Compiled(ref shaders) => {
+            // Use precompiled shader, ignore specialization or layout.
+            shaders
+                .get(source.entry)
+                .cloned()
+                .ok_or(d::ShaderError::MissingEntryPoint(source.entry.into()))
+        }
+        n::ShaderModule::Spirv(ref raw_data) => {
+            let mut ast = parse_spirv(raw_data)?;
+            let spec_constants = ast
+                .get_specialization_constants()
+                .map_err(gen_query_error)?;
+
+            for spec_constant in spec_constants {
+                if let Some(constant) = source
+                    .specialization
+                    .iter()
+                    .find(|c| c.id == spec_constant.constant_id)
+                {
+                    // Override specialization constant values
+                    unsafe {
+                        let value = match constant.value {
+                            pso::Constant::Bool(v) => v as u64,
+                            pso::Constant::U32(v) => v as u64,
+                            pso::Constant::U64(v) => v,
+                            pso::Constant::I32(v) => *(&v as *const _ as *const u64),
+                            pso::Constant::I64(v) => *(&v as *const _ as *const u64),
+                            pso::Constant::F32(v) => *(&v as *const _ as *const u64),
+                            pso::Constant::F64(v) => *(&v as *const _ as *const u64),
+                        };
+                        ast.set_scalar_constant(spec_constant.id, value).map_err(gen_query_error)?;
+                    }
+                }
+            }
+
+            patch_spirv_resources(&mut ast, layout)?;
+            let shader_code = translate_spirv(&mut ast)?;
+            debug!("SPIRV-Cross generated shader:\n{}", shader_code);
+
+            let real_name = ast
+                .get_cleansed_entry_point_name(source.entry, conv::map_stage(stage))
+                .map_err(gen_query_error)?;
+            // TODO: opt: don't query *all* entry points.
+            let entry_points = ast.get_entry_points().map_err(gen_query_error)?;
+            entry_points
+                .iter()
+                .find(|entry_point| entry_point.name == real_name)
+                .ok_or(d::ShaderError::MissingEntryPoint(source.entry.into()))
+                .and_then(|entry_point| {
+                    let stage = conv::map_execution_model(entry_point.execution_model);
+                    compile_hlsl(stage, &entry_point.name, shader_code.as_bytes())
+                })
+        }
+    }
+}
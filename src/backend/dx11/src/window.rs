@@ -0,0 +1,117 @@
+use std::mem;
+
+#[cfg(feature = "winit")]
+use winit;
+
+use winapi::shared::dxgi;
+use winapi::shared::windef::{HWND, RECT};
+use winapi::um::winuser::GetClientRect;
+use wio::com::ComPtr;
+
+use hal::{self, format as f, image as i};
+use {native as n, Backend, Instance, PhysicalDevice, QueueFamily};
+
+use std::os::raw::c_void;
+
+impl Instance {
+    pub fn create_surface_from_hwnd(&self, hwnd: *mut c_void) -> Surface {
+        let (width, height) = unsafe {
+            let mut rect: RECT = mem::zeroed();
+            if GetClientRect(hwnd as *mut _, &mut rect as *mut RECT) == 0 {
+                panic!("GetClientRect failed");
+            }
+            ((rect.right - rect.left) as u32, (rect.bottom - rect.top) as u32)
+        };
+
+        Surface {
+            factory: self.factory.clone(),
+            wnd_handle: hwnd as *mut _,
+            width: width,
+            height: height,
+        }
+    }
+
+    /// Create a surface from the raw handle of a native window.
+    ///
+    /// Panics if the handle isn't a Win32 window handle.
+    pub fn create_surface_from_raw_handle<W: hal::HasRawWindowHandle>(&self, window: &W) -> Surface {
+        match window.raw_window_handle() {
+            hal::RawWindowHandle::Windows { hwnd, .. } => self.create_surface_from_hwnd(hwnd),
+            handle => panic!("Unsupported window handle: {:?}", handle),
+        }
+    }
+
+    #[cfg(feature = "winit")]
+    pub fn create_surface(&self, window: &winit::Window) -> Surface {
+        use winit::os::windows::WindowExt;
+        self.create_surface_from_hwnd(window.get_hwnd() as *mut _)
+    }
+}
+
+pub struct Surface {
+    pub(crate) factory: ComPtr<dxgi::IDXGIFactory1>,
+    pub(crate) wnd_handle: HWND,
+    pub(crate) width: i::Size,
+    pub(crate) height: i::Size,
+}
+
+unsafe impl Send for Surface { }
+unsafe impl Sync for Surface { }
+
+impl hal::Surface<Backend> for Surface {
+    fn supports_queue_family(&self, _queue_family: &QueueFamily) -> bool {
+        true
+    }
+
+    fn kind(&self) -> i::Kind {
+        i::Kind::D2(self.width, self.height, 1, 1)
+    }
+
+    fn capabilities_and_formats(
+        &self, _: &PhysicalDevice,
+    ) -> (hal::SurfaceCapabilities, Option<Vec<f::Format>>) {
+        let extent = hal::window::Extent2D {
+            width: self.width,
+            height: self.height,
+        };
+
+        // The discard swap effect only exposes the first buffer,
+        // a single image is handed out to the user.
+        let capabilities = hal::SurfaceCapabilities {
+            image_count: 1..2,
+            current_extent: Some(extent),
+            extents: extent..extent,
+            max_image_layers: 1,
+        };
+
+        // Roughly ordered by popularity..
+        let formats = vec![
+            f::Format::Bgra8Srgb,
+            f::Format::Bgra8Unorm,
+            f::Format::Rgba8Srgb,
+            f::Format::Rgba8Unorm,
+            f::Format::A2b10g10r10Unorm,
+            f::Format::Rgba16Float,
+        ];
+
+        (capabilities, Some(formats))
+    }
+}
+
+pub struct Swapchain {
+    pub(crate) inner: ComPtr<dxgi::IDXGISwapChain>,
+}
+
+impl hal::Swapchain<Backend> for Swapchain {
+    fn acquire_frame(&mut self, sync: hal::FrameSync<Backend>) -> hal::Frame {
+        // Submissions are executed in order, the back buffer is always
+        // available once the previous frame has been presented.
+        if let hal::FrameSync::Fence(fence) = sync {
+            *fence.state.lock().unwrap() = n::FenceState::Signaled;
+        }
+        hal::Frame::new(0)
+    }
+}
+
+unsafe impl Send for Swapchain { }
+unsafe impl Sync for Swapchain { }
@@ -0,0 +1,278 @@
+//! Measures the CPU overhead of recording draw calls.
+//!
+//! Records 10k draws once with all state bound upfront, once with the
+//! pipeline, vertex buffers, viewport and scissor rebound before every draw,
+//! and with either the full push constant range or a single push constant
+//! updated before every draw. Command buffers are only recorded, never submitted.
+//!
+//! Backend changes are compared against a baseline measured before the change:
+//!
+//! ```text
+//! # on the commit before the change
+//! cargo run --release --example draw_stress -- --save-baseline before.txt
+//! # on the commit with the change
+//! cargo run --release --example draw_stress -- --baseline before.txt
+//! ```
+
+extern crate gfx_backend_dx12 as back;
+extern crate gfx_hal as hal;
+
+use std::{env, iter, mem};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+
+use hal::{buffer, command, format, image, pass, pso, pool};
+use hal::{Backend, Device, Graphics, Instance, MemoryTypeId, PhysicalDevice};
+use hal::command::RawCommandBuffer;
+use hal::pool::RawCommandPool;
+
+const NUM_DRAWS: u32 = 10_000;
+const NUM_CONSTANTS: u32 = 16;
+// Each scenario is recorded several times, the fastest recording is reported.
+const NUM_RUNS: usize = 5;
+
+const SHADER: &str = "
+float4 vs_main(float2 pos : TEXCOORD0) : SV_Position {
+    return float4(pos, 0.0, 1.0);
+}
+
+float4 ps_main() : SV_Target {
+    return float4(1.0, 1.0, 1.0, 1.0);
+}
+";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Scenario {
+    StaticState,
+    ReboundState,
+    FullConstants,
+    SingleConstant,
+}
+
+impl Scenario {
+    const ALL: &'static [Scenario] = &[
+        Scenario::StaticState,
+        Scenario::ReboundState,
+        Scenario::FullConstants,
+        Scenario::SingleConstant,
+    ];
+
+    fn name(&self) -> &'static str {
+        match *self {
+            Scenario::StaticState => "state bound once",
+            Scenario::ReboundState => "state bound every draw",
+            Scenario::FullConstants => "full push constant range",
+            Scenario::SingleConstant => "single push constant",
+        }
+    }
+}
+
+fn nanos_per_draw(duration: Duration) -> f64 {
+    (duration.as_secs() as f64 * 1e9 + duration.subsec_nanos() as f64) / NUM_DRAWS as f64
+}
+
+/// Read the results of a previous run, one `name=ns` line per scenario.
+fn load_baseline(path: &str) -> Vec<(String, f64)> {
+    let mut text = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut text))
+        .expect("Unable to read the baseline");
+    text.lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, '=');
+            let name = parts.next()?;
+            let nanos = parts.next()?.parse().ok()?;
+            Some((name.to_owned(), nanos))
+        })
+        .collect()
+}
+
+fn save_baseline(path: &str, results: &[(Scenario, f64)]) {
+    let mut file = File::create(path).expect("Unable to create the baseline");
+    for &(scenario, nanos) in results {
+        writeln!(file, "{}={}", scenario.name(), nanos).unwrap();
+    }
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let (mut save, mut baseline) = (None, None);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--save-baseline" => save = args.next(),
+            "--baseline" => baseline = args.next(),
+            _ => panic!("Usage: draw_stress [--save-baseline <file>] [--baseline <file>]"),
+        }
+    }
+
+    let instance = back::Instance::create("gfx-rs draw stress", 1);
+    let adapter = instance.enumerate_adapters().remove(0);
+    let memory_types = adapter.physical_device.memory_properties().memory_types;
+    let (device, queue_group) = adapter
+        .open_with::<_, Graphics>(1, |_family| true)
+        .unwrap();
+
+    let vs_module = device
+        .create_shader_module_from_source(pso::Stage::Vertex, "vs_main", "main", SHADER.as_bytes())
+        .unwrap();
+    let fs_module = device
+        .create_shader_module_from_source(pso::Stage::Fragment, "ps_main", "main", SHADER.as_bytes())
+        .unwrap();
+
+    let render_pass = device.create_render_pass(
+        &[pass::Attachment {
+            format: Some(format::Format::Rgba8Unorm),
+            ops: pass::AttachmentOps::new(pass::AttachmentLoadOp::Clear, pass::AttachmentStoreOp::Store),
+            stencil_ops: pass::AttachmentOps::DONT_CARE,
+            layouts: image::Layout::Undefined .. image::Layout::ColorAttachmentOptimal,
+        }],
+        &[pass::SubpassDesc {
+            colors: &[(0, image::Layout::ColorAttachmentOptimal)],
+            depth_stencil: None,
+            inputs: &[],
+            preserves: &[],
+            view_mask: 0,
+            correlation_mask: 0,
+        }],
+        iter::empty::<pass::SubpassDependency>(),
+    );
+    let pipeline_layout = device.create_pipeline_layout(
+        iter::empty::<<back::Backend as Backend>::DescriptorSetLayout>(),
+        &[(pso::ShaderStageFlags::VERTEX, 0 .. NUM_CONSTANTS)],
+    );
+
+    let pipeline = {
+        let shaders = pso::GraphicsShaderSet {
+            vertex: pso::EntryPoint { entry: "main", module: &vs_module, specialization: &[] },
+            hull: None,
+            domain: None,
+            geometry: None,
+            fragment: Some(pso::EntryPoint { entry: "main", module: &fs_module, specialization: &[] }),
+        };
+        let mut desc = pso::GraphicsPipelineDesc::new(
+            shaders,
+            pso::Primitive::TriangleList,
+            pso::Rasterizer::FILL,
+            &pipeline_layout,
+            pass::Subpass { index: 0, main_pass: &render_pass },
+        );
+        desc.blender.targets.push(pso::ColorBlendDesc(pso::ColorMask::ALL, pso::BlendState::Off));
+        desc.vertex_buffers.push(pso::VertexBufferDesc {
+            stride: mem::size_of::<[f32; 2]>() as u32,
+            rate: 0,
+        });
+        desc.attributes.push(pso::AttributeDesc {
+            location: 0,
+            binding: 0,
+            element: pso::Element {
+                format: format::Format::Rg32Float,
+                offset: 0,
+            },
+        });
+        device.create_graphics_pipeline(&desc).unwrap()
+    };
+
+    let buffer_size = 3 * mem::size_of::<[f32; 2]>() as u64;
+    let unbound = device.create_buffer(buffer_size, buffer::Usage::VERTEX).unwrap();
+    let requirements = device.get_buffer_requirements(&unbound);
+    let memory_type = (0 .. memory_types.len())
+        .find(|id| requirements.type_mask & (1 << id) != 0)
+        .map(MemoryTypeId)
+        .unwrap();
+    let memory = device.allocate_memory(memory_type, requirements.size).unwrap();
+    let vertex_buffer = device.bind_buffer_memory(&memory, 0, unbound).unwrap();
+
+    let viewport = pso::Viewport {
+        rect: pso::Rect { x: 0, y: 0, w: 256, h: 256 },
+        depth: 0.0 .. 1.0,
+    };
+
+    let mut command_pool = device.create_command_pool(queue_group.family(), pool::CommandPoolCreateFlags::empty());
+    let mut cmd_buffer = command_pool.allocate(1, command::RawLevel::Primary).remove(0);
+
+    let results = {
+        let mut record = |scenario: Scenario| {
+            cmd_buffer.begin(command::CommandBufferFlags::ONE_TIME_SUBMIT, Default::default());
+            cmd_buffer.bind_graphics_pipeline(&pipeline);
+            cmd_buffer.bind_vertex_buffers(pso::VertexBufferSet(vec![(&vertex_buffer, 0)]));
+            cmd_buffer.set_viewports(0, &[viewport.clone()]);
+            cmd_buffer.set_scissors(0, &[viewport.rect]);
+            cmd_buffer.push_graphics_constants(
+                &pipeline_layout,
+                pso::ShaderStageFlags::VERTEX,
+                0,
+                &[0; NUM_CONSTANTS as usize],
+            );
+
+            let start = Instant::now();
+            for i in 0 .. NUM_DRAWS {
+                match scenario {
+                    Scenario::StaticState => {}
+                    Scenario::ReboundState => {
+                        cmd_buffer.bind_graphics_pipeline(&pipeline);
+                        cmd_buffer.bind_vertex_buffers(pso::VertexBufferSet(vec![(&vertex_buffer, 0)]));
+                        cmd_buffer.set_viewports(0, &[viewport.clone()]);
+                        cmd_buffer.set_scissors(0, &[viewport.rect]);
+                    }
+                    Scenario::FullConstants => cmd_buffer.push_graphics_constants(
+                        &pipeline_layout,
+                        pso::ShaderStageFlags::VERTEX,
+                        0,
+                        &[i; NUM_CONSTANTS as usize],
+                    ),
+                    Scenario::SingleConstant => cmd_buffer.push_graphics_constants(
+                        &pipeline_layout,
+                        pso::ShaderStageFlags::VERTEX,
+                        i % NUM_CONSTANTS,
+                        &[i],
+                    ),
+                }
+                cmd_buffer.draw(0 .. 3, 0 .. 1);
+            }
+            let elapsed = start.elapsed();
+
+            cmd_buffer.finish();
+            elapsed
+        };
+
+        Scenario::ALL
+            .iter()
+            .map(|&scenario| {
+                let fastest = (0 .. NUM_RUNS)
+                    .map(|_| nanos_per_draw(record(scenario)))
+                    .fold(std::f64::INFINITY, f64::min);
+                (scenario, fastest)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    println!("{} draws, fastest of {} runs", NUM_DRAWS, NUM_RUNS);
+    let baseline = baseline.map(|path| load_baseline(&path));
+    for &(scenario, nanos) in &results {
+        let before = baseline
+            .as_ref()
+            .and_then(|baseline| baseline.iter().find(|&&(ref name, _)| name == scenario.name()))
+            .map(|&(_, before)| before);
+        match before {
+            Some(before) => println!(
+                "{:<28} {:>8.1} ns/draw, before {:>8.1} ns/draw ({:+.1}%)",
+                scenario.name(), nanos, before, (nanos - before) / before * 100.0,
+            ),
+            None => println!("{:<28} {:>8.1} ns/draw", scenario.name(), nanos),
+        }
+    }
+    if let Some(path) = save {
+        save_baseline(&path, &results);
+    }
+
+    unsafe { command_pool.free(vec![cmd_buffer]); }
+    device.destroy_command_pool(command_pool);
+    device.destroy_buffer(vertex_buffer);
+    device.free_memory(memory);
+    device.destroy_graphics_pipeline(pipeline);
+    device.destroy_pipeline_layout(pipeline_layout);
+    device.destroy_render_pass(render_pass);
+    device.destroy_shader_module(vs_module);
+    device.destroy_shader_module(fs_module);
+}
@@ -0,0 +1,133 @@
+//! Programmatic GPU captures with PIX.
+//!
+//! PIX hooks D3D12 via `WinPixGpuCapturer.dll`, which has to be loaded before
+//! the device is created. The library is either injected by PIX or loaded by us
+//! from the library search path, e.g. when placed next to the executable.
+
+use std::ffi::OsStr;
+use std::os::windows::ffi::OsStrExt;
+use std::mem;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use winapi::shared::minwindef::{HMODULE, UINT};
+use winapi::shared::winerror;
+use winapi::um::libloaderapi;
+use winapi::um::winnt::{HRESULT, PCWSTR};
+
+use hal::error::CaptureError;
+
+const LIBRARY_NAME: &'static str = "WinPixGpuCapturer.dll";
+// Number of frames captured by `trigger_capture`.
+const TRIGGER_FRAME_COUNT: UINT = 1;
+
+// `PIXCaptureParameters::GpuCaptureParameters`
+#[repr(C)]
+struct GpuCaptureParameters {
+    file_name: PCWSTR,
+}
+
+type BeginCaptureFn = unsafe extern "system" fn(*const GpuCaptureParameters) -> HRESULT;
+type EndCaptureFn = unsafe extern "system" fn() -> HRESULT;
+type CaptureNextFrameFn = unsafe extern "system" fn(PCWSTR, UINT) -> HRESULT;
+
+fn to_wide(s: &str) -> Vec<u16> {
+    OsStr::new(s).encode_wide().chain(Some(0)).collect()
+}
+
+pub struct Pix {
+    begin_capture: BeginCaptureFn,
+    end_capture: EndCaptureFn,
+    capture_next_frame: CaptureNextFrameFn,
+    // Index of the next capture file.
+    next_capture: AtomicUsize,
+    // File name of the active capture, which must stay valid until the capture ends.
+    active_file: Mutex<Option<Vec<u16>>>,
+}
+
+impl Pix {
+    /// Load the PIX capturer library, if available.
+    pub fn load() -> Option<Self> {
+        let name = to_wide(LIBRARY_NAME);
+        let module = unsafe {
+            let module = libloaderapi::GetModuleHandleW(name.as_ptr());
+            if module.is_null() {
+                libloaderapi::LoadLibraryW(name.as_ptr())
+            } else {
+                module
+            }
+        };
+        if module.is_null() {
+            return None;
+        }
+
+        unsafe fn symbol(module: HMODULE, name: &[u8]) -> Option<*const ()> {
+            let address = libloaderapi::GetProcAddress(module, name.as_ptr() as *const _);
+            if address.is_null() {
+                None
+            } else {
+                Some(address as *const ())
+            }
+        }
+
+        let pix = unsafe {
+            Pix {
+                begin_capture: mem::transmute(symbol(module, b"BeginProgrammaticGpuCapture\0")?),
+                end_capture: mem::transmute(symbol(module, b"EndProgrammaticCapture\0")?),
+                capture_next_frame: mem::transmute(symbol(module, b"CaptureNextFrame\0")?),
+                next_capture: AtomicUsize::new(0),
+                active_file: Mutex::new(None),
+            }
+        };
+        info!("PIX capturer is loaded, GPU captures are available");
+        Some(pix)
+    }
+
+    // Captures are written to the working directory.
+    fn next_file_name(&self) -> Vec<u16> {
+        let index = self.next_capture.fetch_add(1, Ordering::Relaxed);
+        to_wide(&format!("gfx-capture-{}.wpix", index))
+    }
+
+    pub fn trigger_capture(&self) -> Result<(), CaptureError> {
+        let file_name = self.next_file_name();
+        let hr = unsafe { (self.capture_next_frame)(file_name.as_ptr(), TRIGGER_FRAME_COUNT) };
+        if winerror::SUCCEEDED(hr) {
+            Ok(())
+        } else {
+            error!("PIX failed to capture the next frame: {:x}", hr);
+            Err(CaptureError::Failed)
+        }
+    }
+
+    pub fn begin_capture(&self) -> Result<(), CaptureError> {
+        let mut active_file = self.active_file.lock().unwrap();
+        if active_file.is_some() {
+            error!("A GPU capture is already in progress");
+            return Err(CaptureError::Failed);
+        }
+        let file_name = self.next_file_name();
+        let params = GpuCaptureParameters {
+            file_name: file_name.as_ptr(),
+        };
+        let hr = unsafe { (self.begin_capture)(&params) };
+        if winerror::SUCCEEDED(hr) {
+            *active_file = Some(file_name);
+            Ok(())
+        } else {
+            error!("PIX failed to begin the capture: {:x}", hr);
+            Err(CaptureError::Failed)
+        }
+    }
+
+    pub fn end_capture(&self) -> Result<(), CaptureError> {
+        let hr = unsafe { (self.end_capture)() };
+        self.active_file.lock().unwrap().take();
+        if winerror::SUCCEEDED(hr) {
+            Ok(())
+        } else {
+            error!("PIX failed to end the capture: {:x}", hr);
+            Err(CaptureError::Failed)
+        }
+    }
+}
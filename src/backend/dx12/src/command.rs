@@ -1,25 +1,31 @@
 
-use hal::{buffer, command as com, image, memory, pass, pso, query};
+use hal::{accel_struct, buffer, command as com, image, memory, pass, pso, query};
 use hal::{IndexCount, IndexType, InstanceCount, VertexCount, VertexOffset, WorkGroupCount};
 use hal::format::Aspects;
+use hal::queue::QueueFamilyId;
+use hal::QueryPool as HalQueryPool;
 
-use std::{cmp, iter, mem, ptr};
+use std::{cmp, fmt, iter, mem, ptr};
 use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::error::Error;
 use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use winapi::um::d3d12;
 use winapi::shared::minwindef::{FALSE, UINT};
-use winapi::shared::dxgiformat;
+use winapi::shared::{dxgiformat, dxgitype};
+use winapi::shared::winerror::SUCCEEDED;
+use winapi::Interface;
 
 use wio::com::ComPtr;
 
-use {conv, native as n, Backend, CmdSignatures, MAX_VERTEX_BUFFERS};
+use {conv, device, ext, native as n, Backend, CmdSignatures, ShaderVisibleHeaps, MAX_VERTEX_BUFFERS, ROOT_SIGNATURE_SIZE};
+use pool::AllocatorState;
 use root_constants::RootConstant;
-use smallvec::SmallVec;
+use smallvec::{Array, SmallVec};
 
-// Fixed size of the root signature.
-// Limited by D3D12.
-const ROOT_SIGNATURE_SIZE: usize = 64;
 
 const NULL_VERTEX_BUFFER_VIEW: d3d12::D3D12_VERTEX_BUFFER_VIEW =
     d3d12::D3D12_VERTEX_BUFFER_VIEW {
@@ -28,6 +34,18 @@ const NULL_VERTEX_BUFFER_VIEW: d3d12::D3D12_VERTEX_BUFFER_VIEW =
         StrideInBytes: 0,
     };
 
+// States which allow writes to the resource, these can't be combined with other states.
+const WRITE_STATES: d3d12::D3D12_RESOURCE_STATES =
+    d3d12::D3D12_RESOURCE_STATE_RENDER_TARGET |
+    d3d12::D3D12_RESOURCE_STATE_UNORDERED_ACCESS |
+    d3d12::D3D12_RESOURCE_STATE_DEPTH_WRITE |
+    d3d12::D3D12_RESOURCE_STATE_STREAM_OUT |
+    d3d12::D3D12_RESOURCE_STATE_COPY_DEST |
+    d3d12::D3D12_RESOURCE_STATE_RESOLVE_DEST;
+
+// Maximum number of viewports and scissors, also the maximum number of rects per clear.
+const MAX_VIEWPORTS: usize = d3d12::D3D12_VIEWPORT_AND_SCISSORRECT_OBJECT_COUNT_PER_PIPELINE as usize;
+
 fn get_rect(rect: &pso::Rect) -> d3d12::D3D12_RECT {
     d3d12::D3D12_RECT {
         left: rect.x as i32,
@@ -37,34 +55,108 @@ fn get_rect(rect: &pso::Rect) -> d3d12::D3D12_RECT {
     }
 }
 
+/// Intersection of two rects, `None` if they don't overlap.
+fn intersect_rect(a: &d3d12::D3D12_RECT, b: &d3d12::D3D12_RECT) -> Option<d3d12::D3D12_RECT> {
+    let rect = d3d12::D3D12_RECT {
+        left: cmp::max(a.left, b.left),
+        top: cmp::max(a.top, b.top),
+        right: cmp::min(a.right, b.right),
+        bottom: cmp::min(a.bottom, b.bottom),
+    };
+    if rect.left < rect.right && rect.top < rect.bottom {
+        Some(rect)
+    } else {
+        None
+    }
+}
+
+/// Clear rects of an attachment view, in batches of up to `MAX_VIEWPORTS` rects.
+///
+/// Rects covering only some layers of an array view are cleared through the
+/// single layer views of these layers.
+fn clear_view_rects<F>(
+    handle: d3d12::D3D12_CPU_DESCRIPTOR_HANDLE,
+    layer_handles: &[d3d12::D3D12_CPU_DESCRIPTOR_HANDLE],
+    rects: &[(d3d12::D3D12_RECT, Range<image::Layer>)],
+    clear: F,
+) where
+    F: Fn(d3d12::D3D12_CPU_DESCRIPTOR_HANDLE, &[d3d12::D3D12_RECT]),
+{
+    let num_layers = cmp::max(layer_handles.len(), 1) as image::Layer;
+    let covers_view = |layers: &Range<image::Layer>| layers.start == 0 && layers.end >= num_layers;
+
+    let full_rects = rects
+        .iter()
+        .filter(|&&(_, ref layers)| covers_view(layers))
+        .map(|&(rect, _)| rect)
+        .collect::<SmallVec<[_; MAX_VIEWPORTS]>>();
+    for chunk in full_rects.chunks(MAX_VIEWPORTS) {
+        clear(handle, chunk);
+    }
+
+    for &(rect, ref layers) in rects {
+        if covers_view(layers) {
+            continue;
+        }
+        for layer in layers.start .. cmp::min(layers.end, num_layers) {
+            clear(layer_handles[layer as usize], &[rect]);
+        }
+    }
+}
+
+/// Discard the slots of the cache beyond `count`, returns `true` if the cache changed.
+fn truncate_cache<A: Array>(cache: &mut SmallVec<A>, count: usize) -> bool {
+    if cache.len() > count {
+        cache.truncate(count);
+        true
+    } else {
+        false
+    }
+}
+
+/// Write `values` into the cache, starting at slot `first`.
+///
+/// Slots between the current end of the cache and `first` are filled with `default`,
+/// as D3D12 only allows setting all viewports or scissors at once.
+fn update_cache<A, I>(cache: &mut SmallVec<A>, first: usize, values: I, default: A::Item)
+where
+    A: Array,
+    A::Item: Copy,
+    I: IntoIterator<Item = A::Item>,
+{
+    for (i, value) in values.into_iter().enumerate() {
+        let slot = first + i;
+        while cache.len() <= slot {
+            cache.push(default);
+        }
+        cache[slot] = value;
+    }
+}
+
 fn div(a: u32, b: u32) -> u32 {
     assert_eq!(a % b, 0);
     a / b
 }
 
+fn div_ceil(a: u32, b: u32) -> u32 {
+    (a + b - 1) / b
+}
+
 fn bind_descriptor_sets<'a, T>(
-    raw: &ComPtr<d3d12::ID3D12GraphicsCommandList>,
+    heaps: &ShaderVisibleHeaps,
     pipeline: &mut PipelineCache,
     layout: &n::PipelineLayout,
     first_set: usize,
     sets: T,
+    tracked: &mut Vec<n::TrackedDescriptor>,
 ) where
     T: IntoIterator,
     T::Item: Borrow<n::DescriptorSet>,
 {
     let mut sets = sets.into_iter().peekable();
     let (srv_cbv_uav_start, sampler_start) = if let Some(set_0) = sets.peek().map(Borrow::borrow) {
-        // Bind descriptor heaps
-        unsafe {
-            // TODO: Can we bind them always or only once?
-            //       Resize while recording?
-            let mut heaps = [
-                set_0.heap_srv_cbv_uav.as_raw(),
-                set_0.heap_samplers.as_raw(),
-            ];
-            raw.SetDescriptorHeaps(2, heaps.as_mut_ptr())
-        }
-
+        // Descriptor heaps are bound once on `begin`, switching heaps
+        // while recording may flush the pipeline.
         (set_0.srv_cbv_uav_gpu_start().ptr, set_0.sampler_gpu_start().ptr)
     } else {
         return
@@ -74,13 +166,14 @@ fn bind_descriptor_sets<'a, T>(
     pipeline.sampler_start = sampler_start;
 
     let mut table_id = 0;
-    for table in &layout.tables[..first_set] {
+    for (table, root_descriptors) in layout.tables[..first_set].iter().zip(&layout.root_descriptors) {
         if table.contains(n::SRV_CBV_UAV) {
             table_id += 1;
         }
         if table.contains(n::SAMPLERS) {
             table_id += 1;
         }
+        table_id += root_descriptors.len() as u32;
     }
 
     let table_base_offset = layout
@@ -88,10 +181,17 @@ fn bind_descriptor_sets<'a, T>(
         .iter()
         .fold(0, |sum, c| sum + c.range.end - c.range.start);
 
-    for (set, table) in sets.zip(layout.tables[first_set..].iter()) {
+    let set_layouts = layout.tables[first_set..].iter().zip(&layout.root_descriptors[first_set..]);
+    for (set, (table, root_descriptors)) in sets.zip(set_layouts) {
         let set = set.borrow();
-        set.first_gpu_view.map(|gpu| {
-            assert!(table.contains(n::SRV_CBV_UAV));
+        assert!(
+            set.heap_srv_cbv_uav.as_raw() == heaps.srv_cbv_uav.as_raw() &&
+                set.heap_samplers.as_raw() == heaps.sampler.as_raw(),
+            "Descriptor set was not allocated from the shader visible heaps of the device",
+        );
+        tracked.extend(set.tracked.lock().unwrap().iter().cloned());
+        // The table is omitted if all views of the set are promoted to root descriptors.
+        set.first_gpu_view.filter(|_| table.contains(n::SRV_CBV_UAV)).map(|gpu| {
 
             let root_offset = table_id + table_base_offset;
             // Cast is safe as offset **must** be in u32 range. Unable to
@@ -116,14 +216,34 @@ fn bind_descriptor_sets<'a, T>(
 
             table_id += 1;
         });
+        for &binding in root_descriptors {
+            let info = &set.binding_infos[binding as usize];
+            let address = info.root_address.get();
+            let element = if info.is_uav {
+                RootElement::RootUav(address)
+            } else {
+                RootElement::RootCbv(address)
+            };
+            pipeline
+                .user_data
+                .set_root_descriptor((table_id + table_base_offset) as _, element);
+
+            table_id += 1;
+        }
     }
 }
 
 #[derive(Clone)]
 struct AttachmentClear {
     subpass_id: Option<pass::SubpassId>,
+    // Last subpass using the attachment.
+    last_subpass_id: Option<pass::SubpassId>,
     value: Option<com::ClearValueRaw>,
     stencil_value: Option<u32>,
+    // Previous contents are not needed by the render pass.
+    discard_load: bool,
+    // Contents are not needed after the render pass.
+    discard_store: bool,
 }
 
 #[derive(Clone)]
@@ -134,6 +254,33 @@ pub struct RenderPassCache {
     attachment_clears: Vec<AttachmentClear>,
 }
 
+/// State of the native render pass of the current subpass.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum NativePass {
+    /// No native render pass is recorded.
+    Inactive,
+    /// The subpass started, the pass begins with the next draw. Barriers and clears
+    /// recorded until then are issued ahead of it.
+    Pending,
+    /// The pass is open on the command list.
+    Active,
+}
+
+/// Clear of `clear_attachments` folded into the beginning access of a native render pass.
+#[derive(Clone, Copy)]
+enum HoistedClear {
+    Color(com::ClearColorRaw),
+    Depth(f32),
+    Stencil(u32),
+}
+
+/// Clear of `clear_attachments` deferred until the next interruption of a native render pass.
+#[derive(Clone)]
+struct DeferredClear {
+    clear: com::AttachmentClear,
+    rects: Vec<(d3d12::D3D12_RECT, Range<image::Layer>)>,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum OcclusionQuery {
     Binary(UINT),
@@ -151,6 +298,10 @@ enum RootElement {
     TableSrvCbvUav(u32),
     /// Descriptor table, storing table offset for the current descriptor heap
     TableSampler(u32),
+    /// Root constant buffer view, storing the GPU virtual address of the buffer
+    RootCbv(u64),
+    /// Root unordered access view, storing the GPU virtual address of the buffer
+    RootUav(u64),
     /// Undefined value, implementation specific
     Undefined,
 }
@@ -196,6 +347,24 @@ impl UserData {
         self.dirty_mask |= 1u64 << offset;
     }
 
+    /// Update root descriptor. Changes are marked as dirty.
+    fn set_root_descriptor(&mut self, offset: usize, element: RootElement) {
+        assert!(offset < ROOT_SIGNATURE_SIZE);
+        // Root descriptors occupy two DWORDs in the signature, but are stored in a single element
+        self.data[offset] = element;
+        self.dirty_mask |= 1u64 << offset;
+    }
+
+    /// Mark the root constants in `range` as dirty, after they have been
+    /// overwritten outside of the user data.
+    fn invalidate_constants(&mut self, range: Range<u32>) {
+        for i in range.start as usize .. ROOT_SIGNATURE_SIZE.min(range.end as usize) {
+            if let RootElement::Constant(_) = self.data[i] {
+                self.dirty_mask |= 1u64 << i;
+            }
+        }
+    }
+
     /// Clear dirty flag.
     fn clear_dirty(&mut self, i: usize) {
         self.dirty_mask &= !(1 << i);
@@ -232,15 +401,128 @@ impl PipelineCache {
     }
 }
 
+bitflags! {
+    /// Graphics state which has to be flushed to the command list before the next draw.
+    struct DirtyState: u8 {
+        const PIPELINE = 0x1;
+        const VERTEX_BUFFERS = 0x2;
+        const VIEWPORTS = 0x4;
+        const SCISSORS = 0x8;
+        // Render targets changed, the pipeline formats need to be checked against them.
+        const TARGETS = 0x10;
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum BindPoint {
     Compute,
     Graphics,
+    RayTracing,
 }
 
-#[derive(Clone)]
+// Number of descriptors in each chunk of transient views.
+const TRANSIENT_VIEWS_PER_CHUNK: u64 = 64;
+
+struct ViewChunk {
+    // Non-shader visible heap the views are created in, kept alive for `cpu_start`.
+    #[allow(dead_code)]
+    cpu_heap: ComPtr<d3d12::ID3D12DescriptorHeap>,
+    cpu_start: d3d12::D3D12_CPU_DESCRIPTOR_HANDLE,
+    // Descriptors of the shader visible heap the views are copied to.
+    gpu: Range<u64>,
+}
+
+/// Descriptors of views created during recording, e.g. UAVs for clearing buffer ranges.
+///
+/// Clearing an UAV requires the view in a non-shader visible heap as well as in the
+/// shader visible heap bound to the command list. Chunks are kept until the
+/// command buffer is freed, resetting the command buffer only rewinds the allocation.
+///
+/// The chunks are owned by the command list, clones of a command buffer share them.
+pub(crate) struct TransientViews {
+    device: ComPtr<d3d12::ID3D12Device>,
+    heap: Arc<Mutex<n::DescriptorHeap>>,
+    chunks: Vec<ViewChunk>,
+    // Number of views allocated since the last reset.
+    allocated: u64,
+}
+
+impl TransientViews {
+    fn new(device: ComPtr<d3d12::ID3D12Device>, heap: Arc<Mutex<n::DescriptorHeap>>) -> Self {
+        TransientViews {
+            device,
+            heap,
+            chunks: Vec::new(),
+            allocated: 0,
+        }
+    }
+
+    /// Allocate a descriptor for a new view.
+    ///
+    /// Returns the non-shader visible handle to create the view at and the
+    /// shader visible descriptor, which the view is copied into by `publish`.
+    fn allocate(&mut self) -> (d3d12::D3D12_CPU_DESCRIPTOR_HANDLE, n::DualHandle) {
+        let chunk_id = (self.allocated / TRANSIENT_VIEWS_PER_CHUNK) as usize;
+        let index = self.allocated % TRANSIENT_VIEWS_PER_CHUNK;
+        if chunk_id == self.chunks.len() {
+            let cpu = ::Device::create_descriptor_heap_impl(
+                &mut self.device,
+                d3d12::D3D12_DESCRIPTOR_HEAP_TYPE_CBV_SRV_UAV,
+                false,
+                TRANSIENT_VIEWS_PER_CHUNK as _,
+            );
+            let gpu = self.heap
+                .lock()
+                .unwrap()
+                .allocator
+                .allocate(TRANSIENT_VIEWS_PER_CHUNK)
+                .expect("Shader visible descriptor heap is exhausted");
+            self.chunks.push(ViewChunk {
+                cpu_heap: cpu.raw,
+                cpu_start: cpu.start.cpu,
+                gpu,
+            });
+        }
+        self.allocated += 1;
+
+        let heap = self.heap.lock().unwrap();
+        let chunk = &self.chunks[chunk_id];
+        let cpu = d3d12::D3D12_CPU_DESCRIPTOR_HANDLE {
+            ptr: chunk.cpu_start.ptr + (heap.handle_size * index) as usize,
+        };
+        (cpu, heap.at(chunk.gpu.start + index))
+    }
+
+    /// Copy a view created at `cpu` into its shader visible descriptor.
+    fn publish(&self, cpu: d3d12::D3D12_CPU_DESCRIPTOR_HANDLE, visible: n::DualHandle) {
+        unsafe {
+            self.device.CopyDescriptorsSimple(
+                1,
+                visible.cpu,
+                cpu,
+                d3d12::D3D12_DESCRIPTOR_HEAP_TYPE_CBV_SRV_UAV,
+            );
+        }
+    }
+
+    fn reset(&mut self) {
+        self.allocated = 0;
+    }
+
+    /// Return the shader visible descriptors to the heap.
+    pub(crate) fn free(&mut self) {
+        let mut heap = self.heap.lock().unwrap();
+        for chunk in self.chunks.drain(..) {
+            heap.allocator.deallocate(chunk.gpu);
+        }
+        self.allocated = 0;
+    }
+}
+
+#[derive(Clone, Debug)]
 struct Copy {
     footprint_offset: u64,
+    footprint_format: dxgiformat::DXGI_FORMAT,
     footprint: image::Extent,
     row_pitch: u32,
     img_subresource: u32,
@@ -249,24 +531,413 @@ struct Copy {
     copy_extent: image::Extent,
 }
 
+// Split a buffer-image copy into copies with footprints satisfying the D3D12 placement
+// and row pitch alignment. Block-compressed formats are split at block granularity:
+// buffer offsets are rounded to blocks and footprints are a whole number of blocks,
+// while copy extents are clamped to the image extent for partial blocks at the edges.
+fn split_buffer_image_copy<F>(
+    copies: &mut Vec<Copy>,
+    r: &com::BufferImageCopy,
+    footprint_format: dxgiformat::DXGI_FORMAT,
+    bytes_per_block: u32,
+    (block_width, block_height): (u32, u32),
+    subresource: F,
+) where
+    F: Fn(image::Layer) -> UINT,
+{
+    let placement_alignment = d3d12::D3D12_TEXTURE_DATA_PLACEMENT_ALIGNMENT as u64;
+    let pitch_alignment = d3d12::D3D12_TEXTURE_DATA_PITCH_ALIGNMENT;
+    let align_pitch = |pitch: u32| (pitch + pitch_alignment - 1) & !(pitch_alignment - 1);
+
+    // copy extent in blocks, partial blocks at the image edge count as whole ones
+    let width = div_ceil(r.image_extent.width, block_width);
+    let height = div_ceil(r.image_extent.height, block_height);
+    let depth = r.image_extent.depth;
+    // texel size of the blocks `start .. end` of a copy row or column
+    let texels_x = |start: u32, end: u32| {
+        cmp::min(end * block_width, r.image_extent.width) - start * block_width
+    };
+    let texels_y = |start: u32, end: u32| {
+        cmp::min(end * block_height, r.image_extent.height) - start * block_height
+    };
+
+    let buffer_width = if r.buffer_width == 0 {
+        width
+    } else {
+        div(r.buffer_width, block_width)
+    };
+    let buffer_height = if r.buffer_height == 0 {
+        height
+    } else {
+        div(r.buffer_height, block_height)
+    };
+    let row_pitch = buffer_width * bytes_per_block;
+    let slice_pitch = buffer_height * row_pitch;
+    let is_pitch_aligned = row_pitch % pitch_alignment == 0;
+
+    for layer in r.image_layers.layers.clone() {
+        let img_subresource = subresource(layer);
+        let layer_index = (layer - r.image_layers.layers.start) as u64;
+        let layer_offset = r.buffer_offset as u64 + layer_index * (slice_pitch * depth) as u64;
+        let aligned_offset = layer_offset & !(placement_alignment - 1);
+        if layer_offset == aligned_offset && is_pitch_aligned {
+            // trivial case: everything is aligned, ready for copying
+            copies.push(Copy {
+                footprint_offset: aligned_offset,
+                footprint_format,
+                footprint: image::Extent {
+                    width: width * block_width,
+                    // slices of the footprint need to be `slice_pitch` apart
+                    height: if depth > 1 { buffer_height } else { height } * block_height,
+                    depth,
+                },
+                row_pitch,
+                img_subresource,
+                img_offset: r.image_offset,
+                buf_offset: image::Offset::ZERO,
+                copy_extent: r.image_extent,
+            });
+            continue;
+        }
+
+        // the remaining cases are split into individual slices
+        for z in 0 .. depth {
+            let slice_offset = layer_offset + (z * slice_pitch) as u64;
+            let img_offset = image::Offset {
+                z: r.image_offset.z + z as i32,
+                .. r.image_offset
+            };
+
+            if is_pitch_aligned {
+                // buffer offset is not aligned
+                let aligned_offset = slice_offset & !(placement_alignment - 1);
+                let gap = (slice_offset - aligned_offset) as u32;
+                let row_pitch_blocks = row_pitch / bytes_per_block;
+                let x = (gap % row_pitch) / bytes_per_block;
+                let y = gap / row_pitch;
+                let buf_offset = image::Offset {
+                    x: (x * block_width) as i32,
+                    y: (y * block_height) as i32,
+                    z: 0,
+                };
+                let copy_extent = image::Extent {
+                    depth: 1,
+                    .. r.image_extent
+                };
+
+                if x + width <= row_pitch_blocks {
+                    // we can map it to the aligned one and adjust the offsets accordingly
+                    copies.push(Copy {
+                        footprint_offset: aligned_offset,
+                        footprint_format,
+                        footprint: image::Extent {
+                            width: (x + width) * block_width,
+                            height: (y + height) * block_height,
+                            depth: 1,
+                        },
+                        row_pitch,
+                        img_subresource,
+                        img_offset,
+                        buf_offset,
+                        copy_extent,
+                    });
+                } else {
+                    // image rows wrap around the footprint rows, split the copy region
+                    // at the wrapping point, the rest of each row continues on the next one
+                    let half = row_pitch_blocks - x;
+
+                    copies.push(Copy {
+                        footprint_offset: aligned_offset,
+                        footprint_format,
+                        footprint: image::Extent {
+                            width: row_pitch_blocks * block_width,
+                            height: (y + height) * block_height,
+                            depth: 1,
+                        },
+                        row_pitch,
+                        img_subresource,
+                        img_offset,
+                        buf_offset,
+                        copy_extent: image::Extent {
+                            width: texels_x(0, half),
+                            .. copy_extent
+                        },
+                    });
+                    copies.push(Copy {
+                        footprint_offset: aligned_offset,
+                        footprint_format,
+                        footprint: image::Extent {
+                            width: (width - half) * block_width,
+                            height: (y + 1 + height) * block_height,
+                            depth: 1,
+                        },
+                        row_pitch,
+                        img_subresource,
+                        img_offset: image::Offset {
+                            x: img_offset.x + (half * block_width) as i32,
+                            .. img_offset
+                        },
+                        buf_offset: image::Offset {
+                            x: 0,
+                            y: ((y + 1) * block_height) as i32,
+                            z: 0,
+                        },
+                        copy_extent: image::Extent {
+                            width: texels_x(half, width),
+                            .. copy_extent
+                        },
+                    });
+                }
+            } else {
+                // worst case: row by row copy, one row of blocks at a time
+                for y in 0 .. height {
+                    // an image row starts non-aligned
+                    let row_offset = slice_offset + (y * row_pitch) as u64;
+                    let aligned_offset = row_offset & !(placement_alignment - 1);
+                    let next_aligned_offset = aligned_offset + placement_alignment;
+                    let gap = ((row_offset - aligned_offset) / bytes_per_block as u64) as u32;
+                    let cut = cmp::min(
+                        width,
+                        ((next_aligned_offset - row_offset) / bytes_per_block as u64) as u32,
+                    );
+                    let img_offset = image::Offset {
+                        y: img_offset.y + (y * block_height) as i32,
+                        .. img_offset
+                    };
+                    let copy_height = texels_y(y, y + 1);
+
+                    copies.push(Copy {
+                        footprint_offset: aligned_offset,
+                        footprint_format,
+                        footprint: image::Extent {
+                            width: (gap + cut) * block_width,
+                            height: block_height,
+                            depth: 1,
+                        },
+                        row_pitch: align_pitch((gap + cut) * bytes_per_block),
+                        img_subresource,
+                        img_offset,
+                        buf_offset: image::Offset {
+                            x: (gap * block_width) as i32,
+                            y: 0,
+                            z: 0,
+                        },
+                        copy_extent: image::Extent {
+                            width: texels_x(0, cut),
+                            height: copy_height,
+                            depth: 1,
+                        },
+                    });
+
+                    // and if it crosses a placement alignment - we copy the rest separately
+                    if cut == width {
+                        continue;
+                    }
+                    let leftover = width - cut;
+
+                    copies.push(Copy {
+                        footprint_offset: next_aligned_offset,
+                        footprint_format,
+                        footprint: image::Extent {
+                            width: leftover * block_width,
+                            height: block_height,
+                            depth: 1,
+                        },
+                        row_pitch: align_pitch(leftover * bytes_per_block),
+                        img_subresource,
+                        img_offset: image::Offset {
+                            x: img_offset.x + (cut * block_width) as i32,
+                            .. img_offset
+                        },
+                        buf_offset: image::Offset::ZERO,
+                        copy_extent: image::Extent {
+                            width: texels_x(cut, width),
+                            height: copy_height,
+                            depth: 1,
+                        },
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Invalid usage detected while recording a command buffer.
+///
+/// Debug builds panic right away. Release builds skip the offending command
+/// and keep the first error until the next reset, `finish` returns it as
+/// `hal::command::RecordingError`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RecordingError {
+    /// A draw, dispatch or trace command was recorded without a bound pipeline.
+    NoPipelineBound(&'static str),
+    /// A command which requires a render pass was recorded outside of one.
+    OutsideRenderPass(&'static str),
+    /// The framebuffer attachments don't match the render pass.
+    FramebufferMismatch,
+    /// No clear value was passed for an attachment cleared on load.
+    MissingClearValue(pass::AttachmentId),
+    /// The image wasn't created with a usage allowing clears.
+    NotClearable(&'static str),
+    /// Only clears of whole images are supported.
+    PartialClear(&'static str),
+    /// The buffer wasn't created with the usage required by the command.
+    MissingUsage(&'static str, buffer::Usage),
+    /// The command isn't supported on copy queues.
+    CopyQueue(&'static str),
+    /// Copy queues only support resources in `COMMON`, `COPY_SOURCE` or `COPY_DEST`.
+    CopyQueueState(d3d12::D3D12_RESOURCE_STATES),
+    /// The command isn't supported on compute queues.
+    ComputeQueue(&'static str),
+    /// The formats of the copied planes aren't in the same format family.
+    IncompatibleFormats(&'static str),
+    /// Buffer copies don't support multisampled images.
+    Multisampled(&'static str),
+    /// Indirect commands only support tightly packed arguments.
+    UnsupportedStride(&'static str, u32),
+    /// The buffer offset isn't a multiple of the required alignment.
+    UnalignedOffset(&'static str, buffer::Offset, buffer::Offset),
+    /// The accessed range exceeds the end of the buffer.
+    OutOfBounds(&'static str),
+    /// Query results can only be copied as 64-bit values without availability.
+    UnsupportedResultFlags(query::ResultFlags),
+    /// Timestamps can only be written with `write_timestamp`.
+    TimestampQuery,
+    /// `end_query` was called without a matching `begin_query`.
+    QueryNotActive(query::QueryId),
+    /// Not all viewports or scissors used by the bound pipeline were set.
+    MissingViewports(&'static str, usize),
+    /// More viewports or scissors were set than supported by the pipeline.
+    TooManyViewports(&'static str, usize),
+    /// The image layout isn't allowed for transfer commands.
+    InvalidLayout(&'static str, image::Layout),
+    /// The bound pipeline was created for a subpass with incompatible attachment formats.
+    IncompatiblePipeline(&'static str),
+}
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RecordingError::NoPipelineBound(command) =>
+                write!(f, "`{}`: No pipeline bound", command),
+            RecordingError::OutsideRenderPass(command) =>
+                write!(f, "`{}`: Command can only be recorded inside a render pass", command),
+            RecordingError::FramebufferMismatch =>
+                write!(f, "Framebuffer attachments don't match the render pass"),
+            RecordingError::MissingClearValue(attachment) =>
+                write!(f, "Missing clear value for attachment {}", attachment),
+            RecordingError::NotClearable(command) =>
+                write!(f, "`{}`: Image needs to be created with a color or depth-stencil attachment usage", command),
+            RecordingError::PartialClear(command) =>
+                write!(f, "`{}`: Only whole images can be cleared", command),
+            RecordingError::MissingUsage(command, usage) =>
+                write!(f, "`{}`: Buffer needs to be created with usage {:?}", command, usage),
+            RecordingError::CopyQueue(command) =>
+                write!(f, "`{}`: Command isn't supported on copy queues", command),
+            RecordingError::CopyQueueState(state) =>
+                write!(f, "Resource state {:#x} isn't supported on copy queues", state),
+            RecordingError::ComputeQueue(command) =>
+                write!(f, "`{}`: Command isn't supported on compute queues", command),
+            RecordingError::IncompatibleFormats(command) =>
+                write!(f, "`{}`: Copied image planes need to have compatible formats", command),
+            RecordingError::Multisampled(command) =>
+                write!(f, "`{}`: Multisampled images need to be resolved before copying to or from buffers", command),
+            RecordingError::UnsupportedStride(command, stride) =>
+                write!(f, "`{}`: Indirect arguments need to be tightly packed (got {})", command, stride),
+            RecordingError::UnalignedOffset(command, offset, alignment) =>
+                write!(f, "`{}`: Buffer offset needs to be a multiple of {} (got {})", command, alignment, offset),
+            RecordingError::OutOfBounds(command) =>
+                write!(f, "`{}`: Accessed range exceeds the buffer size", command),
+            RecordingError::UnsupportedResultFlags(flags) =>
+                write!(f, "Query results can only be copied as 64-bit values without availability (got {:?})", flags),
+            RecordingError::TimestampQuery =>
+                write!(f, "Timestamp queries are written with `write_timestamp`"),
+            RecordingError::QueryNotActive(id) =>
+                write!(f, "Missing `begin_query` call for query {}", id),
+            RecordingError::MissingViewports(command, count) =>
+                write!(f, "`{}`: Viewports or scissors used by the pipeline weren't set (expected {})", command, count),
+            RecordingError::TooManyViewports(command, count) =>
+                write!(f, "`{}`: Exceeded the maximum number of viewports or scissors (got {})", command, count),
+            RecordingError::InvalidLayout(command, layout) =>
+                write!(f, "`{}`: Transfer commands require images in `General` or a transfer layout ({:?})", command, layout),
+            RecordingError::IncompatiblePipeline(command) =>
+                write!(f, "`{}`: Pipeline attachment formats aren't compatible with the current subpass", command),
+        }
+    }
+}
+
+impl Error for RecordingError {}
+
 #[derive(Clone)]
 pub struct CommandBuffer {
     raw: ComPtr<d3d12::ID3D12GraphicsCommandList>,
-    allocator: ComPtr<d3d12::ID3D12CommandAllocator>,
+    // Newer interface of the command list, required for depth bounds and view instancing.
+    raw1: Option<ComPtr<ext::ID3D12GraphicsCommandList1>>,
+    // Newer interface of the command list, required for native render passes and ray tracing.
+    raw4: Option<ComPtr<ext::ID3D12GraphicsCommandList4>>,
+    allocators: Arc<Mutex<AllocatorState>>,
     signatures: CmdSignatures,
+    // Shader visible descriptor heaps, bound on `begin`.
+    heaps: ShaderVisibleHeaps,
+    // Descriptors of views created during recording, shared by all clones.
+    pub(crate) transient_views: Arc<Mutex<TransientViews>>,
+    // List allocated by the pool, identifying the command buffer for the allocator state.
+    // `raw` is a continuation list after `execute_commands` split the recording.
+    base: ComPtr<d3d12::ID3D12GraphicsCommandList>,
+    // Allocator of the current recording, also used by the continuation lists.
+    allocator: Option<ComPtr<d3d12::ID3D12CommandAllocator>>,
+    // Closed lists executed before `raw`: the parts of the recording preceding
+    // each `execute_commands` and the lists of the executed secondary command buffers.
+    executed: Vec<ComPtr<d3d12::ID3D12GraphicsCommandList>>,
+    // Allocator states and base lists of the executed secondary command buffers,
+    // their submissions are tracked along with the ones of this command buffer.
+    secondaries: Vec<(Arc<Mutex<AllocatorState>>, *mut d3d12::ID3D12GraphicsCommandList)>,
+    // Continuation lists of the current recording and spare ones of previous recordings.
+    continuations: Vec<ComPtr<d3d12::ID3D12GraphicsCommandList>>,
+    spare_lists: Vec<ComPtr<d3d12::ID3D12GraphicsCommandList>>,
+    // Upload buffers holding the data of `update_buffer`, kept alive by the
+    // allocator state until the recording finished executing.
+    pub(crate) uploads: Vec<ComPtr<d3d12::ID3D12Resource>>,
+    // Copy command lists only support transfer commands and a restricted
+    // set of resource states (`COMMON`, `COPY_SOURCE` and `COPY_DEST`).
+    list_type: d3d12::D3D12_COMMAND_LIST_TYPE,
+    // Queue family of the pool, used to resolve the release and acquire
+    // halves of queue family ownership transfers.
+    family: QueueFamilyId,
 
     // Cache renderpasses for graphics operations
     pass_cache: Option<RenderPassCache>,
     cur_subpass: usize,
+    // Subpasses are recorded as native D3D12 render passes instead of
+    // binding render targets and clearing them manually.
+    native_render_passes: bool,
+    // State of the native render pass of the current subpass.
+    native_pass: NativePass,
+    // Clears recorded before a native render pass began, applied by its beginning accesses.
+    hoisted_clears: Vec<(pass::AttachmentId, HoistedClear)>,
+    // Clears recorded inside a native render pass, issued with the next interruption.
+    deferred_clears: Vec<DeferredClear>,
 
     // Cache current graphics root signature and pipeline to minimize rebinding and support two
     // bindpoints.
     gr_pipeline: PipelineCache,
+    // Attachment formats of the bound graphics pipeline, which are baked into the
+    // pipeline state and need to be compatible with the current subpass.
+    gr_formats: pass::AttachmentFormats,
     // Cache current compute root signature and pipeline.
     comp_pipeline: PipelineCache,
+    // Cache current ray tracing root signature. Ray tracing uses the compute root signature
+    // slot, the pipeline state object is stored separately in `rt_state_object`.
+    rt_pipeline: PipelineCache,
+    rt_state_object: *mut ext::ID3D12StateObject,
+    // Indicates that the compute root signature slot currently holds the ray tracing signature.
+    rt_root_bound: bool,
     // D3D12 only has one slot for both bindpoints. Need to rebind everything if we want to switch
     // between different bind points (ie. calling draw or dispatch).
     active_bindpoint: BindPoint,
+    // Graphics state changes are deferred until the next draw call, which allows
+    // to skip redundant updates between draws.
+    dirty_state: DirtyState,
 
     // Active queries in the command buffer.
     // Queries must begin and end in the same command buffer, which allows us to track them.
@@ -283,11 +954,34 @@ pub struct CommandBuffer {
 
     // Re-using allocation for the image-buffer copies.
     copies: Vec<Copy>,
+    // Re-using allocation for merging buffer copy regions.
+    buffer_copies: Vec<com::BufferCopy>,
+    // Pending resource barriers, issued in a single batch before the next command
+    // accessing resources. Barriers of consecutive render pass transitions and
+    // pipeline barriers are combined this way.
+    barriers: Vec<d3d12::D3D12_RESOURCE_BARRIER>,
+    // Number of pending barriers already applied to `resource_states`.
+    synced_barriers: usize,
+    // Current states of the resources transitioned in the command buffer.
+    // Resources which have subresources in different states are not tracked.
+    resource_states: HashMap<*mut d3d12::ID3D12Resource, d3d12::D3D12_RESOURCE_STATES>,
+    // States of the tracked images on their first use in the command buffer.
+    // Command buffers which can be submitted multiple times transition them back on `finish`.
+    entry_states: HashMap<*mut d3d12::ID3D12Resource, (*const AtomicUsize, d3d12::D3D12_RESOURCE_STATES)>,
+    // Re-using allocation for the tracked resources of bound descriptor sets.
+    tracked_descriptors: Vec<n::TrackedDescriptor>,
 
     // D3D12 only allows setting all viewports or all scissors at once, not partial updates.
     // So we must cache the implied state for these partial updates.
-    viewport_cache: SmallVec<[d3d12::D3D12_VIEWPORT; d3d12::D3D12_VIEWPORT_AND_SCISSORRECT_OBJECT_COUNT_PER_PIPELINE as usize]>,
-    scissor_cache: SmallVec<[d3d12::D3D12_RECT; d3d12::D3D12_VIEWPORT_AND_SCISSORRECT_OBJECT_COUNT_PER_PIPELINE as usize]>
+    viewport_cache: SmallVec<[d3d12::D3D12_VIEWPORT; MAX_VIEWPORTS]>,
+    scissor_cache: SmallVec<[d3d12::D3D12_RECT; MAX_VIEWPORTS]>,
+    // Number of viewports and scissors used by the bound graphics pipeline.
+    viewport_count: usize,
+
+    // First invalid usage since the last reset, the offending commands are skipped.
+    error: Option<RecordingError>,
+    // Usage flags of the current recording.
+    flags: com::CommandBufferFlags,
 }
 
 unsafe impl Send for CommandBuffer { }
@@ -296,115 +990,902 @@ unsafe impl Sync for CommandBuffer { }
 impl CommandBuffer {
     pub(crate) fn new(
         raw: ComPtr<d3d12::ID3D12GraphicsCommandList>,
-        allocator: ComPtr<d3d12::ID3D12CommandAllocator>,
+        device: ComPtr<d3d12::ID3D12Device>,
+        allocators: Arc<Mutex<AllocatorState>>,
         signatures: CmdSignatures,
+        heaps: ShaderVisibleHeaps,
+        list_type: d3d12::D3D12_COMMAND_LIST_TYPE,
+        family: QueueFamilyId,
+        native_render_passes: bool,
     ) -> Self {
+        let transient_views = TransientViews::new(device, heaps.srv_cbv_uav_allocator.clone());
+        // Older runtimes don't know the interfaces.
+        let raw1 = raw.cast::<ext::ID3D12GraphicsCommandList1>().ok();
+        let raw4 = raw.cast::<ext::ID3D12GraphicsCommandList4>().ok();
         CommandBuffer {
+            base: raw.clone(),
             raw,
-            allocator,
+            raw1,
+            raw4,
+            allocators,
             signatures,
+            heaps,
+            transient_views: Arc::new(Mutex::new(transient_views)),
+            allocator: None,
+            executed: Vec::new(),
+            secondaries: Vec::new(),
+            continuations: Vec::new(),
+            spare_lists: Vec::new(),
+            uploads: Vec::new(),
+            list_type,
+            family,
             pass_cache: None,
             cur_subpass: !0,
+            native_render_passes,
+            native_pass: NativePass::Inactive,
+            hoisted_clears: Vec::new(),
+            deferred_clears: Vec::new(),
             gr_pipeline: PipelineCache::new(),
+            gr_formats: pass::AttachmentFormats::default(),
             comp_pipeline: PipelineCache::new(),
+            rt_pipeline: PipelineCache::new(),
+            rt_state_object: ptr::null_mut(),
+            rt_root_bound: false,
             active_bindpoint: BindPoint::Graphics,
+            dirty_state: DirtyState::empty(),
             occlusion_query: None,
             pipeline_stats_query: None,
             vertex_buffer_views: [NULL_VERTEX_BUFFER_VIEW; MAX_VERTEX_BUFFERS],
             copies: Vec::new(),
+            buffer_copies: Vec::new(),
+            barriers: Vec::new(),
+            synced_barriers: 0,
+            resource_states: HashMap::new(),
+            entry_states: HashMap::new(),
+            tracked_descriptors: Vec::new(),
             viewport_cache: SmallVec::new(),
             scissor_cache: SmallVec::new(),
+            viewport_count: 0,
+            error: None,
+            flags: com::CommandBufferFlags::empty(),
         }
     }
 
-    pub(crate) unsafe fn as_raw_list(&self) -> *mut d3d12::ID3D12CommandList {
-        self.raw.as_raw() as *mut _
+    /// Get the first invalid usage recorded since the last reset, if any.
+    ///
+    /// Commands with invalid usage are skipped, the error is also returned by `finish`.
+    pub fn recording_error(&self) -> Option<&RecordingError> {
+        self.error.as_ref()
     }
 
-    fn reset(&mut self) {
-        unsafe { self.raw.Reset(self.allocator.as_raw(), ptr::null_mut()); }
-        self.pass_cache = None;
-        self.cur_subpass = !0;
-        self.gr_pipeline = PipelineCache::new();
-        self.comp_pipeline = PipelineCache::new();
-        self.active_bindpoint = BindPoint::Graphics;
-        self.occlusion_query = None;
-        self.pipeline_stats_query = None;
-        self.vertex_buffer_views = [NULL_VERTEX_BUFFER_VIEW; MAX_VERTEX_BUFFERS];
+    /// Record an invalid usage, panicking in debug builds.
+    fn record_error(&mut self, error: RecordingError) {
+        if cfg!(debug_assertions) {
+            panic!("{}", error);
+        }
+        warn!("Skipping invalid command: {}", error);
+        if self.error.is_none() {
+            self.error = Some(error);
+        }
     }
 
-    fn insert_subpass_barriers(&self) {
-        let state = self.pass_cache.as_ref().unwrap();
-        let proto_barriers = match state.render_pass.subpasses.get(self.cur_subpass) {
-            Some(subpass) => &subpass.pre_barriers,
-            None => &state.render_pass.post_barriers,
-        };
-
-        let transition_barriers = proto_barriers
-            .iter()
-            .map(|barrier| {
-                let mut resource_barrier = d3d12::D3D12_RESOURCE_BARRIER {
-                    Type: d3d12::D3D12_RESOURCE_BARRIER_TYPE_TRANSITION,
-                    Flags: barrier.flags,
-                    u: unsafe { mem::zeroed() },
-                };
-
-                *unsafe { resource_barrier.u.Transition_mut() } = d3d12::D3D12_RESOURCE_TRANSITION_BARRIER {
-                    pResource: state.framebuffer.attachments[barrier.attachment_id].resource,
-                    Subresource: d3d12::D3D12_RESOURCE_BARRIER_ALL_SUBRESOURCES,
-                    StateBefore: barrier.states.start,
-                    StateAfter: barrier.states.end,
-                };
+    fn is_copy_list(&self) -> bool {
+        self.list_type == d3d12::D3D12_COMMAND_LIST_TYPE_COPY
+    }
 
-                resource_barrier
-            })
-            .collect::<Vec<_>>();
+    fn is_compute_list(&self) -> bool {
+        self.list_type == d3d12::D3D12_COMMAND_LIST_TYPE_COMPUTE
+    }
 
-        if !transition_barriers.is_empty() {
-            unsafe {
-                self.raw.clone().ResourceBarrier(
-                    transition_barriers.len() as _,
-                    transition_barriers.as_ptr(),
-                );
+    /// Check that a resource state is supported by the command list.
+    ///
+    /// Copy queues only know about `COMMON` and the copy states. Resources used
+    /// in other states need to be released into `COMMON` on another queue first,
+    /// anything else records an error and returns `None`.
+    fn legal_resource_state(
+        &mut self,
+        state: d3d12::D3D12_RESOURCE_STATES,
+    ) -> Option<d3d12::D3D12_RESOURCE_STATES> {
+        if self.is_copy_list() {
+            match state {
+                d3d12::D3D12_RESOURCE_STATE_COMMON |
+                d3d12::D3D12_RESOURCE_STATE_COPY_SOURCE |
+                d3d12::D3D12_RESOURCE_STATE_COPY_DEST => {}
+                _ => {
+                    self.record_error(RecordingError::CopyQueueState(state));
+                    return None;
+                }
             }
         }
+        Some(state)
     }
 
-    fn bind_targets(&mut self) {
-        let state = self.pass_cache.as_ref().unwrap();
-        let subpass = &state.render_pass.subpasses[self.cur_subpass];
+    /// Resolve the states of a barrier, which may transfer queue family ownership.
+    ///
+    /// D3D12 has no notion of ownership, resources are implicitly shared between queues.
+    /// A transfer is split into a release on the source family, transitioning
+    /// the resource into `COMMON`, and an acquire on the destination family,
+    /// transitioning it out of `COMMON` again. The queue submissions are expected
+    /// to be ordered by a semaphore.
+    ///
+    /// Returns `None` if one of the states isn't supported by the command list.
+    fn transfer_states(
+        &mut self,
+        families: &Option<Range<QueueFamilyId>>,
+        state_src: d3d12::D3D12_RESOURCE_STATES,
+        state_dst: d3d12::D3D12_RESOURCE_STATES,
+    ) -> Option<(d3d12::D3D12_RESOURCE_STATES, d3d12::D3D12_RESOURCE_STATES)> {
+        let (state_src, state_dst) = match *families {
+            Some(ref families) if families.start != families.end => {
+                debug_assert!(
+                    self.family == families.start || self.family == families.end,
+                    "Queue family {:?} is not part of the ownership transfer {:?}",
+                    self.family,
+                    families,
+                );
+                if self.family == families.start {
+                    (state_src, d3d12::D3D12_RESOURCE_STATE_COMMON)
+                } else {
+                    (d3d12::D3D12_RESOURCE_STATE_COMMON, state_dst)
+                }
+            }
+            _ => (state_src, state_dst),
+        };
+        let state_src = self.legal_resource_state(state_src)?;
+        let state_dst = self.legal_resource_state(state_dst)?;
+        Some((state_src, state_dst))
+    }
 
-        // collect render targets
-        let color_views = subpass.color_attachments
+    /// Lists of the recording in execution order, including the ones
+    /// of the executed secondary command buffers.
+    pub(crate) fn lists<'a>(&'a self) -> impl Iterator<Item = *mut d3d12::ID3D12CommandList> + 'a {
+        self.executed
             .iter()
-            .map(|&(id, _)| state.framebuffer.attachments[id].handle_rtv.unwrap())
-            .collect::<Vec<_>>();
-        let ds_view = match subpass.depth_stencil_attachment {
-            Some((id, _)) => state.framebuffer.attachments[id].handle_dsv.as_ref().unwrap() as *const _,
-            None => ptr::null(),
-        };
-        // set render targets
-        unsafe {
-            self.raw.OMSetRenderTargets(
-                color_views.len() as UINT,
-                color_views.as_ptr(),
-                FALSE,
-                ds_view,
-            );
-        }
+            .chain(iter::once(&self.raw))
+            .map(|list| list.as_raw() as *mut _)
+    }
 
-        // performs clears for all the attachments first used in this subpass
-        for (view, clear) in state.framebuffer.attachments.iter().zip(state.attachment_clears.iter()) {
-            if clear.subpass_id != Some(self.cur_subpass) {
-                continue;
-            }
+    /// Allocator states and base lists of the executed secondary command buffers.
+    pub(crate) fn secondaries(&self) -> &[(Arc<Mutex<AllocatorState>>, *mut d3d12::ID3D12GraphicsCommandList)] {
+        &self.secondaries
+    }
 
-            if let (Some(handle), Some(cv)) = (view.handle_rtv, clear.value) {
-                self.clear_render_target_view(handle, unsafe { cv.color }, &[state.target_rect]);
-            }
+    pub(crate) fn allocators(&self) -> &Arc<Mutex<AllocatorState>> {
+        &self.allocators
+    }
 
-            if let Some(handle) = view.handle_dsv {
-                let depth = clear.value.map(|cv| unsafe { cv.depth_stencil.depth });
+    pub(crate) fn raw_list(&self) -> *mut d3d12::ID3D12GraphicsCommandList {
+        self.base.as_raw()
+    }
+
+    /// Record into `list`, querying its newer interfaces.
+    fn set_list(&mut self, list: ComPtr<d3d12::ID3D12GraphicsCommandList>) {
+        self.raw1 = list.cast::<ext::ID3D12GraphicsCommandList1>().ok();
+        self.raw4 = list.cast::<ext::ID3D12GraphicsCommandList4>().ok();
+        self.raw = list;
+    }
+
+    /// Bind the descriptor heaps of the device on the recording list.
+    fn bind_descriptor_heaps(&mut self) {
+        if !self.is_copy_list() {
+            // All descriptor sets share the heaps of the device,
+            // bind them once for the whole command list.
+            let mut heaps = [
+                self.heaps.srv_cbv_uav.as_raw(),
+                self.heaps.sampler.as_raw(),
+            ];
+            unsafe { self.raw.SetDescriptorHeaps(2, heaps.as_mut_ptr()); }
+        }
+    }
+
+    /// Prepare the submission of the command list to `queue`.
+    ///
+    /// D3D12 doesn't allow executing a command list while a previous execution
+    /// is still in flight. Resubmitting a pending list is serialized on the GPU,
+    /// which emulates `SIMULTANEOUS_USE`, and is an error without the flag.
+    /// Resubmitting a `ONE_TIME_SUBMIT` recording is an error as well.
+    /// Debug builds panic on errors, release builds log a warning.
+    pub(crate) unsafe fn prepare_submit(&self, queue: &ComPtr<d3d12::ID3D12CommandQueue>) {
+        let state = self.allocators.lock().unwrap();
+        let value = match state.submission(self.raw_list()) {
+            Some(value) => value,
+            None => return,
+        };
+
+        if self.flags.contains(com::CommandBufferFlags::ONE_TIME_SUBMIT) {
+            let message = "Command buffer recorded with ONE_TIME_SUBMIT submitted more than once";
+            if cfg!(debug_assertions) {
+                panic!("{}", message);
+            }
+            warn!("{}", message);
+        }
+
+        if state.is_pending(value) {
+            if !self.flags.contains(com::CommandBufferFlags::SIMULTANEOUS_USE) {
+                let message = "Command buffer submitted while still executing, record it with SIMULTANEOUS_USE";
+                if cfg!(debug_assertions) {
+                    panic!("{}", message);
+                }
+                warn!("{}", message);
+            }
+            state.wait(queue, value);
+        }
+    }
+
+    fn reset(&mut self) {
+        let allocator = {
+            let mut state = self.allocators.lock().unwrap();
+            // Resetting the list is allowed while a previous recording is
+            // executing, the new recording is submitted independently.
+            state.retire_uploads(self.base.as_raw(), mem::replace(&mut self.uploads, Vec::new()));
+            state.forget(self.base.as_raw());
+            state.begin_recording(self.base.as_raw())
+        };
+        unsafe { self.base.Reset(allocator.as_raw(), ptr::null_mut()); }
+        self.allocator = Some(allocator);
+        if self.raw.as_raw() != self.base.as_raw() {
+            let base = self.base.clone();
+            self.set_list(base);
+        }
+        self.executed.clear();
+        self.secondaries.clear();
+        let continuations = mem::replace(&mut self.continuations, Vec::new());
+        self.spare_lists.extend(continuations);
+        self.bind_descriptor_heaps();
+        self.pass_cache = None;
+        self.cur_subpass = !0;
+        self.native_pass = NativePass::Inactive;
+        self.hoisted_clears.clear();
+        self.deferred_clears.clear();
+        self.reset_bindings();
+        self.occlusion_query = None;
+        self.pipeline_stats_query = None;
+        self.barriers.clear();
+        self.synced_barriers = 0;
+        self.resource_states.clear();
+        self.entry_states.clear();
+        self.transient_views.lock().unwrap().reset();
+        self.error = None;
+    }
+
+    /// Forget the bound state, which doesn't carry over to another command list.
+    fn reset_bindings(&mut self) {
+        self.gr_pipeline = PipelineCache::new();
+        self.gr_formats = pass::AttachmentFormats::default();
+        self.comp_pipeline = PipelineCache::new();
+        self.rt_pipeline = PipelineCache::new();
+        self.rt_state_object = ptr::null_mut();
+        self.rt_root_bound = false;
+        self.active_bindpoint = BindPoint::Graphics;
+        self.dirty_state = DirtyState::empty();
+        self.vertex_buffer_views = [NULL_VERTEX_BUFFER_VIEW; MAX_VERTEX_BUFFERS];
+        self.viewport_cache.clear();
+        self.scissor_cache.clear();
+        self.viewport_count = 0;
+    }
+
+    /// Close the recording list and continue recording into a new one, which is
+    /// executed after the closed list and the lists of the secondary command buffers.
+    fn split_list(&mut self, secondaries: &[&CommandBuffer]) {
+        if self.occlusion_query.is_some() || self.pipeline_stats_query.is_some() {
+            // D3D12 queries need to begin and end on the same command list.
+            warn!("Queries active during `execute_commands` don't include the secondary command buffers");
+        }
+        unsafe { self.raw.Close(); }
+        self.executed.push(self.raw.clone());
+        for secondary in secondaries {
+            self.executed.extend(secondary.executed.iter().cloned());
+            self.executed.push(secondary.raw.clone());
+            self.secondaries.push((secondary.allocators.clone(), secondary.raw_list()));
+            self.secondaries.extend(secondary.secondaries.iter().cloned());
+        }
+
+        let allocator = self.allocator.clone().expect("Command buffer is not recording");
+        let list = match self.spare_lists.pop() {
+            Some(list) => {
+                unsafe { list.Reset(allocator.as_raw(), ptr::null_mut()); }
+                list
+            }
+            None => {
+                let device = self.transient_views.lock().unwrap().device.clone();
+                let mut list: *mut d3d12::ID3D12GraphicsCommandList = ptr::null_mut();
+                let hr = unsafe {
+                    device.CreateCommandList(
+                        0,
+                        self.list_type,
+                        allocator.as_raw(),
+                        ptr::null_mut(),
+                        &d3d12::IID_ID3D12GraphicsCommandList,
+                        &mut list as *mut *mut _ as *mut *mut _,
+                    )
+                };
+                if !SUCCEEDED(hr) {
+                    error!("error on command list creation: {:x}", hr);
+                }
+                unsafe { ComPtr::from_raw(list) }
+            }
+        };
+        self.continuations.push(list.clone());
+        self.set_list(list);
+        self.reset_bindings();
+    }
+
+    fn insert_subpass_barriers(&mut self) {
+        {
+            let state = self.pass_cache.as_ref().unwrap();
+
+            // Transient attachments may share their memory with other resources,
+            // activate them with an aliasing barrier before their first use unless
+            // the render pass loads their previous contents.
+            let cur_subpass = self.cur_subpass;
+            let aliasing_barriers = state.framebuffer.attachments
+                .iter()
+                .zip(state.attachment_clears.iter())
+                .filter(|&(view, clear)| {
+                    view.is_transient && clear.discard_load && clear.subpass_id == Some(cur_subpass)
+                })
+                .map(|(view, _)| {
+                    let mut resource_barrier = d3d12::D3D12_RESOURCE_BARRIER {
+                        Type: d3d12::D3D12_RESOURCE_BARRIER_TYPE_ALIASING,
+                        Flags: d3d12::D3D12_RESOURCE_BARRIER_FLAG_NONE,
+                        u: unsafe { mem::zeroed() },
+                    };
+                    *unsafe { resource_barrier.u.Aliasing_mut() } = d3d12::D3D12_RESOURCE_ALIASING_BARRIER {
+                        pResourceBefore: ptr::null_mut(),
+                        pResourceAfter: view.resource,
+                    };
+                    resource_barrier
+                });
+            self.barriers.extend(aliasing_barriers);
+
+            let proto_barriers = match state.render_pass.subpasses.get(self.cur_subpass) {
+                Some(subpass) => &subpass.pre_barriers,
+                None => &state.render_pass.post_barriers,
+            };
+
+            let transition_barriers = proto_barriers
+                .iter()
+                .map(|barrier| {
+                    let mut resource_barrier = d3d12::D3D12_RESOURCE_BARRIER {
+                        Type: d3d12::D3D12_RESOURCE_BARRIER_TYPE_TRANSITION,
+                        Flags: barrier.flags,
+                        u: unsafe { mem::zeroed() },
+                    };
+
+                    *unsafe { resource_barrier.u.Transition_mut() } = d3d12::D3D12_RESOURCE_TRANSITION_BARRIER {
+                        pResource: state.framebuffer.attachments[barrier.attachment_id].resource,
+                        Subresource: d3d12::D3D12_RESOURCE_BARRIER_ALL_SUBRESOURCES,
+                        StateBefore: barrier.states.start,
+                        StateAfter: barrier.states.end,
+                    };
+
+                    resource_barrier
+                });
+            self.barriers.extend(transition_barriers);
+        }
+    }
+
+    /// Apply the state changes of the pending barriers to the tracked resource states.
+    ///
+    /// Needs to be called before the resource states are read or updated.
+    fn sync_resource_states(&mut self) {
+        for barrier in &self.barriers[self.synced_barriers ..] {
+            if barrier.Type != d3d12::D3D12_RESOURCE_BARRIER_TYPE_TRANSITION ||
+                barrier.Flags == d3d12::D3D12_RESOURCE_BARRIER_FLAG_BEGIN_ONLY
+            {
+                continue;
+            }
+            let transition = unsafe { barrier.u.Transition() };
+            if transition.Subresource == d3d12::D3D12_RESOURCE_BARRIER_ALL_SUBRESOURCES {
+                self.resource_states.insert(transition.pResource, transition.StateAfter);
+            } else {
+                // Subresources may be in different states now.
+                self.resource_states.remove(&transition.pResource);
+            }
+        }
+        self.synced_barriers = self.barriers.len();
+    }
+
+    /// Issue all pending resource barriers with a single call.
+    ///
+    /// Needs to be called before every command accessing resources. Barriers aren't
+    /// allowed inside native render passes, they stay pending until the next draw
+    /// or the end of the subpass, see `flush_native_pass`.
+    fn flush_barriers(&mut self) {
+        self.sync_resource_states();
+        if self.native_pass != NativePass::Inactive {
+            return;
+        }
+        if !self.barriers.is_empty() {
+            unsafe {
+                self.raw.ResourceBarrier(
+                    self.barriers.len() as _,
+                    self.barriers.as_ptr(),
+                );
+            }
+            self.barriers.clear();
+            self.synced_barriers = 0;
+        }
+    }
+
+    /// Issue the barriers and clears deferred in the native render pass of the
+    /// current subpass, then begin or resume the pass with `flags`.
+    ///
+    /// Called before draws. A pending pass issues them ahead of its beginning, an
+    /// active pass is interrupted once for all of them and resumed with the attachment
+    /// contents preserved. Barriers are issued before clears.
+    fn flush_native_pass(&mut self, flags: ext::D3D12_RENDER_PASS_FLAGS) {
+        match self.native_pass {
+            NativePass::Inactive => return,
+            NativePass::Pending => {
+                self.native_pass = NativePass::Inactive;
+                self.flush_barriers();
+                self.begin_native_pass(false, if self.deferred_clears.is_empty() { flags } else { 0 });
+                self.hoisted_clears.clear();
+                self.native_pass = NativePass::Active;
+                if self.deferred_clears.is_empty() {
+                    return;
+                }
+                // Partial clears are applied on top of the load operations.
+                unsafe { self.raw_list4().EndRenderPass(); }
+            }
+            NativePass::Active => {
+                self.sync_resource_states();
+                if self.barriers.is_empty() && self.deferred_clears.is_empty() && flags == 0 {
+                    return;
+                }
+                unsafe { self.raw_list4().EndRenderPass(); }
+            }
+        }
+        self.native_pass = NativePass::Inactive;
+        self.flush_barriers();
+        self.flush_deferred_clears();
+        self.begin_native_pass(true, flags);
+        self.native_pass = NativePass::Active;
+    }
+
+    /// Suspend the native render pass of the current subpass, returns `false` if there is none.
+    ///
+    /// The next command list needs to resume the pass before any other command.
+    fn suspend_native_pass(&mut self) -> bool {
+        if self.native_pass == NativePass::Inactive {
+            return false;
+        }
+        self.flush_native_pass(ext::D3D12_RENDER_PASS_FLAG_SUSPENDING_PASS);
+        unsafe { self.raw_list4().EndRenderPass(); }
+        self.native_pass = NativePass::Inactive;
+        true
+    }
+
+    /// Resume the native render pass suspended by the previous command list.
+    fn resume_native_pass(&mut self) {
+        self.begin_native_pass(true, ext::D3D12_RENDER_PASS_FLAG_RESUMING_PASS);
+        self.native_pass = NativePass::Active;
+    }
+
+    /// Issue the clears deferred in a native render pass, which needs to be interrupted.
+    fn flush_deferred_clears(&mut self) {
+        let clears = mem::replace(&mut self.deferred_clears, Vec::new());
+        for clear in &clears {
+            self.clear_attachment(&clear.clear, &clear.rects);
+        }
+        self.deferred_clears = clears;
+        self.deferred_clears.clear();
+    }
+
+    /// Get the source state of a transition of a whole resource.
+    ///
+    /// The tracked state takes precedence over the state implied by the user,
+    /// which is meaningless for images in `Undefined` layout.
+    fn source_state(
+        &self,
+        resource: *mut d3d12::ID3D12Resource,
+        state: d3d12::D3D12_RESOURCE_STATES,
+        discard: bool,
+    ) -> d3d12::D3D12_RESOURCE_STATES {
+        match self.resource_states.get(&resource) {
+            Some(&current) if current != state => {
+                if !discard {
+                    warn!(
+                        "Resource {:?} is in state {:#x}, but state {:#x} was specified",
+                        resource, current, state,
+                    );
+                }
+                current
+            }
+            _ => state,
+        }
+    }
+
+    /// State of an image accessed by a transfer command in `layout`.
+    ///
+    /// Images in `General` layout are expected in the state a barrier into the layout
+    /// with the access of the command left them in. `Undefined` and `Preinitialized`
+    /// aren't valid for transfer commands, the state of these images is unknown.
+    fn transfer_state(
+        &mut self,
+        command: &'static str,
+        layout: image::Layout,
+        access: image::Access,
+    ) -> Option<d3d12::D3D12_RESOURCE_STATES> {
+        match layout {
+            image::Layout::Undefined |
+            image::Layout::Preinitialized => {
+                self.record_error(RecordingError::InvalidLayout(command, layout));
+                None
+            }
+            _ => Some(
+                conv::map_image_layout_state(layout)
+                    .unwrap_or_else(|| conv::map_image_resource_state(access, layout))
+            ),
+        }
+    }
+
+    /// Transition a resource into the state required by a transfer command.
+    ///
+    /// `expected` is the state implied by the layout or access passed by the user.
+    /// Textures used for the first time in the command buffer are assumed to be
+    /// in this state, or in `COMMON` if there is none. Buffers always decay to `COMMON`
+    /// at the end of a submission. Returns the state to transition back to after
+    /// the command, if a transition was required.
+    ///
+    /// Tracked resources are transitioned from their tracked state instead
+    /// and stay in the required state after the command.
+    ///
+    /// All pending barriers are issued, the resource can be accessed afterwards.
+    fn require_state(
+        &mut self,
+        resource: *mut d3d12::ID3D12Resource,
+        tracking: n::Tracking,
+        expected: Option<d3d12::D3D12_RESOURCE_STATES>,
+        required: d3d12::D3D12_RESOURCE_STATES,
+        is_texture: bool,
+    ) -> Option<d3d12::D3D12_RESOURCE_STATES> {
+        let previous = if tracking != n::Tracking::None {
+            self.track_state(resource, tracking, required);
+            None
+        } else {
+            self.transition_state(resource, expected, required, is_texture)
+        };
+        self.flush_barriers();
+        previous
+    }
+
+    /// Batch the transition of an untracked resource for `require_state`.
+    fn transition_state(
+        &mut self,
+        resource: *mut d3d12::ID3D12Resource,
+        expected: Option<d3d12::D3D12_RESOURCE_STATES>,
+        required: d3d12::D3D12_RESOURCE_STATES,
+        is_texture: bool,
+    ) -> Option<d3d12::D3D12_RESOURCE_STATES> {
+        self.sync_resource_states();
+        // States illegal on copy queues are reported, the resource is assumed in `COMMON` instead.
+        let expected = expected.and_then(|state| self.legal_resource_state(state));
+        let required = self.legal_resource_state(required)?;
+        let current = match self.resource_states.get(&resource) {
+            Some(&current) => {
+                match expected {
+                    // Resources in `COMMON` are promoted on use, so the layout may still be honored.
+                    Some(expected) if current != d3d12::D3D12_RESOURCE_STATE_COMMON && current & expected != expected => {
+                        warn!(
+                            "Resource {:?} is in state {:#x}, but the layout implies state {:#x}",
+                            resource, current, expected,
+                        );
+                    }
+                    _ => {}
+                }
+                current
+            }
+            None if is_texture => expected.unwrap_or(d3d12::D3D12_RESOURCE_STATE_COMMON),
+            None => d3d12::D3D12_RESOURCE_STATE_COMMON,
+        };
+
+        if current == required || (required != d3d12::D3D12_RESOURCE_STATE_COMMON && current & required == required) {
+            self.resource_states.insert(resource, current);
+            return None;
+        }
+        if current == d3d12::D3D12_RESOURCE_STATE_COMMON && Self::is_promotable(required, is_texture) {
+            // Implicit state promotion on first access, the resource
+            // stays in the promoted state until the command list finished execution.
+            self.resource_states.insert(resource, required);
+            return None;
+        }
+
+        self.barriers.push(Self::transition_barrier(
+            d3d12::D3D12_RESOURCE_TRANSITION_BARRIER {
+                pResource: resource,
+                Subresource: d3d12::D3D12_RESOURCE_BARRIER_ALL_SUBRESOURCES,
+                StateBefore: current,
+                StateAfter: required,
+            }
+        ));
+        Some(current)
+    }
+
+    /// Transition a resource back after a transfer command, undoing `require_state`.
+    ///
+    /// The barrier stays pending until the next command accessing resources.
+    fn restore_state(
+        &mut self,
+        resource: *mut d3d12::ID3D12Resource,
+        previous: Option<d3d12::D3D12_RESOURCE_STATES>,
+    ) {
+        if let Some(previous) = previous {
+            self.sync_resource_states();
+            let current = self.resource_states[&resource];
+            self.barriers.push(Self::transition_barrier(
+                d3d12::D3D12_RESOURCE_TRANSITION_BARRIER {
+                    pResource: resource,
+                    Subresource: d3d12::D3D12_RESOURCE_BARRIER_ALL_SUBRESOURCES,
+                    StateBefore: current,
+                    StateAfter: previous,
+                }
+            ));
+        }
+    }
+
+    /// Transition the subresources of an image copied within itself, the source
+    /// subresources into `COPY_SOURCE` and the destination ones into `COPY_DEST`.
+    ///
+    /// Subresources are expected in the states implied by their layouts, unless the
+    /// state of the whole image is known. Returns the barriers transitioning them back
+    /// after the copy and the known state of the image.
+    fn require_copy_states(
+        &mut self,
+        image: &n::Image,
+        src_expected: Option<d3d12::D3D12_RESOURCE_STATES>,
+        dst_expected: Option<d3d12::D3D12_RESOURCE_STATES>,
+        regions: &[com::ImageCopy],
+    ) -> (Vec<d3d12::D3D12_RESOURCE_BARRIER>, Option<d3d12::D3D12_RESOURCE_STATES>) {
+        self.sync_resource_states();
+        let known = match self.resource_states.get(&image.resource) {
+            Some(&state) => Some(state),
+            None => match image.tracking() {
+                n::Tracking::Image(state) => Some(unsafe { &*state }.load(Ordering::Acquire) as _),
+                _ => None,
+            },
+        };
+        let common = d3d12::D3D12_RESOURCE_STATE_COMMON;
+        let src_state = known.or(src_expected).and_then(|state| self.legal_resource_state(state)).unwrap_or(common);
+        let dst_state = known.or(dst_expected).and_then(|state| self.legal_resource_state(state)).unwrap_or(common);
+
+        let mut subresources = Vec::new();
+        for r in regions {
+            let num_layers = r.src_subresource.layers.end - r.src_subresource.layers.start;
+            let planes = image
+                .planes(r.src_subresource.aspects)
+                .zip(image.planes(r.dst_subresource.aspects));
+            for (src_plane, dst_plane) in planes {
+                for layer in 0 .. num_layers {
+                    let src_layer = r.src_subresource.layers.start + layer;
+                    let dst_layer = r.dst_subresource.layers.start + layer;
+                    subresources.push((
+                        image.calc_subresource(r.src_subresource.level as _, src_layer as _, src_plane),
+                        src_state,
+                        d3d12::D3D12_RESOURCE_STATE_COPY_SOURCE,
+                    ));
+                    subresources.push((
+                        image.calc_subresource(r.dst_subresource.level as _, dst_layer as _, dst_plane),
+                        dst_state,
+                        d3d12::D3D12_RESOURCE_STATE_COPY_DEST,
+                    ));
+                }
+            }
+        }
+        subresources.sort_by_key(|&(subresource, _, _)| subresource);
+        subresources.dedup_by_key(|&mut (subresource, _, _)| subresource);
+
+        let mut restore = Vec::new();
+        for &(subresource, current, required) in &subresources {
+            if current == required {
+                continue;
+            }
+            let barrier = |before, after| Self::transition_barrier(
+                d3d12::D3D12_RESOURCE_TRANSITION_BARRIER {
+                    pResource: image.resource,
+                    Subresource: subresource,
+                    StateBefore: before,
+                    StateAfter: after,
+                }
+            );
+            self.barriers.push(barrier(current, required));
+            restore.push(barrier(required, current));
+        }
+        self.flush_barriers();
+        (restore, known)
+    }
+
+    /// Transition a tracked resource into the state required by the next access.
+    ///
+    /// Read-only states are combined to avoid transitions between reads. The barrier
+    /// is batched, `flush_barriers` needs to be called before the access.
+    fn track_state(
+        &mut self,
+        resource: *mut d3d12::ID3D12Resource,
+        tracking: n::Tracking,
+        required: d3d12::D3D12_RESOURCE_STATES,
+    ) {
+        let shared = match tracking {
+            n::Tracking::None => return,
+            n::Tracking::Buffer => None,
+            n::Tracking::Image(state) => Some(unsafe { &*state }),
+        };
+        self.sync_resource_states();
+        let required = match self.legal_resource_state(required) {
+            Some(required) => required,
+            None => return,
+        };
+        let current = match self.resource_states.get(&resource) {
+            Some(&current) => current,
+            None => shared.map_or(d3d12::D3D12_RESOURCE_STATE_COMMON, |state| state.load(Ordering::Acquire) as _),
+        };
+        // Images left in other states by another queue are reported and assumed in `COMMON`.
+        let current = self.legal_resource_state(current).unwrap_or(d3d12::D3D12_RESOURCE_STATE_COMMON);
+        if let Some(state) = shared {
+            self.entry_states.entry(resource).or_insert((state as *const _, current));
+        }
+        let target = if (current | required) & WRITE_STATES == 0 {
+            current | required
+        } else {
+            required
+        };
+
+        if target == current {
+            if current == d3d12::D3D12_RESOURCE_STATE_UNORDERED_ACCESS {
+                // Unordered accesses are serialized, like in D3D11.
+                let mut barrier = d3d12::D3D12_RESOURCE_BARRIER {
+                    Type: d3d12::D3D12_RESOURCE_BARRIER_TYPE_UAV,
+                    Flags: d3d12::D3D12_RESOURCE_BARRIER_FLAG_NONE,
+                    u: unsafe { mem::zeroed() },
+                };
+                *unsafe { barrier.u.UAV_mut() } = d3d12::D3D12_RESOURCE_UAV_BARRIER {
+                    pResource: resource,
+                };
+                self.barriers.push(barrier);
+            }
+            self.resource_states.insert(resource, current);
+        } else if current == d3d12::D3D12_RESOURCE_STATE_COMMON && shared.is_none() && Self::is_promotable(target, false) {
+            // Buffers are promoted implicitly, the state is tracked until they decay again.
+            self.resource_states.insert(resource, target);
+        } else {
+            // Images are always transitioned explicitly, promoted images would decay.
+            self.barriers.push(Self::transition_barrier(
+                d3d12::D3D12_RESOURCE_TRANSITION_BARRIER {
+                    pResource: resource,
+                    Subresource: d3d12::D3D12_RESOURCE_BARRIER_ALL_SUBRESOURCES,
+                    StateBefore: current,
+                    StateAfter: target,
+                }
+            ));
+        }
+
+        if let Some(state) = shared {
+            // Resources accessed on copy queues decay to `COMMON` after execution.
+            let end = if self.is_copy_list() { d3d12::D3D12_RESOURCE_STATE_COMMON } else { target };
+            state.store(end as _, Ordering::Release);
+        }
+    }
+
+    /// Transition the tracked images back into the states of their first use.
+    ///
+    /// The barriers recorded for tracked images expect the states the images had
+    /// during recording. Command buffers submitted multiple times restore them at the
+    /// end, so each submission finds the images in the expected states.
+    fn restore_entry_states(&mut self) {
+        if self.is_copy_list() {
+            // Resources decay to `COMMON` after the execution of copy lists.
+            return;
+        }
+        self.sync_resource_states();
+        let entry_states = mem::replace(&mut self.entry_states, HashMap::new());
+        for (&resource, &(shared, entry)) in &entry_states {
+            let shared = unsafe { &*shared };
+            let current = match self.resource_states.get(&resource) {
+                Some(&current) => current,
+                None => shared.load(Ordering::Acquire) as _,
+            };
+            if current != entry {
+                self.barriers.push(Self::transition_barrier(
+                    d3d12::D3D12_RESOURCE_TRANSITION_BARRIER {
+                        pResource: resource,
+                        Subresource: d3d12::D3D12_RESOURCE_BARRIER_ALL_SUBRESOURCES,
+                        StateBefore: current,
+                        StateAfter: entry,
+                    }
+                ));
+            }
+            shared.store(entry as _, Ordering::Release);
+        }
+        self.flush_barriers();
+        self.entry_states = entry_states;
+    }
+
+    /// Transition the tracked resources of the descriptor sets bound last.
+    ///
+    /// The barriers are issued with the next draw or dispatch.
+    fn track_descriptors(&mut self) {
+        let tracked = mem::replace(&mut self.tracked_descriptors, Vec::new());
+        for desc in &tracked {
+            self.track_state(desc.resource, desc.tracking, desc.state);
+        }
+        self.tracked_descriptors = tracked;
+        self.tracked_descriptors.clear();
+    }
+
+    /// Check if a resource in `COMMON` state is implicitly promoted to `state` on access.
+    ///
+    /// Buffers can be promoted to any state except depth states, textures without
+    /// simultaneous access only to copy and shader resource states.
+    fn is_promotable(state: d3d12::D3D12_RESOURCE_STATES, is_texture: bool) -> bool {
+        if is_texture {
+            let promotable = d3d12::D3D12_RESOURCE_STATE_COPY_SOURCE |
+                d3d12::D3D12_RESOURCE_STATE_COPY_DEST |
+                d3d12::D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE |
+                d3d12::D3D12_RESOURCE_STATE_NON_PIXEL_SHADER_RESOURCE;
+            state & !promotable == 0
+        } else {
+            state & (d3d12::D3D12_RESOURCE_STATE_DEPTH_READ | d3d12::D3D12_RESOURCE_STATE_DEPTH_WRITE) == 0
+        }
+    }
+
+    fn bind_targets(&mut self) {
+        // Barriers of the previous subpass and the current one are issued together.
+        self.flush_barriers();
+        self.dirty_state |= DirtyState::TARGETS;
+        if self.native_render_passes {
+            // The pass begins with the first draw, commands which would interrupt
+            // it are issued ahead of it until then.
+            self.native_pass = NativePass::Pending;
+            self.hoisted_clears.clear();
+            return;
+        }
+
+        let state = self.pass_cache.as_ref().unwrap();
+        let subpass = &state.render_pass.subpasses[self.cur_subpass];
+
+        // collect render targets
+        let color_views = subpass.color_attachments
+            .iter()
+            .map(|&(id, _)| state.framebuffer.attachments[id].handle_rtv.unwrap())
+            .collect::<Vec<_>>();
+        let ds_view = match subpass.depth_stencil_attachment {
+            Some((id, _)) => state.framebuffer.attachments[id].handle_dsv.as_ref().unwrap() as *const _,
+            None => ptr::null(),
+        };
+        // set render targets
+        unsafe {
+            self.raw.OMSetRenderTargets(
+                color_views.len() as UINT,
+                color_views.as_ptr(),
+                FALSE,
+                ds_view,
+            );
+            if subpass.view_mask != 0 {
+                self.raw_list1().SetViewInstanceMask(subpass.view_mask);
+            }
+        }
+
+        // performs discards and clears for all the attachments first used in this subpass
+        for (i, (view, clear)) in state.framebuffer.attachments.iter().zip(state.attachment_clears.iter()).enumerate() {
+            if clear.subpass_id != Some(self.cur_subpass) {
+                continue;
+            }
+
+            if clear.discard_load && subpass.is_target(i) {
+                // Hint that the previous contents are not needed, which avoids loading
+                // them on tiled hardware. Transient attachments need to be initialized
+                // entirely after aliasing.
+                let area = if view.is_transient { None } else { Some(&state.target_rect) };
+                self.discard_view(view, area);
+            }
+
+            if let (Some(handle), Some(cv)) = (view.handle_rtv, clear.value) {
+                self.clear_render_target_view(handle, unsafe { cv.color }, &[state.target_rect]);
+            }
+
+            if let Some(handle) = view.handle_dsv {
+                let depth = clear.value.map(|cv| unsafe { cv.depth_stencil.depth });
                 let stencil = clear.stencil_value;
 
                 if depth.is_some() || stencil.is_some() {
@@ -414,6 +1895,335 @@ impl CommandBuffer {
         }
     }
 
+    /// Begin a native render pass for the current subpass.
+    ///
+    /// Attachments first used in the subpass are cleared or discarded according to their
+    /// load operations, unless the pass `resume`s a subpass which has been interrupted.
+    /// Clears hoisted from `clear_attachments` replace the load operations of the
+    /// attachments they cover. Contents are always preserved at the end of the pass,
+    /// as subpasses may be interrupted by commands not allowed inside render passes.
+    /// Discards for store operations are issued by `discard_targets` afterwards.
+    fn begin_native_pass(&self, resume: bool, flags: ext::D3D12_RENDER_PASS_FLAGS) {
+        let state = self.pass_cache.as_ref().unwrap();
+        let subpass = &state.render_pass.subpasses[self.cur_subpass];
+        let hoisted = |id: pass::AttachmentId| {
+            self.hoisted_clears
+                .iter()
+                .filter(move |&&(attachment, _)| !resume && attachment == id)
+                .map(|&(_, clear)| clear)
+        };
+
+        // Beginning access of an attachment plane, depending on its load operation.
+        let beginning_access = |id: pass::AttachmentId, load: pass::AttachmentLoadOp, clear_value: d3d12::D3D12_CLEAR_VALUE, hoisted: bool| {
+            let attachment = &state.render_pass.attachments[id];
+            let is_first_use = state.attachment_clears[id].subpass_id == Some(self.cur_subpass);
+            let ty = if hoisted {
+                ext::D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE_CLEAR
+            } else if resume || !is_first_use {
+                ext::D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE_PRESERVE
+            } else if load == pass::AttachmentLoadOp::Clear {
+                ext::D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE_CLEAR
+            } else if load == pass::AttachmentLoadOp::DontCare ||
+                attachment.layouts.start == image::Layout::Undefined
+            {
+                ext::D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE_DISCARD
+            } else {
+                ext::D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE_PRESERVE
+            };
+            ext::D3D12_RENDER_PASS_BEGINNING_ACCESS {
+                Type: ty,
+                Clear: ext::D3D12_RENDER_PASS_BEGINNING_ACCESS_CLEAR_PARAMETERS {
+                    ClearValue: clear_value,
+                },
+            }
+        };
+        let no_beginning_access = ext::D3D12_RENDER_PASS_BEGINNING_ACCESS {
+            Type: ext::D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE_NO_ACCESS,
+            Clear: unsafe { mem::zeroed() },
+        };
+        let ending_access = |ty| ext::D3D12_RENDER_PASS_ENDING_ACCESS {
+            Type: ty,
+            Resolve: unsafe { mem::zeroed() },
+        };
+
+        let render_targets = subpass.color_attachments
+            .iter()
+            .map(|&(id, _)| {
+                let attachment = &state.render_pass.attachments[id];
+                let mut clear_value = d3d12::D3D12_CLEAR_VALUE {
+                    Format: attachment.format
+                        .and_then(conv::map_format)
+                        .unwrap_or(dxgiformat::DXGI_FORMAT_UNKNOWN),
+                    u: unsafe { mem::zeroed() },
+                };
+                if let Some(cv) = state.attachment_clears[id].value {
+                    *unsafe { clear_value.u.Color_mut() } = unsafe { cv.color.float32 };
+                }
+                let mut is_hoisted = false;
+                for clear in hoisted(id) {
+                    if let HoistedClear::Color(color) = clear {
+                        *unsafe { clear_value.u.Color_mut() } = unsafe { color.float32 };
+                        is_hoisted = true;
+                    }
+                }
+
+                ext::D3D12_RENDER_PASS_RENDER_TARGET_DESC {
+                    cpuDescriptor: state.framebuffer.attachments[id].handle_rtv.unwrap(),
+                    BeginningAccess: beginning_access(id, attachment.ops.load, clear_value, is_hoisted),
+                    EndingAccess: ending_access(ext::D3D12_RENDER_PASS_ENDING_ACCESS_TYPE_PRESERVE),
+                }
+            })
+            .collect::<SmallVec<[_; 8]>>();
+
+        let depth_stencil = subpass.depth_stencil_attachment.map(|(id, _)| {
+            let attachment = &state.render_pass.attachments[id];
+            let clear = &state.attachment_clears[id];
+            let aspects = attachment.format.map_or(Aspects::DEPTH | Aspects::STENCIL, |format| format.aspects());
+            let mut clear_value = d3d12::D3D12_CLEAR_VALUE {
+                Format: attachment.format
+                    .and_then(|format| conv::map_format_dsv(format.base_format().0))
+                    .unwrap_or(dxgiformat::DXGI_FORMAT_UNKNOWN),
+                u: unsafe { mem::zeroed() },
+            };
+            let mut value = d3d12::D3D12_DEPTH_STENCIL_VALUE {
+                Depth: clear.value.map_or(0.0, |cv| unsafe { cv.depth_stencil.depth }),
+                Stencil: clear.stencil_value.unwrap_or(0) as _,
+            };
+            let (mut depth_hoisted, mut stencil_hoisted) = (false, false);
+            for clear in hoisted(id) {
+                match clear {
+                    HoistedClear::Depth(depth) => {
+                        value.Depth = depth;
+                        depth_hoisted = true;
+                    }
+                    HoistedClear::Stencil(stencil) => {
+                        value.Stencil = stencil as _;
+                        stencil_hoisted = true;
+                    }
+                    HoistedClear::Color(_) => {}
+                }
+            }
+            *unsafe { clear_value.u.DepthStencil_mut() } = value;
+
+            let (depth_beginning, depth_ending) = if aspects.contains(Aspects::DEPTH) {
+                (
+                    beginning_access(id, attachment.ops.load, clear_value, depth_hoisted),
+                    ending_access(ext::D3D12_RENDER_PASS_ENDING_ACCESS_TYPE_PRESERVE),
+                )
+            } else {
+                (no_beginning_access, ending_access(ext::D3D12_RENDER_PASS_ENDING_ACCESS_TYPE_NO_ACCESS))
+            };
+            let (stencil_beginning, stencil_ending) = if aspects.contains(Aspects::STENCIL) {
+                (
+                    beginning_access(id, attachment.stencil_ops.load, clear_value, stencil_hoisted),
+                    ending_access(ext::D3D12_RENDER_PASS_ENDING_ACCESS_TYPE_PRESERVE),
+                )
+            } else {
+                (no_beginning_access, ending_access(ext::D3D12_RENDER_PASS_ENDING_ACCESS_TYPE_NO_ACCESS))
+            };
+
+            ext::D3D12_RENDER_PASS_DEPTH_STENCIL_DESC {
+                cpuDescriptor: state.framebuffer.attachments[id].handle_dsv.unwrap(),
+                DepthBeginningAccess: depth_beginning,
+                StencilBeginningAccess: stencil_beginning,
+                DepthEndingAccess: depth_ending,
+                StencilEndingAccess: stencil_ending,
+            }
+        });
+
+        let raw = self.raw_list4();
+        unsafe {
+            if subpass.view_mask != 0 {
+                self.raw_list1().SetViewInstanceMask(subpass.view_mask);
+            }
+            // Shaders may write to storage resources during the pass.
+            raw.BeginRenderPass(
+                render_targets.len() as _,
+                render_targets.as_ptr(),
+                depth_stencil.as_ref().map_or(ptr::null(), |ds| ds as *const _),
+                ext::D3D12_RENDER_PASS_FLAG_ALLOW_UAV_WRITES | flags,
+            );
+        }
+    }
+
+    /// End the current subpass, discarding attachments not needed anymore.
+    fn end_subpass(&mut self) {
+        if self.native_pass == NativePass::Pending {
+            // Nothing was drawn, the pass still applies the load operations.
+            self.flush_native_pass(0);
+        }
+        if self.native_pass == NativePass::Active {
+            unsafe { self.raw_list4().EndRenderPass(); }
+            self.native_pass = NativePass::Inactive;
+        }
+        // Barriers and clears recorded inside the subpass precede the discards.
+        self.flush_barriers();
+        self.flush_deferred_clears();
+        self.discard_targets();
+    }
+
+    /// Continue a subpass of the primary command buffer in a secondary one.
+    ///
+    /// The primary command buffer applies the load and store operations of the
+    /// attachments. Native render passes are resumed from the suspended pass.
+    fn continue_render_pass(
+        &mut self,
+        render_pass: &n::RenderPass,
+        subpass: pass::SubpassId,
+        framebuffer: &n::Framebuffer,
+    ) {
+        let extent = framebuffer.attachments.first().map_or(image::Extent { width: 0, height: 0, depth: 0 }, |view| view.extent);
+        self.pass_cache = Some(RenderPassCache {
+            render_pass: render_pass.clone(),
+            framebuffer: framebuffer.clone(),
+            target_rect: d3d12::D3D12_RECT {
+                left: 0,
+                top: 0,
+                right: extent.width as _,
+                bottom: extent.height as _,
+            },
+            attachment_clears: render_pass.attachments
+                .iter()
+                .map(|_| AttachmentClear {
+                    subpass_id: None,
+                    last_subpass_id: None,
+                    value: None,
+                    stencil_value: None,
+                    discard_load: false,
+                    discard_store: false,
+                })
+                .collect(),
+        });
+        self.cur_subpass = subpass;
+        if self.native_render_passes {
+            self.dirty_state |= DirtyState::TARGETS;
+            self.resume_native_pass();
+        } else {
+            self.bind_targets();
+        }
+    }
+
+    /// Clear `rects` of an attachment of the current subpass.
+    fn clear_attachment(&self, clear: &com::AttachmentClear, rects: &[(d3d12::D3D12_RECT, Range<image::Layer>)]) {
+        let pass_cache = self.pass_cache.as_ref().unwrap();
+        let subpass = &pass_cache.render_pass.subpasses[self.cur_subpass];
+        match *clear {
+            com::AttachmentClear::Color(index, cv) => {
+                let view = &pass_cache.framebuffer.attachments[subpass.color_attachments[index].0];
+                let color: com::ClearColorRaw = cv.into();
+                clear_view_rects(view.handle_rtv.unwrap(), &view.layer_rtvs, rects, |rtv, rects| {
+                    self.clear_render_target_view(rtv, color, rects)
+                });
+            }
+            _ => {
+                let (depth, stencil) = match *clear {
+                    com::AttachmentClear::Depth(depth) => (Some(depth), None),
+                    com::AttachmentClear::Stencil(stencil) => (None, Some(stencil)),
+                    com::AttachmentClear::DepthStencil(com::ClearDepthStencil(depth, stencil)) =>
+                        (Some(depth), Some(stencil)),
+                    com::AttachmentClear::Color(..) => unreachable!(),
+                };
+                let view = match subpass.depth_stencil_attachment {
+                    Some((id, _)) => &pass_cache.framebuffer.attachments[id],
+                    None => {
+                        warn!("Depth stencil clear without a depth stencil attachment in the subpass");
+                        return;
+                    }
+                };
+                clear_view_rects(view.handle_dsv.unwrap(), &view.layer_dsvs, rects, |dsv, rects| {
+                    self.clear_depth_stencil_view(dsv, depth, stencil, rects)
+                });
+            }
+        }
+    }
+
+    /// Fold a clear covering whole attachment views into the beginning accesses
+    /// of the pending native render pass, returns `false` if it covers only parts.
+    fn hoist_clear(&mut self, clear: com::AttachmentClear, rects: &[(d3d12::D3D12_RECT, Range<image::Layer>)]) -> bool {
+        let (id, hoisted) = {
+            let pass_cache = self.pass_cache.as_ref().unwrap();
+            let subpass = &pass_cache.render_pass.subpasses[self.cur_subpass];
+            let id = match clear {
+                com::AttachmentClear::Color(index, _) => subpass.color_attachments[index].0,
+                _ => match subpass.depth_stencil_attachment {
+                    Some((id, _)) => id,
+                    None => return false,
+                },
+            };
+            let view = &pass_cache.framebuffer.attachments[id];
+            let num_layers = cmp::max(cmp::max(view.layer_rtvs.len(), view.layer_dsvs.len()), 1) as image::Layer;
+            let covers_view = rects.iter().any(|&(rect, ref layers)| {
+                rect.left <= 0 && rect.top <= 0 &&
+                rect.right >= view.extent.width as i32 && rect.bottom >= view.extent.height as i32 &&
+                layers.start == 0 && layers.end >= num_layers
+            });
+            if !covers_view {
+                return false;
+            }
+            let hoisted = match clear {
+                com::AttachmentClear::Color(_, cv) => vec![HoistedClear::Color(cv.into())],
+                com::AttachmentClear::Depth(depth) => vec![HoistedClear::Depth(depth)],
+                com::AttachmentClear::Stencil(stencil) => vec![HoistedClear::Stencil(stencil)],
+                com::AttachmentClear::DepthStencil(com::ClearDepthStencil(depth, stencil)) =>
+                    vec![HoistedClear::Depth(depth), HoistedClear::Stencil(stencil)],
+            };
+            (id, hoisted)
+        };
+        // Later clears replace earlier ones of the same attachment plane.
+        for clear in hoisted {
+            self.hoisted_clears.retain(|&(attachment, other)| {
+                attachment != id || mem::discriminant(&other) != mem::discriminant(&clear)
+            });
+            self.hoisted_clears.push((id, clear));
+        }
+        true
+    }
+
+    /// Discard the attachments which are last used in the current subpass
+    /// and whose contents are not needed after the render pass.
+    fn discard_targets(&self) {
+        let state = self.pass_cache.as_ref().unwrap();
+        let subpass = &state.render_pass.subpasses[self.cur_subpass];
+        for (i, (view, clear)) in state.framebuffer.attachments.iter().zip(state.attachment_clears.iter()).enumerate() {
+            if clear.last_subpass_id == Some(self.cur_subpass) &&
+               clear.discard_store &&
+               subpass.is_target(i)
+            {
+                let area = if view.is_transient { None } else { Some(&state.target_rect) };
+                self.discard_view(view, area);
+            }
+        }
+    }
+
+    /// Discard the subresources of an attachment view, restricted to `area` if given.
+    ///
+    /// Only the layers and planes of the view are discarded, the rest of the image is untouched.
+    fn discard_view(&self, view: &n::ImageView, area: Option<&d3d12::D3D12_RECT>) {
+        let area = area.filter(|rect| {
+            rect.left > 0 || rect.top > 0 ||
+            rect.right < view.extent.width as i32 || rect.bottom < view.extent.height as i32
+        });
+        let (num_rects, rects) = match area {
+            Some(_) if view.handle_dsv.is_some() => {
+                // Discards of depth stencil views can't be restricted to rects,
+                // keep the contents outside of the render area.
+                return;
+            }
+            Some(rect) => (1, rect as *const _),
+            None => (0, ptr::null()),
+        };
+
+        for subresources in &view.attachment_subresources {
+            let region = d3d12::D3D12_DISCARD_REGION {
+                NumRects: num_rects,
+                pRects: rects,
+                FirstSubresource: subresources.start,
+                NumSubresources: subresources.end - subresources.start,
+            };
+            unsafe { self.raw.DiscardResource(view.resource, &region); }
+        }
+    }
+
     fn clear_render_target_view(
         &self,
         rtv: d3d12::D3D12_CPU_DESCRIPTOR_HANDLE,
@@ -453,85 +2263,292 @@ impl CommandBuffer {
         } else {
             ptr::null()
         };
-
+
+        unsafe {
+            self.raw.clone().ClearDepthStencilView(
+                dsv,
+                flags,
+                depth.unwrap_or_default(),
+                stencil.unwrap_or_default() as _,
+                num_rects,
+                rects,
+            );
+        }
+    }
+
+    /// Prepare the graphics state for `command`, returns `false` if no pipeline is bound.
+    fn set_graphics_bind_point(&mut self, command: &'static str) -> bool {
+        if self.gr_pipeline.pipeline.is_none() {
+            self.record_error(RecordingError::NoPipelineBound(command));
+            return false;
+        }
+        if self.viewport_cache.len() < self.viewport_count || self.scissor_cache.len() < self.viewport_count {
+            let count = self.viewport_count;
+            self.record_error(RecordingError::MissingViewports(command, count));
+            return false;
+        }
+
+        if self.active_bindpoint != BindPoint::Graphics {
+            // Switch to graphics bind point
+            self.dirty_state |= DirtyState::PIPELINE;
+            self.active_bindpoint = BindPoint::Graphics;
+        }
+
+        if self.dirty_state.intersects(DirtyState::PIPELINE | DirtyState::TARGETS) && !self.is_pipeline_compatible() {
+            self.record_error(RecordingError::IncompatiblePipeline(command));
+            return false;
+        }
+
+        if !self.dirty_state.is_empty() {
+            self.flush_graphics_state();
+        }
+        // Barriers and clears deferred in a native render pass are issued with the draw.
+        self.flush_native_pass(0);
+        self.flush_barriers();
+
+        let cmd_buffer = &mut self.raw;
+        // Flush root signature data
+        Self::flush_user_data(
+            &mut self.gr_pipeline,
+            |slot, data, offset| unsafe {
+                cmd_buffer.clone().SetGraphicsRoot32BitConstants(
+                    slot,
+                    data.len() as _,
+                    data.as_ptr() as *const _,
+                    offset,
+                )
+            },
+            |slot, gpu| unsafe {
+                cmd_buffer.clone().SetGraphicsRootDescriptorTable(slot, gpu);
+            },
+            |slot, address| unsafe {
+                cmd_buffer.clone().SetGraphicsRootConstantBufferView(slot, address);
+            },
+            |slot, address| unsafe {
+                cmd_buffer.clone().SetGraphicsRootUnorderedAccessView(slot, address);
+            },
+        );
+        true
+    }
+
+    /// Check if the formats of the bound graphics pipeline are compatible
+    /// with the attachments of the current subpass.
+    fn is_pipeline_compatible(&self) -> bool {
+        match self.pass_cache {
+            Some(ref state) => {
+                let subpass = &state.render_pass.subpasses[self.cur_subpass];
+                let formats = pass::AttachmentFormats::new(
+                    &state.render_pass.attachments,
+                    &subpass.color_attachments,
+                    subpass.depth_stencil_attachment.as_ref(),
+                );
+                self.gr_formats.is_compatible(&formats)
+            }
+            None => true,
+        }
+    }
+
+    fn flush_graphics_state(&mut self) {
+        if self.dirty_state.contains(DirtyState::PIPELINE) {
+            let (pipeline, _) = self.gr_pipeline.pipeline.expect("No graphics pipeline bound");
+            unsafe { self.raw.SetPipelineState(pipeline); }
+        }
+
+        if self.dirty_state.contains(DirtyState::VERTEX_BUFFERS) {
+            // We currently don't support offsets for vertex buffer binding, therefore,
+            // we only need to find out how many vertex buffer we need to bind.
+            let num_vbs = self.vertex_buffer_views
+                .iter()
+                .position(|view| view.SizeInBytes == 0)
+                .unwrap_or(MAX_VERTEX_BUFFERS);
+
+            unsafe {
+                self.raw.IASetVertexBuffers(
+                    0,
+                    num_vbs as _,
+                    self.vertex_buffer_views.as_ptr(),
+                );
+            }
+        }
+
+        if self.dirty_state.contains(DirtyState::VIEWPORTS) {
+            unsafe {
+                self.raw.RSSetViewports(
+                    self.viewport_cache.len() as _,
+                    self.viewport_cache.as_ptr(),
+                );
+            }
+        }
+
+        if self.dirty_state.contains(DirtyState::SCISSORS) {
+            unsafe {
+                self.raw
+                    .RSSetScissorRects(self.scissor_cache.len() as _, self.scissor_cache.as_ptr())
+            };
+        }
+
+        self.dirty_state = DirtyState::empty();
+    }
+
+    /// Record `count` indirect command sequences of `signature`, which are
+    /// `stride` bytes apart, reading the arguments from `buffer` at `offset`.
+    /// Returns `false` if the command was skipped.
+    ///
+    /// The buffer usage and argument range are checked here as the debug layer
+    /// would only report them on execution, if at all, often as a device removal.
+    /// The argument buffer is transitioned before preparing the `bind_point`, so its
+    /// barrier is issued along with the others of a native render pass.
+    fn record_indirect(
+        &mut self,
+        command: &'static str,
+        bind_point: BindPoint,
+        signature: *mut d3d12::ID3D12CommandSignature,
+        stride: u32,
+        buffer: &n::Buffer,
+        offset: buffer::Offset,
+        count: u32,
+    ) -> bool {
+        if !buffer.usage.contains(buffer::Usage::INDIRECT) {
+            self.record_error(RecordingError::MissingUsage(command, buffer::Usage::INDIRECT));
+            return false;
+        }
+        if offset % 4 != 0 {
+            self.record_error(RecordingError::UnalignedOffset(command, offset, 4));
+            return false;
+        }
+        let end = offset + count as buffer::Offset * stride as buffer::Offset;
+        if end > buffer.size_in_bytes as buffer::Offset {
+            self.record_error(RecordingError::OutOfBounds(command));
+            return false;
+        }
+        self.track_state(buffer.resource, buffer.tracking(), d3d12::D3D12_RESOURCE_STATE_INDIRECT_ARGUMENT);
+        let bound = match bind_point {
+            BindPoint::Graphics => self.set_graphics_bind_point(command),
+            BindPoint::Compute => self.set_compute_bind_point(command),
+            BindPoint::RayTracing => unreachable!(),
+        };
+        if !bound {
+            return false;
+        }
         unsafe {
-            self.raw.clone().ClearDepthStencilView(
-                dsv,
-                flags,
-                depth.unwrap_or_default(),
-                stencil.unwrap_or_default() as _,
-                num_rects,
-                rects,
+            self.raw.ExecuteIndirect(
+                signature,
+                count,
+                buffer.resource,
+                offset,
+                ptr::null_mut(),
+                0,
             );
         }
+        true
     }
 
-    fn set_graphics_bind_point(&mut self) {
-        if self.active_bindpoint != BindPoint::Graphics {
-            // Switch to graphics bind point
-            let (pipeline, _) = self.gr_pipeline.pipeline.expect("No graphics pipeline bound");
+    /// Prepare the compute state for `command`, returns `false` if no pipeline is bound.
+    fn set_compute_bind_point(&mut self, command: &'static str) -> bool {
+        let (pipeline, signature) = match self.comp_pipeline.pipeline {
+            Some(pipeline) => pipeline,
+            None => {
+                self.record_error(RecordingError::NoPipelineBound(command));
+                return false;
+            }
+        };
+
+        if self.active_bindpoint != BindPoint::Compute {
+            // Switch to compute bind point
+            if self.rt_root_bound {
+                // Restore the compute root signature overwritten by ray tracing.
+                unsafe { self.raw.SetComputeRootSignature(signature); }
+                self.comp_pipeline.user_data.dirty_mask = !0;
+                self.rt_root_bound = false;
+            }
             unsafe { self.raw.SetPipelineState(pipeline); }
-            self.active_bindpoint = BindPoint::Graphics;
+            self.active_bindpoint = BindPoint::Compute;
         }
+        self.flush_barriers();
 
         let cmd_buffer = &mut self.raw;
-
-        // Bind vertex buffers
-        // We currently don't support offsets for vertex buffer binding, therefore,
-        // we only need to find out how many vertex buffer we need to bind.
-        let num_vbs = self.vertex_buffer_views
-            .iter()
-            .position(|view| view.SizeInBytes == 0)
-            .unwrap_or(MAX_VERTEX_BUFFERS);
-
-        unsafe {
-            cmd_buffer.IASetVertexBuffers(
-                0,
-                num_vbs as _,
-                self.vertex_buffer_views.as_ptr(),
-            );
-        }
-        // Flush root signature data
         Self::flush_user_data(
-            &mut self.gr_pipeline,
-            |slot, data| unsafe {
-                cmd_buffer.clone().SetGraphicsRoot32BitConstants(
+            &mut self.comp_pipeline,
+            |slot, data, offset| unsafe {
+                cmd_buffer.clone().SetComputeRoot32BitConstants(
                     slot,
                     data.len() as _,
                     data.as_ptr() as *const _,
-                    0,
+                    offset,
                 )
             },
             |slot, gpu| unsafe {
-                cmd_buffer.clone().SetGraphicsRootDescriptorTable(slot, gpu);
+                cmd_buffer.clone().SetComputeRootDescriptorTable(slot, gpu);
+            },
+            |slot, address| unsafe {
+                cmd_buffer.clone().SetComputeRootConstantBufferView(slot, address);
+            },
+            |slot, address| unsafe {
+                cmd_buffer.clone().SetComputeRootUnorderedAccessView(slot, address);
             },
         );
+        true
     }
 
-    fn set_compute_bind_point(&mut self) {
-        if self.active_bindpoint != BindPoint::Compute {
-            // Switch to compute bind point
-            assert!(self.comp_pipeline.pipeline.is_some(), "No compute pipeline bound");
-            let (pipeline, _) = self.comp_pipeline.pipeline.unwrap();
-            unsafe { self.raw.SetPipelineState(pipeline); }
-            self.active_bindpoint = BindPoint::Compute;
+    /// Prepare the ray tracing state, returns `false` if no pipeline is bound.
+    fn set_ray_tracing_bind_point(&mut self) -> bool {
+        let signature = match self.rt_pipeline.pipeline {
+            Some((_, signature)) => signature,
+            None => {
+                self.record_error(RecordingError::NoPipelineBound("trace_rays"));
+                return false;
+            }
+        };
+
+        if self.active_bindpoint != BindPoint::RayTracing {
+            // Switch to ray tracing bind point
+            if !self.rt_root_bound {
+                // Restore the ray tracing root signature overwritten by compute.
+                unsafe { self.raw.SetComputeRootSignature(signature); }
+                self.rt_pipeline.user_data.dirty_mask = !0;
+                self.rt_root_bound = true;
+            }
+            unsafe { self.raw_list4().SetPipelineState1(self.rt_state_object); }
+            self.active_bindpoint = BindPoint::RayTracing;
         }
+        self.flush_barriers();
 
         let cmd_buffer = &mut self.raw;
         Self::flush_user_data(
-            &mut self.comp_pipeline,
-            |slot, data| unsafe {
+            &mut self.rt_pipeline,
+            |slot, data, offset| unsafe {
                 cmd_buffer.clone().SetComputeRoot32BitConstants(
                     slot,
                     data.len() as _,
                     data.as_ptr() as *const _,
-                    0,
+                    offset,
                 )
             },
             |slot, gpu| unsafe {
                 cmd_buffer.clone().SetComputeRootDescriptorTable(slot, gpu);
             },
+            |slot, address| unsafe {
+                cmd_buffer.clone().SetComputeRootConstantBufferView(slot, address);
+            },
+            |slot, address| unsafe {
+                cmd_buffer.clone().SetComputeRootUnorderedAccessView(slot, address);
+            },
         );
+        true
+    }
+
+    // Depth bounds and view instancing commands are only exposed by newer command list interfaces.
+    fn raw_list1(&self) -> &ComPtr<ext::ID3D12GraphicsCommandList1> {
+        self.raw1
+            .as_ref()
+            .expect("ID3D12GraphicsCommandList1 is not supported by the device")
+    }
+
+    // Native render pass and ray tracing commands are only exposed by newer command list interfaces.
+    fn raw_list4(&self) -> &ComPtr<ext::ID3D12GraphicsCommandList4> {
+        self.raw4
+            .as_ref()
+            .expect("ID3D12GraphicsCommandList4 is not supported by the device")
     }
 
     fn push_constants(
@@ -553,13 +2570,17 @@ impl CommandBuffer {
         }
     }
 
-    fn flush_user_data<F, G>(
+    fn flush_user_data<F, G, H, K>(
         pipeline: &mut PipelineCache,
         mut constants_update: F,
         mut table_update: G,
+        mut cbv_update: H,
+        mut uav_update: K,
     ) where
-        F: FnMut(u32, &[u32]),
+        F: FnMut(u32, &[u32], u32),
         G: FnMut(u32, d3d12::D3D12_GPU_DESCRIPTOR_HANDLE),
+        H: FnMut(u32, d3d12::D3D12_GPU_VIRTUAL_ADDRESS),
+        K: FnMut(u32, d3d12::D3D12_GPU_VIRTUAL_ADDRESS),
     {
         let user_data = &mut pipeline.user_data;
         if user_data.dirty_mask == 0 {
@@ -567,32 +2588,44 @@ impl CommandBuffer {
         }
 
         let num_root_constant = pipeline.root_constants.len();
-        let mut cur_index = 0;
-        // TODO: opt: Only set dirty root constants?
-        for (i, root_constant) in pipeline.root_constants.iter().enumerate() {
-            let num_constants = (root_constant.range.end-root_constant.range.start) as usize;
-            let mut data = Vec::new();
-            for c in cur_index..cur_index+num_constants {
-                data.push(match user_data.data[c] {
-                    RootElement::Constant(v) => v,
-                    _ => {
-                        warn!("Unset or mismatching root constant at index {:?} ({:?})", c, user_data.data[c]);
-                        0
-                    }
-                });
-                user_data.clear_dirty(c);
-            }
-            constants_update(i as _, &data);
-            cur_index += num_constants;
-        }
-
-        // Flush descriptor tables
         // Index in the user data array where tables are starting
         let table_start = pipeline
             .root_constants
             .iter()
             .fold(0, |sum, c| sum + c.range.end - c.range.start) as usize;
 
+        // Flush root constants, only uploading contiguous runs of dirty DWORDs.
+        let constants_mask = if table_start >= ROOT_SIGNATURE_SIZE { !0 } else { (1u64 << table_start) - 1 };
+        if user_data.dirty_mask & constants_mask != 0 {
+            let mut data = [0u32; ROOT_SIGNATURE_SIZE];
+            let mut cur_index = 0;
+            for (i, root_constant) in pipeline.root_constants.iter().enumerate() {
+                let end_index = cur_index + (root_constant.range.end-root_constant.range.start) as usize;
+                let mut c = cur_index;
+                while c < end_index {
+                    if (user_data.dirty_mask >> c) & 1 == 0 {
+                        c += 1;
+                        continue
+                    }
+                    let run_start = c;
+                    while c < end_index && (user_data.dirty_mask >> c) & 1 == 1 {
+                        data[c - run_start] = match user_data.data[c] {
+                            RootElement::Constant(v) => v,
+                            _ => {
+                                warn!("Unset or mismatching root constant at index {:?} ({:?})", c, user_data.data[c]);
+                                0
+                            }
+                        };
+                        user_data.clear_dirty(c);
+                        c += 1;
+                    }
+                    constants_update(i as _, &data[.. c - run_start], (run_start - cur_index) as _);
+                }
+                cur_index = end_index;
+            }
+        }
+
+        // Flush descriptor tables and root descriptors
         for i in num_root_constant..pipeline.num_parameter_slots {
             let table_index = i - num_root_constant + table_start;
             if ((user_data.dirty_mask >> table_index) & 1) == 1 {
@@ -601,6 +2634,16 @@ impl CommandBuffer {
                         pipeline.srv_cbv_uav_start + offset as u64,
                     RootElement::TableSampler(offset) =>
                         pipeline.sampler_start + offset as u64,
+                    RootElement::RootCbv(address) => {
+                        cbv_update(i as _, address);
+                        user_data.clear_dirty(table_index);
+                        continue
+                    }
+                    RootElement::RootUav(address) => {
+                        uav_update(i as _, address);
+                        user_data.clear_dirty(table_index);
+                        continue
+                    }
                     other => {
                         error!("Unexpected user data element in the root signature ({:?})", other);
                         continue
@@ -627,192 +2670,55 @@ impl CommandBuffer {
     fn split_buffer_copy(
         copies: &mut Vec<Copy>, r: &com::BufferImageCopy, image: &n::Image
     ) {
-        let buffer_width = if r.buffer_width == 0 {
-            r.image_extent.width
-        } else {
-            r.buffer_width
-        };
-        let buffer_height = if r.buffer_height == 0 {
-            r.image_extent.height
-        } else {
-            r.buffer_height
-        };
-        let row_pitch = div(buffer_width, image.block_dim.0 as _) * image.bytes_per_block as u32;
-        let slice_pitch = div(buffer_height, image.block_dim.1 as _) * row_pitch;
-        let is_pitch_aligned = row_pitch % d3d12::D3D12_TEXTURE_DATA_PITCH_ALIGNMENT == 0;
-
-        for layer in r.image_layers.layers.clone() {
-            let img_subresource = image
-                .calc_subresource(r.image_layers.level as _, layer as _, 0);
-            let layer_offset = r.buffer_offset as u64 + (layer as u32 * slice_pitch * r.image_extent.depth) as u64;
-            let aligned_offset = layer_offset & !(d3d12::D3D12_TEXTURE_DATA_PLACEMENT_ALIGNMENT as u64 - 1);
-            if layer_offset == aligned_offset && is_pitch_aligned {
-                // trivial case: everything is aligned, ready for copying
-                copies.push(Copy {
-                    footprint_offset: aligned_offset,
-                    footprint: r.image_extent,
-                    row_pitch,
-                    img_subresource,
-                    img_offset: r.image_offset,
-                    buf_offset: image::Offset::ZERO,
-                    copy_extent: r.image_extent,
-                });
-            } else if is_pitch_aligned {
-                // buffer offset is not aligned
-                assert_eq!(image.block_dim, (1, 1)); // TODO
-                let row_pitch_texels = row_pitch / image.bytes_per_block as u32;
-                let gap = (layer_offset - aligned_offset) as i32;
-                let buf_offset = image::Offset {
-                    x: gap % row_pitch as i32,
-                    y: (gap % slice_pitch as i32) / row_pitch as i32,
-                    z: gap / slice_pitch as i32,
-                };
-                let footprint = image::Extent {
-                    width: buf_offset.x as u32 + r.image_extent.width,
-                    height: buf_offset.y as u32 + r.image_extent.height,
-                    depth: buf_offset.z as u32 + r.image_extent.depth,
-                };
-                if r.image_extent.width + buf_offset.x as u32 <= row_pitch_texels {
-                    // we can map it to the aligned one and adjust the offsets accordingly
-                    copies.push(Copy {
-                        footprint_offset: aligned_offset,
-                        footprint,
-                        row_pitch,
-                        img_subresource,
-                        img_offset: r.image_offset,
-                        buf_offset,
-                        copy_extent: r.image_extent,
-                    });
-                } else {
-                    // split the copy region into 2 that suffice the previous condition
-                    assert!(buf_offset.x as u32 <= row_pitch_texels);
-                    let half = row_pitch_texels - buf_offset.x as u32;
-                    assert!(half <= r.image_extent.width);
-
-                    copies.push(Copy {
-                        footprint_offset: aligned_offset,
-                        footprint: image::Extent {
-                            width: row_pitch_texels,
-                            .. footprint
-                        },
-                        row_pitch,
-                        img_subresource,
-                        img_offset: r.image_offset,
-                        buf_offset,
-                        copy_extent: image::Extent {
-                            width: half,
-                            .. r.image_extent
-                        },
-                    });
-                    copies.push(Copy {
-                        footprint_offset: aligned_offset,
-                        footprint: image::Extent {
-                            width: r.image_extent.width - half,
-                            height: footprint.height + 1,
-                            depth: footprint.depth,
-                        },
-                        row_pitch,
-                        img_subresource,
-                        img_offset: image::Offset {
-                            x: r.image_offset.x + half as i32,
-                            .. r.image_offset
-                        },
-                        buf_offset: image::Offset {
-                            x: 0,
-                            .. buf_offset
-                        },
-                        copy_extent: image::Extent {
-                            width: r.image_extent.width - half,
-                            .. r.image_extent
-                        },
-                    });
-                }
-            } else {
-                // worst case: row by row copy
-                assert_eq!(image.block_dim, (1, 1)); // TODO
-                for z in 0 .. r.image_extent.depth {
-                    for y in 0 .. r.image_extent.height {
-                        // an image row starts non-aligned
-                        let row_offset = layer_offset +
-                            z as u64 * slice_pitch as u64 +
-                            y as u64 * row_pitch as u64;
-                        let aligned_offset = row_offset & !(d3d12::D3D12_TEXTURE_DATA_PLACEMENT_ALIGNMENT as u64 - 1);
-                        let next_aligned_offset = aligned_offset + d3d12::D3D12_TEXTURE_DATA_PLACEMENT_ALIGNMENT as u64;
-                        let cut_row_texels = (next_aligned_offset - row_offset) / image.bytes_per_block as u64;
-                        let cut_width = cmp::min(r.image_extent.width, cut_row_texels as image::Size);
-                        let gap_texels = (row_offset - aligned_offset) as image::Size / image.bytes_per_block as image::Size;
-                        // this is a conservative row pitch that should be compatible with both copies
-                        let max_unaligned_pitch = r.image_extent.width * image.bytes_per_block as u32;
-                        let row_pitch = (max_unaligned_pitch | d3d12::D3D12_TEXTURE_DATA_PITCH_ALIGNMENT) + 1;
-
-                        copies.push(Copy {
-                            footprint_offset: aligned_offset,
-                            footprint: image::Extent {
-                                width: cut_width + gap_texels,
-                                height: 1,
-                                depth: 1,
-                            },
-                            row_pitch,
-                            img_subresource,
-                            img_offset: image::Offset {
-                                x: r.image_offset.x,
-                                y: r.image_offset.y + y as i32,
-                                z: r.image_offset.z + z as i32,
-                            },
-                            buf_offset: image::Offset {
-                                x: gap_texels as i32,
-                                y: 0,
-                                z: 0,
-                            },
-                            copy_extent: image::Extent {
-                                width: cut_width,
-                                height: 1,
-                                depth: 1,
-                            },
-                        });
-
-                        // and if it crosses a pitch alignment - we copy the rest separately
-                        if cut_width == r.image_extent.width {
-                            continue;
-                        }
-                        let leftover = r.image_extent.width - cut_width;
-
-                        copies.push(Copy {
-                            footprint_offset: next_aligned_offset,
-                            footprint: image::Extent {
-                                width: leftover,
-                                height: 1,
-                                depth: 1,
-                            },
-                            row_pitch,
-                            img_subresource,
-                            img_offset: image::Offset {
-                                x: r.image_offset.x + cut_width as i32,
-                                y: r.image_offset.y + y as i32,
-                                z: r.image_offset.z + z as i32,
-                            },
-                            buf_offset: image::Offset::ZERO,
-                            copy_extent: image::Extent {
-                                width: leftover,
-                                height: 1,
-                                depth: 1,
-                            },
-                        });
-                    }
-                }
-            }
-        }
+        // Buffer copies address a single aspect, stored in one plane.
+        let plane = image.planes(r.image_layers.aspects).start;
+        let (footprint_format, bytes_per_block) = image.plane_copy_format(plane);
+        split_buffer_image_copy(
+            copies,
+            r,
+            footprint_format,
+            bytes_per_block,
+            (image.block_dim.0 as _, image.block_dim.1 as _),
+            |layer| image.calc_subresource(r.image_layers.level as _, layer as _, plane),
+        );
     }
 }
 
 impl com::RawCommandBuffer<Backend> for CommandBuffer {
-    fn begin(&mut self, _flags: com::CommandBufferFlags, _info: com::CommandBufferInheritanceInfo<Backend>) {
-        // TODO: Implement flags and secondary command buffers (bundles).
+    fn begin(&mut self, flags: com::CommandBufferFlags, info: com::CommandBufferInheritanceInfo<Backend>) {
+        // Secondary command buffers are recorded into direct command lists as well,
+        // `execute_commands` executes them between the lists of the primary one.
         self.reset();
+        self.flags = flags;
+        if flags.contains(com::CommandBufferFlags::RENDER_PASS_CONTINUE) {
+            match (info.subpass, info.framebuffer) {
+                (Some(subpass), Some(framebuffer)) => {
+                    self.continue_render_pass(subpass.main_pass, subpass.index, framebuffer);
+                }
+                _ => {
+                    error!("Secondary command buffers continuing a render pass need the subpass and the framebuffer");
+                }
+            }
+        }
     }
 
-    fn finish(&mut self) {
+    fn finish(&mut self) -> Result<(), com::RecordingError> {
+        if !self.flags.contains(com::CommandBufferFlags::ONE_TIME_SUBMIT) {
+            self.restore_entry_states();
+        }
+        // Secondary command buffers continuing a native render pass suspend it,
+        // the next list executed by the primary command buffer resumes it.
+        self.suspend_native_pass();
+        self.flush_barriers();
+        // Closed lists can be executed repeatedly, they are only reset
+        // by `begin`, `reset` or resetting the pool.
         unsafe { self.raw.Close(); }
+        self.allocators.lock().unwrap().end_recording(self.base.as_raw());
+
+        match self.error {
+            Some(ref error) => Err(com::RecordingError { message: error.to_string() }),
+            None => Ok(()),
+        }
     }
 
     fn reset(&mut self, _release_resources: bool) {
@@ -830,12 +2736,15 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         T: IntoIterator,
         T::Item: Borrow<com::ClearValueRaw>,
     {
-        assert_eq!(framebuffer.attachments.len(), render_pass.attachments.len());
+        if framebuffer.attachments.len() != render_pass.attachments.len() {
+            self.record_error(RecordingError::FramebufferMismatch);
+            return;
+        }
         // Make sure that no subpass works with Present as intermediate layout.
         // This wouldn't make much sense, and proceeding with this constraint
         // allows the state transitions generated from subpass dependencies
         // to ignore the layouts completely.
-        assert!(!render_pass.subpasses.iter().any(|sp| {
+        debug_assert!(!render_pass.subpasses.iter().any(|sp| {
             sp.color_attachments
                 .iter()
                 .chain(sp.depth_stencil_attachment.iter())
@@ -844,20 +2753,33 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         }));
 
         let mut clear_iter = clear_values.into_iter();
+        let mut missing_clear = None;
         let attachment_clears = render_pass.attachments
             .iter()
             .enumerate()
             .map(|(i, attachment)| {
                 let cv = if attachment.ops.load == pass::AttachmentLoadOp::Clear || attachment.stencil_ops.load == pass::AttachmentLoadOp::Clear {
-                    Some(*clear_iter.next().unwrap().borrow())
+                    match clear_iter.next() {
+                        Some(cv) => Some(*cv.borrow()),
+                        None => {
+                            // Clear to zero instead, the error is recorded below.
+                            missing_clear = missing_clear.or(Some(i));
+                            Some(unsafe { mem::zeroed() })
+                        }
+                    }
                 } else {
                     None
                 };
 
+                // Contents of the attachment are not loaded if cleared, or undefined on load.
+                let undefined_on_load = |op: pass::AttachmentLoadOp| {
+                    op != pass::AttachmentLoadOp::Load || attachment.layouts.start == image::Layout::Undefined
+                };
+
                 AttachmentClear {
                     subpass_id: render_pass.subpasses.iter().position(|sp| sp.is_using(i)),
+                    last_subpass_id: render_pass.subpasses.iter().rposition(|sp| sp.is_using(i)),
                     value: if attachment.ops.load == pass::AttachmentLoadOp::Clear {
-                        assert!(cv.is_some());
                         cv
                     } else {
                         None
@@ -867,9 +2789,23 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                     } else {
                         None
                     },
+                    discard_load: undefined_on_load(attachment.ops.load) &&
+                        undefined_on_load(attachment.stencil_ops.load),
+                    discard_store: attachment.ops.store == pass::AttachmentStoreOp::DontCare &&
+                        attachment.stencil_ops.store == pass::AttachmentStoreOp::DontCare,
                 }
             }).collect();
 
+        if let Some(attachment) = missing_clear {
+            self.record_error(RecordingError::MissingClearValue(attachment));
+        }
+
+        // Tracked attachments are expected in the initial layout of the render pass.
+        for (view, attachment) in framebuffer.attachments.iter().zip(&render_pass.attachments) {
+            let state = conv::map_image_resource_state(image::Access::empty(), attachment.layouts.start);
+            self.track_state(view.resource, view.tracking, state);
+        }
+
         self.pass_cache = Some(RenderPassCache {
             render_pass: render_pass.clone(),
             framebuffer: framebuffer.clone(),
@@ -882,17 +2818,104 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
     }
 
     fn next_subpass(&mut self, _contents: com::SubpassContents) {
+        if self.pass_cache.is_none() {
+            self.record_error(RecordingError::OutsideRenderPass("next_subpass"));
+            return;
+        }
+        self.end_subpass();
         self.cur_subpass += 1;
         self.insert_subpass_barriers();
         self.bind_targets();
     }
 
     fn end_render_pass(&mut self) {
+        if self.pass_cache.is_none() {
+            self.record_error(RecordingError::OutsideRenderPass("end_render_pass"));
+            return;
+        }
+        self.end_subpass();
         self.cur_subpass = !0;
         self.insert_subpass_barriers();
+        {
+            let state = self.pass_cache.as_ref().unwrap();
+            let attachments = state.framebuffer.attachments.iter().zip(&state.render_pass.attachments);
+            for (view, attachment) in attachments {
+                if let n::Tracking::Image(shared) = view.tracking {
+                    let end = conv::map_image_resource_state(image::Access::empty(), attachment.layouts.end);
+                    unsafe { &*shared }.store(end as _, Ordering::Release);
+                }
+            }
+        }
         self.pass_cache = None;
     }
 
+    fn begin_rendering<'a, T>(
+        &mut self,
+        target_rect: pso::Rect,
+        colors: T,
+        depth_stencil: Option<pass::RenderingAttachment<'a, Backend>>,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<pass::RenderingAttachment<'a, Backend>>,
+    {
+        // Rendering is recorded as a render pass with a single subpass, which
+        // keeps the attachments in their layout and therefore needs no barriers.
+        let mut attachments = Vec::new();
+        let mut views = Vec::new();
+        let mut clear_values = Vec::new();
+        let (color_attachments, depth_stencil_attachment) = {
+            let mut add_attachment = |attachment: &pass::RenderingAttachment<Backend>| {
+                let id = attachments.len();
+                attachments.push(pass::Attachment {
+                    format: Some(attachment.format),
+                    ops: attachment.ops,
+                    stencil_ops: attachment.stencil_ops,
+                    layouts: attachment.layout .. attachment.layout,
+                });
+                views.push(*attachment.view);
+                if attachment.ops.load == pass::AttachmentLoadOp::Clear || attachment.stencil_ops.load == pass::AttachmentLoadOp::Clear {
+                    clear_values.push(com::ClearValueRaw::from(attachment.clear_value));
+                }
+                (id, attachment.layout)
+            };
+            let colors = colors
+                .into_iter()
+                .map(|attachment| add_attachment(attachment.borrow()))
+                .collect::<Vec<_>>();
+            (colors, depth_stencil.as_ref().map(add_attachment))
+        };
+
+        let render_pass = n::RenderPass {
+            attachments,
+            subpasses: vec![n::SubpassDesc {
+                color_attachments,
+                depth_stencil_attachment,
+                input_attachments: Vec::new(),
+                pre_barriers: Vec::new(),
+                view_mask: 0,
+            }],
+            post_barriers: Vec::new(),
+        };
+        let framebuffer = n::Framebuffer {
+            attachments: views,
+        };
+        self.begin_render_pass_raw(
+            &render_pass,
+            &framebuffer,
+            target_rect,
+            clear_values,
+            com::SubpassContents::Inline,
+        );
+    }
+
+    fn end_rendering(&mut self) {
+        if self.pass_cache.is_none() {
+            self.record_error(RecordingError::OutsideRenderPass("end_rendering"));
+            return;
+        }
+        self.end_render_pass();
+    }
+
     fn pipeline_barrier<'a, T>(
         &mut self,
         _stages: Range<pso::PipelineStage>,
@@ -902,11 +2925,17 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         T: IntoIterator,
         T::Item: Borrow<memory::Barrier<'a, Backend>>,
     {
-        let mut raw_barriers = Vec::new();
+        // The barriers are combined with the pending ones and issued
+        // before the next command accessing resources.
+        self.sync_resource_states();
 
         // transition barriers
         for barrier in barriers {
             match *barrier.borrow() {
+                memory::Barrier::AllBuffers(_) |
+                memory::Barrier::AllImages(_) if self.is_copy_list() => {
+                    // Copy command lists don't support UAV barriers.
+                }
                 memory::Barrier::AllBuffers(_) |
                 memory::Barrier::AllImages(_) => {
                     // Aliasing barrier with NULL resource is the closest we can get to
@@ -920,11 +2949,22 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                     *unsafe { bar.u.UAV_mut() } = d3d12::D3D12_RESOURCE_UAV_BARRIER {
                         pResource: ptr::null_mut(),
                     };
-                    raw_barriers.push(bar);
+                    self.barriers.push(bar);
+                }
+                memory::Barrier::Buffer { target, .. } if target.tracking() != n::Tracking::None => {
+                    // Tracked resources are transitioned automatically.
                 }
-                memory::Barrier::Buffer { ref states, target } => {
-                    let state_src = conv::map_buffer_resource_state(states.start);
-                    let state_dst = conv::map_buffer_resource_state(states.end);
+                memory::Barrier::Image { target, .. } if target.tracking() != n::Tracking::None => {}
+                memory::Barrier::Buffer { ref states, target, ref families } => {
+                    let (state_src, state_dst) = match self.transfer_states(
+                        families,
+                        conv::map_buffer_resource_state(states.start),
+                        conv::map_buffer_resource_state(states.end),
+                    ) {
+                        Some(states) => states,
+                        None => continue,
+                    };
+                    let state_src = self.source_state(target.resource, state_src, false);
 
                     if state_src == state_dst {
                         continue;
@@ -939,12 +2979,23 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                         }
                     );
 
-                    raw_barriers.push(bar);
+                    self.barriers.push(bar);
                 }
-                memory::Barrier::Image { ref states, target, ref range } => {
-                    let _ = range; //TODO: use subresource range
-                    let state_src = conv::map_image_resource_state(states.start.0, states.start.1);
-                    let state_dst = conv::map_image_resource_state(states.end.0, states.end.1);
+                memory::Barrier::Image { ref states, target, ref range, ref families } => {
+                    let (state_src, state_dst) = match self.transfer_states(
+                        families,
+                        conv::map_image_resource_state(states.start.0, states.start.1),
+                        conv::map_image_resource_state(states.end.0, states.end.1),
+                    ) {
+                        Some(states) => states,
+                        None => continue,
+                    };
+                    let whole_range = *range == target.to_subresource_range(target.surface_type.desc().aspects);
+                    let state_src = if whole_range {
+                        self.source_state(target.resource, state_src, states.start.1 == image::Layout::Undefined)
+                    } else {
+                        state_src
+                    };
 
                     if state_src == state_dst {
                         continue;
@@ -959,18 +3010,20 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                         }
                     );
 
-                    if *range == target.to_subresource_range(range.aspects) {
+                    if whole_range {
                         // Only one barrier if it affects the whole image.
-                        raw_barriers.push(bar);
+                        self.barriers.push(bar);
                     } else {
-                        // Generate barrier for each layer/level combination.
-                        for level in range.levels.clone() {
-                            for layer in range.layers.clone() {
-                                {
-                                    let transition_barrier = &mut *unsafe { bar.u.Transition_mut() };
-                                    transition_barrier.Subresource = target.calc_subresource(level as _, layer as _, 0);
+                        // Generate barrier for each plane/layer/level combination.
+                        for plane in target.planes(range.aspects) {
+                            for level in range.levels.clone() {
+                                for layer in range.layers.clone() {
+                                    {
+                                        let transition_barrier = &mut *unsafe { bar.u.Transition_mut() };
+                                        transition_barrier.Subresource = target.calc_subresource(level as _, layer as _, plane);
+                                    }
+                                    self.barriers.push(bar);
                                 }
-                                raw_barriers.push(bar);
                             }
                         }
                     }
@@ -978,6 +3031,11 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
             }
         }
 
+        if self.is_copy_list() {
+            // Only transition barriers between copy states are legal on copy command lists.
+            return;
+        }
+
         // UAV barriers
         //
         // TODO: Currently always add a global UAV barrier.
@@ -992,7 +3050,7 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
             *unsafe { barrier.u.UAV_mut() } = d3d12::D3D12_RESOURCE_UAV_BARRIER {
                 pResource: ptr::null_mut(),
             };
-            raw_barriers.push(barrier);
+            self.barriers.push(barrier);
         }
 
         // Alias barriers
@@ -1008,38 +3066,70 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                 pResourceBefore: ptr::null_mut(),
                 pResourceAfter: ptr::null_mut(),
             };
-            raw_barriers.push(barrier);
-        }
-
-        unsafe {
-            self.raw.ResourceBarrier(
-                raw_barriers.len() as _,
-                raw_barriers.as_ptr(),
-            );
+            self.barriers.push(barrier);
         }
     }
 
     fn clear_color_image_raw(
         &mut self,
         image: &n::Image,
-        _: image::Layout,
+        layout: image::Layout,
         range: image::SubresourceRange,
         value: com::ClearColorRaw,
     ) {
-        assert_eq!(range, image.to_subresource_range(Aspects::COLOR));
-        let rtv = image.clear_cv.unwrap();
+        debug_assert!(layout == image::Layout::General || layout == image::Layout::TransferDstOptimal);
+        if range != image.to_subresource_range(Aspects::COLOR) {
+            self.record_error(RecordingError::PartialClear("clear_color_image"));
+            return;
+        }
+        let rtv = match image.clear_cv {
+            Some(rtv) => rtv,
+            None => {
+                self.record_error(RecordingError::NotClearable("clear_color_image"));
+                return;
+            }
+        };
+        let expected = self.transfer_state("clear_color_image", layout, image::Access::TRANSFER_WRITE);
+        let previous = self.require_state(
+            image.resource,
+            image.tracking(),
+            expected,
+            d3d12::D3D12_RESOURCE_STATE_RENDER_TARGET,
+            true,
+        );
         self.clear_render_target_view(rtv, value, &[]);
+        self.restore_state(image.resource, previous);
     }
 
     fn clear_depth_stencil_image_raw(
         &mut self,
         image: &n::Image,
-        _layout: image::Layout,
+        layout: image::Layout,
         range: image::SubresourceRange,
         value: com::ClearDepthStencilRaw,
     ) {
-        assert!((Aspects::DEPTH | Aspects::STENCIL).contains(range.aspects));
-        assert_eq!(range, image.to_subresource_range(range.aspects));
+        debug_assert!(layout == image::Layout::General || layout == image::Layout::TransferDstOptimal);
+        if !(Aspects::DEPTH | Aspects::STENCIL).contains(range.aspects) ||
+            range != image.to_subresource_range(range.aspects)
+        {
+            self.record_error(RecordingError::PartialClear("clear_depth_stencil_image"));
+            return;
+        }
+        if (range.aspects.contains(Aspects::DEPTH) && image.clear_dv.is_none()) ||
+            (range.aspects.contains(Aspects::STENCIL) && image.clear_sv.is_none())
+        {
+            self.record_error(RecordingError::NotClearable("clear_depth_stencil_image"));
+            return;
+        }
+
+        let expected = self.transfer_state("clear_depth_stencil_image", layout, image::Access::TRANSFER_WRITE);
+        let previous = self.require_state(
+            image.resource,
+            image.tracking(),
+            expected,
+            d3d12::D3D12_RESOURCE_STATE_DEPTH_WRITE,
+            true,
+        );
         if range.aspects.contains(Aspects::DEPTH) {
             let dsv = image.clear_dv.unwrap();
             self.clear_depth_stencil_view(dsv, Some(value.depth), None, &[]);
@@ -1048,6 +3138,7 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
             let dsv = image.clear_sv.unwrap();
             self.clear_depth_stencil_view(dsv, None, Some(value.stencil as _), &[]);
         }
+        self.restore_state(image.resource, previous);
     }
 
     fn clear_attachments<T, U>(&mut self, clears: T, rects: U)
@@ -1055,64 +3146,78 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         T: IntoIterator,
         T::Item: Borrow<com::AttachmentClear>,
         U: IntoIterator,
-        U::Item: Borrow<pso::Rect>,
+        U::Item: Borrow<pso::ClearRect>,
     {
-        assert!(self.pass_cache.is_some(), "`clear_attachments` can only be called inside a renderpass");
-        let rects: SmallVec<[d3d12::D3D12_RECT; 16]> = rects.into_iter().map(|rect| get_rect(rect.borrow())).collect();
+        if self.pass_cache.is_none() {
+            self.record_error(RecordingError::OutsideRenderPass("clear_attachments"));
+            return;
+        }
+        // Rects are clamped to the render area, rects outside of it are skipped.
+        let rects = {
+            let target_rect = self.pass_cache.as_ref().unwrap().target_rect;
+            rects
+                .into_iter()
+                .filter_map(|rect| {
+                    let rect = rect.borrow();
+                    intersect_rect(&get_rect(&rect.rect), &target_rect)
+                        .map(|clipped| (clipped, rect.layers.clone()))
+                })
+                .collect::<Vec<_>>()
+        };
+        if rects.is_empty() {
+            return;
+        }
+        if self.native_pass == NativePass::Inactive {
+            self.flush_barriers();
+            for clear in clears {
+                self.clear_attachment(clear.borrow(), &rects);
+            }
+            return;
+        }
+        // Clears are not allowed inside native render passes. Clears of whole attachments
+        // before the pass began are folded into its beginning accesses, others are
+        // issued with the next interruption of the pass.
         for clear in clears {
-            let clear = clear.borrow();
-            match *clear {
-                com::AttachmentClear::Color(index, cv) => {
-                    let rtv = {
-                        let pass_cache = self.pass_cache.as_ref().unwrap();
-                        let rtv_id = pass_cache
-                            .render_pass
-                            .subpasses[self.cur_subpass]
-                            .color_attachments[index]
-                            .0;
-
-                        pass_cache
-                            .framebuffer
-                            .attachments[rtv_id]
-                            .handle_rtv
-                            .unwrap()
-                    };
-
-                    self.clear_render_target_view(
-                        rtv,
-                        cv.into(),
-                        &rects,
-                    );
-                }
-                _ => unimplemented!(),
+            let clear = *clear.borrow();
+            if self.native_pass == NativePass::Pending && self.deferred_clears.is_empty() && self.hoist_clear(clear, &rects) {
+                continue;
             }
+            self.deferred_clears.push(DeferredClear {
+                clear,
+                rects: rects.clone(),
+            });
         }
     }
 
     fn resolve_image<T>(
         &mut self,
         src: &n::Image,
-        _src_layout: image::Layout,
+        src_layout: image::Layout,
         dst: &n::Image,
-        _dst_layout: image::Layout,
+        dst_layout: image::Layout,
         regions: T,
     ) where
         T: IntoIterator,
         T::Item: Borrow<com::ImageResolve>,
     {
-        {
-            // Insert barrier for `COPY_DEST` to `RESOLVE_DEST` as we only expose
-            // `TRANSFER_WRITE` which is used for all copy commands.
-            let transition_barrier = Self::transition_barrier(
-                d3d12::D3D12_RESOURCE_TRANSITION_BARRIER {
-                    pResource: dst.resource,
-                    Subresource: d3d12::D3D12_RESOURCE_BARRIER_ALL_SUBRESOURCES, // TODO: only affected ranges
-                    StateBefore: d3d12::D3D12_RESOURCE_STATE_COPY_DEST,
-                    StateAfter: d3d12::D3D12_RESOURCE_STATE_RESOLVE_DEST,
-                }
-            );
-            unsafe { self.raw.ResourceBarrier(1, &transition_barrier) };
-        }
+        // `TRANSFER_READ` and `TRANSFER_WRITE` map to the copy states,
+        // resolves require a transition into the resolve states and back.
+        let src_expected = self.transfer_state("resolve_image", src_layout, image::Access::TRANSFER_READ);
+        let dst_expected = self.transfer_state("resolve_image", dst_layout, image::Access::TRANSFER_WRITE);
+        let src_previous = self.require_state(
+            src.resource,
+            src.tracking(),
+            src_expected,
+            d3d12::D3D12_RESOURCE_STATE_RESOLVE_SOURCE,
+            true,
+        );
+        let dst_previous = self.require_state(
+            dst.resource,
+            dst.tracking(),
+            dst_expected,
+            d3d12::D3D12_RESOURCE_STATE_RESOLVE_DEST,
+            true,
+        );
 
         for region in regions {
             let r = region.borrow();
@@ -1129,18 +3234,8 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
             }
         }
 
-        {
-            // Insert barrier for back transition from `RESOLVE_DEST` to `COPY_DEST`.
-            let transition_barrier = Self::transition_barrier(
-                d3d12::D3D12_RESOURCE_TRANSITION_BARRIER {
-                    pResource: dst.resource,
-                    Subresource: d3d12::D3D12_RESOURCE_BARRIER_ALL_SUBRESOURCES, // TODO: only affected ranges
-                    StateBefore: d3d12::D3D12_RESOURCE_STATE_RESOLVE_DEST,
-                    StateAfter: d3d12::D3D12_RESOURCE_STATE_COPY_DEST,
-                }
-            );
-            unsafe { self.raw.ResourceBarrier(1, &transition_barrier) };
-        }
+        self.restore_state(src.resource, src_previous);
+        self.restore_state(dst.resource, dst_previous);
     }
 
     fn blit_image<T>(
@@ -1173,6 +3268,7 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         unsafe {
             self.raw.IASetIndexBuffer(&mut ibv_raw);
         }
+        self.track_state(ibv.buffer.resource, ibv.buffer.tracking(), d3d12::D3D12_RESOURCE_STATE_INDEX_BUFFER);
     }
 
     fn bind_vertex_buffers(&mut self, vbs: pso::VertexBufferSet<Backend>) {
@@ -1182,6 +3278,10 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
             view.BufferLocation = base + offset as u64;
             view.SizeInBytes = buffer.size_in_bytes - offset as u32;
         }
+        for &(buffer, _) in &vbs.0 {
+            self.track_state(buffer.resource, buffer.tracking(), d3d12::D3D12_RESOURCE_STATE_VERTEX_AND_CONSTANT_BUFFER);
+        }
+        self.dirty_state |= DirtyState::VERTEX_BUFFERS;
     }
 
     fn set_viewports<T>(&mut self, first_viewport: u32, viewports: T)
@@ -1201,23 +3301,26 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                     MinDepth: viewport.depth.start,
                     MaxDepth: viewport.depth.end,
                 }
-            })
-            .enumerate();
-        
-        for (i, viewport) in viewports {
-            if i + first_viewport as usize >= self.viewport_cache.len() {
-                self.viewport_cache.push(viewport);
-            } else {
-                self.viewport_cache[i + first_viewport as usize] = viewport;
-            }
-        }
-
-        unsafe {
-            self.raw.RSSetViewports(
-                self.viewport_cache.len() as _,
-                self.viewport_cache.as_ptr(),
-            );
+            });
+        update_cache(
+            &mut self.viewport_cache,
+            first_viewport as usize,
+            viewports,
+            d3d12::D3D12_VIEWPORT {
+                TopLeftX: 0.0,
+                TopLeftY: 0.0,
+                Width: 0.0,
+                Height: 0.0,
+                MinDepth: 0.0,
+                MaxDepth: 1.0,
+            },
+        );
+        if self.viewport_cache.len() > MAX_VIEWPORTS {
+            let count = self.viewport_cache.len();
+            self.record_error(RecordingError::TooManyViewports("set_viewports", count));
+            self.viewport_cache.truncate(MAX_VIEWPORTS);
         }
+        self.dirty_state |= DirtyState::VIEWPORTS;
     }
 
     fn set_scissors<T>(&mut self, first_scissor: u32, scissors: T)
@@ -1227,27 +3330,36 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
     {
         let rects = scissors
             .into_iter()
-            .map(|rect| get_rect(rect.borrow()))
-            .enumerate();
-
-        for (i, rect) in rects {
-            if i + first_scissor as usize >= self.scissor_cache.len() {
-                self.scissor_cache.push(rect);
-            } else {
-                self.scissor_cache[i + first_scissor as usize] = rect;
-            }
+            .map(|rect| get_rect(rect.borrow()));
+        update_cache(
+            &mut self.scissor_cache,
+            first_scissor as usize,
+            rects,
+            d3d12::D3D12_RECT { left: 0, top: 0, right: 0, bottom: 0 },
+        );
+        if self.scissor_cache.len() > MAX_VIEWPORTS {
+            let count = self.scissor_cache.len();
+            self.record_error(RecordingError::TooManyViewports("set_scissors", count));
+            self.scissor_cache.truncate(MAX_VIEWPORTS);
         }
-
-        unsafe {
-            self.raw
-                .RSSetScissorRects(self.scissor_cache.len() as _, self.scissor_cache.as_ptr())
-        };
+        self.dirty_state |= DirtyState::SCISSORS;
     }
 
     fn set_blend_constants(&mut self, color: pso::ColorValue) {
         unsafe { self.raw.OMSetBlendFactor(&color); }
     }
 
+    fn set_depth_bounds(&mut self, bounds: Range<f32>) {
+        unsafe { self.raw_list1().OMSetDepthBounds(bounds.start, bounds.end); }
+    }
+
+    fn set_line_width(&mut self, width: f32) {
+        // D3D12 only rasterizes lines with a width of 1.0.
+        if width != 1.0 {
+            error!("Unsupported line width: {:?}", width);
+        }
+    }
+
     fn set_stencil_reference(&mut self, front: pso::StencilValue, back: pso::StencilValue) {
         if front != back {
             error!(
@@ -1274,21 +3386,38 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                     self.gr_pipeline.user_data.dirty_mask = !0;
                 }
             }
-            self.raw.SetPipelineState(pipeline.raw);
-            self.raw.IASetPrimitiveTopology(pipeline.topology);
         };
 
-        self.active_bindpoint = BindPoint::Graphics;
-        self.gr_pipeline.pipeline = Some((pipeline.raw, pipeline.signature));
+        // The pipeline state is only set on the next draw call.
+        if self.gr_pipeline.pipeline.map(|(raw, _)| raw) != Some(pipeline.raw) {
+            unsafe { self.raw.IASetPrimitiveTopology(pipeline.topology); }
+            self.gr_pipeline.pipeline = Some((pipeline.raw, pipeline.signature));
+            self.gr_formats = pipeline.formats.clone();
+            self.dirty_state |= DirtyState::PIPELINE;
+        }
 
         // Update strides
         for (view, stride) in self.vertex_buffer_views
                                   .iter_mut()
                                   .zip(pipeline.vertex_strides.iter())
         {
-            view.StrideInBytes = *stride;
+            if view.StrideInBytes != *stride {
+                view.StrideInBytes = *stride;
+                self.dirty_state |= DirtyState::VERTEX_BUFFERS;
+            }
+        }
+
+        // Viewports and scissors beyond the count of the pipeline are discarded,
+        // D3D12 would use them otherwise. Baked states replace the dynamic ones.
+        self.viewport_count = pipeline.viewport_count;
+        let viewport_count = if pipeline.baked_states.viewport.is_some() { 0 } else { pipeline.viewport_count };
+        if truncate_cache(&mut self.viewport_cache, viewport_count) {
+            self.dirty_state |= DirtyState::VIEWPORTS;
+        }
+        let scissor_count = if pipeline.baked_states.scissor.is_some() { 0 } else { pipeline.viewport_count };
+        if truncate_cache(&mut self.scissor_cache, scissor_count) {
+            self.dirty_state |= DirtyState::SCISSORS;
         }
-
         if let Some(ref vp) = pipeline.baked_states.viewport {
             self.set_viewports(0, iter::once(vp));
         }
@@ -1298,6 +3427,9 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         if let Some(color) = pipeline.baked_states.blend_color {
             self.set_blend_constants(color);
         }
+        if let Some(ref bounds) = pipeline.baked_states.depth_bounds {
+            self.set_depth_bounds(bounds.clone());
+        }
     }
 
     fn bind_graphics_descriptor_sets<'a, T>(
@@ -1309,13 +3441,15 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         T: IntoIterator,
         T::Item: Borrow<n::DescriptorSet>,
     {
-        bind_descriptor_sets(&self.raw, &mut self.gr_pipeline, layout, first_set, sets);
+        bind_descriptor_sets(&self.heaps, &mut self.gr_pipeline, layout, first_set, sets, &mut self.tracked_descriptors);
+        self.track_descriptors();
     }
 
     fn bind_compute_pipeline(&mut self, pipeline: &n::ComputePipeline) {
         unsafe {
             match self.comp_pipeline.pipeline {
-                Some((_, signature)) if signature == pipeline.signature => {
+                Some((_, signature))
+                    if signature == pipeline.signature && !self.rt_root_bound => {
                     // Same root signature, nothing to do
                 },
                 _ => {
@@ -1324,6 +3458,7 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                     self.comp_pipeline.root_constants = pipeline.constants.clone();
                     // All slots need to be rebound internally on signature change.
                     self.comp_pipeline.user_data.dirty_mask = !0;
+                    self.rt_root_bound = false;
                 }
             }
             self.raw.SetPipelineState(pipeline.raw);
@@ -1342,81 +3477,297 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         T: IntoIterator,
         T::Item: Borrow<n::DescriptorSet>,
     {
-        bind_descriptor_sets(&self.raw, &mut self.comp_pipeline, layout, first_set, sets);
+        bind_descriptor_sets(&self.heaps, &mut self.comp_pipeline, layout, first_set, sets, &mut self.tracked_descriptors);
+        self.track_descriptors();
     }
 
-    fn dispatch(&mut self, count: WorkGroupCount) {
-        self.set_compute_bind_point();
+    fn bind_ray_tracing_pipeline(&mut self, pipeline: &n::RayTracingPipeline) {
         unsafe {
-            self.raw.Dispatch(count[0], count[1], count[2]);
+            match self.rt_pipeline.pipeline {
+                Some((_, signature))
+                    if signature == pipeline.signature && self.rt_root_bound => {
+                    // Same root signature, nothing to do
+                },
+                _ => {
+                    self.raw.SetComputeRootSignature(pipeline.signature);
+                    self.rt_pipeline.num_parameter_slots = pipeline.num_parameter_slots;
+                    self.rt_pipeline.root_constants = pipeline.constants.clone();
+                    // All slots need to be rebound internally on signature change.
+                    self.rt_pipeline.user_data.dirty_mask = !0;
+                    self.rt_root_bound = true;
+                }
+            }
+            self.raw_list4().SetPipelineState1(pipeline.raw);
         }
+
+        self.active_bindpoint = BindPoint::RayTracing;
+        self.rt_pipeline.pipeline = Some((ptr::null_mut(), pipeline.signature));
+        self.rt_state_object = pipeline.raw;
+    }
+
+    fn bind_ray_tracing_descriptor_sets<T>(
+        &mut self,
+        layout: &n::PipelineLayout,
+        first_set: usize,
+        sets: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<n::DescriptorSet>,
+    {
+        bind_descriptor_sets(&self.heaps, &mut self.rt_pipeline, layout, first_set, sets, &mut self.tracked_descriptors);
+        self.track_descriptors();
     }
 
-    fn dispatch_indirect(&mut self, buffer: &n::Buffer, offset: buffer::Offset) {
-        self.set_compute_bind_point();
+    fn build_acceleration_structure<'a>(
+        &mut self,
+        desc: &accel_struct::BuildDesc<'a, Backend>,
+        dst: &n::AccelerationStructure,
+        scratch: (&n::Buffer, buffer::Offset),
+    ) {
+        debug_assert_eq!(desc.inputs.level(), dst.level);
+        self.flush_barriers();
+
+        let mut geometries = Vec::new();
+        let build_desc = ext::D3D12_BUILD_RAYTRACING_ACCELERATION_STRUCTURE_DESC {
+            DestAccelerationStructureData: dst.location,
+            Inputs: conv::map_acceleration_structure_inputs(desc, &mut geometries),
+            SourceAccelerationStructureData: 0,
+            ScratchAccelerationStructureData: unsafe {
+                (*scratch.0.resource).GetGPUVirtualAddress()
+            } + scratch.1,
+        };
+
         unsafe {
-            self.raw.ExecuteIndirect(
-                self.signatures.dispatch.as_raw(),
-                1,
-                buffer.resource,
-                offset,
-                ptr::null_mut(),
-                0,
-            );
+            self.raw_list4().BuildRaytracingAccelerationStructure(&build_desc, 0, ptr::null());
+        }
+    }
+
+    fn trace_rays<'a>(
+        &mut self,
+        tables: &accel_struct::ShaderBindingTables<'a, Backend>,
+        count: [u32; 3],
+    ) {
+        if !self.set_ray_tracing_bind_point() {
+            return;
+        }
+
+        let ray_gen = conv::map_shader_table(&tables.ray_gen);
+        let desc = ext::D3D12_DISPATCH_RAYS_DESC {
+            RayGenerationShaderRecord: ext::D3D12_GPU_VIRTUAL_ADDRESS_RANGE {
+                StartAddress: ray_gen.StartAddress,
+                SizeInBytes: ray_gen.StrideInBytes,
+            },
+            MissShaderTable: conv::map_shader_table(&tables.miss),
+            HitGroupTable: conv::map_shader_table(&tables.hit_group),
+            CallableShaderTable: tables.callable
+                .as_ref()
+                .map_or(unsafe { mem::zeroed() }, |table| conv::map_shader_table(table)),
+            Width: count[0],
+            Height: count[1],
+            Depth: count[2],
+        };
+
+        unsafe {
+            self.raw_list4().DispatchRays(&desc);
+        }
+    }
+
+    fn dispatch(&mut self, count: WorkGroupCount) {
+        if !self.set_compute_bind_point("dispatch") {
+            return;
+        }
+        unsafe {
+            self.raw.Dispatch(count[0], count[1], count[2]);
         }
     }
 
+    fn dispatch_indirect(
+        &mut self,
+        buffer: &n::Buffer,
+        offset: buffer::Offset,
+        dispatch_count: u32,
+    ) {
+        let signature = device::CommandSignature::Dispatch;
+        let raw = self.signatures.get(signature).as_raw();
+        self.record_indirect(
+            "dispatch_indirect",
+            BindPoint::Compute,
+            raw,
+            signature.stride(),
+            buffer,
+            offset,
+            dispatch_count,
+        );
+    }
+
     fn fill_buffer(
         &mut self,
         buffer: &n::Buffer,
         range: Range<buffer::Offset>,
         data: u32,
     ) {
-        assert!(buffer.clear_uav.is_some(), "Buffer needs to be created with usage `TRANSFER_DST`");
-        assert_eq!(range, 0..buffer.size_in_bytes as u64); // TODO: Need to dynamically create UAVs
+        if self.is_copy_list() {
+            self.record_error(RecordingError::CopyQueue("fill_buffer"));
+            return;
+        }
+        if !buffer.usage.contains(buffer::Usage::TRANSFER_DST) {
+            self.record_error(RecordingError::MissingUsage("fill_buffer", buffer::Usage::TRANSFER_DST));
+            return;
+        }
+        debug_assert_eq!(range.start % 4, 0, "Fill offset must be a multiple of 4");
+        debug_assert!(range.start <= range.end);
+
+        // The fill is done by clearing a raw UAV of the range, which is addressed in DWORDs.
+        // Trailing bytes not forming a whole DWORD are skipped, like `VK_WHOLE_SIZE` fills.
+        let end = cmp::min(range.end, buffer.size_in_bytes as u64);
+        let num_elements = end.saturating_sub(range.start) / 4;
+        if num_elements == 0 {
+            return;
+        }
 
-        // Insert barrier for `COPY_DEST` to `UNORDERED_ACCESS` as we use
-        // `TRANSFER_WRITE` for all clear commands.
-        let transition_barrier = Self::transition_barrier(
-            d3d12::D3D12_RESOURCE_TRANSITION_BARRIER {
-                pResource: buffer.resource,
-                Subresource: d3d12::D3D12_RESOURCE_BARRIER_ALL_SUBRESOURCES,
-                StateBefore: d3d12::D3D12_RESOURCE_STATE_COPY_DEST,
-                StateAfter: d3d12::D3D12_RESOURCE_STATE_UNORDERED_ACCESS,
+        let (cpu, visible) = {
+            let mut views = self.transient_views.lock().unwrap();
+            let (cpu, visible) = views.allocate();
+            let mut desc = d3d12::D3D12_UNORDERED_ACCESS_VIEW_DESC {
+                Format: dxgiformat::DXGI_FORMAT_R32_TYPELESS,
+                ViewDimension: d3d12::D3D12_UAV_DIMENSION_BUFFER,
+                u: unsafe { mem::zeroed() },
+            };
+            *unsafe { desc.u.Buffer_mut() } = d3d12::D3D12_BUFFER_UAV {
+                FirstElement: range.start / 4,
+                NumElements: num_elements as _,
+                StructureByteStride: 0,
+                CounterOffsetInBytes: 0,
+                Flags: d3d12::D3D12_BUFFER_UAV_FLAG_RAW,
+            };
+            unsafe {
+                views.device.CreateUnorderedAccessView(
+                    buffer.resource,
+                    ptr::null_mut(),
+                    &desc,
+                    cpu,
+                );
             }
+            views.publish(cpu, visible);
+            (cpu, visible)
+        };
+
+        // Transition into `UNORDERED_ACCESS` as we use `TRANSFER_WRITE` for all clear commands,
+        // which maps to `COPY_DEST`.
+        let previous = self.require_state(
+            buffer.resource,
+            buffer.tracking(),
+            Some(d3d12::D3D12_RESOURCE_STATE_COPY_DEST),
+            d3d12::D3D12_RESOURCE_STATE_UNORDERED_ACCESS,
+            false,
         );
-        unsafe { self.raw.ResourceBarrier(1, &transition_barrier) };
 
-        let handles = buffer.clear_uav.unwrap();
         unsafe {
             self.raw.ClearUnorderedAccessViewUint(
-                handles.gpu,
-                handles.cpu,
+                visible.gpu,
+                cpu,
                 buffer.resource,
                 &[data as UINT; 4],
                 0,
-                ptr::null_mut(), // TODO: lift with the forementioned restriction
+                ptr::null_mut(),
             );
         }
 
-        let transition_barrier = Self::transition_barrier(
-            d3d12::D3D12_RESOURCE_TRANSITION_BARRIER {
-                pResource: buffer.resource,
-                Subresource: d3d12::D3D12_RESOURCE_BARRIER_ALL_SUBRESOURCES,
-                StateBefore: d3d12::D3D12_RESOURCE_STATE_UNORDERED_ACCESS,
-                StateAfter: d3d12::D3D12_RESOURCE_STATE_COPY_DEST,
-            }
-        );
-        unsafe { self.raw.ResourceBarrier(1, &transition_barrier) };
+        self.restore_state(buffer.resource, previous);
     }
 
     fn update_buffer(
         &mut self,
-        _buffer: &n::Buffer,
-        _offset: buffer::Offset,
-        _data: &[u8],
+        buffer: &n::Buffer,
+        offset: buffer::Offset,
+        data: &[u8],
     ) {
-        unimplemented!()
+        if !buffer.usage.contains(buffer::Usage::TRANSFER_DST) {
+            self.record_error(RecordingError::MissingUsage("update_buffer", buffer::Usage::TRANSFER_DST));
+            return;
+        }
+        if offset % 4 != 0 {
+            self.record_error(RecordingError::UnalignedOffset("update_buffer", offset, 4));
+            return;
+        }
+        if offset + data.len() as u64 > buffer.size_in_bytes as u64 {
+            self.record_error(RecordingError::OutOfBounds("update_buffer"));
+            return;
+        }
+        if data.is_empty() {
+            return;
+        }
+
+        // The data is written into an upload buffer and copied with `CopyBufferRegion`,
+        // which is also available on copy queues, unlike `WriteBufferImmediate`.
+        let upload = {
+            let views = self.transient_views.lock().unwrap();
+            let properties = d3d12::D3D12_HEAP_PROPERTIES {
+                Type: d3d12::D3D12_HEAP_TYPE_UPLOAD,
+                CPUPageProperty: d3d12::D3D12_CPU_PAGE_PROPERTY_UNKNOWN,
+                MemoryPoolPreference: d3d12::D3D12_MEMORY_POOL_UNKNOWN,
+                CreationNodeMask: 0,
+                VisibleNodeMask: 0,
+            };
+            let desc = d3d12::D3D12_RESOURCE_DESC {
+                Dimension: d3d12::D3D12_RESOURCE_DIMENSION_BUFFER,
+                Alignment: 0,
+                Width: data.len() as _,
+                Height: 1,
+                DepthOrArraySize: 1,
+                MipLevels: 1,
+                Format: dxgiformat::DXGI_FORMAT_UNKNOWN,
+                SampleDesc: dxgitype::DXGI_SAMPLE_DESC {
+                    Count: 1,
+                    Quality: 0,
+                },
+                Layout: d3d12::D3D12_TEXTURE_LAYOUT_ROW_MAJOR,
+                Flags: d3d12::D3D12_RESOURCE_FLAG_NONE,
+            };
+            let mut resource = ptr::null_mut();
+            let hr = unsafe {
+                views.device.CreateCommittedResource(
+                    &properties,
+                    d3d12::D3D12_HEAP_FLAG_NONE,
+                    &desc,
+                    d3d12::D3D12_RESOURCE_STATE_GENERIC_READ,
+                    ptr::null(),
+                    &d3d12::ID3D12Resource::uuidof(),
+                    &mut resource,
+                )
+            };
+            if !SUCCEEDED(hr) {
+                error!("Failed to create upload buffer for `update_buffer`: {:x}", hr);
+                return;
+            }
+            let upload = unsafe { ComPtr::<d3d12::ID3D12Resource>::from_raw(resource as _) };
+
+            let mut mapped = ptr::null_mut();
+            let range = d3d12::D3D12_RANGE { Begin: 0, End: 0 };
+            let hr = unsafe { upload.Map(0, &range, &mut mapped) };
+            if !SUCCEEDED(hr) {
+                error!("Failed to map upload buffer for `update_buffer`: {:x}", hr);
+                return;
+            }
+            unsafe {
+                ptr::copy_nonoverlapping(data.as_ptr(), mapped as *mut u8, data.len());
+                upload.Unmap(0, ptr::null());
+            }
+            upload
+        };
+
+        self.track_state(buffer.resource, buffer.tracking(), d3d12::D3D12_RESOURCE_STATE_COPY_DEST);
+        self.flush_barriers();
+        unsafe {
+            self.raw.CopyBufferRegion(
+                buffer.resource,
+                offset,
+                upload.as_raw(),
+                0,
+                data.len() as _,
+            );
+        }
+        self.uploads.push(upload);
     }
 
     fn copy_buffer<T>(&mut self, src: &n::Buffer, dst: &n::Buffer, regions: T)
@@ -1424,34 +3775,117 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         T: IntoIterator,
         T::Item: Borrow<com::BufferCopy>,
     {
-        // copy each region
-        for region in regions {
-            let region = region.borrow();
-            unsafe {
-                self.raw.CopyBufferRegion(
-                    dst.resource,
-                    region.dst as _,
-                    src.resource,
-                    region.src as _,
-                    region.size as _,
-                );
+        assert!(self.buffer_copies.is_empty());
+        self.buffer_copies.extend(
+            regions
+                .into_iter()
+                .map(|region| *region.borrow())
+                .filter(|region| region.size != 0)
+        );
+
+        if cfg!(debug_assertions) && src.resource == dst.resource {
+            for a in &self.buffer_copies {
+                for b in &self.buffer_copies {
+                    debug_assert!(
+                        a.src + a.size <= b.dst || b.dst + b.size <= a.src,
+                        "Source region {:?} overlaps destination region {:?}", a, b,
+                    );
+                }
+            }
+        }
+
+        // Merge regions which are contiguous in both buffers to reduce the number of copies.
+        self.buffer_copies.sort_by_key(|region| region.src);
+        let mut merged = 0;
+        for i in 0 .. self.buffer_copies.len() {
+            let region = self.buffer_copies[i];
+            if merged > 0 {
+                let last = &mut self.buffer_copies[merged - 1];
+                if last.src + last.size == region.src && last.dst + last.size == region.dst {
+                    last.size += region.size;
+                    continue;
+                }
+            }
+            self.buffer_copies[merged] = region;
+            merged += 1;
+        }
+        self.buffer_copies.truncate(merged);
+
+        if src.resource != dst.resource {
+            self.track_state(src.resource, src.tracking(), d3d12::D3D12_RESOURCE_STATE_COPY_SOURCE);
+        }
+        self.track_state(dst.resource, dst.tracking(), d3d12::D3D12_RESOURCE_STATE_COPY_DEST);
+        self.flush_barriers();
+
+        let whole_resource = match self.buffer_copies.first() {
+            Some(region) => {
+                merged == 1 && src.resource != dst.resource &&
+                region.src == 0 && region.dst == 0 &&
+                region.size == src.size_in_bytes as u64 && region.size == dst.size_in_bytes as u64
+            }
+            None => false,
+        };
+
+        if whole_resource {
+            unsafe { self.raw.CopyResource(dst.resource, src.resource); }
+        } else {
+            for region in &self.buffer_copies {
+                unsafe {
+                    self.raw.CopyBufferRegion(
+                        dst.resource,
+                        region.dst as _,
+                        src.resource,
+                        region.src as _,
+                        region.size as _,
+                    );
+                }
             }
         }
 
-        // TODO: Optimization: Copy whole resource if possible
+        self.buffer_copies.clear();
     }
 
     fn copy_image<T>(
         &mut self,
         src: &n::Image,
-        _: image::Layout,
+        src_layout: image::Layout,
         dst: &n::Image,
-        _: image::Layout,
+        dst_layout: image::Layout,
         regions: T,
     ) where
         T: IntoIterator,
         T::Item: Borrow<com::ImageCopy>,
     {
+        let regions = regions
+            .into_iter()
+            .map(|region| region.borrow().clone())
+            .collect::<SmallVec<[_; 4]>>();
+        let src_expected = self.transfer_state("copy_image", src_layout, image::Access::TRANSFER_READ);
+        let dst_expected = self.transfer_state("copy_image", dst_layout, image::Access::TRANSFER_WRITE);
+
+        // Copies within the same image transition the source and destination
+        // subresources separately, as they need to be in different states.
+        let (src_previous, dst_previous, restore) = if src.resource != dst.resource {
+            let src_previous = self.require_state(
+                src.resource,
+                src.tracking(),
+                src_expected,
+                d3d12::D3D12_RESOURCE_STATE_COPY_SOURCE,
+                true,
+            );
+            let dst_previous = self.require_state(
+                dst.resource,
+                dst.tracking(),
+                dst_expected,
+                d3d12::D3D12_RESOURCE_STATE_COPY_DEST,
+                true,
+            );
+            (src_previous, dst_previous, None)
+        } else {
+            let restore = self.require_copy_states(src, src_expected, dst_expected, &regions);
+            (None, None, Some(restore))
+        };
+
         let mut src_image = d3d12::D3D12_TEXTURE_COPY_LOCATION {
             pResource: src.resource,
             Type: d3d12::D3D12_TEXTURE_COPY_TYPE_SUBRESOURCE_INDEX,
@@ -1464,8 +3898,7 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
             u: unsafe { mem::zeroed() },
         };
 
-        for region in regions {
-            let r = region.borrow();
+        for r in &regions {
             debug_assert_eq!(r.src_subresource.layers.len(), r.dst_subresource.layers.len());
             let num_layers = r.src_subresource.layers.len() as image::Layer;
             let src_layer_start = r.src_subresource.layers.start;
@@ -1479,36 +3912,65 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                 back: (r.src_offset.z + r.extent.depth as i32) as _,
             };
 
-            for layer in 0..num_layers {
-                *unsafe { src_image.u.SubresourceIndex_mut() } =
-                    src.calc_subresource(r.src_subresource.level as _, (src_layer_start + layer) as _, 0);
-                *unsafe { dst_image.u.SubresourceIndex_mut() } =
-                    dst.calc_subresource(r.dst_subresource.level as _, (dst_layer_start + layer) as _, 0);
-                unsafe {
-                    self.raw.CopyTextureRegion(
-                        &dst_image,
-                        r.dst_offset.x as _,
-                        r.dst_offset.y as _,
-                        r.dst_offset.z as _,
-                        &src_image,
-                        &src_box,
-                    );
+            // Depth and stencil planes are copied separately, either between
+            // depth-stencil images or from and to color images of the same family.
+            let planes = src
+                .planes(r.src_subresource.aspects)
+                .zip(dst.planes(r.dst_subresource.aspects));
+            let compatible = planes
+                .clone()
+                .all(|(src_plane, dst_plane)| src.plane_format_family(src_plane) == dst.plane_format_family(dst_plane));
+            if !compatible {
+                self.record_error(RecordingError::IncompatibleFormats("copy_image"));
+                continue;
+            }
+            for (src_plane, dst_plane) in planes {
+                for layer in 0..num_layers {
+                    *unsafe { src_image.u.SubresourceIndex_mut() } =
+                        src.calc_subresource(r.src_subresource.level as _, (src_layer_start + layer) as _, src_plane);
+                    *unsafe { dst_image.u.SubresourceIndex_mut() } =
+                        dst.calc_subresource(r.dst_subresource.level as _, (dst_layer_start + layer) as _, dst_plane);
+                    unsafe {
+                        self.raw.CopyTextureRegion(
+                            &dst_image,
+                            r.dst_offset.x as _,
+                            r.dst_offset.y as _,
+                            r.dst_offset.z as _,
+                            &src_image,
+                            &src_box,
+                        );
+                    }
                 }
             }
         }
+
+        self.restore_state(src.resource, src_previous);
+        self.restore_state(dst.resource, dst_previous);
+        if let Some((barriers, known)) = restore {
+            self.barriers.extend(barriers);
+            self.flush_barriers();
+            // The subresources are back in the state of the whole image.
+            if let Some(state) = known {
+                self.resource_states.insert(src.resource, state);
+            }
+        }
     }
 
     fn copy_buffer_to_image<T>(
         &mut self,
         buffer: &n::Buffer,
         image: &n::Image,
-        _: image::Layout,
+        layout: image::Layout,
         regions: T,
     ) where
         T: IntoIterator,
         T::Item: Borrow<com::BufferImageCopy>,
     {
         assert!(self.copies.is_empty());
+        if image.kind.num_samples() > 1 {
+            self.record_error(RecordingError::Multisampled("copy_buffer_to_image"));
+            return;
+        }
 
         for region in regions {
             let r = region.borrow();
@@ -1519,6 +3981,16 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
             return;
         }
 
+        self.track_state(buffer.resource, buffer.tracking(), d3d12::D3D12_RESOURCE_STATE_COPY_SOURCE);
+        let expected = self.transfer_state("copy_buffer_to_image", layout, image::Access::TRANSFER_WRITE);
+        let previous = self.require_state(
+            image.resource,
+            image.tracking(),
+            expected,
+            d3d12::D3D12_RESOURCE_STATE_COPY_DEST,
+            true,
+        );
+
         let mut src = d3d12::D3D12_TEXTURE_COPY_LOCATION {
             pResource: buffer.resource,
             Type: d3d12::D3D12_TEXTURE_COPY_TYPE_PLACED_FOOTPRINT,
@@ -1542,7 +4014,7 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
             let footprint = d3d12::D3D12_PLACED_SUBRESOURCE_FOOTPRINT {
                 Offset: c.footprint_offset,
                 Footprint: d3d12::D3D12_SUBRESOURCE_FOOTPRINT {
-                    Format: image.dxgi_format,
+                    Format: c.footprint_format,
                     Width: c.footprint.width,
                     Height: c.footprint.height,
                     Depth: c.footprint.depth,
@@ -1562,12 +4034,14 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                 );
             }
         }
+
+        self.restore_state(image.resource, previous);
     }
 
     fn copy_image_to_buffer<T>(
         &mut self,
         image: &n::Image,
-        _: image::Layout,
+        layout: image::Layout,
         buffer: &n::Buffer,
         regions: T,
     ) where
@@ -1575,6 +4049,10 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         T::Item: Borrow<com::BufferImageCopy>,
     {
         assert!(self.copies.is_empty());
+        if image.kind.num_samples() > 1 {
+            self.record_error(RecordingError::Multisampled("copy_image_to_buffer"));
+            return;
+        }
 
         for region in regions {
             let r = region.borrow();
@@ -1585,6 +4063,16 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
             return;
         }
 
+        self.track_state(buffer.resource, buffer.tracking(), d3d12::D3D12_RESOURCE_STATE_COPY_DEST);
+        let expected = self.transfer_state("copy_image_to_buffer", layout, image::Access::TRANSFER_READ);
+        let previous = self.require_state(
+            image.resource,
+            image.tracking(),
+            expected,
+            d3d12::D3D12_RESOURCE_STATE_COPY_SOURCE,
+            true,
+        );
+
         let mut src = d3d12::D3D12_TEXTURE_COPY_LOCATION {
             pResource: image.resource,
             Type: d3d12::D3D12_TEXTURE_COPY_TYPE_SUBRESOURCE_INDEX,
@@ -1608,7 +4096,7 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
             let footprint = d3d12::D3D12_PLACED_SUBRESOURCE_FOOTPRINT {
                 Offset: c.footprint_offset,
                 Footprint: d3d12::D3D12_SUBRESOURCE_FOOTPRINT {
-                    Format: image.dxgi_format,
+                    Format: c.footprint_format,
                     Width: c.footprint.width,
                     Height: c.footprint.height,
                     Depth: c.footprint.depth,
@@ -1628,10 +4116,14 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                 );
             }
         }
+
+        self.restore_state(image.resource, previous);
     }
 
     fn draw(&mut self, vertices: Range<VertexCount>, instances: Range<InstanceCount>) {
-        self.set_graphics_bind_point();
+        if !self.set_graphics_bind_point("draw") {
+            return;
+        }
         unsafe {
             self.raw.DrawInstanced(
                 vertices.end - vertices.start,
@@ -1648,7 +4140,9 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         base_vertex: VertexOffset,
         instances: Range<InstanceCount>,
     ) {
-        self.set_graphics_bind_point();
+        if !self.set_graphics_bind_point("draw_indexed") {
+            return;
+        }
         unsafe {
             self.raw.DrawIndexedInstanced(
                 indices.end - indices.start,
@@ -1667,18 +4161,13 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         draw_count: u32,
         stride: u32,
     ) {
-        assert_eq!(stride, 16);
-        self.set_graphics_bind_point();
-        unsafe {
-            self.raw.ExecuteIndirect(
-                self.signatures.draw.as_raw(),
-                draw_count,
-                buffer.resource,
-                offset,
-                ptr::null_mut(),
-                0,
-            );
+        let signature = device::CommandSignature::Draw;
+        if stride != signature.stride() {
+            self.record_error(RecordingError::UnsupportedStride("draw_indirect", stride));
+            return;
         }
+        let raw = self.signatures.get(signature).as_raw();
+        self.record_indirect("draw_indirect", BindPoint::Graphics, raw, stride, buffer, offset, draw_count);
     }
 
     fn draw_indexed_indirect(
@@ -1688,17 +4177,52 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         draw_count: u32,
         stride: u32,
     ) {
-        assert_eq!(stride, 20);
-        self.set_graphics_bind_point();
-        unsafe {
-            self.raw.ExecuteIndirect(
-                self.signatures.draw_indexed.as_raw(),
-                draw_count,
-                buffer.resource,
-                offset,
-                ptr::null_mut(),
-                0,
-            );
+        let signature = device::CommandSignature::DrawIndexed;
+        if stride != signature.stride() {
+            self.record_error(RecordingError::UnsupportedStride("draw_indexed_indirect", stride));
+            return;
+        }
+        let raw = self.signatures.get(signature).as_raw();
+        self.record_indirect("draw_indexed_indirect", BindPoint::Graphics, raw, stride, buffer, offset, draw_count);
+    }
+
+    fn execute_indirect(
+        &mut self,
+        signature: &n::CommandSignature,
+        buffer: &n::Buffer,
+        offset: buffer::Offset,
+        count: u32,
+    ) {
+        let bind_point = if signature.dispatch {
+            BindPoint::Compute
+        } else {
+            BindPoint::Graphics
+        };
+        let recorded = self.record_indirect(
+            "execute_indirect",
+            bind_point,
+            signature.raw.as_raw(),
+            signature.stride,
+            buffer,
+            offset,
+            count,
+        );
+        if !recorded {
+            return;
+        }
+
+        // Bindings changed by the sequences are undefined afterwards,
+        // restore the command buffer state on the next flush.
+        let user_data = if signature.dispatch {
+            &mut self.comp_pipeline.user_data
+        } else {
+            &mut self.gr_pipeline.user_data
+        };
+        for range in &signature.constants {
+            user_data.invalidate_constants(range.clone());
+        }
+        if signature.vertex_buffers {
+            self.dirty_state |= DirtyState::VERTEX_BUFFERS;
         }
     }
 
@@ -1707,9 +4231,17 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         query: query::Query<Backend>,
         flags: query::QueryControl,
     ) {
+        if self.is_copy_list() {
+            self.record_error(RecordingError::CopyQueue("begin_query"));
+            return;
+        }
         let query_ty = match query.pool.ty {
+            d3d12::D3D12_QUERY_HEAP_TYPE_OCCLUSION if self.is_compute_list() => {
+                self.record_error(RecordingError::ComputeQueue("begin_query"));
+                return;
+            }
             d3d12::D3D12_QUERY_HEAP_TYPE_OCCLUSION => {
-                if flags.contains(query::QueryControl::PRECISE) {
+                if flags.contains(query::QueryControl::PRECISE) && !query.pool.predication {
                     self.occlusion_query = Some(OcclusionQuery::Precise(query.id));
                     d3d12::D3D12_QUERY_TYPE_OCCLUSION
                 } else {
@@ -1720,7 +4252,8 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                 }
             }
             d3d12::D3D12_QUERY_HEAP_TYPE_TIMESTAMP => {
-                panic!("Timestap queries are issued via ")
+                self.record_error(RecordingError::TimestampQuery);
+                return;
             }
             d3d12::D3D12_QUERY_HEAP_TYPE_PIPELINE_STATISTICS => {
                 self.pipeline_stats_query = Some(query.id);
@@ -1762,7 +4295,10 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                 self.pipeline_stats_query = None;
                 d3d12::D3D12_QUERY_TYPE_PIPELINE_STATISTICS
             }
-            _ => panic!("Missing `begin_query` call for query: {:?}", query),
+            _ => {
+                self.record_error(RecordingError::QueryNotActive(id));
+                return;
+            }
         };
 
         unsafe {
@@ -1792,6 +4328,12 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         _: pso::PipelineStage,
         query: query::Query<Backend>,
     ) {
+        // Copy queues require a dedicated `COPY_QUEUE_TIMESTAMP` heap.
+        if self.is_copy_list() {
+            self.record_error(RecordingError::CopyQueue("write_timestamp"));
+            return;
+        }
+        self.flush_barriers();
         unsafe {
             self.raw.EndQuery(
                 query.pool.raw.as_raw(),
@@ -1801,6 +4343,53 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         }
     }
 
+    fn copy_query_pool_results(
+        &mut self,
+        pool: &n::QueryPool,
+        queries: Range<query::QueryId>,
+        buffer: &n::Buffer,
+        offset: buffer::Offset,
+        stride: buffer::Offset,
+        flags: query::ResultFlags,
+    ) {
+        if self.is_copy_list() {
+            self.record_error(RecordingError::CopyQueue("copy_query_pool_results"));
+            return;
+        }
+        if !flags.contains(query::ResultFlags::BITS_64) ||
+            flags.intersects(query::ResultFlags::WITH_AVAILABILITY | query::ResultFlags::PARTIAL)
+        {
+            self.record_error(RecordingError::UnsupportedResultFlags(flags));
+            return;
+        }
+        if stride != pool.result_stride() {
+            self.record_error(RecordingError::UnsupportedStride("copy_query_pool_results", stride as u32));
+            return;
+        }
+        if offset % 8 != 0 {
+            self.record_error(RecordingError::UnalignedOffset("copy_query_pool_results", offset, 8));
+            return;
+        }
+        let count = queries.end - queries.start;
+        if offset + count as buffer::Offset * stride > buffer.size_in_bytes as buffer::Offset {
+            self.record_error(RecordingError::OutOfBounds("copy_query_pool_results"));
+            return;
+        }
+
+        self.flush_barriers();
+        // The results are tightly packed, so a single resolve covers the whole range.
+        unsafe {
+            self.raw.ResolveQueryData(
+                pool.raw.as_raw(),
+                pool.resolve_type(),
+                queries.start,
+                count,
+                buffer.resource,
+                offset,
+            );
+        }
+    }
+
     fn push_graphics_constants(
         &mut self,
         layout: &n::PipelineLayout,
@@ -1827,8 +4416,181 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         I: IntoIterator,
         I::Item: Borrow<CommandBuffer>,
     {
-        for _cmd_buf in buffers {
-            error!("TODO: execute_commands");
+        // The recording is split around the lists of the secondary command buffers.
+        // Native render passes are suspended across them, each secondary command
+        // buffer resumes and suspends the pass again.
+        let suspended = self.suspend_native_pass();
+        self.flush_barriers();
+        let buffers = buffers.into_iter().collect::<Vec<_>>();
+        let buffers = buffers.iter().map(|buffer| buffer.borrow()).collect::<Vec<_>>();
+        self.split_list(&buffers);
+        if suspended {
+            self.resume_native_pass();
         }
+        self.bind_descriptor_heaps();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hal::command::BufferImageCopy;
+    use hal::format::{Aspects, Format};
+    use hal::image::{Extent, Offset, SubresourceLayers};
+    use smallvec::SmallVec;
+    use winapi::shared::dxgiformat;
+    use super::{split_buffer_image_copy, update_cache, Copy};
+
+    #[test]
+    fn test_update_cache_append() {
+        let mut cache = SmallVec::<[u32; 4]>::new();
+        update_cache(&mut cache, 0, vec![1, 2], 0);
+        assert_eq!(&cache[..], &[1, 2]);
+        update_cache(&mut cache, 2, vec![3], 0);
+        assert_eq!(&cache[..], &[1, 2, 3]);
+    }
+
+    #[test]
+    fn test_update_cache_partial() {
+        let mut cache = SmallVec::<[u32; 4]>::new();
+        update_cache(&mut cache, 0, vec![1, 2, 3], 0);
+        update_cache(&mut cache, 1, vec![5], 0);
+        assert_eq!(&cache[..], &[1, 5, 3]);
+        update_cache(&mut cache, 2, vec![6, 7], 0);
+        assert_eq!(&cache[..], &[1, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_update_cache_sparse() {
+        let mut cache = SmallVec::<[u32; 4]>::new();
+        update_cache(&mut cache, 2, vec![3], 0);
+        assert_eq!(&cache[..], &[0, 0, 3]);
+        update_cache(&mut cache, 0, vec![1], 0);
+        assert_eq!(&cache[..], &[1, 0, 3]);
+    }
+
+    #[test]
+    fn test_truncate_cache_mixed_pipelines() {
+        // Dynamic pipeline with 3 viewports, followed by a static and a dynamic one with 1 viewport.
+        let mut cache = SmallVec::<[u32; 4]>::new();
+        update_cache(&mut cache, 0, vec![1, 2, 3], 0);
+        assert!(!truncate_cache(&mut cache, 3));
+        assert!(truncate_cache(&mut cache, 0));
+        update_cache(&mut cache, 0, vec![4], 0);
+        assert!(!truncate_cache(&mut cache, 1));
+        assert_eq!(&cache[..], &[4]);
+    }
+
+    fn split(format: Format, buffer_offset: u64, buffer_width: u32, extent: Extent) -> Vec<Copy> {
+        let desc = format.surface_desc();
+        let region = BufferImageCopy {
+            buffer_offset,
+            buffer_width,
+            buffer_height: 0,
+            image_layers: SubresourceLayers {
+                aspects: Aspects::COLOR,
+                level: 0,
+                layers: 0 .. 1,
+            },
+            image_offset: Offset::ZERO,
+            image_extent: extent,
+        };
+        let mut copies = Vec::new();
+        split_buffer_image_copy(
+            &mut copies,
+            &region,
+            dxgiformat::DXGI_FORMAT_UNKNOWN,
+            desc.bits as u32 / 8,
+            (desc.dim.0 as _, desc.dim.1 as _),
+            |layer| layer as _,
+        );
+        copies
+    }
+
+    fn extent(width: u32, height: u32) -> Extent {
+        Extent { width, height, depth: 1 }
+    }
+
+    fn offset(x: i32, y: i32) -> Offset {
+        Offset { x, y, z: 0 }
+    }
+
+    #[test]
+    fn test_split_copy_aligned_bc1() {
+        // 64 blocks of 8 bytes per row
+        let copies = split(Format::Bc1RgbaUnorm, 0, 0, extent(256, 16));
+        assert_eq!(copies.len(), 1);
+        assert_eq!(copies[0].footprint, extent(256, 16));
+        assert_eq!(copies[0].row_pitch, 512);
+        assert_eq!(copies[0].buf_offset, Offset::ZERO);
+        assert_eq!(copies[0].copy_extent, extent(256, 16));
+    }
+
+    #[test]
+    fn test_split_copy_partial_block() {
+        // the last mip levels are smaller than a block
+        let copies = split(Format::Bc1RgbUnorm, 0, 256, extent(2, 2));
+        assert_eq!(copies.len(), 1);
+        assert_eq!(copies[0].footprint, extent(4, 4));
+        assert_eq!(copies[0].copy_extent, extent(2, 2));
+    }
+
+    #[test]
+    fn test_split_copy_unaligned_offset_bc2() {
+        // 16 blocks of 16 bytes per row, the copy starts at the 2nd block row
+        let copies = split(Format::Bc2Unorm, 256 + 512, 0, extent(64, 8));
+        assert_eq!(copies.len(), 1);
+        assert_eq!(copies[0].footprint_offset, 512);
+        assert_eq!(copies[0].footprint, extent(64, 12));
+        assert_eq!(copies[0].row_pitch, 256);
+        assert_eq!(copies[0].buf_offset, offset(0, 4));
+        assert_eq!(copies[0].copy_extent, extent(64, 8));
+    }
+
+    #[test]
+    fn test_split_copy_unaligned_offset_wrapping_bc7() {
+        // the copy starts in the middle of a footprint row and wraps around
+        let copies = split(Format::Bc7Unorm, 128, 0, extent(64, 8));
+        assert_eq!(copies.len(), 2);
+        assert_eq!(copies[0].footprint_offset, 0);
+        assert_eq!(copies[0].footprint, extent(64, 8));
+        assert_eq!(copies[0].buf_offset, offset(32, 0));
+        assert_eq!(copies[0].img_offset, offset(0, 0));
+        assert_eq!(copies[0].copy_extent, extent(32, 8));
+        assert_eq!(copies[1].footprint_offset, 0);
+        assert_eq!(copies[1].footprint, extent(32, 12));
+        assert_eq!(copies[1].buf_offset, offset(0, 4));
+        assert_eq!(copies[1].img_offset, offset(32, 0));
+        assert_eq!(copies[1].copy_extent, extent(32, 8));
+    }
+
+    #[test]
+    fn test_split_copy_unaligned_pitch_bc3() {
+        // 5 blocks of 16 bytes per row, the first row crosses a placement boundary
+        let copies = split(Format::Bc3Unorm, 496, 0, extent(20, 8));
+        assert_eq!(copies.len(), 3);
+        assert_eq!(copies[0].footprint_offset, 0);
+        assert_eq!(copies[0].footprint, extent(128, 4));
+        assert_eq!(copies[0].row_pitch, 512);
+        assert_eq!(copies[0].buf_offset, offset(124, 0));
+        assert_eq!(copies[0].copy_extent, extent(4, 4));
+        assert_eq!(copies[1].footprint_offset, 512);
+        assert_eq!(copies[1].footprint, extent(16, 4));
+        assert_eq!(copies[1].row_pitch, 256);
+        assert_eq!(copies[1].img_offset, offset(4, 0));
+        assert_eq!(copies[1].copy_extent, extent(16, 4));
+        assert_eq!(copies[2].footprint_offset, 512);
+        assert_eq!(copies[2].footprint, extent(36, 4));
+        assert_eq!(copies[2].buf_offset, offset(16, 0));
+        assert_eq!(copies[2].img_offset, offset(0, 4));
+        assert_eq!(copies[2].copy_extent, extent(20, 4));
+    }
+
+    #[test]
+    fn test_split_copy_unaligned_offset_uncompressed() {
+        // buffer offsets are in texels, not bytes
+        let copies = split(Format::Rgba8Unorm, 16, 64, extent(32, 4));
+        assert_eq!(copies.len(), 1);
+        assert_eq!(copies[0].buf_offset, offset(4, 0));
+        assert_eq!(copies[0].footprint, extent(36, 4));
     }
 }
@@ -8,9 +8,12 @@ use winapi::um::d3d12::*;
 use winapi::um::d3dcommon::*;
 
 use hal::format::{Format, SurfaceType};
-use hal::{buffer, image, pso, Primitive};
+use hal::{accel_struct, buffer, image, memory, pso, IndexType, Primitive};
 use hal::pso::DescriptorSetLayoutBinding;
 
+use ext::*;
+use {native as n, Backend};
+
 pub fn map_format(format: Format) -> Option<DXGI_FORMAT> {
     use hal::format::Format::*;
 
@@ -81,6 +84,9 @@ pub fn map_format(format: Format) -> Option<DXGI_FORMAT> {
         Bc6hFloat => DXGI_FORMAT_BC6H_SF16,
         Bc7Unorm => DXGI_FORMAT_BC7_UNORM,
         Bc7Srgb => DXGI_FORMAT_BC7_UNORM_SRGB,
+        Nv12Unorm => DXGI_FORMAT_NV12,
+        P010Unorm => DXGI_FORMAT_P010,
+        Yuy2Unorm => DXGI_FORMAT_YUY2,
 
         _ => return None,
     };
@@ -99,6 +105,41 @@ pub fn map_format_dsv(surface: SurfaceType) -> Option<DXGI_FORMAT> {
     })
 }
 
+/// Map a surface type to the typeless format of its format family.
+///
+/// Resources created with a typeless format can be viewed with
+/// any typed format of the family.
+pub fn map_format_typeless(surface: SurfaceType) -> Option<DXGI_FORMAT> {
+    Some(match surface {
+        SurfaceType::R8                => DXGI_FORMAT_R8_TYPELESS,
+        SurfaceType::R8_G8             => DXGI_FORMAT_R8G8_TYPELESS,
+        SurfaceType::R8_G8_B8_A8       => DXGI_FORMAT_R8G8B8A8_TYPELESS,
+        SurfaceType::B8_G8_R8_A8       => DXGI_FORMAT_B8G8R8A8_TYPELESS,
+        SurfaceType::A2_B10_G10_R10    => DXGI_FORMAT_R10G10B10A2_TYPELESS,
+        SurfaceType::R16               => DXGI_FORMAT_R16_TYPELESS,
+        SurfaceType::R16_G16           => DXGI_FORMAT_R16G16_TYPELESS,
+        SurfaceType::R16_G16_B16_A16   => DXGI_FORMAT_R16G16B16A16_TYPELESS,
+        SurfaceType::R32               => DXGI_FORMAT_R32_TYPELESS,
+        SurfaceType::R32_G32           => DXGI_FORMAT_R32G32_TYPELESS,
+        SurfaceType::R32_G32_B32       => DXGI_FORMAT_R32G32B32_TYPELESS,
+        SurfaceType::R32_G32_B32_A32   => DXGI_FORMAT_R32G32B32A32_TYPELESS,
+        SurfaceType::D16               => DXGI_FORMAT_R16_TYPELESS,
+        SurfaceType::X8D24 |
+        SurfaceType::D24_S8            => DXGI_FORMAT_R24G8_TYPELESS,
+        SurfaceType::D32               => DXGI_FORMAT_R32_TYPELESS,
+        SurfaceType::D32_S8            => DXGI_FORMAT_R32G8X24_TYPELESS,
+        SurfaceType::BC1_RGB |
+        SurfaceType::BC1_RGBA          => DXGI_FORMAT_BC1_TYPELESS,
+        SurfaceType::BC2               => DXGI_FORMAT_BC2_TYPELESS,
+        SurfaceType::BC3               => DXGI_FORMAT_BC3_TYPELESS,
+        SurfaceType::BC4               => DXGI_FORMAT_BC4_TYPELESS,
+        SurfaceType::BC5               => DXGI_FORMAT_BC5_TYPELESS,
+        SurfaceType::BC6               => DXGI_FORMAT_BC6H_TYPELESS,
+        SurfaceType::BC7               => DXGI_FORMAT_BC7_TYPELESS,
+        _ => return None,
+    })
+}
+
 pub fn map_topology_type(primitive: Primitive) -> D3D12_PRIMITIVE_TOPOLOGY_TYPE {
     use hal::Primitive::*;
     match primitive {
@@ -140,8 +181,10 @@ pub fn map_rasterizer(rasterizer: &pso::Rasterizer) -> D3D12_RASTERIZER_DESC {
 
     D3D12_RASTERIZER_DESC {
         FillMode: match rasterizer.polygon_mode {
-            Point => {
-                error!("Point rasterization is not supported");
+            // Triangles are turned into points by a geometry shader on pipeline creation.
+            Point => D3D12_FILL_MODE_SOLID,
+            Line(pso::State::Static(width)) if width != 1.0 => {
+                error!("Unsupported line width: {:?}", width);
                 D3D12_FILL_MODE_WIREFRAME
             },
             Line(_) => D3D12_FILL_MODE_WIREFRAME,
@@ -159,9 +202,11 @@ pub fn map_rasterizer(rasterizer: &pso::Rasterizer) -> D3D12_RASTERIZER_DESC {
         DepthBias: rasterizer.depth_bias.map_or(0, |bias| bias.const_factor as INT),
         DepthBiasClamp: rasterizer.depth_bias.map_or(0.0, |bias| bias.clamp),
         SlopeScaledDepthBias: rasterizer.depth_bias.map_or(0.0, |bias| bias.slope_factor),
-        DepthClipEnable: rasterizer.depth_clamping as _,
-        MultisampleEnable: FALSE, // TODO: currently not supported
-        ForcedSampleCount: 0, // TODO: currently not supported
+        // Depth values are always clamped to the viewport depth range.
+        DepthClipEnable: if rasterizer.depth_clipping { TRUE } else { FALSE },
+        // Multisampling is part of the pipeline multisampling state.
+        MultisampleEnable: FALSE,
+        ForcedSampleCount: 0,
         AntialiasedLineEnable: FALSE, // TODO: currently not supported
         ConservativeRaster: if rasterizer.conservative { // TODO: check support
             D3D12_CONSERVATIVE_RASTERIZATION_MODE_ON
@@ -206,9 +251,29 @@ fn map_blend_op(operation: pso::BlendOp) -> (D3D12_BLEND_OP, D3D12_BLEND, D3D12_
 }
 
 
-pub fn map_render_targets(
-    color_targets: &[pso::ColorBlendDesc],
-) -> [D3D12_RENDER_TARGET_BLEND_DESC; 8] {
+fn map_logic_op(op: &pso::LogicOp) -> D3D12_LOGIC_OP {
+    use hal::pso::LogicOp::*;
+    match *op {
+        Clear => D3D12_LOGIC_OP_CLEAR,
+        And => D3D12_LOGIC_OP_AND,
+        AndReverse => D3D12_LOGIC_OP_AND_REVERSE,
+        AndInverted => D3D12_LOGIC_OP_AND_INVERTED,
+        Copy => D3D12_LOGIC_OP_COPY,
+        CopyInverted => D3D12_LOGIC_OP_COPY_INVERTED,
+        NoOp => D3D12_LOGIC_OP_NOOP,
+        Xor => D3D12_LOGIC_OP_XOR,
+        Nor => D3D12_LOGIC_OP_NOR,
+        Or => D3D12_LOGIC_OP_OR,
+        OrReverse => D3D12_LOGIC_OP_OR_REVERSE,
+        OrInverted => D3D12_LOGIC_OP_OR_INVERTED,
+        Equivalent => D3D12_LOGIC_OP_EQUIV,
+        Invert => D3D12_LOGIC_OP_INVERT,
+        Nand => D3D12_LOGIC_OP_NAND,
+        Set => D3D12_LOGIC_OP_SET,
+    }
+}
+
+pub fn map_blend_desc(desc: &pso::BlendDesc) -> D3D12_BLEND_DESC {
     let dummy_target = D3D12_RENDER_TARGET_BLEND_DESC {
         BlendEnable: FALSE,
         LogicOpEnable: FALSE,
@@ -223,7 +288,25 @@ pub fn map_render_targets(
     };
     let mut targets = [dummy_target; 8];
 
-    for (target, &pso::ColorBlendDesc(mask, blend)) in targets.iter_mut().zip(color_targets.iter()) {
+    if let Some(ref op) = desc.logic_op {
+        // Logic operations replace blending and apply to all render targets,
+        // which requires the description of the first target to be shared.
+        let mask = desc.targets.first().map_or(0, |&pso::ColorBlendDesc(mask, _)| mask.bits());
+        if desc.targets.iter().any(|&pso::ColorBlendDesc(other, _)| other.bits() != mask) {
+            error!("Logic operations require the same color mask for all render targets");
+        }
+        targets[0].LogicOpEnable = TRUE;
+        targets[0].LogicOp = map_logic_op(op);
+        targets[0].RenderTargetWriteMask = mask as UINT8;
+
+        return D3D12_BLEND_DESC {
+            AlphaToCoverageEnable: if desc.alpha_coverage { TRUE } else { FALSE },
+            IndependentBlendEnable: FALSE,
+            RenderTarget: targets,
+        };
+    }
+
+    for (target, &pso::ColorBlendDesc(mask, blend)) in targets.iter_mut().zip(desc.targets.iter()) {
         target.RenderTargetWriteMask = mask.bits() as UINT8;
         if let pso::BlendState::On { color, alpha } = blend {
             let (color_op, color_src, color_dst) = map_blend_op(color);
@@ -238,7 +321,11 @@ pub fn map_render_targets(
         }
     }
 
-    targets
+    D3D12_BLEND_DESC {
+        AlphaToCoverageEnable: if desc.alpha_coverage { TRUE } else { FALSE },
+        IndependentBlendEnable: TRUE,
+        RenderTarget: targets,
+    }
 }
 
 pub fn map_depth_stencil(dsi: &pso::DepthStencilDesc) -> D3D12_DEPTH_STENCIL_DESC {
@@ -323,12 +410,32 @@ fn map_filter_type(filter: image::Filter) -> D3D12_FILTER_TYPE {
     }
 }
 
+pub fn map_reduction(info: &image::SamplerInfo) -> D3D12_FILTER_REDUCTION_TYPE {
+    match (info.comparison, info.reduction) {
+        (Some(_), image::Reduction::WeightedAverage) => D3D12_FILTER_REDUCTION_TYPE_COMPARISON,
+        (Some(_), reduction) => {
+            warn!("Reduction {:?} can't be combined with a comparison", reduction);
+            D3D12_FILTER_REDUCTION_TYPE_COMPARISON
+        }
+        (None, image::Reduction::WeightedAverage) => D3D12_FILTER_REDUCTION_TYPE_STANDARD,
+        (None, image::Reduction::Minimum) => D3D12_FILTER_REDUCTION_TYPE_MINIMUM,
+        (None, image::Reduction::Maximum) => D3D12_FILTER_REDUCTION_TYPE_MAXIMUM,
+    }
+}
+
 pub fn map_filter(
     mag_filter: image::Filter,
     min_filter: image::Filter,
     mip_filter: image::Filter,
+    anisotropic: image::Anisotropic,
     reduction: D3D12_FILTER_REDUCTION_TYPE,
 ) -> D3D12_FILTER {
+    // Anisotropic filters ignore the other filters but keep the reduction type.
+    if let image::Anisotropic::On(_) = anisotropic {
+        return D3D12_FILTER_ANISOTROPIC |
+            (reduction & D3D12_FILTER_REDUCTION_TYPE_MASK) << D3D12_FILTER_REDUCTION_TYPE_SHIFT;
+    }
+
     let mag = map_filter_type(mag_filter);
     let min = map_filter_type(min_filter);
     let mip = map_filter_type(mip_filter);
@@ -339,8 +446,54 @@ pub fn map_filter(
     (reduction & D3D12_FILTER_REDUCTION_TYPE_MASK) << D3D12_FILTER_REDUCTION_TYPE_SHIFT
 }
 
+pub fn map_max_anisotropy(anisotropic: image::Anisotropic) -> u32 {
+    match anisotropic {
+        image::Anisotropic::On(max) => (max as u32).max(1).min(D3D12_MAX_MAXANISOTROPY),
+        image::Anisotropic::Off => 0,
+    }
+}
+
+pub fn map_lod_bias(bias: image::Lod) -> f32 {
+    let bias: f32 = bias.into();
+    bias.max(D3D12_MIP_LOD_BIAS_MIN).min(D3D12_MIP_LOD_BIAS_MAX)
+}
+
+fn map_static_border_color(color: image::PackedColor) -> D3D12_STATIC_BORDER_COLOR {
+    match color.0 {
+        0x0000_0000 => D3D12_STATIC_BORDER_COLOR_TRANSPARENT_BLACK,
+        0xFF00_0000 => D3D12_STATIC_BORDER_COLOR_OPAQUE_BLACK,
+        0xFFFF_FFFF => D3D12_STATIC_BORDER_COLOR_OPAQUE_WHITE,
+        _ => {
+            warn!("Static samplers only support black or white border colors, got {:#x}", color.0);
+            D3D12_STATIC_BORDER_COLOR_TRANSPARENT_BLACK
+        }
+    }
+}
+
+pub fn map_static_sampler(info: &image::SamplerInfo, register: u32, register_space: u32) -> D3D12_STATIC_SAMPLER_DESC {
+    D3D12_STATIC_SAMPLER_DESC {
+        Filter: map_filter(info.mag_filter, info.min_filter, info.mip_filter, info.anisotropic, map_reduction(info)),
+        AddressU: map_wrap(info.wrap_mode.0),
+        AddressV: map_wrap(info.wrap_mode.1),
+        AddressW: map_wrap(info.wrap_mode.2),
+        MipLODBias: map_lod_bias(info.lod_bias),
+        MaxAnisotropy: map_max_anisotropy(info.anisotropic),
+        ComparisonFunc: map_comparison(info.comparison.unwrap_or(pso::Comparison::Always)),
+        BorderColor: map_static_border_color(info.border),
+        MinLOD: info.lod_range.start.into(),
+        MaxLOD: info.lod_range.end.into(),
+        ShaderRegister: register,
+        RegisterSpace: register_space,
+        ShaderVisibility: D3D12_SHADER_VISIBILITY_ALL,
+    }
+}
+
 pub fn map_buffer_resource_state(access: buffer::Access) -> D3D12_RESOURCE_STATES {
     use self::buffer::Access;
+    // Acceleration structures can't leave their state, synchronization is done with UAV barriers.
+    if access.intersects(Access::ACCELERATION_STRUCTURE_READ | Access::ACCELERATION_STRUCTURE_WRITE) {
+        return D3D12_RESOURCE_STATE_RAYTRACING_ACCELERATION_STRUCTURE;
+    }
     // Mutable states
     if access.contains(Access::SHADER_WRITE) {
         return D3D12_RESOURCE_STATE_UNORDERED_ACCESS;
@@ -422,11 +575,35 @@ pub fn map_image_resource_state(access: image::Access, layout: image::Layout) ->
     state
 }
 
+/// Map an image layout to the resource state it implies, independent of the access.
+///
+/// Returns `None` for layouts which don't correspond to a single state,
+/// the state of an image in `General` layout depends on its access.
+pub fn map_image_layout_state(layout: image::Layout) -> Option<D3D12_RESOURCE_STATES> {
+    use self::image::Layout;
+    match layout {
+        Layout::ColorAttachmentOptimal => Some(D3D12_RESOURCE_STATE_RENDER_TARGET),
+        Layout::DepthStencilAttachmentOptimal => Some(D3D12_RESOURCE_STATE_DEPTH_WRITE),
+        Layout::DepthStencilReadOnlyOptimal => Some(D3D12_RESOURCE_STATE_DEPTH_READ),
+        Layout::ShaderReadOnlyOptimal => Some(
+            D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE | D3D12_RESOURCE_STATE_NON_PIXEL_SHADER_RESOURCE
+        ),
+        Layout::TransferSrcOptimal => Some(D3D12_RESOURCE_STATE_COPY_SOURCE),
+        Layout::TransferDstOptimal => Some(D3D12_RESOURCE_STATE_COPY_DEST),
+        Layout::Present => Some(D3D12_RESOURCE_STATE_PRESENT),
+        Layout::General |
+        Layout::Undefined |
+        Layout::Preinitialized => None,
+    }
+}
+
 pub fn map_descriptor_range(bind: &DescriptorSetLayoutBinding, register_space: u32, sampler: bool) -> D3D12_DESCRIPTOR_RANGE {
     D3D12_DESCRIPTOR_RANGE {
         RangeType: match bind.ty {
             pso::DescriptorType::Sampler => D3D12_DESCRIPTOR_RANGE_TYPE_SAMPLER,
-            pso::DescriptorType::SampledImage => D3D12_DESCRIPTOR_RANGE_TYPE_SRV,
+            pso::DescriptorType::SampledImage |
+            pso::DescriptorType::InputAttachment |
+            pso::DescriptorType::AccelerationStructure => D3D12_DESCRIPTOR_RANGE_TYPE_SRV,
             pso::DescriptorType::StorageBuffer |
             pso::DescriptorType::StorageImage => D3D12_DESCRIPTOR_RANGE_TYPE_UAV,
             pso::DescriptorType::UniformBuffer => D3D12_DESCRIPTOR_RANGE_TYPE_CBV,
@@ -444,6 +621,17 @@ pub fn map_descriptor_range(bind: &DescriptorSetLayoutBinding, register_space: u
     }
 }
 
+pub fn map_residency_priority(priority: memory::ResidencyPriority) -> D3D12_RESIDENCY_PRIORITY {
+    use hal::memory::ResidencyPriority::*;
+    match priority {
+        Minimum => D3D12_RESIDENCY_PRIORITY_MINIMUM,
+        Low => D3D12_RESIDENCY_PRIORITY_LOW,
+        Normal => D3D12_RESIDENCY_PRIORITY_NORMAL,
+        High => D3D12_RESIDENCY_PRIORITY_HIGH,
+        Maximum => D3D12_RESIDENCY_PRIORITY_MAXIMUM,
+    }
+}
+
 pub fn map_buffer_flags(usage: buffer::Usage) -> D3D12_RESOURCE_FLAGS {
     let mut flags = D3D12_RESOURCE_FLAG_NONE;
 
@@ -451,10 +639,124 @@ pub fn map_buffer_flags(usage: buffer::Usage) -> D3D12_RESOURCE_FLAGS {
     if usage.contains(buffer::Usage::STORAGE) || usage.contains(buffer::Usage::TRANSFER_DST) {
         flags = flags | D3D12_RESOURCE_FLAG_ALLOW_UNORDERED_ACCESS;
     }
+    // Storage texel buffers are accessed via typed UAVs.
+    if usage.contains(buffer::Usage::STORAGE_TEXEL) {
+        flags = flags | D3D12_RESOURCE_FLAG_ALLOW_UNORDERED_ACCESS;
+    }
+    // Acceleration structures are written by the builds via UAV access.
+    if usage.contains(buffer::Usage::ACCELERATION_STRUCTURE) {
+        flags = flags | D3D12_RESOURCE_FLAG_ALLOW_UNORDERED_ACCESS;
+    }
 
     flags
 }
 
+pub fn map_buffer_initial_state(usage: buffer::Usage) -> D3D12_RESOURCE_STATES {
+    if usage.contains(buffer::Usage::ACCELERATION_STRUCTURE | buffer::Usage::STORAGE) {
+        // Scratch memory of builds, which is written as unordered access.
+        D3D12_RESOURCE_STATE_UNORDERED_ACCESS
+    } else if usage.contains(buffer::Usage::ACCELERATION_STRUCTURE) {
+        D3D12_RESOURCE_STATE_RAYTRACING_ACCELERATION_STRUCTURE
+    } else {
+        D3D12_RESOURCE_STATE_COMMON
+    }
+}
+
+fn buffer_address(buffer: &n::Buffer, offset: u64) -> D3D12_GPU_VIRTUAL_ADDRESS {
+    unsafe { (*buffer.resource).GetGPUVirtualAddress() + offset }
+}
+
+pub fn map_shader_table(table: &accel_struct::ShaderTable<Backend>) -> D3D12_GPU_VIRTUAL_ADDRESS_RANGE_AND_STRIDE {
+    D3D12_GPU_VIRTUAL_ADDRESS_RANGE_AND_STRIDE {
+        StartAddress: buffer_address(table.buffer, table.offset),
+        SizeInBytes: table.size,
+        StrideInBytes: table.stride,
+    }
+}
+
+fn map_geometry(geometry: &accel_struct::Geometry<Backend>) -> D3D12_RAYTRACING_GEOMETRY_DESC {
+    let mut desc = D3D12_RAYTRACING_GEOMETRY_DESC {
+        Type: D3D12_RAYTRACING_GEOMETRY_TYPE_TRIANGLES,
+        Flags: geometry.flags.bits(),
+        u: unsafe { mem::zeroed() },
+    };
+
+    match geometry.data {
+        accel_struct::GeometryData::Triangles(ref triangles) => {
+            let (index_buffer, index_format, index_count) = match triangles.index_buffer {
+                Some((buffer, offset, ty)) => {
+                    let format = match ty {
+                        IndexType::U16 => DXGI_FORMAT_R16_UINT,
+                        IndexType::U32 => DXGI_FORMAT_R32_UINT,
+                    };
+                    (buffer_address(buffer, offset), format, triangles.index_count)
+                }
+                None => (0, DXGI_FORMAT_UNKNOWN, 0),
+            };
+
+            *unsafe { desc.u.Triangles_mut() } = D3D12_RAYTRACING_GEOMETRY_TRIANGLES_DESC {
+                Transform3x4: triangles.transform
+                    .map_or(0, |(buffer, offset)| buffer_address(buffer, offset)),
+                IndexFormat: index_format,
+                VertexFormat: map_format(triangles.vertex_format)
+                    .expect("Unsupported vertex format"),
+                IndexCount: index_count,
+                VertexCount: triangles.vertex_count,
+                IndexBuffer: index_buffer,
+                VertexBuffer: D3D12_GPU_VIRTUAL_ADDRESS_AND_STRIDE {
+                    StartAddress: buffer_address(triangles.vertex_buffer, triangles.vertex_offset),
+                    StrideInBytes: triangles.vertex_stride as _,
+                },
+            };
+        }
+        accel_struct::GeometryData::Aabbs(ref aabbs) => {
+            desc.Type = D3D12_RAYTRACING_GEOMETRY_TYPE_PROCEDURAL_PRIMITIVE_AABBS;
+            *unsafe { desc.u.AABBs_mut() } = D3D12_RAYTRACING_GEOMETRY_AABBS_DESC {
+                AABBCount: aabbs.count as _,
+                AABBs: D3D12_GPU_VIRTUAL_ADDRESS_AND_STRIDE {
+                    StartAddress: buffer_address(aabbs.buffer, aabbs.offset),
+                    StrideInBytes: aabbs.stride as _,
+                },
+            };
+        }
+    }
+
+    desc
+}
+
+/// Translate an acceleration structure build description.
+///
+/// The geometry descriptions are written into `geometries`, which need to outlive
+/// the returned inputs.
+pub fn map_acceleration_structure_inputs(
+    desc: &accel_struct::BuildDesc<Backend>,
+    geometries: &mut Vec<D3D12_RAYTRACING_GEOMETRY_DESC>,
+) -> D3D12_BUILD_RAYTRACING_ACCELERATION_STRUCTURE_INPUTS {
+    let mut inputs = D3D12_BUILD_RAYTRACING_ACCELERATION_STRUCTURE_INPUTS {
+        Type: D3D12_RAYTRACING_ACCELERATION_STRUCTURE_TYPE_TOP_LEVEL,
+        Flags: desc.flags.bits(),
+        NumDescs: 0,
+        DescsLayout: D3D12_ELEMENTS_LAYOUT_ARRAY,
+        u: unsafe { mem::zeroed() },
+    };
+
+    match desc.inputs {
+        accel_struct::Inputs::Bottom(geometry) => {
+            geometries.clear();
+            geometries.extend(geometry.iter().map(map_geometry));
+            inputs.Type = D3D12_RAYTRACING_ACCELERATION_STRUCTURE_TYPE_BOTTOM_LEVEL;
+            inputs.NumDescs = geometries.len() as _;
+            *unsafe { inputs.u.pGeometryDescs_mut() } = geometries.as_ptr();
+        }
+        accel_struct::Inputs::Top { buffer, offset, count } => {
+            inputs.NumDescs = count;
+            *unsafe { inputs.u.InstanceDescs_mut() } = buffer_address(buffer, offset);
+        }
+    }
+
+    inputs
+}
+
 pub fn map_image_flags(usage: image::Usage) -> D3D12_RESOURCE_FLAGS {
     use self::image::Usage;
     let mut flags = D3D12_RESOURCE_FLAG_NONE;
@@ -468,7 +770,7 @@ pub fn map_image_flags(usage: image::Usage) -> D3D12_RESOURCE_FLAGS {
     if usage.contains(Usage::STORAGE) {
         flags = flags | D3D12_RESOURCE_FLAG_ALLOW_UNORDERED_ACCESS;
     }
-    if usage.contains(Usage::DEPTH_STENCIL_ATTACHMENT) && !usage.contains(Usage::SAMPLED) {
+    if usage.contains(Usage::DEPTH_STENCIL_ATTACHMENT) && !usage.intersects(Usage::SAMPLED | Usage::INPUT_ATTACHMENT) {
         flags = flags | D3D12_RESOURCE_FLAG_DENY_SHADER_RESOURCE;
     }
 
@@ -497,3 +799,50 @@ pub fn map_stage(stage: pso::Stage) -> spirv::ExecutionModel {
         pso::Stage::Domain => spirv::ExecutionModel::TessellationEvaluation,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use hal::buffer::Usage;
+    use hal::image::{Anisotropic, Filter, Lod, Reduction, SamplerInfo, WrapMode};
+    use winapi::um::d3d12::*;
+    use ext::D3D12_RESOURCE_STATE_RAYTRACING_ACCELERATION_STRUCTURE;
+    use super::{map_buffer_initial_state, map_filter, map_lod_bias, map_max_anisotropy, map_reduction};
+
+    #[test]
+    fn test_map_filter_reduction() {
+        let mut info = SamplerInfo::new(Filter::Linear, WrapMode::Clamp);
+        info.reduction = Reduction::Minimum;
+        let reduction = map_reduction(&info);
+        assert_eq!(reduction, D3D12_FILTER_REDUCTION_TYPE_MINIMUM);
+        assert_eq!(
+            map_filter(info.mag_filter, info.min_filter, info.mip_filter, info.anisotropic, reduction),
+            D3D12_FILTER_MINIMUM_MIN_MAG_MIP_LINEAR
+        );
+        assert_eq!(
+            map_filter(info.mag_filter, info.min_filter, info.mip_filter, Anisotropic::On(8), reduction),
+            D3D12_FILTER_MINIMUM_ANISOTROPIC
+        );
+    }
+
+    #[test]
+    fn test_map_lod_clamp() {
+        assert_eq!(map_lod_bias(Lod::from(1.5)), 1.5);
+        assert_eq!(map_lod_bias(Lod::from(100.0)), D3D12_MIP_LOD_BIAS_MAX);
+        assert_eq!(map_lod_bias(Lod::from(-100.0)), D3D12_MIP_LOD_BIAS_MIN);
+        assert_eq!(map_max_anisotropy(Anisotropic::On(0)), 1);
+        assert_eq!(map_max_anisotropy(Anisotropic::On(64)), D3D12_MAX_MAXANISOTROPY);
+    }
+
+    #[test]
+    fn test_map_buffer_initial_state() {
+        assert_eq!(map_buffer_initial_state(Usage::STORAGE), D3D12_RESOURCE_STATE_COMMON);
+        assert_eq!(
+            map_buffer_initial_state(Usage::ACCELERATION_STRUCTURE),
+            D3D12_RESOURCE_STATE_RAYTRACING_ACCELERATION_STRUCTURE
+        );
+        assert_eq!(
+            map_buffer_initial_state(Usage::ACCELERATION_STRUCTURE | Usage::STORAGE),
+            D3D12_RESOURCE_STATE_UNORDERED_ACCESS
+        );
+    }
+}
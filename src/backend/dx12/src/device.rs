@@ -1,17 +1,19 @@
 use std::borrow::Borrow;
 use std::collections::{BTreeMap, VecDeque};
 use std::ops::Range;
-use std::{ffi, mem, ptr, slice};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::AtomicUsize;
+use std::{ffi, mem, ptr, slice, thread, time};
 
 use spirv_cross::{hlsl, spirv, ErrorCode as SpirvErrorCode};
 
 use winapi::Interface;
-use winapi::um::{d3d12, d3dcommon, d3dcompiler, synchapi, winbase, winnt};
+use winapi::um::{d3d12, d3d12shader, d3dcommon, d3dcompiler, synchapi, winbase, winnt};
 use winapi::shared::minwindef::{FALSE, TRUE, UINT};
 use winapi::shared::{dxgi, dxgi1_2, dxgi1_4, dxgiformat, dxgitype, winerror};
 use wio::com::ComPtr;
 
-use hal::{self, buffer, device as d, error, format, image, mapping, memory, pass, pso, query};
+use hal::{self, accel_struct, buffer, device as d, error, format, host_memory, image, indirect, mapping, memory, pass, pso, query};
 use hal::format::Aspects;
 use hal::memory::Requirements;
 use hal::pool::CommandPoolCreateFlags;
@@ -19,8 +21,9 @@ use hal::queue::{RawCommandQueue, QueueFamilyId};
 use hal::range::RangeArg;
 
 use {
-    conv, free_list, native as n, root_constants, window as w,
+    conv, ext, free_list, native as n, root_constants, window as w,
     Backend as B, Device, MemoryGroup, QUEUE_FAMILIES, MAX_VERTEX_BUFFERS, NUM_HEAP_PROPERTIES,
+    ROOT_SIGNATURE_SIZE,
 };
 use pool::RawCommandPool;
 use root_constants::RootConstant;
@@ -55,6 +58,19 @@ fn gen_query_error(err: SpirvErrorCode) -> d::ShaderError {
     d::ShaderError::CompilationFailed(msg)
 }
 
+fn wide_name(name: &str) -> Vec<u16> {
+    name.encode_utf16().chain(Some(0)).collect()
+}
+
+/// Name `object` after the current allocation tag of `hal::host_memory`,
+/// which attributes the memory of heaps and resources in PIX and ETW traces.
+fn set_tag_name(object: *mut d3d12::ID3D12Object) {
+    if let Some(tag) = host_memory::current_tag() {
+        let name = wide_name(tag);
+        unsafe { (*object).SetName(name.as_ptr()); }
+    }
+}
+
 fn shader_bytecode(shader: *mut d3dcommon::ID3DBlob) -> d3d12::D3D12_SHADER_BYTECODE {
     unsafe {
         d3d12::D3D12_SHADER_BYTECODE {
@@ -72,6 +88,69 @@ fn shader_bytecode(shader: *mut d3dcommon::ID3DBlob) -> d3d12::D3D12_SHADER_BYTE
     }
 }
 
+#[repr(C)]
+struct StreamSubobject<T> {
+    ty: ext::D3D12_PIPELINE_STATE_SUBOBJECT_TYPE,
+    desc: T,
+    // Subobjects in a stream are aligned to pointer size.
+    _align: [usize; 0],
+}
+
+/// Pipeline state stream, used for pipeline states which can't be
+/// described by `D3D12_GRAPHICS_PIPELINE_STATE_DESC`.
+struct PipelineStream(Vec<usize>);
+
+impl PipelineStream {
+    /// Create a stream containing all states of a graphics pipeline
+    /// description, except for the depth stencil state.
+    fn from_graphics(desc: &d3d12::D3D12_GRAPHICS_PIPELINE_STATE_DESC) -> Self {
+        let mut stream = PipelineStream(Vec::new());
+        stream.push(ext::D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_ROOT_SIGNATURE, desc.pRootSignature);
+        stream.push(ext::D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_VS, desc.VS);
+        stream.push(ext::D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_PS, desc.PS);
+        stream.push(ext::D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_DS, desc.DS);
+        stream.push(ext::D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_HS, desc.HS);
+        stream.push(ext::D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_GS, desc.GS);
+        stream.push(ext::D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_STREAM_OUTPUT, desc.StreamOutput);
+        stream.push(ext::D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_BLEND, desc.BlendState);
+        stream.push(ext::D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_SAMPLE_MASK, desc.SampleMask);
+        stream.push(ext::D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_RASTERIZER, desc.RasterizerState);
+        stream.push(ext::D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_INPUT_LAYOUT, desc.InputLayout);
+        stream.push(ext::D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_IB_STRIP_CUT_VALUE, desc.IBStripCutValue);
+        stream.push(ext::D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_PRIMITIVE_TOPOLOGY, desc.PrimitiveTopologyType);
+        stream.push(ext::D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_RENDER_TARGET_FORMATS, ext::D3D12_RT_FORMAT_ARRAY {
+            RTFormats: desc.RTVFormats,
+            NumRenderTargets: desc.NumRenderTargets,
+        });
+        stream.push(ext::D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_DEPTH_STENCIL_FORMAT, desc.DSVFormat);
+        stream.push(ext::D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_SAMPLE_DESC, desc.SampleDesc);
+        stream.push(ext::D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_NODE_MASK, desc.NodeMask);
+        stream.push(ext::D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_CACHED_PSO, desc.CachedPSO);
+        stream.push(ext::D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_FLAGS, desc.Flags);
+        stream
+    }
+
+    fn push<T>(&mut self, ty: ext::D3D12_PIPELINE_STATE_SUBOBJECT_TYPE, desc: T) {
+        // Write the fields into zeroed words, copying the whole subobject
+        // would read its uninitialized padding.
+        let start = self.0.len();
+        let words = mem::size_of::<StreamSubobject<T>>() / mem::size_of::<usize>();
+        self.0.resize(start + words, 0);
+        unsafe {
+            let subobject = self.0.as_mut_ptr().offset(start as isize) as *mut StreamSubobject<T>;
+            ptr::write(&mut (*subobject).ty, ty);
+            ptr::write(&mut (*subobject).desc, desc);
+        }
+    }
+
+    fn desc(&mut self) -> ext::D3D12_PIPELINE_STATE_STREAM_DESC {
+        ext::D3D12_PIPELINE_STATE_STREAM_DESC {
+            SizeInBytes: self.0.len() * mem::size_of::<usize>(),
+            pPipelineStateSubobjectStream: self.0.as_mut_ptr() as *mut _,
+        }
+    }
+}
+
 #[derive(Clone)]
 struct ViewInfo {
     resource: *mut d3d12::ID3D12Resource,
@@ -82,12 +161,32 @@ struct ViewInfo {
     range: image::SubresourceRange,
 }
 
+impl ViewInfo {
+    /// Plane of the resource addressed by the view, only planar video
+    /// formats are viewed with a plane other than the first.
+    fn plane_slice(&self) -> UINT {
+        if self.range.aspects.contains(Aspects::PLANE_1) { 1 } else { 0 }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub(crate) enum CommandSignature {
     Draw,
     DrawIndexed,
     Dispatch,
 }
 
+impl CommandSignature {
+    /// Size of the tightly packed arguments of one indirect command.
+    pub(crate) fn stride(&self) -> u32 {
+        match *self {
+            CommandSignature::Draw => 16,
+            CommandSignature::DrawIndexed => 20,
+            CommandSignature::Dispatch => 12,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct UnboundBuffer {
     requirements: memory::Requirements,
@@ -100,6 +199,9 @@ pub struct UnboundImage {
     #[derivative(Debug="ignore")]
     desc: d3d12::D3D12_RESOURCE_DESC,
     dsv_format: dxgiformat::DXGI_FORMAT,
+    // Typed format of the image, the resource is typeless for `MUTABLE_FORMAT` images.
+    format: dxgiformat::DXGI_FORMAT,
+    surface_type: format::SurfaceType,
     requirements: memory::Requirements,
     kind: image::Kind,
     usage: image::Usage,
@@ -123,6 +225,7 @@ impl Device {
         let stage_to_str = |stage, shader_model| {
             let stage = match stage {
                 pso::Stage::Vertex => "vs",
+                pso::Stage::Geometry => "gs",
                 pso::Stage::Fragment => "ps",
                 pso::Stage::Compute => "cs",
                 _ => unimplemented!(),
@@ -170,6 +273,95 @@ impl Device {
         }
     }
 
+    // Create a geometry shader emitting the vertices of each triangle as points,
+    // passing through the outputs of the vertex shader `vs`.
+    fn create_point_geometry_shader(
+        vs: *mut d3dcommon::ID3DBlob,
+    ) -> Result<*mut d3dcommon::ID3DBlob, d::ShaderError> {
+        let mut reflection: *mut d3d12shader::ID3D12ShaderReflection = ptr::null_mut();
+        let hr = unsafe {
+            d3dcompiler::D3DReflect(
+                (*vs).GetBufferPointer(),
+                (*vs).GetBufferSize(),
+                &d3d12shader::ID3D12ShaderReflection::uuidof(),
+                &mut reflection as *mut *mut _ as *mut *mut _,
+            )
+        };
+        if !winerror::SUCCEEDED(hr) {
+            error!("D3DReflect error {:x}", hr);
+            return Err(d::ShaderError::CompilationFailed("Vertex shader reflection failed".into()));
+        }
+        let reflection = unsafe { ComPtr::from_raw(reflection) };
+
+        let mut shader_desc: d3d12shader::D3D12_SHADER_DESC = unsafe { mem::zeroed() };
+        unsafe { reflection.GetDesc(&mut shader_desc); }
+
+        let mut fields = String::new();
+        for i in 0 .. shader_desc.OutputParameters {
+            let mut param: d3d12shader::D3D12_SIGNATURE_PARAMETER_DESC = unsafe { mem::zeroed() };
+            unsafe { reflection.GetOutputParameterDesc(i, &mut param); }
+            let semantic = unsafe { ffi::CStr::from_ptr(param.SemanticName) }.to_string_lossy();
+            let ty = match param.ComponentType {
+                d3dcommon::D3D_REGISTER_COMPONENT_UINT32 => "uint",
+                d3dcommon::D3D_REGISTER_COMPONENT_SINT32 => "int",
+                _ => "float",
+            };
+            let components = 8 - param.Mask.leading_zeros();
+            fields.push_str(&format!(
+                "    {}{} field{} : {}{};\n",
+                ty, components, i, semantic, param.SemanticIndex,
+            ));
+        }
+
+        let code = format!(
+            "struct Vertex {{\n{}}};\n\
+            [maxvertexcount(3)]\n\
+            void main(triangle Vertex input[3], inout PointStream<Vertex> output) {{\n\
+                output.Append(input[0]);\n\
+                output.Append(input[1]);\n\
+                output.Append(input[2]);\n\
+            }}\n",
+            fields,
+        );
+        debug!("Point geometry shader:\n{}", code);
+        Self::compile_shader(pso::Stage::Geometry, hlsl::ShaderModel::V5_1, "main", code.as_bytes())
+    }
+
+    // Reflect the workgroup size and instruction statistics of a compute shader.
+    // Registers are counted as DXBC temporaries with four components each, the
+    // hardware allocation isn't exposed by D3D12. DXIL can't be reflected here.
+    fn reflect_compute_statistics(cs: *mut d3dcommon::ID3DBlob) -> Option<pso::Statistics> {
+        let mut reflection: *mut d3d12shader::ID3D12ShaderReflection = ptr::null_mut();
+        let hr = unsafe {
+            d3dcompiler::D3DReflect(
+                (*cs).GetBufferPointer(),
+                (*cs).GetBufferSize(),
+                &d3d12shader::ID3D12ShaderReflection::uuidof(),
+                &mut reflection as *mut *mut _ as *mut *mut _,
+            )
+        };
+        if !winerror::SUCCEEDED(hr) {
+            warn!("D3DReflect error {:x}, compute pipeline statistics are unavailable", hr);
+            return None;
+        }
+        let reflection = unsafe { ComPtr::from_raw(reflection) };
+
+        let mut shader_desc: d3d12shader::D3D12_SHADER_DESC = unsafe { mem::zeroed() };
+        let mut workgroup_size = [0; 3];
+        unsafe {
+            reflection.GetDesc(&mut shader_desc);
+            reflection.GetThreadGroupSize(&mut workgroup_size[0], &mut workgroup_size[1], &mut workgroup_size[2]);
+        }
+
+        Some(pso::Statistics {
+            workgroup_size,
+            instructions: Some(shader_desc.InstructionCount),
+            registers: Some(shader_desc.TempRegisterCount),
+            shared_memory: None,
+            max_waves_per_cu: None,
+        })
+    }
+
     fn parse_spirv(raw_data: &[u8]) -> Result<spirv::Ast<hlsl::Target>, d::ShaderError> {
         // spec requires "codeSize must be a multiple of 4"
         assert_eq!(raw_data.len() & 3, 0);
@@ -228,6 +420,13 @@ impl Device {
                .map_err(gen_unexpected_error)?;
         }
 
+        // Input attachments are read as textures from the SRV of the attachment.
+        for input in &shader_resources.subpass_inputs {
+            let set = ast.get_decoration(input.id, spirv::Decoration::DescriptorSet).map_err(gen_query_error)?;
+            ast.set_decoration(input.id, spirv::Decoration::DescriptorSet, space_offset + 2*set)
+               .map_err(gen_unexpected_error)?;
+        }
+
         // TODO: other resources
 
         Ok(())
@@ -262,14 +461,48 @@ impl Device {
             .map_err(gen_unexpected_error)?;
         ast.set_root_constant_layout(root_constant_layout)
             .map_err(gen_unexpected_error)?;
-        ast.compile()
+
+        // Storage resources keep their descriptor set decoration, as they aren't patched.
+        let mut rasterizer_ordered = Vec::new();
+        if !layout.rasterizer_ordered.is_empty() {
+            let shader_resources = ast.get_shader_resources().map_err(gen_query_error)?;
+            for resource in shader_resources.storage_images.iter().chain(&shader_resources.storage_buffers) {
+                let set = ast.get_decoration(resource.id, spirv::Decoration::DescriptorSet).map_err(gen_query_error)?;
+                let binding = ast.get_decoration(resource.id, spirv::Decoration::Binding).map_err(gen_query_error)?;
+                if layout.rasterizer_ordered.contains(&(set, binding)) {
+                    rasterizer_ordered.push(resource.name.clone());
+                }
+            }
+        }
+
+        let code = ast.compile()
             .map_err(|err| {
                 let msg =  match err {
                     SpirvErrorCode::CompilationError(msg) => msg,
                     SpirvErrorCode::Unhandled => "Unknown compile error".into(),
                 };
                 d::ShaderError::CompilationFailed(msg)
+            })?;
+
+        if rasterizer_ordered.is_empty() {
+            return Ok(code);
+        }
+
+        // SPIRV-Cross only emits `RW*` resources, turn the declarations of the
+        // rasterizer ordered ones into the corresponding `RasterizerOrdered*` types.
+        Ok(code
+            .lines()
+            .map(|line| {
+                let ordered = line.starts_with("RW") && rasterizer_ordered
+                    .iter()
+                    .any(|name| line.contains(&format!(" {} : register(u", name)));
+                if ordered {
+                    format!("RasterizerOrdered{}\n", &line[2..])
+                } else {
+                    format!("{}\n", line)
+                }
             })
+            .collect())
     }
 
     // Extract entry point from shader module on pipeline creation.
@@ -342,6 +575,8 @@ impl Device {
                         Ok((shader, true))
                     })
             }
+            // DXIL libraries can only be consumed by state objects.
+            n::ShaderModule::Library(_) => Err(d::ShaderError::UnsupportedStage(stage)),
         }
     }
 
@@ -359,25 +594,100 @@ impl Device {
         Ok(n::ShaderModule::Compiled(shader_map))
     }
 
-    pub(crate) fn create_command_signature(
+    /// Create a shader module from a precompiled DXIL library (`lib_6_3` target),
+    /// containing the shaders of a ray tracing pipeline.
+    pub fn create_shader_library(&self, dxil: &[u8]) -> n::ShaderModule {
+        n::ShaderModule::Library(dxil.into())
+    }
+
+    // Collect the heaps of memory objects for the residency functions.
+    fn pageable_heaps<I>(memories: I) -> Vec<*mut d3d12::ID3D12Pageable>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<n::Memory>,
+    {
+        memories
+            .into_iter()
+            .map(|memory| memory.borrow().heap.as_raw() as *mut _)
+            .collect()
+    }
+
+    fn create_event() -> winnt::HANDLE {
+        unsafe { synchapi::CreateEventA(ptr::null_mut(), FALSE, FALSE, ptr::null()) }
+    }
+
+    // Remaining time of a wait started at `start`, in milliseconds.
+    fn remaining_timeout(start: time::Instant, timeout_ms: u32) -> u32 {
+        if timeout_ms == winbase::INFINITE {
+            winbase::INFINITE
+        } else {
+            let elapsed = start.elapsed();
+            let elapsed_ms = elapsed.as_secs() as u32 * 1000 + elapsed.subsec_nanos() / 1_000_000;
+            timeout_ms.saturating_sub(elapsed_ms)
+        }
+    }
+
+    // Wait for any of more than `MAXIMUM_WAIT_OBJECTS` fences, which exceeds
+    // the limit of `WaitForMultipleObjects`.
+    fn wait_for_any_fence(&self, fences: &[&n::Fence], timeout_ms: u32) -> bool {
+        let start = time::Instant::now();
+        // `SetEventOnMultipleFenceCompletion` requires Windows 10 1703, older systems poll the fences.
+        if let Ok(device) = self.raw.cast::<ext::ID3D12Device1>() {
+            let raw_fences = fences
+                .iter()
+                .map(|fence| fence.raw.as_raw())
+                .collect::<Vec<_>>();
+            let values = vec![1u64; fences.len()];
+            let mut events = self.events.lock().unwrap();
+            if events.is_empty() {
+                events.push(Self::create_event());
+            }
+            let hr = unsafe {
+                synchapi::ResetEvent(events[0]);
+                device.SetEventOnMultipleFenceCompletion(
+                    raw_fences.as_ptr(),
+                    values.as_ptr(),
+                    fences.len() as _,
+                    d3d12::D3D12_MULTIPLE_FENCE_WAIT_FLAG_ANY,
+                    events[0],
+                )
+            };
+            if !winerror::SUCCEEDED(hr) {
+                error!("error on setting the fence event: {:x}", hr);
+                return false;
+            }
+            return match unsafe { synchapi::WaitForSingleObject(events[0], timeout_ms) } {
+                winbase::WAIT_OBJECT_0 => true,
+                winerror::WAIT_TIMEOUT => false,
+                status => {
+                    error!("Unexpected wait status 0x{:X}", status);
+                    false
+                }
+            };
+        }
+
+        loop {
+            if fences.iter().any(|fence| d::Device::get_fence_status(self, fence)) {
+                return true;
+            }
+            if Self::remaining_timeout(start, timeout_ms) == 0 {
+                return false;
+            }
+            thread::sleep(time::Duration::from_millis(1));
+        }
+    }
+
+    // Create one of the signatures used for the plain indirect draw and dispatch commands.
+    pub(crate) fn create_default_command_signature(
         device: &mut ComPtr<d3d12::ID3D12Device>,
         ty: CommandSignature,
     ) -> ComPtr<d3d12::ID3D12CommandSignature> {
         let mut signature = ptr::null_mut();
 
-        let (arg_ty, stride) = match ty {
-            CommandSignature::Draw => (
-                d3d12::D3D12_INDIRECT_ARGUMENT_TYPE_DRAW,
-                16,
-            ),
-            CommandSignature::DrawIndexed => (
-                d3d12::D3D12_INDIRECT_ARGUMENT_TYPE_DRAW_INDEXED,
-                20,
-            ),
-            CommandSignature::Dispatch => (
-                d3d12::D3D12_INDIRECT_ARGUMENT_TYPE_DISPATCH,
-                12,
-            ),
+        let arg_ty = match ty {
+            CommandSignature::Draw => d3d12::D3D12_INDIRECT_ARGUMENT_TYPE_DRAW,
+            CommandSignature::DrawIndexed => d3d12::D3D12_INDIRECT_ARGUMENT_TYPE_DRAW_INDEXED,
+            CommandSignature::Dispatch => d3d12::D3D12_INDIRECT_ARGUMENT_TYPE_DISPATCH,
         };
 
         let arg = d3d12::D3D12_INDIRECT_ARGUMENT_DESC {
@@ -386,7 +696,7 @@ impl Device {
         };
 
         let desc = d3d12::D3D12_COMMAND_SIGNATURE_DESC {
-            ByteStride: stride,
+            ByteStride: ty.stride(),
             NumArgumentDescs: 1,
             pArgumentDescs: &arg,
             NodeMask: 0,
@@ -407,6 +717,83 @@ impl Device {
         unsafe { ComPtr::from_raw(signature) }
     }
 
+    // Create the null descriptors required for initializing descriptor sets on binding tiers below 3.
+    pub(crate) fn create_null_descriptors(
+        device: &mut ComPtr<d3d12::ID3D12Device>,
+        view_pool: &mut n::DescriptorCpuPool,
+        sampler_pool: &mut n::DescriptorCpuPool,
+        binding_tier: d3d12::D3D12_RESOURCE_BINDING_TIER,
+    ) -> Option<n::NullDescriptors> {
+        if binding_tier >= d3d12::D3D12_RESOURCE_BINDING_TIER_3 {
+            return None;
+        }
+
+        let cbv = view_pool.alloc_handles(1).cpu;
+        let desc = d3d12::D3D12_CONSTANT_BUFFER_VIEW_DESC {
+            BufferLocation: 0,
+            SizeInBytes: 0,
+        };
+        unsafe { device.CreateConstantBufferView(&desc, cbv); }
+
+        let srv_buffer = view_pool.alloc_handles(1).cpu;
+        let srv_texture = view_pool.alloc_handles(1).cpu;
+        for &(handle, dimension) in &[
+            (srv_buffer, d3d12::D3D12_SRV_DIMENSION_BUFFER),
+            (srv_texture, d3d12::D3D12_SRV_DIMENSION_TEXTURE2D),
+        ] {
+            let mut desc = d3d12::D3D12_SHADER_RESOURCE_VIEW_DESC {
+                Format: dxgiformat::DXGI_FORMAT_R32_UINT,
+                ViewDimension: dimension,
+                Shader4ComponentMapping: 0x1688, // identity mapping
+                u: unsafe { mem::zeroed() },
+            };
+            if dimension == d3d12::D3D12_SRV_DIMENSION_TEXTURE2D {
+                unsafe { desc.u.Texture2D_mut() }.MipLevels = 1;
+            }
+            unsafe { device.CreateShaderResourceView(ptr::null_mut(), &desc, handle); }
+        }
+
+        let uav_buffer = view_pool.alloc_handles(1).cpu;
+        let uav_texture = view_pool.alloc_handles(1).cpu;
+        for &(handle, dimension) in &[
+            (uav_buffer, d3d12::D3D12_UAV_DIMENSION_BUFFER),
+            (uav_texture, d3d12::D3D12_UAV_DIMENSION_TEXTURE2D),
+        ] {
+            let desc = d3d12::D3D12_UNORDERED_ACCESS_VIEW_DESC {
+                Format: dxgiformat::DXGI_FORMAT_R32_UINT,
+                ViewDimension: dimension,
+                u: unsafe { mem::zeroed() },
+            };
+            unsafe { device.CreateUnorderedAccessView(ptr::null_mut(), ptr::null_mut(), &desc, handle); }
+        }
+
+        // There are no null samplers, use a default point sampler instead.
+        let sampler = sampler_pool.alloc_handles(1).cpu;
+        let desc = d3d12::D3D12_SAMPLER_DESC {
+            Filter: d3d12::D3D12_FILTER_MIN_MAG_MIP_POINT,
+            AddressU: d3d12::D3D12_TEXTURE_ADDRESS_MODE_CLAMP,
+            AddressV: d3d12::D3D12_TEXTURE_ADDRESS_MODE_CLAMP,
+            AddressW: d3d12::D3D12_TEXTURE_ADDRESS_MODE_CLAMP,
+            MipLODBias: 0.0,
+            MaxAnisotropy: 0,
+            ComparisonFunc: d3d12::D3D12_COMPARISON_FUNC_ALWAYS,
+            BorderColor: [0.0; 4],
+            MinLOD: 0.0,
+            MaxLOD: ::std::f32::MAX,
+        };
+        unsafe { device.CreateSampler(&desc, sampler); }
+
+        Some(n::NullDescriptors {
+            cbv,
+            srv_buffer,
+            srv_texture,
+            uav_buffer,
+            uav_texture,
+            sampler,
+            tier_1: binding_tier == d3d12::D3D12_RESOURCE_BINDING_TIER_1,
+        })
+    }
+
     pub(crate) fn create_descriptor_heap_impl(
         device: &mut ComPtr<d3d12::ID3D12Device>,
         heap_type: d3d12::D3D12_DESCRIPTOR_HEAP_TYPE,
@@ -497,7 +884,7 @@ impl Device {
                 desc.ViewDimension = d3d12::D3D12_RTV_DIMENSION_TEXTURE2D;
                 *unsafe{ desc.u.Texture2D_mut() } = d3d12::D3D12_TEX2D_RTV {
                     MipSlice,
-                    PlaneSlice: 0, //TODO
+                    PlaneSlice: info.plane_slice(),
                 }
             }
             image::ViewKind::D2Array if is_msaa => {
@@ -513,7 +900,7 @@ impl Device {
                     MipSlice,
                     FirstArraySlice,
                     ArraySize,
-                    PlaneSlice: 0, //TODO
+                    PlaneSlice: info.plane_slice(),
                 }
             }
             image::ViewKind::D3 => {
@@ -666,7 +1053,7 @@ impl Device {
                 *unsafe{ desc.u.Texture2D_mut() } = d3d12::D3D12_TEX2D_SRV {
                     MostDetailedMip,
                     MipLevels,
-                    PlaneSlice: 0, //TODO
+                    PlaneSlice: info.plane_slice(),
                     ResourceMinLODClamp: 0.0,
                 }
             }
@@ -684,7 +1071,7 @@ impl Device {
                     MipLevels,
                     FirstArraySlice,
                     ArraySize,
-                    PlaneSlice: 0, //TODO
+                    PlaneSlice: info.plane_slice(),
                     ResourceMinLODClamp: 0.0,
                 }
             }
@@ -771,7 +1158,7 @@ impl Device {
                 desc.ViewDimension = d3d12::D3D12_UAV_DIMENSION_TEXTURE2D;
                 *unsafe{ desc.u.Texture2D_mut() } = d3d12::D3D12_TEX2D_UAV {
                     MipSlice,
-                    PlaneSlice: 0, //TODO
+                    PlaneSlice: info.plane_slice(),
                 }
             }
             image::ViewKind::D2Array => {
@@ -780,7 +1167,7 @@ impl Device {
                     MipSlice,
                     FirstArraySlice,
                     ArraySize,
-                    PlaneSlice: 0, //TODO
+                    PlaneSlice: info.plane_slice(),
                 }
             }
             image::ViewKind::D3 => {
@@ -863,6 +1250,7 @@ impl d::Device<B> for Device {
             return Err(d::OutOfMemory);
         }
         assert_eq!(winerror::S_OK, hr);
+        set_tag_name(heap as *mut _);
 
         // The first memory heap of each group corresponds to the default heap, which is can never
         // be mapped.
@@ -901,6 +1289,7 @@ impl d::Device<B> for Device {
                     &mut resource as *mut *mut _ as *mut *mut _,
                 )
             });
+            set_tag_name(resource as *mut _);
 
             Some(resource)
         } else {
@@ -916,29 +1305,22 @@ impl d::Device<B> for Device {
     }
 
     fn create_command_pool(
-        &self, family: QueueFamilyId, _create_flags: CommandPoolCreateFlags
+        &self, family: QueueFamilyId, create_flags: CommandPoolCreateFlags
     ) -> RawCommandPool {
         let list_type = QUEUE_FAMILIES[family.0].native_type();
-        // create command allocator
-        let mut command_allocator: *mut d3d12::ID3D12CommandAllocator = ptr::null_mut();
-        let hr = unsafe {
-            self.raw.clone().CreateCommandAllocator(
-                list_type,
-                &d3d12::IID_ID3D12CommandAllocator,
-                &mut command_allocator as *mut *mut _ as *mut *mut _,
-            )
-        };
-        // TODO: error handling
-        if !winerror::SUCCEEDED(hr) {
-            error!("error on command allocator creation: {:x}", hr);
-        }
+        // Fence tracking the submissions of the pool's command lists.
+        let fence = unsafe { ComPtr::from_raw(self.create_raw_fence(false)) };
 
-        RawCommandPool {
-            inner: unsafe { ComPtr::from_raw(command_allocator) },
-            device: self.raw.clone(),
+        RawCommandPool::new(
+            self.raw.clone(),
+            fence,
             list_type,
-            signatures: self.signatures.clone(),
-        }
+            family,
+            self.signatures.clone(),
+            self.shader_visible_heaps.clone(),
+            self.private_caps.render_passes,
+            create_flags.contains(CommandPoolCreateFlags::CONCURRENT_RECORDING),
+        )
     }
 
     fn destroy_command_pool(&self, _pool: RawCommandPool) {
@@ -1005,6 +1387,15 @@ impl d::Device<B> for Device {
                 debug_assert_eq!(SubState::Undefined, old);
             }
             for &(id, _layout) in sub.inputs {
+                // Input attachments are bound as shader resources, which can't be
+                // used while the same resource is bound as render target.
+                assert!(
+                    !sub.colors.iter().chain(sub.depth_stencil).any(|&(target, _)| target == id),
+                    "Attachment {} is used as input and render target in subpass {}, \
+                     feedback loops are not supported",
+                    id,
+                    sid,
+                );
                 let state = SubState::New(d3d12::D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE);
                 let old = mem::replace(&mut att_infos[id].sub_states[sid], state);
                 debug_assert_eq!(SubState::Undefined, old);
@@ -1071,6 +1462,8 @@ impl d::Device<B> for Device {
                 depth_stencil_attachment: subpasses[sid].borrow().depth_stencil.cloned(),
                 input_attachments: subpasses[sid].borrow().inputs.iter().cloned().collect(),
                 pre_barriers,
+                // Correlation masks have no D3D12 equivalent, the hint is ignored.
+                view_mask: subpasses[sid].borrow().view_mask,
             });
         }
         // if this fails, our graph has cycles
@@ -1119,8 +1512,14 @@ impl d::Device<B> for Device {
         //       ...
         //     DescriptorTable0: Space: 2 (+1) (SrvCbvUav)
         //     DescriptorTable0: Space: 3 (+1) (Sampler)
+        //     RootDescriptor0: Space: 2 (+1)
+        //       ...
         //     DescriptorTable1: Space: 4 (+1) (SrvCbvUav)
         //     ...
+        //
+        // Single buffer bindings with a high update frequency are promoted to
+        // root descriptors, which avoids the indirection through the descriptor heap,
+        // as long as the root signature stays within its size limit.
 
         let sets = sets.into_iter().collect::<Vec<_>>();
         let root_constants = root_constants::split(push_constant_ranges)
@@ -1180,11 +1579,88 @@ impl d::Device<B> for Device {
             })
             .sum();
         let mut ranges = Vec::with_capacity(total);
+        let mut static_samplers = Vec::new();
         let mut set_tables = Vec::with_capacity(sets.len());
 
+        // Each root constant, descriptor table and root descriptor occupy 1, 1 and 2 DWORDs.
+        // Start with the worst case of two tables per set, leaving the rest for promotion.
+        let mut root_size = root_constants
+            .iter()
+            .fold(0, |sum, c| sum + (c.range.end - c.range.start) as usize) + 2 * sets.len();
+
+        let push_constants_size = 4 * (root_size - 2 * sets.len());
+        if push_constants_size > self.limits.max_push_constants_size {
+            error!("Push constants of {} bytes exceed the limit of {} bytes",
+                push_constants_size, self.limits.max_push_constants_size);
+        }
+        if sets.len() > self.limits.max_bound_descriptor_sets {
+            error!("{} descriptor sets exceed the limit of {}",
+                sets.len(), self.limits.max_bound_descriptor_sets);
+        }
+        if root_size > ROOT_SIGNATURE_SIZE {
+            error!("Root signature of {} DWORDs exceeds the limit of {} DWORDs", root_size, ROOT_SIGNATURE_SIZE);
+        }
+
+        // Conservatively assume that all descriptors are visible to a single stage.
+        let (mut samplers, mut cbvs, mut srvs, mut uavs) = (0, 0, 0, 0);
+        for bind in sets.iter().flat_map(|set| set.borrow().bindings.iter()) {
+            let count = bind.count;
+            match bind.ty {
+                pso::DescriptorType::Sampler => samplers += count,
+                pso::DescriptorType::CombinedImageSampler => {
+                    samplers += count;
+                    srvs += count;
+                }
+                pso::DescriptorType::SampledImage |
+                pso::DescriptorType::UniformTexelBuffer |
+                pso::DescriptorType::UniformImageDynamic |
+                pso::DescriptorType::InputAttachment |
+                pso::DescriptorType::AccelerationStructure => srvs += count,
+                pso::DescriptorType::StorageImage |
+                pso::DescriptorType::StorageTexelBuffer |
+                pso::DescriptorType::StorageBuffer => uavs += count,
+                pso::DescriptorType::UniformBuffer |
+                pso::DescriptorType::UniformBufferDynamic => cbvs += count,
+            }
+        }
+        let limits = [
+            ("samplers", samplers, self.limits.max_per_stage_descriptor_samplers),
+            ("constant buffers", cbvs, self.limits.max_per_stage_descriptor_uniform_buffers),
+            ("shader resource views", srvs, self.limits.max_per_stage_descriptor_sampled_images),
+            ("unordered access views", uavs, self.limits.max_per_stage_descriptor_storage_buffers),
+        ];
+        for &(name, count, limit) in &limits {
+            if count > limit {
+                error!("{} {} exceed the per stage limit of {}", count, name, limit);
+            }
+        }
+        let root_descriptors = sets
+            .iter()
+            .map(|set| {
+                set.borrow()
+                    .bindings
+                    .iter()
+                    .filter(|bind| {
+                        let promotable = bind.count == 1 &&
+                            bind.update_frequency == Some(pso::UpdateFrequency::High) &&
+                            (bind.ty == pso::DescriptorType::UniformBuffer ||
+                                bind.ty == pso::DescriptorType::StorageBuffer);
+                        if promotable && root_size + 2 <= ROOT_SIGNATURE_SIZE {
+                            root_size += 2;
+                            true
+                        } else {
+                            false
+                        }
+                    })
+                    .map(|bind| bind.binding)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
         for (i, set) in sets.iter().enumerate() {
             let set = set.borrow();
             let mut table_type = n::SetTableTypes::empty();
+            let promoted = &root_descriptors[i];
 
             let mut param = d3d12::D3D12_ROOT_PARAMETER {
                 ParameterType: d3d12::D3D12_ROOT_PARAMETER_TYPE_DESCRIPTOR_TABLE,
@@ -1192,12 +1668,18 @@ impl d::Device<B> for Device {
                 .. unsafe { mem::zeroed() }
             };
 
+            // Descriptors of promoted bindings are still allocated in the heap,
+            // therefore the offsets of the remaining ranges need to be explicit.
             let range_base = ranges.len();
-            ranges.extend(set
-                .bindings
-                .iter()
-                .filter(|bind| bind.ty != pso::DescriptorType::Sampler)
-                .map(|bind| conv::map_descriptor_range(bind, (table_space_offset + 2*i) as u32, false)));
+            let mut table_offset = 0;
+            for bind in set.bindings.iter().filter(|bind| bind.ty != pso::DescriptorType::Sampler) {
+                if !promoted.contains(&bind.binding) {
+                    let mut range = conv::map_descriptor_range(bind, (table_space_offset + 2*i) as u32, false);
+                    range.OffsetInDescriptorsFromTableStart = table_offset;
+                    ranges.push(range);
+                }
+                table_offset += bind.count as u32;
+            }
 
             if ranges.len() > range_base {
                 *unsafe{ param.u.DescriptorTable_mut() } = d3d12::D3D12_ROOT_DESCRIPTOR_TABLE {
@@ -1209,11 +1691,27 @@ impl d::Device<B> for Device {
                 table_type |= n::SRV_CBV_UAV;
             }
 
+            // Immutable samplers are baked into the root signature as static samplers,
+            // which don't occupy any descriptor table slots.
+            let is_sampler = |bind: &&pso::DescriptorSetLayoutBinding| {
+                bind.ty == pso::DescriptorType::Sampler || bind.ty == pso::DescriptorType::CombinedImageSampler
+            };
+            for bind in set.bindings.iter().filter(&is_sampler) {
+                for (j, info) in bind.immutable_samplers.iter().enumerate() {
+                    static_samplers.push(conv::map_static_sampler(
+                        info,
+                        bind.binding + j as u32,
+                        (table_space_offset + 2*i+1) as u32,
+                    ));
+                }
+            }
+
             let range_base = ranges.len();
             ranges.extend(set
                 .bindings
                 .iter()
-                .filter(|bind| bind.ty == pso::DescriptorType::Sampler || bind.ty == pso::DescriptorType::CombinedImageSampler)
+                .filter(&is_sampler)
+                .filter(|bind| bind.immutable_samplers.is_empty())
                 .map(|bind| {
                     conv::map_descriptor_range(
                         bind,
@@ -1232,21 +1730,36 @@ impl d::Device<B> for Device {
                 table_type |= n::SAMPLERS;
             }
 
+            for bind in set.bindings.iter().filter(|bind| promoted.contains(&bind.binding)) {
+                let mut param = d3d12::D3D12_ROOT_PARAMETER {
+                    ParameterType: if bind.ty == pso::DescriptorType::StorageBuffer {
+                        d3d12::D3D12_ROOT_PARAMETER_TYPE_UAV
+                    } else {
+                        d3d12::D3D12_ROOT_PARAMETER_TYPE_CBV
+                    },
+                    ShaderVisibility: d3d12::D3D12_SHADER_VISIBILITY_ALL, //TODO
+                    .. unsafe { mem::zeroed() }
+                };
+
+                *unsafe{ param.u.Descriptor_mut() } = d3d12::D3D12_ROOT_DESCRIPTOR {
+                    ShaderRegister: bind.binding as _,
+                    RegisterSpace: (table_space_offset + 2*i) as _,
+                };
+
+                parameters.push(param);
+            }
+
             set_tables.push(table_type);
         }
 
         // Ensure that we didn't reallocate!
-        debug_assert_eq!(ranges.len(), total);
-
-        ranges.get_mut(0).map(|range| {
-            range.OffsetInDescriptorsFromTableStart = 0; // careful!
-        });
+        debug_assert!(ranges.len() <= total);
 
         let desc = d3d12::D3D12_ROOT_SIGNATURE_DESC {
             NumParameters: parameters.len() as u32,
             pParameters: parameters.as_ptr(),
-            NumStaticSamplers: 0,
-            pStaticSamplers: ptr::null(),
+            NumStaticSamplers: static_samplers.len() as u32,
+            pStaticSamplers: static_samplers.as_ptr(),
             Flags: d3d12::D3D12_ROOT_SIGNATURE_FLAG_ALLOW_INPUT_ASSEMBLER_INPUT_LAYOUT,
         };
 
@@ -1281,11 +1794,26 @@ impl d::Device<B> for Device {
             (*signature_raw).Release();
         }
 
+        let rasterizer_ordered = sets
+            .iter()
+            .enumerate()
+            .flat_map(|(i, set)| {
+                set.borrow()
+                    .bindings
+                    .iter()
+                    .filter(|bind| bind.rasterizer_ordered)
+                    .map(|bind| (i as u32, bind.binding))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
         n::PipelineLayout {
             raw: signature,
             tables: set_tables,
             root_constants,
             num_parameter_slots: parameters.len(),
+            root_descriptors,
+            rasterizer_ordered,
         }
     }
 
@@ -1293,6 +1821,24 @@ impl d::Device<B> for Device {
         &self,
         desc: &pso::GraphicsPipelineDesc<'a, B>,
     ) -> Result<n::GraphicsPipeline, pso::CreationError> {
+        // Get associated subpass information
+        let pass = {
+            let subpass = &desc.subpass;
+            match subpass.main_pass.subpasses.get(subpass.index) {
+                Some(subpass) => subpass,
+                None => return Err(pso::CreationError::InvalidSubpass(subpass.index)),
+            }
+        };
+        // Views are rendered with view instancing, which supports a limited number of views.
+        let view_count = 32 - pass.view_mask.leading_zeros();
+        if view_count > ext::D3D12_MAX_VIEW_INSTANCE_COUNT {
+            error!(
+                "View mask {:#x} of the subpass exceeds the maximum of {} views",
+                pass.view_mask, ext::D3D12_MAX_VIEW_INSTANCE_COUNT,
+            );
+            return Err(pso::CreationError::Other);
+        }
+
         let build_shader =
             |stage: pso::Stage, source: Option<&pso::EntryPoint<'a, B>>| {
                 let source = match source {
@@ -1310,6 +1856,21 @@ impl d::Device<B> for Device {
         let (ds, ds_destroy) = build_shader(pso::Stage::Domain, desc.shaders.domain.as_ref())?;
         let (hs, hs_destroy) = build_shader(pso::Stage::Hull, desc.shaders.hull.as_ref())?;
 
+        // D3D12 has no point fill mode, triangles are emitted as points by a geometry shader.
+        // As culling happens after the geometry shader, points of culled faces are still drawn.
+        let emulate_points = desc.rasterizer.polygon_mode == pso::PolygonMode::Point &&
+            conv::map_topology_type(desc.input_assembler.primitive) == d3d12::D3D12_PRIMITIVE_TOPOLOGY_TYPE_TRIANGLE;
+        let (gs, gs_destroy) = if !emulate_points {
+            (gs, gs_destroy)
+        } else if gs.is_null() && hs.is_null() {
+            let point_gs = Self::create_point_geometry_shader(vs)
+                .map_err(|err| pso::CreationError::Shader(err))?;
+            (point_gs, true)
+        } else {
+            error!("Point polygon mode can't be emulated for pipelines with geometry or tessellation shaders");
+            (gs, gs_destroy)
+        };
+
         // Define input element descriptions
         let input_element_descs = desc.attributes
             .iter()
@@ -1352,15 +1913,6 @@ impl d::Device<B> for Device {
         }
 
         // TODO: check maximum number of rtvs
-        // Get associated subpass information
-        let pass = {
-            let subpass = &desc.subpass;
-            match subpass.main_pass.subpasses.get(subpass.index) {
-                Some(subpass) => subpass,
-                None => return Err(pso::CreationError::InvalidSubpass(subpass.index)),
-            }
-        };
-
         // Get color attachment formats from subpass
         let (rtvs, num_rtvs) = {
             let mut rtvs = [dxgiformat::DXGI_FORMAT_UNKNOWN; 8];
@@ -1375,6 +1927,27 @@ impl d::Device<B> for Device {
             (rtvs, num_rtvs)
         };
 
+        let multisampling = desc.multisampling
+            .clone()
+            .unwrap_or(pso::Multisampling::new(1));
+        if multisampling.sample_shading.is_some() {
+            // Per-sample shading is derived from the shader inputs in D3D12.
+            error!("Sample rate shading is not supported");
+        }
+        // Pipelines without attachments rasterize with a forced sample count,
+        // which requires a single-sampled pipeline.
+        let has_targets = num_rtvs > 0 || pass.depth_stencil_attachment.is_some();
+        let (sample_count, forced_sample_count) = if has_targets || multisampling.rasterization_samples == 1 {
+            (multisampling.rasterization_samples, 0)
+        } else {
+            (1, multisampling.rasterization_samples)
+        };
+        let rasterizer_state = d3d12::D3D12_RASTERIZER_DESC {
+            MultisampleEnable: if multisampling.rasterization_samples > 1 { TRUE } else { FALSE },
+            ForcedSampleCount: forced_sample_count as _,
+            .. conv::map_rasterizer(&desc.rasterizer)
+        };
+
         // Setup pipeline description
         let pso_desc = d3d12::D3D12_GRAPHICS_PIPELINE_STATE_DESC {
             pRootSignature: desc.layout.raw,
@@ -1390,13 +1963,9 @@ impl d::Device<B> for Device {
                 NumStrides: 0,
                 RasterizedStream: 0,
             },
-            BlendState: d3d12::D3D12_BLEND_DESC {
-                AlphaToCoverageEnable: if desc.blender.alpha_coverage { TRUE } else { FALSE },
-                IndependentBlendEnable: TRUE,
-                RenderTarget: conv::map_render_targets(&desc.blender.targets),
-            },
-            SampleMask: UINT::max_value(),
-            RasterizerState: conv::map_rasterizer(&desc.rasterizer),
+            BlendState: conv::map_blend_desc(&desc.blender),
+            SampleMask: multisampling.sample_mask as UINT,
+            RasterizerState: rasterizer_state,
             DepthStencilState: desc.depth_stencil.as_ref().map_or(unsafe { mem::zeroed() }, conv::map_depth_stencil),
             InputLayout: d3d12::D3D12_INPUT_LAYOUT_DESC {
                 pInputElementDescs: input_element_descs.as_ptr(),
@@ -1416,8 +1985,8 @@ impl d::Device<B> for Device {
                 )
                 .unwrap_or(dxgiformat::DXGI_FORMAT_UNKNOWN),
             SampleDesc: dxgitype::DXGI_SAMPLE_DESC {
-                Count: 1, // TODO
-                Quality: 0, // TODO
+                Count: sample_count as _,
+                Quality: 0,
             },
             NodeMask: 0,
             CachedPSO: d3d12::D3D12_CACHED_PIPELINE_STATE {
@@ -1431,11 +2000,55 @@ impl d::Device<B> for Device {
 
         // Create PSO
         let mut pipeline = ptr::null_mut();
-        let hr = unsafe {
-            self.raw.clone().CreateGraphicsPipelineState(
-                &pso_desc,
-                &d3d12::IID_ID3D12PipelineState,
-                &mut pipeline as *mut *mut _ as *mut *mut _)
+        let depth_bounds = desc.depth_stencil.map_or(false, |ds| ds.depth_bounds);
+        let hr = if depth_bounds || pass.view_mask != 0 {
+            // Depth bounds testing and view instancing can only be enabled
+            // through a pipeline state stream.
+            let ds = pso_desc.DepthStencilState;
+            let mut stream = PipelineStream::from_graphics(&pso_desc);
+            stream.push(ext::D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_DEPTH_STENCIL1, ext::D3D12_DEPTH_STENCIL_DESC1 {
+                DepthEnable: ds.DepthEnable,
+                DepthWriteMask: ds.DepthWriteMask,
+                DepthFunc: ds.DepthFunc,
+                StencilEnable: ds.StencilEnable,
+                StencilReadMask: ds.StencilReadMask,
+                StencilWriteMask: ds.StencilWriteMask,
+                FrontFace: ds.FrontFace,
+                BackFace: ds.BackFace,
+                DepthBoundsTestEnable: if depth_bounds { TRUE } else { FALSE },
+            });
+            // Each view renders into the render target array layer with the same index,
+            // and uses the viewport with the same index if the pipeline has enough viewports.
+            let per_view_viewports = desc.viewport_count >= view_count as usize;
+            let view_locations = (0 .. view_count)
+                .map(|view| ext::D3D12_VIEW_INSTANCE_LOCATION {
+                    ViewportArrayIndex: if per_view_viewports { view } else { 0 },
+                    RenderTargetArrayIndex: view,
+                })
+                .collect::<Vec<_>>();
+            if pass.view_mask != 0 {
+                stream.push(ext::D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_VIEW_INSTANCING, ext::D3D12_VIEW_INSTANCING_DESC {
+                    ViewInstanceCount: view_locations.len() as _,
+                    pViewInstanceLocations: view_locations.as_ptr(),
+                    Flags: ext::D3D12_VIEW_INSTANCING_FLAG_ENABLE_VIEW_INSTANCE_MASKING,
+                });
+            }
+            match self.raw.cast::<ext::ID3D12Device2>() {
+                Ok(device) => unsafe {
+                    device.CreatePipelineState(
+                        &stream.desc(),
+                        &d3d12::IID_ID3D12PipelineState,
+                        &mut pipeline as *mut *mut _ as *mut *mut _)
+                },
+                Err(hr) => hr,
+            }
+        } else {
+            unsafe {
+                self.raw.clone().CreateGraphicsPipelineState(
+                    &pso_desc,
+                    &d3d12::IID_ID3D12PipelineState,
+                    &mut pipeline as *mut *mut _ as *mut *mut _)
+            }
         };
 
         let destroy_shader = |shader: *mut d3dcommon::ID3DBlob| unsafe { (*shader).Release() };
@@ -1455,6 +2068,12 @@ impl d::Device<B> for Device {
                 constants: desc.layout.root_constants.clone(),
                 vertex_strides,
                 baked_states: desc.baked_states.clone(),
+                viewport_count: desc.viewport_count,
+                formats: pass::AttachmentFormats::new(
+                    &desc.subpass.main_pass.attachments,
+                    &pass.color_attachments,
+                    pass.depth_stencil_attachment.as_ref(),
+                ),
             })
         } else {
             Err(pso::CreationError::Other)
@@ -1492,6 +2111,11 @@ impl d::Device<B> for Device {
                 &d3d12::IID_ID3D12PipelineState,
                 &mut pipeline as *mut *mut _ as *mut *mut _)
         };
+        let statistics = if winerror::SUCCEEDED(hr) {
+            Self::reflect_compute_statistics(cs)
+        } else {
+            None
+        };
 
         if cs_destroy {
             unsafe { (*cs).Release(); }
@@ -1503,50 +2127,287 @@ impl d::Device<B> for Device {
                 signature: desc.layout.raw,
                 num_parameter_slots: desc.layout.num_parameter_slots,
                 constants: desc.layout.root_constants.clone(),
+                statistics,
             })
         } else {
             Err(pso::CreationError::Other)
         }
     }
 
-    fn create_framebuffer<I>(
-        &self,
-        _renderpass: &n::RenderPass,
-        attachments: I,
-        _extent: image::Extent,
-    ) -> Result<n::Framebuffer, d::FramebufferError>
-    where
-        I: IntoIterator,
-        I::Item: Borrow<n::ImageView>
-    {
-        Ok(n::Framebuffer {
-            attachments: attachments.into_iter().map(|att| *att.borrow()).collect(),
-        })
-    }
-
-    fn create_shader_module(&self, raw_data: &[u8]) -> Result<n::ShaderModule, d::ShaderError> {
-        Ok(n::ShaderModule::Spirv(raw_data.into()))
+    fn get_compute_pipeline_statistics(&self, pipeline: &n::ComputePipeline) -> Option<pso::Statistics> {
+        pipeline.statistics.clone()
     }
 
-    fn create_buffer(
+    fn create_ray_tracing_pipeline<'a>(
         &self,
-        mut size: u64,
-        usage: buffer::Usage,
-    ) -> Result<UnboundBuffer, buffer::CreationError> {
-        if usage.contains(buffer::Usage::UNIFORM) {
-            // Constant buffer view sizes need to be aligned.
-            // Coupled with the offset alignment we can enforce an aligned CBV size
-            // on descriptor updates.
-            size = (size + 255) & !255;
+        desc: &pso::RayTracingPipelineDesc<'a, B>,
+    ) -> Result<n::RayTracingPipeline, pso::CreationError> {
+        if !self.private_caps.ray_tracing {
+            return Err(pso::CreationError::Other);
         }
+        let device = self.raw
+            .cast::<ext::ID3D12Device5>()
+            .map_err(|_| pso::CreationError::Other)?;
+
+        // Entry point names may collide between different libraries,
+        // so every shader gets exported under a unique name.
+        let shader_exports = (0 .. desc.shaders.len())
+            .map(|i| wide_name(&format!("shader{}", i)))
+            .collect::<Vec<_>>();
+        let entry_names = desc.shaders
+            .iter()
+            .map(|shader| wide_name(shader.entry.entry))
+            .collect::<Vec<_>>();
+        let export_descs = shader_exports
+            .iter()
+            .zip(&entry_names)
+            .map(|(export, entry)| ext::D3D12_EXPORT_DESC {
+                Name: export.as_ptr(),
+                ExportToRename: entry.as_ptr(),
+                Flags: 0,
+            })
+            .collect::<Vec<_>>();
 
-        let type_mask_shift = if self.private_caps.heterogeneous_resource_heaps {
-            MEM_TYPE_UNIVERSAL_SHIFT
-        } else {
-            MEM_TYPE_BUFFER_SHIFT
-        };
+        let mut libraries = Vec::with_capacity(desc.shaders.len());
+        for (shader, export) in desc.shaders.iter().zip(&export_descs) {
+            match *shader.entry.module {
+                n::ShaderModule::Library(ref dxil) => {
+                    libraries.push(ext::D3D12_DXIL_LIBRARY_DESC {
+                        DXILLibrary: d3d12::D3D12_SHADER_BYTECODE {
+                            pShaderBytecode: dxil.as_ptr() as *const _,
+                            BytecodeLength: dxil.len() as _,
+                        },
+                        NumExports: 1,
+                        pExports: export,
+                    });
+                }
+                _ => {
+                    error!("Ray tracing shader {:?} is not a DXIL library", shader.entry.entry);
+                    return Err(pso::CreationError::Other);
+                }
+            }
+        }
 
-        let requirements = memory::Requirements {
+        // General shaders are referenced by their export name, hit groups by their own.
+        let group_exports = desc.groups
+            .iter()
+            .enumerate()
+            .map(|(i, group)| match *group {
+                pso::ShaderGroup::General(shader) => shader_exports[shader as usize].clone(),
+                _ => wide_name(&format!("group{}", i)),
+            })
+            .collect::<Vec<_>>();
+        let import = |shader: Option<u32>| {
+            shader.map_or(ptr::null(), |i| shader_exports[i as usize].as_ptr())
+        };
+        let hit_groups = desc.groups
+            .iter()
+            .zip(&group_exports)
+            .filter_map(|(group, export)| match *group {
+                pso::ShaderGroup::General(_) => None,
+                pso::ShaderGroup::Triangles { closest_hit, any_hit } => Some(ext::D3D12_HIT_GROUP_DESC {
+                    HitGroupExport: export.as_ptr(),
+                    Type: ext::D3D12_HIT_GROUP_TYPE_TRIANGLES,
+                    AnyHitShaderImport: import(any_hit),
+                    ClosestHitShaderImport: import(closest_hit),
+                    IntersectionShaderImport: ptr::null(),
+                }),
+                pso::ShaderGroup::Procedural { intersection, closest_hit, any_hit } => Some(ext::D3D12_HIT_GROUP_DESC {
+                    HitGroupExport: export.as_ptr(),
+                    Type: ext::D3D12_HIT_GROUP_TYPE_PROCEDURAL_PRIMITIVE,
+                    AnyHitShaderImport: import(any_hit),
+                    ClosestHitShaderImport: import(closest_hit),
+                    IntersectionShaderImport: import(Some(intersection)),
+                }),
+            })
+            .collect::<Vec<_>>();
+
+        let shader_config = ext::D3D12_RAYTRACING_SHADER_CONFIG {
+            MaxPayloadSizeInBytes: desc.max_payload_size,
+            MaxAttributeSizeInBytes: desc.max_attribute_size,
+        };
+        let pipeline_config = ext::D3D12_RAYTRACING_PIPELINE_CONFIG {
+            MaxTraceRecursionDepth: desc.max_recursion_depth,
+        };
+        let root_signature = ext::D3D12_GLOBAL_ROOT_SIGNATURE {
+            pGlobalRootSignature: desc.layout.raw,
+        };
+
+        let mut subobjects = Vec::with_capacity(libraries.len() + hit_groups.len() + 3);
+        subobjects.extend(libraries.iter().map(|library| ext::D3D12_STATE_SUBOBJECT {
+            Type: ext::D3D12_STATE_SUBOBJECT_TYPE_DXIL_LIBRARY,
+            pDesc: library as *const _ as *const _,
+        }));
+        subobjects.extend(hit_groups.iter().map(|hit_group| ext::D3D12_STATE_SUBOBJECT {
+            Type: ext::D3D12_STATE_SUBOBJECT_TYPE_HIT_GROUP,
+            pDesc: hit_group as *const _ as *const _,
+        }));
+        subobjects.push(ext::D3D12_STATE_SUBOBJECT {
+            Type: ext::D3D12_STATE_SUBOBJECT_TYPE_RAYTRACING_SHADER_CONFIG,
+            pDesc: &shader_config as *const _ as *const _,
+        });
+        subobjects.push(ext::D3D12_STATE_SUBOBJECT {
+            Type: ext::D3D12_STATE_SUBOBJECT_TYPE_RAYTRACING_PIPELINE_CONFIG,
+            pDesc: &pipeline_config as *const _ as *const _,
+        });
+        subobjects.push(ext::D3D12_STATE_SUBOBJECT {
+            Type: ext::D3D12_STATE_SUBOBJECT_TYPE_GLOBAL_ROOT_SIGNATURE,
+            pDesc: &root_signature as *const _ as *const _,
+        });
+
+        let state_desc = ext::D3D12_STATE_OBJECT_DESC {
+            Type: ext::D3D12_STATE_OBJECT_TYPE_RAYTRACING_PIPELINE,
+            NumSubobjects: subobjects.len() as _,
+            pSubobjects: subobjects.as_ptr(),
+        };
+
+        let mut state_object: *mut ext::ID3D12StateObject = ptr::null_mut();
+        let hr = unsafe {
+            device.CreateStateObject(
+                &state_desc,
+                &ext::ID3D12StateObject::uuidof(),
+                &mut state_object as *mut *mut _ as *mut *mut _,
+            )
+        };
+        if !winerror::SUCCEEDED(hr) {
+            error!("error on state object creation {:x}", hr);
+            return Err(pso::CreationError::Other);
+        }
+
+        let mut properties: *mut ext::ID3D12StateObjectProperties = ptr::null_mut();
+        let hr = unsafe {
+            (*state_object).QueryInterface(
+                &ext::ID3D12StateObjectProperties::uuidof(),
+                &mut properties as *mut *mut _ as *mut *mut _,
+            )
+        };
+        if !winerror::SUCCEEDED(hr) {
+            error!("error on querying the state object properties {:x}", hr);
+            unsafe { (*state_object).Release(); }
+            return Err(pso::CreationError::Other);
+        }
+
+        Ok(n::RayTracingPipeline {
+            raw: state_object,
+            properties,
+            signature: desc.layout.raw,
+            num_parameter_slots: desc.layout.num_parameter_slots,
+            constants: desc.layout.root_constants.clone(),
+            group_exports,
+        })
+    }
+
+    fn get_ray_tracing_shader_group_handles(
+        &self,
+        pipeline: &n::RayTracingPipeline,
+        groups: Range<u32>,
+    ) -> Vec<u8> {
+        let size = ext::D3D12_SHADER_IDENTIFIER_SIZE_IN_BYTES as usize;
+        let exports = &pipeline.group_exports[groups.start as usize .. groups.end as usize];
+        let mut handles = Vec::with_capacity(size * exports.len());
+        for export in exports {
+            let identifier = unsafe { (*pipeline.properties).GetShaderIdentifier(export.as_ptr()) };
+            if identifier.is_null() {
+                // Zeroed identifiers are treated as null shader records.
+                error!("Missing identifier of shader group export");
+                handles.extend((0 .. size).map(|_| 0));
+            } else {
+                handles.extend_from_slice(unsafe {
+                    slice::from_raw_parts(identifier as *const u8, size)
+                });
+            }
+        }
+        handles
+    }
+
+    fn get_acceleration_structure_prebuild_info<'a>(
+        &self,
+        desc: &accel_struct::BuildDesc<'a, B>,
+    ) -> accel_struct::PrebuildInfo {
+        if !self.private_caps.ray_tracing {
+            error!("Ray tracing is not supported by the device");
+            return accel_struct::PrebuildInfo::default();
+        }
+        let device = match self.raw.cast::<ext::ID3D12Device5>() {
+            Ok(device) => device,
+            Err(_) => return accel_struct::PrebuildInfo::default(),
+        };
+
+        let mut geometries = Vec::new();
+        let inputs = conv::map_acceleration_structure_inputs(desc, &mut geometries);
+        let mut info: ext::D3D12_RAYTRACING_ACCELERATION_STRUCTURE_PREBUILD_INFO = unsafe { mem::zeroed() };
+        unsafe {
+            device.GetRaytracingAccelerationStructurePrebuildInfo(&inputs, &mut info);
+        }
+
+        accel_struct::PrebuildInfo {
+            result_size: info.ResultDataMaxSizeInBytes,
+            build_scratch_size: info.ScratchDataSizeInBytes,
+            update_scratch_size: info.UpdateScratchDataSizeInBytes,
+        }
+    }
+
+    fn create_acceleration_structure<'a>(
+        &self,
+        desc: &accel_struct::Desc<'a, B>,
+    ) -> Result<n::AccelerationStructure, accel_struct::CreationError> {
+        let alignment = ext::D3D12_RAYTRACING_ACCELERATION_STRUCTURE_BYTE_ALIGNMENT as u64;
+        if desc.offset % alignment != 0 {
+            return Err(accel_struct::CreationError::UnalignedOffset(desc.offset));
+        }
+        if desc.offset + desc.size > desc.buffer.size_in_bytes as u64 {
+            return Err(accel_struct::CreationError::OutOfBounds);
+        }
+
+        Ok(n::AccelerationStructure {
+            resource: desc.buffer.resource,
+            location: unsafe { (*desc.buffer.resource).GetGPUVirtualAddress() } + desc.offset,
+            level: desc.level,
+        })
+    }
+
+    fn get_acceleration_structure_address(&self, accel: &n::AccelerationStructure) -> u64 {
+        accel.location
+    }
+
+    fn create_framebuffer<I>(
+        &self,
+        _renderpass: &n::RenderPass,
+        attachments: I,
+        _extent: image::Extent,
+    ) -> Result<n::Framebuffer, d::FramebufferError>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<n::ImageView>
+    {
+        Ok(n::Framebuffer {
+            attachments: attachments.into_iter().map(|att| *att.borrow()).collect(),
+        })
+    }
+
+    fn create_shader_module(&self, raw_data: &[u8]) -> Result<n::ShaderModule, d::ShaderError> {
+        Ok(n::ShaderModule::Spirv(raw_data.into()))
+    }
+
+    fn create_buffer(
+        &self,
+        mut size: u64,
+        usage: buffer::Usage,
+    ) -> Result<UnboundBuffer, buffer::CreationError> {
+        if usage.contains(buffer::Usage::UNIFORM) {
+            // Constant buffer view sizes need to be aligned.
+            // Coupled with the offset alignment we can enforce an aligned CBV size
+            // on descriptor updates.
+            size = (size + 255) & !255;
+        }
+
+        let type_mask_shift = if self.private_caps.heterogeneous_resource_heaps {
+            MEM_TYPE_UNIVERSAL_SHIFT
+        } else {
+            MEM_TYPE_BUFFER_SHIFT
+        };
+
+        let requirements = memory::Requirements {
             size,
             alignment: d3d12::D3D12_DEFAULT_RESOURCE_PLACEMENT_ALIGNMENT as u64,
             type_mask: MEM_TYPE_MASK << type_mask_shift,
@@ -1599,58 +2460,96 @@ impl d::Device<B> for Device {
                 memory.heap.as_raw(),
                 offset,
                 &desc,
-                d3d12::D3D12_RESOURCE_STATE_COMMON,
+                conv::map_buffer_initial_state(buffer.usage),
                 ptr::null(),
                 &d3d12::IID_ID3D12Resource,
                 &mut resource,
             )
         });
+        set_tag_name(resource as *mut _);
 
-        let clear_uav = if buffer.usage.contains(buffer::Usage::TRANSFER_DST) {
-            let handles = self.uav_pool.lock().unwrap().alloc_handles(1);
+        Ok(n::Buffer {
+            resource: resource as *mut _,
+            size_in_bytes: buffer.requirements.size as _,
+            usage: buffer.usage,
+        })
+    }
+
+    fn create_buffer_view<R: RangeArg<u64>>(
+        &self,
+        buffer: &n::Buffer,
+        format: Option<format::Format>,
+        range: R,
+    ) -> Result<n::BufferView, buffer::ViewError> {
+        let format = match format {
+            Some(format) => format,
+            None => return Err(buffer::ViewError::Unsupported),
+        };
+        let dxgi_format = conv::map_format(format).ok_or(buffer::ViewError::Unsupported)?;
+        let element_size = (format.base_format().0.desc().bits / 8) as u64;
+        let start = *range.start().unwrap_or(&0);
+        let end = *range.end().unwrap_or(&(buffer.size_in_bytes as _));
+        // Typed views address the buffer in elements of the view format.
+        assert_eq!(start % element_size, 0);
+        let first_element = start / element_size;
+        let num_elements = (end - start) / element_size;
+        if num_elements > 1 << d3d12::D3D12_REQ_BUFFER_RESOURCE_TEXEL_COUNT_2_TO_EXP {
+            error!("Buffer view exceeds the maximum number of texel elements: {}", num_elements);
+            return Err(buffer::ViewError::Unsupported);
+        }
+
+        let handle_srv = if buffer.usage.contains(buffer::Usage::UNIFORM_TEXEL) {
+            let mut desc = d3d12::D3D12_SHADER_RESOURCE_VIEW_DESC {
+                Format: dxgi_format,
+                ViewDimension: d3d12::D3D12_SRV_DIMENSION_BUFFER,
+                Shader4ComponentMapping: 0x1688, // identity mapping
+                u: unsafe { mem::zeroed() },
+            };
+            *unsafe { desc.u.Buffer_mut() } = d3d12::D3D12_BUFFER_SRV {
+                FirstElement: first_element,
+                NumElements: num_elements as _,
+                StructureByteStride: 0,
+                Flags: d3d12::D3D12_BUFFER_SRV_FLAG_NONE,
+            };
+            let handle = self.srv_pool.lock().unwrap().alloc_handles(1).cpu;
+            unsafe {
+                self.raw.clone().CreateShaderResourceView(buffer.resource, &desc, handle);
+            }
+            Some(handle)
+        } else {
+            None
+        };
+
+        let handle_uav = if buffer.usage.contains(buffer::Usage::STORAGE_TEXEL) {
             let mut desc = d3d12::D3D12_UNORDERED_ACCESS_VIEW_DESC {
-                Format: dxgiformat::DXGI_FORMAT_R32_TYPELESS,
+                Format: dxgi_format,
                 ViewDimension: d3d12::D3D12_UAV_DIMENSION_BUFFER,
                 u: unsafe { mem::zeroed() },
             };
-
-           *unsafe { desc.u.Buffer_mut() } = d3d12::D3D12_BUFFER_UAV {
-                FirstElement: 0,
-                NumElements: (buffer.requirements.size / 4) as _,
+            *unsafe { desc.u.Buffer_mut() } = d3d12::D3D12_BUFFER_UAV {
+                FirstElement: first_element,
+                NumElements: num_elements as _,
                 StructureByteStride: 0,
                 CounterOffsetInBytes: 0,
-                Flags: d3d12::D3D12_BUFFER_UAV_FLAG_RAW,
+                Flags: d3d12::D3D12_BUFFER_UAV_FLAG_NONE,
             };
-
+            let handle = self.uav_pool.lock().unwrap().alloc_handles(1).cpu;
             unsafe {
-                self.raw.clone().CreateUnorderedAccessView(
-                    resource as *mut _,
-                    ptr::null_mut(),
-                    &desc,
-                    handles.cpu,
-                );
+                self.raw.clone().CreateUnorderedAccessView(buffer.resource, ptr::null_mut(), &desc, handle);
             }
-            Some(handles)
+            Some(handle)
         } else {
             None
         };
 
-        Ok(n::Buffer {
-            resource: resource as *mut _,
-            size_in_bytes: buffer.requirements.size as _,
-            clear_uav,
+        Ok(n::BufferView {
+            resource: buffer.resource,
+            handle_srv,
+            handle_uav,
+            tracking: buffer.tracking(),
         })
     }
 
-    fn create_buffer_view<R: RangeArg<u64>>(
-        &self,
-        _buffer: &n::Buffer,
-        _format: Option<format::Format>,
-        _range: R,
-    ) -> Result<n::BufferView, buffer::ViewError> {
-        unimplemented!()
-    }
-
     fn create_image(
         &self,
         kind: image::Kind,
@@ -1669,7 +2568,27 @@ impl d::Device<B> for Device {
         let bytes_per_block = (format_desc.bits / 8) as _;
         let block_dim = format_desc.dim;
 
+        let dxgi_format = match conv::map_format(format) {
+            Some(format) => format,
+            None => return Err(image::CreationError::Format(format)),
+        };
+
         let extent = kind.extent();
+        if base_format.0.is_planar() {
+            // Planar video formats are restricted to single level 2D images,
+            // with even dimensions for the subsampled chroma plane.
+            match kind {
+                image::Kind::D2(_, _, _, 1) if mip_levels == 1 => {}
+                _ => return Err(image::CreationError::Kind),
+            }
+            if extent.width % 2 != 0 {
+                return Err(image::CreationError::Size(extent.width as _));
+            }
+            if extent.height % 2 != 0 {
+                return Err(image::CreationError::Size(extent.height as _));
+            }
+        }
+
         let desc = d3d12::D3D12_RESOURCE_DESC {
             Dimension: match kind {
                 image::Kind::D1(..) => d3d12::D3D12_RESOURCE_DIMENSION_TEXTURE1D,
@@ -1685,9 +2604,12 @@ impl d::Device<B> for Device {
                 kind.num_layers() as _
             },
             MipLevels: mip_levels as _,
-            Format: match conv::map_format(format) {
-                Some(format) => format,
-                None => return Err(image::CreationError::Format(format)),
+            Format: if flags.contains(image::StorageFlags::MUTABLE_FORMAT) {
+                // Typeless resources can be viewed with any format of the family.
+                conv::map_format_typeless(base_format.0)
+                    .unwrap_or(dxgi_format)
+            } else {
+                dxgi_format
             },
             SampleDesc: dxgitype::DXGI_SAMPLE_DESC {
                 Count: kind.num_samples() as _,
@@ -1714,7 +2636,9 @@ impl d::Device<B> for Device {
 
         Ok(UnboundImage {
             dsv_format: conv::map_format_dsv(base_format.0)
-                .unwrap_or(desc.Format),
+                .unwrap_or(dxgi_format),
+            format: dxgi_format,
+            surface_type: base_format.0,
             desc,
             requirements: memory::Requirements {
                 size: alloc_info.SizeInBytes,
@@ -1766,6 +2690,7 @@ impl d::Device<B> for Device {
                 &mut resource,
             )
         });
+        set_tag_name(resource as *mut _);
 
         let info = ViewInfo {
             resource: resource as *mut _,
@@ -1776,7 +2701,7 @@ impl d::Device<B> for Device {
                 image::Kind::D2(..) => image::ViewKind::D2Array,
                 image::Kind::D3(..) => image::ViewKind::D3,
             },
-            format: image.desc.Format,
+            format: image.format,
             range: image::SubresourceRange {
                 aspects: Aspects::COLOR,
                 levels: 0 .. 1, //TODO?
@@ -1793,7 +2718,8 @@ impl d::Device<B> for Device {
             kind: image.kind,
             usage: image.usage,
             storage_flags: image.storage_flags,
-            dxgi_format: image.desc.Format,
+            dxgi_format: image.format,
+            surface_type: image.surface_type,
             bytes_per_block: image.bytes_per_block,
             block_dim: image.block_dim,
             num_levels: image.num_levels,
@@ -1828,6 +2754,12 @@ impl d::Device<B> for Device {
             } else {
                 None
             },
+            tracked: if image.storage_flags.contains(image::StorageFlags::TRACKED) {
+                // Images are created in `COMMON` state.
+                Some(Arc::new(AtomicUsize::new(d3d12::D3D12_RESOURCE_STATE_COMMON as _)))
+            } else {
+                None
+            },
         })
     }
 
@@ -1839,19 +2771,103 @@ impl d::Device<B> for Device {
         _swizzle: format::Swizzle,
         range: image::SubresourceRange,
     ) -> Result<n::ImageView, image::ViewError> {
+        // Planes of planar video formats are viewed with the matching color format,
+        // either given directly or derived from the format of the image.
+        let plane_surface = image.surface_type
+            .plane(range.aspects)
+            .map(|(surface, _)| surface);
+        let format = match plane_surface {
+            Some(_) if format.base_format().0 == image.surface_type => {
+                match format.plane(range.aspects) {
+                    Some((format, _)) => format,
+                    None => return Err(image::ViewError::BadFormat),
+                }
+            }
+            Some(surface) if format.base_format().0 != surface => {
+                error!("Plane {:?} of {:?} can't be viewed as {:?}", range.aspects, image.dxgi_format, format);
+                return Err(image::ViewError::BadFormat);
+            }
+            Some(_) => format,
+            None if image.surface_type.is_planar() => {
+                error!("Planar image views require a single plane aspect, got {:?}", range.aspects);
+                return Err(image::ViewError::BadFormat);
+            }
+            None => format,
+        };
+
+        let dxgi_format = conv::map_format(format)
+            .ok_or(image::ViewError::BadFormat)?;
+        if dxgi_format != image.dxgi_format && plane_surface.is_none() {
+            // Views with a different format require a typeless resource of the same family.
+            let mutable = image.storage_flags.contains(image::StorageFlags::MUTABLE_FORMAT);
+            let compatible = format.base_format().0 == image.surface_type &&
+                conv::map_format_typeless(image.surface_type).is_some();
+            if !mutable || !compatible {
+                error!("Image of format {:?} can't be viewed as {:?}", image.dxgi_format, format);
+                return Err(image::ViewError::BadFormat);
+            }
+        }
+
         let info = ViewInfo {
             resource: image.resource,
             kind: image.kind,
             flags: image.storage_flags,
             view_kind,
-            format: conv::map_format(format)
-                .ok_or(image::ViewError::BadFormat)?,
+            format: dxgi_format,
             range,
         };
 
+        // Render targets only cover the first level of the view.
+        let level = info.range.levels.start;
+        let mut attachment_subresources: Vec<Range<UINT>> = Vec::new();
+        for plane in image.planes(info.range.aspects) {
+            for layer in info.range.layers.clone() {
+                let subresource = image.calc_subresource(level as _, layer as _, plane);
+                match attachment_subresources.last_mut() {
+                    Some(ref mut run) if run.end == subresource => {
+                        run.end += 1;
+                        continue;
+                    }
+                    _ => {}
+                }
+                attachment_subresources.push(subresource .. subresource + 1);
+            }
+        }
+
+        let dsv_info = if image.usage.contains(image::Usage::DEPTH_STENCIL_ATTACHMENT) {
+            Some(ViewInfo {
+                format: conv::map_format_dsv(format.base_format().0)
+                    .ok_or(image::ViewError::BadFormat)?,
+                .. info.clone()
+            })
+        } else {
+            None
+        };
+
+        // Clears of a subset of the layers of an array attachment go through single layer views.
+        let layers = match view_kind {
+            image::ViewKind::D1Array |
+            image::ViewKind::D2Array if info.range.layers.end - info.range.layers.start > 1 => info.range.layers.clone(),
+            _ => 0 .. 0,
+        };
+        let mut layer_rtvs = Vec::new();
+        let mut layer_dsvs = Vec::new();
+        for layer in layers {
+            if image.usage.contains(image::Usage::COLOR_ATTACHMENT) {
+                let mut layer_info = info.clone();
+                layer_info.range.layers = layer .. layer + 1;
+                layer_rtvs.push(self.view_image_as_render_target(layer_info)?);
+            }
+            if let Some(ref dsv_info) = dsv_info {
+                let mut layer_info = dsv_info.clone();
+                layer_info.range.layers = layer .. layer + 1;
+                layer_dsvs.push(self.view_image_as_depth_stencil(layer_info)?);
+            }
+        }
+
         Ok(n::ImageView {
             resource: image.resource,
-            handle_srv: if image.usage.contains(image::Usage::SAMPLED) {
+            handle_srv: if image.usage.intersects(image::Usage::SAMPLED | image::Usage::INPUT_ATTACHMENT) {
                 Some(self.view_image_as_shader_resource(info.clone())?)
             } else {
                 None
@@ -1862,39 +2878,39 @@ impl d::Device<B> for Device {
                 None
             },
             handle_uav: if image.usage.contains(image::Usage::STORAGE) {
-                Some(self.view_image_as_storage(info.clone())?)
+                Some(self.view_image_as_storage(info)?)
             } else {
                 None
             },
-            handle_dsv: if image.usage.contains(image::Usage::DEPTH_STENCIL_ATTACHMENT) {
-                Some(self.view_image_as_depth_stencil(ViewInfo {
-                    format: conv::map_format_dsv(format.base_format().0)
-                        .ok_or(image::ViewError::BadFormat)?,
-                    .. info
-                })?)
-            } else {
-                None
+            handle_dsv: match dsv_info {
+                Some(dsv_info) => Some(self.view_image_as_depth_stencil(dsv_info)?),
+                None => None,
             },
+            layer_rtvs,
+            layer_dsvs,
+            attachment_subresources,
+            extent: image.kind.level_extent(level),
+            is_transient: image.usage.contains(image::Usage::TRANSIENT_ATTACHMENT),
+            tracking: image.tracking(),
         })
     }
 
     fn create_sampler(&self, info: image::SamplerInfo) -> n::Sampler {
         let handle = self.sampler_pool.lock().unwrap().alloc_handles(1).cpu;
 
-        let op = match info.comparison {
-            Some(_) => d3d12::D3D12_FILTER_REDUCTION_TYPE_COMPARISON,
-            None => d3d12::D3D12_FILTER_REDUCTION_TYPE_STANDARD,
-        };
         let desc = d3d12::D3D12_SAMPLER_DESC {
-            Filter: conv::map_filter(info.mag_filter, info.min_filter, info.mip_filter, op),
+            Filter: conv::map_filter(
+                info.mag_filter,
+                info.min_filter,
+                info.mip_filter,
+                info.anisotropic,
+                conv::map_reduction(&info),
+            ),
             AddressU: conv::map_wrap(info.wrap_mode.0),
             AddressV: conv::map_wrap(info.wrap_mode.1),
             AddressW: conv::map_wrap(info.wrap_mode.2),
-            MipLODBias: info.lod_bias.into(),
-            MaxAnisotropy: match info.anisotropic {
-                image::Anisotropic::On(max) => max as _, // TODO: check support here?
-                image::Anisotropic::Off => 0,
-            },
+            MipLODBias: conv::map_lod_bias(info.lod_bias),
+            MaxAnisotropy: conv::map_max_anisotropy(info.anisotropic),
             ComparisonFunc: conv::map_comparison(info.comparison.unwrap_or(pso::Comparison::Always)),
             BorderColor: info.border.into(),
             MinLOD: info.lod_range.start.into(),
@@ -1912,6 +2928,7 @@ impl d::Device<B> for Device {
         &self,
         max_sets: usize,
         descriptor_pools: I,
+        flags: pso::DescriptorPoolCreateFlags,
     ) -> n::DescriptorPool
     where
         I: IntoIterator,
@@ -1948,13 +2965,12 @@ impl d::Device<B> for Device {
                 .allocator
                 .allocate(num_srv_cbv_uav as _)
                 .unwrap(); // TODO: error/resize
-            n::DescriptorHeapSlice {
-                heap: heap_srv_cbv_uav.raw.clone(),
-                handle_size: heap_srv_cbv_uav.handle_size as _,
-                next: range.start,
+            n::DescriptorHeapSlice::new(
+                heap_srv_cbv_uav.raw.clone(),
                 range,
-                start: heap_srv_cbv_uav.start,
-            }
+                heap_srv_cbv_uav.start,
+                heap_srv_cbv_uav.handle_size as _,
+            )
         };
 
         let heap_sampler = {
@@ -1967,20 +2983,22 @@ impl d::Device<B> for Device {
                 .allocator
                 .allocate(num_samplers as _)
                 .unwrap(); // TODO: error/resize
-            n::DescriptorHeapSlice {
-                heap: heap_sampler.raw.clone(),
-                handle_size: heap_sampler.handle_size as _,
-                next: range.start as _,
+            n::DescriptorHeapSlice::new(
+                heap_sampler.raw.clone(),
                 range,
-                start: heap_sampler.start,
-            }
+                heap_sampler.start,
+                heap_sampler.handle_size as _,
+            )
         };
 
         n::DescriptorPool {
+            device: self.raw.clone(),
             heap_srv_cbv_uav,
             heap_sampler,
             pools: descriptor_pools,
             max_size: max_sets as _,
+            flags,
+            null_descriptors: self.null_descriptors,
         }
     }
 
@@ -2018,6 +3036,7 @@ impl d::Device<B> for Device {
             let mut offset = write.array_offset as u64;
             let mut target_binding = write.binding as usize;
             let mut bind_info = &write.set.binding_infos[target_binding];
+            let mut tracked = write.set.tracked.lock().unwrap();
             for descriptor in write.descriptors {
                 // spill over the writes onto the next binding
                 while offset >= bind_info.count {
@@ -2026,30 +3045,35 @@ impl d::Device<B> for Device {
                     bind_info = &write.set.binding_infos[target_binding];
                     offset = 0;
                 }
+                let mut alloc_update_handle = || {
+                    if update_pool_index == descriptor_update_pools.len() {
+                        let max_size = 1u64<<12; //arbitrary
+                        descriptor_update_pools.push(n::DescriptorCpuPool {
+                            heap: Self::create_descriptor_heap_impl(
+                                &mut self.raw.clone(),
+                                d3d12::D3D12_DESCRIPTOR_HEAP_TYPE_CBV_SRV_UAV,
+                                false,
+                                max_size as _,
+                            ),
+                            offset: 0,
+                            size: 0,
+                            max_size,
+                        });
+                    }
+                    let heap = descriptor_update_pools.last_mut().unwrap();
+                    let handle = heap.alloc_handles(1).cpu;
+                    if heap.size == heap.max_size {
+                        // pool is full, move to the next one
+                        update_pool_index += 1;
+                    }
+                    handle
+                };
                 match *descriptor.borrow() {
                     pso::Descriptor::Buffer(buffer, ref range) => {
-                        if update_pool_index == descriptor_update_pools.len() {
-                            let max_size = 1u64<<12; //arbitrary
-                            descriptor_update_pools.push(n::DescriptorCpuPool {
-                                heap: Self::create_descriptor_heap_impl(
-                                    &mut self.raw.clone(),
-                                    d3d12::D3D12_DESCRIPTOR_HEAP_TYPE_CBV_SRV_UAV,
-                                    false,
-                                    max_size as _,
-                                ),
-                                offset: 0,
-                                size: 0,
-                                max_size,
-                            });
-                        }
-                        let heap = descriptor_update_pools.last_mut().unwrap();
-                        let handle = heap.alloc_handles(1).cpu;
-                        if heap.size == heap.max_size {
-                            // pool is full, move to the next one
-                            update_pool_index += 1;
-                        }
+                        let handle = alloc_update_handle();
                         let start = range.start.unwrap_or(0);
                         let end = range.end.unwrap_or(buffer.size_in_bytes as _);
+                        bind_info.root_address.set(unsafe { (*buffer.resource).GetGPUVirtualAddress() } + start);
 
                         if bind_info.is_uav {
                             assert_eq!((end - start) % 4, 0);
@@ -2100,16 +3124,102 @@ impl d::Device<B> for Device {
                         src_views.push(image.handle_srv.unwrap());
                         dst_views.push(bind_info.view_range.as_ref().unwrap().at(offset));
                         num_views.push(1);
-                        src_samplers.push(sampler.handle);
-                        dst_samplers.push(bind_info.sampler_range.as_ref().unwrap().at(offset));
-                        num_samplers.push(1);
+                        // Bindings with immutable samplers have no sampler range.
+                        if let Some(ref range) = bind_info.sampler_range {
+                            src_samplers.push(sampler.handle);
+                            dst_samplers.push(range.at(offset));
+                            num_samplers.push(1);
+                        }
                     }
                     pso::Descriptor::Sampler(sampler) => {
-                        src_samplers.push(sampler.handle);
-                        dst_samplers.push(bind_info.sampler_range.as_ref().unwrap().at(offset));
-                        num_samplers.push(1);
+                        if let Some(ref range) = bind_info.sampler_range {
+                            src_samplers.push(sampler.handle);
+                            dst_samplers.push(range.at(offset));
+                            num_samplers.push(1);
+                        }
+                    }
+                    pso::Descriptor::TexelBuffer(view) => {
+                        let handle = if bind_info.is_uav {
+                            view.handle_uav.unwrap()
+                        } else {
+                            view.handle_srv.unwrap()
+                        };
+                        src_views.push(handle);
+                        dst_views.push(bind_info.view_range.as_ref().unwrap().at(offset));
+                        num_views.push(1);
+                    }
+                    pso::Descriptor::AccelerationStructure(accel) => {
+                        let handle = alloc_update_handle();
+                        let mut desc = d3d12::D3D12_SHADER_RESOURCE_VIEW_DESC {
+                            Format: dxgiformat::DXGI_FORMAT_UNKNOWN,
+                            ViewDimension: ext::D3D12_SRV_DIMENSION_RAYTRACING_ACCELERATION_STRUCTURE,
+                            Shader4ComponentMapping: 0x1688, // identity mapping
+                            u: unsafe { mem::zeroed() },
+                        };
+                        // The acceleration structure view only consists of the GPU address,
+                        // which aliases the first element of the buffer view.
+                        unsafe { desc.u.Buffer_mut() }.FirstElement = accel.location;
+                        unsafe {
+                            self.raw.CreateShaderResourceView(ptr::null_mut(), &desc, handle);
+                        }
+
+                        src_views.push(handle);
+                        dst_views.push(bind_info.view_range.as_ref().unwrap().at(offset));
+                        num_views.push(1);
                     }
-                    pso::Descriptor::TexelBuffer(_) => unimplemented!()
+                }
+
+                // Keep track of the resources which need to be transitioned when binding the set.
+                let shader_resource = d3d12::D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE |
+                    d3d12::D3D12_RESOURCE_STATE_NON_PIXEL_SHADER_RESOURCE;
+                let usage = match *descriptor.borrow() {
+                    pso::Descriptor::Buffer(buffer, _) => Some((
+                        buffer.resource,
+                        buffer.tracking(),
+                        if bind_info.is_uav {
+                            d3d12::D3D12_RESOURCE_STATE_UNORDERED_ACCESS
+                        } else {
+                            d3d12::D3D12_RESOURCE_STATE_VERTEX_AND_CONSTANT_BUFFER
+                        },
+                    )),
+                    pso::Descriptor::Image(image, _) => Some((
+                        image.resource,
+                        image.tracking,
+                        if bind_info.is_uav {
+                            d3d12::D3D12_RESOURCE_STATE_UNORDERED_ACCESS
+                        } else {
+                            shader_resource
+                        },
+                    )),
+                    pso::Descriptor::CombinedImageSampler(image, _, _) => Some((
+                        image.resource,
+                        image.tracking,
+                        shader_resource,
+                    )),
+                    pso::Descriptor::TexelBuffer(view) => Some((
+                        view.resource,
+                        view.tracking,
+                        if bind_info.is_uav {
+                            d3d12::D3D12_RESOURCE_STATE_UNORDERED_ACCESS
+                        } else {
+                            shader_resource
+                        },
+                    )),
+                    _ => None,
+                };
+                let binding = target_binding as pso::DescriptorBinding;
+                tracked.retain(|desc| desc.binding != binding || desc.array_index != offset);
+                match usage {
+                    Some((resource, tracking, state)) if tracking != n::Tracking::None => {
+                        tracked.push(n::TrackedDescriptor {
+                            binding,
+                            array_index: offset,
+                            resource,
+                            tracking,
+                            state,
+                        });
+                    }
+                    _ => {}
                 }
                 offset += 1;
             }
@@ -2178,6 +3288,27 @@ impl d::Device<B> for Device {
                 dst_samplers.push(dst_range.at(copy.dst_array_offset as _));
                 num_samplers.push(copy.count as u32);
             }
+
+            // The sets may be the same, release the lock before updating the destination.
+            let in_range = |desc: &n::TrackedDescriptor, binding: pso::DescriptorBinding, start: pso::DescriptorArrayIndex| {
+                desc.binding == binding &&
+                    desc.array_index >= start as u64 &&
+                    desc.array_index < (start + copy.count) as u64
+            };
+            let tracked = copy.src_set.tracked
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|desc| in_range(desc, copy.src_binding, copy.src_array_offset))
+                .cloned()
+                .collect::<Vec<_>>();
+            let mut dst_tracked = copy.dst_set.tracked.lock().unwrap();
+            dst_tracked.retain(|desc| !in_range(desc, copy.dst_binding, copy.dst_array_offset));
+            dst_tracked.extend(tracked.into_iter().map(|desc| n::TrackedDescriptor {
+                binding: copy.dst_binding,
+                array_index: desc.array_index - copy.src_array_offset as u64 + copy.dst_array_offset as u64,
+                .. desc
+            }));
         }
 
         if !num_views.is_empty() {
@@ -2350,45 +3481,70 @@ impl d::Device<B> for Device {
         I::Item: Borrow<n::Fence>,
     {
         let fences = fences.into_iter().collect::<Vec<_>>();
-        let mut events = self.events.lock().unwrap();
-        for _ in events.len() .. fences.len() {
-            events.push(unsafe {
-                synchapi::CreateEventA(
-                    ptr::null_mut(),
-                    FALSE,
-                    FALSE,
-                    ptr::null(),
-                )
-            });
-        }
-
-        for (&event, fence) in events.iter().zip(fences.iter()) {
-            assert_eq!(winerror::S_OK, unsafe {
-                synchapi::ResetEvent(event);
-                fence.borrow().raw.clone().SetEventOnCompletion(1, event)
-            });
+        // Signaled fences don't need to be waited on.
+        let pending = fences
+            .iter()
+            .map(|fence| fence.borrow())
+            .filter(|fence| !self.get_fence_status(fence))
+            .collect::<Vec<_>>();
+        if pending.is_empty() {
+            return true;
         }
 
-        let all = match wait {
-            d::WaitFor::Any => FALSE,
-            d::WaitFor::All => TRUE,
-        };
-        let hr = unsafe {
-            synchapi::WaitForMultipleObjects(fences.len() as u32, events.as_ptr(), all, timeout_ms)
+        let (all, max_events) = match wait {
+            d::WaitFor::Any => {
+                if pending.len() < fences.len() {
+                    return true;
+                }
+                if pending.len() > winnt::MAXIMUM_WAIT_OBJECTS as usize {
+                    return self.wait_for_any_fence(&pending, timeout_ms);
+                }
+                (FALSE, pending.len())
+            }
+            // `WaitForMultipleObjects` waits for at most `MAXIMUM_WAIT_OBJECTS` events,
+            // larger sets of fences are waited on in batches.
+            d::WaitFor::All => (TRUE, winnt::MAXIMUM_WAIT_OBJECTS as usize),
         };
 
-        const WAIT_OBJECT_LAST: u32 = winbase::WAIT_OBJECT_0 + winnt::MAXIMUM_WAIT_OBJECTS;
-        const WAIT_ABANDONED_LAST: u32 = winbase::WAIT_ABANDONED_0 + winnt::MAXIMUM_WAIT_OBJECTS;
-        match hr {
-            winbase::WAIT_OBJECT_0 ... WAIT_OBJECT_LAST => true,
-            winbase::WAIT_ABANDONED_0 ... WAIT_ABANDONED_LAST => true, //TODO?
-            winerror::WAIT_TIMEOUT => false,
-            _ => panic!("Unexpected wait status 0x{:X}", hr),
+        let start = time::Instant::now();
+        let mut events = self.events.lock().unwrap();
+        for batch in pending.chunks(max_events) {
+            for _ in events.len() .. batch.len() {
+                events.push(Self::create_event());
+            }
+
+            for (&event, fence) in events.iter().zip(batch.iter()) {
+                let hr = unsafe {
+                    synchapi::ResetEvent(event);
+                    fence.raw.SetEventOnCompletion(1, event)
+                };
+                if !winerror::SUCCEEDED(hr) {
+                    error!("error on setting the fence event: {:x}", hr);
+                    return false;
+                }
+            }
+
+            let timeout = Self::remaining_timeout(start, timeout_ms);
+            let hr = unsafe {
+                synchapi::WaitForMultipleObjects(batch.len() as u32, events.as_ptr(), all, timeout)
+            };
+
+            const WAIT_OBJECT_LAST: u32 = winbase::WAIT_OBJECT_0 + winnt::MAXIMUM_WAIT_OBJECTS;
+            const WAIT_ABANDONED_LAST: u32 = winbase::WAIT_ABANDONED_0 + winnt::MAXIMUM_WAIT_OBJECTS;
+            match hr {
+                winbase::WAIT_OBJECT_0 ... WAIT_OBJECT_LAST => {}
+                winbase::WAIT_ABANDONED_0 ... WAIT_ABANDONED_LAST => {} //TODO?
+                winerror::WAIT_TIMEOUT => return false,
+                _ => panic!("Unexpected wait status 0x{:X}", hr),
+            }
         }
+
+        true
     }
 
-    fn get_fence_status(&self, _fence: &n::Fence) -> bool {
-        unimplemented!()
+    fn get_fence_status(&self, fence: &n::Fence) -> bool {
+        // Fences are signaled with a value of 1 and reset to 0.
+        unsafe { fence.raw.GetCompletedValue() >= 1 }
     }
 
     fn free_memory(&self, memory: n::Memory) {
@@ -2397,7 +3553,56 @@ impl d::Device<B> for Device {
         }
     }
 
-    fn create_query_pool(&self, query_ty: query::QueryType, count: u32) -> n::QueryPool {
+    fn set_resource_priority<I>(&self, memories: I, priority: memory::ResidencyPriority)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<n::Memory>,
+    {
+        let heaps = Self::pageable_heaps(memories);
+        let priorities = vec![conv::map_residency_priority(priority); heaps.len()];
+        // Residency priorities require Windows 10 1709, older systems ignore the hint.
+        if let Ok(device) = self.raw.cast::<ext::ID3D12Device1>() {
+            let hr = unsafe {
+                device.SetResidencyPriority(heaps.len() as _, heaps.as_ptr(), priorities.as_ptr())
+            };
+            if !winerror::SUCCEEDED(hr) {
+                error!("error on setting residency priority: {:x}", hr);
+            }
+        }
+    }
+
+    fn make_resident<I>(&self, memories: I) -> Result<(), d::OutOfMemory>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<n::Memory>,
+    {
+        let heaps = Self::pageable_heaps(memories);
+        let hr = unsafe { self.raw.MakeResident(heaps.len() as _, heaps.as_ptr()) };
+        if hr == winerror::E_OUTOFMEMORY {
+            return Err(d::OutOfMemory);
+        }
+        assert_eq!(winerror::S_OK, hr);
+        Ok(())
+    }
+
+    fn evict<I>(&self, memories: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<n::Memory>,
+    {
+        let heaps = Self::pageable_heaps(memories);
+        let hr = unsafe { self.raw.Evict(heaps.len() as _, heaps.as_ptr()) };
+        if !winerror::SUCCEEDED(hr) {
+            error!("error on evicting memory: {:x}", hr);
+        }
+    }
+
+    fn create_query_pool(
+        &self,
+        query_ty: query::QueryType,
+        count: u32,
+        flags: query::QueryPoolCreateFlags,
+    ) -> n::QueryPool {
         let heap_ty = match query_ty {
             query::QueryType::Occlusion =>
                 d3d12::D3D12_QUERY_HEAP_TYPE_OCCLUSION,
@@ -2425,6 +3630,7 @@ impl d::Device<B> for Device {
         n::QueryPool {
             raw: unsafe { ComPtr::from_raw(handle as *mut _) },
             ty: heap_ty,
+            predication: flags.contains(query::QueryPoolCreateFlags::PREDICATION),
         }
     }
 
@@ -2432,6 +3638,120 @@ impl d::Device<B> for Device {
         // Just drop
     }
 
+    fn create_command_signature(
+        &self,
+        arguments: &[indirect::Argument],
+        stride: u32,
+        layout: Option<&n::PipelineLayout>,
+    ) -> Result<n::CommandSignature, indirect::CreationError> {
+        indirect::validate_sequence(arguments, stride, layout.is_some())?;
+
+        let mut descs = Vec::with_capacity(arguments.len());
+        let mut constants = Vec::new();
+        let mut vertex_buffers = false;
+
+        for (i, argument) in arguments.iter().enumerate() {
+            let ty = match *argument {
+                indirect::Argument::PushConstants { offset, count, .. } => {
+                    // Root constants are the first parameters of the root signature,
+                    // the range may span multiple of them but must be fully covered.
+                    let layout = layout.unwrap();
+                    let range = offset .. offset + count;
+                    let mut covered = range.start;
+                    for (index, root_constant) in layout.root_constants.iter().enumerate() {
+                        let start = range.start.max(root_constant.range.start);
+                        let end = range.end.min(root_constant.range.end);
+                        if start >= end {
+                            continue;
+                        }
+                        if start != covered {
+                            return Err(indirect::CreationError::UnsupportedArgument(i));
+                        }
+                        let mut desc = d3d12::D3D12_INDIRECT_ARGUMENT_DESC {
+                            Type: d3d12::D3D12_INDIRECT_ARGUMENT_TYPE_CONSTANT,
+                            .. unsafe { mem::zeroed() }
+                        };
+                        *unsafe { desc.u.Constant_mut() } = d3d12::D3D12_INDIRECT_ARGUMENT_DESC_Constant {
+                            RootParameterIndex: index as _,
+                            DestOffsetIn32BitValues: start - root_constant.range.start,
+                            Num32BitValuesToSet: end - start,
+                        };
+                        descs.push(desc);
+                        covered = end;
+                    }
+                    if covered != range.end {
+                        return Err(indirect::CreationError::UnsupportedArgument(i));
+                    }
+                    constants.push(range);
+                    continue;
+                }
+                indirect::Argument::VertexBuffer(slot) => {
+                    if slot as usize >= MAX_VERTEX_BUFFERS {
+                        return Err(indirect::CreationError::UnsupportedArgument(i));
+                    }
+                    let mut desc = d3d12::D3D12_INDIRECT_ARGUMENT_DESC {
+                        Type: d3d12::D3D12_INDIRECT_ARGUMENT_TYPE_VERTEX_BUFFER_VIEW,
+                        .. unsafe { mem::zeroed() }
+                    };
+                    *unsafe { desc.u.VertexBuffer_mut() } = d3d12::D3D12_INDIRECT_ARGUMENT_DESC_VertexBuffer {
+                        Slot: slot,
+                    };
+                    descs.push(desc);
+                    vertex_buffers = true;
+                    continue;
+                }
+                indirect::Argument::Draw => d3d12::D3D12_INDIRECT_ARGUMENT_TYPE_DRAW,
+                indirect::Argument::DrawIndexed => d3d12::D3D12_INDIRECT_ARGUMENT_TYPE_DRAW_INDEXED,
+                indirect::Argument::Dispatch => d3d12::D3D12_INDIRECT_ARGUMENT_TYPE_DISPATCH,
+            };
+            descs.push(d3d12::D3D12_INDIRECT_ARGUMENT_DESC {
+                Type: ty,
+                .. unsafe { mem::zeroed() }
+            });
+        }
+
+        let desc = d3d12::D3D12_COMMAND_SIGNATURE_DESC {
+            ByteStride: stride,
+            NumArgumentDescs: descs.len() as _,
+            pArgumentDescs: descs.as_ptr(),
+            NodeMask: 0,
+        };
+        // The root signature is only required if the sequences change root arguments.
+        let root_signature = match layout {
+            Some(layout) if !constants.is_empty() => layout.raw,
+            _ => ptr::null_mut(),
+        };
+
+        let mut signature = ptr::null_mut();
+        let hr = unsafe {
+            self.raw.clone().CreateCommandSignature(
+                &desc,
+                root_signature,
+                &d3d12::IID_ID3D12CommandSignature,
+                &mut signature as *mut *mut _ as *mut *mut _,
+            )
+        };
+
+        if hr == winerror::E_OUTOFMEMORY {
+            return Err(d::OutOfMemory.into());
+        } else if !winerror::SUCCEEDED(hr) {
+            error!("error on command signature creation: {:x}", hr);
+            return Err(indirect::CreationError::InvalidSequence);
+        }
+
+        Ok(n::CommandSignature {
+            raw: unsafe { ComPtr::from_raw(signature) },
+            stride,
+            dispatch: arguments.last() == Some(&indirect::Argument::Dispatch),
+            constants,
+            vertex_buffers,
+        })
+    }
+
+    fn destroy_command_signature(&self, _signature: n::CommandSignature) {
+        // Just drop
+    }
+
     fn destroy_shader_module(&self, shader_lib: n::ShaderModule) {
         if let n::ShaderModule::Compiled(shaders) = shader_lib {
             for (_, _blob) in shaders {
@@ -2456,10 +3776,25 @@ impl d::Device<B> for Device {
         unsafe { (*pipeline.raw).Release(); }
     }
 
+    fn destroy_ray_tracing_pipeline(&self, pipeline: n::RayTracingPipeline) {
+        unsafe {
+            (*pipeline.properties).Release();
+            (*pipeline.raw).Release();
+        }
+    }
+
+    fn destroy_acceleration_structure(&self, _accel: n::AccelerationStructure) {
+        // Just drop, the storage is owned by the buffer
+    }
+
     fn destroy_framebuffer(&self, _fb: n::Framebuffer) {
         // Just drop
     }
 
+    fn get_buffer_address(&self, buffer: &n::Buffer) -> u64 {
+        unsafe { (*buffer.resource).GetGPUVirtualAddress() }
+    }
+
     fn destroy_buffer(&self, buffer: n::Buffer) {
         unsafe { (*buffer.resource).Release(); }
     }
@@ -2503,7 +3838,7 @@ impl d::Device<B> for Device {
         &self,
         surface: &mut w::Surface,
         config: hal::SwapchainConfig,
-    ) -> (w::Swapchain, hal::Backbuffer<B>) {
+    ) -> Result<(w::Swapchain, hal::Backbuffer<B>), hal::window::CreationError> {
         let mut swap_chain: *mut dxgi1_2::IDXGISwapChain1 = ptr::null_mut();
 
         let format = match config.color_format {
@@ -2518,10 +3853,11 @@ impl d::Device<B> for Device {
             format => format,
         };
 
-        let format = conv::map_format(format).unwrap(); // TODO: error handling
+        let unsupported = hal::window::CreationError::UnsupportedFormat(config.color_format);
+        let format = conv::map_format(format).ok_or(unsupported.clone())?;
 
         let rtv_desc = d3d12::D3D12_RENDER_TARGET_VIEW_DESC {
-            Format: conv::map_format(config.color_format).unwrap(),
+            Format: conv::map_format(config.color_format).ok_or(unsupported)?,
             ViewDimension: d3d12::D3D12_RTV_DIMENSION_TEXTURE2D,
             .. unsafe { mem::zeroed() }
         };
@@ -2534,7 +3870,11 @@ impl d::Device<B> for Device {
 
         // TODO: double-check values
         let desc = dxgi1_2::DXGI_SWAP_CHAIN_DESC1 {
-            AlphaMode: dxgi1_2::DXGI_ALPHA_MODE_IGNORE,
+            AlphaMode: match surface.target {
+                w::SurfaceTarget::Window(_) => dxgi1_2::DXGI_ALPHA_MODE_IGNORE,
+                // Composition content is blended with the other visuals of the tree.
+                w::SurfaceTarget::Composition => dxgi1_2::DXGI_ALPHA_MODE_PREMULTIPLIED,
+            },
             BufferCount: config.image_count,
             Width: surface.width,
             Height: surface.height,
@@ -2550,20 +3890,42 @@ impl d::Device<B> for Device {
             SwapEffect: dxgi::DXGI_SWAP_EFFECT_FLIP_DISCARD,
         };
 
-        let hr = unsafe {
-            // TODO
-            surface.factory.CreateSwapChainForHwnd(
-                self.present_queue.as_raw() as *mut _,
-                surface.wnd_handle,
-                &desc,
-                ptr::null(),
-                ptr::null_mut(),
-                &mut swap_chain as *mut *mut _,
-            )
+        let present_queue = match self.present_queue {
+            Some(ref queue) => queue.as_raw(),
+            None => {
+                error!("Swapchains require an adapter with presentation support");
+                return Err(hal::window::CreationError::InitializationFailed);
+            }
+        };
+        let hr = match surface.target {
+            w::SurfaceTarget::Window(wnd_handle) => unsafe {
+                // TODO
+                surface.factory.CreateSwapChainForHwnd(
+                    present_queue as *mut _,
+                    wnd_handle,
+                    &desc,
+                    ptr::null(),
+                    ptr::null_mut(),
+                    &mut swap_chain as *mut *mut _,
+                )
+            },
+            w::SurfaceTarget::Composition => unsafe {
+                surface.factory.CreateSwapChainForComposition(
+                    present_queue as *mut _,
+                    &desc,
+                    ptr::null_mut(),
+                    &mut swap_chain as *mut *mut _,
+                )
+            },
         };
 
         if !winerror::SUCCEEDED(hr) {
             error!("error on swapchain creation 0x{:x}", hr);
+            return Err(if hr == winerror::E_OUTOFMEMORY {
+                hal::window::CreationError::OutOfMemory
+            } else {
+                hal::window::CreationError::InitializationFailed
+            });
         }
 
         let swap_chain = unsafe { ComPtr::<dxgi1_4::IDXGISwapChain3>::from_raw(swap_chain as _) };
@@ -2597,14 +3959,17 @@ impl d::Device<B> for Device {
                 resource,
                 kind,
                 usage: config.image_usage,
-                storage_flags: image::StorageFlags::empty(),
+                // Backbuffers are created without sRGB formats, but can be viewed as sRGB.
+                storage_flags: image::StorageFlags::MUTABLE_FORMAT,
                 dxgi_format: format,
+                surface_type: config.color_format.base_format().0,
                 bytes_per_block,
                 block_dim,
                 num_levels: 1,
                 clear_cv: Some(rtv_handle),
                 clear_dv: None,
                 clear_sv: None,
+                tracked: None,
             }
         }).collect();
 
@@ -2613,9 +3978,10 @@ impl d::Device<B> for Device {
             next_frame: 0,
             frame_queue: VecDeque::new(),
             rtv_heap,
+            last_sync: Mutex::new(None),
         };
 
-        (swapchain, hal::Backbuffer::Images(images))
+        Ok((swapchain, hal::Backbuffer::Images(images)))
     }
 
     fn destroy_swapchain(&self, _swapchain: w::Swapchain) {
@@ -0,0 +1,174 @@
+//! Device removal diagnostics with DRED (Device Removed Extended Data).
+//!
+//! DRED has to be configured before the device is created. Page fault reporting
+//! is enabled together with the debug layer, auto-breadcrumbs are opt-in as the
+//! runtime tags every recorded command with a marker, which adds CPU overhead.
+
+use std::ffi::{CStr, OsString};
+use std::os::windows::ffi::OsStringExt;
+use std::{mem, ptr, slice};
+
+use winapi::Interface;
+use winapi::ctypes::c_char;
+use winapi::shared::winerror;
+use winapi::um::{d3d12, winnt};
+use wio::com::ComPtr;
+
+use hal::error;
+use ext;
+
+// Number of completed command markers reported per command list.
+const MAX_COMPLETED_MARKERS: usize = 8;
+
+/// Configure DRED for devices created afterwards.
+///
+/// Returns `false` if DRED isn't supported by the runtime.
+pub(crate) fn configure(auto_breadcrumbs: bool, page_faults: bool) -> bool {
+    let mut settings: *mut ext::ID3D12DeviceRemovedExtendedDataSettings = ptr::null_mut();
+    let hr = unsafe {
+        d3d12::D3D12GetDebugInterface(
+            &ext::ID3D12DeviceRemovedExtendedDataSettings::uuidof(),
+            &mut settings as *mut *mut _ as *mut *mut _,
+        )
+    };
+    if !winerror::SUCCEEDED(hr) {
+        return false;
+    }
+
+    let enablement = |enable| if enable {
+        ext::D3D12_DRED_ENABLEMENT_FORCED_ON
+    } else {
+        ext::D3D12_DRED_ENABLEMENT_SYSTEM_CONTROLLED
+    };
+    let settings = unsafe { ComPtr::from_raw(settings) };
+    unsafe {
+        settings.SetAutoBreadcrumbsEnablement(enablement(auto_breadcrumbs));
+        settings.SetPageFaultEnablement(enablement(page_faults));
+    }
+    true
+}
+
+/// Check if the device has been removed, collecting the DRED output if so.
+pub(crate) fn check_device(device: &ComPtr<d3d12::ID3D12Device>) -> Result<(), error::DeviceLost> {
+    let reason = unsafe { device.GetDeviceRemovedReason() };
+    if winerror::SUCCEEDED(reason) {
+        return Ok(());
+    }
+
+    error!("Device removed: {:x}", reason);
+    let dred = match device.cast::<ext::ID3D12DeviceRemovedExtendedData>() {
+        Ok(dred) => dred,
+        Err(_) => return Err(error::DeviceLost::default()),
+    };
+
+    let mut lost = error::DeviceLost::default();
+
+    let mut breadcrumbs = unsafe { mem::zeroed::<ext::D3D12_DRED_AUTO_BREADCRUMBS_OUTPUT>() };
+    if winerror::SUCCEEDED(unsafe { dred.GetAutoBreadcrumbsOutput(&mut breadcrumbs) }) {
+        let mut node = breadcrumbs.pHeadAutoBreadcrumbNode;
+        while let Some(n) = unsafe { node.as_ref() } {
+            if let Some(trail) = unsafe { breadcrumb_trail(n) } {
+                lost.breadcrumbs.push(trail);
+            }
+            node = n.pNext;
+        }
+    }
+
+    let mut page_fault = unsafe { mem::zeroed::<ext::D3D12_DRED_PAGE_FAULT_OUTPUT>() };
+    if winerror::SUCCEEDED(unsafe { dred.GetPageFaultAllocationOutput(&mut page_fault) }) &&
+        page_fault.PageFaultVA != 0
+    {
+        lost.page_fault = Some(error::PageFault {
+            address: page_fault.PageFaultVA,
+            allocations: unsafe { allocation_names(page_fault.pHeadExistingAllocationNode) },
+            freed_allocations: unsafe { allocation_names(page_fault.pHeadRecentFreedAllocationNode) },
+        });
+    }
+
+    for trail in &lost.breadcrumbs {
+        error!("\tCommand list {:?}: completed {:?}, pending {:?}",
+            trail.command_buffer, trail.completed, trail.pending);
+    }
+    if let Some(ref fault) = lost.page_fault {
+        error!("\tPage fault at {:x}: allocated {:?}, freed {:?}",
+            fault.address, fault.allocations, fault.freed_allocations);
+    }
+
+    Err(lost)
+}
+
+// Markers of a command list which didn't finish execution.
+unsafe fn breadcrumb_trail(node: &ext::D3D12_AUTO_BREADCRUMB_NODE) -> Option<error::Breadcrumbs> {
+    let count = node.BreadcrumbCount as usize;
+    let completed = match node.pLastBreadcrumbValue.as_ref() {
+        Some(&value) => value as usize,
+        None => return None,
+    };
+    if count == 0 || completed >= count || node.pCommandHistory.is_null() {
+        return None;
+    }
+
+    let history = slice::from_raw_parts(node.pCommandHistory, count);
+    let first = completed.saturating_sub(MAX_COMPLETED_MARKERS);
+    Some(error::Breadcrumbs {
+        command_buffer: debug_name(node.pCommandListDebugNameA, node.pCommandListDebugNameW),
+        completed: history[first .. completed].iter().map(|&op| op_name(op)).collect(),
+        pending: Some(op_name(history[completed])),
+    })
+}
+
+unsafe fn allocation_names(mut node: *const ext::D3D12_DRED_ALLOCATION_NODE) -> Vec<String> {
+    let mut names = Vec::new();
+    while let Some(n) = node.as_ref() {
+        names.push(debug_name(n.ObjectNameA, n.ObjectNameW).unwrap_or_else(|| "<unnamed>".to_owned()));
+        node = n.pNext;
+    }
+    names
+}
+
+unsafe fn debug_name(name_a: *const c_char, name_w: *const winnt::WCHAR) -> Option<String> {
+    if !name_w.is_null() {
+        let len = (0 ..).take_while(|&i| *name_w.offset(i) != 0).count();
+        let name = slice::from_raw_parts(name_w, len);
+        Some(OsString::from_wide(name).to_string_lossy().into_owned())
+    } else if !name_a.is_null() {
+        Some(CStr::from_ptr(name_a).to_string_lossy().into_owned())
+    } else {
+        None
+    }
+}
+
+// `D3D12_AUTO_BREADCRUMB_OP` values, named after the command list methods.
+fn op_name(op: ext::D3D12_AUTO_BREADCRUMB_OP) -> String {
+    let name = match op {
+        0 => "SetMarker",
+        1 => "BeginEvent",
+        2 => "EndEvent",
+        3 => "DrawInstanced",
+        4 => "DrawIndexedInstanced",
+        5 => "ExecuteIndirect",
+        6 => "Dispatch",
+        7 => "CopyBufferRegion",
+        8 => "CopyTextureRegion",
+        9 => "CopyResource",
+        10 => "CopyTiles",
+        11 => "ResolveSubresource",
+        12 => "ClearRenderTargetView",
+        13 => "ClearUnorderedAccessView",
+        14 => "ClearDepthStencilView",
+        15 => "ResourceBarrier",
+        16 => "ExecuteBundle",
+        17 => "Present",
+        18 => "ResolveQueryData",
+        19 => "BeginSubmission",
+        20 => "EndSubmission",
+        25 => "ResolveSubresourceRegion",
+        26 => "WriteBufferImmediate",
+        31 => "BuildRaytracingAccelerationStructure",
+        32 => "EmitRaytracingAccelerationStructurePostbuildInfo",
+        33 => "CopyRaytracingAccelerationStructure",
+        34 => "DispatchRays",
+        _ => return format!("Unknown({})", op),
+    };
+    name.to_owned()
+}
@@ -0,0 +1,689 @@
+//! Bindings for D3D12 interfaces and structures which are not exposed by `winapi` yet.
+//!
+//! The interfaces are declared with their complete vtables, including the methods
+//! we don't make use of, as they are only accessible via `QueryInterface`.
+
+#![allow(non_camel_case_types, non_snake_case, dead_code)]
+
+use winapi::ctypes::{c_char, c_void};
+use winapi::shared::basetsd::{SIZE_T, UINT64, UINT8};
+use winapi::shared::dxgiformat::DXGI_FORMAT;
+use winapi::shared::guiddef::{REFGUID, REFIID};
+use winapi::shared::minwindef::{BOOL, FLOAT, UINT};
+use winapi::shared::windef::RECT;
+use winapi::um::d3d12::*;
+use winapi::um::unknwnbase::{IUnknown, IUnknownVtbl};
+use winapi::um::winnt::{HANDLE, HRESULT, LPCWSTR, WCHAR};
+
+pub const D3D12_FEATURE_D3D12_OPTIONS1: D3D12_FEATURE = 8;
+pub const D3D12_FEATURE_D3D12_OPTIONS2: D3D12_FEATURE = 18;
+pub const D3D12_FEATURE_D3D12_OPTIONS3: D3D12_FEATURE = 21;
+pub const D3D12_FEATURE_D3D12_OPTIONS5: D3D12_FEATURE = 27;
+
+pub type D3D12_PROGRAMMABLE_SAMPLE_POSITIONS_TIER = UINT;
+
+STRUCT!{struct D3D12_FEATURE_DATA_D3D12_OPTIONS1 {
+    WaveOps: BOOL,
+    WaveLaneCountMin: UINT,
+    WaveLaneCountMax: UINT,
+    TotalLaneCount: UINT,
+    ExpandedComputeResourceStates: BOOL,
+    Int64ShaderOps: BOOL,
+}}
+
+STRUCT!{struct D3D12_FEATURE_DATA_D3D12_OPTIONS2 {
+    DepthBoundsTestSupported: BOOL,
+    ProgrammableSamplePositionsTier: D3D12_PROGRAMMABLE_SAMPLE_POSITIONS_TIER,
+}}
+
+pub type D3D12_COMMAND_LIST_SUPPORT_FLAGS = UINT;
+pub type D3D12_VIEW_INSTANCING_TIER = UINT;
+pub const D3D12_VIEW_INSTANCING_TIER_NOT_SUPPORTED: D3D12_VIEW_INSTANCING_TIER = 0;
+
+STRUCT!{struct D3D12_FEATURE_DATA_D3D12_OPTIONS3 {
+    CopyQueueTimestampQueriesSupported: BOOL,
+    CastingFullyTypedFormatSupported: BOOL,
+    WriteBufferImmediateSupportFlags: D3D12_COMMAND_LIST_SUPPORT_FLAGS,
+    ViewInstancingTier: D3D12_VIEW_INSTANCING_TIER,
+    BarycentricsSupported: BOOL,
+}}
+
+pub const D3D12_MAX_VIEW_INSTANCE_COUNT: UINT = 4;
+
+pub type D3D12_VIEW_INSTANCING_FLAGS = UINT;
+pub const D3D12_VIEW_INSTANCING_FLAG_NONE: D3D12_VIEW_INSTANCING_FLAGS = 0;
+pub const D3D12_VIEW_INSTANCING_FLAG_ENABLE_VIEW_INSTANCE_MASKING: D3D12_VIEW_INSTANCING_FLAGS = 0x1;
+
+STRUCT!{struct D3D12_VIEW_INSTANCE_LOCATION {
+    ViewportArrayIndex: UINT,
+    RenderTargetArrayIndex: UINT,
+}}
+
+STRUCT!{struct D3D12_VIEW_INSTANCING_DESC {
+    ViewInstanceCount: UINT,
+    pViewInstanceLocations: *const D3D12_VIEW_INSTANCE_LOCATION,
+    Flags: D3D12_VIEW_INSTANCING_FLAGS,
+}}
+
+pub type D3D12_RENDER_PASS_TIER = UINT;
+pub const D3D12_RENDER_PASS_TIER_0: D3D12_RENDER_PASS_TIER = 0;
+pub const D3D12_RENDER_PASS_TIER_1: D3D12_RENDER_PASS_TIER = 1;
+pub const D3D12_RENDER_PASS_TIER_2: D3D12_RENDER_PASS_TIER = 2;
+pub type D3D12_RAYTRACING_TIER = UINT;
+pub const D3D12_RAYTRACING_TIER_NOT_SUPPORTED: D3D12_RAYTRACING_TIER = 0;
+pub const D3D12_RAYTRACING_TIER_1_0: D3D12_RAYTRACING_TIER = 10;
+
+STRUCT!{struct D3D12_FEATURE_DATA_D3D12_OPTIONS5 {
+    SRVOnlyTiledResourceTier3: BOOL,
+    RenderPassesTier: D3D12_RENDER_PASS_TIER,
+    RaytracingTier: D3D12_RAYTRACING_TIER,
+}}
+
+pub type D3D12_RESIDENCY_PRIORITY = UINT;
+pub const D3D12_RESIDENCY_PRIORITY_MINIMUM: D3D12_RESIDENCY_PRIORITY = 0x2800_0000;
+pub const D3D12_RESIDENCY_PRIORITY_LOW: D3D12_RESIDENCY_PRIORITY = 0x5000_0000;
+pub const D3D12_RESIDENCY_PRIORITY_NORMAL: D3D12_RESIDENCY_PRIORITY = 0x7800_0000;
+pub const D3D12_RESIDENCY_PRIORITY_HIGH: D3D12_RESIDENCY_PRIORITY = 0xa001_0000;
+pub const D3D12_RESIDENCY_PRIORITY_MAXIMUM: D3D12_RESIDENCY_PRIORITY = 0xc800_0000;
+
+pub type D3D12_PIPELINE_STATE_SUBOBJECT_TYPE = UINT;
+pub const D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_ROOT_SIGNATURE: D3D12_PIPELINE_STATE_SUBOBJECT_TYPE = 0;
+pub const D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_VS: D3D12_PIPELINE_STATE_SUBOBJECT_TYPE = 1;
+pub const D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_PS: D3D12_PIPELINE_STATE_SUBOBJECT_TYPE = 2;
+pub const D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_DS: D3D12_PIPELINE_STATE_SUBOBJECT_TYPE = 3;
+pub const D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_HS: D3D12_PIPELINE_STATE_SUBOBJECT_TYPE = 4;
+pub const D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_GS: D3D12_PIPELINE_STATE_SUBOBJECT_TYPE = 5;
+pub const D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_CS: D3D12_PIPELINE_STATE_SUBOBJECT_TYPE = 6;
+pub const D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_STREAM_OUTPUT: D3D12_PIPELINE_STATE_SUBOBJECT_TYPE = 7;
+pub const D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_BLEND: D3D12_PIPELINE_STATE_SUBOBJECT_TYPE = 8;
+pub const D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_SAMPLE_MASK: D3D12_PIPELINE_STATE_SUBOBJECT_TYPE = 9;
+pub const D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_RASTERIZER: D3D12_PIPELINE_STATE_SUBOBJECT_TYPE = 10;
+pub const D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_DEPTH_STENCIL: D3D12_PIPELINE_STATE_SUBOBJECT_TYPE = 11;
+pub const D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_INPUT_LAYOUT: D3D12_PIPELINE_STATE_SUBOBJECT_TYPE = 12;
+pub const D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_IB_STRIP_CUT_VALUE: D3D12_PIPELINE_STATE_SUBOBJECT_TYPE = 13;
+pub const D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_PRIMITIVE_TOPOLOGY: D3D12_PIPELINE_STATE_SUBOBJECT_TYPE = 14;
+pub const D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_RENDER_TARGET_FORMATS: D3D12_PIPELINE_STATE_SUBOBJECT_TYPE = 15;
+pub const D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_DEPTH_STENCIL_FORMAT: D3D12_PIPELINE_STATE_SUBOBJECT_TYPE = 16;
+pub const D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_SAMPLE_DESC: D3D12_PIPELINE_STATE_SUBOBJECT_TYPE = 17;
+pub const D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_NODE_MASK: D3D12_PIPELINE_STATE_SUBOBJECT_TYPE = 18;
+pub const D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_CACHED_PSO: D3D12_PIPELINE_STATE_SUBOBJECT_TYPE = 19;
+pub const D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_FLAGS: D3D12_PIPELINE_STATE_SUBOBJECT_TYPE = 20;
+pub const D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_DEPTH_STENCIL1: D3D12_PIPELINE_STATE_SUBOBJECT_TYPE = 21;
+pub const D3D12_PIPELINE_STATE_SUBOBJECT_TYPE_VIEW_INSTANCING: D3D12_PIPELINE_STATE_SUBOBJECT_TYPE = 22;
+
+STRUCT!{struct D3D12_PIPELINE_STATE_STREAM_DESC {
+    SizeInBytes: SIZE_T,
+    pPipelineStateSubobjectStream: *mut c_void,
+}}
+
+STRUCT!{struct D3D12_RT_FORMAT_ARRAY {
+    RTFormats: [DXGI_FORMAT; 8],
+    NumRenderTargets: UINT,
+}}
+
+STRUCT!{struct D3D12_DEPTH_STENCIL_DESC1 {
+    DepthEnable: BOOL,
+    DepthWriteMask: D3D12_DEPTH_WRITE_MASK,
+    DepthFunc: D3D12_COMPARISON_FUNC,
+    StencilEnable: BOOL,
+    StencilReadMask: UINT8,
+    StencilWriteMask: UINT8,
+    FrontFace: D3D12_DEPTH_STENCILOP_DESC,
+    BackFace: D3D12_DEPTH_STENCILOP_DESC,
+    DepthBoundsTestEnable: BOOL,
+}}
+
+pub const D3D12_RESOURCE_STATE_RAYTRACING_ACCELERATION_STRUCTURE: D3D12_RESOURCE_STATES = 0x40_0000;
+pub const D3D12_SRV_DIMENSION_RAYTRACING_ACCELERATION_STRUCTURE: D3D12_SRV_DIMENSION = 11;
+
+pub const D3D12_SHADER_IDENTIFIER_SIZE_IN_BYTES: UINT = 32;
+pub const D3D12_RAYTRACING_SHADER_TABLE_BYTE_ALIGNMENT: UINT = 64;
+pub const D3D12_RAYTRACING_SHADER_RECORD_BYTE_ALIGNMENT: UINT = 32;
+pub const D3D12_RAYTRACING_ACCELERATION_STRUCTURE_BYTE_ALIGNMENT: UINT = 256;
+pub const D3D12_RAYTRACING_MAX_DECLARABLE_TRACE_RECURSION_DEPTH: UINT = 31;
+
+pub type D3D12_RAYTRACING_ACCELERATION_STRUCTURE_TYPE = UINT;
+pub const D3D12_RAYTRACING_ACCELERATION_STRUCTURE_TYPE_TOP_LEVEL: D3D12_RAYTRACING_ACCELERATION_STRUCTURE_TYPE = 0;
+pub const D3D12_RAYTRACING_ACCELERATION_STRUCTURE_TYPE_BOTTOM_LEVEL: D3D12_RAYTRACING_ACCELERATION_STRUCTURE_TYPE = 1;
+
+pub type D3D12_RAYTRACING_ACCELERATION_STRUCTURE_BUILD_FLAGS = UINT;
+pub type D3D12_ELEMENTS_LAYOUT = UINT;
+pub const D3D12_ELEMENTS_LAYOUT_ARRAY: D3D12_ELEMENTS_LAYOUT = 0;
+pub const D3D12_ELEMENTS_LAYOUT_ARRAY_OF_POINTERS: D3D12_ELEMENTS_LAYOUT = 1;
+
+pub type D3D12_RAYTRACING_GEOMETRY_TYPE = UINT;
+pub const D3D12_RAYTRACING_GEOMETRY_TYPE_TRIANGLES: D3D12_RAYTRACING_GEOMETRY_TYPE = 0;
+pub const D3D12_RAYTRACING_GEOMETRY_TYPE_PROCEDURAL_PRIMITIVE_AABBS: D3D12_RAYTRACING_GEOMETRY_TYPE = 1;
+pub type D3D12_RAYTRACING_GEOMETRY_FLAGS = UINT;
+
+STRUCT!{struct D3D12_GPU_VIRTUAL_ADDRESS_AND_STRIDE {
+    StartAddress: D3D12_GPU_VIRTUAL_ADDRESS,
+    StrideInBytes: UINT64,
+}}
+
+STRUCT!{struct D3D12_GPU_VIRTUAL_ADDRESS_RANGE {
+    StartAddress: D3D12_GPU_VIRTUAL_ADDRESS,
+    SizeInBytes: UINT64,
+}}
+
+STRUCT!{struct D3D12_GPU_VIRTUAL_ADDRESS_RANGE_AND_STRIDE {
+    StartAddress: D3D12_GPU_VIRTUAL_ADDRESS,
+    SizeInBytes: UINT64,
+    StrideInBytes: UINT64,
+}}
+
+STRUCT!{struct D3D12_RAYTRACING_GEOMETRY_TRIANGLES_DESC {
+    Transform3x4: D3D12_GPU_VIRTUAL_ADDRESS,
+    IndexFormat: DXGI_FORMAT,
+    VertexFormat: DXGI_FORMAT,
+    IndexCount: UINT,
+    VertexCount: UINT,
+    IndexBuffer: D3D12_GPU_VIRTUAL_ADDRESS,
+    VertexBuffer: D3D12_GPU_VIRTUAL_ADDRESS_AND_STRIDE,
+}}
+
+STRUCT!{struct D3D12_RAYTRACING_GEOMETRY_AABBS_DESC {
+    AABBCount: UINT64,
+    AABBs: D3D12_GPU_VIRTUAL_ADDRESS_AND_STRIDE,
+}}
+
+UNION!{union D3D12_RAYTRACING_GEOMETRY_DESC_u {
+    [u64; 6],
+    Triangles Triangles_mut: D3D12_RAYTRACING_GEOMETRY_TRIANGLES_DESC,
+    AABBs AABBs_mut: D3D12_RAYTRACING_GEOMETRY_AABBS_DESC,
+}}
+
+STRUCT!{struct D3D12_RAYTRACING_GEOMETRY_DESC {
+    Type: D3D12_RAYTRACING_GEOMETRY_TYPE,
+    Flags: D3D12_RAYTRACING_GEOMETRY_FLAGS,
+    u: D3D12_RAYTRACING_GEOMETRY_DESC_u,
+}}
+
+UNION!{union D3D12_BUILD_RAYTRACING_ACCELERATION_STRUCTURE_INPUTS_u {
+    [u64; 1],
+    InstanceDescs InstanceDescs_mut: D3D12_GPU_VIRTUAL_ADDRESS,
+    pGeometryDescs pGeometryDescs_mut: *const D3D12_RAYTRACING_GEOMETRY_DESC,
+    ppGeometryDescs ppGeometryDescs_mut: *const *const D3D12_RAYTRACING_GEOMETRY_DESC,
+}}
+
+STRUCT!{struct D3D12_BUILD_RAYTRACING_ACCELERATION_STRUCTURE_INPUTS {
+    Type: D3D12_RAYTRACING_ACCELERATION_STRUCTURE_TYPE,
+    Flags: D3D12_RAYTRACING_ACCELERATION_STRUCTURE_BUILD_FLAGS,
+    NumDescs: UINT,
+    DescsLayout: D3D12_ELEMENTS_LAYOUT,
+    u: D3D12_BUILD_RAYTRACING_ACCELERATION_STRUCTURE_INPUTS_u,
+}}
+
+STRUCT!{struct D3D12_BUILD_RAYTRACING_ACCELERATION_STRUCTURE_DESC {
+    DestAccelerationStructureData: D3D12_GPU_VIRTUAL_ADDRESS,
+    Inputs: D3D12_BUILD_RAYTRACING_ACCELERATION_STRUCTURE_INPUTS,
+    SourceAccelerationStructureData: D3D12_GPU_VIRTUAL_ADDRESS,
+    ScratchAccelerationStructureData: D3D12_GPU_VIRTUAL_ADDRESS,
+}}
+
+STRUCT!{struct D3D12_RAYTRACING_ACCELERATION_STRUCTURE_PREBUILD_INFO {
+    ResultDataMaxSizeInBytes: UINT64,
+    ScratchDataSizeInBytes: UINT64,
+    UpdateScratchDataSizeInBytes: UINT64,
+}}
+
+STRUCT!{struct D3D12_DISPATCH_RAYS_DESC {
+    RayGenerationShaderRecord: D3D12_GPU_VIRTUAL_ADDRESS_RANGE,
+    MissShaderTable: D3D12_GPU_VIRTUAL_ADDRESS_RANGE_AND_STRIDE,
+    HitGroupTable: D3D12_GPU_VIRTUAL_ADDRESS_RANGE_AND_STRIDE,
+    CallableShaderTable: D3D12_GPU_VIRTUAL_ADDRESS_RANGE_AND_STRIDE,
+    Width: UINT,
+    Height: UINT,
+    Depth: UINT,
+}}
+
+pub type D3D12_STATE_OBJECT_TYPE = UINT;
+pub const D3D12_STATE_OBJECT_TYPE_COLLECTION: D3D12_STATE_OBJECT_TYPE = 0;
+pub const D3D12_STATE_OBJECT_TYPE_RAYTRACING_PIPELINE: D3D12_STATE_OBJECT_TYPE = 3;
+
+pub type D3D12_STATE_SUBOBJECT_TYPE = UINT;
+pub const D3D12_STATE_SUBOBJECT_TYPE_STATE_OBJECT_CONFIG: D3D12_STATE_SUBOBJECT_TYPE = 0;
+pub const D3D12_STATE_SUBOBJECT_TYPE_GLOBAL_ROOT_SIGNATURE: D3D12_STATE_SUBOBJECT_TYPE = 1;
+pub const D3D12_STATE_SUBOBJECT_TYPE_LOCAL_ROOT_SIGNATURE: D3D12_STATE_SUBOBJECT_TYPE = 2;
+pub const D3D12_STATE_SUBOBJECT_TYPE_NODE_MASK: D3D12_STATE_SUBOBJECT_TYPE = 3;
+pub const D3D12_STATE_SUBOBJECT_TYPE_DXIL_LIBRARY: D3D12_STATE_SUBOBJECT_TYPE = 5;
+pub const D3D12_STATE_SUBOBJECT_TYPE_EXISTING_COLLECTION: D3D12_STATE_SUBOBJECT_TYPE = 6;
+pub const D3D12_STATE_SUBOBJECT_TYPE_SUBOBJECT_TO_EXPORTS_ASSOCIATION: D3D12_STATE_SUBOBJECT_TYPE = 7;
+pub const D3D12_STATE_SUBOBJECT_TYPE_DXIL_SUBOBJECT_TO_EXPORTS_ASSOCIATION: D3D12_STATE_SUBOBJECT_TYPE = 8;
+pub const D3D12_STATE_SUBOBJECT_TYPE_RAYTRACING_SHADER_CONFIG: D3D12_STATE_SUBOBJECT_TYPE = 9;
+pub const D3D12_STATE_SUBOBJECT_TYPE_RAYTRACING_PIPELINE_CONFIG: D3D12_STATE_SUBOBJECT_TYPE = 10;
+pub const D3D12_STATE_SUBOBJECT_TYPE_HIT_GROUP: D3D12_STATE_SUBOBJECT_TYPE = 11;
+
+STRUCT!{struct D3D12_STATE_SUBOBJECT {
+    Type: D3D12_STATE_SUBOBJECT_TYPE,
+    pDesc: *const c_void,
+}}
+
+STRUCT!{struct D3D12_STATE_OBJECT_DESC {
+    Type: D3D12_STATE_OBJECT_TYPE,
+    NumSubobjects: UINT,
+    pSubobjects: *const D3D12_STATE_SUBOBJECT,
+}}
+
+STRUCT!{struct D3D12_GLOBAL_ROOT_SIGNATURE {
+    pGlobalRootSignature: *mut ID3D12RootSignature,
+}}
+
+STRUCT!{struct D3D12_EXPORT_DESC {
+    Name: LPCWSTR,
+    ExportToRename: LPCWSTR,
+    Flags: UINT,
+}}
+
+STRUCT!{struct D3D12_DXIL_LIBRARY_DESC {
+    DXILLibrary: D3D12_SHADER_BYTECODE,
+    NumExports: UINT,
+    pExports: *const D3D12_EXPORT_DESC,
+}}
+
+pub type D3D12_HIT_GROUP_TYPE = UINT;
+pub const D3D12_HIT_GROUP_TYPE_TRIANGLES: D3D12_HIT_GROUP_TYPE = 0;
+pub const D3D12_HIT_GROUP_TYPE_PROCEDURAL_PRIMITIVE: D3D12_HIT_GROUP_TYPE = 1;
+
+STRUCT!{struct D3D12_HIT_GROUP_DESC {
+    HitGroupExport: LPCWSTR,
+    Type: D3D12_HIT_GROUP_TYPE,
+    AnyHitShaderImport: LPCWSTR,
+    ClosestHitShaderImport: LPCWSTR,
+    IntersectionShaderImport: LPCWSTR,
+}}
+
+STRUCT!{struct D3D12_RAYTRACING_SHADER_CONFIG {
+    MaxPayloadSizeInBytes: UINT,
+    MaxAttributeSizeInBytes: UINT,
+}}
+
+STRUCT!{struct D3D12_RAYTRACING_PIPELINE_CONFIG {
+    MaxTraceRecursionDepth: UINT,
+}}
+
+RIDL!{#[uuid(0x77acce80, 0x638e, 0x4e65, 0x88, 0x95, 0xc1, 0xf2, 0x33, 0x86, 0x86, 0x3e)]
+interface ID3D12Device1(ID3D12Device1Vtbl): ID3D12Device(ID3D12DeviceVtbl) {
+    fn CreatePipelineLibrary(
+        pLibraryBlob: *const c_void,
+        BlobLength: SIZE_T,
+        riid: REFIID,
+        ppPipelineLibrary: *mut *mut c_void,
+    ) -> HRESULT,
+    fn SetEventOnMultipleFenceCompletion(
+        ppFences: *const *mut ID3D12Fence,
+        pFenceValues: *const UINT64,
+        NumFences: UINT,
+        Flags: UINT,
+        hEvent: HANDLE,
+    ) -> HRESULT,
+    fn SetResidencyPriority(
+        NumObjects: UINT,
+        ppObjects: *const *mut ID3D12Pageable,
+        pPriorities: *const UINT,
+    ) -> HRESULT,
+}}
+
+RIDL!{#[uuid(0x30baa41e, 0xb15b, 0x475c, 0xa0, 0xbb, 0x1a, 0xf5, 0xc5, 0xb6, 0x43, 0x28)]
+interface ID3D12Device2(ID3D12Device2Vtbl): ID3D12Device1(ID3D12Device1Vtbl) {
+    fn CreatePipelineState(
+        pDesc: *const D3D12_PIPELINE_STATE_STREAM_DESC,
+        riid: REFIID,
+        ppPipelineState: *mut *mut c_void,
+    ) -> HRESULT,
+}}
+
+RIDL!{#[uuid(0x81dadc15, 0x2bad, 0x4392, 0x93, 0xc5, 0x10, 0x13, 0x45, 0xc4, 0xaa, 0x98)]
+interface ID3D12Device3(ID3D12Device3Vtbl): ID3D12Device2(ID3D12Device2Vtbl) {
+    fn OpenExistingHeapFromAddress(
+        pAddress: *const c_void,
+        riid: REFIID,
+        ppvHeap: *mut *mut c_void,
+    ) -> HRESULT,
+    fn OpenExistingHeapFromFileMapping(
+        hFileMapping: HANDLE,
+        riid: REFIID,
+        ppvHeap: *mut *mut c_void,
+    ) -> HRESULT,
+    fn EnqueueMakeResident(
+        Flags: UINT,
+        NumObjects: UINT,
+        ppObjects: *const *mut ID3D12Pageable,
+        pFenceToSignal: *mut ID3D12Fence,
+        FenceValueToSignal: UINT64,
+    ) -> HRESULT,
+}}
+
+RIDL!{#[uuid(0xe865df17, 0xa9ee, 0x46f9, 0xa4, 0x63, 0x30, 0x98, 0x31, 0x5a, 0xa2, 0xe5)]
+interface ID3D12Device4(ID3D12Device4Vtbl): ID3D12Device3(ID3D12Device3Vtbl) {
+    fn CreateCommandList1(
+        nodeMask: UINT,
+        type_: D3D12_COMMAND_LIST_TYPE,
+        flags: UINT,
+        riid: REFIID,
+        ppCommandList: *mut *mut c_void,
+    ) -> HRESULT,
+    fn CreateProtectedResourceSession(
+        pDesc: *const c_void,
+        riid: REFIID,
+        ppSession: *mut *mut c_void,
+    ) -> HRESULT,
+    fn CreateCommittedResource1(
+        pHeapProperties: *const D3D12_HEAP_PROPERTIES,
+        HeapFlags: D3D12_HEAP_FLAGS,
+        pDesc: *const D3D12_RESOURCE_DESC,
+        InitialResourceState: D3D12_RESOURCE_STATES,
+        pOptimizedClearValue: *const D3D12_CLEAR_VALUE,
+        pProtectedSession: *mut IUnknown,
+        riidResource: REFIID,
+        ppvResource: *mut *mut c_void,
+    ) -> HRESULT,
+    fn CreateHeap1(
+        pDesc: *const D3D12_HEAP_DESC,
+        pProtectedSession: *mut IUnknown,
+        riid: REFIID,
+        ppvHeap: *mut *mut c_void,
+    ) -> HRESULT,
+    fn CreateReservedResource1(
+        pDesc: *const D3D12_RESOURCE_DESC,
+        InitialState: D3D12_RESOURCE_STATES,
+        pOptimizedClearValue: *const D3D12_CLEAR_VALUE,
+        pProtectedSession: *mut IUnknown,
+        riid: REFIID,
+        ppvResource: *mut *mut c_void,
+    ) -> HRESULT,
+    // Returns `D3D12_RESOURCE_ALLOCATION_INFO` by value, only declared for the vtable layout.
+    fn GetResourceAllocationInfo1(
+        visibleMask: UINT,
+        numResourceDescs: UINT,
+        pResourceDescs: *const D3D12_RESOURCE_DESC,
+        pResourceAllocationInfo1: *mut c_void,
+    ) -> (),
+}}
+
+RIDL!{#[uuid(0x8b4f173b, 0x2fea, 0x4b80, 0x8f, 0x58, 0x43, 0x07, 0x19, 0x1a, 0xb9, 0x5d)]
+interface ID3D12Device5(ID3D12Device5Vtbl): ID3D12Device4(ID3D12Device4Vtbl) {
+    fn CreateLifetimeTracker(
+        pOwner: *mut IUnknown,
+        riid: REFIID,
+        ppvTracker: *mut *mut c_void,
+    ) -> HRESULT,
+    fn RemoveDevice() -> (),
+    fn EnumerateMetaCommands(
+        pNumMetaCommands: *mut UINT,
+        pDescs: *mut c_void,
+    ) -> HRESULT,
+    fn EnumerateMetaCommandParameters(
+        CommandId: REFGUID,
+        Stage: UINT,
+        pTotalStructureSizeInBytes: *mut UINT,
+        pParameterCount: *mut UINT,
+        pParameterDescs: *mut c_void,
+    ) -> HRESULT,
+    fn CreateMetaCommand(
+        CommandId: REFGUID,
+        NodeMask: UINT,
+        pCreationParametersData: *const c_void,
+        CreationParametersDataSizeInBytes: SIZE_T,
+        riid: REFIID,
+        ppMetaCommand: *mut *mut c_void,
+    ) -> HRESULT,
+    fn CreateStateObject(
+        pDesc: *const D3D12_STATE_OBJECT_DESC,
+        riid: REFIID,
+        ppStateObject: *mut *mut c_void,
+    ) -> HRESULT,
+    fn GetRaytracingAccelerationStructurePrebuildInfo(
+        pDesc: *const D3D12_BUILD_RAYTRACING_ACCELERATION_STRUCTURE_INPUTS,
+        pInfo: *mut D3D12_RAYTRACING_ACCELERATION_STRUCTURE_PREBUILD_INFO,
+    ) -> (),
+    fn CheckDriverMatchingIdentifier(
+        SerializedDataType: UINT,
+        pIdentifierToCheck: *const c_void,
+    ) -> UINT,
+}}
+
+RIDL!{#[uuid(0x553103fb, 0x1fe7, 0x4557, 0xbb, 0x38, 0x94, 0x6d, 0x7d, 0x0e, 0x7c, 0xa7)]
+interface ID3D12GraphicsCommandList1(ID3D12GraphicsCommandList1Vtbl):
+    ID3D12GraphicsCommandList(ID3D12GraphicsCommandListVtbl) {
+    fn AtomicCopyBufferUINT(
+        pDstBuffer: *mut ID3D12Resource,
+        DstOffset: UINT64,
+        pSrcBuffer: *mut ID3D12Resource,
+        SrcOffset: UINT64,
+        Dependencies: UINT,
+        ppDependentResources: *const *mut ID3D12Resource,
+        pDependentSubresourceRanges: *const c_void,
+    ) -> (),
+    fn AtomicCopyBufferUINT64(
+        pDstBuffer: *mut ID3D12Resource,
+        DstOffset: UINT64,
+        pSrcBuffer: *mut ID3D12Resource,
+        SrcOffset: UINT64,
+        Dependencies: UINT,
+        ppDependentResources: *const *mut ID3D12Resource,
+        pDependentSubresourceRanges: *const c_void,
+    ) -> (),
+    fn OMSetDepthBounds(
+        Min: FLOAT,
+        Max: FLOAT,
+    ) -> (),
+    fn SetSamplePositions(
+        NumSamplesPerPixel: UINT,
+        NumPixels: UINT,
+        pSamplePositions: *mut c_void,
+    ) -> (),
+    fn ResolveSubresourceRegion(
+        pDstResource: *mut ID3D12Resource,
+        DstSubresource: UINT,
+        DstX: UINT,
+        DstY: UINT,
+        pSrcResource: *mut ID3D12Resource,
+        SrcSubresource: UINT,
+        pSrcRect: *mut RECT,
+        Format: DXGI_FORMAT,
+        ResolveMode: UINT,
+    ) -> (),
+    fn SetViewInstanceMask(
+        Mask: UINT,
+    ) -> (),
+}}
+
+RIDL!{#[uuid(0x38c3e585, 0xff17, 0x412c, 0x91, 0x50, 0x4f, 0xc6, 0xf9, 0xd7, 0x2a, 0x28)]
+interface ID3D12GraphicsCommandList2(ID3D12GraphicsCommandList2Vtbl):
+    ID3D12GraphicsCommandList1(ID3D12GraphicsCommandList1Vtbl) {
+    fn WriteBufferImmediate(
+        Count: UINT,
+        pParams: *const c_void,
+        pModes: *const UINT,
+    ) -> (),
+}}
+
+RIDL!{#[uuid(0x6fda83a7, 0xb84c, 0x4e38, 0x9a, 0xc8, 0xc7, 0xbd, 0x22, 0x01, 0x6b, 0x3d)]
+interface ID3D12GraphicsCommandList3(ID3D12GraphicsCommandList3Vtbl):
+    ID3D12GraphicsCommandList2(ID3D12GraphicsCommandList2Vtbl) {
+    fn SetProtectedResourceSession(
+        pProtectedResourceSession: *mut IUnknown,
+    ) -> (),
+}}
+
+pub type D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE = UINT;
+pub const D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE_DISCARD: D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE = 0;
+pub const D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE_PRESERVE: D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE = 1;
+pub const D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE_CLEAR: D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE = 2;
+pub const D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE_NO_ACCESS: D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE = 3;
+
+STRUCT!{struct D3D12_RENDER_PASS_BEGINNING_ACCESS_CLEAR_PARAMETERS {
+    ClearValue: D3D12_CLEAR_VALUE,
+}}
+
+// The parameters are declared as an union with a single member.
+STRUCT!{struct D3D12_RENDER_PASS_BEGINNING_ACCESS {
+    Type: D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE,
+    Clear: D3D12_RENDER_PASS_BEGINNING_ACCESS_CLEAR_PARAMETERS,
+}}
+
+pub type D3D12_RENDER_PASS_ENDING_ACCESS_TYPE = UINT;
+pub const D3D12_RENDER_PASS_ENDING_ACCESS_TYPE_DISCARD: D3D12_RENDER_PASS_ENDING_ACCESS_TYPE = 0;
+pub const D3D12_RENDER_PASS_ENDING_ACCESS_TYPE_PRESERVE: D3D12_RENDER_PASS_ENDING_ACCESS_TYPE = 1;
+pub const D3D12_RENDER_PASS_ENDING_ACCESS_TYPE_RESOLVE: D3D12_RENDER_PASS_ENDING_ACCESS_TYPE = 2;
+pub const D3D12_RENDER_PASS_ENDING_ACCESS_TYPE_NO_ACCESS: D3D12_RENDER_PASS_ENDING_ACCESS_TYPE = 3;
+
+pub type D3D12_RESOLVE_MODE = UINT;
+
+STRUCT!{struct D3D12_RENDER_PASS_ENDING_ACCESS_RESOLVE_PARAMETERS {
+    pSrcResource: *mut ID3D12Resource,
+    pDstResource: *mut ID3D12Resource,
+    SubresourceCount: UINT,
+    pSubresourceParameters: *const c_void,
+    Format: DXGI_FORMAT,
+    ResolveMode: D3D12_RESOLVE_MODE,
+    PreserveResolveSource: BOOL,
+}}
+
+// The parameters are declared as an union with a single member.
+STRUCT!{struct D3D12_RENDER_PASS_ENDING_ACCESS {
+    Type: D3D12_RENDER_PASS_ENDING_ACCESS_TYPE,
+    Resolve: D3D12_RENDER_PASS_ENDING_ACCESS_RESOLVE_PARAMETERS,
+}}
+
+STRUCT!{struct D3D12_RENDER_PASS_RENDER_TARGET_DESC {
+    cpuDescriptor: D3D12_CPU_DESCRIPTOR_HANDLE,
+    BeginningAccess: D3D12_RENDER_PASS_BEGINNING_ACCESS,
+    EndingAccess: D3D12_RENDER_PASS_ENDING_ACCESS,
+}}
+
+STRUCT!{struct D3D12_RENDER_PASS_DEPTH_STENCIL_DESC {
+    cpuDescriptor: D3D12_CPU_DESCRIPTOR_HANDLE,
+    DepthBeginningAccess: D3D12_RENDER_PASS_BEGINNING_ACCESS,
+    StencilBeginningAccess: D3D12_RENDER_PASS_BEGINNING_ACCESS,
+    DepthEndingAccess: D3D12_RENDER_PASS_ENDING_ACCESS,
+    StencilEndingAccess: D3D12_RENDER_PASS_ENDING_ACCESS,
+}}
+
+pub type D3D12_RENDER_PASS_FLAGS = UINT;
+pub const D3D12_RENDER_PASS_FLAG_NONE: D3D12_RENDER_PASS_FLAGS = 0;
+pub const D3D12_RENDER_PASS_FLAG_ALLOW_UAV_WRITES: D3D12_RENDER_PASS_FLAGS = 0x1;
+pub const D3D12_RENDER_PASS_FLAG_SUSPENDING_PASS: D3D12_RENDER_PASS_FLAGS = 0x2;
+pub const D3D12_RENDER_PASS_FLAG_RESUMING_PASS: D3D12_RENDER_PASS_FLAGS = 0x4;
+
+RIDL!{#[uuid(0x8754318e, 0xd3a9, 0x4541, 0x98, 0xcf, 0x64, 0x5b, 0x50, 0xdc, 0x48, 0x74)]
+interface ID3D12GraphicsCommandList4(ID3D12GraphicsCommandList4Vtbl):
+    ID3D12GraphicsCommandList3(ID3D12GraphicsCommandList3Vtbl) {
+    fn BeginRenderPass(
+        NumRenderTargets: UINT,
+        pRenderTargets: *const D3D12_RENDER_PASS_RENDER_TARGET_DESC,
+        pDepthStencil: *const D3D12_RENDER_PASS_DEPTH_STENCIL_DESC,
+        Flags: D3D12_RENDER_PASS_FLAGS,
+    ) -> (),
+    fn EndRenderPass() -> (),
+    fn InitializeMetaCommand(
+        pMetaCommand: *mut IUnknown,
+        pInitializationParametersData: *const c_void,
+        InitializationParametersDataSizeInBytes: SIZE_T,
+    ) -> (),
+    fn ExecuteMetaCommand(
+        pMetaCommand: *mut IUnknown,
+        pExecutionParametersData: *const c_void,
+        ExecutionParametersDataSizeInBytes: SIZE_T,
+    ) -> (),
+    fn BuildRaytracingAccelerationStructure(
+        pDesc: *const D3D12_BUILD_RAYTRACING_ACCELERATION_STRUCTURE_DESC,
+        NumPostbuildInfoDescs: UINT,
+        pPostbuildInfoDescs: *const c_void,
+    ) -> (),
+    fn EmitRaytracingAccelerationStructurePostbuildInfo(
+        pDesc: *const c_void,
+        NumSourceAccelerationStructures: UINT,
+        pSourceAccelerationStructureData: *const D3D12_GPU_VIRTUAL_ADDRESS,
+    ) -> (),
+    fn CopyRaytracingAccelerationStructure(
+        DestAccelerationStructureData: D3D12_GPU_VIRTUAL_ADDRESS,
+        SourceAccelerationStructureData: D3D12_GPU_VIRTUAL_ADDRESS,
+        Mode: UINT,
+    ) -> (),
+    fn SetPipelineState1(
+        pStateObject: *mut ID3D12StateObject,
+    ) -> (),
+    fn DispatchRays(
+        pDesc: *const D3D12_DISPATCH_RAYS_DESC,
+    ) -> (),
+}}
+
+RIDL!{#[uuid(0x47016943, 0xfca8, 0x4594, 0x93, 0xea, 0xaf, 0x25, 0x8b, 0x55, 0x34, 0x6d)]
+interface ID3D12StateObject(ID3D12StateObjectVtbl): ID3D12Pageable(ID3D12PageableVtbl) {}}
+
+RIDL!{#[uuid(0xde5fa827, 0x9bf9, 0x4f26, 0x89, 0xff, 0xd7, 0xf5, 0x6f, 0xde, 0x38, 0x60)]
+interface ID3D12StateObjectProperties(ID3D12StateObjectPropertiesVtbl): IUnknown(IUnknownVtbl) {
+    fn GetShaderIdentifier(
+        pExportName: LPCWSTR,
+    ) -> *mut c_void,
+    fn GetShaderStackSize(
+        pExportName: LPCWSTR,
+    ) -> UINT64,
+    fn GetPipelineStackSize() -> UINT64,
+    fn SetPipelineStackSize(
+        PipelineStackSizeInBytes: UINT64,
+    ) -> (),
+}}
+
+pub type D3D12_DRED_ENABLEMENT = UINT;
+pub const D3D12_DRED_ENABLEMENT_SYSTEM_CONTROLLED: D3D12_DRED_ENABLEMENT = 0;
+pub const D3D12_DRED_ENABLEMENT_FORCED_OFF: D3D12_DRED_ENABLEMENT = 1;
+pub const D3D12_DRED_ENABLEMENT_FORCED_ON: D3D12_DRED_ENABLEMENT = 2;
+
+pub type D3D12_AUTO_BREADCRUMB_OP = UINT;
+pub type D3D12_DRED_ALLOCATION_TYPE = UINT;
+
+STRUCT!{struct D3D12_AUTO_BREADCRUMB_NODE {
+    pCommandListDebugNameA: *const c_char,
+    pCommandListDebugNameW: *const WCHAR,
+    pCommandQueueDebugNameA: *const c_char,
+    pCommandQueueDebugNameW: *const WCHAR,
+    pCommandList: *mut ID3D12GraphicsCommandList,
+    pCommandQueue: *mut ID3D12CommandQueue,
+    BreadcrumbCount: UINT,
+    pLastBreadcrumbValue: *const UINT,
+    pCommandHistory: *const D3D12_AUTO_BREADCRUMB_OP,
+    pNext: *const D3D12_AUTO_BREADCRUMB_NODE,
+}}
+
+STRUCT!{struct D3D12_DRED_ALLOCATION_NODE {
+    ObjectNameA: *const c_char,
+    ObjectNameW: *const WCHAR,
+    AllocationType: D3D12_DRED_ALLOCATION_TYPE,
+    pNext: *const D3D12_DRED_ALLOCATION_NODE,
+}}
+
+STRUCT!{struct D3D12_DRED_AUTO_BREADCRUMBS_OUTPUT {
+    pHeadAutoBreadcrumbNode: *const D3D12_AUTO_BREADCRUMB_NODE,
+}}
+
+STRUCT!{struct D3D12_DRED_PAGE_FAULT_OUTPUT {
+    PageFaultVA: D3D12_GPU_VIRTUAL_ADDRESS,
+    pHeadExistingAllocationNode: *const D3D12_DRED_ALLOCATION_NODE,
+    pHeadRecentFreedAllocationNode: *const D3D12_DRED_ALLOCATION_NODE,
+}}
+
+RIDL!{#[uuid(0x82bc481c, 0x6b9b, 0x4030, 0xae, 0xdb, 0x7e, 0xe3, 0xd1, 0xdf, 0x1e, 0x63)]
+interface ID3D12DeviceRemovedExtendedDataSettings(ID3D12DeviceRemovedExtendedDataSettingsVtbl): IUnknown(IUnknownVtbl) {
+    fn SetAutoBreadcrumbsEnablement(
+        Enablement: D3D12_DRED_ENABLEMENT,
+    ) -> (),
+    fn SetPageFaultEnablement(
+        Enablement: D3D12_DRED_ENABLEMENT,
+    ) -> (),
+    fn SetWatsonDumpEnablement(
+        Enablement: D3D12_DRED_ENABLEMENT,
+    ) -> (),
+}}
+
+RIDL!{#[uuid(0x98931d33, 0x5ae8, 0x4791, 0xaa, 0x3c, 0x1a, 0x73, 0xa2, 0x93, 0x4e, 0x71)]
+interface ID3D12DeviceRemovedExtendedData(ID3D12DeviceRemovedExtendedDataVtbl): IUnknown(IUnknownVtbl) {
+    fn GetAutoBreadcrumbsOutput(
+        pOutput: *mut D3D12_DRED_AUTO_BREADCRUMBS_OUTPUT,
+    ) -> HRESULT,
+    fn GetPageFaultAllocationOutput(
+        pOutput: *mut D3D12_DRED_PAGE_FAULT_OUTPUT,
+    ) -> HRESULT,
+}}
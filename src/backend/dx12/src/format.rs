@@ -1117,6 +1117,26 @@ pub fn query_properties() -> [Properties; NUM_FORMATS] {
             optimal_tiling: ImageFeature::empty(),
             buffer_features: BufferFeature::empty(),
         },
+        // Nv12Unorm
+        // TODO: check optional supports
+        Properties {
+            linear_tiling: ImageFeature::empty(),
+            optimal_tiling: ImageFeature::SAMPLED | ImageFeature::SAMPLED_LINEAR,
+            buffer_features: BufferFeature::empty(),
+        },
+        // P010Unorm
+        // TODO: check optional supports
+        Properties {
+            linear_tiling: ImageFeature::empty(),
+            optimal_tiling: ImageFeature::SAMPLED | ImageFeature::SAMPLED_LINEAR,
+            buffer_features: BufferFeature::empty(),
+        },
+        // Yuy2Unorm
+        Properties {
+            linear_tiling: ImageFeature::empty(),
+            optimal_tiling: ImageFeature::empty(),
+            buffer_features: BufferFeature::empty(),
+        },
     ];
 
     properties
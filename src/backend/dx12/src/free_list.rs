@@ -3,6 +3,17 @@
 use std::collections::LinkedList;
 use std::ops::Range;
 
+/// Fragmentation statistics of a free-list allocator.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FragmentationStats {
+    /// Total number of free elements.
+    pub free: u64,
+    /// Size of the largest free range, which bounds the largest possible allocation.
+    pub largest_free_range: u64,
+    /// Number of disjoint free ranges.
+    pub free_ranges: usize,
+}
+
 #[derive(Debug)]
 pub struct Allocator {
     size: u64,
@@ -63,6 +74,19 @@ impl Allocator {
         })
     }
 
+    pub fn stats(&self) -> FragmentationStats {
+        self.free_list
+            .iter()
+            .fold(FragmentationStats::default(), |stats, node| {
+                let size = node.end - node.start;
+                FragmentationStats {
+                    free: stats.free + size,
+                    largest_free_range: stats.largest_free_range.max(size),
+                    free_ranges: stats.free_ranges + 1,
+                }
+            })
+    }
+
     pub fn deallocate(&mut self, mut range: Range<u64>) {
         // early out for invalid or empty ranges
         if range.end <= range.start {
@@ -115,7 +139,7 @@ impl Allocator {
 
 #[cfg(test)]
 mod tests {
-    use super::Allocator;
+    use super::{Allocator, FragmentationStats};
 
     #[test]
     fn test_allocate() {
@@ -141,4 +165,17 @@ mod tests {
         allocator.deallocate(middle);
         assert_eq!(Some(0..5), allocator.allocate(5));
     }
+
+    #[test]
+    fn test_stats() {
+        let mut allocator = Allocator::new(8);
+        let front = allocator.allocate(4).unwrap();
+        let _middle = allocator.allocate(2).unwrap();
+        allocator.deallocate(front);
+
+        assert_eq!(
+            FragmentationStats { free: 6, largest_free_range: 4, free_ranges: 2 },
+            allocator.stats(),
+        );
+    }
 }
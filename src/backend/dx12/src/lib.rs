@@ -7,14 +7,18 @@ extern crate gfx_hal as hal;
 extern crate log;
 extern crate smallvec;
 extern crate spirv_cross;
+#[macro_use]
 extern crate winapi;
 #[cfg(feature = "winit")]
 extern crate winit;
 extern crate wio;
 
+mod capture;
 mod command;
 mod conv;
 mod device;
+mod dred;
+mod ext;
 mod format;
 mod free_list;
 mod native;
@@ -22,6 +26,11 @@ mod pool;
 mod root_constants;
 mod window;
 
+pub use command::RecordingError;
+pub use free_list::FragmentationStats;
+pub use native::DescriptorPoolStats;
+pub use pool::AllocatorStats;
+
 use hal::{error, format as f, image, memory, Features, Limits, QueueType};
 use hal::queue::{QueueFamily as HalQueueFamily, QueueFamilyId, Queues};
 
@@ -36,15 +45,61 @@ use std::os::windows::ffi::OsStringExt;
 use std::ffi::OsString;
 use std::sync::{Arc, Mutex};
 
+// Properties of the custom heaps backing the exposed memory types.
+#[derive(Debug, Clone, Copy)]
 pub(crate) struct HeapProperties {
     pub page_property: d3d12::D3D12_CPU_PAGE_PROPERTY,
     pub memory_pool: d3d12::D3D12_MEMORY_POOL,
 }
 
+impl HeapProperties {
+    // Query the custom heap equivalent of the heap type on the adapter.
+    fn query(device: &ComPtr<d3d12::ID3D12Device>, heap_type: d3d12::D3D12_HEAP_TYPE) -> Self {
+        let properties = unsafe { device.GetCustomHeapProperties(0, heap_type) };
+        HeapProperties {
+            page_property: properties.CPUPageProperty,
+            memory_pool: properties.MemoryPoolPreference,
+        }
+    }
+
+    fn memory_type(&self, architecture: MemoryArchitecture) -> hal::MemoryType {
+        use self::memory::Properties;
+
+        // All memory is local to the GPU on UMA devices, the memory
+        // of the L0 pool is stored in system memory on NUMA devices.
+        let system_memory = match architecture {
+            MemoryArchitecture::NUMA => self.memory_pool == d3d12::D3D12_MEMORY_POOL_L0,
+            MemoryArchitecture::UMA | MemoryArchitecture::CacheCoherentUMA => false,
+        };
+        let mut properties = if system_memory {
+            Properties::empty()
+        } else {
+            Properties::DEVICE_LOCAL
+        };
+        match self.page_property {
+            d3d12::D3D12_CPU_PAGE_PROPERTY_NOT_AVAILABLE => {}
+            d3d12::D3D12_CPU_PAGE_PROPERTY_WRITE_COMBINE => {
+                properties |= Properties::CPU_VISIBLE | Properties::COHERENT;
+            }
+            _ => {
+                properties |= Properties::CPU_VISIBLE | Properties::COHERENT | Properties::CPU_CACHED;
+            }
+        }
+        hal::MemoryType {
+            properties,
+            heap_index: if system_memory { 1 } else { 0 },
+        }
+    }
+}
+
 // https://msdn.microsoft.com/de-de/library/windows/desktop/dn770377(v=vs.85).aspx
 // Only 16 input slots allowed.
 const MAX_VERTEX_BUFFERS: usize = 16;
 
+// Fixed size of the root signature in DWORDs.
+// Limited by D3D12.
+const ROOT_SIGNATURE_SIZE: usize = 64;
+
 const NUM_HEAP_PROPERTIES: usize = 3;
 
 // Memory types are grouped according to the supported resources.
@@ -60,62 +115,6 @@ enum MemoryGroup {
     NumGroups,
 }
 
-// https://msdn.microsoft.com/de-de/library/windows/desktop/dn788678(v=vs.85).aspx
-static HEAPS_NUMA: [HeapProperties; NUM_HEAP_PROPERTIES] = [
-    // DEFAULT
-    HeapProperties {
-        page_property: d3d12::D3D12_CPU_PAGE_PROPERTY_NOT_AVAILABLE,
-        memory_pool: d3d12::D3D12_MEMORY_POOL_L1,
-    },
-    // UPLOAD
-    HeapProperties {
-        page_property: d3d12::D3D12_CPU_PAGE_PROPERTY_WRITE_COMBINE,
-        memory_pool: d3d12::D3D12_MEMORY_POOL_L0,
-
-    },
-    // READBACK
-    HeapProperties {
-        page_property: d3d12::D3D12_CPU_PAGE_PROPERTY_WRITE_BACK,
-        memory_pool: d3d12::D3D12_MEMORY_POOL_L0,
-    },
-];
-
-static HEAPS_UMA: [HeapProperties; NUM_HEAP_PROPERTIES] = [
-    // DEFAULT
-    HeapProperties {
-        page_property: d3d12::D3D12_CPU_PAGE_PROPERTY_NOT_AVAILABLE,
-        memory_pool: d3d12::D3D12_MEMORY_POOL_L0,
-    },
-    // UPLOAD
-    HeapProperties {
-        page_property: d3d12::D3D12_CPU_PAGE_PROPERTY_WRITE_COMBINE,
-        memory_pool: d3d12::D3D12_MEMORY_POOL_L0,
-    },
-    // READBACK
-    HeapProperties {
-        page_property: d3d12::D3D12_CPU_PAGE_PROPERTY_WRITE_BACK,
-        memory_pool: d3d12::D3D12_MEMORY_POOL_L0,
-    },
-];
-
-static HEAPS_CCUMA: [HeapProperties; NUM_HEAP_PROPERTIES] = [
-    // DEFAULT
-    HeapProperties {
-        page_property: d3d12::D3D12_CPU_PAGE_PROPERTY_NOT_AVAILABLE,
-        memory_pool: d3d12::D3D12_MEMORY_POOL_L0,
-    },
-    // UPLOAD
-    HeapProperties {
-        page_property: d3d12::D3D12_CPU_PAGE_PROPERTY_WRITE_BACK,
-        memory_pool: d3d12::D3D12_MEMORY_POOL_L0,
-    },
-    //READBACK
-    HeapProperties {
-        page_property: d3d12::D3D12_CPU_PAGE_PROPERTY_WRITE_BACK,
-        memory_pool: d3d12::D3D12_MEMORY_POOL_L0,
-    },
-];
-
 #[derive(Debug, Copy, Clone)]
 pub enum QueueFamily {
     // Specially marked present queue.
@@ -153,6 +152,13 @@ impl hal::QueueFamily for QueueFamily {
 }
 
 impl QueueFamily {
+    fn is_present(&self) -> bool {
+        match *self {
+            QueueFamily::Present => true,
+            QueueFamily::Normal(_) => false,
+        }
+    }
+
     fn native_type(&self) -> d3d12::D3D12_COMMAND_LIST_TYPE {
         use hal::QueueFamily;
         let queue_type = self.queue_type();
@@ -176,8 +182,10 @@ pub struct PhysicalDevice {
     features: hal::Features,
     limits: hal::Limits,
     private_caps: Capabilities,
-    heap_properties: &'static [HeapProperties; NUM_HEAP_PROPERTIES],
+    heap_properties: [HeapProperties; NUM_HEAP_PROPERTIES],
     memory_properties: hal::MemoryProperties,
+    // Create the present queue on `open`, disabled for compute only adapters.
+    presentation: bool,
     // Indicates that there is currently an active logical device.
     // Opening the same adapter multiple times will return the same D3D12Device again.
     is_open: Arc<Mutex<bool>>,
@@ -186,6 +194,16 @@ pub struct PhysicalDevice {
 unsafe impl Send for PhysicalDevice { }
 unsafe impl Sync for PhysicalDevice { }
 
+impl PhysicalDevice {
+    /// Get the memory architecture of the adapter.
+    ///
+    /// UMA adapters expose device local memory types which are `CPU_VISIBLE`,
+    /// resources can be written directly instead of using staging buffers.
+    pub fn memory_architecture(&self) -> MemoryArchitecture {
+        self.private_caps.memory_architecture
+    }
+}
+
 impl hal::PhysicalDevice<Backend> for PhysicalDevice {
     fn open(
         &self, families: &[(&QueueFamily, &[hal::QueuePriority])]
@@ -214,8 +232,9 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
             unsafe { ComPtr::<d3d12::ID3D12Device>::from_raw(device_raw) }
         };
 
-        // Always create the presentation queue in case we want to build a swapchain.
-        let present_queue = {
+        // Create the presentation queue in case we want to build a swapchain,
+        // unless the adapter was enumerated for compute only.
+        let present_queue = if self.presentation {
             let queue_desc = d3d12::D3D12_COMMAND_QUEUE_DESC {
                 Type: QueueFamily::Present.native_type(),
                 Priority: 0,
@@ -236,7 +255,9 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
                 error!("error on queue creation: {:x}", hr);
             }
 
-            unsafe { ComPtr::<d3d12::ID3D12CommandQueue>::from_raw(queue) }
+            Some(unsafe { ComPtr::<d3d12::ID3D12CommandQueue>::from_raw(queue) })
+        } else {
+            None
         };
 
         let mut device = Device::new(
@@ -265,7 +286,8 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
                         // Number of queues need to be larger than 0 else it
                         // violates the specification.
                         let queue = CommandQueue {
-                            raw: device.present_queue.clone(),
+                            raw: device.present_queue.clone().unwrap(),
+                            device: device.raw.clone(),
                             idle_fence: device.create_raw_fence(false),
                             idle_event: create_idle_event(),
                         };
@@ -293,6 +315,7 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
                             if winerror::SUCCEEDED(hr) {
                                 let queue = CommandQueue {
                                     raw: unsafe { ComPtr::from_raw(queue) },
+                                    device: device.raw.clone(),
                                     idle_fence: device.create_raw_fence(false),
                                     idle_event: create_idle_event(),
                                 };
@@ -379,6 +402,7 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
 #[derive(Clone)]
 pub struct CommandQueue {
     pub(crate) raw: ComPtr<d3d12::ID3D12CommandQueue>,
+    device: ComPtr<d3d12::ID3D12Device>,
     idle_fence: *mut d3d12::ID3D12Fence,
     idle_event: winnt::HANDLE,
 }
@@ -400,14 +424,36 @@ impl hal::queue::RawCommandQueue<Backend> for CommandQueue {
         (*self.idle_fence).Signal(0);
         synchapi::ResetEvent(self.idle_event);
 
-        // TODO: semaphores
-        let mut lists = submission
-            .cmd_buffers
-            .into_iter()
-            .map(|buf| buf.borrow().as_raw_list())
-            .collect::<Vec<_>>();
+        // Semaphores are fences toggled between 0 and 1. Waiting on the GPU
+        // timeline orders submissions across queues, which is required for
+        // the split barriers of queue family ownership transfers.
+        for &(semaphore, _) in submission.wait_semaphores {
+            assert_eq!(winerror::S_OK, self.raw.Wait(semaphore.raw.as_raw(), 1));
+            assert_eq!(winerror::S_OK, self.raw.Signal(semaphore.raw.as_raw(), 0));
+        }
+
+        let mut allocators = Vec::new();
+        let mut lists = Vec::new();
+        for buf in submission.cmd_buffers {
+            let buf = buf.borrow();
+            buf.prepare_submit(&self.raw);
+            allocators.push((buf.allocators().clone(), buf.raw_list()));
+            // Secondary command buffers executed by the command buffer are in flight as well.
+            allocators.extend(buf.secondaries().iter().cloned());
+            lists.extend(buf.lists());
+        }
         self.raw.ExecuteCommandLists(lists.len() as _, lists.as_mut_ptr());
 
+        // Track the submission for recycling the command allocators
+        // and detecting resubmissions of executing lists.
+        for (allocators, list) in allocators {
+            allocators.lock().unwrap().signal(&self.raw, list);
+        }
+
+        for semaphore in submission.signal_semaphores {
+            assert_eq!(winerror::S_OK, self.raw.Signal(semaphore.raw.as_raw(), 1));
+        }
+
         if let Some(fence) = fence {
             assert_eq!(winerror::S_OK,
                 self.raw.Signal(fence.raw.as_raw(), 1)
@@ -424,7 +470,11 @@ impl hal::queue::RawCommandQueue<Backend> for CommandQueue {
     {
         // TODO: semaphores
         for swapchain in swapchains {
-            unsafe { swapchain.borrow().inner.Present(1, 0); }
+            let hr = unsafe { swapchain.borrow().inner.Present(1, 0) };
+            if hr == winerror::DXGI_ERROR_DEVICE_REMOVED || hr == winerror::DXGI_ERROR_DEVICE_RESET {
+                // Diagnostics are logged, the error is reported by the next `wait_idle`.
+                let _ = dred::check_device(&self.device);
+            }
         }
     }
 
@@ -435,14 +485,22 @@ impl hal::queue::RawCommandQueue<Backend> for CommandQueue {
             synchapi::WaitForSingleObject(self.idle_event, winbase::INFINITE);
         }
 
-        Ok(())
+        // Fences of a removed device complete immediately.
+        dred::check_device(&self.device)
+            .map_err(error::HostExecutionError::DeviceLost)
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-enum MemoryArchitecture {
+/// Memory architecture of an adapter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryArchitecture {
+    /// Discrete adapter with dedicated video memory.
     NUMA,
+    /// Adapter sharing the system memory, typically an integrated GPU.
+    ///
+    /// Device local memory can be mapped, which avoids staging copies.
     UMA,
+    /// UMA adapter with memory caches coherent with the CPU.
     CacheCoherentUMA,
 }
 
@@ -450,6 +508,11 @@ enum MemoryArchitecture {
 pub struct Capabilities {
     heterogeneous_resource_heaps: bool,
     memory_architecture: MemoryArchitecture,
+    // Native render passes via `ID3D12GraphicsCommandList4`.
+    render_passes: bool,
+    binding_tier: d3d12::D3D12_RESOURCE_BINDING_TIER,
+    // DXR tier 1.0 or higher, see `Features::RAY_TRACING`.
+    ray_tracing: bool,
 }
 
 #[derive(Clone)]
@@ -459,10 +522,33 @@ struct CmdSignatures {
     dispatch: ComPtr<d3d12::ID3D12CommandSignature>,
 }
 
+impl CmdSignatures {
+    fn get(&self, ty: device::CommandSignature) -> &ComPtr<d3d12::ID3D12CommandSignature> {
+        match ty {
+            device::CommandSignature::Draw => &self.draw,
+            device::CommandSignature::DrawIndexed => &self.draw_indexed,
+            device::CommandSignature::Dispatch => &self.dispatch,
+        }
+    }
+}
+
+// Shader visible descriptor heaps of a device.
+// All descriptor sets are allocated from these heaps, which allows
+// command buffers to bind them once at the start of recording.
+#[derive(Clone)]
+struct ShaderVisibleHeaps {
+    srv_cbv_uav: ComPtr<d3d12::ID3D12DescriptorHeap>,
+    sampler: ComPtr<d3d12::ID3D12DescriptorHeap>,
+    // Allocator of the CBV/SRV/UAV heap, command buffers allocate
+    // descriptors for views created during recording from it.
+    srv_cbv_uav_allocator: Arc<Mutex<native::DescriptorHeap>>,
+}
+
 pub struct Device {
     raw: ComPtr<d3d12::ID3D12Device>,
     private_caps: Capabilities,
-    heap_properties: &'static [HeapProperties],
+    limits: hal::Limits,
+    heap_properties: [HeapProperties; NUM_HEAP_PROPERTIES],
     // CPU only pools
     rtv_pool: Mutex<native::DescriptorCpuPool>,
     dsv_pool: Mutex<native::DescriptorCpuPool>,
@@ -470,14 +556,17 @@ pub struct Device {
     uav_pool: Mutex<native::DescriptorCpuPool>,
     sampler_pool: Mutex<native::DescriptorCpuPool>,
     descriptor_update_pools: Mutex<Vec<native::DescriptorCpuPool>>,
+    null_descriptors: Option<native::NullDescriptors>,
     // CPU/GPU descriptor heaps
-    heap_srv_cbv_uav: Mutex<native::DescriptorHeap>,
+    heap_srv_cbv_uav: Arc<Mutex<native::DescriptorHeap>>,
     heap_sampler: Mutex<native::DescriptorHeap>,
     events: Mutex<Vec<winnt::HANDLE>>,
     signatures: CmdSignatures,
+    shader_visible_heaps: ShaderVisibleHeaps,
     // Present queue exposed by the `Present` queue family.
     // Required for swapchain creation. Only a single queue supports presentation.
-    present_queue: ComPtr<d3d12::ID3D12CommandQueue>,
+    // Not created for compute only adapters.
+    present_queue: Option<ComPtr<d3d12::ID3D12CommandQueue>>,
     // List of all queues created from this device, including present queue.
     // Needed for `wait_idle`.
     queues: Vec<CommandQueue>,
@@ -491,7 +580,7 @@ impl Device {
     fn new(
         mut device: ComPtr<d3d12::ID3D12Device>,
         physical_device: &PhysicalDevice,
-        present_queue: ComPtr<d3d12::ID3D12CommandQueue>,
+        present_queue: Option<ComPtr<d3d12::ID3D12CommandQueue>>,
     ) -> Self {
         // Allocate descriptor heaps
         let max_rtvs = 256; // TODO
@@ -521,7 +610,7 @@ impl Device {
         };
 
         let max_srvs = 0x1000; // TODO
-        let srv_pool = native::DescriptorCpuPool {
+        let mut srv_pool = native::DescriptorCpuPool {
             heap: Self::create_descriptor_heap_impl(
                 &mut device,
                 d3d12::D3D12_DESCRIPTOR_HEAP_TYPE_CBV_SRV_UAV,
@@ -547,7 +636,7 @@ impl Device {
         };
 
         let max_samplers = 2048; // D3D12 doesn't allow more samplers for one heap.
-        let sampler_pool = native::DescriptorCpuPool {
+        let mut sampler_pool = native::DescriptorCpuPool {
             heap: Self::create_descriptor_heap_impl(
                 &mut device,
                 d3d12::D3D12_DESCRIPTOR_HEAP_TYPE_SAMPLER,
@@ -559,6 +648,13 @@ impl Device {
             max_size: max_samplers as _,
         };
 
+        let null_descriptors = Self::create_null_descriptors(
+            &mut device,
+            &mut srv_pool,
+            &mut sampler_pool,
+            physical_device.private_caps.binding_tier,
+        );
+
         let heap_srv_cbv_uav = Self::create_descriptor_heap_impl(
             &mut device,
             d3d12::D3D12_DESCRIPTOR_HEAP_TYPE_CBV_SRV_UAV,
@@ -573,24 +669,33 @@ impl Device {
             max_samplers,
         );
 
-        let draw_signature = Self::create_command_signature(
+        let draw_signature = Self::create_default_command_signature(
             &mut device,
             device::CommandSignature::Draw,
         );
 
-        let draw_indexed_signature = Self::create_command_signature(
+        let draw_indexed_signature = Self::create_default_command_signature(
             &mut device,
             device::CommandSignature::DrawIndexed,
         );
 
-        let dispatch_signature = Self::create_command_signature(
+        let dispatch_signature = Self::create_default_command_signature(
             &mut device,
             device::CommandSignature::Dispatch,
         );
 
+        let srv_cbv_uav_raw = heap_srv_cbv_uav.raw.clone();
+        let heap_srv_cbv_uav = Arc::new(Mutex::new(heap_srv_cbv_uav));
+        let shader_visible_heaps = ShaderVisibleHeaps {
+            srv_cbv_uav: srv_cbv_uav_raw,
+            sampler: heap_sampler.raw.clone(),
+            srv_cbv_uav_allocator: heap_srv_cbv_uav.clone(),
+        };
+
         Device {
             raw: device,
             private_caps: physical_device.private_caps,
+            limits: physical_device.limits,
             heap_properties: physical_device.heap_properties,
             rtv_pool: Mutex::new(rtv_pool),
             dsv_pool: Mutex::new(dsv_pool),
@@ -598,7 +703,8 @@ impl Device {
             uav_pool: Mutex::new(uav_pool),
             sampler_pool: Mutex::new(sampler_pool),
             descriptor_update_pools: Mutex::new(Vec::new()),
-            heap_srv_cbv_uav: Mutex::new(heap_srv_cbv_uav),
+            null_descriptors,
+            heap_srv_cbv_uav,
             heap_sampler: Mutex::new(heap_sampler),
             events: Mutex::new(Vec::new()),
             signatures: CmdSignatures {
@@ -606,6 +712,7 @@ impl Device {
                 draw_indexed: draw_indexed_signature,
                 dispatch: dispatch_signature,
             },
+            shader_visible_heaps,
             present_queue,
             queues: Vec::new(),
             open: physical_device.is_open.clone(),
@@ -631,13 +738,25 @@ impl Drop for Device {
 
 pub struct Instance {
     pub(crate) factory: ComPtr<dxgi1_4::IDXGIFactory4>,
+    // PIX capturer, loaded before any device is created.
+    pix: Option<capture::Pix>,
 }
 
 unsafe impl Send for Instance { }
 unsafe impl Sync for Instance { }
 
 impl Instance {
-    pub fn create(_: &str, _: u32) -> Instance {
+    pub fn create(name: &str, version: u32) -> Instance {
+        Self::try_create(name, version).expect("Unable to create DXGI factory")
+    }
+
+    /// Create an instance, returning `None` if the DXGI factory can't be created.
+    ///
+    /// Adapters without D3D12 support are skipped by `enumerate_adapters`,
+    /// which returns no adapters at all on systems without D3D12.
+    pub fn try_create(_: &str, _: u32) -> Option<Instance> {
+        let pix = capture::Pix::load();
+
         #[cfg(debug_assertions)]
         {
             // Enable debug layer
@@ -652,6 +771,9 @@ impl Instance {
                 unsafe { (*debug_controller).EnableDebugLayer() };
                 unsafe { (*debug_controller).Release(); }
             }
+
+            // Report page faults on device removal.
+            dred::configure(false, true);
         }
 
         // Create DXGI factory
@@ -666,11 +788,22 @@ impl Instance {
 
         if !winerror::SUCCEEDED(hr) {
             error!("Failed on dxgi factory creation: {:?}", hr);
+            return None;
         }
 
-        Instance {
+        Some(Instance {
             factory: unsafe { ComPtr::from_raw(dxgi_factory) },
-        }
+            pix,
+        })
+    }
+
+    /// Tag every command recorded on devices opened afterwards with a breadcrumb.
+    ///
+    /// The markers of unfinished command buffers are reported with
+    /// `HostExecutionError::DeviceLost` if the device gets removed.
+    /// Returns `false` if the runtime doesn't support breadcrumbs.
+    pub fn enable_auto_breadcrumbs(&self) -> bool {
+        dred::configure(true, cfg!(debug_assertions))
     }
 }
 
@@ -745,6 +878,24 @@ impl hal::Instance for Instance {
                     mem::size_of::<d3d12::D3D12_FEATURE_DATA_D3D12_OPTIONS>() as _)
             });
 
+            let feature_levels = [
+                d3dcommon::D3D_FEATURE_LEVEL_11_0,
+                d3dcommon::D3D_FEATURE_LEVEL_11_1,
+                d3dcommon::D3D_FEATURE_LEVEL_12_0,
+                d3dcommon::D3D_FEATURE_LEVEL_12_1,
+            ];
+            let mut features_levels = d3d12::D3D12_FEATURE_DATA_FEATURE_LEVELS {
+                NumFeatureLevels: feature_levels.len() as _,
+                pFeatureLevelsRequested: feature_levels.as_ptr(),
+                MaxSupportedFeatureLevel: d3dcommon::D3D_FEATURE_LEVEL_11_0,
+            };
+            assert_eq!(winerror::S_OK, unsafe {
+                device.CheckFeatureSupport(d3d12::D3D12_FEATURE_FEATURE_LEVELS,
+                    &mut features_levels as *mut _ as *mut _,
+                    mem::size_of::<d3d12::D3D12_FEATURE_DATA_FEATURE_LEVELS>() as _)
+            });
+            let feature_level = features_levels.MaxSupportedFeatureLevel;
+
             let mut features_architecture: d3d12::D3D12_FEATURE_DATA_ARCHITECTURE = unsafe { mem::zeroed() };
             assert_eq!(winerror::S_OK, unsafe {
                 device.CheckFeatureSupport(d3d12::D3D12_FEATURE_ARCHITECTURE,
@@ -752,71 +903,104 @@ impl hal::Instance for Instance {
                     mem::size_of::<d3d12::D3D12_FEATURE_DATA_ARCHITECTURE>() as _)
             });
 
+            let mut features1: ext::D3D12_FEATURE_DATA_D3D12_OPTIONS1 = unsafe { mem::zeroed() };
+            let has_options1 = winerror::SUCCEEDED(unsafe {
+                device.CheckFeatureSupport(ext::D3D12_FEATURE_D3D12_OPTIONS1,
+                    &mut features1 as *mut _ as *mut _,
+                    mem::size_of::<ext::D3D12_FEATURE_DATA_D3D12_OPTIONS1>() as _)
+            });
+            let wave_ops = has_options1 && features1.WaveOps == TRUE;
+            let int64_shader_ops = has_options1 && features1.Int64ShaderOps == TRUE;
+
+            let mut features2: ext::D3D12_FEATURE_DATA_D3D12_OPTIONS2 = unsafe { mem::zeroed() };
+            let depth_bounds = winerror::SUCCEEDED(unsafe {
+                device.CheckFeatureSupport(ext::D3D12_FEATURE_D3D12_OPTIONS2,
+                    &mut features2 as *mut _ as *mut _,
+                    mem::size_of::<ext::D3D12_FEATURE_DATA_D3D12_OPTIONS2>() as _)
+            }) && features2.DepthBoundsTestSupported == TRUE;
+
+            let mut features3: ext::D3D12_FEATURE_DATA_D3D12_OPTIONS3 = unsafe { mem::zeroed() };
+            let view_instancing = winerror::SUCCEEDED(unsafe {
+                device.CheckFeatureSupport(ext::D3D12_FEATURE_D3D12_OPTIONS3,
+                    &mut features3 as *mut _ as *mut _,
+                    mem::size_of::<ext::D3D12_FEATURE_DATA_D3D12_OPTIONS3>() as _)
+            }) && features3.ViewInstancingTier != ext::D3D12_VIEW_INSTANCING_TIER_NOT_SUPPORTED;
+
+            // Options5 are only known to recent runtimes, the query fails on older ones.
+            let mut features5: ext::D3D12_FEATURE_DATA_D3D12_OPTIONS5 = unsafe { mem::zeroed() };
+            let has_options5 = winerror::SUCCEEDED(unsafe {
+                device.CheckFeatureSupport(ext::D3D12_FEATURE_D3D12_OPTIONS5,
+                    &mut features5 as *mut _ as *mut _,
+                    mem::size_of::<ext::D3D12_FEATURE_DATA_D3D12_OPTIONS5>() as _)
+            });
+            let ray_tracing = has_options5 && features5.RaytracingTier >= ext::D3D12_RAYTRACING_TIER_1_0;
+            // Tier 0 render passes are emulated by the runtime, only use them if implemented by the driver.
+            let render_passes = has_options5 && features5.RenderPassesTier >= ext::D3D12_RENDER_PASS_TIER_1;
+
+            let logic_op = features.OutputMergerLogicOp == TRUE;
+            let double_shader_ops = features.DoublePrecisionFloatShaderOps == TRUE;
+            let typed_uav_loads = features.TypedUAVLoadAdditionalFormats == TRUE;
+            let rovs = features.ROVsSupported == TRUE;
+            // Viewport indices written by vertex or tessellation shaders, not only by geometry shaders.
+            let viewport_index = features.VPAndRTArrayIndexFromAnyShaderFeedingRasterizerSupportedWithoutGSEmulation == TRUE;
+            // Min/max filtering is tied to tiled resources tier 2.
+            let sampler_reduction = features.TiledResourcesTier >= d3d12::D3D12_TILED_RESOURCES_TIER_2;
+            let binding_tier = features.ResourceBindingTier;
             let heterogeneous_resource_heaps = features.ResourceHeapTier != d3d12::D3D12_RESOURCE_HEAP_TIER_1;
 
+            // Descriptors accessible by a single shader stage, depending on the resource binding tier.
+            // https://docs.microsoft.com/en-us/windows/desktop/direct3d12/hardware-support
+            let heap_size = d3d12::D3D12_MAX_SHADER_VISIBLE_DESCRIPTOR_HEAP_SIZE_TIER_1 as usize;
+            let (stage_samplers, stage_cbvs, stage_srvs, stage_uavs) = match binding_tier {
+                d3d12::D3D12_RESOURCE_BINDING_TIER_1 => (
+                    d3d12::D3D12_COMMONSHADER_SAMPLER_SLOT_COUNT as usize,
+                    d3d12::D3D12_COMMONSHADER_CONSTANT_BUFFER_API_SLOT_COUNT as usize,
+                    d3d12::D3D12_COMMONSHADER_INPUT_RESOURCE_SLOT_COUNT as usize,
+                    if feature_level >= d3dcommon::D3D_FEATURE_LEVEL_11_1 {
+                        d3d12::D3D12_UAV_SLOT_COUNT as usize
+                    } else {
+                        d3d12::D3D12_PS_CS_UAV_REGISTER_COUNT as usize
+                    },
+                ),
+                d3d12::D3D12_RESOURCE_BINDING_TIER_2 => (
+                    d3d12::D3D12_MAX_SHADER_VISIBLE_SAMPLER_HEAP_SIZE as usize,
+                    d3d12::D3D12_COMMONSHADER_CONSTANT_BUFFER_API_SLOT_COUNT as usize,
+                    heap_size,
+                    d3d12::D3D12_UAV_SLOT_COUNT as usize,
+                ),
+                _ => (
+                    d3d12::D3D12_MAX_SHADER_VISIBLE_SAMPLER_HEAP_SIZE as usize,
+                    heap_size,
+                    heap_size,
+                    heap_size,
+                ),
+            };
+
             let uma = features_architecture.UMA == TRUE;
             let cc_uma = features_architecture.CacheCoherentUMA == TRUE;
 
-            let (memory_architecture, heap_properties) = match (uma, cc_uma) {
-                (true, true)  => (MemoryArchitecture::CacheCoherentUMA, &HEAPS_CCUMA),
-                (true, false) => (MemoryArchitecture::UMA, &HEAPS_UMA),
-                (false, _)    => (MemoryArchitecture::NUMA, &HEAPS_NUMA),
+            let memory_architecture = match (uma, cc_uma) {
+                (true, true)  => MemoryArchitecture::CacheCoherentUMA,
+                (true, false) => MemoryArchitecture::UMA,
+                (false, _)    => MemoryArchitecture::NUMA,
             };
 
+            // Memory is allocated from custom heaps with the properties of the
+            // DEFAULT, UPLOAD and READBACK heaps of the adapter. On UMA devices
+            // the UPLOAD and READBACK equivalents are device local, allowing
+            // persistently mapped device local resources.
+            let heap_properties = [
+                HeapProperties::query(&device, d3d12::D3D12_HEAP_TYPE_DEFAULT),
+                HeapProperties::query(&device, d3d12::D3D12_HEAP_TYPE_UPLOAD),
+                HeapProperties::query(&device, d3d12::D3D12_HEAP_TYPE_READBACK),
+            ];
+
             // https://msdn.microsoft.com/en-us/library/windows/desktop/dn788678(v=vs.85).aspx
-            let base_memory_types: [hal::MemoryType; NUM_HEAP_PROPERTIES] = match memory_architecture {
-                MemoryArchitecture::NUMA => [
-                    // DEFAULT
-                    hal::MemoryType {
-                        properties: Properties::DEVICE_LOCAL,
-                        heap_index: 0,
-                    },
-                    // UPLOAD
-                    hal::MemoryType {
-                        properties: Properties::CPU_VISIBLE | Properties::COHERENT,
-                        heap_index: 1,
-                    },
-                    // READBACK
-                    hal::MemoryType {
-                        properties: Properties::CPU_VISIBLE | Properties::COHERENT | Properties::CPU_CACHED,
-                        heap_index: 1,
-                    },
-                ],
-                MemoryArchitecture::UMA => [
-                    // DEFAULT
-                    hal::MemoryType {
-                        properties: Properties::DEVICE_LOCAL,
-                        heap_index: 0,
-                    },
-                    // UPLOAD
-                    hal::MemoryType {
-                        properties: Properties::DEVICE_LOCAL | Properties::CPU_VISIBLE | Properties::COHERENT,
-                        heap_index: 0,
-                    },
-                    // READBACK
-                    hal::MemoryType {
-                        properties: Properties::DEVICE_LOCAL | Properties::CPU_VISIBLE | Properties::COHERENT | Properties::CPU_CACHED,
-                        heap_index: 0,
-                    },
-                ],
-                MemoryArchitecture::CacheCoherentUMA => [
-                    // DEFAULT
-                    hal::MemoryType {
-                        properties: Properties::DEVICE_LOCAL,
-                        heap_index: 0,
-                    },
-                    // UPLOAD
-                    hal::MemoryType {
-                        properties: Properties::DEVICE_LOCAL | Properties::CPU_VISIBLE | Properties::COHERENT | Properties::CPU_CACHED,
-                        heap_index: 0,
-                    },
-                    // READBACK
-                    hal::MemoryType {
-                        properties: Properties::DEVICE_LOCAL | Properties::CPU_VISIBLE | Properties::COHERENT | Properties::CPU_CACHED,
-                        heap_index: 0,
-                    },
-                ],
-            };
+            let base_memory_types = [
+                heap_properties[0].memory_type(memory_architecture),
+                heap_properties[1].memory_type(memory_architecture),
+                heap_properties[2].memory_type(memory_architecture),
+            ];
 
             let memory_types = if heterogeneous_resource_heaps {
                 base_memory_types.to_vec()
@@ -895,41 +1079,111 @@ impl hal::Instance for Instance {
                     Features::IMAGE_CUBE_ARRAY |
                     Features::GEOMETRY_SHADER |
                     Features::TESSELLATION_SHADER |
-                    //logic_op: false, // Optional on feature level 11_0
+                    Features::INDEPENDENT_BLENDING |
                     Features::MULTI_DRAW_INDIRECT |
+                    Features::DRAW_INDIRECT_FIRST_INSTANCE |
+                    Features::DRAW_FIRST_INSTANCE |
+                    Features::DRAW_BASE_VERTEX |
                     Features::FORMAT_BC |
-                    Features::INSTANCE_RATE,
-                limits: Limits { // TODO
-                    max_texture_size: 0,
-                    max_patch_size: 0,
-                    max_viewports: 0,
+                    Features::INSTANCE_RATE |
+                    Features::BUFFER_DEVICE_ADDRESS |
+                    Features::DEPTH_CLIP_CONTROL |
+                    Features::INDIRECT_STATE_ARGUMENTS |
+                    Features::TRACKED_RESOURCES |
+                    Features::SAMPLER_ANISOTROPY |
+                    Features::SAMPLER_BORDER_COLOR |
+                    // Command allocators are sharded per recording thread.
+                    Features::CONCURRENT_COMMAND_RECORDING |
+                    // Render targets are bound directly, render passes are emulated on top.
+                    Features::DYNAMIC_RENDERING |
+                    // Point polygon mode is emulated with geometry shaders.
+                    Features::NON_FILL_POLYGON_MODE |
+                    // Dynamic indexing of resource arrays is part of shader model 5.1.
+                    Features::SHADER_UNIFORM_BUFFER_ARRAY_DYNAMIC_INDEXING |
+                    Features::SHADER_SAMPLED_IMAGE_ARRAY_DYNAMIC_INDEXING |
+                    Features::SHADER_STORAGE_BUFFER_ARRAY_DYNAMIC_INDEXING |
+                    Features::SHADER_STORAGE_IMAGE_ARRAY_DYNAMIC_INDEXING |
+                    // Optional on feature level 11_0
+                    if logic_op { Features::LOGIC_OP } else { Features::empty() } |
+                    if double_shader_ops { Features::SHADER_FLOAT64 } else { Features::empty() } |
+                    if int64_shader_ops { Features::SHADER_INT64 } else { Features::empty() } |
+                    if typed_uav_loads { Features::SHADER_STORAGE_IMAGE_READ_WITHOUT_FORMAT } else { Features::empty() } |
+                    if rovs { Features::RASTERIZER_ORDERED_VIEWS } else { Features::empty() } |
+                    if viewport_index { Features::MULTI_VIEWPORTS } else { Features::empty() } |
+                    if sampler_reduction { Features::SAMPLER_REDUCTION } else { Features::empty() } |
+                    if wave_ops { Features::SUBGROUP_OPERATIONS } else { Features::empty() } |
+                    if binding_tier == d3d12::D3D12_RESOURCE_BINDING_TIER_3 {
+                        Features::PARTIALLY_BOUND_DESCRIPTORS
+                    } else {
+                        Features::empty()
+                    } |
+                    if depth_bounds { Features::DEPTH_BOUNDS } else { Features::empty() } |
+                    if view_instancing { Features::MULTIVIEW } else { Features::empty() } |
+                    if ray_tracing { Features::RAY_TRACING } else { Features::empty() },
+                limits: Limits {
+                    // Texture dimensions are the same for all feature levels from 11_0 on.
+                    max_texture_size: d3d12::D3D12_REQ_TEXTURE2D_U_OR_V_DIMENSION as _,
+                    max_texture_3d_size: d3d12::D3D12_REQ_TEXTURE3D_U_V_OR_W_DIMENSION as _,
+                    max_texture_array_layers: d3d12::D3D12_REQ_TEXTURE2D_ARRAY_AXIS_DIMENSION as _,
+                    max_patch_size: d3d12::D3D12_IA_PATCH_MAX_CONTROL_POINT_COUNT as _,
+                    max_viewports: d3d12::D3D12_VIEWPORT_AND_SCISSORRECT_OBJECT_COUNT_PER_PIPELINE as _,
                     max_compute_group_count: [
+                        d3d12::D3D12_CS_DISPATCH_MAX_THREAD_GROUPS_PER_DIMENSION,
+                        d3d12::D3D12_CS_DISPATCH_MAX_THREAD_GROUPS_PER_DIMENSION,
+                        d3d12::D3D12_CS_DISPATCH_MAX_THREAD_GROUPS_PER_DIMENSION,
+                    ],
+                    max_compute_group_size: [
                         d3d12::D3D12_CS_THREAD_GROUP_MAX_X,
                         d3d12::D3D12_CS_THREAD_GROUP_MAX_Y,
                         d3d12::D3D12_CS_THREAD_GROUP_MAX_Z,
                     ],
-                    max_compute_group_size: [
-                        d3d12::D3D12_CS_THREAD_GROUP_MAX_THREADS_PER_GROUP,
-                        1, //TODO
-                        1, //TODO
-                    ],
+                    // Root constants share the root signature with the descriptor tables,
+                    // each set takes up to 2 DWORDs, which is checked on pipeline layout creation.
+                    max_push_constants_size: ROOT_SIGNATURE_SIZE * 4,
+                    max_bound_descriptor_sets: ROOT_SIGNATURE_SIZE / 2,
+                    max_per_stage_descriptor_samplers: stage_samplers,
+                    max_per_stage_descriptor_uniform_buffers: stage_cbvs,
+                    max_per_stage_descriptor_storage_buffers: stage_uavs,
+                    max_per_stage_descriptor_sampled_images: stage_srvs,
+                    max_per_stage_descriptor_storage_images: stage_uavs,
+                    max_texel_elements: 1 << d3d12::D3D12_REQ_BUFFER_RESOURCE_TEXEL_COUNT_2_TO_EXP,
                     min_buffer_copy_offset_alignment: d3d12::D3D12_TEXTURE_DATA_PLACEMENT_ALIGNMENT as _,
                     min_buffer_copy_pitch_alignment: d3d12::D3D12_TEXTURE_DATA_PITCH_ALIGNMENT as _,
                     min_uniform_buffer_offset_alignment: 256, // Required alignment for CBVs
+                    non_coherent_atom_size: 1,
+                    shader_group_handle_size: ext::D3D12_SHADER_IDENTIFIER_SIZE_IN_BYTES,
+                    max_ray_recursion_depth: if ray_tracing {
+                        ext::D3D12_RAYTRACING_MAX_DECLARABLE_TRACE_RECURSION_DEPTH
+                    } else {
+                        0
+                    },
+                    min_shader_table_alignment: ext::D3D12_RAYTRACING_SHADER_TABLE_BYTE_ALIGNMENT as _,
+                    min_shader_record_alignment: ext::D3D12_RAYTRACING_SHADER_RECORD_BYTE_ALIGNMENT as _,
+                    max_multiview_view_count: if view_instancing { ext::D3D12_MAX_VIEW_INSTANCE_COUNT } else { 0 },
                 },
                 private_caps: Capabilities {
                     heterogeneous_resource_heaps,
                     memory_architecture,
+                    render_passes,
+                    binding_tier,
+                    ray_tracing,
                 },
                 heap_properties,
                 memory_properties: hal::MemoryProperties {
                     memory_types,
                     memory_heaps,
                 },
+                // Composition surfaces don't require windowing support,
+                // so presentation is always available.
+                presentation: true,
                 is_open: Arc::new(Mutex::new(false)),
             };
 
-            let queue_families = QUEUE_FAMILIES.to_vec();
+            let queue_families = QUEUE_FAMILIES
+                .iter()
+                .filter(|&&family| physical_device.presentation || !family.is_present())
+                .cloned()
+                .collect();
 
             adapters.push(hal::Adapter {
                 info,
@@ -939,6 +1193,37 @@ impl hal::Instance for Instance {
         }
         adapters
     }
+
+    fn enumerate_adapters_compute_only(&self) -> Vec<hal::Adapter<Backend>> {
+        let mut adapters = self.enumerate_adapters();
+        for adapter in &mut adapters {
+            // Skip the present queue, which is only needed for swapchains.
+            adapter.physical_device.presentation = false;
+            adapter.queue_families.retain(|family| !family.is_present());
+        }
+        adapters
+    }
+
+    fn trigger_capture(&self) -> Result<(), error::CaptureError> {
+        match self.pix {
+            Some(ref pix) => pix.trigger_capture(),
+            None => Err(error::CaptureError::NotAvailable),
+        }
+    }
+
+    fn begin_capture(&self) -> Result<(), error::CaptureError> {
+        match self.pix {
+            Some(ref pix) => pix.begin_capture(),
+            None => Err(error::CaptureError::NotAvailable),
+        }
+    }
+
+    fn end_capture(&self) -> Result<(), error::CaptureError> {
+        match self.pix {
+            Some(ref pix) => pix.end_capture(),
+            None => Err(error::CaptureError::NotAvailable),
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
@@ -979,4 +1264,8 @@ impl hal::Backend for Backend {
     type Fence = native::Fence;
     type Semaphore = native::Semaphore;
     type QueryPool = native::QueryPool;
+    type CommandSignature = native::CommandSignature;
+
+    type AccelerationStructure = native::AccelerationStructure;
+    type RayTracingPipeline = native::RayTracingPipeline;
 }
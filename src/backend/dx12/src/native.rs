@@ -1,22 +1,28 @@
 use winapi::shared::minwindef::UINT;
-use winapi::shared::dxgiformat::DXGI_FORMAT;
+use winapi::shared::dxgiformat::{self, DXGI_FORMAT};
 use winapi::um::{d3d12, d3dcommon};
 use wio::com::ComPtr;
 
-use hal::{format, image, pass, pso, DescriptorPool as HalDescriptorPool};
-use {free_list, Backend, MAX_VERTEX_BUFFERS};
+use hal::{accel_struct, buffer, format, image, pass, pso, DescriptorPool as HalDescriptorPool, QueryPool as HalQueryPool};
+use {conv, ext, free_list, Backend, MAX_VERTEX_BUFFERS};
 use root_constants::RootConstant;
 
+use std::cell::Cell;
 use std::collections::BTreeMap;
+use std::mem;
 use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::AtomicUsize;
 
 // ShaderModule is either a precompiled if the source comes from HLSL or
 // the SPIR-V module doesn't contain specialization constants or push constants
 // because they need to be adjusted on pipeline creation.
+// DXIL libraries containing ray tracing shaders are always precompiled.
 #[derive(Debug, Hash)]
 pub enum ShaderModule {
     Compiled(BTreeMap<String, *mut d3dcommon::ID3DBlob>),
     Spirv(Vec<u8>),
+    Library(Vec<u8>),
 }
 unsafe impl Send for ShaderModule { }
 unsafe impl Sync for ShaderModule { }
@@ -59,6 +65,7 @@ pub struct SubpassDesc {
     pub(crate) depth_stencil_attachment: Option<pass::AttachmentRef>,
     pub(crate) input_attachments: Vec<pass::AttachmentRef>,
     pub(crate) pre_barriers: Vec<BarrierDesc>,
+    pub(crate) view_mask: u32,
 }
 
 impl SubpassDesc {
@@ -70,6 +77,13 @@ impl SubpassDesc {
             .chain(self.input_attachments.iter())
             .any(|&(id, _)| id == at_id)
     }
+
+    /// Check if the attachment is bound as render target in this subpass.
+    pub(crate) fn is_target(&self, at_id: pass::AttachmentId) -> bool {
+        self.color_attachments.iter()
+            .chain(self.depth_stencil_attachment.iter())
+            .any(|&(id, _)| id == at_id)
+    }
 }
 
 #[derive(Debug, Hash, Clone)]
@@ -88,6 +102,9 @@ pub struct GraphicsPipeline {
     pub(crate) constants: Vec<RootConstant>,
     pub(crate) vertex_strides: [UINT; MAX_VERTEX_BUFFERS],
     pub(crate) baked_states: pso::BakedStates,
+    pub(crate) viewport_count: usize,
+    // Attachment formats of the subpass the pipeline was created for.
+    pub(crate) formats: pass::AttachmentFormats,
 }
 unsafe impl Send for GraphicsPipeline { }
 unsafe impl Sync for GraphicsPipeline { }
@@ -98,11 +115,27 @@ pub struct ComputePipeline {
     pub(crate) signature: *mut d3d12::ID3D12RootSignature, // weak-ptr, owned by `PipelineLayout`
     pub(crate) num_parameter_slots: usize, // signature parameter slots, see `PipelineLayout`
     pub(crate) constants: Vec<RootConstant>,
+    // Reflected when the pipeline is created, the shader blob isn't kept.
+    pub(crate) statistics: Option<pso::Statistics>,
 }
 
 unsafe impl Send for ComputePipeline { }
 unsafe impl Sync for ComputePipeline { }
 
+#[derive(Debug)]
+pub struct RayTracingPipeline {
+    pub(crate) raw: *mut ext::ID3D12StateObject,
+    pub(crate) properties: *mut ext::ID3D12StateObjectProperties,
+    pub(crate) signature: *mut d3d12::ID3D12RootSignature, // weak-ptr, owned by `PipelineLayout`
+    pub(crate) num_parameter_slots: usize, // signature parameter slots, see `PipelineLayout`
+    pub(crate) constants: Vec<RootConstant>,
+    // Null-terminated export names of the shader groups, required for querying the shader identifiers.
+    pub(crate) group_exports: Vec<Vec<u16>>,
+}
+
+unsafe impl Send for RayTracingPipeline { }
+unsafe impl Sync for RayTracingPipeline { }
+
 bitflags! {
     pub struct SetTableTypes: u8 {
         const SRV_CBV_UAV = 0x1;
@@ -124,6 +157,11 @@ pub struct PipelineLayout {
     // Number of parameter slots in this layout, can be larger than number of tables.
     // Required for updating the root signature when flusing user data.
     pub(crate) num_parameter_slots: usize,
+    // Buffer bindings of each set, which are promoted to root descriptors.
+    // Their parameters follow the tables of the set.
+    pub(crate) root_descriptors: Vec<Vec<pso::DescriptorBinding>>,
+    // Set and binding indices of the bindings accessed as rasterizer ordered views.
+    pub(crate) rasterizer_ordered: Vec<(u32, pso::DescriptorBinding)>,
 }
 unsafe impl Send for PipelineLayout { }
 unsafe impl Sync for PipelineLayout { }
@@ -137,13 +175,57 @@ pub struct Framebuffer {
 pub struct Buffer {
     pub(crate) resource: *mut d3d12::ID3D12Resource,
     pub(crate) size_in_bytes: u32,
-    pub(crate) clear_uav: Option<DualHandle>,
+    pub(crate) usage: buffer::Usage,
 }
 unsafe impl Send for Buffer { }
 unsafe impl Sync for Buffer { }
 
-#[derive(Debug, Hash, PartialEq, Eq)]
-pub struct BufferView;
+impl Buffer {
+    pub(crate) fn tracking(&self) -> Tracking {
+        if self.usage.contains(buffer::Usage::TRACKED) {
+            Tracking::Buffer
+        } else {
+            Tracking::None
+        }
+    }
+}
+
+/// How the state of a resource is tracked, see `StorageFlags::TRACKED`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tracking {
+    /// States are transitioned by the user with `pipeline_barrier`.
+    None,
+    /// Tracked buffer. Buffers decay to `COMMON` at the end of each submission,
+    /// so their states are only tracked within a command buffer.
+    Buffer,
+    /// Tracked image, pointing to the state shared by all command buffers.
+    Image(*const AtomicUsize),
+}
+
+#[derive(Copy, Derivative, Clone)]
+#[derivative(Debug)]
+pub struct BufferView {
+    #[derivative(Debug="ignore")]
+    pub(crate) resource: *mut d3d12::ID3D12Resource,
+    // Typed SRV, for uniform texel buffers.
+    #[derivative(Debug="ignore")]
+    pub(crate) handle_srv: Option<d3d12::D3D12_CPU_DESCRIPTOR_HANDLE>,
+    // Typed UAV, for storage texel buffers.
+    #[derivative(Debug="ignore")]
+    pub(crate) handle_uav: Option<d3d12::D3D12_CPU_DESCRIPTOR_HANDLE>,
+    pub(crate) tracking: Tracking,
+}
+unsafe impl Send for BufferView { }
+unsafe impl Sync for BufferView { }
+
+#[derive(Debug)]
+pub struct AccelerationStructure {
+    pub(crate) resource: *mut d3d12::ID3D12Resource, // weak-ptr, owned by `Buffer`
+    pub(crate) location: d3d12::D3D12_GPU_VIRTUAL_ADDRESS,
+    pub(crate) level: accel_struct::Level,
+}
+unsafe impl Send for AccelerationStructure { }
+unsafe impl Sync for AccelerationStructure { }
 
 
 #[derive(Clone, Derivative)]
@@ -154,6 +236,7 @@ pub struct Image {
     pub(crate) usage: image::Usage,
     pub(crate) storage_flags: image::StorageFlags,
     pub(crate) dxgi_format: DXGI_FORMAT,
+    pub(crate) surface_type: format::SurfaceType,
     pub(crate) bytes_per_block: u8,
     // Dimension of a texel block (compressed formats).
     pub(crate) block_dim: (u8, u8),
@@ -164,11 +247,20 @@ pub struct Image {
     pub(crate) clear_dv: Option<d3d12::D3D12_CPU_DESCRIPTOR_HANDLE>,
     #[derivative(Debug="ignore")]
     pub(crate) clear_sv: Option<d3d12::D3D12_CPU_DESCRIPTOR_HANDLE>,
+    // State after the last recorded use, for images created with `StorageFlags::TRACKED`.
+    pub(crate) tracked: Option<Arc<AtomicUsize>>,
 }
 unsafe impl Send for Image { }
 unsafe impl Sync for Image { }
 
 impl Image {
+    pub(crate) fn tracking(&self) -> Tracking {
+        match self.tracked {
+            Some(ref state) => Tracking::Image(&**state),
+            None => Tracking::None,
+        }
+    }
+
     /// Get `SubresourceRange` of the whole image.
     pub fn to_subresource_range(&self, aspects: format::Aspects) -> image::SubresourceRange {
         image::SubresourceRange {
@@ -181,6 +273,68 @@ impl Image {
     pub fn calc_subresource(&self, mip_level: UINT, layer: UINT, plane: UINT) -> UINT {
         mip_level + (layer * self.num_levels as UINT) + (plane * self.num_levels as UINT * self.kind.num_layers() as UINT)
     }
+
+    /// Check if the image has a depth-stencil format, which is stored in two planes.
+    pub fn is_depth_stencil(&self) -> bool {
+        self.surface_type.desc().aspects == format::Aspects::DEPTH | format::Aspects::STENCIL
+    }
+
+    /// Get the planes storing `aspects`.
+    ///
+    /// Depth-stencil formats store depth in the first and stencil in the second plane,
+    /// planar video formats store each plane aspect in the matching plane (the color
+    /// aspect covers all of them), all other formats have a single plane.
+    pub fn planes(&self, aspects: format::Aspects) -> Range<UINT> {
+        if self.surface_type.is_planar() {
+            if aspects.contains(format::Aspects::COLOR) {
+                return 0 .. 2;
+            }
+            let start = if aspects.contains(format::Aspects::PLANE_0) { 0 } else { 1 };
+            let end = if aspects.contains(format::Aspects::PLANE_1) { 2 } else { 1 };
+            return start .. end;
+        }
+        if !self.is_depth_stencil() {
+            return 0 .. 1;
+        }
+        let start = if aspects.contains(format::Aspects::DEPTH) { 0 } else { 1 };
+        let end = if aspects.contains(format::Aspects::STENCIL) { 2 } else { 1 };
+        start .. end
+    }
+
+    /// Get the surface type of a plane of a planar image.
+    fn planar_surface(&self, plane: UINT) -> Option<format::SurfaceType> {
+        let aspect = match plane {
+            0 => format::Aspects::PLANE_0,
+            _ => format::Aspects::PLANE_1,
+        };
+        self.surface_type.plane(aspect).map(|(surface, _)| surface)
+    }
+
+    /// Get the footprint format and bytes per block of a plane in buffer copies.
+    pub fn plane_copy_format(&self, plane: UINT) -> (DXGI_FORMAT, u32) {
+        if let Some(surface) = self.planar_surface(plane) {
+            let format = conv::map_format_typeless(surface).unwrap();
+            return (format, surface.desc().bits as u32 / 8);
+        }
+        match (self.is_depth_stencil(), plane) {
+            (true, 0) => (dxgiformat::DXGI_FORMAT_R32_TYPELESS, 4),
+            (true, _) => (dxgiformat::DXGI_FORMAT_R8_TYPELESS, 1),
+            (false, _) => (self.dxgi_format, self.bytes_per_block as _),
+        }
+    }
+
+    /// Get the format family of a plane.
+    ///
+    /// Image copies are possible between planes of the same family, which includes
+    /// copies between depth or stencil planes and color formats of the same size,
+    /// as well as between planes of video formats and the matching color formats.
+    pub fn plane_format_family(&self, plane: UINT) -> DXGI_FORMAT {
+        if self.is_depth_stencil() || self.surface_type.is_planar() {
+            self.plane_copy_format(plane).0
+        } else {
+            conv::map_format_typeless(self.surface_type).unwrap_or(self.dxgi_format)
+        }
+    }
 }
 
 #[derive(Copy, Derivative, Clone)]
@@ -196,6 +350,22 @@ pub struct ImageView {
     pub(crate) handle_dsv: Option<d3d12::D3D12_CPU_DESCRIPTOR_HANDLE>,
     #[derivative(Debug="ignore")]
     pub(crate) handle_uav: Option<d3d12::D3D12_CPU_DESCRIPTOR_HANDLE>,
+    // Single layer render target and depth stencil views of array views,
+    // used for clearing a subset of the layers.
+    #[derivative(Debug="ignore")]
+    pub(crate) layer_rtvs: Vec<d3d12::D3D12_CPU_DESCRIPTOR_HANDLE>,
+    #[derivative(Debug="ignore")]
+    pub(crate) layer_dsvs: Vec<d3d12::D3D12_CPU_DESCRIPTOR_HANDLE>,
+    // Image was created with `TRANSIENT_ATTACHMENT` usage, it may be placed in memory
+    // overlapping other transient images and is activated with an aliasing barrier
+    // when a render pass doesn't load its contents.
+    pub(crate) is_transient: bool,
+    // Subresources written when bound as attachment, the first level of each layer
+    // and plane of the view, merged into contiguous runs.
+    pub(crate) attachment_subresources: Vec<Range<UINT>>,
+    // Extent of the first level of the view.
+    pub(crate) extent: image::Extent,
+    pub(crate) tracking: Tracking,
 }
 unsafe impl Send for ImageView { }
 unsafe impl Sync for ImageView { }
@@ -267,6 +437,18 @@ pub struct DescriptorBindingInfo {
     pub(crate) view_range: Option<DescriptorRange>,
     pub(crate) sampler_range: Option<DescriptorRange>,
     pub(crate) is_uav: bool,
+    // GPU address of the last written buffer, used if the binding is promoted to a root descriptor.
+    pub(crate) root_address: Cell<d3d12::D3D12_GPU_VIRTUAL_ADDRESS>,
+}
+
+/// Tracked resource referenced by a descriptor, transitioned when binding the set.
+#[derive(Clone, Copy, Debug)]
+pub struct TrackedDescriptor {
+    pub(crate) binding: pso::DescriptorBinding,
+    pub(crate) array_index: u64,
+    pub(crate) resource: *mut d3d12::ID3D12Resource,
+    pub(crate) tracking: Tracking,
+    pub(crate) state: d3d12::D3D12_RESOURCE_STATES,
 }
 
 #[derive(Derivative)]
@@ -284,6 +466,13 @@ pub struct DescriptorSet {
     pub(crate) first_gpu_sampler: Option<d3d12::D3D12_GPU_DESCRIPTOR_HANDLE>,
     #[derivative(Debug="ignore")]
     pub(crate) first_gpu_view: Option<d3d12::D3D12_GPU_DESCRIPTOR_HANDLE>,
+
+    // Handles of the set inside the heaps, returned to the pool on `free_sets`.
+    pub(crate) srv_cbv_uav_handles: Range<u64>,
+    pub(crate) sampler_handles: Range<u64>,
+
+    // Tracked resources written to the set.
+    pub(crate) tracked: Mutex<Vec<TrackedDescriptor>>,
 }
 
 // TODO: is this really safe?
@@ -365,40 +554,178 @@ pub struct DescriptorHeapSlice {
     pub(crate) range: Range<u64>,
     pub(crate) start: DualHandle,
     pub(crate) handle_size: u64,
-    pub(crate) next: u64,
+    // Allocates handles inside `range`, offsets are relative to `range.start`.
+    allocator: free_list::Allocator,
 }
 
 impl DescriptorHeapSlice {
-    pub(crate) fn alloc_handles(&mut self, count: u64) -> Option<DualHandle> {
-        if self.next + count <= self.range.end {
-            let index = self.next;
-            self.next += count;
-            Some(DualHandle {
-                cpu: d3d12::D3D12_CPU_DESCRIPTOR_HANDLE { ptr: self.start.cpu.ptr + (self.handle_size * index) as usize },
-                gpu: d3d12::D3D12_GPU_DESCRIPTOR_HANDLE { ptr: self.start.gpu.ptr + (self.handle_size * index) as u64 },
-            })
-        } else {
-            None
+    pub(crate) fn new(
+        heap: ComPtr<d3d12::ID3D12DescriptorHeap>,
+        range: Range<u64>,
+        start: DualHandle,
+        handle_size: u64,
+    ) -> Self {
+        DescriptorHeapSlice {
+            heap,
+            allocator: free_list::Allocator::new(range.end - range.start),
+            range,
+            start,
+            handle_size,
+        }
+    }
+
+    /// Allocate a contiguous range of handles, returning the heap indices.
+    pub(crate) fn alloc_handles(&mut self, count: u64) -> Result<Range<u64>, pso::AllocationError> {
+        match self.allocator.allocate(count) {
+            Some(range) => Ok(self.range.start + range.start .. self.range.start + range.end),
+            None if self.allocator.stats().free >= count => Err(pso::AllocationError::FragmentedPool),
+            None => Err(pso::AllocationError::OutOfPoolMemory),
         }
     }
+
+    pub(crate) fn free_handles(&mut self, handles: Range<u64>) {
+        self.allocator.deallocate(handles.start - self.range.start .. handles.end - self.range.start);
+    }
+
+    pub(crate) fn at(&self, index: u64) -> DualHandle {
+        debug_assert!(index < self.range.end);
+        DualHandle {
+            cpu: d3d12::D3D12_CPU_DESCRIPTOR_HANDLE { ptr: self.start.cpu.ptr + (self.handle_size * index) as usize },
+            gpu: d3d12::D3D12_GPU_DESCRIPTOR_HANDLE { ptr: self.start.gpu.ptr + (self.handle_size * index) as u64 },
+        }
+    }
+
+    fn clear(&mut self) {
+        self.allocator = free_list::Allocator::new(self.range.end - self.range.start);
+    }
 }
 
-#[derive(Debug)]
+/// Fragmentation statistics of a descriptor pool, useful for debugging allocation failures.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DescriptorPoolStats {
+    /// Statistics of the CBV/SRV/UAV handles.
+    pub srv_cbv_uav: free_list::FragmentationStats,
+    /// Statistics of the sampler handles.
+    pub samplers: free_list::FragmentationStats,
+}
+
+/// Null descriptors for initializing the descriptors of new sets.
+///
+/// Resource binding tiers below 3 require all CBVs and UAVs of the bound
+/// descriptor tables to be initialized, even if they aren't accessed by the shaders.
+/// Tier 1 extends this to SRVs and samplers.
+#[derive(Derivative, Clone, Copy)]
+#[derivative(Debug)]
+pub(crate) struct NullDescriptors {
+    #[derivative(Debug="ignore")]
+    pub(crate) cbv: d3d12::D3D12_CPU_DESCRIPTOR_HANDLE,
+    #[derivative(Debug="ignore")]
+    pub(crate) srv_buffer: d3d12::D3D12_CPU_DESCRIPTOR_HANDLE,
+    #[derivative(Debug="ignore")]
+    pub(crate) srv_texture: d3d12::D3D12_CPU_DESCRIPTOR_HANDLE,
+    #[derivative(Debug="ignore")]
+    pub(crate) uav_buffer: d3d12::D3D12_CPU_DESCRIPTOR_HANDLE,
+    #[derivative(Debug="ignore")]
+    pub(crate) uav_texture: d3d12::D3D12_CPU_DESCRIPTOR_HANDLE,
+    #[derivative(Debug="ignore")]
+    pub(crate) sampler: d3d12::D3D12_CPU_DESCRIPTOR_HANDLE,
+    // Resource binding tier 1, SRVs and samplers need to be initialized as well.
+    pub(crate) tier_1: bool,
+}
+
+impl NullDescriptors {
+    fn initialize(&self, device: &ComPtr<d3d12::ID3D12Device>, bindings: &[DescriptorBindingInfo]) {
+        for binding in bindings {
+            if let Some(ref range) = binding.view_range {
+                let null = match range.ty {
+                    pso::DescriptorType::UniformBuffer |
+                    pso::DescriptorType::UniformBufferDynamic => Some(self.cbv),
+                    pso::DescriptorType::StorageBuffer |
+                    pso::DescriptorType::StorageTexelBuffer => Some(self.uav_buffer),
+                    pso::DescriptorType::StorageImage => Some(self.uav_texture),
+                    pso::DescriptorType::UniformTexelBuffer |
+                    pso::DescriptorType::AccelerationStructure if self.tier_1 => Some(self.srv_buffer),
+                    _ if self.tier_1 => Some(self.srv_texture),
+                    _ => None,
+                };
+                if let Some(null) = null {
+                    for i in 0 .. range.count {
+                        unsafe {
+                            device.CopyDescriptorsSimple(1, range.at(i), null, d3d12::D3D12_DESCRIPTOR_HEAP_TYPE_CBV_SRV_UAV);
+                        }
+                    }
+                }
+            }
+            if let Some(ref range) = binding.sampler_range {
+                if self.tier_1 {
+                    for i in 0 .. range.count {
+                        unsafe {
+                            device.CopyDescriptorsSimple(1, range.at(i), self.sampler, d3d12::D3D12_DESCRIPTOR_HEAP_TYPE_SAMPLER);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
 pub struct DescriptorPool {
+    #[derivative(Debug="ignore")]
+    pub(crate) device: ComPtr<d3d12::ID3D12Device>,
     pub(crate) heap_srv_cbv_uav: DescriptorHeapSlice,
     pub(crate) heap_sampler: DescriptorHeapSlice,
     pub(crate) pools: Vec<pso::DescriptorRangeDesc>,
     pub(crate) max_size: u64,
+    pub(crate) flags: pso::DescriptorPoolCreateFlags,
+    // Only required for resource binding tiers below 3.
+    pub(crate) null_descriptors: Option<NullDescriptors>,
 }
 unsafe impl Send for DescriptorPool {}
 unsafe impl Sync for DescriptorPool {}
 
+impl DescriptorPool {
+    /// Get the fragmentation statistics of the pool.
+    pub fn stats(&self) -> DescriptorPoolStats {
+        DescriptorPoolStats {
+            srv_cbv_uav: self.heap_srv_cbv_uav.allocator.stats(),
+            samplers: self.heap_sampler.allocator.stats(),
+        }
+    }
+}
+
 impl HalDescriptorPool<Backend> for DescriptorPool {
     fn allocate_set(&mut self, layout: &DescriptorSetLayout) -> Result<DescriptorSet, pso::AllocationError> {
         let mut binding_infos = Vec::new();
         let mut first_gpu_sampler = None;
         let mut first_gpu_view = None;
 
+        // Descriptor tables require the handles of a set to be contiguous,
+        // allocate them at once and hand them out to the bindings in order.
+        let (num_views, num_samplers) = layout.bindings
+            .iter()
+            .fold((0, 0), |(views, samplers), binding| {
+                let count = binding.count as u64;
+                // Immutable samplers are static samplers of the root signature.
+                let sampler_count = if binding.immutable_samplers.is_empty() { count } else { 0 };
+                match binding.ty {
+                    pso::DescriptorType::Sampler => (views, samplers + sampler_count),
+                    pso::DescriptorType::CombinedImageSampler => (views + count, samplers + sampler_count),
+                    _ => (views + count, samplers),
+                }
+            });
+        let srv_cbv_uav_handles = self.heap_srv_cbv_uav.alloc_handles(num_views)?;
+        let sampler_handles = match self.heap_sampler.alloc_handles(num_samplers) {
+            Ok(handles) => handles,
+            Err(err) => {
+                self.heap_srv_cbv_uav.free_handles(srv_cbv_uav_handles);
+                return Err(err);
+            }
+        };
+        let mut next_view = srv_cbv_uav_handles.start;
+        let mut next_sampler = sampler_handles.start;
+
         for binding in &layout.bindings {
             let (has_view, has_sampler, is_uav) = match binding.ty {
                 pso::DescriptorType::Sampler => (false, true, false),
@@ -406,7 +733,8 @@ impl HalDescriptorPool<Backend> for DescriptorPool {
                 pso::DescriptorType::InputAttachment |
                 pso::DescriptorType::SampledImage |
                 pso::DescriptorType::UniformTexelBuffer |
-                pso::DescriptorType::UniformBuffer => (true, false, false),
+                pso::DescriptorType::UniformBuffer |
+                pso::DescriptorType::AccelerationStructure => (true, false, false),
                 pso::DescriptorType::StorageImage |
                 pso::DescriptorType::StorageTexelBuffer |
                 pso::DescriptorType::StorageBuffer => (true, false, true),
@@ -419,8 +747,8 @@ impl HalDescriptorPool<Backend> for DescriptorPool {
             binding_infos[binding.binding as usize] = DescriptorBindingInfo {
                 count: binding.count as _,
                 view_range: if has_view {
-                    let handle = self.heap_srv_cbv_uav.alloc_handles(binding.count as u64)
-                        .ok_or(pso::AllocationError::OutOfPoolMemory)?;
+                    let handle = self.heap_srv_cbv_uav.at(next_view);
+                    next_view += binding.count as u64;
                     if first_gpu_view.is_none() {
                         first_gpu_view = Some(handle.gpu);
                     }
@@ -433,9 +761,9 @@ impl HalDescriptorPool<Backend> for DescriptorPool {
                 } else {
                     None
                 },
-                sampler_range: if has_sampler {
-                    let handle = self.heap_sampler.alloc_handles(binding.count as u64)
-                        .ok_or(pso::AllocationError::OutOfPoolMemory)?;
+                sampler_range: if has_sampler && binding.immutable_samplers.is_empty() {
+                    let handle = self.heap_sampler.at(next_sampler);
+                    next_sampler += binding.count as u64;
                     if first_gpu_sampler.is_none() {
                         first_gpu_sampler = Some(handle.gpu);
                     }
@@ -452,17 +780,39 @@ impl HalDescriptorPool<Backend> for DescriptorPool {
             };
         }
 
+        if let Some(ref null_descriptors) = self.null_descriptors {
+            null_descriptors.initialize(&self.device, &binding_infos);
+        }
+
         Ok(DescriptorSet {
             heap_srv_cbv_uav: self.heap_srv_cbv_uav.heap.clone(),
             heap_samplers: self.heap_sampler.heap.clone(),
             binding_infos,
             first_gpu_sampler,
             first_gpu_view,
+            srv_cbv_uav_handles,
+            sampler_handles,
+            tracked: Mutex::new(Vec::new()),
         })
     }
 
+    fn free_sets<I>(&mut self, descriptor_sets: I)
+    where
+        I: IntoIterator<Item = DescriptorSet>,
+    {
+        assert!(
+            self.flags.contains(pso::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET),
+            "Descriptor pool was not created with `FREE_DESCRIPTOR_SET`",
+        );
+        for set in descriptor_sets {
+            self.heap_srv_cbv_uav.free_handles(set.srv_cbv_uav_handles);
+            self.heap_sampler.free_handles(set.sampler_handles);
+        }
+    }
+
     fn reset(&mut self) {
-        unimplemented!()
+        self.heap_srv_cbv_uav.clear();
+        self.heap_sampler.clear();
     }
 }
 
@@ -472,7 +822,55 @@ pub struct QueryPool {
     #[derivative(Debug="ignore")]
     pub(crate) raw: ComPtr<d3d12::ID3D12QueryHeap>,
     pub(crate) ty: d3d12::D3D12_QUERY_HEAP_TYPE,
+    // Occlusion queries are always binary, matching `SetPredication`.
+    pub(crate) predication: bool,
 }
 
 unsafe impl Send for QueryPool {}
 unsafe impl Sync for QueryPool {}
+
+impl QueryPool {
+    /// Query type for resolving the results of the pool.
+    ///
+    /// Binary and precise occlusion queries both resolve to a single `u64`,
+    /// which allows resolving a range of either in one go.
+    pub(crate) fn resolve_type(&self) -> d3d12::D3D12_QUERY_TYPE {
+        match self.ty {
+            d3d12::D3D12_QUERY_HEAP_TYPE_OCCLUSION if self.predication =>
+                d3d12::D3D12_QUERY_TYPE_BINARY_OCCLUSION,
+            d3d12::D3D12_QUERY_HEAP_TYPE_OCCLUSION => d3d12::D3D12_QUERY_TYPE_OCCLUSION,
+            d3d12::D3D12_QUERY_HEAP_TYPE_TIMESTAMP => d3d12::D3D12_QUERY_TYPE_TIMESTAMP,
+            d3d12::D3D12_QUERY_HEAP_TYPE_PIPELINE_STATISTICS => d3d12::D3D12_QUERY_TYPE_PIPELINE_STATISTICS,
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct CommandSignature {
+    #[derivative(Debug="ignore")]
+    pub(crate) raw: ComPtr<d3d12::ID3D12CommandSignature>,
+    pub(crate) stride: u32,
+    // Sequences end with a dispatch instead of a draw.
+    pub(crate) dispatch: bool,
+    // Push constants and vertex buffers overwritten by the sequences,
+    // which need to be restored after execution.
+    pub(crate) constants: Vec<Range<u32>>,
+    pub(crate) vertex_buffers: bool,
+}
+
+unsafe impl Send for CommandSignature {}
+unsafe impl Sync for CommandSignature {}
+
+impl HalQueryPool for QueryPool {
+    fn result_stride(&self) -> buffer::Offset {
+        // Pipeline statistics are always resolved with all counters.
+        let size = match self.ty {
+            d3d12::D3D12_QUERY_HEAP_TYPE_PIPELINE_STATISTICS =>
+                mem::size_of::<d3d12::D3D12_QUERY_DATA_PIPELINE_STATISTICS>(),
+            _ => mem::size_of::<u64>(),
+        };
+        size as buffer::Offset
+    }
+}
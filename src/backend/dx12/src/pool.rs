@@ -1,22 +1,393 @@
 use wio::com::ComPtr;
-use std::ptr;
+use std::collections::{HashMap, VecDeque};
+use std::{mem, ptr};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, ThreadId};
 
 use winapi::um::d3d12;
-use winapi::shared::winerror::SUCCEEDED;
+use winapi::shared::winerror::{self, SUCCEEDED};
 
 use hal::{pool, command};
+use hal::queue::QueueFamilyId;
 use command::{CommandBuffer};
-use {Backend, CmdSignatures};
+use {Backend, CmdSignatures, ShaderVisibleHeaps};
+
+// Number of retired allocators which may still be referenced by executing
+// command lists. Resetting the pool blocks on the oldest one if exceeded.
+const MAX_ALLOCATORS_IN_FLIGHT: usize = 3;
+
+/// Allocator statistics of a command pool.
+///
+/// D3D12 doesn't expose the size of the memory backing a command allocator,
+/// an allocator keeps the memory of its largest recording until it's released.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AllocatorStats {
+    /// Number of allocators owned by the pool, including the active one.
+    pub allocators: usize,
+    /// Number of retired allocators still referenced by submitted command lists.
+    pub in_flight: usize,
+    /// Number of allocators created over the lifetime of the pool.
+    pub created: usize,
+    /// Number of allocators released by `trim`.
+    pub released: usize,
+}
+
+// Allocator state shared between the pool and its command buffers.
+//
+// Submissions signal the pool fence with increasing values, which allows to
+// recycle retired allocators once the GPU finished executing their lists.
+pub(crate) struct AllocatorState {
+    pub(crate) active: ComPtr<d3d12::ID3D12CommandAllocator>,
+    // Fence value of the latest submission of a list recorded with `active`.
+    active_value: u64,
+    // Retired allocators and the fence value they are in use until.
+    retired: VecDeque<(ComPtr<d3d12::ID3D12CommandAllocator>, u64)>,
+    fence: ComPtr<d3d12::ID3D12Fence>,
+    next_value: u64,
+    // Fence value of the latest submission of each list since it was last recorded.
+    submissions: HashMap<*mut d3d12::ID3D12GraphicsCommandList, u64>,
+    // Allocators of the recording threads, used instead of `active` by pools created
+    // with `CONCURRENT_RECORDING`.
+    threads: Option<ThreadAllocators<ComPtr<d3d12::ID3D12CommandAllocator>>>,
+    // Allocator each list was last recorded with.
+    recorded: HashMap<*mut d3d12::ID3D12GraphicsCommandList, *mut d3d12::ID3D12CommandAllocator>,
+    // List currently recording with an allocator, only one list may record at a time.
+    recording: HashMap<*mut d3d12::ID3D12CommandAllocator, *mut d3d12::ID3D12GraphicsCommandList>,
+    device: ComPtr<d3d12::ID3D12Device>,
+    list_type: d3d12::D3D12_COMMAND_LIST_TYPE,
+    stats: AllocatorStats,
+    // Upload buffers of `update_buffer` and the fence value they are in use until.
+    uploads: VecDeque<(ComPtr<d3d12::ID3D12Resource>, u64)>,
+}
+
+unsafe impl Send for AllocatorState { }
+
+impl AllocatorState {
+    // Signal the pool fence after the submission of a command list.
+    pub(crate) unsafe fn signal(
+        &mut self,
+        queue: &ComPtr<d3d12::ID3D12CommandQueue>,
+        list: *mut d3d12::ID3D12GraphicsCommandList,
+    ) {
+        self.next_value += 1;
+        assert_eq!(winerror::S_OK, queue.Signal(self.fence.as_raw(), self.next_value));
+        let value = self.next_value;
+        let allocator = self.recorded.get(&list).cloned();
+        let signaled = match (allocator, self.threads.as_mut()) {
+            (Some(allocator), Some(threads)) => threads.signal(|raw| raw.as_raw() == allocator, value),
+            _ => false,
+        };
+        if !signaled {
+            self.active_value = value;
+        }
+        self.submissions.insert(list, value);
+    }
+
+    // Start recording `list`, returns the allocator to record with.
+    //
+    // Pools with concurrent recording hand out one allocator per thread.
+    // Debug builds panic if another list is still recording with the allocator,
+    // release builds log a warning.
+    pub(crate) fn begin_recording(
+        &mut self,
+        list: *mut d3d12::ID3D12GraphicsCommandList,
+    ) -> ComPtr<d3d12::ID3D12CommandAllocator> {
+        let allocator = match self.threads {
+            Some(ref mut threads) => {
+                let stats = &mut self.stats;
+                let (device, list_type) = (&self.device, self.list_type);
+                threads.acquire(thread::current().id(), || {
+                    stats.allocators += 1;
+                    stats.created += 1;
+                    RawCommandPool::create_allocator(device, list_type)
+                })
+            }
+            None => self.active.clone(),
+        };
+
+        if let Some(&other) = self.recording.get(&allocator.as_raw()) {
+            if other != list {
+                let message = if self.threads.is_some() {
+                    "Command buffers of a pool can only be recorded one at a time per thread"
+                } else {
+                    "Command buffers of a pool can only be recorded one at a time, \
+                     create the pool with CONCURRENT_RECORDING to record on multiple threads"
+                };
+                if cfg!(debug_assertions) {
+                    panic!("{}", message);
+                }
+                warn!("{}", message);
+            }
+        }
+        self.recording.insert(allocator.as_raw(), list);
+        self.recorded.insert(list, allocator.as_raw());
+        allocator
+    }
+
+    // Finish recording `list`, the allocator can record another list afterwards.
+    pub(crate) fn end_recording(&mut self, list: *mut d3d12::ID3D12GraphicsCommandList) {
+        if let Some(allocator) = self.recorded.get(&list) {
+            if self.recording.get(allocator) == Some(&list) {
+                self.recording.remove(allocator);
+            }
+        }
+    }
+
+    // Fence value of the latest submission of `list` since it was recorded.
+    pub(crate) fn submission(&self, list: *mut d3d12::ID3D12GraphicsCommandList) -> Option<u64> {
+        self.submissions.get(&list).cloned()
+    }
+
+    // Forget the submissions of a list, called when it's recorded again or freed.
+    pub(crate) fn forget(&mut self, list: *mut d3d12::ID3D12GraphicsCommandList) {
+        self.submissions.remove(&list);
+    }
+
+    // Keep the upload buffers of the recording of `list` alive until its latest
+    // submission finished executing, called before the list is recorded again or freed.
+    pub(crate) fn retire_uploads(
+        &mut self,
+        list: *mut d3d12::ID3D12GraphicsCommandList,
+        uploads: Vec<ComPtr<d3d12::ID3D12Resource>>,
+    ) {
+        let completed = self.completed_value();
+        while self.uploads.front().map_or(false, |&(_, value)| value <= completed) {
+            self.uploads.pop_front();
+        }
+        match self.submission(list) {
+            Some(value) if value > completed => {
+                self.uploads.extend(uploads.into_iter().map(|upload| (upload, value)));
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) fn is_pending(&self, value: u64) -> bool {
+        value > self.completed_value()
+    }
+
+    // Make the queue wait on the GPU until the submission `value` finished executing.
+    pub(crate) unsafe fn wait(&self, queue: &ComPtr<d3d12::ID3D12CommandQueue>, value: u64) {
+        assert_eq!(winerror::S_OK, queue.Wait(self.fence.as_raw(), value));
+    }
+
+    fn completed_value(&self) -> u64 {
+        unsafe { self.fence.GetCompletedValue() }
+    }
+
+    // Get an allocator ready for recording in place of `allocator`, which is
+    // referenced by submissions until fence value `value`.
+    //
+    // The allocator is reset directly if the GPU finished executing its lists,
+    // otherwise it's retired and replaced by a recycled or new one.
+    fn renew(
+        &mut self,
+        allocator: ComPtr<d3d12::ID3D12CommandAllocator>,
+        value: u64,
+    ) -> ComPtr<d3d12::ID3D12CommandAllocator> {
+        let completed = self.completed_value();
+        if value <= completed {
+            // Fast path: no submitted list references the allocator anymore.
+            unsafe { allocator.Reset(); }
+            return allocator;
+        }
+
+        // Each recording thread may keep up to `MAX_ALLOCATORS_IN_FLIGHT` allocators in flight.
+        let max_in_flight = MAX_ALLOCATORS_IN_FLIGHT * (1 + self.threads.as_ref().map_or(0, |threads| threads.len()));
+        if self.retired.len() >= max_in_flight {
+            let oldest = self.retired.front().map_or(0, |&(_, value)| value);
+            if oldest > completed {
+                // Block until the oldest allocator is available again.
+                assert_eq!(winerror::S_OK, unsafe {
+                    self.fence.SetEventOnCompletion(oldest, ptr::null_mut())
+                });
+            }
+        }
+
+        let completed = self.completed_value();
+        let recycled = match self.retired.front() {
+            Some(&(_, value)) if value <= completed => self.retired.pop_front().map(|(allocator, _)| allocator),
+            _ => None,
+        };
+        let renewed = match recycled {
+            Some(allocator) => {
+                unsafe { allocator.Reset(); }
+                allocator
+            }
+            None => {
+                self.stats.allocators += 1;
+                self.stats.created += 1;
+                RawCommandPool::create_allocator(&self.device, self.list_type)
+            }
+        };
+
+        self.retired.push_back((allocator, value));
+        renewed
+    }
+
+    fn in_flight(&self) -> usize {
+        let completed = self.completed_value();
+        self.retired.iter().filter(|&&(_, value)| value > completed).count()
+    }
+}
+
+// Allocators of the recording threads of a pool with concurrent recording.
+//
+// Threads record with their own allocator, created on their first recording. Allocators
+// of threads which stopped recording are evicted by `trim` and `reset` of the pool,
+// otherwise the map would grow with every thread that ever recorded.
+pub(crate) struct ThreadAllocators<A> {
+    allocators: HashMap<ThreadId, ThreadAllocator<A>>,
+}
+
+struct ThreadAllocator<A> {
+    raw: A,
+    // Fence value of the latest submission of a list recorded with the allocator.
+    value: u64,
+    // A list was recorded with the allocator since the last reset of the pool.
+    used: bool,
+}
+
+impl<A: Clone> ThreadAllocators<A> {
+    fn new() -> Self {
+        ThreadAllocators {
+            allocators: HashMap::new(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.allocators.len()
+    }
+
+    // Get the allocator of `thread` for recording, created with `create` if the
+    // thread has none.
+    fn acquire<F: FnOnce() -> A>(&mut self, thread: ThreadId, create: F) -> A {
+        let allocator = self.allocators
+            .entry(thread)
+            .or_insert_with(|| ThreadAllocator {
+                raw: create(),
+                value: 0,
+                used: false,
+            });
+        allocator.used = true;
+        allocator.raw.clone()
+    }
+
+    // Store the fence value of a submission of a list recorded with the allocator
+    // matching `recorded_with`, returns `false` if no thread owns the allocator.
+    fn signal<P: Fn(&A) -> bool>(&mut self, recorded_with: P, value: u64) -> bool {
+        match self.allocators.values_mut().find(|allocator| recorded_with(&allocator.raw)) {
+            Some(allocator) => {
+                allocator.value = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Evict the allocators which didn't record since the last reset, returns their number.
+    //
+    // These allocators don't hold any commands, the lists recorded with them
+    // before were invalidated by the reset.
+    fn evict_unused(&mut self) -> usize {
+        let count = self.allocators.len();
+        self.allocators.retain(|_, allocator| allocator.used);
+        count - self.allocators.len()
+    }
+
+    // Reset the allocators with `renew` for the next recordings, which gets the allocator
+    // and the fence value of its latest submission. Allocators of threads which didn't
+    // record since the previous reset are evicted, returns their number.
+    fn reset<F: FnMut(A, u64) -> A>(&mut self, mut renew: F) -> usize {
+        let evicted = self.evict_unused();
+        let allocators = mem::replace(&mut self.allocators, HashMap::new());
+        self.allocators = allocators
+            .into_iter()
+            .map(|(thread, allocator)| (thread, ThreadAllocator {
+                raw: renew(allocator.raw, allocator.value),
+                value: 0,
+                used: false,
+            }))
+            .collect();
+        evicted
+    }
+}
 
 pub struct RawCommandPool {
-    pub(crate) inner: ComPtr<d3d12::ID3D12CommandAllocator>,
+    pub(crate) allocators: Arc<Mutex<AllocatorState>>,
     pub(crate) device: ComPtr<d3d12::ID3D12Device>,
     pub(crate) list_type: d3d12::D3D12_COMMAND_LIST_TYPE,
+    pub(crate) family: QueueFamilyId,
     pub(crate) signatures: CmdSignatures,
+    pub(crate) heaps: ShaderVisibleHeaps,
+    pub(crate) native_render_passes: bool,
 }
 
 impl RawCommandPool {
+    pub(crate) fn new(
+        device: ComPtr<d3d12::ID3D12Device>,
+        fence: ComPtr<d3d12::ID3D12Fence>,
+        list_type: d3d12::D3D12_COMMAND_LIST_TYPE,
+        family: QueueFamilyId,
+        signatures: CmdSignatures,
+        heaps: ShaderVisibleHeaps,
+        native_render_passes: bool,
+        concurrent_recording: bool,
+    ) -> Self {
+        let active = Self::create_allocator(&device, list_type);
+        let state = AllocatorState {
+            active,
+            active_value: 0,
+            retired: VecDeque::new(),
+            fence,
+            next_value: 0,
+            submissions: HashMap::new(),
+            threads: if concurrent_recording { Some(ThreadAllocators::new()) } else { None },
+            recorded: HashMap::new(),
+            recording: HashMap::new(),
+            device: device.clone(),
+            list_type,
+            stats: AllocatorStats {
+                allocators: 1,
+                created: 1,
+                .. AllocatorStats::default()
+            },
+            uploads: VecDeque::new(),
+        };
+
+        RawCommandPool {
+            allocators: Arc::new(Mutex::new(state)),
+            device,
+            list_type,
+            family,
+            signatures,
+            heaps,
+            native_render_passes,
+        }
+    }
+
+    fn create_allocator(
+        device: &ComPtr<d3d12::ID3D12Device>,
+        list_type: d3d12::D3D12_COMMAND_LIST_TYPE,
+    ) -> ComPtr<d3d12::ID3D12CommandAllocator> {
+        let mut command_allocator: *mut d3d12::ID3D12CommandAllocator = ptr::null_mut();
+        let hr = unsafe {
+            device.CreateCommandAllocator(
+                list_type,
+                &d3d12::IID_ID3D12CommandAllocator,
+                &mut command_allocator as *mut *mut _ as *mut *mut _,
+            )
+        };
+        // TODO: error handling
+        if !SUCCEEDED(hr) {
+            error!("error on command allocator creation: {:x}", hr);
+        }
+
+        unsafe { ComPtr::from_raw(command_allocator) }
+    }
+
     fn create_command_list(&mut self) -> ComPtr<d3d12::ID3D12GraphicsCommandList> {
+        let allocator = self.allocators.lock().unwrap().active.clone();
         // allocate command lists
         let command_list = {
             let mut command_list: *mut d3d12::ID3D12GraphicsCommandList = ptr::null_mut();
@@ -24,7 +395,7 @@ impl RawCommandPool {
                 self.device.CreateCommandList(
                     0, // single gpu only atm
                     self.list_type,
-                    self.inner.as_raw(),
+                    allocator.as_raw(),
                     ptr::null_mut(),
                     &d3d12::IID_ID3D12GraphicsCommandList,
                     &mut command_list as *mut *mut _ as *mut *mut _,
@@ -45,6 +416,15 @@ impl RawCommandPool {
 
         command_list
     }
+
+    /// Get the allocator statistics of the pool.
+    pub fn allocator_stats(&self) -> AllocatorStats {
+        let state = self.allocators.lock().unwrap();
+        AllocatorStats {
+            in_flight: state.in_flight(),
+            .. state.stats
+        }
+    }
 }
 
 unsafe impl Send for RawCommandPool { }
@@ -52,23 +432,170 @@ unsafe impl Sync for RawCommandPool { }
 
 impl pool::RawCommandPool<Backend> for RawCommandPool {
     fn reset(&mut self) {
-        unsafe { self.inner.Reset(); }
+        let mut state = self.allocators.lock().unwrap();
+        // Resetting the pool ends all recordings.
+        state.recording.clear();
+
+        let active = state.active.clone();
+        let active_value = state.active_value;
+        state.active = state.renew(active, active_value);
+        state.active_value = 0;
+
+        // Allocators of the recording threads of pools with concurrent recording.
+        if let Some(mut threads) = state.threads.take() {
+            let evicted = threads.reset(|allocator, value| state.renew(allocator, value));
+            state.stats.allocators -= evicted;
+            state.stats.released += evicted;
+            state.threads = Some(threads);
+        }
+    }
+
+    fn trim(&mut self) {
+        // Release retired allocators which are not referenced by executing
+        // command lists anymore, returning their memory to the system.
+        let mut guard = self.allocators.lock().unwrap();
+        let state = &mut *guard;
+        let completed = state.completed_value();
+        let num_retired = state.retired.len();
+        state.retired.retain(|&(_, value)| value > completed);
+        // Threads which didn't record since the last reset keep empty allocators.
+        let evicted = state.threads.as_mut().map_or(0, |threads| threads.evict_unused());
+        let released = num_retired - state.retired.len() + evicted;
+        state.stats.allocators -= released;
+        state.stats.released += released;
     }
 
     fn allocate(
-        &mut self, num: usize, level: command::RawLevel
-    ) -> Vec<CommandBuffer> { // TODO: Implement secondary buffers
-        assert_eq!(level, command::RawLevel::Primary);
+        &mut self, num: usize, _level: command::RawLevel
+    ) -> Vec<CommandBuffer> {
+        // Secondary command buffers are direct command lists as well, see `execute_commands`.
         (0..num)
             .map(|_| CommandBuffer::new(
                 self.create_command_list(),
-                self.inner.clone(),
+                self.device.clone(),
+                self.allocators.clone(),
                 self.signatures.clone(),
+                self.heaps.clone(),
+                self.list_type,
+                self.family,
+                self.native_render_passes,
             ))
             .collect()
     }
 
-    unsafe fn free(&mut self, _cbufs: Vec<CommandBuffer>) {
-        // Just let the command buffers drop
+    unsafe fn free(&mut self, cbufs: Vec<CommandBuffer>) {
+        let mut state = self.allocators.lock().unwrap();
+        for mut cbuf in cbufs {
+            let list = cbuf.raw_list();
+            state.retire_uploads(list, mem::replace(&mut cbuf.uploads, Vec::new()));
+            state.forget(list);
+            state.end_recording(list);
+            state.recorded.remove(&list);
+            cbuf.transient_views.lock().unwrap().free();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ThreadAllocators;
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    fn record_on_threads(
+        threads: &Arc<Mutex<ThreadAllocators<usize>>>,
+        created: &Arc<AtomicUsize>,
+        num_threads: usize,
+    ) -> Vec<(usize, usize)> {
+        let handles = (0 .. num_threads)
+            .map(|_| {
+                let threads = threads.clone();
+                let created = created.clone();
+                thread::spawn(move || {
+                    let id = thread::current().id();
+                    let acquire = || threads
+                        .lock()
+                        .unwrap()
+                        .acquire(id, || created.fetch_add(1, Ordering::SeqCst));
+                    (acquire(), acquire())
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_concurrent_acquire() {
+        let threads = Arc::new(Mutex::new(ThreadAllocators::new()));
+        let created = Arc::new(AtomicUsize::new(0));
+        let mut allocators = record_on_threads(&threads, &created, 8);
+
+        // Each thread records with its own allocator.
+        for &(first, second) in &allocators {
+            assert_eq!(first, second);
+        }
+        allocators.sort();
+        allocators.dedup();
+        assert_eq!(8, allocators.len());
+        assert_eq!(8, created.load(Ordering::SeqCst));
+        assert_eq!(8, threads.lock().unwrap().len());
+    }
+
+    #[test]
+    fn test_reset_evicts_idle_threads() {
+        let threads = Arc::new(Mutex::new(ThreadAllocators::new()));
+        let created = Arc::new(AtomicUsize::new(0));
+        record_on_threads(&threads, &created, 4);
+        let main = threads.lock().unwrap().acquire(thread::current().id(), || 100);
+
+        let mut renewed = Vec::new();
+        assert_eq!(0, threads.lock().unwrap().reset(|allocator, _| {
+            renewed.push(allocator);
+            allocator
+        }));
+        renewed.sort();
+        assert_eq!(vec![0, 1, 2, 3, 100], renewed);
+
+        // Only the main thread records after the reset, the exited threads are evicted.
+        assert_eq!(main, threads.lock().unwrap().acquire(thread::current().id(), || 200));
+        assert_eq!(4, threads.lock().unwrap().reset(|allocator, _| allocator));
+        assert_eq!(1, threads.lock().unwrap().len());
+    }
+
+    #[test]
+    fn test_trim_evicts_unused() {
+        let threads = Arc::new(Mutex::new(ThreadAllocators::new()));
+        let created = Arc::new(AtomicUsize::new(0));
+        record_on_threads(&threads, &created, 4);
+
+        // Allocators which recorded since the last reset may be referenced by lists.
+        assert_eq!(0, threads.lock().unwrap().evict_unused());
+        threads.lock().unwrap().reset(|allocator, _| allocator);
+        assert_eq!(4, threads.lock().unwrap().evict_unused());
+        assert_eq!(0, threads.lock().unwrap().len());
+
+        // Threads get a new allocator when recording again.
+        record_on_threads(&threads, &created, 2);
+        assert_eq!(6, created.load(Ordering::SeqCst));
+        assert_eq!(2, threads.lock().unwrap().len());
+    }
+
+    #[test]
+    fn test_signal() {
+        let mut threads = ThreadAllocators::new();
+        let allocator = threads.acquire(thread::current().id(), || 7);
+        assert!(threads.signal(|&raw| raw == allocator, 5));
+        assert!(!threads.signal(|&raw| raw == 8, 6));
+
+        let mut values = Vec::new();
+        threads.reset(|allocator, value| {
+            values.push(value);
+            allocator
+        });
+        assert_eq!(vec![5], values);
     }
 }
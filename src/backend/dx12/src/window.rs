@@ -1,19 +1,26 @@
 use std::collections::VecDeque;
 use std::mem;
+use std::sync::Mutex;
 
-#[cfg(feature = "winit")]
+#[cfg(all(feature = "window", feature = "winit"))]
 use winit;
 
-use winapi::shared::dxgi1_4;
-use winapi::shared::windef::{HWND, RECT};
+use winapi::shared::{dxgi, dxgi1_4, winerror};
+use winapi::shared::windef::HWND;
+use winapi::um::profileapi::QueryPerformanceFrequency;
+#[cfg(feature = "window")]
+use winapi::shared::windef::RECT;
+#[cfg(feature = "window")]
 use winapi::um::winuser::GetClientRect;
 use wio::com::ComPtr;
 
 use hal::{self, format as f, image as i};
 use {native as n, Backend, Instance, PhysicalDevice, QueueFamily};
 
+#[cfg(feature = "window")]
 use std::os::raw::c_void;
 
+#[cfg(feature = "window")]
 impl Instance {
     pub fn create_surface_from_hwnd(&self, hwnd: *mut c_void) -> Surface {
         let (width, height) = unsafe {
@@ -26,12 +33,22 @@ impl Instance {
 
         Surface {
             factory: self.factory.clone(),
-            wnd_handle: hwnd as *mut _,
+            target: SurfaceTarget::Window(hwnd as *mut _),
             width: width,
             height: height,
         }
     }
 
+    /// Create a surface from the raw handle of a native window.
+    ///
+    /// Panics if the handle isn't a Win32 window handle.
+    pub fn create_surface_from_raw_handle<W: hal::HasRawWindowHandle>(&self, window: &W) -> Surface {
+        match window.raw_window_handle() {
+            hal::RawWindowHandle::Windows { hwnd, .. } => self.create_surface_from_hwnd(hwnd),
+            handle => panic!("Unsupported window handle: {:?}", handle),
+        }
+    }
+
     #[cfg(feature = "winit")]
     pub fn create_surface(&self, window: &winit::Window) -> Surface {
         use winit::os::windows::WindowExt;
@@ -39,13 +56,51 @@ impl Instance {
     }
 }
 
+impl Instance {
+    /// Create a surface for presenting to a DirectComposition visual tree.
+    ///
+    /// Swapchains created from this surface are not bound to a window. Instead,
+    /// the application attaches them to one of its visuals by passing
+    /// `Swapchain::as_raw` to `IDCompositionVisual::SetContent`.
+    /// Composition swapchains use premultiplied alpha.
+    pub fn create_surface_for_composition(&self, width: i::Size, height: i::Size) -> Surface {
+        Surface {
+            factory: self.factory.clone(),
+            target: SurfaceTarget::Composition,
+            width,
+            height,
+        }
+    }
+}
+
+/// Presentation target of a surface.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum SurfaceTarget {
+    /// Swapchains present to a native window.
+    Window(HWND),
+    /// Swapchains are attached to a DirectComposition visual by the application.
+    Composition,
+}
+
 pub struct Surface {
     pub(crate) factory: ComPtr<dxgi1_4::IDXGIFactory4>,
-    pub(crate) wnd_handle: HWND,
+    pub(crate) target: SurfaceTarget,
     pub(crate) width: i::Size,
     pub(crate) height: i::Size,
 }
 
+impl Surface {
+    /// Set the extent of swapchains created from a composition surface.
+    ///
+    /// Composition surfaces aren't bound to a window, which defines the extent otherwise.
+    /// Swapchains need to be recreated to take the new extent into account.
+    pub fn set_composition_extent(&mut self, width: i::Size, height: i::Size) {
+        assert_eq!(self.target, SurfaceTarget::Composition, "Only composition surfaces can be resized");
+        self.width = width;
+        self.height = height;
+    }
+}
+
 unsafe impl Send for Surface { }
 unsafe impl Sync for Surface { }
 
@@ -79,7 +134,7 @@ impl hal::Surface<Backend> for Surface {
         // Sticking to FLIP swap effects for the moment.
         // We also expose sRGB buffers but they are handled internally as UNORM.
         // Roughly ordered by popularity..
-        let formats = vec![
+        let mut formats = vec![
             f::Format::Bgra8Srgb,
             f::Format::Bgra8Unorm,
             f::Format::Rgba8Srgb,
@@ -87,6 +142,10 @@ impl hal::Surface<Backend> for Surface {
             f::Format::A2b10g10r10Unorm,
             f::Format::Rgba16Float,
         ];
+        if self.target == SurfaceTarget::Composition {
+            // Composition swapchains don't support 10-bit formats.
+            formats.retain(|&format| format != f::Format::A2b10g10r10Unorm);
+        }
 
         (capabilities, Some(formats))
     }
@@ -98,6 +157,19 @@ pub struct Swapchain {
     pub(crate) frame_queue: VecDeque<usize>,
     #[allow(dead_code)]
     pub(crate) rtv_heap: n::DescriptorHeap,
+    // Refresh count and time of the last queried vertical blank,
+    // for measuring the refresh duration.
+    pub(crate) last_sync: Mutex<Option<(u32, u64)>>,
+}
+
+impl Swapchain {
+    /// Get the raw DXGI swapchain.
+    ///
+    /// Swapchains of composition surfaces are attached to a visual with
+    /// `IDCompositionVisual::SetContent`.
+    pub fn as_raw(&self) -> *mut dxgi1_4::IDXGISwapChain3 {
+        self.inner.as_raw()
+    }
 }
 
 impl hal::Swapchain<Backend> for Swapchain {
@@ -117,6 +189,48 @@ impl hal::Swapchain<Backend> for Swapchain {
         let index = unsafe { self.inner.GetCurrentBackBufferIndex() };
         hal::Frame::new(index as usize)
     }
+
+    fn frame_statistics(&self) -> Option<hal::FrameStatistics> {
+        let mut stats: dxgi::DXGI_FRAME_STATISTICS = unsafe { mem::zeroed() };
+        let hr = unsafe { self.inner.GetFrameStatistics(&mut stats) };
+        if !winerror::SUCCEEDED(hr) {
+            // Fails with `DXGI_ERROR_FRAME_STATISTICS_DISJOINT` until a frame
+            // has been displayed and after mode changes.
+            return None;
+        }
+
+        let (counter, frequency) = unsafe {
+            let mut frequency = mem::zeroed();
+            QueryPerformanceFrequency(&mut frequency);
+            (*stats.SyncQPCTime.QuadPart(), *frequency.QuadPart())
+        };
+        if frequency <= 0 {
+            return None;
+        }
+        // Split the conversion to avoid overflowing the nanoseconds.
+        let (seconds, rest) = (counter as u64 / frequency as u64, counter as u64 % frequency as u64);
+        let sync_time = seconds * 1_000_000_000 + rest * 1_000_000_000 / frequency as u64;
+
+        let refresh_duration = {
+            let mut last_sync = self.last_sync.lock().unwrap();
+            let duration = match *last_sync {
+                Some((refresh_count, time)) if stats.SyncRefreshCount != refresh_count && sync_time > time => {
+                    Some((sync_time - time) / stats.SyncRefreshCount.wrapping_sub(refresh_count) as u64)
+                }
+                _ => None,
+            };
+            *last_sync = Some((stats.SyncRefreshCount, sync_time));
+            duration
+        };
+
+        Some(hal::FrameStatistics {
+            present_count: stats.PresentCount,
+            present_refresh_count: stats.PresentRefreshCount,
+            sync_refresh_count: stats.SyncRefreshCount,
+            sync_time,
+            refresh_duration,
+        })
+    }
 }
 
 unsafe impl Send for Swapchain { }
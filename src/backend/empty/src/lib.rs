@@ -6,7 +6,7 @@ extern crate gfx_hal as hal;
 use std::borrow::{Borrow, BorrowMut};
 use std::ops::Range;
 use hal::{
-    buffer, command, device, error, format, image, mapping,
+    accel_struct, buffer, command, device, error, format, image, indirect, mapping,
     memory, pass, pool, pso, query, queue,
 };
 use hal::range::RangeArg;
@@ -49,7 +49,11 @@ impl hal::Backend for Backend {
 
     type Fence = ();
     type Semaphore = ();
-    type QueryPool = ();
+    type QueryPool = QueryPool;
+    type CommandSignature = ();
+
+    type AccelerationStructure = ();
+    type RayTracingPipeline = ();
 }
 
 /// Dummy physical device.
@@ -212,7 +216,7 @@ impl hal::Device<Backend> for Device {
         unimplemented!()
     }
 
-    fn create_descriptor_pool<I>(&self, _: usize, _: I) -> DescriptorPool
+    fn create_descriptor_pool<I>(&self, _: usize, _: I, _: pso::DescriptorPoolCreateFlags) -> DescriptorPool
     where
         I: IntoIterator,
         I::Item: Borrow<pso::DescriptorRangeDesc>,
@@ -257,11 +261,24 @@ impl hal::Device<Backend> for Device {
         unimplemented!()
     }
 
-    fn create_query_pool(&self, _: query::QueryType, _: u32) -> () {
+    fn create_query_pool(&self, _: query::QueryType, _: u32, _: query::QueryPoolCreateFlags) -> QueryPool {
+        unimplemented!()
+    }
+
+    fn destroy_query_pool(&self, _: QueryPool) {
         unimplemented!()
     }
 
-    fn destroy_query_pool(&self, _: ()) {
+    fn create_command_signature(
+        &self,
+        _: &[indirect::Argument],
+        _: u32,
+        _: Option<&()>,
+    ) -> Result<(), indirect::CreationError> {
+        unimplemented!()
+    }
+
+    fn destroy_command_signature(&self, _: ()) {
         unimplemented!()
     }
 
@@ -312,10 +329,47 @@ impl hal::Device<Backend> for Device {
     fn destroy_compute_pipeline(&self, _: ()) {
         unimplemented!()
     }
+
+    fn create_ray_tracing_pipeline<'a>(
+        &self, _: &pso::RayTracingPipelineDesc<'a, Backend>
+    ) -> Result<(), pso::CreationError> {
+        unimplemented!()
+    }
+
+    fn destroy_ray_tracing_pipeline(&self, _: ()) {
+        unimplemented!()
+    }
+
+    fn get_ray_tracing_shader_group_handles(&self, _: &(), _: Range<u32>) -> Vec<u8> {
+        unimplemented!()
+    }
+
+    fn get_acceleration_structure_prebuild_info<'a>(
+        &self, _: &accel_struct::BuildDesc<'a, Backend>
+    ) -> accel_struct::PrebuildInfo {
+        unimplemented!()
+    }
+
+    fn create_acceleration_structure<'a>(
+        &self, _: &accel_struct::Desc<'a, Backend>
+    ) -> Result<(), accel_struct::CreationError> {
+        unimplemented!()
+    }
+
+    fn get_acceleration_structure_address(&self, _: &()) -> u64 {
+        unimplemented!()
+    }
+
+    fn destroy_acceleration_structure(&self, _: ()) {
+        unimplemented!()
+    }
     fn destroy_framebuffer(&self, _: ()) {
         unimplemented!()
     }
 
+    fn get_buffer_address(&self, _: &()) -> u64 {
+        unimplemented!()
+    }
     fn destroy_buffer(&self, _: ()) {
         unimplemented!()
     }
@@ -352,7 +406,7 @@ impl hal::Device<Backend> for Device {
         &self,
         _: &mut Surface,
         _: hal::SwapchainConfig,
-    ) -> (Swapchain, hal::Backbuffer<Backend>) {
+    ) -> Result<(Swapchain, hal::Backbuffer<Backend>), hal::window::CreationError> {
         unimplemented!()
     }
 
@@ -403,7 +457,7 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         unimplemented!()
     }
 
-    fn finish(&mut self) {
+    fn finish(&mut self) -> Result<(), command::RecordingError> {
         unimplemented!()
     }
 
@@ -456,7 +510,7 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         T: IntoIterator,
         T::Item: Borrow<command::AttachmentClear>,
         U: IntoIterator,
-        U::Item: Borrow<pso::Rect>,
+        U::Item: Borrow<pso::ClearRect>,
     {
         unimplemented!()
     }
@@ -524,6 +578,14 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         unimplemented!()
     }
 
+    fn set_depth_bounds(&mut self, _: Range<f32>) {
+        unimplemented!()
+    }
+
+    fn set_line_width(&mut self, _: f32) {
+        unimplemented!()
+    }
+
 
     fn begin_render_pass_raw<T>(
         &mut self,
@@ -547,6 +609,22 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         unimplemented!()
     }
 
+    fn begin_rendering<'a, T>(
+        &mut self,
+        _: pso::Rect,
+        _: T,
+        _: Option<pass::RenderingAttachment<'a, Backend>>,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<pass::RenderingAttachment<'a, Backend>>,
+    {
+        unimplemented!()
+    }
+
+    fn end_rendering(&mut self) {
+        unimplemented!()
+    }
+
     fn bind_graphics_pipeline(&mut self, _: &()) {
         unimplemented!()
     }
@@ -575,7 +653,29 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         unimplemented!()
     }
 
-    fn dispatch_indirect(&mut self, _: &(), _: buffer::Offset) {
+    fn dispatch_indirect(&mut self, _: &(), _: buffer::Offset, _: u32) {
+        unimplemented!()
+    }
+
+    fn build_acceleration_structure<'a>(
+        &mut self, _: &accel_struct::BuildDesc<'a, Backend>, _: &(), _: (&(), buffer::Offset)
+    ) {
+        unimplemented!()
+    }
+
+    fn bind_ray_tracing_pipeline(&mut self, _: &()) {
+        unimplemented!()
+    }
+
+    fn bind_ray_tracing_descriptor_sets<I>(&mut self, _: &(), _: usize, _: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<()>,
+    {
+        unimplemented!()
+    }
+
+    fn trace_rays<'a>(&mut self, _: &accel_struct::ShaderBindingTables<'a, Backend>, _: [u32; 3]) {
         unimplemented!()
     }
 
@@ -657,6 +757,10 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         unimplemented!()
     }
 
+    fn execute_indirect(&mut self, _: &(), _: &(), _: buffer::Offset, _: u32) {
+        unimplemented!()
+    }
+
     fn begin_query(
         &mut self,
         _: query::Query<Backend>,
@@ -674,7 +778,7 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
 
     fn reset_query_pool(
         &mut self,
-        _: &(),
+        _: &QueryPool,
         _: Range<query::QueryId>,
     ) {
         unimplemented!()
@@ -688,6 +792,18 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         unimplemented!()
     }
 
+    fn copy_query_pool_results(
+        &mut self,
+        _: &QueryPool,
+        _: Range<query::QueryId>,
+        _: &(),
+        _: buffer::Offset,
+        _: buffer::Offset,
+        _: query::ResultFlags,
+    ) {
+        unimplemented!()
+    }
+
     fn push_graphics_constants(
         &mut self,
         _: &(),
@@ -728,6 +844,15 @@ impl pso::DescriptorPool<Backend> for DescriptorPool {
     }
 }
 
+// Dummy query pool.
+#[derive(Debug)]
+pub struct QueryPool;
+impl query::QueryPool for QueryPool {
+    fn result_stride(&self) -> buffer::Offset {
+        unimplemented!()
+    }
+}
+
 /// Dummy surface.
 pub struct Surface;
 impl hal::Surface<Backend> for Surface {
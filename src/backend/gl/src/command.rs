@@ -2,7 +2,7 @@
 
 use gl;
 
-use hal::{self, buffer, command, image, memory, pass, pso, query, ColorSlot};
+use hal::{self, accel_struct, buffer, command, image, memory, pass, pso, query, ColorSlot};
 use hal::format::ChannelType;
 
 use {native as n, Backend};
@@ -78,6 +78,7 @@ pub enum Command {
     },
     SetScissors(u32, BufferSlice),
     SetBlendColor(pso::ColorValue),
+    SetLineWidth(f32),
 
     /// Clear floating-point color drawbuffer of bound framebuffer.
     ClearBufferColorF(DrawBuffer, [f32; 4]),
@@ -486,8 +487,9 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         }
     }
 
-    fn finish(&mut self) {
+    fn finish(&mut self) -> Result<(), command::RecordingError> {
         // no-op
+        Ok(())
     }
 
     fn reset(&mut self, _release_resources: bool) {
@@ -605,6 +607,22 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         // TODO
     }
 
+    fn begin_rendering<'a, T>(
+        &mut self,
+        _render_area: pso::Rect,
+        _colors: T,
+        _depth_stencil: Option<pass::RenderingAttachment<'a, Backend>>,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<pass::RenderingAttachment<'a, Backend>>,
+    {
+        unimplemented!()
+    }
+
+    fn end_rendering(&mut self) {
+        unimplemented!()
+    }
+
     fn clear_color_image_raw(
         &mut self,
         image: &n::Image,
@@ -652,7 +670,7 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         T: IntoIterator,
         T::Item: Borrow<command::AttachmentClear>,
         U: IntoIterator,
-        U::Item: Borrow<pso::Rect>,
+        U::Item: Borrow<pso::ClearRect>,
     {
         unimplemented!()
     }
@@ -791,6 +809,14 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         }
     }
 
+    fn set_depth_bounds(&mut self, _: Range<f32>) {
+        warn!("Depth bounds test is not supported");
+    }
+
+    fn set_line_width(&mut self, width: f32) {
+        self.push_cmd(Command::SetLineWidth(width));
+    }
+
     fn bind_graphics_pipeline(&mut self, pipeline: &n::GraphicsPipeline) {
         let n::GraphicsPipeline {
             primitive,
@@ -863,8 +889,35 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         self.push_cmd(Command::Dispatch(count));
     }
 
-    fn dispatch_indirect(&mut self, buffer: &n::Buffer, offset: buffer::Offset) {
-        self.push_cmd(Command::DispatchIndirect(buffer.raw, offset));
+    fn dispatch_indirect(&mut self, buffer: &n::Buffer, offset: buffer::Offset, dispatch_count: u32) {
+        for i in 0 .. dispatch_count as buffer::Offset {
+            self.push_cmd(Command::DispatchIndirect(buffer.raw, offset + i * 12));
+        }
+    }
+
+    fn build_acceleration_structure<'a>(
+        &mut self,
+        _desc: &accel_struct::BuildDesc<'a, Backend>,
+        _dst: &(),
+        _scratch: (&n::Buffer, buffer::Offset),
+    ) {
+        unimplemented!()
+    }
+
+    fn bind_ray_tracing_pipeline(&mut self, _pipeline: &()) {
+        unimplemented!()
+    }
+
+    fn bind_ray_tracing_descriptor_sets<T>(&mut self, _layout: &n::PipelineLayout, _first_set: usize, _sets: T)
+    where
+        T: IntoIterator,
+        T::Item: Borrow<n::DescriptorSet>,
+    {
+        unimplemented!()
+    }
+
+    fn trace_rays<'a>(&mut self, _tables: &accel_struct::ShaderBindingTables<'a, Backend>, _count: [u32; 3]) {
+        unimplemented!()
     }
 
     fn copy_buffer<T>(&mut self, src: &n::Buffer, dst: &n::Buffer, regions: T)
@@ -1032,6 +1085,16 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         unimplemented!()
     }
 
+    fn execute_indirect(
+        &mut self,
+        _signature: &(),
+        _buffer: &n::Buffer,
+        _offset: buffer::Offset,
+        _count: u32,
+    ) {
+        unimplemented!()
+    }
+
     fn begin_query(
         &mut self,
         _query: query::Query<Backend>,
@@ -1059,7 +1122,7 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
 
     fn reset_query_pool(
         &mut self,
-        _pool: &(),
+        _pool: &n::QueryPool,
         _queries: Range<query::QueryId>,
     ) {
         unimplemented!()
@@ -1073,6 +1136,18 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         unimplemented!()
     }
 
+    fn copy_query_pool_results(
+        &mut self,
+        _pool: &n::QueryPool,
+        _queries: Range<query::QueryId>,
+        _buffer: &n::Buffer,
+        _offset: buffer::Offset,
+        _stride: buffer::Offset,
+        _flags: query::ResultFlags,
+    ) {
+        unimplemented!()
+    }
+
     fn push_compute_constants(
         &mut self,
         _layout: &n::PipelineLayout,
@@ -1,5 +1,5 @@
 use std::borrow::Borrow;
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::iter::repeat;
 use std::ops::Range;
@@ -9,7 +9,7 @@ use std::sync::{Arc, Mutex};
 use gl;
 use gl::types::{GLint, GLenum, GLfloat};
 
-use hal::{self as c, device as d, error, image as i, memory, pass, pso, buffer, mapping, query};
+use hal::{self as c, accel_struct, device as d, error, image as i, indirect, memory, pass, pso, buffer, mapping, query};
 use hal::format::{ChannelType, Format, Swizzle};
 use hal::pool::CommandPoolCreateFlags;
 use hal::queue::QueueFamilyId;
@@ -219,7 +219,10 @@ impl Device {
     ) -> Result<String, d::ShaderError> {
         let mut compile_options = glsl::CompilerOptions::default();
         // see version table at https://en.wikipedia.org/wiki/OpenGL_Shading_Language
-        compile_options.version = match self.share.info.shading_language.tuple() {
+        let shading_language = self.share.info.shading_language;
+        compile_options.version = match shading_language.tuple() {
+            (3, 00) if shading_language.is_embedded => glsl::Version::V3_00Es,
+            (1, 00) if shading_language.is_embedded => glsl::Version::V1_00Es,
             (4, 60) => glsl::Version::V4_60,
             (4, 50) => glsl::Version::V4_50,
             (4, 40) => glsl::Version::V4_40,
@@ -272,13 +275,38 @@ impl Device {
 
 impl d::Device<B> for Device {
     fn allocate_memory(
-        &self, _mem_type: c::MemoryTypeId, size: u64,
+        &self, mem_type: c::MemoryTypeId, size: u64,
     ) -> Result<n::Memory, d::OutOfMemory> {
-        // TODO
+        if self.share.private_caps.map {
+            // TODO
+            return Ok(n::Memory {
+                properties: memory::Properties::CPU_VISIBLE | memory::Properties::CPU_CACHED,
+                first_bound_buffer: Cell::new(0),
+                first_bound_target: Cell::new(0),
+                size,
+                emulated: None,
+            });
+        }
+
+        // Mapping is emulated with a host copy of the memory contents,
+        // the types match the ones reported by `PhysicalDevice::memory_properties`.
+        let properties = match mem_type.0 {
+            0 => memory::Properties::DEVICE_LOCAL,
+            1 => memory::Properties::CPU_VISIBLE,
+            _ => memory::Properties::CPU_VISIBLE | memory::Properties::CPU_CACHED,
+        };
+        let emulated = if properties.contains(memory::Properties::CPU_VISIBLE) {
+            Some(RefCell::new(vec![0; size as usize]))
+        } else {
+            None
+        };
+
         Ok(n::Memory {
-            properties: memory::Properties::CPU_VISIBLE | memory::Properties::CPU_CACHED,
+            properties,
             first_bound_buffer: Cell::new(0),
+            first_bound_target: Cell::new(0),
             size,
+            emulated,
         })
     }
 
@@ -672,6 +700,7 @@ impl d::Device<B> for Device {
 
         if offset == 0 {
             memory.first_bound_buffer.set(unbound.name);
+            memory.first_bound_target.set(target);
         } else {
             assert_ne!(0, memory.first_bound_buffer.get());
         }
@@ -734,13 +763,20 @@ impl d::Device<B> for Device {
             other => other,
         };
 
+        let offset = *range.start().unwrap_or(&0);
+        let size = *range.end().unwrap_or(&memory.size) - offset;
+
+        if let Some(ref emulated) = memory.emulated {
+            // Contents are transferred on flush and invalidation.
+            assert!(offset + size <= memory.size);
+            let ptr = unsafe { emulated.borrow_mut().as_mut_ptr().offset(offset as isize) };
+            return Ok(ptr);
+        }
+
         assert!(self.share.private_caps.buffer_role_change);
         let target = gl::PIXEL_PACK_BUFFER;
         let access = memory.map_flags();
 
-        let offset = *range.start().unwrap_or(&0);
-        let size = *range.end().unwrap_or(&memory.size) - offset;
-
         let ptr = unsafe {
             gl.BindBuffer(target, buffer);
             let ptr = gl.MapBufferRange(target, offset as _, size as _, access);
@@ -761,6 +797,9 @@ impl d::Device<B> for Device {
             0 => panic!("No buffer has been bound yet, can't map memory!"),
             other => other,
         };
+        if memory.emulated.is_some() {
+            return;
+        }
         let target = gl::PIXEL_PACK_BUFFER;
 
         unsafe {
@@ -775,23 +814,76 @@ impl d::Device<B> for Device {
         }
     }
 
-    fn flush_mapped_memory_ranges<'a, I, R>(&self, _: I)
+    fn flush_mapped_memory_ranges<'a, I, R>(&self, ranges: I)
     where
         I: IntoIterator,
         I::Item: Borrow<(&'a n::Memory, R)>,
         R: RangeArg<u64>,
     {
-        // unimplemented!()
-        warn!("memory range invalidation not implemented!");
+        let gl = &self.share.context;
+        for range in ranges {
+            let (memory, ref range) = *range.borrow();
+            let emulated = match memory.emulated {
+                Some(ref emulated) => emulated.borrow(),
+                None => {
+                    // unimplemented!()
+                    warn!("memory range invalidation not implemented!");
+                    continue
+                }
+            };
+            let target = memory.first_bound_target.get();
+            let offset = *range.start().unwrap_or(&0);
+            let size = *range.end().unwrap_or(&memory.size) - offset;
+
+            unsafe {
+                gl.BindBuffer(target, memory.first_bound_buffer.get());
+                gl.BufferSubData(
+                    target,
+                    offset as _,
+                    size as _,
+                    emulated[offset as usize ..].as_ptr() as *const _,
+                );
+                gl.BindBuffer(target, 0);
+            }
+        }
+
+        if let Err(err) = self.share.check() {
+            panic!("Error flushing memory: {:?}", err);
+        }
     }
 
-    fn invalidate_mapped_memory_ranges<'a, I, R>(&self, _ranges: I)
+    fn invalidate_mapped_memory_ranges<'a, I, R>(&self, ranges: I)
     where
         I: IntoIterator,
         I::Item: Borrow<(&'a n::Memory, R)>,
         R: RangeArg<u64>,
     {
-        unimplemented!()
+        let gl = &self.share.context;
+        for range in ranges {
+            let (memory, ref range) = *range.borrow();
+            let mut emulated = match memory.emulated {
+                Some(ref emulated) => emulated.borrow_mut(),
+                None => unimplemented!(),
+            };
+            let target = memory.first_bound_target.get();
+            let offset = *range.start().unwrap_or(&0);
+            let size = *range.end().unwrap_or(&memory.size) - offset;
+
+            unsafe {
+                gl.BindBuffer(target, memory.first_bound_buffer.get());
+                gl.GetBufferSubData(
+                    target,
+                    offset as _,
+                    size as _,
+                    emulated[offset as usize ..].as_mut_ptr() as *mut _,
+                );
+                gl.BindBuffer(target, 0);
+            }
+        }
+
+        if let Err(err) = self.share.check() {
+            panic!("Error invalidating memory: {:?}", err);
+        }
     }
 
     fn create_buffer_view<R: RangeArg<u64>>(
@@ -917,7 +1009,7 @@ impl d::Device<B> for Device {
         }
     }
 
-    fn create_descriptor_pool<I>(&self, _: usize, _: I) -> n::DescriptorPool
+    fn create_descriptor_pool<I>(&self, _: usize, _: I, _: pso::DescriptorPoolCreateFlags) -> n::DescriptorPool
     where
         I: IntoIterator,
         I::Item: Borrow<pso::DescriptorRangeDesc>,
@@ -1007,19 +1099,37 @@ impl d::Device<B> for Device {
         }
     }
 
-    fn get_fence_status(&self, _: &n::Fence) -> bool {
-        unimplemented!()
+    fn get_fence_status(&self, fence: &n::Fence) -> bool {
+        self.wait_for_fence(fence, 0)
     }
 
     fn free_memory(&self, memory: n::Memory) {
         // Nothing to do
     }
 
-    fn create_query_pool(&self, _ty: query::QueryType, _count: u32) -> () {
+    fn create_query_pool(
+        &self,
+        _ty: query::QueryType,
+        _count: u32,
+        _flags: query::QueryPoolCreateFlags,
+    ) -> n::QueryPool {
+        unimplemented!()
+    }
+
+    fn destroy_query_pool(&self, _: n::QueryPool) {
+        unimplemented!()
+    }
+
+    fn create_command_signature(
+        &self,
+        _arguments: &[indirect::Argument],
+        _stride: u32,
+        _layout: Option<&n::PipelineLayout>,
+    ) -> Result<(), indirect::CreationError> {
         unimplemented!()
     }
 
-    fn destroy_query_pool(&self, _: ()) {
+    fn destroy_command_signature(&self, _: ()) {
         unimplemented!()
     }
 
@@ -1047,11 +1157,49 @@ impl d::Device<B> for Device {
         }
     }
 
+    fn create_ray_tracing_pipeline<'a>(
+        &self, _desc: &pso::RayTracingPipelineDesc<'a, B>,
+    ) -> Result<(), pso::CreationError> {
+        unimplemented!()
+    }
+
+    fn destroy_ray_tracing_pipeline(&self, _pipeline: ()) {
+        unimplemented!()
+    }
+
+    fn get_ray_tracing_shader_group_handles(&self, _pipeline: &(), _groups: Range<u32>) -> Vec<u8> {
+        unimplemented!()
+    }
+
+    fn get_acceleration_structure_prebuild_info<'a>(
+        &self, _desc: &accel_struct::BuildDesc<'a, B>,
+    ) -> accel_struct::PrebuildInfo {
+        unimplemented!()
+    }
+
+    fn create_acceleration_structure<'a>(
+        &self, _desc: &accel_struct::Desc<'a, B>,
+    ) -> Result<(), accel_struct::CreationError> {
+        unimplemented!()
+    }
+
+    fn get_acceleration_structure_address(&self, _accel: &()) -> u64 {
+        unimplemented!()
+    }
+
+    fn destroy_acceleration_structure(&self, _accel: ()) {
+        unimplemented!()
+    }
+
     fn destroy_framebuffer(&self, frame_buffer: n::FrameBuffer) {
         let gl = &self.share.context;
         unsafe { gl.DeleteFramebuffers(1, &frame_buffer); }
     }
 
+    fn get_buffer_address(&self, _buffer: &n::Buffer) -> u64 {
+        unimplemented!()
+    }
+
     fn destroy_buffer(&self, buffer: n::Buffer) {
         unsafe {
             self.share.context.DeleteBuffers(1, &buffer.raw);
@@ -1103,8 +1251,8 @@ impl d::Device<B> for Device {
         &self,
         surface: &mut Surface,
         config: c::SwapchainConfig,
-    ) -> (Swapchain, c::Backbuffer<B>) {
-        self.create_swapchain_impl(surface, config)
+    ) -> Result<(Swapchain, c::Backbuffer<B>), c::window::CreationError> {
+        Ok(self.create_swapchain_impl(surface, config))
     }
 
     fn destroy_swapchain(&self, _swapchain: Swapchain) {
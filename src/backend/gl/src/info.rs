@@ -58,14 +58,21 @@ impl Version {
     /// Note that this function is intentionally lenient in regards to parsing,
     /// and will try to recover at least the first two version numbers without
     /// resulting in an `Err`.
+    ///
+    /// WebGL contexts prefix the version with `WebGL`, the underlying ES
+    /// version is only reported by some browsers. WebGL 1.0 and 2.0 are
+    /// parsed as their ES 2.0 and ES 3.0 counterparts.
     pub fn parse(mut src: &'static str) -> Result<Version, &'static str> {
         let es_sig = " ES ";
-        let is_es = match src.rfind(es_sig) {
-            Some(pos) => {
-                src = &src[pos + es_sig.len() ..];
-                true
-            },
-            None => false,
+        let webgl_sig = "WebGL ";
+        let (is_es, major_offset) = if let Some(pos) = src.rfind(es_sig) {
+            src = &src[pos + es_sig.len() ..];
+            (true, 0)
+        } else if src.starts_with(webgl_sig) {
+            src = &src[webgl_sig.len() ..];
+            (true, 1)
+        } else {
+            (false, 0)
         };
         let (version, vendor_info) = match src.find(' ') {
             Some(i) => (&src[..i], &src[i+1..]),
@@ -75,14 +82,14 @@ impl Version {
         // TODO: make this even more lenient so that we can also accept
         // `<major> "." <minor> [<???>]`
         let mut it = version.split('.');
-        let major = it.next().and_then(|s| s.parse().ok());
+        let major: Option<u32> = it.next().and_then(|s| s.parse().ok());
         let minor = it.next().and_then(|s| s.parse().ok());
         let revision = it.next().and_then(|s| s.parse().ok());
 
         match (major, minor, revision) {
             (Some(major), Some(minor), revision) => Ok(Version {
                 is_embedded: is_es,
-                major: major,
+                major: major + major_offset,
                 minor: minor,
                 revision: revision,
                 vendor_info: vendor_info,
@@ -145,7 +152,7 @@ pub struct PlatformName {
 }
 
 impl PlatformName {
-    fn get(gl: &gl::Gl) -> Self {
+    pub(crate) fn get(gl: &gl::Gl) -> Self {
         PlatformName {
             vendor: get_string(gl, gl::VENDOR),
             renderer: get_string(gl, gl::RENDERER),
@@ -299,10 +306,13 @@ pub fn query_all(gl: &gl::Gl) -> (Info, Features, LegacyFeatures, Limits, Privat
 
     let mut limits = Limits {
         max_texture_size: get_usize(gl, gl::MAX_TEXTURE_SIZE),
+        max_texture_3d_size: get_usize(gl, gl::MAX_3D_TEXTURE_SIZE),
+        max_texture_array_layers: get_usize(gl, gl::MAX_ARRAY_TEXTURE_LAYERS),
         max_viewports: 1,
         min_buffer_copy_offset_alignment: 1,
         min_buffer_copy_pitch_alignment: 1,
         min_uniform_buffer_offset_alignment: 1, // TODO
+        non_coherent_atom_size: 1,
         .. Limits::default()
     };
 
@@ -352,6 +362,10 @@ pub fn query_all(gl: &gl::Gl) -> (Info, Features, LegacyFeatures, Limits, Privat
     ]) {
         features |= Features::INSTANCE_RATE;
     }
+    if info.is_supported(&[Core(3, 2), Es(2, 0)]) {
+        // Point sizes written by shaders are enabled on device creation.
+        features |= Features::POINT_SIZE;
+    }
 
     if info.is_supported(&[Core(4, 3), Es(3, 1)]) { // TODO: extension
         legacy |= LegacyFeatures::INDIRECT_EXECUTION;
@@ -426,6 +440,14 @@ pub fn query_all(gl: &gl::Gl) -> (Info, Features, LegacyFeatures, Limits, Privat
         legacy |= LegacyFeatures::SAMPLER_BORDER_COLOR;
     }
 
+    // Non-zero base instances and vertices need to be supported by all draw variants.
+    if legacy.contains(LegacyFeatures::DRAW_INSTANCED_BASE | LegacyFeatures::DRAW_INDEXED_INSTANCED_BASE) {
+        features |= Features::DRAW_FIRST_INSTANCE;
+    }
+    if legacy.contains(LegacyFeatures::DRAW_INDEXED_BASE | LegacyFeatures::DRAW_INDEXED_INSTANCED_BASE_VERTEX) {
+        features |= Features::DRAW_BASE_VERTEX;
+    }
+
     let private = PrivateCaps {
         vertex_array:                       info.is_supported(&[Core(3,0),
                                                                 Es  (3,0),
@@ -476,5 +498,8 @@ mod tests {
         assert_eq!(Version::parse("OpenGL ES 3.1"), Ok(Version::new_embedded(3, 1, "")));
         assert_eq!(Version::parse("OpenGL ES 2.0 Google Nexus"), Ok(Version::new_embedded(2, 0, "Google Nexus")));
         assert_eq!(Version::parse("GLSL ES 1.1"), Ok(Version::new_embedded(1, 1, "")));
+        assert_eq!(Version::parse("WebGL 2.0"), Ok(Version::new_embedded(3, 0, "")));
+        assert_eq!(Version::parse("WebGL 2.0 (OpenGL ES 3.0 Chromium)"), Ok(Version::new_embedded(3, 0, "Chromium)")));
+        assert_eq!(Version::parse("WebGL GLSL ES 3.00"), Ok(Version::new_embedded(3, 0, "")));
     }
 }
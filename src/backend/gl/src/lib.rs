@@ -38,6 +38,10 @@ mod window;
 
 #[cfg(feature = "glutin")]
 pub use window::glutin::{config_context, Headless, Surface, Swapchain};
+#[cfg(feature = "software")]
+pub use window::glutin::SoftwareError;
+#[cfg(all(target_arch = "wasm32", not(feature = "glutin")))]
+pub use window::web::{Surface, Swapchain};
 
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Backend {}
@@ -76,7 +80,11 @@ impl hal::Backend for Backend {
 
     type Fence = native::Fence;
     type Semaphore = native::Semaphore;
-    type QueryPool = ();
+    type QueryPool = native::QueryPool;
+    type CommandSignature = ();
+
+    type AccelerationStructure = ();
+    type RayTracingPipeline = ();
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
@@ -104,6 +112,20 @@ impl Error {
     }
 }
 
+/// Substrings of the renderer names of known software rasterizers.
+const SOFTWARE_RENDERERS: &'static [&'static str] = &[
+    "llvmpipe",
+    "softpipe",
+    "Software Rasterizer",
+    "SwiftShader",
+    "GDI Generic",
+];
+
+/// Check if a renderer name belongs to a software rasterizer.
+fn is_software_renderer(renderer: &str) -> bool {
+    SOFTWARE_RENDERERS.iter().any(|r| renderer.contains(r))
+}
+
 /// Internal struct of shared data between the physical and logical device.
 struct Share {
     context: gl::Gl,
@@ -189,11 +211,18 @@ impl PhysicalDevice {
         info!("Shading Language: {:?}", info.shading_language);
         info!("Features: {:?}", features);
         info!("Legacy Features: {:?}", legacy_features);
+        if !private_caps.map {
+            info!("Buffer mapping is not supported, host visible memory is emulated");
+        }
+        if limits.max_compute_group_count[0] == 0 {
+            info!("Compute shaders are not supported, dispatches are unavailable");
+        }
         debug!("Loaded Extensions:");
         for extension in info.extensions.iter() {
             debug!("- {}", *extension);
         }
         let name = info.platform_name.renderer.into();
+        let software_rendering = is_software_renderer(info.platform_name.renderer);
 
         // create the shared context
         let share = Share {
@@ -214,7 +243,7 @@ impl PhysicalDevice {
                 name,
                 vendor: 0, // TODO
                 device: 0, // TODO
-                software_rendering: false, // not always true ..
+                software_rendering,
             },
             physical_device: PhysicalDevice(Starc::new(share)),
             queue_families: vec![QueueFamily],
@@ -313,9 +342,19 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
                 },
             ]
         } else {
+            // Without buffer mapping (e.g. WebGL2) host visible memory is emulated,
+            // requiring explicit flushing and invalidation of the mapped ranges.
             vec![
                 hal::MemoryType {
                     properties: Properties::DEVICE_LOCAL,
+                    heap_index: 1,
+                },
+                hal::MemoryType { // upload
+                    properties: Properties::CPU_VISIBLE,
+                    heap_index: 0,
+                },
+                hal::MemoryType { // download
+                    properties: Properties::CPU_VISIBLE | Properties::CPU_CACHED,
                     heap_index: 0,
                 },
             ]
@@ -1,6 +1,6 @@
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 
-use hal::{format, image as i, pass, pso};
+use hal::{self, buffer, format, image as i, pass, pso};
 use hal::memory::Properties;
 
 use gl;
@@ -104,6 +104,15 @@ impl pso::DescriptorPool<Backend> for DescriptorPool {
     }
 }
 
+#[derive(Debug)]
+pub struct QueryPool;
+
+impl hal::QueryPool for QueryPool {
+    fn result_stride(&self) -> buffer::Offset {
+        unimplemented!()
+    }
+}
+
 #[derive(Clone, Debug, Hash)]
 pub enum ShaderModule {
     Raw(Shader),
@@ -114,8 +123,13 @@ pub enum ShaderModule {
 pub struct Memory {
     pub(crate) properties: Properties,
     pub(crate) first_bound_buffer: Cell<RawBuffer>,
+    /// Target the first bound buffer has been created for
+    pub(crate) first_bound_target: Cell<gl::types::GLenum>,
     /// Allocation size
     pub(crate) size: u64,
+    /// Host copy of the memory contents, used to emulate mapping
+    /// on contexts without buffer mapping support (e.g. WebGL2).
+    pub(crate) emulated: Option<RefCell<Vec<u8>>>,
 }
 
 unsafe impl Send for Memory {}
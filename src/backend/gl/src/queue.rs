@@ -426,6 +426,9 @@ impl CommandQueue {
             com::Command::SetBlendColor(color) => {
                 state::set_blend_color(&self.share.context, color);
             }
+            com::Command::SetLineWidth(width) => {
+                state::set_line_width(&self.share.context, width);
+            }
             com::Command::ClearBufferColorF(draw_buffer, cv) => unsafe {
                 self.share.context.ClearBufferfv(gl::COLOR, draw_buffer, cv.as_ptr());
             }
@@ -719,6 +722,17 @@ impl hal::queue::RawCommandQueue<Backend> for CommandQueue {
         }
     }
 
+    #[cfg(all(target_arch = "wasm32", not(feature = "glutin")))]
+    fn present<IS, IW>(&mut self, _swapchains: IS, _wait_semaphores: IW)
+    where
+        IS: IntoIterator,
+        IS::Item: BorrowMut<window::web::Swapchain>,
+        IW: IntoIterator,
+        IW::Item: Borrow<native::Semaphore>,
+    {
+        // The browser presents the canvas after returning to the event loop.
+    }
+
     fn wait_idle(&self) -> Result<(), error::HostExecutionError> {
         unsafe { self.share.context.Finish(); }
         Ok(())
@@ -11,7 +11,9 @@ pub fn bind_polygon_mode(gl: &gl::Gl, mode: pso::PolygonMode, bias: Option<pso::
     let (gl_draw, gl_offset) = match mode {
         Point => (gl::POINT, gl::POLYGON_OFFSET_POINT),
         Line(width) => {
-            unsafe { gl.LineWidth(width) };
+            if let pso::State::Static(w) = width {
+                unsafe { gl.LineWidth(w) };
+            }
             (gl::LINE, gl::POLYGON_OFFSET_LINE)
         },
         Fill => (gl::FILL, gl::POLYGON_OFFSET_FILL),
@@ -225,6 +227,10 @@ pub fn unlock_color_mask(gl: &gl::Gl) {
     unsafe { gl.ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE) };
 }
 
+pub fn set_line_width(gl: &gl::Gl, width: f32) {
+    unsafe { gl.LineWidth(width) };
+}
+
 pub fn set_blend_color(gl: &gl::Gl, color: pso::ColorValue) {
     unsafe {
         gl.BlendColor(color[0], color[1], color[2], color[3])
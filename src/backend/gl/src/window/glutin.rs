@@ -42,10 +42,33 @@
 //!     let _adapters = headless.enumerate_adapters();
 //! }
 //! ```
+//!
+//! Headless initialization with a software rasterizer, producing images
+//! without a GPU (requires the `software` feature and Mesa's llvmpipe as the
+//! system OpenGL implementation, e.g. on Linux).
+//!
+//! ```no_run
+//! extern crate gfx_backend_gl;
+//! extern crate gfx_hal;
+//!
+//! use gfx_hal::Instance;
+//! use gfx_backend_gl::Headless;
+//!
+//! fn main() {
+//!     let headless = Headless::software(256, 256)
+//!         .expect("Failed to build software context");
+//!     let _adapters = headless.enumerate_adapters();
+//! }
+//! ```
+
+#[cfg(feature = "software")]
+use std::env;
 
 use hal::{self, format as f, image};
 
 use {Backend as B, Device, PhysicalDevice, QueueFamily, Starc};
+#[cfg(feature = "software")]
+use {gl, is_software_renderer, PlatformName};
 
 use glutin::{self, GlContext};
 
@@ -186,6 +209,85 @@ pub struct Headless(pub glutin::HeadlessContext);
 unsafe impl Send for Headless {}
 unsafe impl Sync for Headless {}
 
+/// Error creating a software rendering context.
+#[cfg(feature = "software")]
+#[derive(Debug)]
+pub enum SoftwareError {
+    /// The context couldn't be created.
+    Creation(glutin::CreationError),
+    /// The context couldn't be made current.
+    Context(glutin::ContextError),
+    /// The context doesn't render with a software rasterizer, e.g. because
+    /// the OpenGL implementation isn't Mesa. Contains the renderer name.
+    NotSoftware(String),
+}
+
+#[cfg(feature = "software")]
+impl From<glutin::CreationError> for SoftwareError {
+    fn from(error: glutin::CreationError) -> Self {
+        SoftwareError::Creation(error)
+    }
+}
+
+#[cfg(feature = "software")]
+impl From<glutin::ContextError> for SoftwareError {
+    fn from(error: glutin::ContextError) -> Self {
+        SoftwareError::Context(error)
+    }
+}
+
+// Environment variables selecting llvmpipe in Mesa.
+#[cfg(feature = "software")]
+const SOFTWARE_VARS: &'static [(&'static str, &'static str)] = &[
+    ("LIBGL_ALWAYS_SOFTWARE", "1"),
+    ("GALLIUM_DRIVER", "llvmpipe"),
+];
+
+#[cfg(feature = "software")]
+impl Headless {
+    /// Create a headless context rendering with Mesa's llvmpipe rasterizer.
+    ///
+    /// Rendering doesn't depend on the available GPU and driver,
+    /// which makes the results suitable for image comparisons on CI.
+    ///
+    /// Requires Mesa as the system OpenGL implementation, which is usually
+    /// only the case on Linux and BSDs. The rasterizer is selected with Mesa's
+    /// environment variables, which are set for the duration of the context
+    /// creation and restored afterwards, so contexts created by other threads
+    /// at the same time may pick them up as well. Returns
+    /// `SoftwareError::NotSoftware` if the created context doesn't render
+    /// with a software rasterizer.
+    pub fn software(width: u32, height: u32) -> Result<Self, SoftwareError> {
+        // Mesa selects the driver on context creation.
+        let previous = SOFTWARE_VARS
+            .iter()
+            .map(|&(name, value)| {
+                let previous = env::var_os(name);
+                env::set_var(name, value);
+                (name, previous)
+            })
+            .collect::<Vec<_>>();
+        let context = glutin::HeadlessRendererBuilder::new(width, height)
+            .with_gl(glutin::GlRequest::Latest)
+            .build();
+        for (name, value) in previous {
+            match value {
+                Some(value) => env::set_var(name, value),
+                None => env::remove_var(name),
+            }
+        }
+        let context = context?;
+
+        unsafe { context.make_current()? };
+        let gl = gl::Gl::load_with(|s| context.get_proc_address(s) as *const _);
+        let renderer = PlatformName::get(&gl).renderer;
+        if !is_software_renderer(renderer) {
+            return Err(SoftwareError::NotSoftware(renderer.to_string()));
+        }
+        Ok(Headless(context))
+    }
+}
+
 impl hal::Instance for Headless {
     type Backend = B;
     fn enumerate_adapters(&self) -> Vec<hal::Adapter<B>> {
@@ -1,2 +1,4 @@
 #[cfg(feature = "glutin")]
 pub mod glutin;
+#[cfg(all(target_arch = "wasm32", not(feature = "glutin")))]
+pub mod web;
@@ -0,0 +1,119 @@
+//! Surface and swapchain for a WebGL2 canvas.
+//!
+//! The browser owns the canvas context and presents its default framebuffer
+//! once control is returned to the event loop, therefore presentation is a no-op.
+//!
+//! The GL entry points have to be provided by the embedder, as there is no
+//! standard way of querying them on `wasm32-unknown-unknown`.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! extern crate gfx_backend_gl;
+//! extern crate gfx_hal;
+//!
+//! use gfx_hal::Instance;
+//! use gfx_backend_gl::Surface;
+//!
+//! fn init(width: u32, height: u32) {
+//!     // `get_proc_address` resolves GL function names to the
+//!     // bindings of the WebGL2 context of the canvas.
+//!     let surface = Surface::from_canvas(width, height, get_proc_address);
+//!     let _adapters = surface.enumerate_adapters();
+//! }
+//! ```
+
+use std::os::raw::c_void;
+
+use hal::{self, format as f, image};
+
+use {Backend as B, Device, PhysicalDevice, QueueFamily};
+
+
+pub struct Swapchain;
+
+impl hal::Swapchain<B> for Swapchain {
+    fn acquire_frame(&mut self, _sync: hal::FrameSync<B>) -> hal::Frame {
+        // The canvas only has a single drawing buffer.
+        hal::Frame::new(0)
+    }
+}
+
+pub struct Surface {
+    extent: image::Extent,
+    get_proc_address: Box<Fn(&str) -> *const c_void>,
+}
+
+unsafe impl Send for Surface {}
+unsafe impl Sync for Surface {}
+
+impl Surface {
+    /// Create a surface for a canvas with a current WebGL2 context.
+    pub fn from_canvas<F>(width: image::Size, height: image::Size, get_proc_address: F) -> Self
+    where
+        F: Fn(&str) -> *const c_void + 'static,
+    {
+        Surface {
+            extent: image::Extent {
+                width,
+                height,
+                depth: 1,
+            },
+            get_proc_address: Box::new(get_proc_address),
+        }
+    }
+
+    /// Update the size of the drawing buffer after the canvas has been resized.
+    ///
+    /// Swapchains have to be recreated afterwards.
+    pub fn set_extent(&mut self, width: image::Size, height: image::Size) {
+        self.extent.width = width;
+        self.extent.height = height;
+    }
+
+    fn swapchain_formats(&self) -> Vec<f::Format> {
+        // The default drawing buffer of a canvas is RGBA8.
+        vec![f::Format::Rgba8Unorm]
+    }
+}
+
+impl hal::Surface<B> for Surface {
+    fn kind(&self) -> hal::image::Kind {
+        hal::image::Kind::D2(self.extent.width, self.extent.height, 1, 1)
+    }
+
+    fn capabilities_and_formats(&self, _: &PhysicalDevice) -> (hal::SurfaceCapabilities, Option<Vec<f::Format>>) {
+        let extent = hal::window::Extent2D::from(self.extent);
+
+        (hal::SurfaceCapabilities {
+            image_count: 1..2,
+            current_extent: Some(extent),
+            extents: extent .. hal::window::Extent2D {
+                width: self.extent.width + 1,
+                height: self.extent.height + 1,
+            },
+            max_image_layers: 1,
+        }, Some(self.swapchain_formats()))
+    }
+
+    fn supports_queue_family(&self, _: &QueueFamily) -> bool { true }
+}
+
+impl Device {
+    pub(crate) fn create_swapchain_impl(
+        &self,
+        _surface: &mut Surface,
+        _config: hal::SwapchainConfig,
+    ) -> (Swapchain, hal::Backbuffer<B>) {
+        let backbuffer = hal::Backbuffer::Framebuffer(0);
+        (Swapchain, backbuffer)
+    }
+}
+
+impl hal::Instance for Surface {
+    type Backend = B;
+    fn enumerate_adapters(&self) -> Vec<hal::Adapter<B>> {
+        let adapter = PhysicalDevice::new_adapter(|s| (self.get_proc_address)(s));
+        vec![adapter]
+    }
+}
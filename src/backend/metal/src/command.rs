@@ -7,11 +7,11 @@ use std::ops::{Deref, Range};
 use std::sync::{Arc};
 use std::{iter, mem};
 
-use hal::{buffer, command as com, error, memory, pool, pso};
+use hal::{accel_struct, buffer, command as com, error, memory, pass, pool, pso};
 use hal::{VertexCount, VertexOffset, InstanceCount, IndexCount, WorkGroupCount};
 use hal::format::FormatDesc;
 use hal::image::{Filter, Layout, SubresourceRange};
-use hal::query::{Query, QueryControl, QueryId};
+use hal::query::{Query, QueryControl, QueryId, ResultFlags};
 use hal::queue::{RawCommandQueue, RawSubmission};
 
 use metal::{self, MTLViewport, MTLScissorRect, MTLPrimitiveType, MTLClearColor, MTLIndexType, MTLSize, MTLOrigin};
@@ -940,8 +940,9 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         }
     }
 
-    fn finish(&mut self) {
+    fn finish(&mut self) -> Result<(), com::RecordingError> {
         self.inner().stop_encoding();
+        Ok(())
     }
 
     fn reset(&mut self, release_resources: bool) {
@@ -1026,7 +1027,7 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         T: IntoIterator,
         T::Item: Borrow<com::AttachmentClear>,
         U: IntoIterator,
-        U::Item: Borrow<pso::Rect>,
+        U::Item: Borrow<pso::ClearRect>,
     {
         unimplemented!()
     }
@@ -1138,6 +1139,16 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         self.inner().set_blend_color(&color);
     }
 
+    fn set_depth_bounds(&mut self, _: Range<f32>) {
+        warn!("Depth bounds test is not supported");
+    }
+
+    fn set_line_width(&mut self, width: f32) {
+        if width != 1.0 {
+            warn!("Unsupported line width: {:?}", width);
+        }
+    }
+
     fn begin_render_pass_raw<T>(
         &mut self,
         render_pass: &native::RenderPass,
@@ -1184,6 +1195,22 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         self.inner().stop_encoding();
     }
 
+    fn begin_rendering<'a, T>(
+        &mut self,
+        _render_area: pso::Rect,
+        _colors: T,
+        _depth_stencil: Option<pass::RenderingAttachment<'a, Backend>>,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<pass::RenderingAttachment<'a, Backend>>,
+    {
+        unimplemented!()
+    }
+
+    fn end_rendering(&mut self) {
+        unimplemented!()
+    }
+
     fn bind_graphics_pipeline(&mut self, pipeline: &native::GraphicsPipeline) {
         let inner = self.inner();
         let pipeline_state = pipeline.raw.to_owned();
@@ -1471,20 +1498,46 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         inner.stop_encoding();
     }
 
-    fn dispatch_indirect(&mut self, buffer: &native::Buffer, offset: buffer::Offset) {
+    fn dispatch_indirect(&mut self, buffer: &native::Buffer, offset: buffer::Offset, dispatch_count: u32) {
         let inner = self.inner();
 
-        let command = soft::ComputeCommand::DispatchIndirect {
-            wg_size: inner.begin_compute(),
+        let wg_size = inner.begin_compute();
+        let commands = (0 .. dispatch_count as buffer::Offset).map(|i| soft::ComputeCommand::DispatchIndirect {
+            wg_size,
             buffer: buffer.raw.clone(),
-            offset,
-        };
-        inner.sink.compute_commands(iter::once(command));
+            offset: offset + i * 12,
+        });
+        inner.sink.compute_commands(commands);
 
         //TODO: re-use compute encoders
         inner.stop_encoding();
     }
 
+    fn build_acceleration_structure<'a>(
+        &mut self,
+        _desc: &accel_struct::BuildDesc<'a, Backend>,
+        _dst: &(),
+        _scratch: (&native::Buffer, buffer::Offset),
+    ) {
+        unimplemented!()
+    }
+
+    fn bind_ray_tracing_pipeline(&mut self, _pipeline: &()) {
+        unimplemented!()
+    }
+
+    fn bind_ray_tracing_descriptor_sets<T>(&mut self, _layout: &native::PipelineLayout, _first_set: usize, _sets: T)
+    where
+        T: IntoIterator,
+        T::Item: Borrow<native::DescriptorSet>,
+    {
+        unimplemented!()
+    }
+
+    fn trace_rays<'a>(&mut self, _tables: &accel_struct::ShaderBindingTables<'a, Backend>, _count: [u32; 3]) {
+        unimplemented!()
+    }
+
     fn copy_buffer<T>(
         &mut self,
         src: &native::Buffer,
@@ -1613,6 +1666,16 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         unimplemented!()
     }
 
+    fn execute_indirect(
+        &mut self,
+        _signature: &(),
+        _buffer: &native::Buffer,
+        _offset: buffer::Offset,
+        _count: u32,
+    ) {
+        unimplemented!()
+    }
+
     fn begin_query(
         &mut self,
         _query: Query<Backend>,
@@ -1630,7 +1693,7 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
 
     fn reset_query_pool(
         &mut self,
-        _pool: &(),
+        _pool: &native::QueryPool,
         _queries: Range<QueryId>,
     ) {
         unimplemented!()
@@ -1644,6 +1707,18 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         // nothing to do, timestamps are unsupported on Metal
     }
 
+    fn copy_query_pool_results(
+        &mut self,
+        _pool: &native::QueryPool,
+        _queries: Range<QueryId>,
+        _buffer: &native::Buffer,
+        _offset: buffer::Offset,
+        _stride: buffer::Offset,
+        _flags: ResultFlags,
+    ) {
+        unimplemented!()
+    }
+
     fn push_graphics_constants(
         &mut self,
         _layout: &native::PipelineLayout,
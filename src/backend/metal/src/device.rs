@@ -9,7 +9,7 @@ use std::path::Path;
 use std::sync::{Arc, Mutex};
 use std::{cmp, mem, ptr, slice};
 
-use hal::{self, error, image, pass, format, mapping, memory, buffer, pso, query};
+use hal::{self, accel_struct, error, image, indirect, pass, format, mapping, memory, buffer, pso, query};
 use hal::command::BufferCopy;
 use hal::device::{BindError, OutOfMemory, FramebufferError, ShaderError};
 use hal::memory::Properties;
@@ -249,15 +249,34 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
     fn limits(&self) -> hal::Limits {
         hal::Limits {
             max_texture_size: 4096, // TODO: feature set
+            max_texture_3d_size: 2048,
+            max_texture_array_layers: 2048,
             max_patch_size: 0, // No tessellation
             max_viewports: 1,
 
             min_buffer_copy_offset_alignment: if self.is_mac() {256} else {64},
             min_buffer_copy_pitch_alignment: 4, // TODO: made this up
             min_uniform_buffer_offset_alignment: 1, // TODO
+            non_coherent_atom_size: 1,
 
             max_compute_group_count: [16; 3], // TODO
             max_compute_group_size: [64; 3], // TODO
+
+            // Push constants are passed with `set*Bytes`, limited to 4KB.
+            max_push_constants_size: 4096,
+            max_bound_descriptor_sets: 8, // TODO
+            max_per_stage_descriptor_samplers: 16,
+            max_per_stage_descriptor_uniform_buffers: 31,
+            max_per_stage_descriptor_storage_buffers: 31,
+            max_per_stage_descriptor_sampled_images: 31, // TODO: 128 with argument buffers
+            max_per_stage_descriptor_storage_images: 8,
+            max_texel_elements: 0, // TODO: texel buffers
+
+            shader_group_handle_size: 0, // No ray tracing
+            max_ray_recursion_depth: 0,
+            min_shader_table_alignment: 1,
+            min_shader_record_alignment: 1,
+            max_multiview_view_count: 0,
         }
     }
 }
@@ -1016,7 +1035,9 @@ impl hal::Device<Backend> for Device {
         unsafe { n::Semaphore(n::dispatch_semaphore_create(1)) } // Returns retained
     }
 
-    fn create_descriptor_pool<I>(&self, _max_sets: usize, descriptor_ranges: I) -> n::DescriptorPool
+    fn create_descriptor_pool<I>(
+        &self, _max_sets: usize, descriptor_ranges: I, _flags: pso::DescriptorPoolCreateFlags,
+    ) -> n::DescriptorPool
     where
         I: IntoIterator,
         I::Item: Borrow<pso::DescriptorRangeDesc>,
@@ -1142,7 +1163,8 @@ impl hal::Device<Backend> for Device {
                                 encoder.set_buffer(&buffer.raw, range.start.unwrap_or(0), write.binding as _);
                             }
                             pso::Descriptor::CombinedImageSampler(..) |
-                            pso::Descriptor::TexelBuffer(..) => unimplemented!(),
+                            pso::Descriptor::TexelBuffer(..) |
+                            pso::Descriptor::AccelerationStructure(..) => unimplemented!(),
                         }
                     }
                 }
@@ -1181,6 +1203,40 @@ impl hal::Device<Backend> for Device {
     fn destroy_compute_pipeline(&self, _pipeline: n::ComputePipeline) {
     }
 
+    fn create_ray_tracing_pipeline<'a>(
+        &self, _desc: &pso::RayTracingPipelineDesc<'a, Backend>,
+    ) -> Result<(), pso::CreationError> {
+        unimplemented!()
+    }
+
+    fn destroy_ray_tracing_pipeline(&self, _pipeline: ()) {
+        unimplemented!()
+    }
+
+    fn get_ray_tracing_shader_group_handles(&self, _pipeline: &(), _groups: Range<u32>) -> Vec<u8> {
+        unimplemented!()
+    }
+
+    fn get_acceleration_structure_prebuild_info<'a>(
+        &self, _desc: &accel_struct::BuildDesc<'a, Backend>,
+    ) -> accel_struct::PrebuildInfo {
+        unimplemented!()
+    }
+
+    fn create_acceleration_structure<'a>(
+        &self, _desc: &accel_struct::Desc<'a, Backend>,
+    ) -> Result<(), accel_struct::CreationError> {
+        unimplemented!()
+    }
+
+    fn get_acceleration_structure_address(&self, _accel: &()) -> u64 {
+        unimplemented!()
+    }
+
+    fn destroy_acceleration_structure(&self, _accel: ()) {
+        unimplemented!()
+    }
+
     fn destroy_framebuffer(&self, _buffer: n::FrameBuffer) {
     }
 
@@ -1293,6 +1349,10 @@ impl hal::Device<Backend> for Device {
         })
     }
 
+    fn get_buffer_address(&self, _buffer: &n::Buffer) -> u64 {
+        unimplemented!()
+    }
+
     fn destroy_buffer(&self, buffer: n::Buffer) {
         if let Some(alloc) = buffer.allocations {
             alloc.lock().unwrap().remove(buffer.offset .. (buffer.offset + buffer.raw.length()));
@@ -1504,11 +1564,29 @@ impl hal::Device<Backend> for Device {
     fn destroy_fence(&self, _fence: n::Fence) {
     }
 
-    fn create_query_pool(&self, _ty: query::QueryType, _count: u32) -> () {
+    fn create_query_pool(
+        &self,
+        _ty: query::QueryType,
+        _count: u32,
+        _flags: query::QueryPoolCreateFlags,
+    ) -> n::QueryPool {
+        unimplemented!()
+    }
+
+    fn destroy_query_pool(&self, _: n::QueryPool) {
+        unimplemented!()
+    }
+
+    fn create_command_signature(
+        &self,
+        _arguments: &[indirect::Argument],
+        _stride: u32,
+        _layout: Option<&n::PipelineLayout>,
+    ) -> Result<(), indirect::CreationError> {
         unimplemented!()
     }
 
-    fn destroy_query_pool(&self, _: ()) {
+    fn destroy_command_signature(&self, _: ()) {
         unimplemented!()
     }
 
@@ -1516,7 +1594,7 @@ impl hal::Device<Backend> for Device {
         &self,
         surface: &mut Surface,
         config: hal::SwapchainConfig,
-    ) -> (Swapchain, hal::Backbuffer<Backend>) {
+    ) -> Result<(Swapchain, hal::Backbuffer<Backend>), hal::window::CreationError> {
         self.build_swapchain(surface, config)
     }
 
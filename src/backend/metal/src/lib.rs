@@ -100,6 +100,16 @@ impl Instance {
         }
     }
 
+    /// Create a surface from the raw handle of a native window.
+    ///
+    /// Panics if the handle isn't a macOS view.
+    pub fn create_surface_from_raw_handle<W: hal::HasRawWindowHandle>(&self, window: &W) -> Surface {
+        match window.raw_window_handle() {
+            hal::RawWindowHandle::MacOS { ns_view } => self.create_surface_from_nsview(ns_view),
+            handle => panic!("Unsupported window handle: {:?}", handle),
+        }
+    }
+
     #[cfg(feature = "winit")]
     pub fn create_surface(&self, window: &winit::Window) -> Surface {
         use winit::os::macos::WindowExt;
@@ -144,7 +154,11 @@ impl hal::Backend for Backend {
 
     type Fence = native::Fence;
     type Semaphore = native::Semaphore;
-    type QueryPool = ();
+    type QueryPool = native::QueryPool;
+    type CommandSignature = ();
+
+    type AccelerationStructure = ();
+    type RayTracingPipeline = ();
 }
 
 pub struct AutoreleasePool {
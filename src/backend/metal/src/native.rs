@@ -194,6 +194,15 @@ impl hal::DescriptorPool<Backend> for DescriptorPool {
     }
 }
 
+#[derive(Debug)]
+pub struct QueryPool;
+
+impl hal::QueryPool for QueryPool {
+    fn result_stride(&self) -> hal::buffer::Offset {
+        unimplemented!()
+    }
+}
+
 #[derive(Debug)]
 pub enum DescriptorSetLayout {
     Emulated(Vec<pso::DescriptorSetLayoutBinding>),
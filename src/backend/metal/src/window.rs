@@ -98,11 +98,12 @@ impl Device {
         &self,
         surface: &mut Surface,
         config: SwapchainConfig,
-    ) -> (Swapchain, Backbuffer<Backend>) {
+    ) -> Result<(Swapchain, Backbuffer<Backend>), hal::window::CreationError> {
         let format_desc = config.color_format.base_format().0.desc();
         let (mtl_format, cv_format) = match config.color_format {
             format::Format::Rgba8Srgb => (MTLPixelFormat::RGBA8Unorm_sRGB, kCVPixelFormatType_32RGBA),
-            _ => panic!("unsupported backbuffer format"), // TODO: more formats
+            // TODO: more formats
+            format => return Err(hal::window::CreationError::UnsupportedFormat(format)),
         };
 
         let render_layer_borrow = surface.0.render_layer.lock().unwrap();
@@ -161,7 +162,7 @@ impl Device {
                 present_index: 0,
             };
 
-            (swapchain, Backbuffer::Images(images))
+            Ok((swapchain, Backbuffer::Images(images)))
         }
     }
 }
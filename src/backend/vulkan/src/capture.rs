@@ -0,0 +1,93 @@
+//! Programmatic frame captures with the in-application API of RenderDoc.
+//!
+//! The API is only available if RenderDoc injected its library into the
+//! application, e.g. when launched from the RenderDoc UI.
+
+use std::os::raw::{c_int, c_void};
+use std::path::Path;
+use std::ptr;
+
+use shared_library::dynamic_library::DynamicLibrary;
+
+use hal::error::CaptureError;
+
+#[cfg(windows)]
+const LIBRARY_NAME: &'static str = "renderdoc.dll";
+#[cfg(target_os = "android")]
+const LIBRARY_NAME: &'static str = "libVkLayer_GLES_RenderDoc.so";
+#[cfg(all(unix, not(target_os = "android")))]
+const LIBRARY_NAME: &'static str = "librenderdoc.so";
+
+// `eRENDERDOC_API_Version_1_1_2`
+const API_VERSION: c_int = 10102;
+
+type GetApiFn = unsafe extern "C" fn(version: c_int, api: *mut *mut c_void) -> c_int;
+
+// Entry points of `RENDERDOC_API_1_1_2`, in declaration order.
+// Entry points we don't use are left untyped.
+#[repr(C)]
+struct Api {
+    // `GetAPIVersion` .. `GetCapture`
+    _unused0: [*const c_void; 15],
+    trigger_capture: unsafe extern "C" fn(),
+    // `IsTargetControlConnected`, `LaunchReplayUI`, `SetActiveWindow`
+    _unused1: [*const c_void; 3],
+    start_frame_capture: unsafe extern "C" fn(device: *mut c_void, window: *mut c_void),
+    is_frame_capturing: unsafe extern "C" fn() -> u32,
+    end_frame_capture: unsafe extern "C" fn(device: *mut c_void, window: *mut c_void) -> u32,
+}
+
+pub struct RenderDoc {
+    // Keeps the library loaded while `api` is in use.
+    _library: DynamicLibrary,
+    api: *const Api,
+}
+
+unsafe impl Send for RenderDoc { }
+unsafe impl Sync for RenderDoc { }
+
+impl RenderDoc {
+    /// Connect to RenderDoc, if it's attached to the application.
+    pub fn load() -> Option<Self> {
+        let library = DynamicLibrary::open(Some(Path::new(LIBRARY_NAME))).ok()?;
+        let get_api = unsafe { library.symbol::<c_void>("RENDERDOC_GetAPI").ok()? };
+        let get_api: GetApiFn = unsafe { ::std::mem::transmute(get_api) };
+
+        let mut api = ptr::null_mut();
+        if unsafe { get_api(API_VERSION, &mut api) } != 1 || api.is_null() {
+            warn!("RenderDoc doesn't support API version {}", API_VERSION);
+            return None;
+        }
+
+        info!("RenderDoc is attached, frame captures are available");
+        Some(RenderDoc {
+            _library: library,
+            api: api as *const Api,
+        })
+    }
+
+    fn api(&self) -> &Api {
+        unsafe { &*self.api }
+    }
+
+    pub fn trigger_capture(&self) {
+        unsafe { (self.api().trigger_capture)() }
+    }
+
+    pub fn begin_capture(&self) -> Result<(), CaptureError> {
+        if unsafe { (self.api().is_frame_capturing)() } != 0 {
+            error!("A frame capture is already in progress");
+            return Err(CaptureError::Failed);
+        }
+        // NULL device and window handles capture the active device and window.
+        unsafe { (self.api().start_frame_capture)(ptr::null_mut(), ptr::null_mut()) };
+        Ok(())
+    }
+
+    pub fn end_capture(&self) -> Result<(), CaptureError> {
+        match unsafe { (self.api().end_frame_capture)(ptr::null_mut(), ptr::null_mut()) } {
+            0 => Err(CaptureError::Failed),
+            _ => Ok(()),
+        }
+    }
+}
@@ -5,11 +5,13 @@ use std::sync::Arc;
 use smallvec::SmallVec;
 use ash::vk;
 use ash::version::DeviceV1_0;
+use byteorder::{ByteOrder, NativeEndian};
 
-use hal::{buffer, command as com, memory, pso, query};
-use hal::{IndexCount, InstanceCount, VertexCount, VertexOffset, WorkGroupCount};
+use hal::{accel_struct, buffer, command as com, indirect, memory, pass, pso, query};
+use hal::{Features, IndexCount, InstanceCount, VertexCount, VertexOffset, WorkGroupCount};
 use hal::format::Aspects;
 use hal::image::{Filter, Layout, SubresourceRange};
+use hal::queue::QueueFamilyId;
 use {conv, native as n};
 use {Backend, RawDevice};
 
@@ -17,6 +19,10 @@ use {Backend, RawDevice};
 pub struct CommandBuffer {
     pub raw: vk::CommandBuffer,
     pub device: Arc<RawDevice>,
+    // Vertex buffers and push constants set by the user, restored after
+    // indirect sequences with state arguments changed them.
+    pub(crate) vertex_buffers: Vec<(vk::Buffer, vk::DeviceSize)>,
+    pub(crate) push_constants: Vec<u32>,
 }
 
 fn map_subpass_contents(contents: com::SubpassContents) -> vk::SubpassContents {
@@ -26,6 +32,13 @@ fn map_subpass_contents(contents: com::SubpassContents) -> vk::SubpassContents {
     }
 }
 
+fn map_queue_families(families: &Option<Range<QueueFamilyId>>) -> (u32, u32) {
+    match *families {
+        Some(ref families) => (families.start.0 as u32, families.end.0 as u32),
+        None => (vk::VK_QUEUE_FAMILY_IGNORED, vk::VK_QUEUE_FAMILY_IGNORED),
+    }
+}
+
 fn map_buffer_image_regions<T>(
     _image: &n::Image,
     regions: T,
@@ -76,6 +89,152 @@ impl CommandBuffer {
             );
         }
     }
+
+    fn set_push_constants(
+        &mut self,
+        layout: vk::PipelineLayout,
+        stages: vk::ShaderStageFlags,
+        offset: u32,
+        constants: &[u32],
+    ) {
+        let range = offset as usize .. offset as usize + constants.len();
+        if self.push_constants.len() < range.end {
+            self.push_constants.resize(range.end, 0);
+        }
+        self.push_constants[range].copy_from_slice(constants);
+
+        unsafe {
+            self.device.0.cmd_push_constants(
+                self.raw,
+                layout,
+                stages,
+                offset * 4,
+                memory::cast_slice(constants),
+            );
+        }
+    }
+
+    // Expand sequences with state arguments into separate commands, reading
+    // the arguments on the host.
+    fn execute_expanded(
+        &mut self,
+        signature: &n::CommandSignature,
+        buffer: &n::Buffer,
+        offset: buffer::Offset,
+        count: u32,
+    ) {
+        if count == 0 {
+            return;
+        }
+        let state_size: u32 = signature.state.iter().map(indirect::Argument::size).sum();
+        let size = signature.stride as buffer::Offset * (count - 1) as buffer::Offset
+            + (state_size + signature.command.size()) as buffer::Offset;
+        let data = match self.device.4.read(&self.device.0, buffer, offset .. offset + size) {
+            Some(data) => data,
+            None => {
+                error!("Indirect state arguments must be readable by the host");
+                return;
+            }
+        };
+
+        for i in 0 .. count as usize {
+            let mut position = i * signature.stride as usize;
+            for argument in &signature.state {
+                let bytes = &data[position .. position + argument.size() as usize];
+                match *argument {
+                    indirect::Argument::PushConstants { stages, offset: first, .. } => unsafe {
+                        self.device.0.cmd_push_constants(
+                            self.raw,
+                            signature.layout,
+                            conv::map_stage_flags(stages),
+                            first * 4,
+                            bytes,
+                        );
+                    },
+                    indirect::Argument::VertexBuffer(binding) => {
+                        // The size and stride of the binding are defined by the pipeline.
+                        let address = NativeEndian::read_u64(bytes);
+                        match self.device.4.resolve(address) {
+                            Some((raw, vertex_offset)) => unsafe {
+                                self.device.0.cmd_bind_vertex_buffers(
+                                    self.raw,
+                                    binding as u32,
+                                    &[raw],
+                                    &[vertex_offset],
+                                );
+                            },
+                            None => error!("Unknown vertex buffer address {:#x}", address),
+                        }
+                    }
+                    _ => unreachable!(),
+                }
+                position += argument.size() as usize;
+            }
+
+            let command_offset = offset + position as buffer::Offset;
+            unsafe {
+                match signature.command {
+                    indirect::Argument::Draw => self.device.0.cmd_draw_indirect(
+                        self.raw,
+                        buffer.raw,
+                        command_offset,
+                        1,
+                        signature.stride,
+                    ),
+                    indirect::Argument::DrawIndexed => self.device.0.cmd_draw_indexed_indirect(
+                        self.raw,
+                        buffer.raw,
+                        command_offset,
+                        1,
+                        signature.stride,
+                    ),
+                    indirect::Argument::Dispatch => self.device.0.cmd_dispatch_indirect(
+                        self.raw,
+                        buffer.raw,
+                        command_offset,
+                    ),
+                    _ => unreachable!(),
+                }
+            }
+        }
+
+        // Restore the state set by the user.
+        for argument in &signature.state {
+            match *argument {
+                indirect::Argument::PushConstants { stages, offset: first, count } => {
+                    let mut constants = vec![0u32; count as usize];
+                    for (i, constant) in constants.iter_mut().enumerate() {
+                        if let Some(&value) = self.push_constants.get(first as usize + i) {
+                            *constant = value;
+                        }
+                    }
+                    unsafe {
+                        self.device.0.cmd_push_constants(
+                            self.raw,
+                            signature.layout,
+                            conv::map_stage_flags(stages),
+                            first * 4,
+                            memory::cast_slice(&constants),
+                        );
+                    }
+                }
+                indirect::Argument::VertexBuffer(binding) => {
+                    match self.vertex_buffers.get(binding as usize) {
+                        Some(&(raw, vertex_offset)) if raw != vk::Buffer::null() => unsafe {
+                            self.device.0.cmd_bind_vertex_buffers(
+                                self.raw,
+                                binding as u32,
+                                &[raw],
+                                &[vertex_offset],
+                            );
+                        },
+                        _ => {}
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
 }
 
 impl com::RawCommandBuffer<Backend> for CommandBuffer {
@@ -97,19 +256,24 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
             flags: conv::map_command_buffer_flags(flags),
             p_inheritance_info: &inheritance_info,
         };
+        self.vertex_buffers.clear();
+        self.push_constants.clear();
 
         assert_eq!(Ok(()),
             unsafe { self.device.0.begin_command_buffer(self.raw, &info) }
         );
     }
 
-    fn finish(&mut self) {
+    fn finish(&mut self) -> Result<(), com::RecordingError> {
         assert_eq!(Ok(()), unsafe {
             self.device.0.end_command_buffer(self.raw)
         });
+        Ok(())
     }
 
     fn reset(&mut self, release_resources: bool) {
+        self.vertex_buffers.clear();
+        self.push_constants.clear();
         let flags = if release_resources {
             vk::COMMAND_BUFFER_RESET_RELEASE_RESOURCES_BIT
         } else {
@@ -176,6 +340,22 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         }
     }
 
+    fn begin_rendering<'a, T>(
+        &mut self,
+        _render_area: pso::Rect,
+        _colors: T,
+        _depth_stencil: Option<pass::RenderingAttachment<'a, Backend>>,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<pass::RenderingAttachment<'a, Backend>>,
+    {
+        unimplemented!()
+    }
+
+    fn end_rendering(&mut self) {
+        unimplemented!()
+    }
+
     fn pipeline_barrier<'a, T>(
         &mut self,
         stages: Range<pso::PipelineStage>,
@@ -207,21 +387,23 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                         dst_access_mask: conv::map_image_access(access.end),
                     });
                 }
-                memory::Barrier::Buffer { ref states, target} => {
+                memory::Barrier::Buffer { ref states, target, ref families } => {
+                    let (src_family, dst_family) = map_queue_families(families);
                     buffer_bars.push(vk::BufferMemoryBarrier {
                         s_type: vk::StructureType::BufferMemoryBarrier,
                         p_next: ptr::null(),
                         src_access_mask: conv::map_buffer_access(states.start),
                         dst_access_mask: conv::map_buffer_access(states.end),
-                        src_queue_family_index: vk::VK_QUEUE_FAMILY_IGNORED, // TODO
-                        dst_queue_family_index: vk::VK_QUEUE_FAMILY_IGNORED, // TODO
+                        src_queue_family_index: src_family,
+                        dst_queue_family_index: dst_family,
                         buffer: target.raw,
                         offset: 0,
                         size: vk::VK_WHOLE_SIZE,
                     });
                 }
-                memory::Barrier::Image { ref states, target, ref range } => {
+                memory::Barrier::Image { ref states, target, ref range, ref families } => {
                     let subresource_range = conv::map_subresource_range(range);
+                    let (src_family, dst_family) = map_queue_families(families);
                     image_bars.push(vk::ImageMemoryBarrier {
                         s_type: vk::StructureType::ImageMemoryBarrier,
                         p_next: ptr::null(),
@@ -229,8 +411,8 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                         dst_access_mask: conv::map_image_access(states.end.0),
                         old_layout: conv::map_image_layout(states.start.1),
                         new_layout: conv::map_image_layout(states.end.1),
-                        src_queue_family_index: vk::VK_QUEUE_FAMILY_IGNORED, // TODO
-                        dst_queue_family_index: vk::VK_QUEUE_FAMILY_IGNORED, // TODO
+                        src_queue_family_index: src_family,
+                        dst_queue_family_index: dst_family,
                         image: target.raw,
                         subresource_range,
                     });
@@ -337,7 +519,7 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         T: IntoIterator,
         T::Item: Borrow<com::AttachmentClear>,
         U: IntoIterator,
-        U::Item: Borrow<pso::Rect>,
+        U::Item: Borrow<pso::ClearRect>,
     {
         let clears: SmallVec<[vk::ClearAttachment; 16]> = clears
             .into_iter()
@@ -379,10 +561,11 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         let rects: SmallVec<[vk::ClearRect; 16]> = rects
             .into_iter()
             .map(|rect| {
+                let rect = rect.borrow();
                 vk::ClearRect {
-                    base_array_layer: 0,
-                    layer_count: vk::VK_REMAINING_ARRAY_LAYERS,
-                    rect: conv::map_rect(rect.borrow()),
+                    base_array_layer: rect.layers.start as _,
+                    layer_count: (rect.layers.end - rect.layers.start) as _,
+                    rect: conv::map_rect(&rect.rect),
                 }
             })
             .collect();
@@ -481,6 +664,12 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
             vbs.0.iter().map(|&(ref buffer, _)| buffer.raw).collect();
         let offsets: SmallVec<[vk::DeviceSize; 16]> =
             vbs.0.iter().map(|&(_, offset)| offset as u64).collect();
+        if self.vertex_buffers.len() < buffers.len() {
+            self.vertex_buffers.resize(buffers.len(), (vk::Buffer::null(), 0));
+        }
+        for (i, (&buffer, &offset)) in buffers.iter().zip(offsets.iter()).enumerate() {
+            self.vertex_buffers[i] = (buffer, offset);
+        }
 
         unsafe {
             self.device.0.cmd_bind_vertex_buffers(
@@ -559,6 +748,18 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         }
     }
 
+    fn set_depth_bounds(&mut self, bounds: Range<f32>) {
+        unsafe {
+            self.device.0.cmd_set_depth_bounds(self.raw, bounds.start, bounds.end);
+        }
+    }
+
+    fn set_line_width(&mut self, width: f32) {
+        unsafe {
+            self.device.0.cmd_set_line_width(self.raw, width);
+        }
+    }
+
     fn bind_graphics_pipeline(&mut self, pipeline: &n::GraphicsPipeline) {
         unsafe {
             self.device.0.cmd_bind_pipeline(
@@ -624,16 +825,44 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         }
     }
 
-    fn dispatch_indirect(&mut self, buffer: &n::Buffer, offset: buffer::Offset) {
-        unsafe {
-            self.device.0.cmd_dispatch_indirect(
-                self.raw,
-                buffer.raw,
-                offset,
-            )
+    fn dispatch_indirect(&mut self, buffer: &n::Buffer, offset: buffer::Offset, dispatch_count: u32) {
+        // Vulkan has no multi-dispatch variant, the arguments are tightly packed.
+        for i in 0 .. dispatch_count as buffer::Offset {
+            unsafe {
+                self.device.0.cmd_dispatch_indirect(
+                    self.raw,
+                    buffer.raw,
+                    offset + i * 12,
+                )
+            }
         }
     }
 
+    fn build_acceleration_structure<'a>(
+        &mut self,
+        _desc: &accel_struct::BuildDesc<'a, Backend>,
+        _dst: &(),
+        _scratch: (&n::Buffer, buffer::Offset),
+    ) {
+        unimplemented!()
+    }
+
+    fn bind_ray_tracing_pipeline(&mut self, _pipeline: &()) {
+        unimplemented!()
+    }
+
+    fn bind_ray_tracing_descriptor_sets<T>(&mut self, _layout: &n::PipelineLayout, _first_set: usize, _sets: T)
+    where
+        T: IntoIterator,
+        T::Item: Borrow<n::DescriptorSet>,
+    {
+        unimplemented!()
+    }
+
+    fn trace_rays<'a>(&mut self, _tables: &accel_struct::ShaderBindingTables<'a, Backend>, _count: [u32; 3]) {
+        unimplemented!()
+    }
+
     fn copy_buffer<T>(&mut self, src: &n::Buffer, dst: &n::Buffer, regions: T)
     where
         T: IntoIterator,
@@ -810,6 +1039,59 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         }
     }
 
+    fn execute_indirect(
+        &mut self,
+        signature: &n::CommandSignature,
+        buffer: &n::Buffer,
+        offset: buffer::Offset,
+        count: u32,
+    ) {
+        if !signature.state.is_empty() {
+            return self.execute_expanded(signature, buffer, offset, count);
+        }
+        // Without multi draw support the sequences are split into single draws.
+        let (draws, draw_count) = if self.device.1.contains(Features::MULTI_DRAW_INDIRECT) {
+            (1, count)
+        } else {
+            (count, 1)
+        };
+        let stride = signature.stride as buffer::Offset;
+        match signature.command {
+            indirect::Argument::Draw => for i in 0 .. draws as buffer::Offset {
+                unsafe {
+                    self.device.0.cmd_draw_indirect(
+                        self.raw,
+                        buffer.raw,
+                        offset + i * stride,
+                        draw_count,
+                        signature.stride,
+                    )
+                }
+            },
+            indirect::Argument::DrawIndexed => for i in 0 .. draws as buffer::Offset {
+                unsafe {
+                    self.device.0.cmd_draw_indexed_indirect(
+                        self.raw,
+                        buffer.raw,
+                        offset + i * stride,
+                        draw_count,
+                        signature.stride,
+                    )
+                }
+            },
+            indirect::Argument::Dispatch => for i in 0 .. count as buffer::Offset {
+                unsafe {
+                    self.device.0.cmd_dispatch_indirect(
+                        self.raw,
+                        buffer.raw,
+                        offset + i * stride,
+                    )
+                }
+            },
+            _ => unreachable!(),
+        }
+    }
+
     fn begin_query(
         &mut self,
         query: query::Query<Backend>,
@@ -823,7 +1105,7 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         unsafe {
             self.device.0.cmd_begin_query(
                 self.raw,
-                query.pool.0,
+                query.pool.raw,
                 query.id,
                 flags
             )
@@ -837,7 +1119,7 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         unsafe {
             self.device.0.cmd_end_query(
                 self.raw,
-                query.pool.0,
+                query.pool.raw,
                 query.id,
             )
         }
@@ -851,7 +1133,7 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         unsafe {
             self.device.0.cmd_reset_query_pool(
                 self.raw,
-                pool.0,
+                pool.raw,
                 queries.start,
                 queries.end - queries.start,
             )
@@ -867,27 +1149,42 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
             self.device.0.cmd_write_timestamp(
                 self.raw,
                 conv::map_pipeline_stage(stage),
-                query.pool.0,
+                query.pool.raw,
                 query.id,
             )
         }
     }
 
+    fn copy_query_pool_results(
+        &mut self,
+        pool: &n::QueryPool,
+        queries: Range<query::QueryId>,
+        buffer: &n::Buffer,
+        offset: buffer::Offset,
+        stride: buffer::Offset,
+        flags: query::ResultFlags,
+    ) {
+        unsafe {
+            self.device.0.cmd_copy_query_pool_results(
+                self.raw,
+                pool.raw,
+                queries.start,
+                queries.end - queries.start,
+                buffer.raw,
+                offset,
+                stride,
+                conv::map_query_result_flags(flags),
+            )
+        }
+    }
+
     fn push_compute_constants(
         &mut self,
         layout: &n::PipelineLayout,
         offset: u32,
         constants: &[u32],
     ) {
-        unsafe {
-            self.device.0.cmd_push_constants(
-                self.raw,
-                layout.raw,
-                vk::SHADER_STAGE_COMPUTE_BIT,
-                offset * 4,
-                memory::cast_slice(constants),
-            );
-        }
+        self.set_push_constants(layout.raw, vk::SHADER_STAGE_COMPUTE_BIT, offset, constants);
     }
 
     fn push_graphics_constants(
@@ -897,15 +1194,7 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         offset: u32,
         constants: &[u32],
     ) {
-        unsafe {
-            self.device.0.cmd_push_constants(
-                self.raw,
-                layout.raw,
-                conv::map_stage_flags(stages),
-                offset * 4,
-                memory::cast_slice(constants),
-            );
-        }
+        self.set_push_constants(layout.raw, conv::map_stage_flags(stages), offset, constants);
     }
 
     fn execute_commands<'a, I>(
@@ -14,12 +14,18 @@ use std::ptr;
 
 
 pub fn map_format(format: format::Format) -> vk::Format {
-    // Safe due to equivalence of HAL format values and Vulkan format values
-    unsafe { mem::transmute(format) }
+    match format {
+        // Video formats are not part of the core Vulkan formats.
+        format::Format::Nv12Unorm |
+        format::Format::P010Unorm |
+        format::Format::Yuy2Unorm => vk::Format::Undefined,
+        // Safe due to equivalence of HAL format values and Vulkan format values
+        _ => unsafe { mem::transmute(format) },
+    }
 }
 
 pub fn map_vk_format(format: vk::Format) -> Option<format::Format> {
-    if (format as usize) < format::NUM_FORMATS &&
+    if (format as usize) <= format::Format::Astc12x12Srgb as usize &&
         format != vk::Format::Undefined
     {
         // Safe due to equivalence of HAL format values and Vulkan format values
@@ -182,7 +188,27 @@ pub fn map_pipeline_stage(stage: pso::PipelineStage) -> vk::PipelineStageFlags {
     unsafe { mem::transmute(stage) }
 }
 
+pub fn map_sample_count(samples: image::NumSamples) -> vk::SampleCountFlags {
+    match samples {
+        1 => vk::SAMPLE_COUNT_1_BIT,
+        2 => vk::SAMPLE_COUNT_2_BIT,
+        4 => vk::SAMPLE_COUNT_4_BIT,
+        8 => vk::SAMPLE_COUNT_8_BIT,
+        16 => vk::SAMPLE_COUNT_16_BIT,
+        32 => vk::SAMPLE_COUNT_32_BIT,
+        64 => vk::SAMPLE_COUNT_64_BIT,
+        _ => panic!("Unsupported sample count: {}", samples),
+    }
+}
+
+pub fn map_query_result_flags(flags: query::ResultFlags) -> vk::QueryResultFlags {
+    // Safe due to equivalence of HAL values and Vulkan values
+    unsafe { mem::transmute(flags) }
+}
+
 pub fn map_buffer_usage(usage: buffer::Usage) -> vk::BufferUsageFlags {
+    // Tracking is not supported, the flag has no Vulkan equivalent.
+    let usage = usage - buffer::Usage::TRACKED;
     // Safe due to equivalence of HAL values and Vulkan values
     unsafe { mem::transmute(usage) }
 }
@@ -197,6 +223,11 @@ pub fn map_descriptor_type(ty: pso::DescriptorType) -> vk::DescriptorType {
     unsafe { mem::transmute(ty) }
 }
 
+pub fn map_descriptor_pool_create_flags(flags: pso::DescriptorPoolCreateFlags) -> vk::DescriptorPoolCreateFlags {
+    // the flag values have to match Vulkan
+    unsafe { mem::transmute(flags) }
+}
+
 pub fn map_stage_flags(stages: pso::ShaderStageFlags) -> vk::ShaderStageFlags {
     // Safe due to equivalence of HAL values and Vulkan values
     unsafe { mem::transmute(stages) }
@@ -250,7 +281,8 @@ pub fn map_topology(prim: Primitive) -> vk::PrimitiveTopology {
 pub fn map_polygon_mode(rm: pso::PolygonMode) -> (vk::PolygonMode, f32) {
     match rm {
         pso::PolygonMode::Point   => (vk::PolygonMode::Point, 1.0),
-        pso::PolygonMode::Line(w) => (vk::PolygonMode::Line, w),
+        pso::PolygonMode::Line(pso::State::Static(w)) => (vk::PolygonMode::Line, w),
+        pso::PolygonMode::Line(pso::State::Dynamic) => (vk::PolygonMode::Line, 1.0),
         pso::PolygonMode::Fill    => (vk::PolygonMode::Fill, 1.0),
     }
 }
@@ -359,6 +391,28 @@ pub fn map_blend_op(
     }
 }
 
+pub fn map_logic_op(op: &pso::LogicOp) -> vk::LogicOp {
+    use hal::pso::LogicOp::*;
+    match *op {
+        Clear => vk::LogicOp::Clear,
+        And => vk::LogicOp::And,
+        AndReverse => vk::LogicOp::AndReverse,
+        AndInverted => vk::LogicOp::AndInverted,
+        Copy => vk::LogicOp::Copy,
+        CopyInverted => vk::LogicOp::CopyInverted,
+        NoOp => vk::LogicOp::NoOp,
+        Xor => vk::LogicOp::Xor,
+        Nor => vk::LogicOp::Nor,
+        Or => vk::LogicOp::Or,
+        OrReverse => vk::LogicOp::OrReverse,
+        OrInverted => vk::LogicOp::OrInverted,
+        Equivalent => vk::LogicOp::Equivalent,
+        Invert => vk::LogicOp::Invert,
+        Nand => vk::LogicOp::Nand,
+        Set => vk::LogicOp::Set,
+    }
+}
+
 pub fn map_specialization_constants(
     specialization: &[pso::Specialization],
     data: &mut SmallVec<[u8; 64]>,
@@ -504,6 +558,8 @@ pub fn map_viewport(vp: &pso::Viewport) -> vk::Viewport {
 }
 
 pub fn map_image_flags(flags: image::StorageFlags) -> vk::ImageCreateFlags {
+    // Tracking is not supported, the flag has no Vulkan equivalent.
+    let flags = flags - image::StorageFlags::TRACKED;
     // the flag values have to match Vulkan
     unsafe { mem::transmute(flags) }
 }
@@ -3,7 +3,7 @@ use ash::extensions as ext;
 use ash::version::DeviceV1_0;
 use smallvec::SmallVec;
 
-use hal::{buffer, device as d, format, image, mapping, pass, pso, query, queue};
+use hal::{accel_struct, buffer, device as d, format, image, indirect, mapping, pass, pso, query, queue};
 use hal::{Backbuffer, Features, MemoryTypeId, SwapchainConfig};
 use hal::error::HostExecutionError;
 use hal::memory::Requirements;
@@ -18,7 +18,7 @@ use std::ops::Range;
 use std::sync::Arc;
 
 use {Backend as B, Device};
-use {conv, native as n, result, window as w};
+use {conv, khr, native as n, result, window as w};
 use pool::RawCommandPool;
 
 
@@ -69,8 +69,9 @@ impl d::Device<B> for Device {
         };
 
         let memory = unsafe {
-            self.raw.0.allocate_memory(&info, None)
+            self.raw.0.allocate_memory(&info, self.raw.allocator())
         }.expect("Error on memory allocation"); // TODO: error handling
+        self.raw.4.allocated(memory, mem_type.0);
 
         Ok(n::Memory { raw: memory })
     }
@@ -95,7 +96,7 @@ impl d::Device<B> for Device {
 
         let command_pool_raw = unsafe {
             self.raw.0
-                .create_command_pool(&info, None)
+                .create_command_pool(&info, self.raw.allocator())
         }.expect("Error on command pool creation"); // TODO: better error handling
 
         RawCommandPool {
@@ -107,7 +108,7 @@ impl d::Device<B> for Device {
     fn destroy_command_pool(&self, pool: RawCommandPool) {
         unsafe {
             self.raw.0
-                .destroy_command_pool(pool.raw, None)
+                .destroy_command_pool(pool.raw, self.raw.allocator())
         };
     }
 
@@ -145,9 +146,15 @@ impl d::Device<B> for Device {
         }).collect::<Vec<_>>();
 
         let mut attachment_refs = Vec::new();
+        let mut view_masks = Vec::new();
+        let mut correlation_masks = Vec::new();
 
         let subpasses = subpasses.into_iter().map(|subpass| {
             let subpass = subpass.borrow();
+            view_masks.push(subpass.view_mask);
+            if subpass.correlation_mask != 0 {
+                correlation_masks.push(subpass.correlation_mask);
+            }
             {
                 fn make_ref(&(id, layout): &pass::AttachmentRef) -> vk::AttachmentReference {
                     vk::AttachmentReference {
@@ -204,9 +211,24 @@ impl d::Device<B> for Device {
             }
         }).collect::<Vec<_>>();
 
+        let multiview = khr::RenderPassMultiviewCreateInfo {
+            s_type: khr::STRUCTURE_TYPE_RENDER_PASS_MULTIVIEW_CREATE_INFO,
+            p_next: ptr::null(),
+            subpass_count: view_masks.len() as u32,
+            p_view_masks: view_masks.as_ptr(),
+            dependency_count: 0,
+            p_view_offsets: ptr::null(),
+            correlation_mask_count: correlation_masks.len() as u32,
+            p_correlation_masks: correlation_masks.as_ptr(),
+        };
+
         let info = vk::RenderPassCreateInfo {
             s_type: vk::StructureType::RenderPassCreateInfo,
-            p_next: ptr::null(),
+            p_next: if view_masks.iter().any(|&mask| mask != 0) {
+                &multiview as *const _ as *const _
+            } else {
+                ptr::null()
+            },
             flags: vk::RenderPassCreateFlags::empty(),
             attachment_count: attachments.len() as u32,
             p_attachments: attachments.as_ptr(),
@@ -217,7 +239,7 @@ impl d::Device<B> for Device {
         };
 
         let renderpass = unsafe {
-            self.raw.0.create_render_pass(&info, None)
+            self.raw.0.create_render_pass(&info, self.raw.allocator())
                 .expect("Error on render pass creation") // TODO: handle this better
         };
 
@@ -261,7 +283,7 @@ impl d::Device<B> for Device {
         };
 
         let raw = unsafe {
-            self.raw.0.create_pipeline_layout(&info, None)
+            self.raw.0.create_pipeline_layout(&info, self.raw.allocator())
                 .expect("Error on pipeline signature creation") // TODO: handle this better
         };
 
@@ -288,6 +310,7 @@ impl d::Device<B> for Device {
         let mut info_tessellation_states   = Vec::with_capacity(descs.len());
         let mut info_viewport_states       = Vec::with_capacity(descs.len());
         let mut info_rasterization_states  = Vec::with_capacity(descs.len());
+        let mut info_depth_clip_states     = Vec::with_capacity(descs.len());
         let mut info_multisample_states    = Vec::with_capacity(descs.len());
         let mut info_depth_stencil_states  = Vec::with_capacity(descs.len());
         let mut info_color_blend_states    = Vec::with_capacity(descs.len());
@@ -298,6 +321,7 @@ impl d::Device<B> for Device {
         let mut dynamic_states             = Vec::with_capacity(descs.len() * MAX_DYNAMIC_STATES);
         let mut viewports                  = Vec::with_capacity(descs.len());
         let mut scissors                   = Vec::with_capacity(descs.len());
+        let mut sample_masks               = Vec::with_capacity(descs.len());
 
         let mut c_strings = Vec::new(); // hold the C strings temporarily
         let mut make_stage = |stage, source: &pso::EntryPoint<'a, B>| {
@@ -406,9 +430,25 @@ impl d::Device<B> for Device {
                 primitive_restart_enable: vk::VK_FALSE,
             });
 
+            // Without depth clip control, clipping is disabled by enabling depth clamping.
+            let depth_clip_next = if self.raw.1.contains(Features::DEPTH_CLIP_CONTROL) {
+                info_depth_clip_states.push(khr::PipelineRasterizationDepthClipStateCreateInfo {
+                    s_type: khr::STRUCTURE_TYPE_PIPELINE_RASTERIZATION_DEPTH_CLIP_STATE_CREATE_INFO_EXT,
+                    p_next: ptr::null(),
+                    flags: 0,
+                    depth_clip_enable: if desc.rasterizer.depth_clipping { vk::VK_TRUE } else { vk::VK_FALSE },
+                });
+                info_depth_clip_states.last().unwrap() as *const _ as *const _
+            } else {
+                if desc.rasterizer.depth_clipping == desc.rasterizer.depth_clamping {
+                    warn!("Depth clipping can't be controlled independently of depth clamping on this device");
+                }
+                ptr::null()
+            };
+
             info_rasterization_states.push(vk::PipelineRasterizationStateCreateInfo {
                 s_type: vk::StructureType::PipelineRasterizationStateCreateInfo,
-                p_next: ptr::null(),
+                p_next: depth_clip_next,
                 flags: vk::PipelineRasterizationStateCreateFlags::empty(),
                 depth_clamp_enable: if desc.rasterizer.depth_clamping {
                     if self.raw.1.contains(Features::DEPTH_CLAMP) {
@@ -443,11 +483,15 @@ impl d::Device<B> for Device {
 
             let dynamic_state_base = dynamic_states.len();
 
+            if let pso::PolygonMode::Line(pso::State::Dynamic) = desc.rasterizer.polygon_mode {
+                dynamic_states.push(vk::DynamicState::LineWidth);
+            }
+
             info_viewport_states.push(vk::PipelineViewportStateCreateInfo {
                 s_type: vk::StructureType::PipelineViewportStateCreateInfo,
                 p_next: ptr::null(),
                 flags: vk::PipelineViewportStateCreateFlags::empty(),
-                scissor_count: 1, // TODO
+                scissor_count: desc.viewport_count as _,
                 p_scissors: match desc.baked_states.scissor {
                     Some(ref rect) => {
                         scissors.push(conv::map_rect(rect));
@@ -458,7 +502,7 @@ impl d::Device<B> for Device {
                         ptr::null()
                     },
                 },
-                viewport_count: 1, // TODO
+                viewport_count: desc.viewport_count as _,
                 p_viewports:  match desc.baked_states.viewport {
                     Some(ref vp) => {
                         viewports.push(conv::map_viewport(vp));
@@ -471,15 +515,30 @@ impl d::Device<B> for Device {
                 },
             });
 
+            let multisampling = desc.multisampling
+                .clone()
+                .unwrap_or(pso::Multisampling::new(1));
+            sample_masks.push([
+                multisampling.sample_mask as u32,
+                (multisampling.sample_mask >> 32) as u32,
+            ]);
             info_multisample_states.push(vk::PipelineMultisampleStateCreateInfo {
                 s_type: vk::StructureType::PipelineMultisampleStateCreateInfo,
                 p_next: ptr::null(),
                 flags: vk::PipelineMultisampleStateCreateFlags::empty(),
-                rasterization_samples: vk::SAMPLE_COUNT_1_BIT, // TODO
-                sample_shading_enable: vk::VK_FALSE, // TODO
-                min_sample_shading: 0.0,  // TODO
-                p_sample_mask: ptr::null(), // TODO
-                alpha_to_coverage_enable: vk::VK_FALSE, // TODO
+                rasterization_samples: conv::map_sample_count(multisampling.rasterization_samples),
+                sample_shading_enable: if multisampling.sample_shading.is_some() {
+                    vk::VK_TRUE
+                } else {
+                    vk::VK_FALSE
+                },
+                min_sample_shading: multisampling.sample_shading.unwrap_or(0.0),
+                p_sample_mask: sample_masks.last().unwrap().as_ptr(),
+                alpha_to_coverage_enable: if desc.blender.alpha_coverage {
+                    vk::VK_TRUE
+                } else {
+                    vk::VK_FALSE
+                },
                 alpha_to_one_enable: vk::VK_FALSE, // TODO
             });
 
@@ -496,6 +555,15 @@ impl d::Device<B> for Device {
                 ),
                 pso::StencilTest::Off => unsafe { mem::zeroed() },
             };
+            let (min_depth_bounds, max_depth_bounds) = match desc.baked_states.depth_bounds {
+                Some(ref range) => (range.start, range.end),
+                None => {
+                    if depth_stencil.depth_bounds {
+                        dynamic_states.push(vk::DynamicState::DepthBounds);
+                    }
+                    (0.0, 1.0)
+                },
+            };
 
             info_depth_stencil_states.push(vk::PipelineDepthStencilStateCreateInfo {
                 s_type: vk::StructureType::PipelineDepthStencilStateCreateInfo,
@@ -508,8 +576,8 @@ impl d::Device<B> for Device {
                 stencil_test_enable,
                 front,
                 back,
-                min_depth_bounds: 0.0,
-                max_depth_bounds: 1.0,
+                min_depth_bounds,
+                max_depth_bounds,
             });
 
             // Build blend states for color attachments
@@ -545,8 +613,14 @@ impl d::Device<B> for Device {
                 s_type: vk::StructureType::PipelineColorBlendStateCreateInfo,
                 p_next: ptr::null(),
                 flags: vk::PipelineColorBlendStateCreateFlags::empty(),
-                logic_op_enable: vk::VK_FALSE, // TODO
-                logic_op: vk::LogicOp::Clear,
+                logic_op_enable: if desc.blender.logic_op.is_some() {
+                    vk::VK_TRUE
+                } else {
+                    vk::VK_FALSE
+                },
+                logic_op: desc.blender.logic_op
+                    .as_ref()
+                    .map_or(vk::LogicOp::Clear, conv::map_logic_op),
                 attachment_count: color_attachments.last().unwrap().len() as _,
                 p_attachments: color_attachments.last().unwrap().as_ptr(), // TODO:
                 blend_constants: match desc.baked_states.blend_color {
@@ -617,7 +691,7 @@ impl d::Device<B> for Device {
                 self.raw.0.create_graphics_pipelines(
                     vk::PipelineCache::null(),
                     &valid_infos,
-                    None,
+                    self.raw.allocator(),
                 )
             }
         };
@@ -723,7 +797,7 @@ impl d::Device<B> for Device {
                 self.raw.0.create_compute_pipelines(
                     vk::PipelineCache::null(),
                     &valid_infos,
-                    None,
+                    self.raw.allocator(),
                 )
             }
         };
@@ -775,7 +849,7 @@ impl d::Device<B> for Device {
         };
 
         let framebuffer = unsafe {
-            self.raw.0.create_framebuffer(&info, None)
+            self.raw.0.create_framebuffer(&info, self.raw.allocator())
         }.expect("error on framebuffer creation");
 
         Ok(n::Framebuffer { raw: framebuffer })
@@ -794,7 +868,7 @@ impl d::Device<B> for Device {
         };
 
         let module = unsafe {
-            self.raw.0.create_shader_module(&info, None)
+            self.raw.0.create_shader_module(&info, self.raw.allocator())
         };
 
         match module {
@@ -820,6 +894,10 @@ impl d::Device<B> for Device {
                 }
             },
         };
+        if sampler_info.reduction != image::Reduction::WeightedAverage {
+            // TODO: VK_EXT_sampler_filter_minmax
+            error!("Sampler reduction {:?} is not supported", sampler_info.reduction);
+        }
         let info = vk::SamplerCreateInfo {
             s_type: vk::StructureType::SamplerCreateInfo,
             p_next: ptr::null(),
@@ -848,7 +926,7 @@ impl d::Device<B> for Device {
         };
 
         let sampler = unsafe {
-            self.raw.0.create_sampler(&info, None)
+            self.raw.0.create_sampler(&info, self.raw.allocator())
                         .expect("error on sampler creation")
         };
 
@@ -869,7 +947,7 @@ impl d::Device<B> for Device {
         };
 
         let buffer = unsafe {
-            self.raw.0.create_buffer(&info, None)
+            self.raw.0.create_buffer(&info, self.raw.allocator())
                 .expect("Error on buffer creation") // TODO: error handling
         };
 
@@ -893,6 +971,8 @@ impl d::Device<B> for Device {
 
         let buffer = n::Buffer {
             raw: buffer.0.raw,
+            memory: memory.raw,
+            offset,
         };
 
         Ok(buffer)
@@ -913,7 +993,7 @@ impl d::Device<B> for Device {
         };
 
         let view = unsafe {
-            self.raw.0.create_buffer_view(&info, None)
+            self.raw.0.create_buffer_view(&info, self.raw.allocator())
         }.expect("Error on buffer view creation"); //TODO: Proper error handling
 
         Ok(n::BufferView { raw: view })
@@ -960,7 +1040,7 @@ impl d::Device<B> for Device {
         };
 
         let raw = unsafe {
-            self.raw.0.create_image(&info, None)
+            self.raw.0.create_image(&info, self.raw.allocator())
         }.expect("Error on image creation"); // TODO: error handling
 
         Ok(UnboundImage(n::Image{ raw, ty: image_type, flags, extent }))
@@ -1010,7 +1090,7 @@ impl d::Device<B> for Device {
         };
 
         let view = unsafe {
-            self.raw.0.create_image_view(&info, None)
+            self.raw.0.create_image_view(&info, self.raw.allocator())
         }.expect("Error on image view creation"); // TODO
 
         Ok(n::ImageView {
@@ -1020,7 +1100,9 @@ impl d::Device<B> for Device {
         })
     }
 
-    fn create_descriptor_pool<T>(&self, max_sets: usize, descriptor_pools: T) -> n::DescriptorPool
+    fn create_descriptor_pool<T>(
+        &self, max_sets: usize, descriptor_pools: T, flags: pso::DescriptorPoolCreateFlags,
+    ) -> n::DescriptorPool
     where
         T: IntoIterator,
         T::Item: Borrow<pso::DescriptorRangeDesc>,
@@ -1036,14 +1118,14 @@ impl d::Device<B> for Device {
         let info = vk::DescriptorPoolCreateInfo {
             s_type: vk::StructureType::DescriptorPoolCreateInfo,
             p_next: ptr::null(),
-            flags: vk::DescriptorPoolCreateFlags::empty(), // disallow individual freeing
+            flags: conv::map_descriptor_pool_create_flags(flags),
             max_sets: max_sets as u32,
             pool_size_count: pools.len() as u32,
             p_pool_sizes: pools.as_ptr(),
         };
 
         let pool = unsafe {
-            self.raw.0.create_descriptor_pool(&info, None)
+            self.raw.0.create_descriptor_pool(&info, self.raw.allocator())
                         .expect("Error on descriptor set pool creation") // TODO
         };
 
@@ -1066,13 +1148,31 @@ impl d::Device<B> for Device {
             .collect::<Vec<_>>()
         );
 
-        let raw_bindings = bindings.iter().map(|b| {
+        // The immutable samplers are owned by the layout.
+        let immutable_samplers = bindings
+            .iter()
+            .map(|b| {
+                b.immutable_samplers
+                    .iter()
+                    .map(|info| self.create_sampler(info.clone()).0)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let raw_bindings = bindings.iter().zip(&immutable_samplers).map(|(b, samplers)| {
+            if !samplers.is_empty() {
+                assert_eq!(samplers.len(), b.count);
+            }
             vk::DescriptorSetLayoutBinding {
                 binding: b.binding,
                 descriptor_type: conv::map_descriptor_type(b.ty),
                 descriptor_count: b.count as _,
                 stage_flags: conv::map_stage_flags(b.stage_flags),
-                p_immutable_samplers: ptr::null(), // TODO
+                p_immutable_samplers: if samplers.is_empty() {
+                    ptr::null()
+                } else {
+                    samplers.as_ptr()
+                },
             }
         }).collect::<Vec<_>>();
 
@@ -1087,12 +1187,13 @@ impl d::Device<B> for Device {
         };
 
         let layout = unsafe {
-            self.raw.0.create_descriptor_set_layout(&info, None)
+            self.raw.0.create_descriptor_set_layout(&info, self.raw.allocator())
         }.expect("Error on descriptor set layout creation"); // TODO
 
         n::DescriptorSetLayout {
             raw: layout,
             bindings,
+            immutable_samplers: immutable_samplers.into_iter().flat_map(|samplers| samplers).collect(),
         }
     }
 
@@ -1163,6 +1264,7 @@ impl d::Device<B> for Device {
                     pso::Descriptor::TexelBuffer(view) => {
                         texel_buffer_views.push(view.raw);
                     }
+                    pso::Descriptor::AccelerationStructure(_) => unimplemented!(),
                 }
             }
 
@@ -1248,11 +1350,13 @@ impl d::Device<B> for Device {
                 vk::MemoryMapFlags::empty(),
             ).expect("Error on memory mapping") // TODO
         };
+        self.raw.4.mapped(memory.raw, offset, size, ptr as *mut _);
 
         Ok(ptr as *mut _)
     }
 
     fn unmap_memory(&self, memory: &n::Memory) {
+        self.raw.4.unmapped(memory.raw);
         unsafe { self.raw.0.unmap_memory(memory.raw) }
     }
 
@@ -1292,7 +1396,7 @@ impl d::Device<B> for Device {
         };
 
         let semaphore = unsafe {
-            self.raw.0.create_semaphore(&info, None)
+            self.raw.0.create_semaphore(&info, self.raw.allocator())
                         .expect("Error on semaphore creation") // TODO: error handling
         };
 
@@ -1311,7 +1415,7 @@ impl d::Device<B> for Device {
         };
 
         let fence = unsafe {
-            self.raw.0.create_fence(&info, None)
+            self.raw.0.create_fence(&info, self.raw.allocator())
                         .expect("Error on fence creation") // TODO: error handling
         };
 
@@ -1361,10 +1465,18 @@ impl d::Device<B> for Device {
     }
 
     fn free_memory(&self, memory: n::Memory) {
-        unsafe { self.raw.0.free_memory(memory.raw, None); }
+        self.raw.4.freed(memory.raw);
+        unsafe { self.raw.0.free_memory(memory.raw, self.raw.allocator()); }
     }
 
-    fn create_query_pool(&self, ty: query::QueryType, query_count: u32) -> n::QueryPool {
+    fn create_query_pool(
+        &self,
+        ty: query::QueryType,
+        query_count: u32,
+        _flags: query::QueryPoolCreateFlags,
+    ) -> n::QueryPool {
+        // Occlusion results are non-zero if any samples passed, which is all
+        // predicated rendering needs, so `PREDICATION` requires no changes.
         let (query_type, pipeline_statistics) = match ty {
             query::QueryType::Occlusion =>
                 (vk::QueryType::Occlusion, vk::QueryPipelineStatisticFlags::empty()),
@@ -1384,20 +1496,29 @@ impl d::Device<B> for Device {
         };
 
         let pool = unsafe {
-            self.raw.0.create_query_pool(&info, None)
+            self.raw.0.create_query_pool(&info, self.raw.allocator())
                         .expect("Error on query pool creation") // TODO: error handling
         };
 
-        n::QueryPool(pool)
+        // Pipeline statistics write one value per enabled statistic.
+        let values = match ty {
+            query::QueryType::PipelineStatistics(statistics) => statistics.bits().count_ones(),
+            query::QueryType::Occlusion | query::QueryType::Timestamp => 1,
+        };
+
+        n::QueryPool {
+            raw: pool,
+            stride: values as buffer::Offset * 8,
+        }
     }
 
     fn create_swapchain(
         &self,
         surface: &mut w::Surface,
         config: SwapchainConfig,
-    ) -> (w::Swapchain, Backbuffer<B>) {
+    ) -> Result<(w::Swapchain, Backbuffer<B>), hal::window::CreationError> {
         let functor = ext::Swapchain::new(&surface.raw.instance.0, &self.raw.0)
-            .expect("Unable to query swapchain function");
+            .map_err(|_| hal::window::CreationError::InitializationFailed)?;
 
         // TODO: check for better ones if available
         let present_mode = vk::PresentModeKHR::Fifo; // required to be supported
@@ -1429,11 +1550,21 @@ impl d::Device<B> for Device {
             old_swapchain: vk::SwapchainKHR::null(),
         };
 
-        let swapchain_raw = unsafe { functor.create_swapchain_khr(&info, None) }
-            .expect("Unable to create a swapchain");
-
-        let backbuffer_images = functor.get_swapchain_images_khr(swapchain_raw)
-            .expect("Unable to get swapchain images");
+        let map_err = |err| match err {
+            vk::Result::ErrorOutOfHostMemory |
+            vk::Result::ErrorOutOfDeviceMemory => hal::window::CreationError::OutOfMemory,
+            _ => hal::window::CreationError::InitializationFailed,
+        };
+        let swapchain_raw = unsafe { functor.create_swapchain_khr(&info, self.raw.allocator()) }
+            .map_err(map_err)?;
+
+        let backbuffer_images = match functor.get_swapchain_images_khr(swapchain_raw) {
+            Ok(images) => images,
+            Err(err) => {
+                unsafe { functor.destroy_swapchain_khr(swapchain_raw, self.raw.allocator()); }
+                return Err(map_err(err));
+            }
+        };
 
         let swapchain = w::Swapchain {
             raw: swapchain_raw,
@@ -1457,75 +1588,142 @@ impl d::Device<B> for Device {
             })
             .collect();
 
-        (swapchain, Backbuffer::Images(images))
+        Ok((swapchain, Backbuffer::Images(images)))
     }
 
     fn destroy_swapchain(&self, swapchain: w::Swapchain) {
-        unsafe { swapchain.functor.destroy_swapchain_khr(swapchain.raw, None); }
+        unsafe { swapchain.functor.destroy_swapchain_khr(swapchain.raw, self.raw.allocator()); }
     }
 
     fn destroy_query_pool(&self, pool: n::QueryPool) {
-        unsafe { self.raw.0.destroy_query_pool(pool.0, None); }
+        unsafe { self.raw.0.destroy_query_pool(pool.raw, self.raw.allocator()); }
+    }
+
+    fn create_command_signature(
+        &self,
+        arguments: &[indirect::Argument],
+        stride: u32,
+        layout: Option<&n::PipelineLayout>,
+    ) -> Result<n::CommandSignature, indirect::CreationError> {
+        indirect::validate_sequence(arguments, stride, layout.is_some())?;
+        // State arguments are expanded on the host when executing the sequences,
+        // `VK_NV_device_generated_commands` isn't exposed by ash yet.
+        let (command, state) = arguments.split_last().unwrap();
+        Ok(n::CommandSignature {
+            state: state.to_vec(),
+            command: command.clone(),
+            stride,
+            layout: layout.map_or(vk::PipelineLayout::null(), |layout| layout.raw),
+        })
+    }
+
+    fn destroy_command_signature(&self, _signature: n::CommandSignature) {
+        // Just drop
     }
 
     fn destroy_shader_module(&self, module: n::ShaderModule) {
-        unsafe { self.raw.0.destroy_shader_module(module.raw, None); }
+        unsafe { self.raw.0.destroy_shader_module(module.raw, self.raw.allocator()); }
     }
 
     fn destroy_render_pass(&self, rp: n::RenderPass) {
-        unsafe { self.raw.0.destroy_render_pass(rp.raw, None); }
+        unsafe { self.raw.0.destroy_render_pass(rp.raw, self.raw.allocator()); }
     }
 
     fn destroy_pipeline_layout(&self, pl: n::PipelineLayout) {
-        unsafe { self.raw.0.destroy_pipeline_layout(pl.raw, None); }
+        unsafe { self.raw.0.destroy_pipeline_layout(pl.raw, self.raw.allocator()); }
     }
 
     fn destroy_graphics_pipeline(&self, pipeline: n::GraphicsPipeline) {
-        unsafe { self.raw.0.destroy_pipeline(pipeline.0, None); }
+        unsafe { self.raw.0.destroy_pipeline(pipeline.0, self.raw.allocator()); }
     }
 
     fn destroy_compute_pipeline(&self, pipeline: n::ComputePipeline) {
-        unsafe { self.raw.0.destroy_pipeline(pipeline.0, None); }
+        unsafe { self.raw.0.destroy_pipeline(pipeline.0, self.raw.allocator()); }
+    }
+
+    fn create_ray_tracing_pipeline<'a>(
+        &self, _desc: &pso::RayTracingPipelineDesc<'a, B>,
+    ) -> Result<(), pso::CreationError> {
+        unimplemented!()
+    }
+
+    fn destroy_ray_tracing_pipeline(&self, _pipeline: ()) {
+        unimplemented!()
+    }
+
+    fn get_ray_tracing_shader_group_handles(&self, _pipeline: &(), _groups: Range<u32>) -> Vec<u8> {
+        unimplemented!()
+    }
+
+    fn get_acceleration_structure_prebuild_info<'a>(
+        &self, _desc: &accel_struct::BuildDesc<'a, B>,
+    ) -> accel_struct::PrebuildInfo {
+        unimplemented!()
+    }
+
+    fn create_acceleration_structure<'a>(
+        &self, _desc: &accel_struct::Desc<'a, B>,
+    ) -> Result<(), accel_struct::CreationError> {
+        unimplemented!()
+    }
+
+    fn get_acceleration_structure_address(&self, _accel: &()) -> u64 {
+        unimplemented!()
+    }
+
+    fn destroy_acceleration_structure(&self, _accel: ()) {
+        unimplemented!()
     }
 
     fn destroy_framebuffer(&self, fb: n::Framebuffer) {
-        unsafe { self.raw.0.destroy_framebuffer(fb.raw, None); }
+        unsafe { self.raw.0.destroy_framebuffer(fb.raw, self.raw.allocator()); }
+    }
+
+    fn get_buffer_address(&self, buffer: &n::Buffer) -> u64 {
+        // VK_KHR_buffer_device_address isn't exposed by `ash` yet, the addresses
+        // are only understood by the vertex buffer arguments of `execute_indirect`.
+        let requirements = unsafe { self.raw.0.get_buffer_memory_requirements(buffer.raw) };
+        self.raw.4.address(buffer.raw, requirements.size)
     }
 
     fn destroy_buffer(&self, buffer: n::Buffer) {
-        unsafe { self.raw.0.destroy_buffer(buffer.raw, None); }
+        self.raw.4.destroyed(buffer.raw);
+        unsafe { self.raw.0.destroy_buffer(buffer.raw, self.raw.allocator()); }
     }
 
     fn destroy_buffer_view(&self, view: n::BufferView) {
-        unsafe { self.raw.0.destroy_buffer_view(view.raw, None); }
+        unsafe { self.raw.0.destroy_buffer_view(view.raw, self.raw.allocator()); }
     }
 
     fn destroy_image(&self, image: n::Image) {
-        unsafe { self.raw.0.destroy_image(image.raw, None); }
+        unsafe { self.raw.0.destroy_image(image.raw, self.raw.allocator()); }
     }
 
     fn destroy_image_view(&self, view: n::ImageView) {
-        unsafe { self.raw.0.destroy_image_view(view.view, None); }
+        unsafe { self.raw.0.destroy_image_view(view.view, self.raw.allocator()); }
     }
 
     fn destroy_sampler(&self, sampler: n::Sampler) {
-        unsafe { self.raw.0.destroy_sampler(sampler.0, None); }
+        unsafe { self.raw.0.destroy_sampler(sampler.0, self.raw.allocator()); }
     }
 
     fn destroy_descriptor_pool(&self, pool: n::DescriptorPool) {
-        unsafe { self.raw.0.destroy_descriptor_pool(pool.raw, None); }
+        unsafe { self.raw.0.destroy_descriptor_pool(pool.raw, self.raw.allocator()); }
     }
 
     fn destroy_descriptor_set_layout(&self, layout: n::DescriptorSetLayout) {
-        unsafe { self.raw.0.destroy_descriptor_set_layout(layout.raw, None); }
+        unsafe { self.raw.0.destroy_descriptor_set_layout(layout.raw, self.raw.allocator()); }
+        for sampler in layout.immutable_samplers {
+            unsafe { self.raw.0.destroy_sampler(sampler, self.raw.allocator()); }
+        }
     }
 
     fn destroy_fence(&self, fence: n::Fence) {
-        unsafe { self.raw.0.destroy_fence(fence.0, None); }
+        unsafe { self.raw.0.destroy_fence(fence.0, self.raw.allocator()); }
     }
 
     fn destroy_semaphore(&self, semaphore: n::Semaphore) {
-        unsafe { self.raw.0.destroy_semaphore(semaphore.0, None); }
+        unsafe { self.raw.0.destroy_semaphore(semaphore.0, self.raw.allocator()); }
     }
 
     fn wait_idle(&self) -> Result<(), HostExecutionError> {
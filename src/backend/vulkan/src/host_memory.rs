@@ -0,0 +1,91 @@
+//! `VkAllocationCallbacks` forwarding to `hal::host_memory::AllocationCallbacks`.
+
+use ash::vk;
+use hal::host_memory::{AllocationCallbacks, Scope};
+
+use std::sync::Arc;
+
+/// Native allocation callbacks of an instance or device.
+pub struct HostAllocator {
+    // Boxed to keep the user data pointer of `raw` stable.
+    #[allow(dead_code)]
+    callbacks: Box<Arc<AllocationCallbacks>>,
+    raw: vk::AllocationCallbacks,
+}
+
+unsafe impl Send for HostAllocator {}
+unsafe impl Sync for HostAllocator {}
+
+impl HostAllocator {
+    pub fn new(callbacks: Arc<AllocationCallbacks>) -> Self {
+        let callbacks = Box::new(callbacks);
+        let raw = vk::AllocationCallbacks {
+            p_user_data: &*callbacks as *const Arc<AllocationCallbacks> as *mut _,
+            pfn_allocation: allocation,
+            pfn_reallocation: reallocation,
+            pfn_free: free,
+            pfn_internal_allocation: internal_allocation,
+            pfn_internal_free: internal_free,
+        };
+        HostAllocator { callbacks, raw }
+    }
+
+    pub fn raw(&self) -> &vk::AllocationCallbacks {
+        &self.raw
+    }
+}
+
+fn map_scope(scope: vk::SystemAllocationScope) -> Scope {
+    match scope {
+        vk::SystemAllocationScope::Command => Scope::Command,
+        vk::SystemAllocationScope::Object => Scope::Object,
+        vk::SystemAllocationScope::Cache => Scope::Cache,
+        vk::SystemAllocationScope::Device => Scope::Device,
+        vk::SystemAllocationScope::Instance => Scope::Instance,
+    }
+}
+
+unsafe fn callbacks<'a>(user_data: *mut vk::types::c_void) -> &'a Arc<AllocationCallbacks> {
+    &*(user_data as *const Arc<AllocationCallbacks>)
+}
+
+extern "system" fn allocation(
+    user_data: *mut vk::types::c_void,
+    size: usize,
+    alignment: usize,
+    scope: vk::SystemAllocationScope,
+) -> *mut vk::types::c_void {
+    unsafe { callbacks(user_data).allocate(size, alignment, map_scope(scope)) as *mut _ }
+}
+
+extern "system" fn reallocation(
+    user_data: *mut vk::types::c_void,
+    original: *mut vk::types::c_void,
+    size: usize,
+    alignment: usize,
+    scope: vk::SystemAllocationScope,
+) -> *mut vk::types::c_void {
+    unsafe { callbacks(user_data).reallocate(original as *mut _, size, alignment, map_scope(scope)) as *mut _ }
+}
+
+extern "system" fn free(user_data: *mut vk::types::c_void, memory: *mut vk::types::c_void) {
+    unsafe { callbacks(user_data).free(memory as *mut _) }
+}
+
+extern "system" fn internal_allocation(
+    user_data: *mut vk::types::c_void,
+    size: usize,
+    _allocation_type: vk::InternalAllocationType,
+    scope: vk::SystemAllocationScope,
+) {
+    unsafe { callbacks(user_data).internal_allocation(size, map_scope(scope)) }
+}
+
+extern "system" fn internal_free(
+    user_data: *mut vk::types::c_void,
+    size: usize,
+    _allocation_type: vk::InternalAllocationType,
+    scope: vk::SystemAllocationScope,
+) {
+    unsafe { callbacks(user_data).internal_free(size, map_scope(scope)) }
+}
@@ -0,0 +1,177 @@
+//! Host access to the arguments of indirect command sequences.
+//!
+//! Vulkan can't change push constants or vertex buffers from indirect arguments
+//! without `VK_NV_device_generated_commands`, which isn't exposed by `ash` yet.
+//! Sequences with state arguments are read on the host while recording
+//! `execute_indirect` instead and expanded into the equivalent commands.
+//! This requires the arguments to be bound to host visible memory and to be
+//! written before the command buffer is recorded.
+
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::sync::Mutex;
+use std::{ptr, slice};
+
+use ash::vk;
+use ash::version::{DeviceV1_0, V1_0};
+use ash::Device;
+
+use native as n;
+
+// Alignment of the addresses handed out by `Device::get_buffer_address`.
+const ADDRESS_ALIGNMENT: u64 = 0x100;
+
+#[derive(Debug)]
+struct MemoryState {
+    coherent: bool,
+    // Range and pointer of the mapping made by the user, if any.
+    mapping: Option<(Range<u64>, usize)>,
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    // Host visible allocations.
+    memory: HashMap<vk::DeviceMemory, MemoryState>,
+    // Buffers with an address, keyed by their first address.
+    addresses: BTreeMap<u64, (vk::Buffer, u64)>,
+    buffers: HashMap<vk::Buffer, u64>,
+    next_address: u64,
+}
+
+#[derive(Debug)]
+pub(crate) struct HostAccess {
+    // Host visible and host coherent memory types, as bit masks.
+    visible_types: u32,
+    coherent_types: u32,
+    registry: Mutex<Registry>,
+}
+
+impl HostAccess {
+    pub fn new(properties: &vk::PhysicalDeviceMemoryProperties) -> Self {
+        let mut visible_types = 0;
+        let mut coherent_types = 0;
+        for (i, ty) in properties.memory_types[.. properties.memory_type_count as usize].iter().enumerate() {
+            if ty.property_flags.intersects(vk::MEMORY_PROPERTY_HOST_VISIBLE_BIT) {
+                visible_types |= 1 << i;
+            }
+            if ty.property_flags.intersects(vk::MEMORY_PROPERTY_HOST_COHERENT_BIT) {
+                coherent_types |= 1 << i;
+            }
+        }
+
+        HostAccess {
+            visible_types,
+            coherent_types,
+            registry: Mutex::new(Registry {
+                // Keep zero as an invalid address.
+                next_address: ADDRESS_ALIGNMENT,
+                .. Registry::default()
+            }),
+        }
+    }
+
+    pub fn allocated(&self, memory: vk::DeviceMemory, memory_type: usize) {
+        if self.visible_types & (1 << memory_type) != 0 {
+            self.registry.lock().unwrap().memory.insert(memory, MemoryState {
+                coherent: self.coherent_types & (1 << memory_type) != 0,
+                mapping: None,
+            });
+        }
+    }
+
+    pub fn freed(&self, memory: vk::DeviceMemory) {
+        self.registry.lock().unwrap().memory.remove(&memory);
+    }
+
+    pub fn mapped(&self, memory: vk::DeviceMemory, offset: u64, size: u64, ptr: *mut u8) {
+        let end = if size == vk::VK_WHOLE_SIZE { !0 } else { offset + size };
+        if let Some(state) = self.registry.lock().unwrap().memory.get_mut(&memory) {
+            state.mapping = Some((offset .. end, ptr as usize));
+        }
+    }
+
+    pub fn unmapped(&self, memory: vk::DeviceMemory) {
+        if let Some(state) = self.registry.lock().unwrap().memory.get_mut(&memory) {
+            state.mapping = None;
+        }
+    }
+
+    /// Return the address of a buffer, assigning one the first time.
+    pub fn address(&self, buffer: vk::Buffer, size: u64) -> u64 {
+        let mut registry = self.registry.lock().unwrap();
+        if let Some(&address) = registry.buffers.get(&buffer) {
+            return address;
+        }
+        let address = registry.next_address;
+        registry.next_address += (size + ADDRESS_ALIGNMENT - 1) / ADDRESS_ALIGNMENT * ADDRESS_ALIGNMENT;
+        registry.addresses.insert(address, (buffer, size));
+        registry.buffers.insert(buffer, address);
+        address
+    }
+
+    pub fn destroyed(&self, buffer: vk::Buffer) {
+        let mut registry = self.registry.lock().unwrap();
+        if let Some(address) = registry.buffers.remove(&buffer) {
+            registry.addresses.remove(&address);
+        }
+    }
+
+    /// Find the buffer and offset of an address returned by `address`.
+    pub fn resolve(&self, address: u64) -> Option<(vk::Buffer, u64)> {
+        let registry = self.registry.lock().unwrap();
+        registry.addresses
+            .range(.. address + 1)
+            .next_back()
+            .and_then(|(&start, &(buffer, size))| {
+                if address < start + size {
+                    Some((buffer, address - start))
+                } else {
+                    None
+                }
+            })
+    }
+
+    /// Read a range of a buffer on the host.
+    ///
+    /// Uses the mapping of the user if it covers the range, otherwise the memory
+    /// is mapped for the duration of the read. Returns `None` if the memory is
+    /// not host visible or mapped elsewhere.
+    pub fn read(&self, device: &Device<V1_0>, buffer: &n::Buffer, range: Range<u64>) -> Option<Vec<u8>> {
+        let registry = self.registry.lock().unwrap();
+        let state = registry.memory.get(&buffer.memory)?;
+        let start = buffer.offset + range.start;
+        let size = (range.end - range.start) as usize;
+
+        match state.mapping {
+            Some((ref mapped, ptr)) if mapped.start <= start && start + size as u64 <= mapped.end => {
+                let data = unsafe {
+                    slice::from_raw_parts((ptr as *const u8).offset((start - mapped.start) as isize), size)
+                };
+                Some(data.to_vec())
+            }
+            Some(_) => None,
+            None => unsafe {
+                // Map the whole allocation, non-coherent ranges need to be aligned otherwise.
+                let ptr = device
+                    .map_memory(buffer.memory, 0, vk::VK_WHOLE_SIZE, vk::MemoryMapFlags::empty())
+                    .ok()? as *const u8;
+                if !state.coherent {
+                    let range = vk::MappedMemoryRange {
+                        s_type: vk::StructureType::MappedMemoryRange,
+                        p_next: ptr::null(),
+                        memory: buffer.memory,
+                        offset: 0,
+                        size: vk::VK_WHOLE_SIZE,
+                    };
+                    if device.invalidate_mapped_memory_ranges(&[range]).is_err() {
+                        device.unmap_memory(buffer.memory);
+                        return None;
+                    }
+                }
+                let data = slice::from_raw_parts(ptr.offset(start as isize), size).to_vec();
+                device.unmap_memory(buffer.memory);
+                Some(data)
+            },
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! Structures of Vulkan extensions which are not exposed by `ash` yet.
+//!
+//! The structure types are declared as plain integers, as the corresponding
+//! `vk::StructureType` variants don't exist.
+
+use ash::vk;
+use std::os::raw::c_void;
+
+pub const VK_KHR_MULTIVIEW_EXTENSION_NAME: &'static str = "VK_KHR_multiview";
+pub const VK_EXT_FRAGMENT_SHADER_INTERLOCK_EXTENSION_NAME: &'static str = "VK_EXT_fragment_shader_interlock";
+pub const VK_EXT_DEPTH_CLIP_ENABLE_EXTENSION_NAME: &'static str = "VK_EXT_depth_clip_enable";
+pub const VK_KHR_MAINTENANCE1_EXTENSION_NAME: &'static str = "VK_KHR_maintenance1";
+pub const VK_KHR_GET_PHYSICAL_DEVICE_PROPERTIES_2_EXTENSION_NAME: &'static str = "VK_KHR_get_physical_device_properties2";
+
+pub const STRUCTURE_TYPE_RENDER_PASS_MULTIVIEW_CREATE_INFO: u32 = 1000053000;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_MULTIVIEW_FEATURES: u32 = 1000053001;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_MULTIVIEW_PROPERTIES: u32 = 1000053002;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_PROPERTIES_2: u32 = 1000059001;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_FRAGMENT_SHADER_INTERLOCK_FEATURES_EXT: u32 = 1000251000;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_DEPTH_CLIP_ENABLE_FEATURES_EXT: u32 = 1000102000;
+pub const STRUCTURE_TYPE_PIPELINE_RASTERIZATION_DEPTH_CLIP_STATE_CREATE_INFO_EXT: u32 = 1000102001;
+
+/// `vkTrimCommandPoolKHR` of `VK_KHR_maintenance1`, the flags are reserved.
+#[allow(non_camel_case_types)]
+pub type PFN_vkTrimCommandPool = unsafe extern "system" fn(vk::Device, vk::CommandPool, u32);
+
+/// `vkGetPhysicalDeviceProperties2KHR` of `VK_KHR_get_physical_device_properties2`.
+#[allow(non_camel_case_types)]
+pub type PFN_vkGetPhysicalDeviceProperties2 = unsafe extern "system" fn(vk::PhysicalDevice, *mut PhysicalDeviceProperties2);
+
+/// Minimum `maxMultiviewViewCount` guaranteed by `VK_KHR_multiview`.
+pub const MIN_MULTIVIEW_VIEW_COUNT: u32 = 6;
+
+#[repr(C)]
+pub struct PhysicalDeviceProperties2 {
+    pub s_type: u32,
+    pub p_next: *mut c_void,
+    pub properties: vk::PhysicalDeviceProperties,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceMultiviewProperties {
+    pub s_type: u32,
+    pub p_next: *mut c_void,
+    pub max_multiview_view_count: u32,
+    pub max_multiview_instance_index: u32,
+}
+
+#[repr(C)]
+pub struct RenderPassMultiviewCreateInfo {
+    pub s_type: u32,
+    pub p_next: *const c_void,
+    pub subpass_count: u32,
+    pub p_view_masks: *const u32,
+    pub dependency_count: u32,
+    pub p_view_offsets: *const i32,
+    pub correlation_mask_count: u32,
+    pub p_correlation_masks: *const u32,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceMultiviewFeatures {
+    pub s_type: u32,
+    pub p_next: *mut c_void,
+    pub multiview: vk::Bool32,
+    pub multiview_geometry_shader: vk::Bool32,
+    pub multiview_tessellation_shader: vk::Bool32,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceFragmentShaderInterlockFeatures {
+    pub s_type: u32,
+    pub p_next: *mut c_void,
+    pub fragment_shader_sample_interlock: vk::Bool32,
+    pub fragment_shader_pixel_interlock: vk::Bool32,
+    pub fragment_shader_shading_rate_interlock: vk::Bool32,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceDepthClipEnableFeatures {
+    pub s_type: u32,
+    pub p_next: *mut c_void,
+    pub depth_clip_enable: vk::Bool32,
+}
+
+#[repr(C)]
+pub struct PipelineRasterizationDepthClipStateCreateInfo {
+    pub s_type: u32,
+    pub p_next: *const c_void,
+    pub flags: u32,
+    pub depth_clip_enable: vk::Bool32,
+}
@@ -6,6 +6,7 @@ extern crate byteorder;
 extern crate gfx_hal as hal;
 #[macro_use]
 extern crate lazy_static;
+extern crate shared_library;
 extern crate smallvec;
 
 #[cfg(windows)]
@@ -27,17 +28,23 @@ use ash::vk;
 
 use hal::{format, image, memory, queue};
 use hal::{Features, Limits, PatchSize, QueueType};
-use hal::error::{DeviceCreationError, HostExecutionError};
+use hal::error::{CaptureError, DeviceCreationError, HostExecutionError};
+use hal::host_memory::AllocationCallbacks;
 
 use std::{fmt, mem, ptr};
 use std::borrow::{Borrow, BorrowMut};
 use std::ffi::{CStr, CString};
+use std::os::raw::c_void;
 use std::sync::Arc;
 
+mod capture;
 mod command;
 mod conv;
 mod device;
+mod host_memory;
+mod indirect;
 mod info;
+mod khr;
 mod native;
 mod pool;
 mod result;
@@ -50,6 +57,7 @@ const LAYERS: &'static [&'static str] = &[
 const EXTENSIONS: &'static [&'static str] = &[
     #[cfg(debug_assertions)]
     "VK_EXT_debug_report",
+    khr::VK_KHR_GET_PHYSICAL_DEVICE_PROPERTIES_2_EXTENSION_NAME,
 ];
 const DEVICE_EXTENSIONS: &'static [&'static str] = &[
     vk::VK_KHR_SWAPCHAIN_EXTENSION_NAME,
@@ -71,18 +79,27 @@ lazy_static! {
     pub static ref VK_ENTRY: Result<Entry<V1_0>, LoadingError> = Entry::new();
 }
 
-pub struct RawInstance(pub ash::Instance<V1_0>, Option<(ext::DebugReport, vk::DebugReportCallbackEXT)>);
+pub struct RawInstance(
+    pub ash::Instance<V1_0>,
+    Option<(ext::DebugReport, vk::DebugReportCallbackEXT)>,
+    Option<Arc<host_memory::HostAllocator>>,
+);
+impl RawInstance {
+    fn allocator(&self) -> Option<&vk::AllocationCallbacks> {
+        self.2.as_ref().map(|allocator| allocator.raw())
+    }
+}
 impl Drop for RawInstance {
     fn drop(&mut self) {
         unsafe {
             #[cfg(debug_assertions)]
             {
                 if let Some((ref ext, callback)) = self.1 {
-                    ext.destroy_debug_report_callback_ext(callback, None);
+                    ext.destroy_debug_report_callback_ext(callback, self.allocator());
                 }
             }
 
-            self.0.destroy_instance(None);
+            self.0.destroy_instance(self.allocator());
         }
     }
 }
@@ -92,6 +109,8 @@ pub struct Instance {
 
     /// Supported extensions of this instance.
     pub extensions: Vec<&'static str>,
+
+    renderdoc: Option<capture::RenderDoc>,
 }
 
 fn map_queue_type(flags: vk::QueueFlags) -> QueueType {
@@ -134,8 +153,51 @@ extern "system" fn callback(
 
 impl Instance {
     pub fn create(name: &str, version: u32) -> Self {
-        // TODO: return errors instead of panic
-        let entry = VK_ENTRY.as_ref().expect("Unable to load Vulkan entry points");
+        Self::try_create(name, version).expect("Unable to create Vulkan instance")
+    }
+
+    /// Create an instance, returning `None` if Vulkan isn't available
+    /// on the system, e.g. because the loader isn't installed.
+    pub fn try_create(name: &str, version: u32) -> Option<Self> {
+        Self::create_impl(name, version, SURFACE_EXTENSIONS, None)
+    }
+
+    /// Create an instance routing the host memory allocations of the driver
+    /// through `callbacks`, see `hal::host_memory`.
+    ///
+    /// Devices opened with `PhysicalDevice::open` use the same callbacks.
+    pub fn create_with_callbacks(name: &str, version: u32, callbacks: Arc<AllocationCallbacks>) -> Self {
+        let allocator = Arc::new(host_memory::HostAllocator::new(callbacks));
+        Self::create_impl(name, version, SURFACE_EXTENSIONS, Some(allocator))
+            .expect("Unable to create Vulkan instance")
+    }
+
+    /// Create an instance without the window system integration extensions.
+    ///
+    /// Surfaces can't be created from a headless instance, which allows running
+    /// on systems without a windowing system, e.g. for server-side rendering.
+    /// See `hal::offscreen` for rendering without a swapchain.
+    ///
+    /// This is specific to the Vulkan backend, instances of the other backends
+    /// don't depend on a windowing system and have no headless variant.
+    pub fn create_headless(name: &str, version: u32) -> Self {
+        Self::create_impl(name, version, &[], None).expect("Unable to create Vulkan instance")
+    }
+
+    fn create_impl(
+        name: &str,
+        version: u32,
+        surface_extensions: &[&'static str],
+        allocator: Option<Arc<host_memory::HostAllocator>>,
+    ) -> Option<Self> {
+        let entry = match *VK_ENTRY {
+            Ok(ref entry) => entry,
+            Err(ref err) => {
+                error!("Unable to load Vulkan entry points: {:?}", err);
+                return None;
+            }
+        };
+        let renderdoc = capture::RenderDoc::load();
 
         let app_name = CString::new(name).unwrap();
         let app_info = vk::ApplicationInfo {
@@ -157,7 +219,7 @@ impl Instance {
             .expect("Unable to enumerate instance layers");
 
         // Check our xtensions against the available extensions
-        let extensions = SURFACE_EXTENSIONS
+        let extensions = surface_extensions
             .iter()
             .chain(EXTENSIONS.iter())
             .filter_map(|&ext| {
@@ -214,9 +276,14 @@ impl Instance {
                 pp_enabled_extension_names: str_pointers[layers.len()..].as_ptr(),
             };
 
-            unsafe {
-                entry.create_instance(&create_info, None)
-            }.expect("Unable to create Vulkan instance")
+            let callbacks = allocator.as_ref().map(|allocator| allocator.raw());
+            match unsafe { entry.create_instance(&create_info, callbacks) } {
+                Ok(instance) => instance,
+                Err(err) => {
+                    error!("Unable to create Vulkan instance: {:?}", err);
+                    return None;
+                }
+            }
         };
 
         #[cfg(debug_assertions)]
@@ -232,17 +299,18 @@ impl Instance {
                 p_user_data: ptr::null_mut(),
             };
             let handle = unsafe {
-                ext.create_debug_report_callback_ext(&info, None)
+                ext.create_debug_report_callback_ext(&info, allocator.as_ref().map(|allocator| allocator.raw()))
             }.unwrap();
             Some((ext, handle))
         };
         #[cfg(not(debug_assertions))]
         let debug_report = None;
 
-        Instance {
-            raw: Arc::new(RawInstance(instance, debug_report)),
+        Some(Instance {
+            raw: Arc::new(RawInstance(instance, debug_report, allocator)),
             extensions,
-        }
+            renderdoc,
+        })
     }
 }
 
@@ -266,10 +334,15 @@ impl hal::Instance for Instance {
                     device: properties.device_id as usize,
                     software_rendering: properties.device_type == vk::PhysicalDeviceType::Cpu,
                 };
+                // Extensions are queried once, they are checked repeatedly on device creation.
+                let extensions = self.raw.0
+                    .enumerate_device_extension_properties(device)
+                    .unwrap_or_else(|_| Vec::new());
                 let physical_device = PhysicalDevice {
                     instance: self.raw.clone(),
                     handle: device,
                     properties,
+                    extensions,
                 };
                 let queue_families = self.raw.0
                     .get_physical_device_queue_family_properties(device)
@@ -290,6 +363,30 @@ impl hal::Instance for Instance {
             })
             .collect()
     }
+
+    fn trigger_capture(&self) -> Result<(), CaptureError> {
+        match self.renderdoc {
+            Some(ref renderdoc) => {
+                renderdoc.trigger_capture();
+                Ok(())
+            }
+            None => Err(CaptureError::NotAvailable),
+        }
+    }
+
+    fn begin_capture(&self) -> Result<(), CaptureError> {
+        match self.renderdoc {
+            Some(ref renderdoc) => renderdoc.begin_capture(),
+            None => Err(CaptureError::NotAvailable),
+        }
+    }
+
+    fn end_capture(&self) -> Result<(), CaptureError> {
+        match self.renderdoc {
+            Some(ref renderdoc) => renderdoc.end_capture(),
+            None => Err(CaptureError::NotAvailable),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -316,11 +413,54 @@ pub struct PhysicalDevice {
     instance: Arc<RawInstance>,
     handle: vk::PhysicalDevice,
     properties: vk::PhysicalDeviceProperties,
+    extensions: Vec<vk::ExtensionProperties>,
 }
 
-impl hal::PhysicalDevice<Backend> for PhysicalDevice {
-    fn open(
-        &self, families: &[(&QueueFamily, &[hal::QueuePriority])]
+impl PhysicalDevice {
+    // Query `maxMultiviewViewCount`, falling back to the guaranteed minimum
+    // if `VK_KHR_get_physical_device_properties2` isn't available.
+    fn max_multiview_view_count(&self) -> u32 {
+        if !self.supports_extension(khr::VK_KHR_MULTIVIEW_EXTENSION_NAME) {
+            return 0;
+        }
+        let entry = match *VK_ENTRY {
+            Ok(ref entry) => entry,
+            Err(_) => return khr::MIN_MULTIVIEW_VIEW_COUNT,
+        };
+        let name = CString::new("vkGetPhysicalDeviceProperties2KHR").unwrap();
+        let address: *const c_void = unsafe {
+            mem::transmute(entry.static_fn().get_instance_proc_addr(self.instance.0.handle(), name.as_ptr()))
+        };
+        if address.is_null() {
+            return khr::MIN_MULTIVIEW_VIEW_COUNT;
+        }
+        let get_properties2 = unsafe { mem::transmute::<_, khr::PFN_vkGetPhysicalDeviceProperties2>(address) };
+
+        let mut multiview = khr::PhysicalDeviceMultiviewProperties {
+            s_type: khr::STRUCTURE_TYPE_PHYSICAL_DEVICE_MULTIVIEW_PROPERTIES,
+            p_next: ptr::null_mut(),
+            max_multiview_view_count: 0,
+            max_multiview_instance_index: 0,
+        };
+        let mut properties = khr::PhysicalDeviceProperties2 {
+            s_type: khr::STRUCTURE_TYPE_PHYSICAL_DEVICE_PROPERTIES_2,
+            p_next: &mut multiview as *mut _ as *mut _,
+            properties: unsafe { mem::zeroed() },
+        };
+        unsafe { get_properties2(self.handle, &mut properties) };
+        multiview.max_multiview_view_count
+    }
+
+    fn supports_extension(&self, name: &str) -> bool {
+        self.extensions.iter().any(|props| unsafe {
+            CStr::from_ptr(props.extension_name.as_ptr()).to_bytes() == name.as_bytes()
+        })
+    }
+
+    fn open_impl(
+        &self,
+        families: &[(&QueueFamily, &[hal::QueuePriority])],
+        allocator: Option<Arc<host_memory::HostAllocator>>,
     ) -> Result<hal::Gpu<Backend>, DeviceCreationError> {
         let family_infos = families
             .iter()
@@ -335,13 +475,69 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
             .collect::<Vec<_>>();
 
         // enabled features mask
-        let features = Features::empty();
+        let mut features = Features::empty();
+
+        // Extension features are chained into the device creation info.
+        let mut features_next: *mut c_void = ptr::null_mut();
+
+        // Depth clip control is only used by pipelines explicitly setting `depth_clipping`.
+        let depth_clip = self.supports_extension(khr::VK_EXT_DEPTH_CLIP_ENABLE_EXTENSION_NAME);
+        let mut depth_clip_features = khr::PhysicalDeviceDepthClipEnableFeatures {
+            s_type: khr::STRUCTURE_TYPE_PHYSICAL_DEVICE_DEPTH_CLIP_ENABLE_FEATURES_EXT,
+            p_next: features_next,
+            depth_clip_enable: vk::VK_TRUE,
+        };
+        if depth_clip {
+            features |= Features::DEPTH_CLIP_CONTROL;
+            features_next = &mut depth_clip_features as *mut _ as *mut _;
+        }
+
+        // Pixel interlock backs `Features::RASTERIZER_ORDERED_VIEWS`, shaders opt into it.
+        let interlock = self.supports_extension(khr::VK_EXT_FRAGMENT_SHADER_INTERLOCK_EXTENSION_NAME);
+        let mut interlock_features = khr::PhysicalDeviceFragmentShaderInterlockFeatures {
+            s_type: khr::STRUCTURE_TYPE_PHYSICAL_DEVICE_FRAGMENT_SHADER_INTERLOCK_FEATURES_EXT,
+            p_next: features_next,
+            fragment_shader_sample_interlock: vk::VK_FALSE,
+            fragment_shader_pixel_interlock: vk::VK_TRUE,
+            fragment_shader_shading_rate_interlock: vk::VK_FALSE,
+        };
+        if interlock {
+            features_next = &mut interlock_features as *mut _ as *mut _;
+        }
+
+        // Multiview is enabled whenever available, as render passes opt into it.
+        let multiview = self.supports_extension(khr::VK_KHR_MULTIVIEW_EXTENSION_NAME);
+        let mut multiview_features = khr::PhysicalDeviceMultiviewFeatures {
+            s_type: khr::STRUCTURE_TYPE_PHYSICAL_DEVICE_MULTIVIEW_FEATURES,
+            p_next: features_next,
+            multiview: vk::VK_TRUE,
+            multiview_geometry_shader: vk::VK_FALSE,
+            multiview_tessellation_shader: vk::VK_FALSE,
+        };
+        if multiview {
+            features_next = &mut multiview_features as *mut _ as *mut _;
+        }
+
+        // Allows returning the memory of command pools to the system with `trim`.
+        let maintenance1 = self.supports_extension(khr::VK_KHR_MAINTENANCE1_EXTENSION_NAME);
+
+        // Headless devices may not support presentation.
+        let swapchain = self.supports_extension(vk::VK_KHR_SWAPCHAIN_EXTENSION_NAME);
+        if !swapchain {
+            warn!("Swapchain extension is not supported, presentation is disabled");
+        }
 
         // Create device
         let device_raw = {
             let cstrings = DEVICE_EXTENSIONS
                 .iter()
-                .map(|&s| CString::new(s).unwrap())
+                .cloned()
+                .filter(|&ext| swapchain || ext != vk::VK_KHR_SWAPCHAIN_EXTENSION_NAME)
+                .chain(if multiview { Some(khr::VK_KHR_MULTIVIEW_EXTENSION_NAME) } else { None })
+                .chain(if interlock { Some(khr::VK_EXT_FRAGMENT_SHADER_INTERLOCK_EXTENSION_NAME) } else { None })
+                .chain(if depth_clip { Some(khr::VK_EXT_DEPTH_CLIP_ENABLE_EXTENSION_NAME) } else { None })
+                .chain(if maintenance1 { Some(khr::VK_KHR_MAINTENANCE1_EXTENSION_NAME) } else { None })
+                .map(|s| CString::new(s).unwrap())
                 .collect::<Vec<_>>();
 
             let str_pointers = cstrings
@@ -353,7 +549,7 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
             let enabled_features = unsafe { mem::zeroed() };
             let info = vk::DeviceCreateInfo {
                 s_type: vk::StructureType::DeviceCreateInfo,
-                p_next: ptr::null(),
+                p_next: features_next as *const _,
                 flags: vk::DeviceCreateFlags::empty(),
                 queue_create_info_count: family_infos.len() as u32,
                 p_queue_create_infos: family_infos.as_ptr(),
@@ -367,7 +563,7 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
             unsafe {
                 self.instance
                     .0
-                    .create_device(self.handle, &info, None)
+                    .create_device(self.handle, &info, allocator.as_ref().map(|allocator| allocator.raw()))
                     .map_err(|err| {
                         match err {
                             ash::DeviceError::LoadError(err) => panic!("{:?}", err),
@@ -378,18 +574,39 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
             }
         };
 
-        let swapchain_fn = vk::SwapchainFn::load(|name| unsafe {
-            mem::transmute(
-                self.instance.0
-                    .get_device_proc_addr(
-                        device_raw.handle(),
-                        name.as_ptr(),
-                    )
-            )
-        }).unwrap();
+        let swapchain_fn = if swapchain {
+            Some(vk::SwapchainFn::load(|name| unsafe {
+                mem::transmute(
+                    self.instance.0
+                        .get_device_proc_addr(
+                            device_raw.handle(),
+                            name.as_ptr(),
+                        )
+                )
+            }).unwrap())
+        } else {
+            None
+        };
 
+        let trim_fn = if maintenance1 {
+            let name = CString::new("vkTrimCommandPoolKHR").unwrap();
+            let address: *const c_void = unsafe {
+                mem::transmute(self.instance.0.get_device_proc_addr(device_raw.handle(), name.as_ptr()))
+            };
+            if address.is_null() {
+                None
+            } else {
+                Some(unsafe { mem::transmute::<_, khr::PFN_vkTrimCommandPool>(address) })
+            }
+        } else {
+            None
+        };
+
+        let host_access = indirect::HostAccess::new(
+            &self.instance.0.get_physical_device_memory_properties(self.handle),
+        );
         let device = Device {
-            raw: Arc::new(RawDevice(device_raw, features)),
+            raw: Arc::new(RawDevice(device_raw, features, allocator, trim_fn, host_access)),
         };
 
         let device_arc = device.raw.clone();
@@ -417,6 +634,23 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
             queues: queue::Queues::new(queues),
         })
     }
+}
+
+impl hal::PhysicalDevice<Backend> for PhysicalDevice {
+    fn open(
+        &self, families: &[(&QueueFamily, &[hal::QueuePriority])]
+    ) -> Result<hal::Gpu<Backend>, DeviceCreationError> {
+        self.open_impl(families, self.instance.2.clone())
+    }
+
+    fn open_with_callbacks(
+        &self,
+        families: &[(&QueueFamily, &[hal::QueuePriority])],
+        callbacks: Arc<AllocationCallbacks>,
+    ) -> Result<hal::Gpu<Backend>, DeviceCreationError> {
+        let allocator = Arc::new(host_memory::HostAllocator::new(callbacks));
+        self.open_impl(families, Some(allocator))
+    }
 
     fn format_properties(&self, format: Option<format::Format>) -> format::Properties {
         let properties = self.instance.0
@@ -553,6 +787,8 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
         if features.draw_indirect_first_instance != 0 {
             bits |= Features::DRAW_INDIRECT_FIRST_INSTANCE;
         }
+        // Core Vulkan always supports first instance and base vertex values.
+        bits |= Features::DRAW_FIRST_INSTANCE | Features::DRAW_BASE_VERTEX;
         if features.depth_clamp != 0 {
             bits |= Features::DEPTH_CLAMP;
         }
@@ -600,6 +836,16 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
         }
         //TODO: cover more features
 
+        if self.supports_extension(khr::VK_KHR_MULTIVIEW_EXTENSION_NAME) {
+            bits |= Features::MULTIVIEW;
+        }
+        if self.supports_extension(khr::VK_EXT_FRAGMENT_SHADER_INTERLOCK_EXTENSION_NAME) {
+            bits |= Features::RASTERIZER_ORDERED_VIEWS;
+        }
+        if self.supports_extension(khr::VK_EXT_DEPTH_CLIP_ENABLE_EXTENSION_NAME) {
+            bits |= Features::DEPTH_CLIP_CONTROL;
+        }
+
         bits
     }
 
@@ -610,27 +856,55 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
 
         Limits {
             max_texture_size: limits.max_image_dimension3d as _,
+            max_texture_3d_size: limits.max_image_dimension3d as _,
+            max_texture_array_layers: limits.max_image_array_layers as _,
             max_patch_size: limits.max_tessellation_patch_size as PatchSize,
             max_viewports: limits.max_viewports as _,
             max_compute_group_count: [max_group_count[0] as _, max_group_count[1] as _, max_group_count[2] as _],
             max_compute_group_size: [max_group_size[0] as _, max_group_size[1] as _, max_group_size[2] as _],
+            max_push_constants_size: limits.max_push_constants_size as _,
+            max_bound_descriptor_sets: limits.max_bound_descriptor_sets as _,
+            max_per_stage_descriptor_samplers: limits.max_per_stage_descriptor_samplers as _,
+            max_per_stage_descriptor_uniform_buffers: limits.max_per_stage_descriptor_uniform_buffers as _,
+            max_per_stage_descriptor_storage_buffers: limits.max_per_stage_descriptor_storage_buffers as _,
+            max_per_stage_descriptor_sampled_images: limits.max_per_stage_descriptor_sampled_images as _,
+            max_per_stage_descriptor_storage_images: limits.max_per_stage_descriptor_storage_images as _,
+            max_texel_elements: limits.max_texel_buffer_elements as _,
             min_buffer_copy_offset_alignment: limits.optimal_buffer_copy_offset_alignment as _,
             min_buffer_copy_pitch_alignment: limits.optimal_buffer_copy_row_pitch_alignment as _,
             min_uniform_buffer_offset_alignment: limits.min_uniform_buffer_offset_alignment as _,
+            non_coherent_atom_size: limits.non_coherent_atom_size as _,
+            shader_group_handle_size: 0, // TODO: VK_NV_ray_tracing
+            max_ray_recursion_depth: 0,
+            min_shader_table_alignment: 1,
+            min_shader_record_alignment: 1,
+            max_multiview_view_count: self.max_multiview_view_count(),
         }
     }
 }
 
 #[doc(hidden)]
-pub struct RawDevice(pub ash::Device<V1_0>, Features);
+pub struct RawDevice(
+    pub ash::Device<V1_0>,
+    Features,
+    Option<Arc<host_memory::HostAllocator>>,
+    // `vkTrimCommandPoolKHR`, if `VK_KHR_maintenance1` is supported.
+    Option<khr::PFN_vkTrimCommandPool>,
+    indirect::HostAccess,
+);
 impl fmt::Debug for RawDevice {
     fn fmt(&self, _formatter: &mut fmt::Formatter) -> fmt::Result {
         unimplemented!()
     }
 }
+impl RawDevice {
+    fn allocator(&self) -> Option<&vk::AllocationCallbacks> {
+        self.2.as_ref().map(|allocator| allocator.raw())
+    }
+}
 impl Drop for RawDevice {
     fn drop(&mut self) {
-        unsafe { self.0.destroy_device(None); }
+        unsafe { self.0.destroy_device(self.allocator()); }
     }
 }
 
@@ -640,7 +914,7 @@ pub type RawCommandQueue = Arc<vk::Queue>;
 pub struct CommandQueue {
     raw: RawCommandQueue,
     device: Arc<RawDevice>,
-    swapchain_fn: vk::SwapchainFn,
+    swapchain_fn: Option<vk::SwapchainFn>,
 }
 
 impl hal::queue::RawCommandQueue<Backend> for CommandQueue {
@@ -728,6 +1002,8 @@ impl hal::queue::RawCommandQueue<Backend> for CommandQueue {
 
         assert_eq!(vk::Result::Success, unsafe {
             self.swapchain_fn
+                .as_ref()
+                .expect("Presentation is not supported by the device")
                 .queue_present_khr(*self.raw, &info)
         });
     }
@@ -785,4 +1061,8 @@ impl hal::Backend for Backend {
     type Fence = native::Fence;
     type Semaphore = native::Semaphore;
     type QueryPool = native::QueryPool;
+    type CommandSignature = native::CommandSignature;
+
+    type AccelerationStructure = ();
+    type RayTracingPipeline = ();
 }
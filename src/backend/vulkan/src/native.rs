@@ -1,9 +1,10 @@
 use ash::vk;
 use ash::version::DeviceV1_0;
-use hal::pso;
+use hal::{buffer, indirect, pso, QueryPool as HalQueryPool};
 use hal::image::SubresourceRange;
 use std::borrow::Borrow;
 use std::sync::Arc;
+use smallvec::SmallVec;
 use {Backend, RawDevice};
 
 #[derive(Debug, Hash)]
@@ -26,6 +27,9 @@ pub struct Memory {
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Buffer {
     pub(crate) raw: vk::Buffer,
+    // Bound memory, for host access to indirect arguments.
+    pub(crate) memory: vk::DeviceMemory,
+    pub(crate) offset: u64,
 }
 
 unsafe impl Sync for Buffer {}
@@ -68,6 +72,7 @@ pub struct Framebuffer {
 pub struct DescriptorSetLayout {
     pub(crate) raw: vk::DescriptorSetLayout,
     pub(crate) bindings: Arc<Vec<pso::DescriptorSetLayoutBinding>>,
+    pub(crate) immutable_samplers: Vec<vk::Sampler>,
 }
 
 #[derive(Debug)]
@@ -138,6 +143,19 @@ impl pso::DescriptorPool<Backend> for DescriptorPool {
         }
     }
 
+    fn free_sets<I>(&mut self, descriptor_sets: I)
+    where
+        I: IntoIterator<Item = DescriptorSet>,
+    {
+        let sets: SmallVec<[vk::DescriptorSet; 16]> = descriptor_sets
+            .into_iter()
+            .map(|set| set.raw)
+            .collect();
+        unsafe {
+            self.device.0.free_descriptor_sets(self.raw, &sets);
+        }
+    }
+
     fn reset(&mut self) {
         assert_eq!(Ok(()), unsafe {
             self.device.0.reset_descriptor_pool(
@@ -148,5 +166,25 @@ impl pso::DescriptorPool<Backend> for DescriptorPool {
     }
 }
 
+// Command sequences without state arguments are executed with the plain
+// indirect commands, others are expanded on the host, see `indirect`.
+#[derive(Debug)]
+pub struct CommandSignature {
+    pub(crate) state: Vec<indirect::Argument>,
+    pub(crate) command: indirect::Argument,
+    pub(crate) stride: u32,
+    pub(crate) layout: vk::PipelineLayout,
+}
+
 #[derive(Debug, Hash)]
-pub struct QueryPool(pub vk::QueryPool);
+pub struct QueryPool {
+    pub raw: vk::QueryPool,
+    // Size of the 64-bit results of one query.
+    pub(crate) stride: buffer::Offset,
+}
+
+impl HalQueryPool for QueryPool {
+    fn result_stride(&self) -> buffer::Offset {
+        self.stride
+    }
+}
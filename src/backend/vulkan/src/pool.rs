@@ -25,6 +25,13 @@ impl pool::RawCommandPool<Backend> for RawCommandPool {
         });
     }
 
+    fn trim(&mut self) {
+        // Trimming requires `VK_KHR_maintenance1`, the memory stays in the pool otherwise.
+        if let Some(trim) = self.device.3 {
+            unsafe { trim(self.device.0.handle(), self.raw, 0) };
+        }
+    }
+
     fn allocate(&mut self, num: usize, level: command::RawLevel) -> Vec<CommandBuffer> {
         let info = vk::CommandBufferAllocateInfo {
             s_type: vk::StructureType::CommandBufferAllocateInfo,
@@ -45,6 +52,8 @@ impl pool::RawCommandPool<Backend> for RawCommandPool {
                 CommandBuffer {
                     raw: buffer,
                     device: device.clone(),
+                    vertex_buffers: Vec::new(),
+                    push_constants: Vec::new(),
                 }
             }).collect()
     }
@@ -1,7 +1,7 @@
 
 use ash::vk;
 
-use hal::error::{DeviceCreationError, HostExecutionError};
+use hal::error::{DeviceCreationError, DeviceLost, HostExecutionError};
 
 // Generic error codes from Vulkan
 #[derive(Debug)]
@@ -59,15 +59,16 @@ impl From<vk::Result> for Error {
 // Syntax:
 //    #HalError {
 //       #VulkanError => #HalErrorVariant,
+//       #VulkanError => #HalErrorVariant(#Payload),
 //    }
 macro_rules! from_error {
-    { $($name:ident { $($base_error:ident => $err:ident,)* },)* } => {
+    { $($name:ident { $($base_error:ident => $err:ident $(($payload:expr))*,)* },)* } => {
         $(
             impl From<Error> for $name {
                 fn from(err: Error) -> Self {
                     match err {
                         $(
-                            Error::$base_error => $name::$err,
+                            Error::$base_error => $name::$err $(($payload))*,
                         )*
                         _ => unreachable!("Unexpected error code ({:?}). Non specification conformant driver.", err),
                     }
@@ -93,6 +94,6 @@ from_error! {
     HostExecutionError {
         OutOfHostMemory => OutOfHostMemory,
         OutOfDeviceMemory => OutOfDeviceMemory,
-        DeviceLost => DeviceLost,
+        DeviceLost => DeviceLost(DeviceLost::default()),
     },
 }
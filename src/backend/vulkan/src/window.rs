@@ -270,6 +270,35 @@ impl Instance {
         }
     }
 
+    /// Create a surface from the raw handle of a native window.
+    ///
+    /// Panics if the handle doesn't belong to a platform supported by the instance.
+    pub fn create_surface_from_raw_handle<W: hal::HasRawWindowHandle>(&self, window: &W) -> Surface {
+        match window.raw_window_handle() {
+            #[cfg(all(unix, not(target_os = "android")))]
+            hal::RawWindowHandle::Xlib { display, window } => {
+                self.create_surface_from_xlib(display as *mut _, window as _)
+            }
+            #[cfg(all(unix, not(target_os = "android")))]
+            hal::RawWindowHandle::Xcb { connection, window } => {
+                self.create_surface_from_xcb(connection as *mut _, window as _)
+            }
+            #[cfg(all(unix, not(target_os = "android")))]
+            hal::RawWindowHandle::Wayland { display, surface, width, height } => {
+                self.create_surface_from_wayland(display, surface, width, height)
+            }
+            #[cfg(target_os = "android")]
+            hal::RawWindowHandle::Android { native_window, width, height } => {
+                self.create_surface_android(native_window, width, height)
+            }
+            #[cfg(windows)]
+            hal::RawWindowHandle::Windows { hinstance, hwnd } => {
+                self.create_surface_from_hwnd(hinstance, hwnd)
+            }
+            handle => panic!("Unsupported window handle: {:?}", handle),
+        }
+    }
+
     fn create_surface_from_vk_surface_khr(
         &self, surface: vk::SurfaceKHR, width: Size, height: Size, samples: NumSamples
     ) -> Surface {
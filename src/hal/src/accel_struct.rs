@@ -0,0 +1,333 @@
+//! Ray tracing acceleration structures.
+//!
+//! An acceleration structure is an opaque, device-built spatial index over geometry
+//! which is traversed by the ray tracing shaders. There are two levels:
+//!
+//! - *Bottom level* structures contain the actual geometry (triangles or AABBs).
+//! - *Top level* structures contain a list of `Instance`s, each referencing
+//!   a bottom level structure with an associated transform.
+//!
+//! Acceleration structures live inside buffers created with
+//! `buffer::Usage::ACCELERATION_STRUCTURE`. The required sizes for the storage
+//! and the scratch memory needed during the build can be queried with
+//! `Device::get_acceleration_structure_prebuild_info`.
+
+use std::error::Error;
+use std::fmt;
+
+use device::OutOfMemory;
+use {buffer, format, Backend, IndexType};
+
+/// Level of an acceleration structure.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Level {
+    /// Bottom level structure, containing geometry.
+    Bottom,
+    /// Top level structure, containing instances of bottom level structures.
+    Top,
+}
+
+bitflags!(
+    /// Acceleration structure build flags.
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct BuildFlags: u32 {
+        /// Allow the structure to be updated (refitted) later on.
+        const ALLOW_UPDATE = 0x1;
+        /// Allow the structure to be compacted.
+        const ALLOW_COMPACTION = 0x2;
+        /// Prioritize traversal performance over build time.
+        const PREFER_FAST_TRACE = 0x4;
+        /// Prioritize build time over traversal performance.
+        const PREFER_FAST_BUILD = 0x8;
+        /// Minimize the memory footprint of the structure and the scratch memory.
+        const LOW_MEMORY = 0x10;
+    }
+);
+
+bitflags!(
+    /// Per geometry flags.
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct GeometryFlags: u32 {
+        /// Any-hit shaders won't be invoked for this geometry.
+        const OPAQUE = 0x1;
+        /// The any-hit shader will be invoked exactly once per primitive.
+        const NO_DUPLICATE_ANY_HIT_INVOCATION = 0x2;
+    }
+);
+
+bitflags!(
+    /// Per instance flags.
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct InstanceFlags: u8 {
+        /// Disable face culling for this instance.
+        const TRIANGLE_CULL_DISABLE = 0x1;
+        /// Counter-clockwise triangles are considered front facing.
+        const TRIANGLE_FRONT_COUNTERCLOCKWISE = 0x2;
+        /// Treat all geometries of the instance as opaque.
+        const FORCE_OPAQUE = 0x4;
+        /// Treat all geometries of the instance as non-opaque.
+        const FORCE_NO_OPAQUE = 0x8;
+    }
+);
+
+/// Triangle geometry for bottom level acceleration structures.
+#[derive(Debug)]
+pub struct Triangles<'a, B: Backend> {
+    /// Buffer containing the vertex positions.
+    pub vertex_buffer: &'a B::Buffer,
+    /// Offset of the first vertex in the buffer, in bytes.
+    pub vertex_offset: buffer::Offset,
+    /// Stride between two vertices, in bytes.
+    pub vertex_stride: u32,
+    /// Number of vertices.
+    pub vertex_count: u32,
+    /// Format of the vertex positions.
+    ///
+    /// Only `Rgb32Float`, `Rg32Float`, `Rgba16Float` and `Rg16Float` are supported.
+    pub vertex_format: format::Format,
+    /// Optional index buffer, offset and index type.
+    pub index_buffer: Option<(&'a B::Buffer, buffer::Offset, IndexType)>,
+    /// Number of indices, ignored if no index buffer is set.
+    pub index_count: u32,
+    /// Optional buffer and offset of a row-major 3x4 `f32` transformation matrix
+    /// applied to the vertices.
+    pub transform: Option<(&'a B::Buffer, buffer::Offset)>,
+}
+
+impl<'a, B: Backend> Clone for Triangles<'a, B> {
+    fn clone(&self) -> Self {
+        Triangles {
+            vertex_buffer: self.vertex_buffer,
+            vertex_offset: self.vertex_offset,
+            vertex_stride: self.vertex_stride,
+            vertex_count: self.vertex_count,
+            vertex_format: self.vertex_format,
+            index_buffer: self.index_buffer,
+            index_count: self.index_count,
+            transform: self.transform,
+        }
+    }
+}
+
+/// Axis-aligned bounding boxes for procedural geometry in bottom level
+/// acceleration structures.
+///
+/// Each box is stored as 6 `f32` values: `min_x, min_y, min_z, max_x, max_y, max_z`.
+#[derive(Debug)]
+pub struct Aabbs<'a, B: Backend> {
+    /// Buffer containing the boxes.
+    pub buffer: &'a B::Buffer,
+    /// Offset of the first box in the buffer, in bytes.
+    pub offset: buffer::Offset,
+    /// Stride between two boxes, in bytes.
+    pub stride: u32,
+    /// Number of boxes.
+    pub count: u32,
+}
+
+impl<'a, B: Backend> Clone for Aabbs<'a, B> {
+    fn clone(&self) -> Self {
+        Aabbs {
+            buffer: self.buffer,
+            offset: self.offset,
+            stride: self.stride,
+            count: self.count,
+        }
+    }
+}
+
+/// Geometry data of a bottom level acceleration structure.
+#[allow(missing_docs)]
+#[derive(Clone, Debug)]
+pub enum GeometryData<'a, B: Backend> {
+    Triangles(Triangles<'a, B>),
+    Aabbs(Aabbs<'a, B>),
+}
+
+/// A single geometry of a bottom level acceleration structure.
+#[derive(Clone, Debug)]
+pub struct Geometry<'a, B: Backend> {
+    /// Geometry data.
+    pub data: GeometryData<'a, B>,
+    /// Geometry flags.
+    pub flags: GeometryFlags,
+}
+
+/// Inputs of an acceleration structure build.
+#[derive(Debug)]
+pub enum Inputs<'a, B: Backend> {
+    /// Geometries of a bottom level structure.
+    Bottom(&'a [Geometry<'a, B>]),
+    /// Instances of a top level structure.
+    ///
+    /// The buffer contains `count` tightly packed `Instance`s starting at `offset`.
+    Top {
+        /// Buffer containing the instances.
+        buffer: &'a B::Buffer,
+        /// Offset of the first instance, in bytes.
+        offset: buffer::Offset,
+        /// Number of instances.
+        count: u32,
+    },
+}
+
+impl<'a, B: Backend> Inputs<'a, B> {
+    /// Level of the acceleration structure built from these inputs.
+    pub fn level(&self) -> Level {
+        match *self {
+            Inputs::Bottom(_) => Level::Bottom,
+            Inputs::Top { .. } => Level::Top,
+        }
+    }
+}
+
+/// Description of an acceleration structure build.
+#[derive(Debug)]
+pub struct BuildDesc<'a, B: Backend> {
+    /// Build flags.
+    pub flags: BuildFlags,
+    /// Geometry or instances to build the structure from.
+    pub inputs: Inputs<'a, B>,
+}
+
+/// Memory requirements of an acceleration structure build.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PrebuildInfo {
+    /// Upper bound of the size of the built structure, in bytes.
+    pub result_size: u64,
+    /// Size of the scratch memory required for a build, in bytes.
+    pub build_scratch_size: u64,
+    /// Size of the scratch memory required for an update, in bytes.
+    pub update_scratch_size: u64,
+}
+
+/// Description of an acceleration structure object.
+#[derive(Debug)]
+pub struct Desc<'a, B: Backend> {
+    /// Level of the acceleration structure.
+    pub level: Level,
+    /// Buffer holding the acceleration structure, created with
+    /// `buffer::Usage::ACCELERATION_STRUCTURE`.
+    pub buffer: &'a B::Buffer,
+    /// Offset into the buffer, in bytes. Must be a multiple of 256.
+    pub offset: buffer::Offset,
+    /// Size of the acceleration structure storage, in bytes.
+    pub size: u64,
+}
+
+/// Error creating an acceleration structure.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CreationError {
+    /// Out of either host or device memory.
+    OutOfMemory(OutOfMemory),
+    /// The offset into the buffer is not a multiple of 256.
+    UnalignedOffset(buffer::Offset),
+    /// The storage exceeds the size of the buffer.
+    OutOfBounds,
+}
+
+impl From<OutOfMemory> for CreationError {
+    fn from(error: OutOfMemory) -> Self {
+        CreationError::OutOfMemory(error)
+    }
+}
+
+impl fmt::Display for CreationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CreationError::UnalignedOffset(offset) => write!(f, "{}: {}", self.description(), offset),
+            _ => write!(f, "{}", self.description()),
+        }
+    }
+}
+
+impl Error for CreationError {
+    fn description(&self) -> &str {
+        match *self {
+            CreationError::OutOfMemory(_) => "Out of memory",
+            CreationError::UnalignedOffset(_) => "Acceleration structure offset must be a multiple of 256",
+            CreationError::OutOfBounds => "Acceleration structure storage exceeds the buffer",
+        }
+    }
+}
+
+/// An instance of a bottom level acceleration structure, as stored in the
+/// instance buffer of a top level build.
+///
+/// The layout matches `D3D12_RAYTRACING_INSTANCE_DESC` and the Vulkan equivalent.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Instance {
+    /// Row-major 3x4 transformation matrix.
+    pub transform: [[f32; 4]; 3],
+    /// 24 bit user defined instance id (low bits) and 8 bit visibility mask (high bits).
+    pub id_and_mask: u32,
+    /// 24 bit hit group offset into the shader binding table (low bits)
+    /// and 8 bit `InstanceFlags` (high bits).
+    pub hit_group_offset_and_flags: u32,
+    /// Device address of the bottom level acceleration structure,
+    /// see `Device::get_acceleration_structure_address`.
+    pub acceleration_structure: u64,
+}
+
+impl Instance {
+    /// Create a new instance of the bottom level structure at `address`.
+    pub fn new(
+        transform: [[f32; 4]; 3],
+        id: u32,
+        mask: u8,
+        hit_group_offset: u32,
+        flags: InstanceFlags,
+        address: u64,
+    ) -> Self {
+        debug_assert!(id < 1 << 24 && hit_group_offset < 1 << 24);
+        Instance {
+            transform,
+            id_and_mask: id | (mask as u32) << 24,
+            hit_group_offset_and_flags: hit_group_offset | (flags.bits() as u32) << 24,
+            acceleration_structure: address,
+        }
+    }
+}
+
+/// A strided region of a buffer holding shader records of a shader binding table.
+///
+/// Each record starts with a shader group handle, see
+/// `Device::get_ray_tracing_shader_group_handles`.
+#[derive(Debug)]
+pub struct ShaderTable<'a, B: Backend> {
+    /// Buffer containing the shader records.
+    pub buffer: &'a B::Buffer,
+    /// Offset of the first record. Must be aligned to `Limits::min_shader_table_alignment`.
+    pub offset: buffer::Offset,
+    /// Total size of the table, in bytes.
+    pub size: u64,
+    /// Stride between two records, in bytes.
+    pub stride: u64,
+}
+
+impl<'a, B: Backend> Clone for ShaderTable<'a, B> {
+    fn clone(&self) -> Self {
+        ShaderTable {
+            buffer: self.buffer,
+            offset: self.offset,
+            size: self.size,
+            stride: self.stride,
+        }
+    }
+}
+
+/// The shader tables used by `trace_rays`.
+#[derive(Clone, Debug)]
+pub struct ShaderBindingTables<'a, B: Backend> {
+    /// Ray generation record. Only the first record of the table is used.
+    pub ray_gen: ShaderTable<'a, B>,
+    /// Miss shader records.
+    pub miss: ShaderTable<'a, B>,
+    /// Hit group records.
+    pub hit_group: ShaderTable<'a, B>,
+    /// Callable shader records.
+    pub callable: Option<ShaderTable<'a, B>>,
+}
@@ -7,9 +7,11 @@
 //! that has the properties specified.
 
 use std::any::Any;
+use std::sync::Arc;
 
 use {format, image, memory, Backend, Gpu, Features, Limits};
 use error::DeviceCreationError;
+use host_memory::AllocationCallbacks;
 use queue::{Capability, QueueGroup};
 
 /// Scheduling hint for devices about the priority of a queue.  Values range from `0.0` (low) to
@@ -74,6 +76,20 @@ pub trait PhysicalDevice<B: Backend>: Any + Send + Sync {
         &self, families: &[(&B::QueueFamily, &[QueuePriority])]
     ) -> Result<Gpu<B>, DeviceCreationError>;
 
+    /// Create a new logical device like `open`, routing the host memory
+    /// allocations of the device through `callbacks`.
+    ///
+    /// Backends without support for custom allocators ignore the callbacks.
+    /// See `hal::host_memory` for more details.
+    fn open_with_callbacks(
+        &self,
+        families: &[(&B::QueueFamily, &[QueuePriority])],
+        callbacks: Arc<AllocationCallbacks>,
+    ) -> Result<Gpu<B>, DeviceCreationError> {
+        let _ = callbacks;
+        self.open(families)
+    }
+
     /// Fetch details for a particular format.
     fn format_properties(
         &self, format: Option<format::Format>
@@ -5,6 +5,12 @@ use std::fmt;
 
 use {IndexType, Backend};
 
+#[cfg(feature = "ring")]
+mod ring;
+
+#[cfg(feature = "ring")]
+pub use self::ring::{DynamicRing, DynamicRingError};
+
 
 /// An offset inside a buffer, in bytes.
 pub type Offset = u64;
@@ -91,6 +97,14 @@ bitflags!(
         const VERTEX = 0x80;
         ///
         const INDIRECT = 0x100;
+        /// Storage of acceleration structures, and inputs or scratch memory of their builds.
+        /// Scratch memory additionally requires `STORAGE` usage.
+        const ACCELERATION_STRUCTURE = 0x400;
+        /// The state of the buffer is tracked by the backend, which inserts the
+        /// required transitions while recording commands, see `image::StorageFlags::TRACKED`.
+        ///
+        /// Requires `Features::TRACKED_RESOURCES`.
+        const TRACKED = 0x8000_0000;
     }
 );
 
@@ -129,6 +143,10 @@ bitflags!(
         const MEMORY_READ = 0x8000;
         ///
         const MEMORY_WRITE = 0x10000;
+        ///
+        const ACCELERATION_STRUCTURE_READ = 0x20_0000;
+        ///
+        const ACCELERATION_STRUCTURE_WRITE = 0x40_0000;
     }
 );
 
@@ -0,0 +1,409 @@
+//! Ring buffer for data which changes every frame.
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::ops::Range;
+use std::{cmp, fmt, mem, ptr, slice};
+
+use smallvec::SmallVec;
+
+use {mapping, memory};
+use {Backend, Limits, MemoryType};
+use device::{BindError, Device, OutOfMemory};
+use offscreen::find_memory_type;
+use super::{CreationError, Offset, Usage};
+
+
+/// Error creating a `DynamicRing`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DynamicRingError {
+    /// Buffer creation failed.
+    Buffer(CreationError),
+    /// No host visible memory type satisfies the requirements of the buffer.
+    NoMemoryType,
+    /// Out of memory.
+    OutOfMemory,
+    /// Binding of the buffer to memory failed.
+    Bind(BindError),
+    /// Mapping of the buffer memory failed.
+    Map(mapping::Error),
+}
+
+impl fmt::Display for DynamicRingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = self.description();
+        match *self {
+            DynamicRingError::Buffer(ref err) => write!(f, "{}: {}", description, err),
+            DynamicRingError::Bind(ref err) => write!(f, "{}: {}", description, err),
+            DynamicRingError::Map(ref err) => write!(f, "{}: {}", description, err),
+            _ => write!(f, "{}", description),
+        }
+    }
+}
+
+impl Error for DynamicRingError {
+    fn description(&self) -> &str {
+        match *self {
+            DynamicRingError::Buffer(_) => "Failed to create the ring buffer",
+            DynamicRingError::NoMemoryType => "No host visible memory type satisfies the buffer requirements",
+            DynamicRingError::OutOfMemory => "Out of memory",
+            DynamicRingError::Bind(_) => "Failed to bind the ring buffer to memory",
+            DynamicRingError::Map(_) => "Failed to map the ring buffer memory",
+        }
+    }
+}
+
+impl From<CreationError> for DynamicRingError {
+    fn from(err: CreationError) -> Self {
+        DynamicRingError::Buffer(err)
+    }
+}
+
+impl From<OutOfMemory> for DynamicRingError {
+    fn from(_: OutOfMemory) -> Self {
+        DynamicRingError::OutOfMemory
+    }
+}
+
+impl From<BindError> for DynamicRingError {
+    fn from(err: BindError) -> Self {
+        DynamicRingError::Bind(err)
+    }
+}
+
+impl From<mapping::Error> for DynamicRingError {
+    fn from(err: mapping::Error) -> Self {
+        DynamicRingError::Map(err)
+    }
+}
+
+/// Persistently mapped, host visible buffer for data written anew every frame,
+/// like uniforms.
+///
+/// Allocations are made linearly and wrap around at the end of the buffer.
+/// They stay valid until the frame they were made in has finished execution
+/// on the GPU, which the user reports by releasing the frame.
+///
+/// Offsets are aligned for all uses of the buffer, e.g. to 256 bytes for
+/// constant buffers on D3D12, as reported by the device limits.
+///
+/// ```no_run
+/// # extern crate gfx_backend_empty as empty;
+/// # extern crate gfx_hal;
+/// # fn main() {
+/// use gfx_hal::buffer::{DynamicRing, Usage};
+/// # use gfx_hal::{Limits, MemoryType};
+///
+/// # let device: empty::Device = return;
+/// # let memory_types: Vec<MemoryType> = return;
+/// # let limits: Limits = return;
+/// # let fence = ();
+/// let mut ring = DynamicRing::<empty::Backend>::new(&device, &memory_types, &limits, 1 << 16, Usage::UNIFORM)
+///     .unwrap();
+///
+/// let transform = [1.0f32, 0.0, 0.0, 1.0];
+/// let offset = ring.upload(&transform).unwrap();
+/// // bind `ring.buffer()` at `offset`, record and submit the frame..
+/// ring.end_frame(&device);
+///
+/// // before reusing the memory of the oldest frame in flight..
+/// ring.wait_frame(&device, &fence);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct DynamicRing<B: Backend> {
+    buffer: B::Buffer,
+    memory: B::Memory,
+    mapping: *mut u8,
+    coherent: bool,
+    state: RingState,
+}
+
+impl<B: Backend> DynamicRing<B> {
+    /// Create a ring of at least `size` bytes, mapped for its whole lifetime.
+    ///
+    /// Coherent memory is preferred, other host visible memory is flushed
+    /// at the end of each frame in multiples of `non_coherent_atom_size`.
+    pub fn new(
+        device: &B::Device,
+        memory_types: &[MemoryType],
+        limits: &Limits,
+        size: Offset,
+        usage: Usage,
+    ) -> Result<Self, DynamicRingError> {
+        let alignment = Self::required_alignment(limits, usage);
+        // The size is a multiple of the atom size as well, flushed ranges
+        // can be aligned without exceeding the buffer.
+        let granularity = cmp::max(alignment, limits.non_coherent_atom_size);
+        let size = (size + granularity - 1) / granularity * granularity;
+
+        let unbound = device.create_buffer(size, usage)?;
+        let requirements = device.get_buffer_requirements(&unbound);
+        let (memory_type, coherent) = match find_memory_type(
+            memory_types,
+            requirements.type_mask,
+            memory::Properties::CPU_VISIBLE | memory::Properties::COHERENT,
+        ) {
+            Some(memory_type) => (memory_type, true),
+            None => {
+                let memory_type = find_memory_type(memory_types, requirements.type_mask, memory::Properties::CPU_VISIBLE)
+                    .ok_or(DynamicRingError::NoMemoryType)?;
+                (memory_type, false)
+            }
+        };
+        let memory = device.allocate_memory(memory_type, requirements.size)?;
+        let buffer = device.bind_buffer_memory(&memory, 0, unbound)?;
+        let mapping = device.map_memory(&memory, 0 .. size)?;
+
+        Ok(DynamicRing {
+            buffer,
+            memory,
+            mapping,
+            coherent,
+            state: RingState::new(size, alignment, limits.non_coherent_atom_size),
+        })
+    }
+
+    fn required_alignment(limits: &Limits, usage: Usage) -> Offset {
+        let mut alignment = 4;
+        if usage.contains(Usage::UNIFORM) {
+            alignment = cmp::max(alignment, limits.min_uniform_buffer_offset_alignment);
+        }
+        if usage.contains(Usage::TRANSFER_SRC) {
+            alignment = cmp::max(alignment, limits.min_buffer_copy_offset_alignment);
+        }
+        alignment
+    }
+
+    /// Get the underlying buffer.
+    pub fn buffer(&self) -> &B::Buffer {
+        &self.buffer
+    }
+
+    /// Get the size of the buffer in bytes.
+    pub fn size(&self) -> Offset {
+        self.state.size
+    }
+
+    /// Get the alignment of the allocation offsets.
+    pub fn alignment(&self) -> Offset {
+        self.state.alignment
+    }
+
+    /// Get the number of frames which have ended but haven't been released.
+    pub fn frames_in_flight(&self) -> usize {
+        self.state.frames.len()
+    }
+
+    /// Allocate `size` bytes, returning the offset into the buffer and
+    /// the mapped memory to write the data to.
+    ///
+    /// Returns `None` if the ring has no space left before the oldest
+    /// frame in flight.
+    pub fn allocate(&mut self, size: Offset) -> Option<(Offset, &mut [u8])> {
+        let offset = self.state.allocate(size)?;
+        let data = unsafe {
+            slice::from_raw_parts_mut(self.mapping.offset(offset as isize), size as usize)
+        };
+        Some((offset, data))
+    }
+
+    /// Allocate and write `data`, returning the offset into the buffer.
+    pub fn upload<T: Copy>(&mut self, data: &[T]) -> Option<Offset> {
+        let size = mem::size_of_val(data);
+        self.allocate(size as Offset).map(|(offset, memory)| {
+            unsafe {
+                ptr::copy_nonoverlapping(data.as_ptr() as *const u8, memory.as_mut_ptr(), size);
+            }
+            offset
+        })
+    }
+
+    /// End the current frame, flushing its allocations if necessary.
+    ///
+    /// Call this after the last allocation of the frame and before submitting
+    /// the command buffers using them.
+    pub fn end_frame(&mut self, device: &B::Device) {
+        let ranges = self.state.end_frame();
+        if !self.coherent && !ranges.is_empty() {
+            device.flush_mapped_memory_ranges(ranges.into_iter().map(|range| (&self.memory, range)));
+        }
+    }
+
+    /// Release the allocations of the oldest frame in flight.
+    ///
+    /// The GPU must have finished executing the command buffers of the frame.
+    pub fn release_frame(&mut self) {
+        self.state.release_frame();
+    }
+
+    /// Wait for `fence`, signaled by the submission of the oldest frame in flight,
+    /// and release the allocations of the frame.
+    pub fn wait_frame(&mut self, device: &B::Device, fence: &B::Fence) {
+        device.wait_for_fence(fence, !0);
+        self.release_frame();
+    }
+
+    /// Unmap and destroy the buffer.
+    ///
+    /// Submitted command buffers using the allocations must have finished execution.
+    pub fn destroy(self, device: &B::Device) {
+        device.unmap_memory(&self.memory);
+        device.destroy_buffer(self.buffer);
+        device.free_memory(self.memory);
+    }
+}
+
+// Allocation positions of a ring, independent of the buffer memory.
+#[derive(Debug)]
+struct RingState {
+    size: Offset,
+    alignment: Offset,
+    atom_size: Offset,
+    // Total number of bytes allocated and released, the offset
+    // into the buffer is the position modulo `size`.
+    head: Offset,
+    tail: Offset,
+    // Position of the first allocation of the current frame.
+    frame_start: Offset,
+    // End positions of the frames in flight, oldest first.
+    frames: VecDeque<Offset>,
+}
+
+impl RingState {
+    fn new(size: Offset, alignment: Offset, atom_size: Offset) -> Self {
+        RingState {
+            size,
+            alignment,
+            atom_size,
+            head: 0,
+            tail: 0,
+            frame_start: 0,
+            frames: VecDeque::new(),
+        }
+    }
+
+    // Allocate `size` bytes, returning the offset into the buffer.
+    fn allocate(&mut self, size: Offset) -> Option<Offset> {
+        let mut start = (self.head + self.alignment - 1) / self.alignment * self.alignment;
+        if start % self.size + size > self.size {
+            // Doesn't fit into the rest of the buffer, wrap around.
+            start = (start / self.size + 1) * self.size;
+        }
+        if start + size - self.tail > self.size {
+            return None;
+        }
+
+        self.head = start + size;
+        Some(start % self.size)
+    }
+
+    // End the current frame, returning the ranges of the buffer to flush.
+    //
+    // The ranges are aligned to the atom size, the start rounded down and the end up.
+    fn end_frame(&mut self) -> SmallVec<[Range<Offset>; 2]> {
+        let (size, atom_size) = (self.size, self.atom_size);
+        let ranges = if self.head != self.frame_start {
+            self.buffer_ranges(self.frame_start .. self.head)
+                .into_iter()
+                .map(|range| {
+                    let start = range.start / atom_size * atom_size;
+                    let end = (range.end + atom_size - 1) / atom_size * atom_size;
+                    start .. cmp::min(end, size)
+                })
+                .collect()
+        } else {
+            SmallVec::new()
+        };
+        self.frames.push_back(self.head);
+        self.frame_start = self.head;
+        ranges
+    }
+
+    fn release_frame(&mut self) {
+        if let Some(end) = self.frames.pop_front() {
+            self.tail = end;
+        }
+    }
+
+    // Split a range of positions into ranges of the buffer.
+    fn buffer_ranges(&self, positions: Range<Offset>) -> SmallVec<[Range<Offset>; 2]> {
+        let len = positions.end - positions.start;
+        let start = positions.start % self.size;
+        let mut ranges = SmallVec::new();
+        if start + len <= self.size {
+            ranges.push(start .. start + len);
+        } else {
+            ranges.push(start .. self.size);
+            ranges.push(0 .. start + len - self.size);
+        }
+        ranges
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraparound() {
+        let mut state = RingState::new(256, 16, 1);
+        assert_eq!(state.allocate(100), Some(0));
+        assert_eq!(state.allocate(100), Some(112));
+        state.end_frame();
+        state.release_frame();
+
+        // Doesn't fit before the end, the allocation starts at the beginning again.
+        assert_eq!(state.allocate(64), Some(0));
+        assert_eq!(state.head, 256 + 64);
+        // The buffer is free up to the end of the released frame.
+        assert_eq!(state.allocate(148), Some(64));
+        assert_eq!(state.allocate(1), None);
+    }
+
+    #[test]
+    fn frame_release() {
+        let mut state = RingState::new(256, 4, 1);
+        assert_eq!(state.allocate(128), Some(0));
+        state.end_frame();
+        assert_eq!(state.allocate(128), Some(128));
+        state.end_frame();
+        assert_eq!(state.frames.len(), 2);
+
+        // Both frames are in flight, the ring is full.
+        assert_eq!(state.allocate(4), None);
+        state.release_frame();
+        assert_eq!(state.frames.len(), 1);
+        assert_eq!(state.allocate(128), Some(0));
+        assert_eq!(state.allocate(4), None);
+        state.end_frame();
+
+        state.release_frame();
+        state.release_frame();
+        assert!(state.frames.is_empty());
+        assert_eq!(state.tail, state.head);
+        // Releasing without frames in flight has no effect.
+        state.release_frame();
+        assert_eq!(state.tail, state.head);
+    }
+
+    #[test]
+    fn flush_ranges() {
+        let mut state = RingState::new(256, 4, 64);
+        assert_eq!(state.allocate(8), Some(0));
+        assert_eq!(&state.end_frame()[..], &[0 .. 64]);
+        // Empty frames don't flush.
+        assert!(state.end_frame().is_empty());
+
+        assert_eq!(state.allocate(100), Some(8));
+        assert_eq!(&state.end_frame()[..], &[0 .. 128]);
+        state.release_frame();
+        state.release_frame();
+        state.release_frame();
+
+        // Frames wrapping around are flushed in two ranges, both aligned.
+        assert_eq!(state.allocate(100), Some(108));
+        assert_eq!(state.allocate(100), Some(0));
+        assert_eq!(&state.end_frame()[..], &[64 .. 256, 0 .. 128]);
+    }
+}
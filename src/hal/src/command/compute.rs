@@ -2,7 +2,7 @@
 
 use std::borrow::Borrow;
 
-use {Backend, WorkGroupCount};
+use {accel_struct, Backend, WorkGroupCount};
 use buffer::Offset;
 use queue::capability::{Compute, Supports};
 use super::{CommandBuffer, RawCommandBuffer, Shot, Level};
@@ -10,6 +10,7 @@ use super::{CommandBuffer, RawCommandBuffer, Shot, Level};
 impl<'a, B: Backend, C: Supports<Compute>, S: Shot, L: Level> CommandBuffer<'a, B, C, S, L> {
     /// Identical to the `RawCommandBuffer` method of the same name.
     pub fn bind_compute_pipeline(&mut self, pipeline: &B::ComputePipeline) {
+        self.record_stats(|stats| stats.bindings += 1);
         self.raw.bind_compute_pipeline(pipeline)
     }
 
@@ -23,21 +24,68 @@ impl<'a, B: Backend, C: Supports<Compute>, S: Shot, L: Level> CommandBuffer<'a,
         T: IntoIterator,
         T::Item: Borrow<B::DescriptorSet>,
     {
+        self.record_stats(|stats| stats.bindings += 1);
         self.raw.bind_compute_descriptor_sets(layout, first_set, sets)
     }
 
     /// Identical to the `RawCommandBuffer` method of the same name.
     pub fn dispatch(&mut self, count: WorkGroupCount) {
+        self.record_stats(|stats| stats.dispatches += 1);
         self.raw.dispatch(count)
     }
 
     /// Identical to the `RawCommandBuffer` method of the same name.
-    pub fn dispatch_indirect(&mut self, buffer: &B::Buffer, offset: Offset) {
-        self.raw.dispatch_indirect(buffer, offset)
+    pub fn dispatch_indirect(&mut self, buffer: &B::Buffer, offset: Offset, dispatch_count: u32) {
+        debug_assert_eq!(offset % 4, 0, "Indirect argument offset {} must be a multiple of 4", offset);
+        self.record_stats(|stats| stats.dispatches += dispatch_count);
+        self.raw.dispatch_indirect(buffer, offset, dispatch_count)
+    }
+
+    /// Identical to the `RawCommandBuffer` method of the same name.
+    pub fn execute_indirect(&mut self, signature: &B::CommandSignature, buffer: &B::Buffer, offset: Offset, count: u32) {
+        self.record_stats(|stats| stats.dispatches += count);
+        self.raw.execute_indirect(signature, buffer, offset, count)
     }
 
     /// Identical to the `RawCommandBuffer` method of the same name.
     pub fn push_compute_constants(&mut self, layout: &B::PipelineLayout, offset: u32, constants: &[u32]) {
+        self.record_stats(|stats| stats.bindings += 1);
         self.raw.push_compute_constants(layout, offset, constants);
     }
+
+    /// Identical to the `RawCommandBuffer` method of the same name.
+    pub fn build_acceleration_structure(
+        &mut self,
+        desc: &accel_struct::BuildDesc<B>,
+        dst: &B::AccelerationStructure,
+        scratch: (&B::Buffer, Offset),
+    ) {
+        self.raw.build_acceleration_structure(desc, dst, scratch)
+    }
+
+    /// Identical to the `RawCommandBuffer` method of the same name.
+    pub fn bind_ray_tracing_pipeline(&mut self, pipeline: &B::RayTracingPipeline) {
+        self.record_stats(|stats| stats.bindings += 1);
+        self.raw.bind_ray_tracing_pipeline(pipeline)
+    }
+
+    /// Identical to the `RawCommandBuffer` method of the same name.
+    pub fn bind_ray_tracing_descriptor_sets<T>(
+        &mut self,
+        layout: &B::PipelineLayout,
+        first_set: usize,
+        sets: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<B::DescriptorSet>,
+    {
+        self.record_stats(|stats| stats.bindings += 1);
+        self.raw.bind_ray_tracing_descriptor_sets(layout, first_set, sets)
+    }
+
+    /// Identical to the `RawCommandBuffer` method of the same name.
+    pub fn trace_rays(&mut self, tables: &accel_struct::ShaderBindingTables<B>, count: [u32; 3]) {
+        self.record_stats(|stats| stats.dispatches += 1);
+        self.raw.trace_rays(tables, count)
+    }
 }
@@ -3,14 +3,16 @@ use std::borrow::Borrow;
 use std::ops::Range;
 
 use Backend;
-use {image, pso};
-use buffer::IndexBufferView;
-use query::{Query, QueryControl, QueryId};
+use {image, pass, pso};
+use buffer::{self, IndexBufferView};
+use memory::{Barrier, Dependencies};
+use query::{Query, QueryControl, QueryId, ResultFlags};
 use queue::capability::{Graphics, GraphicsOrCompute, Supports};
 use super::{
     CommandBuffer, RawCommandBuffer,
     RenderPassInlineEncoder, RenderPassSecondaryEncoder,
-    Shot, Level, Primary, ClearColorRaw,
+    RenderingEncoder, Shot, Level, Primary, BufferImageCopy,
+    ClearColorRaw, ClearDepthStencilRaw, ClearValueRaw,
 };
 
 
@@ -92,6 +94,16 @@ pub enum ClearValue {
     DepthStencil(ClearDepthStencil),
 }
 
+impl From<ClearValue> for ClearValueRaw {
+    fn from(cv: ClearValue) -> Self {
+        match cv {
+            ClearValue::Color(cv) => ClearValueRaw { color: cv.into() },
+            ClearValue::DepthStencil(ClearDepthStencil(depth, stencil)) =>
+                ClearValueRaw { depth_stencil: ClearDepthStencilRaw { depth, stencil } },
+        }
+    }
+}
+
 /// Attachment clear description for the current subpass.
 #[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -157,6 +169,22 @@ impl<'a, B: Backend, C: Supports<Graphics>, S: Shot, L: Level> CommandBuffer<'a,
         RenderPassInlineEncoder::new(self, render_pass, frame_buffer, render_area, clear_values)
     }
 
+    /// Identical to the `RawCommandBuffer` method of the same name.
+    ///
+    /// Rendering ends when the returned encoder is dropped.
+    pub fn begin_rendering<'b, T>(
+        &mut self,
+        render_area: pso::Rect,
+        colors: T,
+        depth_stencil: Option<pass::RenderingAttachment<'b, B>>,
+    ) -> RenderingEncoder<B, L>
+    where
+        T: IntoIterator,
+        T::Item: Borrow<pass::RenderingAttachment<'b, B>>,
+    {
+        RenderingEncoder::new(self, render_area, colors, depth_stencil)
+    }
+
     /// Identical to the `RawCommandBuffer` method of the same name.
     pub fn clear_color_image(
         &mut self,
@@ -181,16 +209,19 @@ impl<'a, B: Backend, C: Supports<Graphics>, S: Shot, L: Level> CommandBuffer<'a,
 
     /// Identical to the `RawCommandBuffer` method of the same name.
     pub fn bind_index_buffer(&mut self, ibv: IndexBufferView<B>) {
+        self.record_stats(|stats| stats.bindings += 1);
         self.raw.bind_index_buffer(ibv)
     }
 
     /// Identical to the `RawCommandBuffer` method of the same name.
     pub fn bind_vertex_buffers(&mut self, vbs: pso::VertexBufferSet<B>) {
+        self.record_stats(|stats| stats.bindings += 1);
         self.raw.bind_vertex_buffers(vbs)
     }
 
     /// Identical to the `RawCommandBuffer` method of the same name.
     pub fn bind_graphics_pipeline(&mut self, pipeline: &B::GraphicsPipeline) {
+        self.record_stats(|stats| stats.bindings += 1);
         self.raw.bind_graphics_pipeline(pipeline)
     }
 
@@ -204,6 +235,7 @@ impl<'a, B: Backend, C: Supports<Graphics>, S: Shot, L: Level> CommandBuffer<'a,
         T: IntoIterator,
         T::Item: Borrow<B::DescriptorSet>,
     {
+        self.record_stats(|stats| stats.bindings += 1);
         self.raw.bind_graphics_descriptor_sets(layout, first_set, sets)
     }
 
@@ -235,8 +267,19 @@ impl<'a, B: Backend, C: Supports<Graphics>, S: Shot, L: Level> CommandBuffer<'a,
         self.raw.set_blend_constants(cv)
     }
 
+    /// Identical to the `RawCommandBuffer` method of the same name.
+    pub fn set_depth_bounds(&mut self, bounds: Range<f32>) {
+        self.raw.set_depth_bounds(bounds)
+    }
+
+    /// Identical to the `RawCommandBuffer` method of the same name.
+    pub fn set_line_width(&mut self, width: f32) {
+        self.raw.set_line_width(width)
+    }
+
     /// Identical to the `RawCommandBuffer` method of the same name.
     pub fn push_graphics_constants(&mut self, layout: &B::PipelineLayout, stages: pso::ShaderStageFlags, offset: u32, constants: &[u32]) {
+        self.record_stats(|stats| stats.bindings += 1);
         self.raw.push_graphics_constants(layout, stages, offset, constants)
     }
 
@@ -255,6 +298,99 @@ impl<'a, B: Backend, C: Supports<Graphics>, S: Shot, L: Level> CommandBuffer<'a,
         self.raw.resolve_image(src, src_layout, dst, dst_layout, regions)
     }
 
+    /// Copy regions of a possibly multisampled image to a buffer.
+    ///
+    /// Multisampled images can't be copied to buffers directly. Their regions are
+    /// resolved into the same subresources and offsets of `resolved` first, which
+    /// is then copied to `dst`. `resolved` is a transient single-sampled image with
+    /// the format of `src` and the `TRANSFER_SRC | TRANSFER_DST` usage. Its previous
+    /// contents are discarded and it's left in the `TransferSrcOptimal` layout.
+    ///
+    /// Single-sampled images are copied directly if `resolved` is `None`.
+    pub fn resolve_to_buffer<T>(
+        &mut self,
+        src: &B::Image,
+        src_layout: image::Layout,
+        resolved: Option<&B::Image>,
+        dst: &B::Buffer,
+        regions: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<BufferImageCopy>,
+    {
+        let resolved = match resolved {
+            Some(resolved) => resolved,
+            None => {
+                self.raw.copy_image_to_buffer(src, src_layout, dst, regions);
+                return;
+            }
+        };
+
+        let regions = regions.into_iter().collect::<Vec<_>>();
+        let ranges = regions
+            .iter()
+            .map(|r| {
+                let layers = &r.borrow().image_layers;
+                image::SubresourceRange {
+                    aspects: layers.aspects,
+                    levels: layers.level .. layers.level + 1,
+                    layers: layers.layers.clone(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let pre_barriers = ranges.iter().map(|range| Barrier::Image {
+            states: (image::Access::empty(), image::Layout::Undefined) ..
+                (image::Access::TRANSFER_WRITE, image::Layout::TransferDstOptimal),
+            target: resolved,
+            range: range.clone(),
+            families: None,
+        });
+        self.raw.pipeline_barrier(
+            pso::PipelineStage::TOP_OF_PIPE .. pso::PipelineStage::TRANSFER,
+            Dependencies::empty(),
+            pre_barriers,
+        );
+
+        self.raw.resolve_image(
+            src,
+            src_layout,
+            resolved,
+            image::Layout::TransferDstOptimal,
+            regions.iter().map(|r| {
+                let r = r.borrow();
+                ImageResolve {
+                    src_subresource: r.image_layers.clone(),
+                    src_offset: r.image_offset,
+                    dst_subresource: r.image_layers.clone(),
+                    dst_offset: r.image_offset,
+                    extent: r.image_extent,
+                }
+            }),
+        );
+
+        let post_barriers = ranges.iter().map(|range| Barrier::Image {
+            states: (image::Access::TRANSFER_WRITE, image::Layout::TransferDstOptimal) ..
+                (image::Access::TRANSFER_READ, image::Layout::TransferSrcOptimal),
+            target: resolved,
+            range: range.clone(),
+            families: None,
+        });
+        self.raw.pipeline_barrier(
+            pso::PipelineStage::TRANSFER .. pso::PipelineStage::TRANSFER,
+            Dependencies::empty(),
+            post_barriers,
+        );
+        self.record_stats(|stats| stats.barriers += 2 * ranges.len() as u32);
+
+        self.raw.copy_image_to_buffer(
+            resolved,
+            image::Layout::TransferSrcOptimal,
+            dst,
+            regions.iter().map(|r| r.borrow()),
+        );
+    }
+
     /// Identical to the `RawCommandBuffer` method of the same name.
     pub fn blit_image<T>(
         &mut self,
@@ -309,4 +445,17 @@ impl<'a, B: Backend, C: Supports<GraphicsOrCompute>, S: Shot, L: Level> CommandB
     pub fn write_timestamp(&mut self, stage: pso::PipelineStage, query: Query<B>) {
         self.raw.write_timestamp(stage, query)
     }
+
+    /// Identical to the `RawCommandBuffer` method of the same name.
+    pub fn copy_query_pool_results(
+        &mut self,
+        pool: &B::QueryPool,
+        queries: Range<QueryId>,
+        buffer: &B::Buffer,
+        offset: buffer::Offset,
+        stride: buffer::Offset,
+        flags: ResultFlags,
+    ) {
+        self.raw.copy_query_pool_results(pool, queries, buffer, offset, stride, flags)
+    }
 }
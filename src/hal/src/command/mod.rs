@@ -13,9 +13,10 @@
 
 // TODO: Document pipelines and subpasses better.
 
-use Backend;
+use {Backend, Features};
 use queue::capability::Supports;
 use std::marker::PhantomData;
+use std::mem;
 
 mod compute;
 mod graphics;
@@ -24,7 +25,7 @@ mod render_pass;
 mod transfer;
 
 pub use self::graphics::*;
-pub use self::raw::{ClearValueRaw, ClearColorRaw, ClearDepthStencilRaw, RawCommandBuffer, CommandBufferFlags, Level as RawLevel, CommandBufferInheritanceInfo};
+pub use self::raw::{ClearValueRaw, ClearColorRaw, ClearDepthStencilRaw, RawCommandBuffer, CommandBufferFlags, Level as RawLevel, CommandBufferInheritanceInfo, RecordingError};
 pub use self::render_pass::*;
 pub use self::transfer::*;
 
@@ -36,10 +37,18 @@ pub trait Shot {
     const FLAGS: CommandBufferFlags;
 }
 /// Indicates a Submit that can only be submitted once.
+///
+/// The `Submit` is consumed by the submission.
 pub enum OneShot { }
 impl Shot for OneShot { const FLAGS: CommandBufferFlags = CommandBufferFlags::ONE_TIME_SUBMIT; }
 
 /// Indicates a Submit that can be submitted multiple times.
+///
+/// The `Submit` is submitted by reference and replays the recorded commands
+/// on each submission without re-recording them, e.g. across several frames.
+/// Resubmitting while a previous submission is still executing requires the
+/// command buffer to be acquired with `allow_pending_resubmit`.
+/// Resetting the command pool invalidates the `Submit`.
 pub enum MultiShot { }
 impl Shot for MultiShot { const FLAGS: CommandBufferFlags = CommandBufferFlags::EMPTY; }
 
@@ -62,11 +71,46 @@ impl Level for Primary { }
 pub enum Secondary { }
 impl Level for Secondary { }
 
+/// Statistics of the commands recorded into a command buffer.
+///
+/// Collection is opt-in, see `CommandBuffer::enable_statistics`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CommandStats {
+    /// Number of draw calls, including indirect draws.
+    pub draws: u32,
+    /// Number of compute dispatches, including indirect dispatches and ray traces.
+    pub dispatches: u32,
+    /// Number of memory barriers passed to `pipeline_barrier`.
+    pub barriers: u32,
+    /// Number of pipeline, descriptor set, vertex buffer, index buffer and push constant bindings.
+    pub bindings: u32,
+    /// Number of render passes.
+    pub render_passes: u32,
+}
+
 /// Thread-safe finished command buffer for submission.
-pub struct Submit<B: Backend, C, S, L>(pub(crate) B::CommandBuffer, pub(crate) PhantomData<(C, S, L)>);
+pub struct Submit<B: Backend, C, S, L>(
+    pub(crate) B::CommandBuffer,
+    pub(crate) PhantomData<(C, S, L)>,
+    pub(crate) CommandStats,
+    pub(crate) Option<RecordingError>,
+);
 impl<B: Backend, C, S, L> Submit<B, C, S, L> {
-    fn new(buffer: B::CommandBuffer) -> Self {
-        Submit(buffer, PhantomData)
+    fn new(buffer: B::CommandBuffer, stats: CommandStats, error: Option<RecordingError>) -> Self {
+        Submit(buffer, PhantomData, stats, error)
+    }
+
+    /// Get the first invalid usage detected by the backend while recording, if any.
+    pub fn error(&self) -> Option<&RecordingError> {
+        self.3.as_ref()
+    }
+
+    /// Get the statistics of the recorded commands.
+    ///
+    /// All counters are zero if statistics collection wasn't enabled during recording.
+    pub fn statistics(&self) -> CommandStats {
+        self.2
     }
 }
 unsafe impl<B: Backend, C, S, L> Send for Submit<B, C, S, L> {}
@@ -94,6 +138,8 @@ pub type SecondaryCommandBuffer<'a, B: Backend, C, S: Shot = OneShot> = CommandB
 /// it supports.
 pub struct CommandBuffer<'a, B: Backend, C, S: Shot = OneShot, L: Level = Primary> {
     pub(crate) raw: &'a mut B::CommandBuffer,
+    pub(crate) stats: Option<CommandStats>,
+    pub(crate) features: Option<Features>,
     pub(crate) _marker: PhantomData<(C, S, L)>
 }
 
@@ -102,16 +148,47 @@ impl<'a, B: Backend, C, S: Shot, L: Level> CommandBuffer<'a, B, C, S, L> {
     pub unsafe fn new(raw: &'a mut B::CommandBuffer) -> Self {
         CommandBuffer {
             raw: raw,
+            stats: None,
+            features: None,
             _marker: PhantomData,
         }
     }
 
+    /// Start collecting statistics of the commands recorded from now on.
+    ///
+    /// The statistics are counted on the CPU while recording and are
+    /// available after `finish` via `Submit::statistics`.
+    /// Commands recorded into secondary command buffers are not counted.
+    pub fn enable_statistics(&mut self) {
+        self.stats = Some(CommandStats::default());
+    }
+
+    /// Start validating the parameters of the commands recorded from now on
+    /// against the `features` of the device.
+    ///
+    /// Commands relying on unsupported features panic in debug builds,
+    /// instead of silently rendering wrong results on some backends.
+    pub fn validate_features(&mut self, features: Features) {
+        self.features = Some(features);
+    }
+
+    pub(crate) fn record_stats<F: FnOnce(&mut CommandStats)>(&mut self, f: F) {
+        if let Some(ref mut stats) = self.stats {
+            f(stats);
+        }
+    }
+
     /// Finish recording commands to the command buffers.
     ///
     /// The command buffer will be consumed and can't be modified further.
     /// The command pool must be reset to able to re-record commands.
-    pub fn finish(self) -> Submit<B, C, S, L> {
-        Submit::new(self.raw.clone())
+    /// `MultiShot` submits can be submitted multiple times until then.
+    pub fn finish(mut self) -> Submit<B, C, S, L> {
+        let error = self.raw.finish().err();
+        let submit = Submit::new(self.raw.clone(), self.stats.unwrap_or_default(), error);
+        // Already finished, skip the finish on drop.
+        mem::forget(self);
+        submit
     }
 
     /// Downgrade a command buffer to a lesser capability type.
@@ -141,6 +218,7 @@ impl<'a, B: Backend, C, S: Shot> CommandBuffer<'a, B, C, S, Primary> {
 
 impl<'a, B: Backend, C, S: Shot, L: Level> Drop for CommandBuffer<'a, B, C, S, L> {
     fn drop(&mut self) {
-        self.raw.finish();
+        // Errors are only reported by `finish`.
+        let _ = self.raw.finish();
     }
 }
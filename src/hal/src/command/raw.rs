@@ -1,18 +1,42 @@
 use std::any::Any;
 use std::borrow::Borrow;
+use std::error::Error;
+use std::fmt;
 use std::ops::Range;
 
-use {buffer, pass, pso};
+use {accel_struct, buffer, pass, pso};
 use {Backend, IndexCount, InstanceCount, VertexCount, VertexOffset, WorkGroupCount};
 use image::{Filter, Layout, SubresourceRange};
 use memory::{Barrier, Dependencies};
-use query::{PipelineStatistic, Query, QueryControl, QueryId};
+use query::{PipelineStatistic, Query, QueryControl, QueryId, ResultFlags};
 use super::{
     AttachmentClear, BufferCopy, BufferImageCopy,
     ClearColor, ClearDepthStencil, ClearValue,
     ImageBlit, ImageCopy, ImageResolve, SubpassContents,
 };
 
+/// Invalid usage detected by the backend while recording a command buffer.
+///
+/// Backends which validate commands skip the invalid ones and keep the
+/// first error until the command buffer is reset.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordingError {
+    /// Description of the invalid usage.
+    pub message: String,
+}
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.description(), self.message)
+    }
+}
+
+impl Error for RecordingError {
+    fn description(&self) -> &str {
+        "Invalid usage while recording a command buffer"
+    }
+}
+
 /// Unsafe variant of `ClearColor`.
 #[repr(C)]
 #[derive(Clone, Copy)]
@@ -110,7 +134,10 @@ pub trait RawCommandBuffer<B: Backend>: Clone + Any + Send + Sync {
     fn begin(&mut self, flags: CommandBufferFlags, inheritance_info: CommandBufferInheritanceInfo<B>);
 
     /// Finish recording commands to a command buffer.
-    fn finish(&mut self);
+    ///
+    /// Returns the first invalid usage detected while recording, if the
+    /// backend validates commands. The invalid commands were skipped.
+    fn finish(&mut self) -> Result<(), RecordingError>;
 
     /// Empties the command buffer, optionally releasing all
     /// resources from the commands that have been submitted.
@@ -130,6 +157,10 @@ pub trait RawCommandBuffer<B: Backend>: Clone + Any + Send + Sync {
         T::Item: Borrow<Barrier<'a, B>>;
 
     /// Fill a buffer with the given `u32` value.
+    ///
+    /// The start and the length of `range` must be multiples of 4.
+    ///
+    /// Not supported on the copy queues of the DX12 backend, which can only copy.
     fn fill_buffer(
         &mut self,
         buffer: &B::Buffer,
@@ -198,12 +229,15 @@ pub trait RawCommandBuffer<B: Backend>: Clone + Any + Send + Sync {
 
     /// Takes an iterator of attachments and an iterator of rect's,
     /// and clears the given rect's for *each* attachment.
+    ///
+    /// The layers of each rect need to be within the layers of all cleared
+    /// attachments, a `pso::Rect` converts into a clear of the first layer.
     fn clear_attachments<T, U>(&mut self, clears: T, rects: U)
     where
         T: IntoIterator,
         T::Item: Borrow<AttachmentClear>,
         U: IntoIterator,
-        U::Item: Borrow<pso::Rect>;
+        U::Item: Borrow<pso::ClearRect>;
 
     /// "Resolves" a multisampled image, converting it into a non-multisampled
     /// image. Takes an iterator of regions to apply the resolution to.
@@ -256,7 +290,7 @@ pub trait RawCommandBuffer<B: Backend>: Clone + Any + Send + Sync {
     /// - Only queues with graphics capability support this function.
     /// - The bound pipeline must not have baked viewport state.
     /// - All viewports used by the pipeline must be specified before the first
-    ///   draw call.
+    ///   draw call, and after binding the last pipeline with baked viewport state.
     fn set_viewports<T>(&mut self, first_viewport: u32, viewports: T)
     where
         T: IntoIterator,
@@ -278,7 +312,7 @@ pub trait RawCommandBuffer<B: Backend>: Clone + Any + Send + Sync {
     /// - Only queues with graphics capability support this function.
     /// - The bound pipeline must not have baked scissor state.
     /// - All scissors used by the pipeline must be specified before the first draw
-    ///   call.
+    ///   call, and after binding the last pipeline with baked scissor state.
     fn set_scissors<T>(&mut self, first_scissor: u32, rects: T)
     where
         T: IntoIterator,
@@ -292,6 +326,18 @@ pub trait RawCommandBuffer<B: Backend>: Clone + Any + Send + Sync {
     /// Set the blend constant values dynamically.
     fn set_blend_constants(&mut self, pso::ColorValue);
 
+    /// Set the depth bounds test range dynamically.
+    ///
+    /// Only has an effect if depth bounds testing is enabled in the bound pipeline.
+    /// Requires `Features::DEPTH_BOUNDS`.
+    fn set_depth_bounds(&mut self, bounds: Range<f32>);
+
+    /// Set the line width dynamically.
+    ///
+    /// Only has an effect if the bound pipeline uses `PolygonMode::Line(State::Dynamic)`.
+    /// Widths other than `1.0` require `Features::LINE_WIDTH`.
+    fn set_line_width(&mut self, width: f32);
+
     /// Just does some type conversions and calls `begin_render_pass_raw`.
     fn begin_render_pass<T>(
         &mut self,
@@ -352,6 +398,27 @@ pub trait RawCommandBuffer<B: Backend>: Clone + Any + Send + Sync {
     /// Finishes recording commands for the current a render pass.
     fn end_render_pass(&mut self);
 
+    /// Begins rendering into the given attachments, without creating a render pass
+    /// and framebuffer. Requires `Features::DYNAMIC_RENDERING`.
+    ///
+    /// Rendering behaves like a render pass with a single subpass, which uses the
+    /// `colors` and `depth_stencil` attachments in the given order and doesn't
+    /// change their layouts. Commands are recorded inline until `end_rendering`.
+    ///
+    /// Graphics pipelines can be used if the formats of the subpass they were created
+    /// for are compatible with the attachments, see `pass::AttachmentFormats`.
+    fn begin_rendering<'a, T>(
+        &mut self,
+        render_area: pso::Rect,
+        colors: T,
+        depth_stencil: Option<pass::RenderingAttachment<'a, B>>,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<pass::RenderingAttachment<'a, B>>;
+
+    /// Finishes rendering started with `begin_rendering`.
+    fn end_rendering(&mut self);
+
     /// Bind a graphics pipeline.
     ///
     /// # Errors
@@ -361,6 +428,11 @@ pub trait RawCommandBuffer<B: Backend>: Clone + Any + Send + Sync {
     ///
     /// - Command buffer must be in recording state.
     /// - Only queues with graphics capability support this function.
+    ///
+    /// Binding a pipeline with baked viewport or scissor state replaces the
+    /// dynamic viewports or scissors set before, which need to be set again
+    /// for the next pipeline using dynamic state. Viewports and scissors beyond
+    /// the `viewport_count` of the pipeline are discarded.
     fn bind_graphics_pipeline(&mut self, &B::GraphicsPipeline);
 
     /// Takes an iterator of graphics `DescriptorSet`'s, and binds them to the command buffer.
@@ -415,8 +487,56 @@ pub trait RawCommandBuffer<B: Backend>: Clone + Any + Send + Sync {
     fn dispatch(&mut self, count: WorkGroupCount);
 
     /// Works similarly to `dispatch()` but reads parameters from the given
-    /// buffer during execution.
-    fn dispatch_indirect(&mut self, buffer: &B::Buffer, offset: buffer::Offset);
+    /// buffer during execution, starting at `offset`. Performs `dispatch_count`
+    /// dispatches total, which may be zero.
+    ///
+    /// Each dispatch command in the buffer is a series of 3 `u32` values specifying
+    /// the work group count, tightly packed one after the other.
+    ///
+    /// - `buffer` must be created with `buffer::Usage::INDIRECT`.
+    /// - `offset` must be a multiple of 4.
+    /// - The arguments of all dispatches must be within the buffer.
+    fn dispatch_indirect(&mut self, buffer: &B::Buffer, offset: buffer::Offset, dispatch_count: u32);
+
+    /// Build an acceleration structure from the given geometry or instances.
+    ///
+    /// - Only queues with compute capability support this function.
+    /// - `scratch` must point to a buffer region of at least
+    ///   `PrebuildInfo::build_scratch_size` bytes, created with `buffer::Usage::STORAGE`.
+    /// - All buffers referenced by `desc` need to be readable by the
+    ///   `ACCELERATION_STRUCTURE_BUILD` stage.
+    fn build_acceleration_structure<'a>(
+        &mut self,
+        desc: &accel_struct::BuildDesc<'a, B>,
+        dst: &B::AccelerationStructure,
+        scratch: (&B::Buffer, buffer::Offset),
+    );
+
+    /// Bind a ray tracing pipeline.
+    ///
+    /// - Only queues with compute capability support this function.
+    fn bind_ray_tracing_pipeline(&mut self, pipeline: &B::RayTracingPipeline);
+
+    /// Takes an iterator of ray tracing `DescriptorSet`'s, and binds them to the command buffer,
+    /// `first_set` is the index that the first descriptor is mapped to in the command buffer.
+    fn bind_ray_tracing_descriptor_sets<T>(
+        &mut self,
+        layout: &B::PipelineLayout,
+        first_set: usize,
+        sets: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<B::DescriptorSet>;
+
+    /// Launch `width` * `height` * `depth` ray generation shader invocations.
+    ///
+    /// - A ray tracing pipeline must be bound using `bind_ray_tracing_pipeline`.
+    /// - This function must be called outside of a render pass.
+    fn trace_rays<'a>(
+        &mut self,
+        tables: &accel_struct::ShaderBindingTables<'a, B>,
+        count: [u32; 3],
+    );
 
     /// Adds a command to copy regions from the source to destination buffer.
     fn copy_buffer<T>(
@@ -455,6 +575,9 @@ pub trait RawCommandBuffer<B: Backend>: Clone + Any + Send + Sync {
         T::Item: Borrow<BufferImageCopy>;
 
     /// Copies regions from the source image to the destination buffer.
+    ///
+    /// The source image needs to be single-sampled, multisampled images can be
+    /// copied with `CommandBuffer::resolve_to_buffer`.
     fn copy_image_to_buffer<T>(
         &mut self,
         src: &B::Image,
@@ -498,6 +621,13 @@ pub trait RawCommandBuffer<B: Backend>: Clone + Any + Send + Sync {
     /// in order, the number of vertices to draw, the number of instances to draw,
     /// the index of the first vertex to draw, and the instance ID of the first
     /// instance to draw.
+    ///
+    /// - `buffer` must be created with `buffer::Usage::INDIRECT`.
+    /// - `offset` must be a multiple of 4.
+    /// - If `draw_count` is greater than 1, `stride` must be a multiple of 4 and
+    ///   at least the size of the arguments, and `Features::MULTI_DRAW_INDIRECT`
+    ///   must be supported.
+    /// - The arguments of all draws must be within the buffer.
     fn draw_indirect(
         &mut self,
         buffer: &B::Buffer,
@@ -513,6 +643,8 @@ pub trait RawCommandBuffer<B: Backend>: Clone + Any + Send + Sync {
     /// in order, the number of indices, the number of instances, the first index,
     /// the vertex offset, and the first instance.  All are `u32`'s except
     /// the vertex offset, which is an `i32`.
+    ///
+    /// The requirements of `draw_indirect()` apply as well.
     fn draw_indexed_indirect(
         &mut self,
         buffer: &B::Buffer,
@@ -521,6 +653,28 @@ pub trait RawCommandBuffer<B: Backend>: Clone + Any + Send + Sync {
         stride: u32,
     );
 
+    /// Execute `count` indirect command sequences described by `signature`,
+    /// reading the arguments from the given buffer, starting at `offset`.
+    /// `count` may be zero.
+    ///
+    /// Sequences ending with a draw argument are executed like `draw_indirect()`,
+    /// inside of a render pass with a graphics pipeline bound, sequences ending
+    /// with a dispatch like `dispatch_indirect()`. Push constants and vertex buffers
+    /// changed by the sequences are restored to the values set on the command
+    /// buffer afterwards.
+    ///
+    /// - `offset` must be a multiple of 4.
+    /// - The arguments of all sequences must be within the buffer.
+    /// - Signatures with push constant arguments require the bound pipeline
+    ///   to use the layout the signature was created with.
+    fn execute_indirect(
+        &mut self,
+        signature: &B::CommandSignature,
+        buffer: &B::Buffer,
+        offset: buffer::Offset,
+        count: u32,
+    );
+
     /// Begins a query operation.  Queries count operations or record timestamps
     /// resulting from commands that occur between the beginning and end of the query,
     /// and save the results to the query pool.
@@ -535,6 +689,25 @@ pub trait RawCommandBuffer<B: Backend>: Clone + Any + Send + Sync {
     /// Requests a timestamp to be written.
     fn write_timestamp(&mut self, pso::PipelineStage, Query<B>);
 
+    /// Copy the results of the queries in the given range of the query pool
+    /// into `buffer`, starting at `offset` and increasing `stride` bytes with
+    /// each successive query. `QueryPool::result_stride` is the stride of
+    /// tightly packed 64-bit results.
+    ///
+    /// - This function must be called outside of a render pass.
+    /// - None of the queries may be active.
+    /// - `offset` and `stride` must be a multiple of 4, or of 8 if `flags`
+    ///   contains `ResultFlags::BITS_64`.
+    fn copy_query_pool_results(
+        &mut self,
+        pool: &B::QueryPool,
+        queries: Range<QueryId>,
+        buffer: &B::Buffer,
+        offset: buffer::Offset,
+        stride: buffer::Offset,
+        flags: ResultFlags,
+    );
+
     /// Modify constant data in a graphics pipeline.
     /// Push constants are intended to modify data in a pipeline more
     /// quickly than a updating the values inside a descriptor set.
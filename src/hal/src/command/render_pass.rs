@@ -1,12 +1,13 @@
 use std::borrow::Borrow;
 use std::ops::{Range, Deref, DerefMut};
 use std::marker::PhantomData;
+use std::mem;
 
-use {buffer, pso};
-use {Backend, IndexCount, InstanceCount, VertexCount, VertexOffset};
+use {buffer, pass, pso};
+use {Backend, Features, IndexCount, InstanceCount, VertexCount, VertexOffset};
 use queue::{Supports, Graphics};
 use super::{
-    AttachmentClear, ClearValue, CommandBuffer, RawCommandBuffer,
+    AttachmentClear, ClearValue, CommandBuffer, CommandStats, RawCommandBuffer,
     Shot, Level, Primary, Secondary, Submittable, Submit
 };
 
@@ -26,50 +27,114 @@ pub enum SubpassContents {
 ///
 /// Where methods are undocumented, they are identical to the methods on the `RawCommandBuffer`
 /// trait with the same names.
-pub struct RenderSubpassCommon<'a, B: Backend>(pub(crate) &'a mut B::CommandBuffer);
+pub struct RenderSubpassCommon<'a, B: Backend>(
+    pub(crate) &'a mut B::CommandBuffer,
+    pub(crate) Option<&'a mut CommandStats>,
+    pub(crate) Option<Features>,
+);
 
 impl<'a, B: Backend> RenderSubpassCommon<'a, B> {
+    fn record_stats<F: FnOnce(&mut CommandStats)>(&mut self, f: F) {
+        if let Some(ref mut stats) = self.1 {
+            f(stats);
+        }
+    }
+
+    fn validate_draw(&self, instances: &Range<InstanceCount>, base_vertex: VertexOffset) {
+        if let Some(features) = self.2 {
+            debug_assert!(
+                instances.start == 0 || features.contains(Features::DRAW_FIRST_INSTANCE),
+                "Non-zero first instance {} requires `Features::DRAW_FIRST_INSTANCE`",
+                instances.start
+            );
+            debug_assert!(
+                base_vertex == 0 || features.contains(Features::DRAW_BASE_VERTEX),
+                "Non-zero base vertex {} requires `Features::DRAW_BASE_VERTEX`",
+                base_vertex
+            );
+        }
+    }
+
+    fn validate_indirect(&self, offset: buffer::Offset, draw_count: u32, stride: u32, size: u32) {
+        debug_assert_eq!(offset % 4, 0, "Indirect argument offset {} must be a multiple of 4", offset);
+        debug_assert!(
+            draw_count <= 1 || (stride % 4 == 0 && stride >= size),
+            "Indirect argument stride {} must be a multiple of 4 and at least {}",
+            stride,
+            size
+        );
+        if let Some(features) = self.2 {
+            debug_assert!(
+                draw_count <= 1 || features.contains(Features::MULTI_DRAW_INDIRECT),
+                "Draw count {} requires `Features::MULTI_DRAW_INDIRECT`",
+                draw_count
+            );
+        }
+    }
+
+    /// Start validating the parameters of the commands recorded from now on
+    /// against the `features` of the device, see `CommandBuffer::validate_features`.
+    pub fn validate_features(&mut self, features: Features) {
+        self.2 = Some(features);
+    }
+
     ///
     pub fn clear_attachments<T, U>(&mut self, clears: T, rects: U)
     where
         T: IntoIterator,
         T::Item: Borrow<AttachmentClear>,
         U: IntoIterator,
-        U::Item: Borrow<pso::Rect>,
+        U::Item: Borrow<pso::ClearRect>,
     {
         self.0.clear_attachments(clears, rects)
     }
 
     ///
     pub fn draw(&mut self, vertices: Range<VertexCount>, instances: Range<InstanceCount>) {
+        self.validate_draw(&instances, 0);
+        self.record_stats(|stats| stats.draws += 1);
         self.0.draw(vertices, instances)
     }
 
     ///
     pub fn draw_indexed(&mut self, indices: Range<IndexCount>, base_vertex: VertexOffset, instances: Range<InstanceCount>) {
+        self.validate_draw(&instances, base_vertex);
+        self.record_stats(|stats| stats.draws += 1);
         self.0.draw_indexed(indices, base_vertex, instances)
     }
     ///
     pub fn draw_indirect(&mut self, buffer: &B::Buffer, offset: buffer::Offset, draw_count: u32, stride: u32) {
+        self.validate_indirect(offset, draw_count, stride, 16);
+        self.record_stats(|stats| stats.draws += 1);
         self.0.draw_indirect(buffer, offset, draw_count, stride)
     }
     ///
     pub fn draw_indexed_indirect(&mut self, buffer: &B::Buffer, offset: buffer::Offset, draw_count: u32, stride: u32) {
+        self.validate_indirect(offset, draw_count, stride, 20);
+        self.record_stats(|stats| stats.draws += 1);
         self.0.draw_indexed_indirect(buffer, offset, draw_count, stride)
     }
+    ///
+    pub fn execute_indirect(&mut self, signature: &B::CommandSignature, buffer: &B::Buffer, offset: buffer::Offset, count: u32) {
+        self.record_stats(|stats| stats.draws += 1);
+        self.0.execute_indirect(signature, buffer, offset, count)
+    }
 
     ///
     pub fn bind_index_buffer(&mut self, ibv: buffer::IndexBufferView<B>) {
+        self.record_stats(|stats| stats.bindings += 1);
         self.0.bind_index_buffer(ibv)
     }
 
     ///
     pub fn bind_vertex_buffers(&mut self, vbs: pso::VertexBufferSet<B>) {
+        self.record_stats(|stats| stats.bindings += 1);
         self.0.bind_vertex_buffers(vbs);
     }
 
     ///
     pub fn bind_graphics_pipeline(&mut self, pipeline: &B::GraphicsPipeline) {
+        self.record_stats(|stats| stats.bindings += 1);
         self.0.bind_graphics_pipeline(pipeline)
     }
 
@@ -83,6 +148,7 @@ impl<'a, B: Backend> RenderSubpassCommon<'a, B> {
         T: IntoIterator,
         T::Item: Borrow<B::DescriptorSet>,
     {
+        self.record_stats(|stats| stats.bindings += 1);
         self.0.bind_graphics_descriptor_sets(layout, first_set, sets)
     }
 
@@ -114,13 +180,22 @@ impl<'a, B: Backend> RenderSubpassCommon<'a, B> {
         self.0.set_blend_constants(cv)
     }
 
+    /// Identical to the `RawCommandBuffer` method of the same name.
+    pub fn set_depth_bounds(&mut self, bounds: Range<f32>) {
+        self.0.set_depth_bounds(bounds)
+    }
+
+    /// Identical to the `RawCommandBuffer` method of the same name.
+    pub fn set_line_width(&mut self, width: f32) {
+        self.0.set_line_width(width)
+    }
+
     ///
     pub fn push_graphics_constants(&mut self, layout: &B::PipelineLayout, stages: pso::ShaderStageFlags, offset: u32, constants: &[u32]) {
+        self.record_stats(|stats| stats.bindings += 1);
         self.0.push_graphics_constants(layout, stages, offset, constants);
     }
 
-    // TODO: set_line_width
-    // TODO: set_depth_bounds
     // TODO: set_depth_bias
     // TODO: set_stencil_compare_mask
     // TODO: set_stencil_write_mask
@@ -154,7 +229,8 @@ impl<'a, B: Backend, L: Level> RenderPassInlineEncoder<'a, B, L> {
             render_area,
             clear_values,
             SubpassContents::Inline);
-        RenderPassInlineEncoder(Some(RenderSubpassCommon(cmd_buffer.raw)), PhantomData)
+        cmd_buffer.record_stats(|stats| stats.render_passes += 1);
+        RenderPassInlineEncoder(Some(RenderSubpassCommon(cmd_buffer.raw, cmd_buffer.stats.as_mut(), cmd_buffer.features)), PhantomData)
     }
 
     /// Start the next subpass.
@@ -170,7 +246,7 @@ impl<'a, B: Backend> RenderPassInlineEncoder<'a, B, Primary> {
     pub fn next_subpass_secondary(mut self) -> RenderPassSecondaryEncoder<'a, B> {
         let buffer = self.0.take().unwrap();
         buffer.0.next_subpass(SubpassContents::SecondaryBuffers);
-        RenderPassSecondaryEncoder(Some(buffer.0))
+        RenderPassSecondaryEncoder(Some(buffer.0), buffer.1, buffer.2)
     }
 }
 
@@ -197,7 +273,11 @@ impl<'a, B: Backend, L: Level> Drop for RenderPassInlineEncoder<'a, B, L> {
 
 /// An object that records commands into a command buffer where each command must
 /// be a call to execute a secondary command buffer.
-pub struct RenderPassSecondaryEncoder<'a, B: Backend>(pub(crate) Option<&'a mut B::CommandBuffer>)
+pub struct RenderPassSecondaryEncoder<'a, B: Backend>(
+    pub(crate) Option<&'a mut B::CommandBuffer>,
+    pub(crate) Option<&'a mut CommandStats>,
+    pub(crate) Option<Features>,
+)
 where B::CommandBuffer: 'a;
 
 impl<'a, B: Backend> RenderPassSecondaryEncoder<'a, B> {
@@ -223,7 +303,8 @@ impl<'a, B: Backend> RenderPassSecondaryEncoder<'a, B> {
             clear_values,
             SubpassContents::SecondaryBuffers
         );
-        RenderPassSecondaryEncoder(Some(cmd_buffer.raw))
+        cmd_buffer.record_stats(|stats| stats.render_passes += 1);
+        RenderPassSecondaryEncoder(Some(cmd_buffer.raw), cmd_buffer.stats.as_mut(), cmd_buffer.features)
     }
 
     /// Executes the given commands as a secondary command buffer.
@@ -240,7 +321,7 @@ impl<'a, B: Backend> RenderPassSecondaryEncoder<'a, B> {
     pub fn next_subpass_inline(mut self) -> RenderPassInlineEncoder<'a, B, Primary> {
         let buffer = self.0.take().unwrap();
         buffer.next_subpass(SubpassContents::Inline);
-        RenderPassInlineEncoder(Some(RenderSubpassCommon(buffer)), PhantomData)
+        RenderPassInlineEncoder(Some(RenderSubpassCommon(buffer, self.1.take(), self.2)), PhantomData)
     }
 
     /// Starts a new subpass with secondary command buffers.
@@ -258,6 +339,56 @@ impl<'a, B: Backend> Drop for RenderPassSecondaryEncoder<'a, B> {
     }
 }
 
+/// An object that records commands inline while rendering into attachments
+/// bound with `begin_rendering`, without a render pass or framebuffer.
+pub struct RenderingEncoder<'a, B: Backend, L: Level>(pub(crate) RenderSubpassCommon<'a, B>, PhantomData<L>)
+where B::CommandBuffer: 'a;
+
+impl<'a, B: Backend, L: Level> RenderingEncoder<'a, B, L> {
+    /// Creates a new `RenderingEncoder`, beginning to render into the
+    /// given attachments in the `CommandBuffer`.
+    pub fn new<'b, C, T, S: Shot>(
+        cmd_buffer: &'a mut CommandBuffer<B, C, S, L>,
+        render_area: pso::Rect,
+        colors: T,
+        depth_stencil: Option<pass::RenderingAttachment<'b, B>>,
+    ) -> Self
+    where
+        C: Supports<Graphics>,
+        T: IntoIterator,
+        T::Item: Borrow<pass::RenderingAttachment<'b, B>>,
+    {
+        if let Some(features) = cmd_buffer.features {
+            debug_assert!(
+                features.contains(Features::DYNAMIC_RENDERING),
+                "`begin_rendering` requires `Features::DYNAMIC_RENDERING`"
+            );
+        }
+        cmd_buffer.raw.begin_rendering(render_area, colors, depth_stencil);
+        cmd_buffer.record_stats(|stats| stats.render_passes += 1);
+        RenderingEncoder(RenderSubpassCommon(cmd_buffer.raw, cmd_buffer.stats.as_mut(), cmd_buffer.features), PhantomData)
+    }
+}
+
+impl<'a, B: Backend, L: Level> Deref for RenderingEncoder<'a, B, L> {
+    type Target = RenderSubpassCommon<'a, B>;
+    fn deref(&self) -> &RenderSubpassCommon<'a, B> {
+        &self.0
+    }
+}
+
+impl<'a, B: Backend, L: Level> DerefMut for RenderingEncoder<'a, B, L> {
+    fn deref_mut(&mut self) -> &mut RenderSubpassCommon<'a, B> {
+        &mut self.0
+    }
+}
+
+impl<'a, B: Backend, L: Level> Drop for RenderingEncoder<'a, B, L> {
+    fn drop(&mut self) {
+        (self.0).0.end_rendering();
+    }
+}
+
 /// Capability used only for subpass command buffers' Submits.
 pub enum Subpass { }
 
@@ -268,15 +399,19 @@ impl<'a, B: Backend, S: Shot> SubpassCommandBuffer<'a, B, S> {
     /// Wraps the given `CommandBuffer` in a `SubpassCommandBuffer`, starting
     /// to record a new subpass.
     pub unsafe fn new(raw: &mut B::CommandBuffer) -> SubpassCommandBuffer<B, S> {
-        SubpassCommandBuffer(RenderSubpassCommon(raw), PhantomData)
+        SubpassCommandBuffer(RenderSubpassCommon(raw, None, None), PhantomData)
     }
 
     /// Finish recording commands to the command buffer.
     ///
     /// The command buffer will be consumed and can't be modified further.
     /// The command pool must be reset to able to re-record commands.
-    pub fn finish(self) -> Submit<B, Subpass, S, Secondary> {
-        Submit::new((self.0).0.clone())
+    pub fn finish(mut self) -> Submit<B, Subpass, S, Secondary> {
+        let error = (self.0).0.finish().err();
+        let submit = Submit::new((self.0).0.clone(), CommandStats::default(), error);
+        // Already finished, skip the finish on drop.
+        mem::forget(self);
+        submit
     }
 
 }
@@ -296,6 +431,7 @@ impl<'a, B: Backend, S: Shot> DerefMut for SubpassCommandBuffer<'a, B, S> {
 
 impl<'a, B: Backend, S: Shot> Drop for SubpassCommandBuffer<'a, B, S> {
     fn drop(&mut self) {
-        (self.0).0.finish();
+        // Errors are only reported by `finish`.
+        let _ = (self.0).0.finish();
     }
 }
@@ -1,4 +1,33 @@
 //! `CommandBuffer` methods for transfer operations.
+//!
+//! These commands are legal on dedicated transfer queues, so command buffers
+//! with the `Transfer` capability can be handed to upload threads without
+//! exposing graphics or compute commands:
+//!
+//! ```
+//! # extern crate gfx_hal;
+//! # fn main() {}
+//! use gfx_hal::{Backend, Transfer};
+//! use gfx_hal::command::{BufferCopy, CommandBuffer};
+//!
+//! fn upload<B: Backend>(cmd_buffer: &mut CommandBuffer<B, Transfer>, staging: &B::Buffer, dst: &B::Buffer, size: u64) {
+//!     cmd_buffer.copy_buffer(staging, dst, Some(BufferCopy { src: 0, dst: 0, size }));
+//! }
+//! ```
+//!
+//! Commands requiring a graphics or compute queue are rejected at compile time:
+//!
+//! ```compile_fail
+//! # extern crate gfx_hal;
+//! # fn main() {}
+//! use gfx_hal::{Backend, Transfer};
+//! use gfx_hal::command::CommandBuffer;
+//!
+//! fn run<B: Backend>(cmd_buffer: &mut CommandBuffer<B, Transfer>) {
+//!     cmd_buffer.dispatch([1, 1, 1]);
+//! }
+//! ```
+
 use std::borrow::Borrow;
 use std::ops::Range;
 
@@ -72,7 +101,9 @@ impl<'a, B: Backend, C: Supports<Transfer>, S: Shot, L: Level> CommandBuffer<'a,
         T: IntoIterator,
         T::Item: Borrow<Barrier<'i, B>>,
     {
-        self.raw.pipeline_barrier(stages, dependencies, barriers)
+        let mut num_barriers = 0;
+        self.raw.pipeline_barrier(stages, dependencies, barriers.into_iter().inspect(|_| num_barriers += 1));
+        self.record_stats(|stats| stats.barriers += num_barriers);
     }
 
 
@@ -15,15 +15,15 @@ use std::borrow::Borrow;
 use std::error::Error;
 use std::ops::Range;
 
-use {buffer, format, image, mapping, pass, pso, query};
+use {accel_struct, buffer, format, image, indirect, mapping, pass, pso, query};
 use {Backend, MemoryTypeId};
 
 use error::HostExecutionError;
-use memory::Requirements;
+use memory::{self, Requirements};
 use pool::{CommandPool, CommandPoolCreateFlags};
 use queue::{QueueFamilyId, QueueGroup};
 use range::RangeArg;
-use window::{Backbuffer, SwapchainConfig};
+use window::{self, Backbuffer, SwapchainConfig};
 
 
 /// Error allocating memory.
@@ -142,6 +142,47 @@ pub trait Device<B: Backend>: Any + Send + Sync {
     ///
     fn free_memory(&self, memory: B::Memory);
 
+    /// Set the residency priority of memory objects, which decides the order
+    /// in which memory is paged out when the device memory is oversubscribed.
+    ///
+    /// This is only a hint, the default implementation ignores it.
+    fn set_resource_priority<I>(&self, memories: I, priority: memory::ResidencyPriority)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<B::Memory>,
+    {
+        let _ = (memories, priority);
+    }
+
+    /// Make memory objects resident in device memory again after they have been
+    /// evicted, blocking until the contents have been paged in.
+    ///
+    /// Memory is resident after allocation, making resident memory resident again
+    /// has no effect. The default implementation does nothing.
+    fn make_resident<I>(&self, memories: I) -> Result<(), OutOfMemory>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<B::Memory>,
+    {
+        let _ = memories;
+        Ok(())
+    }
+
+    /// Evict memory objects, allowing the system to page them out of the device memory
+    /// to manage oversubscription explicitly. The contents are preserved.
+    ///
+    /// - Evicted memory must not be accessed by the device, including by pending
+    ///   submissions, until it has been made resident again with `make_resident`.
+    ///
+    /// The default implementation does nothing.
+    fn evict<I>(&self, memories: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<B::Memory>,
+    {
+        let _ = memories;
+    }
+
     /// Creates a new command pool for a given queue family.
     ///
     /// *Note*: the family has to be associated by one as the `Gpu::queue_groups`.
@@ -251,6 +292,61 @@ pub trait Device<B: Backend>: Any + Send + Sync {
     /// which references the compute pipeline, has finished execution.
     fn destroy_compute_pipeline(&self, pipeline: B::ComputePipeline);
 
+    /// Get the resource usage of a compute pipeline.
+    ///
+    /// Returns `None` if the backend doesn't support pipeline introspection.
+    fn get_compute_pipeline_statistics(&self, _pipeline: &B::ComputePipeline) -> Option<pso::Statistics> {
+        None
+    }
+
+    /// Create a ray tracing pipeline.
+    ///
+    /// Requires `Features::RAY_TRACING`.
+    fn create_ray_tracing_pipeline<'a>(
+        &self,
+        desc: &pso::RayTracingPipelineDesc<'a, B>,
+    ) -> Result<B::RayTracingPipeline, pso::CreationError>;
+
+    /// Destroys a ray tracing pipeline.
+    ///
+    /// The ray tracing pipeline shouldn't be destroyed before any submitted command buffer,
+    /// which references the ray tracing pipeline, has finished execution.
+    fn destroy_ray_tracing_pipeline(&self, pipeline: B::RayTracingPipeline);
+
+    /// Get the opaque handles of a range of shader groups of a ray tracing pipeline.
+    ///
+    /// Each handle is `Limits::shader_group_handle_size` bytes long and has to be
+    /// copied to the start of the corresponding shader binding table record.
+    fn get_ray_tracing_shader_group_handles(
+        &self,
+        pipeline: &B::RayTracingPipeline,
+        groups: Range<u32>,
+    ) -> Vec<u8>;
+
+    /// Query the memory requirements of an acceleration structure build.
+    fn get_acceleration_structure_prebuild_info<'a>(
+        &self,
+        desc: &accel_struct::BuildDesc<'a, B>,
+    ) -> accel_struct::PrebuildInfo;
+
+    /// Create an acceleration structure object located in the specified buffer region.
+    ///
+    /// The contents are undefined until the structure has been built with
+    /// `build_acceleration_structure`.
+    fn create_acceleration_structure<'a>(
+        &self,
+        desc: &accel_struct::Desc<'a, B>,
+    ) -> Result<B::AccelerationStructure, accel_struct::CreationError>;
+
+    /// Get the device address of a bottom level acceleration structure,
+    /// to be referenced by `accel_struct::Instance`s.
+    fn get_acceleration_structure_address(&self, accel: &B::AccelerationStructure) -> u64;
+
+    /// Destroys an acceleration structure object.
+    ///
+    /// The backing buffer isn't affected and needs to be destroyed separately.
+    fn destroy_acceleration_structure(&self, accel: B::AccelerationStructure);
+
     ///
     fn create_framebuffer<I>(
         &self,
@@ -295,6 +391,15 @@ pub trait Device<B: Backend>: Any + Send + Sync {
         &self, memory: &B::Memory, offset: u64, buf: B::UnboundBuffer
     ) -> Result<B::Buffer, BindError>;
 
+    /// Get the device address of the start of a buffer.
+    ///
+    /// Addresses of buffer regions are computed by adding the offset, which
+    /// allows storing pointers in GPU data structures, e.g. for generating
+    /// indirect arguments. The address is valid until the buffer is destroyed.
+    ///
+    /// Requires `Features::BUFFER_DEVICE_ADDRESS`.
+    fn get_buffer_address(&self, buffer: &B::Buffer) -> u64;
+
     /// Destroys a buffer.
     ///
     /// The buffer shouldn't be destroyed before any submitted command buffer,
@@ -352,7 +457,12 @@ pub trait Device<B: Backend>: Any + Send + Sync {
     ///
     /// Descriptor pools allow allocation of descriptor sets.
     /// Ihe pool can't be modified directly, only through updating descriptor sets.
-    fn create_descriptor_pool<I>(&self, max_sets: usize, descriptor_ranges: I) -> B::DescriptorPool
+    fn create_descriptor_pool<I>(
+        &self,
+        max_sets: usize,
+        descriptor_ranges: I,
+        flags: pso::DescriptorPoolCreateFlags,
+    ) -> B::DescriptorPool
     where
         I: IntoIterator,
         I::Item: Borrow<pso::DescriptorRangeDesc>;
@@ -536,18 +646,43 @@ pub trait Device<B: Backend>: Any + Send + Sync {
         }
     }
 
-    /// true for signaled, false for not ready
+    /// Query the status of a fence without blocking.
+    /// Returns true for signaled, false for not ready.
+    ///
+    /// Used for polling, e.g. by `RetirementQueue::cleanup`, so backends must
+    /// not wait for the fence here.
     fn get_fence_status(&self, fence: &B::Fence) -> bool;
 
     ///
     fn destroy_fence(&self, fence: B::Fence);
 
-    ///
-    fn create_query_pool(&self, ty: query::QueryType, count: u32) -> B::QueryPool;
+    /// Create a new query pool holding `count` queries of the given type.
+    fn create_query_pool(
+        &self,
+        ty: query::QueryType,
+        count: u32,
+        flags: query::QueryPoolCreateFlags,
+    ) -> B::QueryPool;
 
     ///
     fn destroy_query_pool(&self, pool: B::QueryPool);
 
+    /// Create a command signature for `execute_indirect`, describing the arguments
+    /// of one command sequence. The sequences are `stride` bytes apart.
+    ///
+    /// - The last argument must be the only draw or dispatch argument.
+    /// - `stride` must be a multiple of 4 and hold all arguments.
+    /// - `layout` is required if the arguments contain push constants.
+    fn create_command_signature(
+        &self,
+        arguments: &[indirect::Argument],
+        stride: u32,
+        layout: Option<&B::PipelineLayout>,
+    ) -> Result<B::CommandSignature, indirect::CreationError>;
+
+    /// Destroys a command signature.
+    fn destroy_command_signature(&self, signature: B::CommandSignature);
+
     /// Create a new swapchain from a surface and a queue family.
     ///
     /// *Note*: The number of exposed images in the back buffer might differ
@@ -572,14 +707,15 @@ pub trait Device<B: Backend>: Any + Send + Sync {
     /// # let mut surface: empty::Surface = return;
     /// # let device: empty::Device = return;
     /// let swapchain_config = SwapchainConfig::new().with_color(Format::Rgba8Srgb);
-    /// device.create_swapchain(&mut surface, swapchain_config);
+    /// device.create_swapchain(&mut surface, swapchain_config)
+    ///     .expect("Can't create swapchain");
     /// # }
     /// ```
     fn create_swapchain(
         &self,
         surface: &mut B::Surface,
         config: SwapchainConfig,
-    ) -> (B::Swapchain, Backbuffer<B>);
+    ) -> Result<(B::Swapchain, Backbuffer<B>), window::CreationError>;
 
     /// 
     fn destroy_swapchain(
@@ -56,6 +56,51 @@ pub enum HostExecutionError {
     ///
     /// This may be caused by hardware failure, physical device removal,
     /// power outage, etc.
-    #[fail(display = "Physical or logical device lost.")]
-    DeviceLost,
+    #[fail(display = "{}", _0)]
+    DeviceLost(#[cause] DeviceLost),
+}
+
+/// Diagnostics of a lost device.
+///
+/// Backends fill in as much as the driver reports, which may be nothing.
+#[derive(Fail, Debug, Clone, Default, PartialEq, Eq)]
+#[fail(display = "Physical or logical device lost.")]
+pub struct DeviceLost {
+    /// Command markers of the command buffers which didn't finish execution.
+    pub breadcrumbs: Vec<Breadcrumbs>,
+    /// Page fault which caused the device loss, if any.
+    pub page_fault: Option<PageFault>,
+}
+
+/// Command markers of a command buffer executing when the device was lost.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Breadcrumbs {
+    /// Debug name of the command buffer, if any.
+    pub command_buffer: Option<String>,
+    /// Markers of the last commands completed by the device, oldest first.
+    pub completed: Vec<String>,
+    /// Marker of the first command which didn't complete.
+    pub pending: Option<String>,
+}
+
+/// GPU page fault reported for a lost device.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PageFault {
+    /// Faulting GPU virtual address.
+    pub address: u64,
+    /// Names of the resources allocated at the faulting address.
+    pub allocations: Vec<String>,
+    /// Names of the resources recently freed at the faulting address.
+    pub freed_allocations: Vec<String>,
+}
+
+/// Errors of programmatic frame captures.
+#[derive(Fail, Debug, Clone, PartialEq, Eq)]
+pub enum CaptureError {
+    /// No frame capture tool is attached to the application.
+    #[fail(display = "No capture tool is attached.")]
+    NotAvailable,
+    /// The capture tool failed to start or finish the capture.
+    #[fail(display = "Capture failed.")]
+    Failed,
 }
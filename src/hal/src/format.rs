@@ -23,6 +23,10 @@ bitflags!(
         const DEPTH = 0x2;
         /// Stencil aspect.
         const STENCIL = 0x4;
+        /// First plane of a multi-planar format, e.g. the luma plane of `Nv12Unorm`.
+        const PLANE_0 = 0x10;
+        /// Second plane of a multi-planar format, e.g. the chroma plane of `Nv12Unorm`.
+        const PLANE_1 = 0x20;
     }
 );
 
@@ -303,6 +307,32 @@ surface_types! {
     ASTC_10x10          { 128, COLOR, (10, 10) },
     ASTC_12x10          { 128, COLOR, (12, 10) },
     ASTC_12x12          { 128, COLOR, (12, 12) },
+    NV12                {  12, COLOR | PLANE_0 | PLANE_1, (1, 1), color: 24 }, // 4:2:0, averaged bits per texel
+    P010                {  24, COLOR | PLANE_0 | PLANE_1, (1, 1), color: 30 }, // 4:2:0, averaged bits per texel
+    YUY2                {  32, COLOR, (2, 1), color: 24 }, // 4:2:2, packed pair of texels
+}
+
+impl SurfaceType {
+    /// Returns if the surface type is stored in multiple planes,
+    /// which are addressed individually by the `PLANE_*` aspects.
+    pub fn is_planar(&self) -> bool {
+        self.desc().aspects.contains(Aspects::PLANE_0 | Aspects::PLANE_1)
+    }
+
+    /// Get the surface type of a single plane of a multi-planar surface,
+    /// together with the subsampling factors (width, height) of the plane.
+    ///
+    /// Returns `None` if the surface isn't planar or `plane` doesn't specify
+    /// exactly one plane aspect.
+    pub fn plane(&self, plane: Aspects) -> Option<(SurfaceType, (u8, u8))> {
+        match (*self, plane) {
+            (SurfaceType::NV12, Aspects::PLANE_0) => Some((SurfaceType::R8, (1, 1))),
+            (SurfaceType::NV12, Aspects::PLANE_1) => Some((SurfaceType::R8_G8, (2, 2))),
+            (SurfaceType::P010, Aspects::PLANE_0) => Some((SurfaceType::R16, (1, 1))),
+            (SurfaceType::P010, Aspects::PLANE_1) => Some((SurfaceType::R16_G16, (2, 2))),
+            _ => None,
+        }
+    }
 }
 
 /// Generic run-time base format.
@@ -562,6 +592,10 @@ formats! {
     Astc12x10Srgb = (ASTC_12x10, Srgb),
     Astc12x12Unorm = (ASTC_12x12, Unorm),
     Astc12x12Srgb = (ASTC_12x12, Srgb),
+    // Video formats, not part of the core Vulkan format list.
+    Nv12Unorm = (NV12, Unorm),
+    P010Unorm = (P010, Unorm),
+    Yuy2Unorm = (YUY2, Unorm),
 }
 
 impl Format {
@@ -595,6 +629,50 @@ impl Format {
     pub fn is_stencil(self) -> bool {
         self.aspects().contains(Aspects::STENCIL)
     }
+
+    /// Returns if an image of this format can be viewed with the `other` format,
+    /// provided it's created with `StorageFlags::MUTABLE_FORMAT`.
+    ///
+    /// Formats are view compatible if they share the same surface type,
+    /// only differing in the interpretation of the channels.
+    ///
+    /// ```rust
+    /// use gfx_hal::format::Format;
+    ///
+    /// assert!(Format::Rgba8Unorm.is_view_compatible(Format::Rgba8Srgb));
+    /// assert!(Format::Rgba8Unorm.is_view_compatible(Format::Rgba8Uint));
+    /// assert!(!Format::Rgba8Unorm.is_view_compatible(Format::Bgra8Unorm));
+    /// ```
+    pub fn is_view_compatible(self, other: Format) -> bool {
+        self.base_format().0 == other.base_format().0
+    }
+
+    /// Returns if the format is stored in multiple planes.
+    pub fn is_planar(self) -> bool {
+        self.base_format().0.is_planar()
+    }
+
+    /// Get the format used to view a single plane of a multi-planar format,
+    /// together with the subsampling factors (width, height) of the plane.
+    ///
+    /// ```rust
+    /// use gfx_hal::format::{Aspects, Format};
+    ///
+    /// assert_eq!(Format::Nv12Unorm.plane(Aspects::PLANE_0), Some((Format::R8Unorm, (1, 1))));
+    /// assert_eq!(Format::Nv12Unorm.plane(Aspects::PLANE_1), Some((Format::Rg8Unorm, (2, 2))));
+    /// assert_eq!(Format::Rgba8Unorm.plane(Aspects::PLANE_0), None);
+    /// ```
+    pub fn plane(self, plane: Aspects) -> Option<(Format, (u8, u8))> {
+        let (surface, subsampling) = self.base_format().0.plane(plane)?;
+        let format = match surface {
+            SurfaceType::R8 => Format::R8Unorm,
+            SurfaceType::R8_G8 => Format::Rg8Unorm,
+            SurfaceType::R16 => Format::R16Unorm,
+            SurfaceType::R16_G16 => Format::Rg16Unorm,
+            _ => return None,
+        };
+        Some((format, subsampling))
+    }
 }
 
 // Common vertex attribute formats
@@ -0,0 +1,121 @@
+//! Host memory allocation callbacks and tags.
+//!
+//! Backends supporting custom allocators, e.g. Vulkan with `VkAllocationCallbacks`,
+//! route the host memory allocations of the driver through `AllocationCallbacks`
+//! passed at instance or device creation. The callbacks are called from any thread.
+//!
+//! Allocations can be attributed to a `Tag`, which is active on the current thread
+//! until it's dropped. Allocation callbacks and memory profilers query the innermost
+//! tag with `current_tag`, backends also use it to name native objects, e.g. D3D12
+//! resources for PIX.
+//!
+//! ```rust
+//! use gfx_hal::host_memory::{current_tag, Tag};
+//!
+//! assert_eq!(current_tag(), None);
+//! {
+//!     let _terrain = Tag::new("terrain");
+//!     // create and bind the terrain resources..
+//!     assert_eq!(current_tag(), Some("terrain"));
+//!     {
+//!         let _normals = Tag::new("terrain normals");
+//!         assert_eq!(current_tag(), Some("terrain normals"));
+//!     }
+//!     assert_eq!(current_tag(), Some("terrain"));
+//! }
+//! assert_eq!(current_tag(), None);
+//! ```
+
+use std::cell::Cell;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Lifetime of a host memory allocation, mirroring `VkSystemAllocationScope`.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Scope {
+    /// Allocation used during a single command.
+    Command,
+    /// Allocation bound to the lifetime of an object.
+    Object,
+    /// Allocation of a pipeline cache.
+    Cache,
+    /// Allocation bound to the lifetime of the device.
+    Device,
+    /// Allocation bound to the lifetime of the instance.
+    Instance,
+}
+
+/// Host memory allocator of the driver.
+///
+/// Pointers returned by `allocate` and `reallocate` must be aligned to
+/// `alignment` and are only freed by the same callbacks.
+pub trait AllocationCallbacks: fmt::Debug + Send + Sync {
+    /// Allocate `size` bytes, returns a null pointer on failure.
+    ///
+    /// # Safety
+    ///
+    /// `alignment` _must_ be a power of two. The returned memory is uninitialized
+    /// and _must_ only be released with `reallocate` or `free` of the same callbacks.
+    unsafe fn allocate(&self, size: usize, alignment: usize, scope: Scope) -> *mut u8;
+
+    /// Resize an allocation to `size` bytes, keeping its contents.
+    ///
+    /// `ptr` may be null, then this behaves like `allocate`. If `size` is zero,
+    /// this behaves like `free` and returns a null pointer. On failure the original
+    /// allocation isn't freed and a null pointer is returned.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` _must_ be null or a live allocation of the same callbacks, allocated with
+    /// the same `alignment`. On success `ptr` is invalidated and _must not_ be used anymore.
+    unsafe fn reallocate(&self, ptr: *mut u8, size: usize, alignment: usize, scope: Scope) -> *mut u8;
+
+    /// Free an allocation, `ptr` may be null.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` _must_ be null or a live allocation of the same callbacks,
+    /// it _must not_ be used after this call.
+    unsafe fn free(&self, ptr: *mut u8);
+
+    /// Notification of an allocation the driver made on its own, e.g. for executable code.
+    fn internal_allocation(&self, _size: usize, _scope: Scope) {}
+
+    /// Notification of the release of an internal allocation.
+    fn internal_free(&self, _size: usize, _scope: Scope) {}
+}
+
+thread_local! {
+    static CURRENT_TAG: Cell<Option<&'static str>> = Cell::new(None);
+}
+
+/// Allocation tag, active on the current thread until dropped.
+///
+/// Tags nest, the tag created last is the current one.
+#[derive(Debug)]
+pub struct Tag {
+    previous: Option<&'static str>,
+    // Tags are restored on the thread they were created on.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Tag {
+    /// Attribute the allocations of the current thread to `name`.
+    pub fn new(name: &'static str) -> Self {
+        Tag {
+            previous: CURRENT_TAG.with(|tag| tag.replace(Some(name))),
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl Drop for Tag {
+    fn drop(&mut self) {
+        CURRENT_TAG.with(|tag| tag.set(self.previous));
+    }
+}
+
+/// Innermost tag of the current thread.
+pub fn current_tag() -> Option<&'static str> {
+    CURRENT_TAG.with(|tag| tag.get())
+}
@@ -216,6 +216,25 @@ pub enum Anisotropic {
     On(u8),
 }
 
+/// Reduction of the texels fetched by a filtered sampling operation.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Reduction {
+    /// Weighted average of the texels, as given by the filters.
+    WeightedAverage,
+    /// Component-wise minimum of the texels with non-zero weights,
+    /// e.g. for building depth pyramids.
+    Minimum,
+    /// Component-wise maximum of the texels with non-zero weights.
+    Maximum,
+}
+
+impl Default for Reduction {
+    fn default() -> Self {
+        Reduction::WeightedAverage
+    }
+}
+
 /// The face of a cube image to do an operation on.
 #[allow(missing_docs)]
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -359,14 +378,29 @@ bitflags!(
     /// Image storage flags
     #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
     pub struct StorageFlags: u32 {
+        /// Support creation of views with a different format than the image.
+        ///
+        /// The view formats need to be compatible with the image format,
+        /// see `Format::is_view_compatible`. Allows viewing render targets
+        /// as both `Unorm` and `Srgb` for example.
+        const MUTABLE_FORMAT = 0b0001000;
         /// Support creation of `Cube` and `CubeArray` views.
         const CUBE_VIEW = 0b0010000;
+        /// The state of the image is tracked by the backend, which inserts the
+        /// required transitions while recording commands. Barriers and layouts
+        /// specified by the user are ignored for the image.
+        ///
+        /// Command buffers using tracked images must be submitted in the order
+        /// they were recorded in. Images are transitioned as a whole when used by
+        /// transfer commands, as render pass attachments or when binding descriptor
+        /// sets referencing them, later writes to bound sets are not tracked.
+        ///
+        /// Requires `Features::TRACKED_RESOURCES`.
+        const TRACKED = 0x8000_0000;
     }
 );
 
 bitflags!(
-    /// TODO: Find out if TRANSIENT_ATTACHMENT + INPUT_ATTACHMENT
-    /// are applicable on backends other than Vulkan. --AP
     /// Image usage flags
     #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
     pub struct Usage: u32 {
@@ -382,9 +416,15 @@ bitflags!(
         const COLOR_ATTACHMENT = 0x10;
         /// The image is used as a depth attachment.
         const DEPTH_STENCIL_ATTACHMENT = 0x20;
+        /// The image is only used as attachment within render passes.
         ///
+        /// The load and store operations of the render passes are honored, backends
+        /// only skip loading and storing contents which aren't needed. Transient images
+        /// may be bound to memory overlapping other transient images which aren't used
+        /// at the same time. Their contents are lost when another image uses the memory,
+        /// so the first render pass using them afterwards must not load their contents.
         const TRANSIENT_ATTACHMENT = 0x40;
-        ///
+        /// The image is read as input attachment by subpasses of a render pass.
         const INPUT_ATTACHMENT = 0x80;
 
     }
@@ -482,9 +522,17 @@ pub struct SamplerInfo {
     /// Comparison mode, used primary for a shadow map.
     pub comparison: Option<Comparison>,
     /// Border color is used when one of the wrap modes is set to border.
+    ///
+    /// Colors other than transparent black, opaque black and opaque white
+    /// require `Features::SAMPLER_BORDER_COLOR`.
     pub border: PackedColor,
     /// Anisotropic filtering.
     pub anisotropic: Anisotropic,
+    /// Reduction of the filtered texels, can't be combined with `comparison`.
+    ///
+    /// Modes other than `WeightedAverage` require `Features::SAMPLER_REDUCTION`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub reduction: Reduction,
 }
 
 impl SamplerInfo {
@@ -501,6 +549,7 @@ impl SamplerInfo {
             comparison: None,
             border: PackedColor(0),
             anisotropic: Anisotropic::Off,
+            reduction: Reduction::WeightedAverage,
         }
     }
 }
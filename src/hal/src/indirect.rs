@@ -0,0 +1,164 @@
+//! Indirect command signatures.
+//!
+//! A command signature describes the layout of one indirect command sequence,
+//! a list of `Argument`s read from a buffer by `RawCommandBuffer::execute_indirect`.
+//! Each sequence may change some state before issuing a single draw or dispatch,
+//! which allows generating whole command streams on the device.
+//!
+//! The arguments are tightly packed in the order they are specified, the sequences
+//! are `stride` bytes apart:
+//!
+//! ```no_run
+//! # extern crate gfx_hal;
+//! # fn main() {
+//! use gfx_hal::indirect::Argument;
+//! use gfx_hal::pso::ShaderStageFlags;
+//!
+//! // Per draw constants, followed by the vertex buffer and the draw arguments.
+//! let arguments = [
+//!     Argument::PushConstants { stages: ShaderStageFlags::VERTEX, offset: 0, count: 4 },
+//!     Argument::VertexBuffer(0),
+//!     Argument::Draw,
+//! ];
+//! let stride: u32 = arguments.iter().map(Argument::size).sum();
+//! assert_eq!(stride, 48);
+//! # }
+//! ```
+
+use std::error::Error;
+use std::fmt;
+
+use device::OutOfMemory;
+use pso;
+
+/// Argument of an indirect command sequence.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Argument {
+    /// Update the push constants `offset .. offset + count`, in units of `u32`,
+    /// read as `count` `u32` values.
+    ///
+    /// Requires `Features::INDIRECT_STATE_ARGUMENTS`.
+    PushConstants {
+        /// Stages the constants are visible to.
+        stages: pso::ShaderStageFlags,
+        /// Index of the first constant.
+        offset: u32,
+        /// Number of constants.
+        count: u32,
+    },
+    /// Bind a vertex buffer to the given slot, read as a `VertexBufferArgument`.
+    ///
+    /// Requires `Features::INDIRECT_STATE_ARGUMENTS`.
+    VertexBuffer(pso::BufferIndex),
+    /// Draw command, read like the arguments of `draw_indirect`.
+    Draw,
+    /// Indexed draw command, read like the arguments of `draw_indexed_indirect`.
+    DrawIndexed,
+    /// Dispatch command, read like the arguments of `dispatch_indirect`.
+    Dispatch,
+}
+
+impl Argument {
+    /// Size of the argument in the buffer, in bytes.
+    pub fn size(&self) -> u32 {
+        match *self {
+            Argument::PushConstants { count, .. } => 4 * count,
+            Argument::VertexBuffer(_) => 16,
+            Argument::Draw => 16,
+            Argument::DrawIndexed => 20,
+            Argument::Dispatch => 12,
+        }
+    }
+
+    /// Check if the argument issues a draw or dispatch command.
+    pub fn is_command(&self) -> bool {
+        match *self {
+            Argument::Draw | Argument::DrawIndexed | Argument::Dispatch => true,
+            Argument::PushConstants { .. } | Argument::VertexBuffer(_) => false,
+        }
+    }
+}
+
+/// Vertex buffer binding, as read by `Argument::VertexBuffer`.
+///
+/// The layout matches `D3D12_VERTEX_BUFFER_VIEW`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VertexBufferArgument {
+    /// Device address of the vertex data, see `Device::get_buffer_address`.
+    pub address: u64,
+    /// Size of the vertex data, in bytes.
+    pub size: u32,
+    /// Distance between two consecutive vertices, in bytes.
+    pub stride: u32,
+}
+
+/// Error creating a command signature.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CreationError {
+    /// Out of either host or device memory.
+    OutOfMemory(OutOfMemory),
+    /// The sequence doesn't end with its only draw or dispatch argument.
+    InvalidSequence,
+    /// The stride is smaller than the arguments or not a multiple of 4.
+    InvalidStride(u32),
+    /// The argument at the given index is not supported by the device.
+    UnsupportedArgument(usize),
+    /// Push constant arguments require a pipeline layout.
+    MissingLayout,
+}
+
+impl From<OutOfMemory> for CreationError {
+    fn from(error: OutOfMemory) -> Self {
+        CreationError::OutOfMemory(error)
+    }
+}
+
+impl fmt::Display for CreationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CreationError::InvalidStride(stride) => write!(f, "{}: {}", self.description(), stride),
+            CreationError::UnsupportedArgument(index) => write!(f, "{}: {}", self.description(), index),
+            _ => write!(f, "{}", self.description()),
+        }
+    }
+}
+
+impl Error for CreationError {
+    fn description(&self) -> &str {
+        match *self {
+            CreationError::OutOfMemory(_) => "Out of memory",
+            CreationError::InvalidSequence => "The sequence must end with its only draw or dispatch argument",
+            CreationError::InvalidStride(_) => "Invalid stride of the command sequences",
+            CreationError::UnsupportedArgument(_) => "Unsupported indirect argument",
+            CreationError::MissingLayout => "Push constant arguments require a pipeline layout",
+        }
+    }
+}
+
+/// Check the argument order, stride and layout requirement of a command sequence.
+///
+/// Backends may call this before translating the arguments.
+pub fn validate_sequence(
+    arguments: &[Argument],
+    stride: u32,
+    has_layout: bool,
+) -> Result<(), CreationError> {
+    match arguments.split_last() {
+        Some((last, state)) if last.is_command() && !state.iter().any(Argument::is_command) => {}
+        _ => return Err(CreationError::InvalidSequence),
+    }
+    let size: u32 = arguments.iter().map(Argument::size).sum();
+    if stride < size || stride % 4 != 0 {
+        return Err(CreationError::InvalidStride(stride));
+    }
+    let has_constants = arguments.iter().any(|arg| match *arg {
+        Argument::PushConstants { .. } => true,
+        _ => false,
+    });
+    if has_constants && !has_layout {
+        return Err(CreationError::MissingLayout);
+    }
+    Ok(())
+}
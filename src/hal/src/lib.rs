@@ -30,29 +30,39 @@ pub use self::adapter::{
 pub use self::device::Device;
 pub use self::pool::CommandPool;
 pub use self::pso::DescriptorPool;
+pub use self::query::QueryPool;
 pub use self::queue::{
     CommandQueue, QueueGroup, QueueFamily, QueueType, Submission,
     Capability, Supports, General, Graphics, Compute, Transfer,
 };
 pub use self::window::{
-    Backbuffer, Frame, FrameSync, Surface, SurfaceCapabilities, Swapchain, SwapchainConfig,
+    Backbuffer, Frame, FrameStatistics, FrameSync, HasRawWindowHandle, ImageData,
+    RawWindowHandle, ReadbackError, Surface, SurfaceCapabilities, Swapchain, SwapchainConfig,
 };
 
+pub mod accel_struct;
 pub mod adapter;
 pub mod buffer;
 pub mod command;
 pub mod device;
 pub mod error;
 pub mod format;
+pub mod host_memory;
 pub mod image;
+pub mod indirect;
 pub mod mapping;
 pub mod memory;
+pub mod offscreen;
 pub mod pass;
 pub mod pool;
+pub mod profiler;
 pub mod pso;
 pub mod query;
 pub mod queue;
 pub mod range;
+pub mod retirement;
+#[cfg(feature = "transcode")]
+pub mod transcode;
 pub mod window;
 
 #[doc(hidden)]
@@ -86,13 +96,16 @@ pub type SamplerSlot = u8;
 
 bitflags! {
     /// Features that the device supports.
-    /// These only include features of the core interface and not API extensions.
+    /// The lower 64 bits include features of the core interface,
+    /// the upper ones optional features exposed by API extensions.
     #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-    pub struct Features: u64 {
+    pub struct Features: u128 {
         /// Bit mask of Vulkan Core features.
         const CORE_MASK   = 0x0FFF_FFFF_FFFF_FFFF;
         /// Bit mask of Vulkan Portability features.
         const PORTABILITY_MASK  = 0xF000_0000_0000_0000;
+        /// Bit mask of extension features.
+        const EXTENSION_MASK = 0xFFFF_FFFF_FFFF_FFFF_0000_0000_0000_0000;
 
         /// Support for robust buffer access.
         /// Buffer access by SPIR-V shaders is checked against the buffer/image boundaries.
@@ -127,13 +140,16 @@ bitflags! {
         const NON_FILL_POLYGON_MODE = 0x000_0000_0000_2000;
         /// Support depth bounds test.
         const DEPTH_BOUNDS = 0x000_0000_0000_4000;
-        /// Support lines with width other than 1.0.
+        /// Support lines with width other than 1.0, either baked into
+        /// the pipeline or set with `set_line_width`.
         const LINE_WIDTH = 0x000_0000_0000_8000;
-        /// Support points with size greater than 1.0.
+        /// Support points with size greater than 1.0, written by the shaders
+        /// with the `PointSize` built-in. If not supported, points have a size of 1.0.
         const POINT_SIZE = 0x000_0000_0001_0000;
         /// Support replacing alpha values with 1.0.
         const ALPHA_TO_ONE = 0x000_0000_0002_0000;
-        /// Support multiple viewports and scissors.
+        /// Support multiple viewports and scissors, selected per primitive
+        /// with the `ViewportIndex` shader built-in.
         const MULTI_VIEWPORTS = 0x000_0000_0004_0000;
         /// Support anisotropic filtering.
         const SAMPLER_ANISOTROPY = 0x000_0000_0008_0000;
@@ -218,6 +234,48 @@ bitflags! {
         const SEPARATE_STENCIL_REF_VALUES = 0x2000_0000_0000_0000;
         /// Support manually specified vertex attribute rates (divisors).
         const INSTANCE_RATE = 0x8000_0000_0000_0000;
+
+        /// Support ray tracing pipelines and acceleration structures.
+        const RAY_TRACING = 0x0001_0000_0000_0000_0000;
+        /// Support rendering multiple views in a single subpass.
+        const MULTIVIEW = 0x0002_0000_0000_0000_0000;
+        /// Support querying the device address of buffers with `Device::get_buffer_address`.
+        const BUFFER_DEVICE_ADDRESS = 0x0004_0000_0000_0000_0000;
+        /// Support rasterizer ordered access to storage resources in the fragment shader,
+        /// see `DescriptorSetLayoutBinding::rasterizer_ordered`.
+        const RASTERIZER_ORDERED_VIEWS = 0x0008_0000_0000_0000_0000;
+        /// Support subgroup operations in shaders, e.g. wave intrinsics.
+        const SUBGROUP_OPERATIONS = 0x0010_0000_0000_0000_0000;
+        /// Support bound descriptor sets with invalid descriptors, as long as
+        /// these aren't accessed by any shader. If not supported, all descriptors
+        /// of a bound set **must** reference valid resources.
+        const PARTIALLY_BOUND_DESCRIPTORS = 0x0020_0000_0000_0000_0000;
+        /// Support controlling depth clipping independently of depth clamping,
+        /// see `Rasterizer::depth_clipping`.
+        const DEPTH_CLIP_CONTROL = 0x0040_0000_0000_0000_0000;
+        /// Support push constant and vertex buffer arguments in indirect command signatures,
+        /// see `indirect::Argument`.
+        const INDIRECT_STATE_ARGUMENTS = 0x0080_0000_0000_0000_0000;
+        /// Support automatic state transitions of resources created with the
+        /// `TRACKED` flag, see `image::StorageFlags::TRACKED`.
+        const TRACKED_RESOURCES = 0x0100_0000_0000_0000_0000;
+        /// Support non-zero first instance values in `draw` and `draw_indexed`.
+        /// If not supported, instance ranges **must** start at 0.
+        const DRAW_FIRST_INSTANCE = 0x0200_0000_0000_0000_0000;
+        /// Support non-zero base vertex values in `draw_indexed`.
+        /// If not supported, the base vertex **must** be 0.
+        const DRAW_BASE_VERTEX = 0x0400_0000_0000_0000_0000;
+        /// Support minimum and maximum reduction of filtered texels,
+        /// see `SamplerInfo::reduction`.
+        const SAMPLER_REDUCTION = 0x0800_0000_0000_0000_0000;
+        /// Support arbitrary sampler border colors. If not supported, border colors
+        /// **must** be transparent black, opaque black or opaque white.
+        const SAMPLER_BORDER_COLOR = 0x1000_0000_0000_0000_0000;
+        /// Support command pools created with `CommandPoolCreateFlags::CONCURRENT_RECORDING`.
+        const CONCURRENT_COMMAND_RECORDING = 0x2000_0000_0000_0000_0000;
+        /// Support rendering without render pass and framebuffer objects,
+        /// see `RawCommandBuffer::begin_rendering`.
+        const DYNAMIC_RENDERING = 0x4000_0000_0000_0000_0000;
     }
 }
 
@@ -227,6 +285,10 @@ bitflags! {
 pub struct Limits {
     /// Maximum supported texture size.
     pub max_texture_size: usize,
+    /// Maximum supported size of 3D textures.
+    pub max_texture_3d_size: usize,
+    /// Maximum number of layers of array textures.
+    pub max_texture_array_layers: usize,
     /// Maximum number of vertices for each patch.
     pub max_patch_size: PatchSize,
     /// Maximum number of viewports.
@@ -236,6 +298,25 @@ pub struct Limits {
     ///
     pub max_compute_group_size: [u32; 3],
 
+    /// Maximum size of the push constants of a pipeline layout, in bytes.
+    pub max_push_constants_size: usize,
+    /// Maximum number of descriptor sets of a pipeline layout.
+    pub max_bound_descriptor_sets: usize,
+    /// Maximum number of samplers accessible by a single shader stage.
+    pub max_per_stage_descriptor_samplers: usize,
+    /// Maximum number of uniform buffers accessible by a single shader stage.
+    pub max_per_stage_descriptor_uniform_buffers: usize,
+    /// Maximum number of storage buffers accessible by a single shader stage.
+    pub max_per_stage_descriptor_storage_buffers: usize,
+    /// Maximum number of sampled images and uniform texel buffers accessible
+    /// by a single shader stage.
+    pub max_per_stage_descriptor_sampled_images: usize,
+    /// Maximum number of storage images and storage texel buffers accessible
+    /// by a single shader stage.
+    pub max_per_stage_descriptor_storage_images: usize,
+    /// Maximum number of elements addressable by a texel buffer view.
+    pub max_texel_elements: usize,
+
     /// The alignment of the start of the buffer used as a GPU copy source, in bytes, non-zero.
     pub min_buffer_copy_offset_alignment: buffer::Offset,
     /// The alignment of the row pitch of the texture data stored in a buffer that is
@@ -243,6 +324,21 @@ pub struct Limits {
     pub min_buffer_copy_pitch_alignment: buffer::Offset,
     /// The alignment of the start of buffer used for uniform buffer updates, in bytes, non-zero.
     pub min_uniform_buffer_offset_alignment: buffer::Offset,
+    /// The alignment of the ranges of non-coherent memory which are flushed
+    /// or invalidated, in bytes, non-zero.
+    pub non_coherent_atom_size: buffer::Offset,
+
+    /// Size of a shader group handle at the start of each shader binding table record, in bytes.
+    pub shader_group_handle_size: u32,
+    /// Maximum recursion depth of ray tracing pipelines.
+    pub max_ray_recursion_depth: u32,
+    /// The alignment of the start of a shader binding table, in bytes.
+    pub min_shader_table_alignment: buffer::Offset,
+    /// The alignment of the stride between shader binding table records, in bytes.
+    pub min_shader_record_alignment: buffer::Offset,
+
+    /// Maximum number of views of a multiview subpass.
+    pub max_multiview_view_count: u32,
 }
 
 /// Describes the type of geometric primitives,
@@ -307,6 +403,47 @@ pub trait Instance: Any + Send + Sync {
     type Backend: Backend;
     /// Return all available adapters.
     fn enumerate_adapters(&self) -> Vec<Adapter<Self::Backend>>;
+
+    /// Return all available adapters supporting compute, for headless applications.
+    ///
+    /// Only queue families with compute or transfer capability are exposed.
+    /// Backends may skip the setup required for presentation when opening
+    /// these adapters, swapchains can't be created from the resulting devices.
+    fn enumerate_adapters_compute_only(&self) -> Vec<Adapter<Self::Backend>> {
+        self.enumerate_adapters()
+            .into_iter()
+            .filter_map(|mut adapter| {
+                adapter.queue_families.retain(|family| family.queue_type() != QueueType::Graphics);
+                if adapter.queue_families.iter().any(|family| family.supports_compute()) {
+                    Some(adapter)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Capture the next frame presented by the application.
+    ///
+    /// Requires a frame capture tool like RenderDoc or PIX to be attached to the
+    /// application before the instance is created, `CaptureError::NotAvailable`
+    /// is returned otherwise.
+    fn trigger_capture(&self) -> Result<(), error::CaptureError> {
+        Err(error::CaptureError::NotAvailable)
+    }
+
+    /// Begin capturing all GPU work until `end_capture` is called.
+    ///
+    /// This allows capturing work not tied to the presentation of frames,
+    /// e.g. compute workloads or offscreen rendering.
+    fn begin_capture(&self) -> Result<(), error::CaptureError> {
+        Err(error::CaptureError::NotAvailable)
+    }
+
+    /// End a capture started with `begin_capture`.
+    fn end_capture(&self) -> Result<(), error::CaptureError> {
+        Err(error::CaptureError::NotAvailable)
+    }
 }
 
 /// The `Backend` trait wraps together all the types needed
@@ -349,7 +486,11 @@ pub trait Backend: 'static + Sized + Eq + Clone + Hash + fmt::Debug + Any + Send
 
     type Fence:               fmt::Debug + Any + Send + Sync;
     type Semaphore:           fmt::Debug + Any + Send + Sync;
-    type QueryPool:           fmt::Debug + Any + Send + Sync;
+    type QueryPool:           query::QueryPool;
+    type CommandSignature:    fmt::Debug + Any + Send + Sync;
+
+    type AccelerationStructure: fmt::Debug + Any + Send + Sync;
+    type RayTracingPipeline:  fmt::Debug + Any + Send + Sync;
 }
 
 /// Marks that an error occured submitting a command to a command buffer.
@@ -4,6 +4,7 @@ use std::mem;
 use std::ops::Range;
 use {buffer, image};
 use Backend;
+use queue::QueueFamilyId;
 
 /// A trait for plain-old-data types.
 ///
@@ -78,6 +79,14 @@ bitflags!(
 // trying to explain the whole synchronization model.
 /// A [memory barrier](https://www.khronos.org/registry/vulkan/specs/1.0/html/vkspec.html#synchronization-memory-barriers)
 /// type for either buffers or images.
+///
+/// # Queue family ownership transfer
+///
+/// Buffer and image barriers can transfer the ownership of a resource between two
+/// queue families, e.g. for asynchronous compute. The same barrier needs to be recorded
+/// on both families: the release on a queue of the source family and the acquire on a
+/// queue of the destination family. The acquiring submission must wait on a semaphore
+/// signalled by the releasing submission.
 #[allow(missing_docs)] 
 #[derive(Clone, Debug)]
 pub enum Barrier<'a, B: Backend> {
@@ -91,6 +100,9 @@ pub enum Barrier<'a, B: Backend> {
         states: Range<buffer::State>,
         /// The buffer the barrier controls.
         target: &'a B::Buffer,
+        /// Source and destination queue family of an ownership transfer,
+        /// `None` if the ownership doesn't change.
+        families: Option<Range<QueueFamilyId>>,
     },
     /// A memory barrier that defines access to (a subset of) an image.
     Image {
@@ -100,6 +112,9 @@ pub enum Barrier<'a, B: Backend> {
         target: &'a B::Image,
         /// A `SubresourceRange` that defines which section of an image the barrier applies to.
         range: image::SubresourceRange,
+        /// Source and destination queue family of an ownership transfer,
+        /// `None` if the ownership doesn't change.
+        families: Option<Range<QueueFamilyId>>,
     },
 }
 
@@ -113,3 +128,22 @@ pub struct Requirements {
     /// Supported memory types.
     pub type_mask: u64,
 }
+
+/// Residency priority of a memory object.
+///
+/// When the device memory is oversubscribed, memory objects with a lower
+/// priority are paged out first.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ResidencyPriority {
+    /// Evicted first, e.g. for data which can be regenerated cheaply.
+    Minimum,
+    /// Below the default priority.
+    Low,
+    /// Default priority of newly allocated memory.
+    Normal,
+    /// Above the default priority.
+    High,
+    /// Evicted last, e.g. for render targets and other data accessed every frame.
+    Maximum,
+}
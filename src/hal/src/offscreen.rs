@@ -0,0 +1,349 @@
+//! Offscreen rendering without a window.
+//!
+//! An [`OffscreenSwapchain`] provides a chain of color images, which can be
+//! rendered to like the backbuffers of a swapchain, but don't require a surface.
+//! Rendered frames are copied into host visible memory and read back,
+//! which allows server-side rendering and image comparisons in tests.
+//!
+//! Offscreen swapchains work with instances of any backend. Only the Vulkan backend
+//! requires window system integration extensions on instance creation, its
+//! `Instance::create_headless` creates instances without them, for systems without
+//! a windowing system. The other backends don't provide headless instances, as
+//! their regular instances don't depend on one.
+//!
+//! [`OffscreenSwapchain`]: struct.OffscreenSwapchain.html
+//!
+//! ```no_run
+//! # extern crate gfx_backend_empty as empty;
+//! # extern crate gfx_hal;
+//! # fn main() {
+//! use gfx_hal::SwapchainConfig;
+//! use gfx_hal::format::Format;
+//! use gfx_hal::image::Layout;
+//! use gfx_hal::offscreen::OffscreenSwapchain;
+//! use gfx_hal::window::Extent2D;
+//! # use gfx_hal::{command, Graphics, Limits, MemoryType};
+//!
+//! # let device: empty::Device = return;
+//! # let memory_types: Vec<MemoryType> = return;
+//! # let limits: Limits = return;
+//! # let mut cmd_buffer: command::CommandBuffer<empty::Backend, Graphics> = return;
+//! let config = SwapchainConfig::new()
+//!     .with_color(Format::Rgba8Unorm)
+//!     .with_image_count(1);
+//! let extent = Extent2D { width: 256, height: 256 };
+//! let mut swapchain = OffscreenSwapchain::<empty::Backend>::new(&device, &memory_types, &limits, &config, extent)
+//!     .unwrap();
+//!
+//! let frame = swapchain.acquire_frame();
+//! // render the scene into `swapchain.image(&frame)`..
+//! swapchain.copy_to_readback(&mut cmd_buffer, &frame, Layout::Present);
+//! // submit the command buffer and wait for its completion..
+//! let pixels = swapchain.read_pixels(&device, &frame).unwrap();
+//! swapchain.destroy(&device);
+//! # }
+//! ```
+
+use std::error::Error;
+use std::fmt;
+
+use {buffer, format, image, mapping, memory};
+use {Backend, Frame, Limits, MemoryType, MemoryTypeId};
+use command::{BufferImageCopy, CommandBuffer, Level, Shot};
+use device::{BindError, Device, OutOfMemory};
+use pso::PipelineStage;
+use queue::capability::{Supports, Transfer};
+use window::{Extent2D, SwapchainConfig};
+
+
+/// Error creating an `OffscreenSwapchain`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CreationError {
+    /// Image creation failed.
+    Image(image::CreationError),
+    /// Readback buffer creation failed.
+    Buffer(buffer::CreationError),
+    /// No memory type satisfies the requirements of the images or readback buffers.
+    NoMemoryType,
+    /// Out of memory.
+    OutOfMemory,
+    /// Binding of the resources to memory failed.
+    Bind(BindError),
+}
+
+impl fmt::Display for CreationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = self.description();
+        match *self {
+            CreationError::Image(ref err) => write!(f, "{}: {}", description, err),
+            CreationError::Buffer(ref err) => write!(f, "{}: {}", description, err),
+            CreationError::Bind(ref err) => write!(f, "{}: {}", description, err),
+            _ => write!(f, "{}", description),
+        }
+    }
+}
+
+impl Error for CreationError {
+    fn description(&self) -> &str {
+        match *self {
+            CreationError::Image(_) => "Failed to create an image",
+            CreationError::Buffer(_) => "Failed to create a readback buffer",
+            CreationError::NoMemoryType => "No memory type satisfies the resource requirements",
+            CreationError::OutOfMemory => "Out of memory",
+            CreationError::Bind(_) => "Failed to bind a resource to memory",
+        }
+    }
+}
+
+impl From<image::CreationError> for CreationError {
+    fn from(err: image::CreationError) -> Self {
+        CreationError::Image(err)
+    }
+}
+
+impl From<buffer::CreationError> for CreationError {
+    fn from(err: buffer::CreationError) -> Self {
+        CreationError::Buffer(err)
+    }
+}
+
+impl From<OutOfMemory> for CreationError {
+    fn from(_: OutOfMemory) -> Self {
+        CreationError::OutOfMemory
+    }
+}
+
+impl From<BindError> for CreationError {
+    fn from(err: BindError) -> Self {
+        CreationError::Bind(err)
+    }
+}
+
+pub(crate) fn find_memory_type(
+    memory_types: &[MemoryType],
+    type_mask: u64,
+    properties: memory::Properties,
+) -> Option<MemoryTypeId> {
+    memory_types
+        .iter()
+        .enumerate()
+        .position(|(id, ty)| type_mask & (1 << id) != 0 && ty.properties.contains(properties))
+        .map(MemoryTypeId)
+}
+
+#[derive(Debug)]
+struct OffscreenFrame<B: Backend> {
+    image: B::Image,
+    image_memory: B::Memory,
+    buffer: B::Buffer,
+    buffer_memory: B::Memory,
+}
+
+/// Chain of color images used in place of a swapchain when rendering without a window.
+///
+/// Each image has an associated readback buffer in host visible memory.
+#[derive(Debug)]
+pub struct OffscreenSwapchain<B: Backend> {
+    frames: Vec<OffscreenFrame<B>>,
+    extent: Extent2D,
+    format: format::Format,
+    row_pitch: buffer::Offset,
+    next_frame: usize,
+}
+
+impl<B: Backend> OffscreenSwapchain<B> {
+    /// Create the images and readback buffers.
+    ///
+    /// The images are created with the color format, image count and usage of `config`,
+    /// with `COLOR_ATTACHMENT` and `TRANSFER_SRC` usage added. Depth-stencil formats are ignored.
+    ///
+    /// The rows of the readback buffers are aligned to the `min_buffer_copy_pitch_alignment`
+    /// of `limits`.
+    pub fn new(
+        device: &B::Device,
+        memory_types: &[MemoryType],
+        limits: &Limits,
+        config: &SwapchainConfig,
+        extent: Extent2D,
+    ) -> Result<Self, CreationError> {
+        let format = config.color_format;
+        let kind = image::Kind::D2(extent.width, extent.height, 1, 1);
+        let usage = config.image_usage | image::Usage::COLOR_ATTACHMENT | image::Usage::TRANSFER_SRC;
+        let row_alignment_mask = limits.min_buffer_copy_pitch_alignment - 1;
+        let row_pitch = (extent.width as buffer::Offset * Self::texel_size(format) + row_alignment_mask) & !row_alignment_mask;
+        debug_assert_eq!(row_pitch % Self::texel_size(format), 0);
+        let readback_size = row_pitch * extent.height as buffer::Offset;
+
+        let frames = (0 .. config.image_count.max(1))
+            .map(|_| {
+                let unbound = device.create_image(
+                    kind, 1, format, image::Tiling::Optimal, usage, image::StorageFlags::empty(),
+                )?;
+                let requirements = device.get_image_requirements(&unbound);
+                let memory_type = find_memory_type(memory_types, requirements.type_mask, memory::Properties::DEVICE_LOCAL)
+                    .ok_or(CreationError::NoMemoryType)?;
+                let image_memory = device.allocate_memory(memory_type, requirements.size)?;
+                let image = device.bind_image_memory(&image_memory, 0, unbound)?;
+
+                let unbound = device.create_buffer(readback_size, buffer::Usage::TRANSFER_DST)?;
+                let requirements = device.get_buffer_requirements(&unbound);
+                // Prefer cached memory, as the contents are only read by the host.
+                let memory_type = find_memory_type(
+                        memory_types,
+                        requirements.type_mask,
+                        memory::Properties::CPU_VISIBLE | memory::Properties::CPU_CACHED,
+                    )
+                    .or_else(|| find_memory_type(memory_types, requirements.type_mask, memory::Properties::CPU_VISIBLE))
+                    .ok_or(CreationError::NoMemoryType)?;
+                let buffer_memory = device.allocate_memory(memory_type, requirements.size)?;
+                let buffer = device.bind_buffer_memory(&buffer_memory, 0, unbound)?;
+
+                Ok(OffscreenFrame {
+                    image,
+                    image_memory,
+                    buffer,
+                    buffer_memory,
+                })
+            })
+            .collect::<Result<Vec<_>, CreationError>>()?;
+
+        Ok(OffscreenSwapchain {
+            frames,
+            extent,
+            format,
+            row_pitch,
+            next_frame: 0,
+        })
+    }
+
+    fn texel_size(format: format::Format) -> buffer::Offset {
+        format.base_format().0.desc().bits as buffer::Offset / 8
+    }
+
+    /// Acquire the next image for rendering.
+    ///
+    /// Images are handed out in order, the image is immediately available.
+    pub fn acquire_frame(&mut self) -> Frame {
+        let frame = Frame(self.next_frame);
+        self.next_frame = (self.next_frame + 1) % self.frames.len();
+        frame
+    }
+
+    /// Get the image of a frame.
+    pub fn image(&self, frame: &Frame) -> &B::Image {
+        &self.frames[frame.id()].image
+    }
+
+    /// Get the number of images in the chain.
+    pub fn image_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Get the extent of the images.
+    pub fn extent(&self) -> Extent2D {
+        self.extent
+    }
+
+    /// Get the color format of the images.
+    pub fn format(&self) -> format::Format {
+        self.format
+    }
+
+    /// Record the copy of a frame into its readback buffer.
+    ///
+    /// `layout` is the layout of the image after rendering, usually the final layout
+    /// of the render pass. The image is transitioned to `TransferSrcOptimal`.
+    pub fn copy_to_readback<C, S, L>(
+        &self,
+        cmd_buffer: &mut CommandBuffer<B, C, S, L>,
+        frame: &Frame,
+        layout: image::Layout,
+    ) where
+        C: Supports<Transfer>,
+        S: Shot,
+        L: Level,
+    {
+        let frame = &self.frames[frame.id()];
+
+        let image_barrier = memory::Barrier::Image {
+            states: (image::Access::COLOR_ATTACHMENT_WRITE, layout) ..
+                (image::Access::TRANSFER_READ, image::Layout::TransferSrcOptimal),
+            target: &frame.image,
+            range: image::SubresourceRange {
+                aspects: format::Aspects::COLOR,
+                levels: 0 .. 1,
+                layers: 0 .. 1,
+            },
+            families: None,
+        };
+        cmd_buffer.pipeline_barrier(
+            PipelineStage::COLOR_ATTACHMENT_OUTPUT .. PipelineStage::TRANSFER,
+            memory::Dependencies::empty(),
+            &[image_barrier],
+        );
+
+        cmd_buffer.copy_image_to_buffer(
+            &frame.image,
+            image::Layout::TransferSrcOptimal,
+            &frame.buffer,
+            &[BufferImageCopy {
+                buffer_offset: 0,
+                buffer_width: (self.row_pitch / Self::texel_size(self.format)) as u32,
+                buffer_height: self.extent.height,
+                image_layers: image::SubresourceLayers {
+                    aspects: format::Aspects::COLOR,
+                    level: 0,
+                    layers: 0 .. 1,
+                },
+                image_offset: image::Offset { x: 0, y: 0, z: 0 },
+                image_extent: image::Extent {
+                    width: self.extent.width,
+                    height: self.extent.height,
+                    depth: 1,
+                },
+            }],
+        );
+
+        let buffer_barrier = memory::Barrier::Buffer {
+            states: buffer::Access::TRANSFER_WRITE .. buffer::Access::HOST_READ,
+            target: &frame.buffer,
+            families: None,
+        };
+        cmd_buffer.pipeline_barrier(
+            PipelineStage::TRANSFER .. PipelineStage::HOST,
+            memory::Dependencies::empty(),
+            &[buffer_barrier],
+        );
+    }
+
+    /// Read back the contents of a frame.
+    ///
+    /// The command buffer containing the `copy_to_readback` commands of the frame
+    /// must have finished execution. Rows of texels are tightly packed,
+    /// the padding of the aligned rows in the readback buffer is removed.
+    pub fn read_pixels(&self, device: &B::Device, frame: &Frame) -> Result<Vec<u8>, mapping::Error> {
+        let frame = &self.frames[frame.id()];
+        let row_pitch = self.row_pitch as usize;
+        let row_size = self.extent.width as usize * Self::texel_size(self.format) as usize;
+        let size = self.row_pitch * self.extent.height as buffer::Offset;
+        let reader = device.acquire_mapping_reader::<u8>(&frame.buffer_memory, 0 .. size)?;
+        let mut pixels = Vec::with_capacity(row_size * self.extent.height as usize);
+        for row in reader.chunks(row_pitch) {
+            pixels.extend_from_slice(&row[.. row_size]);
+        }
+        device.release_mapping_reader(reader);
+        Ok(pixels)
+    }
+
+    /// Destroy the images and readback buffers.
+    ///
+    /// Submitted command buffers referencing the images must have finished execution.
+    pub fn destroy(self, device: &B::Device) {
+        for frame in self.frames {
+            device.destroy_image(frame.image);
+            device.free_memory(frame.image_memory);
+            device.destroy_buffer(frame.buffer);
+            device.free_memory(frame.buffer_memory);
+        }
+    }
+}
@@ -1,5 +1,6 @@
 //! RenderPass handling.
 
+use command::ClearValue;
 use format::Format;
 use image;
 use pso::PipelineStage;
@@ -130,11 +131,123 @@ pub struct SubpassDesc<'a> {
     /// Attachments that are not used by the subpass but must be preserved to be
     /// passed on to subsequent passes.
     pub preserves: &'a [AttachmentId],
+    /// Views rendered by this subpass, one bit per view.
+    ///
+    /// Each view renders into the array layer of the attachments with the
+    /// same index. Zero disables multiview. Requires `Features::MULTIVIEW`
+    /// otherwise; all subpasses must either use multiview or not.
+    pub view_mask: u32,
+    /// Views which are spatially correlated and may be rendered more
+    /// efficiently together. This is only a hint, backends without an
+    /// equivalent (e.g. DX12) ignore it.
+    pub correlation_mask: u32,
 }
 
 /// Index of a subpass.
 pub type SubpassId = usize;
 
+/// Attachment formats of a subpass, which decide whether graphics pipelines
+/// can be used with a render pass.
+///
+/// Like in Vulkan, a pipeline created for a subpass can be used in any subpass
+/// or dynamic rendering with compatible formats, not only in the subpass it
+/// was created for. Color attachments are matched by position. Unknown formats,
+/// which are used by dummy render passes, are compatible with any format.
+///
+/// ```rust
+/// # extern crate gfx_hal as hal;
+/// # fn main() {
+/// use hal::format::Format;
+/// use hal::pass::AttachmentFormats;
+///
+/// let pipeline = AttachmentFormats {
+///     colors: vec![Some(Format::Rgba8Srgb)],
+///     depth_stencil: Some(Format::D32Float),
+/// };
+/// let target = AttachmentFormats {
+///     colors: vec![Some(Format::Rgba8Srgb)],
+///     depth_stencil: None,
+/// };
+/// assert!(pipeline.is_compatible(&pipeline.clone()));
+/// assert!(!pipeline.is_compatible(&target));
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AttachmentFormats {
+    /// Formats of the color attachments.
+    pub colors: Vec<Option<Format>>,
+    /// Format of the depth-stencil attachment, if any.
+    pub depth_stencil: Option<Format>,
+}
+
+impl AttachmentFormats {
+    /// Collect the formats of the `colors` and `depth_stencil` attachments
+    /// of a subpass, referencing the render pass `attachments`.
+    pub fn new(
+        attachments: &[Attachment],
+        colors: &[AttachmentRef],
+        depth_stencil: Option<&AttachmentRef>,
+    ) -> Self {
+        AttachmentFormats {
+            colors: colors
+                .iter()
+                .map(|&(id, _)| attachments[id].format)
+                .collect(),
+            depth_stencil: depth_stencil.and_then(|&(id, _)| attachments[id].format),
+        }
+    }
+
+    /// Check if pipelines created for one set of formats can be used with the other.
+    pub fn is_compatible(&self, other: &Self) -> bool {
+        fn matches(a: Option<Format>, b: Option<Format>) -> bool {
+            match (a, b) {
+                (Some(a), Some(b)) => a == b,
+                _ => true,
+            }
+        }
+
+        self.colors.len() == other.colors.len() &&
+        self.colors
+            .iter()
+            .zip(&other.colors)
+            .all(|(&a, &b)| matches(a, b)) &&
+        self.depth_stencil.is_some() == other.depth_stencil.is_some() &&
+        matches(self.depth_stencil, other.depth_stencil)
+    }
+}
+
+/// Attachment used by dynamic rendering, see `RawCommandBuffer::begin_rendering`.
+#[derive(Debug)]
+pub struct RenderingAttachment<'a, B: Backend> {
+    /// View of the attachment image.
+    pub view: &'a B::ImageView,
+    /// Format of the view.
+    pub format: Format,
+    /// Layout of the image during rendering. The image is expected to be in
+    /// this layout when rendering begins, and remains in it afterwards.
+    pub layout: AttachmentLayout,
+    /// Load and store operations of the attachment.
+    pub ops: AttachmentOps,
+    /// Load and store operations of the stencil aspect, if any.
+    pub stencil_ops: AttachmentOps,
+    /// Value the attachment is cleared to, if one of the load operations is `Clear`.
+    pub clear_value: ClearValue,
+}
+
+impl<'a, B: Backend> Clone for RenderingAttachment<'a, B> {
+    fn clone(&self) -> Self {
+        RenderingAttachment {
+            view: self.view,
+            format: self.format,
+            layout: self.layout,
+            ops: self.ops,
+            stencil_ops: self.stencil_ops,
+            clear_value: self.clear_value,
+        }
+    }
+}
+
 /// A sub-pass borrow of a pass.
 #[derive(Debug)]
 pub struct Subpass<'a, B: Backend> {
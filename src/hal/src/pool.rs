@@ -21,6 +21,15 @@ bitflags!(
         const TRANSIENT = 0x1;
         /// Allow command buffers to be reset individually.
         const RESET_INDIVIDUAL = 0x2;
+        /// Allow command buffers of the pool to be recorded concurrently on multiple threads.
+        ///
+        /// Raw command buffers allocated from the pool can be handed to worker threads
+        /// and recorded at the same time, each thread recording at most one of them at once.
+        /// Allocating, freeing and resetting still require external synchronization of the pool.
+        /// Without this flag, only a single command buffer of a pool may be recording at a time.
+        ///
+        /// Requires the `CONCURRENT_COMMAND_RECORDING` feature.
+        const CONCURRENT_RECORDING = 0x4;
     }
 );
 
@@ -31,6 +40,12 @@ pub trait RawCommandPool<B: Backend>: Any + Send + Sync {
     /// # Synchronization: You may _not_ free the pool if a command buffer is still in use (pool memory still in use)
     fn reset(&mut self);
 
+    /// Return unused memory of the command pool to the system.
+    ///
+    /// Command buffers allocated from the pool are not affected. Backends
+    /// without control over the pool memory treat this as a no-op.
+    fn trim(&mut self) {}
+
     /// Allocate new command buffers from the pool.
     fn allocate(&mut self, num: usize, level: RawLevel) -> Vec<B::CommandBuffer>;
 
@@ -76,6 +91,19 @@ impl<B: Backend, C> CommandPool<B, C> {
         self.next_secondary_buffer = 0;
     }
 
+    /// Trim the command pool.
+    ///
+    /// Frees the command buffers which haven't been acquired since the last reset
+    /// and returns unused memory of the pool to the system.
+    /// Useful for long-running applications after a spike in command buffer usage.
+    pub fn trim(&mut self) {
+        unsafe {
+            self.pool.free(self.buffers.drain(self.next_buffer ..).collect::<Vec<_>>());
+            self.pool.free(self.secondary_buffers.drain(self.next_secondary_buffer ..).collect::<Vec<_>>());
+        }
+        self.pool.trim();
+    }
+
     /// Reserve an additional amount of primary command buffers.
     pub fn reserve(&mut self, additional: usize) {
         let available = self.buffers.len() - self.next_buffer;
@@ -99,6 +127,9 @@ impl<B: Backend, C> CommandPool<B, C> {
     /// You can only record to one command buffer per pool at the same time.
     /// If more command buffers are requested than allocated, new buffers will be reserved.
     /// The command buffer will be returned in 'recording' state.
+    ///
+    /// `allow_pending_resubmit` allows submitting a `MultiShot` command buffer
+    /// again while a previous submission is still executing.
     pub fn acquire_command_buffer<S: Shot>(&mut self, allow_pending_resubmit: bool) -> CommandBuffer<B, C, S> {
         self.reserve(1);
 
@@ -0,0 +1,599 @@
+//! GPU profiling with timestamp queries.
+//!
+//! A [`Profiler`] measures the GPU execution time of nested scopes of commands.
+//! Timestamps are written into a ring of query ranges, one per frame in flight,
+//! and resolved once the frame has finished execution, `frames_in_flight` frames later.
+//!
+//! [`Profiler`]: struct.Profiler.html
+//!
+//! ```no_run
+//! # extern crate gfx_backend_empty as empty;
+//! # extern crate gfx_hal;
+//! # fn main() {
+//! use gfx_hal::profiler::Profiler;
+//! # use gfx_hal::{command, Graphics, MemoryType};
+//!
+//! # let device: empty::Device = return;
+//! # let memory_types: Vec<MemoryType> = return;
+//! # let mut cmd_buffer: command::CommandBuffer<empty::Backend, Graphics> = return;
+//! let mut profiler = Profiler::<empty::Backend>::new(&device, &memory_types, 2, 64, 1.0)
+//!     .unwrap();
+//!
+//! // wait for the frame which used the profiler 2 frames ago..
+//! profiler.begin_frame(&device, &mut cmd_buffer).unwrap();
+//! {
+//!     let mut frame = profiler.scope(&mut cmd_buffer, "frame");
+//!     {
+//!         let _shadows = frame.scope("shadow pass");
+//!         // record the shadow pass..
+//!     }
+//!     let _lighting = frame.scope("lighting pass");
+//!     // record the lighting pass..
+//! }
+//! profiler.end_frame(&mut cmd_buffer);
+//!
+//! for timing in profiler.results() {
+//!     println!("{}: {} ms", timing.name, timing.duration());
+//! }
+//! profiler.destroy(&device);
+//! # }
+//! ```
+
+use std::error::Error;
+use std::{fmt, mem};
+use std::ops::{Deref, DerefMut, Range};
+
+use {buffer, mapping, memory, query};
+use query::QueryPool;
+use {Backend, MemoryType};
+use command::{CommandBuffer, Level, Shot};
+use device::{BindError, Device, OutOfMemory};
+use offscreen::find_memory_type;
+use pso::PipelineStage;
+use queue::capability::{GraphicsOrCompute, Supports, Transfer};
+
+
+/// Error creating a `Profiler`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CreationError {
+    /// Readback buffer creation failed.
+    Buffer(buffer::CreationError),
+    /// No host visible memory type satisfies the requirements of the readback buffer.
+    NoMemoryType,
+    /// Out of memory.
+    OutOfMemory,
+    /// Binding of the readback buffer to memory failed.
+    Bind(BindError),
+}
+
+impl fmt::Display for CreationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = self.description();
+        match *self {
+            CreationError::Buffer(ref err) => write!(f, "{}: {}", description, err),
+            CreationError::Bind(ref err) => write!(f, "{}: {}", description, err),
+            _ => write!(f, "{}", description),
+        }
+    }
+}
+
+impl Error for CreationError {
+    fn description(&self) -> &str {
+        match *self {
+            CreationError::Buffer(_) => "Failed to create a readback buffer",
+            CreationError::NoMemoryType => "No memory type satisfies the readback buffer requirements",
+            CreationError::OutOfMemory => "Out of memory",
+            CreationError::Bind(_) => "Failed to bind the readback buffer to memory",
+        }
+    }
+}
+
+impl From<buffer::CreationError> for CreationError {
+    fn from(err: buffer::CreationError) -> Self {
+        CreationError::Buffer(err)
+    }
+}
+
+impl From<OutOfMemory> for CreationError {
+    fn from(_: OutOfMemory) -> Self {
+        CreationError::OutOfMemory
+    }
+}
+
+impl From<BindError> for CreationError {
+    fn from(err: BindError) -> Self {
+        CreationError::Bind(err)
+    }
+}
+
+/// Measured GPU time of a scope, including its nested scopes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScopeTiming {
+    /// Name of the scope.
+    pub name: String,
+    /// Start and end of the scope in milliseconds,
+    /// relative to the start of the first scope of the frame.
+    pub time: Range<f64>,
+    /// Scopes recorded while this scope was active.
+    pub children: Vec<ScopeTiming>,
+}
+
+impl ScopeTiming {
+    /// Duration of the scope in milliseconds.
+    pub fn duration(&self) -> f64 {
+        self.time.end - self.time.start
+    }
+}
+
+#[derive(Debug)]
+struct ScopeRecord {
+    name: String,
+    depth: usize,
+    // Queries storing the begin and end timestamps.
+    begin: query::QueryId,
+    end: query::QueryId,
+}
+
+#[derive(Debug, Default)]
+struct ProfilerFrame {
+    scopes: Vec<ScopeRecord>,
+    // Number of queries used by the frame.
+    num_queries: query::QueryId,
+}
+
+/// Scopes of the frames in flight, each frame owning a range of `queries_per_frame` queries.
+#[derive(Debug)]
+struct FrameRing {
+    frames: Vec<ProfilerFrame>,
+    current: usize,
+    depth: usize,
+    queries_per_frame: query::QueryId,
+}
+
+impl FrameRing {
+    fn new(frames_in_flight: usize, queries_per_frame: query::QueryId) -> Self {
+        FrameRing {
+            frames: (0 .. frames_in_flight).map(|_| ProfilerFrame::default()).collect(),
+            current: frames_in_flight - 1,
+            depth: 0,
+            queries_per_frame,
+        }
+    }
+
+    /// First query of the current frame.
+    fn base(&self) -> query::QueryId {
+        self.current as query::QueryId * self.queries_per_frame
+    }
+
+    /// Move on to the next frame, returning the frame previously recorded into its
+    /// query range, `frames_in_flight` frames ago, if it recorded any scopes.
+    fn advance(&mut self) -> Option<ProfilerFrame> {
+        self.current = (self.current + 1) % self.frames.len();
+        self.depth = 0;
+        let finished = mem::replace(&mut self.frames[self.current], ProfilerFrame::default());
+        if finished.scopes.is_empty() {
+            None
+        } else {
+            Some(finished)
+        }
+    }
+
+    /// Open a scope, returns the query of its begin timestamp if the frame has queries left.
+    fn begin_scope(&mut self, name: &str) -> Option<query::QueryId> {
+        let base = self.base();
+        let frame = &mut self.frames[self.current];
+        self.depth += 1;
+        if frame.num_queries + 2 > self.queries_per_frame {
+            // Out of queries, the scope isn't measured.
+            return None;
+        }
+
+        let begin = base + frame.num_queries;
+        frame.num_queries += 2;
+        frame.scopes.push(ScopeRecord {
+            name: name.to_owned(),
+            depth: self.depth - 1,
+            begin,
+            end: begin + 1,
+        });
+        Some(begin)
+    }
+
+    fn end_scope(&mut self) {
+        self.depth -= 1;
+    }
+}
+
+/// Build the timing tree of a frame.
+///
+/// `timestamps` holds the resolved queries of the frame, starting at query `base`.
+fn build_timings(
+    frame: &ProfilerFrame,
+    base: query::QueryId,
+    timestamps: &[u64],
+    timestamp_period: f32,
+) -> Vec<ScopeTiming> {
+    let timestamp = |id: query::QueryId| timestamps[(id - base) as usize];
+    let origin = frame.scopes
+        .iter()
+        .map(|scope| timestamp(scope.begin))
+        .min()
+        .unwrap_or(0);
+    let to_ms = |ticks: u64| {
+        ticks.saturating_sub(origin) as f64 * timestamp_period as f64 / 1_000_000.0
+    };
+
+    // Scopes are recorded in pre-order, build the tree with a stack of open parents.
+    let mut roots = Vec::new();
+    let mut stack: Vec<(usize, ScopeTiming)> = Vec::new();
+    for scope in &frame.scopes {
+        while stack.last().map_or(false, |&(depth, _)| depth >= scope.depth) {
+            let (_, timing) = stack.pop().unwrap();
+            match stack.last_mut() {
+                Some(&mut (_, ref mut parent)) => parent.children.push(timing),
+                None => roots.push(timing),
+            }
+        }
+        stack.push((scope.depth, ScopeTiming {
+            name: scope.name.clone(),
+            time: to_ms(timestamp(scope.begin)) .. to_ms(timestamp(scope.end)),
+            children: Vec::new(),
+        }));
+    }
+    while let Some((_, timing)) = stack.pop() {
+        match stack.last_mut() {
+            Some(&mut (_, ref mut parent)) => parent.children.push(timing),
+            None => roots.push(timing),
+        }
+    }
+
+    roots
+}
+
+/// Profiler measuring the GPU time of nested scopes with timestamp queries.
+///
+/// Each frame in flight owns a range of the timestamp query pool and of the
+/// readback buffer, so results can be resolved without stalling the GPU.
+#[derive(Debug)]
+pub struct Profiler<B: Backend> {
+    query_pool: B::QueryPool,
+    buffer: B::Buffer,
+    memory: B::Memory,
+    ring: FrameRing,
+    timestamp_period: f32,
+    results: Vec<ScopeTiming>,
+}
+
+impl<B: Backend> Profiler<B> {
+    /// Create the timestamp query pool and readback buffer.
+    ///
+    /// * `frames_in_flight` is the number of frames which may be executing on the GPU
+    ///   while a new frame is recorded.
+    /// * `max_scopes` is the maximum number of scopes per frame, additional scopes are not measured.
+    /// * `timestamp_period` is the number of nanoseconds per timestamp tick of the queue
+    ///   executing the command buffers.
+    pub fn new(
+        device: &B::Device,
+        memory_types: &[MemoryType],
+        frames_in_flight: usize,
+        max_scopes: u32,
+        timestamp_period: f32,
+    ) -> Result<Self, CreationError> {
+        let frames_in_flight = frames_in_flight.max(1);
+        let queries_per_frame = max_scopes * 2;
+        let num_queries = queries_per_frame * frames_in_flight as query::QueryId;
+
+        let query_pool = device.create_query_pool(
+            query::QueryType::Timestamp,
+            num_queries,
+            query::QueryPoolCreateFlags::empty(),
+        );
+        let size = num_queries as u64 * query_pool.result_stride();
+
+        let unbound = device.create_buffer(size, buffer::Usage::TRANSFER_DST)?;
+        let requirements = device.get_buffer_requirements(&unbound);
+        // Prefer cached memory, as the contents are only read by the host.
+        let memory_type = find_memory_type(
+                memory_types,
+                requirements.type_mask,
+                memory::Properties::CPU_VISIBLE | memory::Properties::CPU_CACHED,
+            )
+            .or_else(|| find_memory_type(memory_types, requirements.type_mask, memory::Properties::CPU_VISIBLE))
+            .ok_or(CreationError::NoMemoryType)?;
+        let memory = device.allocate_memory(memory_type, requirements.size)?;
+        let buffer = device.bind_buffer_memory(&memory, 0, unbound)?;
+
+        Ok(Profiler {
+            query_pool,
+            buffer,
+            memory,
+            ring: FrameRing::new(frames_in_flight, queries_per_frame),
+            timestamp_period,
+            results: Vec::new(),
+        })
+    }
+
+    /// Start profiling a new frame.
+    ///
+    /// Resolves the results of the frame recorded `frames_in_flight` frames ago,
+    /// which must have finished execution, and resets its queries.
+    pub fn begin_frame<C, S, L>(
+        &mut self,
+        device: &B::Device,
+        cmd_buffer: &mut CommandBuffer<B, C, S, L>,
+    ) -> Result<(), mapping::Error>
+    where
+        C: Supports<GraphicsOrCompute>,
+        S: Shot,
+        L: Level,
+    {
+        if let Some(finished) = self.ring.advance() {
+            self.results = self.resolve(device, &finished)?;
+        }
+
+        let base = self.ring.base();
+        cmd_buffer.reset_query_pool(&self.query_pool, base .. base + self.ring.queries_per_frame);
+        Ok(())
+    }
+
+    /// Finish profiling the current frame.
+    ///
+    /// Records the copy of the timestamps into the readback buffer.
+    /// All scopes of the frame must have ended.
+    pub fn end_frame<C, S, L>(&mut self, cmd_buffer: &mut CommandBuffer<B, C, S, L>)
+    where
+        C: Supports<GraphicsOrCompute> + Supports<Transfer>,
+        S: Shot,
+        L: Level,
+    {
+        assert_eq!(self.ring.depth, 0, "Profiler scopes must end before the frame");
+        let num_queries = self.ring.frames[self.ring.current].num_queries;
+        if num_queries == 0 {
+            return;
+        }
+
+        let stride = self.query_pool.result_stride();
+        let base = self.ring.base();
+        cmd_buffer.copy_query_pool_results(
+            &self.query_pool,
+            base .. base + num_queries,
+            &self.buffer,
+            base as buffer::Offset * stride,
+            stride,
+            query::ResultFlags::BITS_64 | query::ResultFlags::WAIT,
+        );
+        cmd_buffer.pipeline_barrier(
+            PipelineStage::TRANSFER .. PipelineStage::HOST,
+            memory::Dependencies::empty(),
+            &[memory::Barrier::Buffer {
+                states: buffer::Access::TRANSFER_WRITE .. buffer::Access::HOST_READ,
+                target: &self.buffer,
+                families: None,
+            }],
+        );
+    }
+
+    /// Begin a new top-level scope.
+    ///
+    /// The end timestamp is written when the returned scope is dropped.
+    /// Commands of the scope are recorded through the scope, which dereferences to the command buffer.
+    pub fn scope<'a, 'b, C, S, L>(
+        &'a mut self,
+        cmd_buffer: &'a mut CommandBuffer<'b, B, C, S, L>,
+        name: &str,
+    ) -> Scope<'a, 'b, B, C, S, L>
+    where
+        C: Supports<GraphicsOrCompute>,
+        S: Shot,
+        L: Level,
+    {
+        let end = self.begin_scope(cmd_buffer, name);
+        Scope {
+            profiler: self,
+            cmd_buffer,
+            end,
+        }
+    }
+
+    fn begin_scope<C, S, L>(&mut self, cmd_buffer: &mut CommandBuffer<B, C, S, L>, name: &str) -> Option<query::QueryId>
+    where
+        C: Supports<GraphicsOrCompute>,
+        S: Shot,
+        L: Level,
+    {
+        let begin = self.ring.begin_scope(name)?;
+        cmd_buffer.write_timestamp(
+            PipelineStage::TOP_OF_PIPE,
+            query::Query { pool: &self.query_pool, id: begin },
+        );
+        Some(begin + 1)
+    }
+
+    fn end_scope<C, S, L>(&mut self, cmd_buffer: &mut CommandBuffer<B, C, S, L>, end: Option<query::QueryId>)
+    where
+        C: Supports<GraphicsOrCompute>,
+        S: Shot,
+        L: Level,
+    {
+        self.ring.end_scope();
+        if let Some(id) = end {
+            cmd_buffer.write_timestamp(
+                PipelineStage::BOTTOM_OF_PIPE,
+                query::Query { pool: &self.query_pool, id },
+            );
+        }
+    }
+
+    fn resolve(&self, device: &B::Device, frame: &ProfilerFrame) -> Result<Vec<ScopeTiming>, mapping::Error> {
+        let stride = self.query_pool.result_stride();
+        let base = self.ring.base();
+        let range = base as u64 * stride .. (base + frame.num_queries) as u64 * stride;
+
+        let reader = device.acquire_mapping_reader::<u64>(&self.memory, range)?;
+        // Timestamps are the first value of each query result.
+        let timestamps = reader
+            .chunks((stride / 8) as usize)
+            .map(|result| result[0])
+            .collect::<Vec<_>>();
+        device.release_mapping_reader(reader);
+
+        Ok(build_timings(frame, base, &timestamps, self.timestamp_period))
+    }
+
+    /// Get the timing tree of the most recently resolved frame.
+    pub fn results(&self) -> &[ScopeTiming] {
+        &self.results
+    }
+
+    /// Destroy the query pool and readback buffer.
+    ///
+    /// Submitted command buffers referencing the profiler must have finished execution.
+    pub fn destroy(self, device: &B::Device) {
+        device.destroy_query_pool(self.query_pool);
+        device.destroy_buffer(self.buffer);
+        device.free_memory(self.memory);
+    }
+}
+
+/// Active profiler scope, writing the end timestamp when dropped.
+///
+/// Dereferences to the command buffer to record the commands of the scope.
+pub struct Scope<'a, 'b: 'a, B: Backend + 'a, C: 'a, S: Shot + 'a, L: Level + 'a>
+where
+    C: Supports<GraphicsOrCompute>,
+    B::CommandBuffer: 'b,
+{
+    profiler: &'a mut Profiler<B>,
+    cmd_buffer: &'a mut CommandBuffer<'b, B, C, S, L>,
+    end: Option<query::QueryId>,
+}
+
+impl<'a, 'b, B: Backend, C, S: Shot, L: Level> Scope<'a, 'b, B, C, S, L>
+where
+    C: Supports<GraphicsOrCompute>,
+{
+    /// Begin a nested scope.
+    pub fn scope<'c>(&'c mut self, name: &str) -> Scope<'c, 'b, B, C, S, L> {
+        self.profiler.scope(self.cmd_buffer, name)
+    }
+}
+
+impl<'a, 'b, B: Backend, C, S: Shot, L: Level> Deref for Scope<'a, 'b, B, C, S, L>
+where
+    C: Supports<GraphicsOrCompute>,
+{
+    type Target = CommandBuffer<'b, B, C, S, L>;
+    fn deref(&self) -> &CommandBuffer<'b, B, C, S, L> {
+        self.cmd_buffer
+    }
+}
+
+impl<'a, 'b, B: Backend, C, S: Shot, L: Level> DerefMut for Scope<'a, 'b, B, C, S, L>
+where
+    C: Supports<GraphicsOrCompute>,
+{
+    fn deref_mut(&mut self) -> &mut CommandBuffer<'b, B, C, S, L> {
+        self.cmd_buffer
+    }
+}
+
+impl<'a, 'b, B: Backend, C, S: Shot, L: Level> Drop for Scope<'a, 'b, B, C, S, L>
+where
+    C: Supports<GraphicsOrCompute>,
+{
+    fn drop(&mut self) {
+        let end = self.end;
+        self.profiler.end_scope(self.cmd_buffer, end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timing(name: &str, time: Range<f64>, children: Vec<ScopeTiming>) -> ScopeTiming {
+        ScopeTiming { name: name.to_owned(), time, children }
+    }
+
+    #[test]
+    fn frame_latency() {
+        let mut ring = FrameRing::new(2, 8);
+        assert!(ring.advance().is_none());
+        assert_eq!(ring.base(), 0);
+        assert_eq!(ring.begin_scope("first"), Some(0));
+        ring.end_scope();
+
+        // The next frame records into its own range while the first one executes.
+        assert!(ring.advance().is_none());
+        assert_eq!(ring.base(), 8);
+        assert_eq!(ring.begin_scope("second"), Some(8));
+        ring.end_scope();
+
+        // Frames are resolved once their range is reused, `frames_in_flight` frames later.
+        let first = ring.advance().unwrap();
+        assert_eq!(ring.base(), 0);
+        assert_eq!(first.scopes[0].name, "first");
+        assert_eq!(first.num_queries, 2);
+        assert_eq!(ring.frames[0].num_queries, 0);
+
+        let second = ring.advance().unwrap();
+        assert_eq!(ring.base(), 8);
+        assert_eq!(second.scopes[0].name, "second");
+        assert_eq!((second.scopes[0].begin, second.scopes[0].end), (8, 9));
+
+        // Nothing was recorded into the reused range.
+        assert!(ring.advance().is_none());
+    }
+
+    #[test]
+    fn out_of_queries() {
+        let mut ring = FrameRing::new(1, 4);
+        ring.advance();
+        assert_eq!(ring.begin_scope("a"), Some(0));
+        assert_eq!(ring.begin_scope("b"), Some(2));
+        assert_eq!(ring.begin_scope("c"), None);
+        ring.end_scope();
+        ring.end_scope();
+        ring.end_scope();
+        assert_eq!(ring.depth, 0);
+
+        let frame = ring.advance().unwrap();
+        assert_eq!(frame.scopes.len(), 2);
+        assert_eq!(frame.num_queries, 4);
+    }
+
+    #[test]
+    fn timing_tree() {
+        let mut ring = FrameRing::new(2, 16);
+        ring.advance();
+        ring.advance();
+        assert_eq!(ring.base(), 16);
+        // frame { shadows, lighting { sky } }, post
+        ring.begin_scope("frame");
+        ring.begin_scope("shadows");
+        ring.end_scope();
+        ring.begin_scope("lighting");
+        ring.begin_scope("sky");
+        ring.end_scope();
+        ring.end_scope();
+        ring.end_scope();
+        ring.begin_scope("post");
+        ring.end_scope();
+        ring.advance();
+        let frame = ring.advance().unwrap();
+
+        let timestamps = [100, 900, 100, 300, 300, 800, 400, 500, 900, 1000];
+        // One tick per millisecond.
+        let roots = build_timings(&frame, 16, &timestamps, 1_000_000.0);
+        assert_eq!(roots, vec![
+            timing("frame", 0.0 .. 800.0, vec![
+                timing("shadows", 0.0 .. 200.0, vec![]),
+                timing("lighting", 200.0 .. 700.0, vec![
+                    timing("sky", 300.0 .. 400.0, vec![]),
+                ]),
+            ]),
+            timing("post", 800.0 .. 900.0, vec![]),
+        ]);
+        assert_eq!(roots[0].duration(), 800.0);
+    }
+}
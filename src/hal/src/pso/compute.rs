@@ -16,6 +16,27 @@ pub struct ComputePipelineDesc<'a, B: Backend> {
     pub parent: BasePipeline<'a, B::ComputePipeline>,
 }
 
+/// Resource usage of a compiled compute pipeline, for tuning workgroup sizes.
+///
+/// Obtained with `Device::get_compute_pipeline_statistics`. Values which the
+/// backend or driver doesn't report are `None`, the occupancy related ones
+/// usually require vendor extensions.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Statistics {
+    /// Workgroup size declared by the shader.
+    pub workgroup_size: [u32; 3],
+    /// Number of instructions of the compiled shader.
+    pub instructions: Option<u32>,
+    /// Number of registers used per invocation.
+    pub registers: Option<u32>,
+    /// Workgroup shared memory used by the shader, in bytes.
+    pub shared_memory: Option<u32>,
+    /// Maximum number of waves (warps) of the pipeline which can be resident
+    /// on a compute unit at the same time.
+    pub max_waves_per_cu: Option<u32>,
+}
+
 impl<'a, B: Backend> ComputePipelineDesc<'a, B> {
     /// Create a new empty PSO descriptor.
     pub fn new(
@@ -11,7 +11,7 @@ use std::ops::Range;
 
 use {Backend};
 use buffer::Offset;
-use image::Layout;
+use image::{Layout, SamplerInfo};
 use pso::ShaderStageFlags;
 
 ///
@@ -51,6 +51,8 @@ pub enum DescriptorType {
     UniformImageDynamic = 9,
     /// Allows unfiltered loads of pixel local data in the fragment shader.
     InputAttachment = 10,
+    /// Top level acceleration structure, traversed by ray tracing shaders.
+    AccelerationStructure = 1000165000,
 }
 
 /// Binding description of a descriptor set
@@ -74,7 +76,38 @@ pub struct DescriptorSetLayoutBinding {
     pub count: DescriptorArrayIndex,
     /// Valid shader stages.
     pub stage_flags: ShaderStageFlags,
-    // TODO: immutable samplers?
+    /// Expected update frequency of the descriptors, `None` if unknown.
+    ///
+    /// Backends may use this hint to optimize the pipeline layout, e.g. by binding
+    /// frequently updated buffers directly instead of through descriptor tables.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub update_frequency: Option<UpdateFrequency>,
+    /// Accesses of fragment shaders to the storage images and buffers of this binding
+    /// are ordered like the primitives covering the fragments, e.g. for order-independent
+    /// transparency. Requires `Features::RASTERIZER_ORDERED_VIEWS`.
+    ///
+    /// *Note*: Vulkan has no equivalent binding property, shaders enclose ordered accesses
+    /// in a critical section of `SPV_EXT_fragment_shader_interlock` instead.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rasterizer_ordered: bool,
+    /// Samplers baked into the layout for `Sampler` and `CombinedImageSampler` bindings,
+    /// one for each descriptor of the binding or none.
+    ///
+    /// The samplers of these bindings are initialized on descriptor set allocation and
+    /// sampler writes to them are ignored. Backends may place them directly into the
+    /// pipeline layouts, e.g. as static samplers of D3D12 root signatures.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub immutable_samplers: Vec<SamplerInfo>,
+}
+
+/// Hint about how often the descriptors of a binding are updated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum UpdateFrequency {
+    /// Descriptors are rarely updated, e.g. material textures.
+    Low,
+    /// Descriptors are updated frequently, e.g. per-draw uniform buffers.
+    High,
 }
 
 /// Set of descriptors of a specific type.
@@ -110,6 +143,15 @@ pub enum AllocationError {
     IncompatibleLayout,
 }
 
+bitflags!(
+    /// Descriptor pool creation flags.
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct DescriptorPoolCreateFlags: u32 {
+        /// Allow descriptor sets to be freed individually with `free_sets`.
+        const FREE_DESCRIPTOR_SET = 0x1;
+    }
+);
+
 /// A descriptor pool is a collection of memory from which descriptor sets are allocated.
 pub trait DescriptorPool<B: Backend>: Send + Sync + fmt::Debug {
     /// Allocate a descriptor set from the pool.
@@ -137,6 +179,17 @@ pub trait DescriptorPool<B: Backend>: Send + Sync + fmt::Debug {
             .collect()
     }
 
+    /// Free descriptor sets allocated from this pool, returning their memory to the pool.
+    ///
+    /// The pool must have been created with `DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET`.
+    /// The descriptor sets must not be in use by pending command buffers.
+    fn free_sets<I>(&mut self, descriptor_sets: I)
+    where
+        I: IntoIterator<Item = B::DescriptorSet>,
+    {
+        for _ in descriptor_sets {}
+    }
+
     /// Resets a descriptor pool, releasing all resources from all the descriptor sets
     /// allocated from it and freeing the descriptor sets. Invalidates all descriptor
     /// sets allocated from the pool; trying to use one after the pool has been reset
@@ -166,6 +219,7 @@ pub enum Descriptor<'a, B: Backend> {
     CombinedImageSampler(&'a B::ImageView, Layout, &'a B::Sampler),
     Buffer(&'a B::Buffer, Range<Option<Offset>>),
     TexelBuffer(&'a B::BufferView),
+    AccelerationStructure(&'a B::AccelerationStructure),
 }
 
 
@@ -1,6 +1,6 @@
 //! Graphics pipeline descriptor.
 
-use {pass, Backend, Primitive};
+use {image, pass, Backend, Primitive};
 use super::{BasePipeline, EntryPoint, PipelineCreationFlags};
 use super::input_assembler::{AttributeDesc, InputAssemblerDesc, VertexBufferDesc};
 use super::output_merger::{ColorBlendDesc, DepthStencilDesc};
@@ -22,6 +22,25 @@ pub struct Rect {
     pub h: u16,
 }
 
+/// A rect of the attachments cleared by `RawCommandBuffer::clear_attachments`.
+#[derive(Clone, Debug, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ClearRect {
+    /// Cleared area, needs to be inside of the render area.
+    pub rect: Rect,
+    /// Cleared layers, relative to the first layer of the attachments.
+    pub layers: Range<image::Layer>,
+}
+
+impl From<Rect> for ClearRect {
+    fn from(rect: Rect) -> Self {
+        ClearRect {
+            rect,
+            layers: 0 .. 1,
+        }
+    }
+}
+
 /// A viewport, generally equating to a window on a display.
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -81,6 +100,8 @@ pub struct BakedStates {
     pub scissor: Option<Rect>,
     /// Static blend constant color.
     pub blend_color: Option<ColorValue>,
+    /// Static depth bounds, used if depth bounds testing is enabled.
+    pub depth_bounds: Option<Range<f32>>,
     //pub stencil_read: Option<Stencil>,
     //pub stencil_write: Option<Stencil>,
     //pub stencil_ref: Option<Stencil>,
@@ -103,10 +124,17 @@ pub struct GraphicsPipelineDesc<'a, B: Backend> {
     pub input_assembler: InputAssemblerDesc,
     /// Description of how blend operations should be performed.
     pub blender: BlendDesc,
+    /// Multisampling state, `None` for single-sampled rendering.
+    pub multisampling: Option<Multisampling>,
     /// Depth stencil (DSV)
     pub depth_stencil: Option<DepthStencilDesc>,
     /// Static pipeline states.
     pub baked_states: BakedStates,
+    /// Number of viewports and scissors used by the pipeline.
+    ///
+    /// Must be at least 1, values larger than 1 require `Features::MULTI_VIEWPORTS`.
+    /// Static viewports and scissors in `baked_states` require a single viewport.
+    pub viewport_count: usize,
     /// Pipeline layout.
     pub layout: &'a B::PipelineLayout,
     /// Subpass in which the pipeline can be executed.
@@ -134,8 +162,10 @@ impl<'a, B: Backend> GraphicsPipelineDesc<'a, B> {
             attributes: Vec::new(),
             input_assembler: InputAssemblerDesc::new(primitive),
             blender: BlendDesc::default(),
+            multisampling: None,
             depth_stencil: None,
             baked_states: BakedStates::default(),
+            viewport_count: 1,
             layout,
             subpass,
             flags: PipelineCreationFlags::empty(),
@@ -144,6 +174,17 @@ impl<'a, B: Backend> GraphicsPipelineDesc<'a, B> {
     }
 }
 
+/// A pipeline state which is either baked into the pipeline
+/// or set dynamically on the command buffer.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum State<T> {
+    /// Static state, baked into the pipeline.
+    Static(T),
+    /// Dynamic state, set with the corresponding command buffer method.
+    Dynamic,
+}
+
 /// Methods for rasterizing polygons, ie, turning the mesh
 /// into a raster image.
 #[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
@@ -152,7 +193,10 @@ pub enum PolygonMode {
     /// Rasterize as a point.
     Point,
     /// Rasterize as a line with the given width.
-    Line(f32),
+    ///
+    /// Widths other than `1.0` require `Features::LINE_WIDTH`. A dynamic
+    /// width is set with `set_line_width`.
+    Line(State<f32>),
     /// Rasterize as a face.
     Fill,
 }
@@ -214,11 +258,17 @@ pub struct Rasterizer {
     /// fragments being omitted when they are outside the bounds of the z-plane,
     /// they will be clamped to the min or max z value.
     pub depth_clamping: bool,
+    /// Whether or not to clip primitives against the near and far planes.
+    /// Disabling clipping allows rendering geometry in front of the near plane,
+    /// e.g. for shadow map pancaking, combined with `depth_clamping`.
+    ///
+    /// Without `Features::DEPTH_CLIP_CONTROL`, clipping is only disabled
+    /// together with enabling `depth_clamping`.
+    pub depth_clipping: bool,
     /// What depth bias, if any, to use for the drawn primitives.
     pub depth_bias: Option<DepthBias>,
     /// Controls how triangles will be rasterized depending on their overlap with pixels.
     pub conservative: bool,
-    //TODO: multisampling
 }
 
 impl Rasterizer {
@@ -228,11 +278,41 @@ impl Rasterizer {
         cull_face: None,
         front_face: FrontFace::CounterClockwise,
         depth_clamping: false,
+        depth_clipping: true,
         depth_bias: None,
         conservative: false,
     };
 }
 
+/// Bitmask of the samples covered by rasterization, one bit per sample.
+pub type SampleMask = u64;
+
+/// Multisampling state of a graphics pipeline.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Multisampling {
+    /// Number of samples per pixel used in rasterization.
+    pub rasterization_samples: image::NumSamples,
+    /// Minimum fraction of samples with individually shaded fragments,
+    /// `None` shades a single fragment per pixel.
+    ///
+    /// Requires the `SAMPLE_RATE_SHADING` feature.
+    pub sample_shading: Option<f32>,
+    /// Static mask of the samples which can be covered by fragments.
+    pub sample_mask: SampleMask,
+}
+
+impl Multisampling {
+    /// Multisampling with `samples` samples per pixel, shading once per pixel.
+    pub fn new(samples: image::NumSamples) -> Self {
+        Multisampling {
+            rasterization_samples: samples,
+            sample_shading: None,
+            sample_mask: !0,
+        }
+    }
+}
+
 /// A description of an equation for how to blend transparent, overlapping fragments.
 #[derive(Clone, Debug, Default, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -242,6 +322,10 @@ pub struct BlendDesc {
     /// See [here]( https://msdn.microsoft.com/en-us/library/windows/desktop/bb205072(v=vs.85).aspx#Alpha_To_Coverage) for a full description.
     pub alpha_coverage: bool,
     /// The logic operation to apply to the blending equation, if any.
+    ///
+    /// Logic operations replace blending for all color targets and require
+    /// the `LOGIC_OP` feature. Some backends (e.g. D3D12) additionally need
+    /// the same color mask for all targets.
     pub logic_op: Option<LogicOp>,
     /// Which color targets to apply the blending operation to.
     pub targets: Vec<ColorBlendDesc>,
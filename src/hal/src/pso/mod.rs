@@ -13,12 +13,14 @@ mod descriptor;
 mod graphics;
 mod input_assembler;
 mod output_merger;
+mod ray_tracing;
 
 pub use self::compute::*;
 pub use self::descriptor::*;
 pub use self::graphics::*;
 pub use self::input_assembler::*;
 pub use self::output_merger::*;
+pub use self::ray_tracing::*;
 
 use Backend;
 
@@ -91,6 +93,10 @@ bitflags!(
         /// Read/Write access from host.
         /// (Not a real pipeline stage)
         const HOST = 0x4000;
+        /// Ray tracing shader execution.
+        const RAY_TRACING_SHADER = 0x20_0000;
+        /// Acceleration structure build execution.
+        const ACCELERATION_STRUCTURE_BUILD = 0x200_0000;
     }
 );
 
@@ -110,11 +116,26 @@ bitflags!(
         const FRAGMENT = 0x10;
         /// Compute shader stage.
         const COMPUTE  = 0x20;
+        /// Ray generation shader stage.
+        const RAY_GEN = 0x100;
+        /// Any hit shader stage.
+        const ANY_HIT = 0x200;
+        /// Closest hit shader stage.
+        const CLOSEST_HIT = 0x400;
+        /// Miss shader stage.
+        const MISS = 0x800;
+        /// Intersection shader stage.
+        const INTERSECTION = 0x1000;
+        /// Callable shader stage.
+        const CALLABLE = 0x2000;
         /// All graphics pipeline shader stages.
         const GRAPHICS = Self::VERTEX.bits | Self::HULL.bits |
             Self::DOMAIN.bits | Self::GEOMETRY.bits | Self::FRAGMENT.bits;
+        /// All ray tracing pipeline shader stages.
+        const RAY_TRACING = Self::RAY_GEN.bits | Self::MISS.bits | Self::CLOSEST_HIT.bits |
+            Self::ANY_HIT.bits | Self::INTERSECTION.bits | Self::CALLABLE.bits;
         /// All shader stages.
-        const ALL      = Self::GRAPHICS.bits | Self::COMPUTE.bits;
+        const ALL      = Self::GRAPHICS.bits | Self::COMPUTE.bits | Self::RAY_TRACING.bits;
     }
 );
 
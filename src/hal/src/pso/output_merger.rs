@@ -298,7 +298,8 @@ impl Default for StencilTest {
 pub struct DepthStencilDesc {
     /// Optional depth testing/writing.
     pub depth: DepthTest,
-    /// Enable depth bounds testing.
+    /// Enable depth bounds testing, discarding fragments whose stored depth
+    /// lies outside of the depth bounds range. Requires `Features::DEPTH_BOUNDS`.
     pub depth_bounds: bool,
     /// Stencil test/write.
     pub stencil: StencilTest,
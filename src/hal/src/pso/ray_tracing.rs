@@ -0,0 +1,92 @@
+//! Ray tracing pipeline descriptor.
+
+use Backend;
+use super::{EntryPoint, PipelineCreationFlags};
+
+/// Type of a ray tracing shader stage.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RayTracingStage {
+    RayGen,
+    Miss,
+    ClosestHit,
+    AnyHit,
+    Intersection,
+    Callable,
+}
+
+/// A shader stage of a ray tracing pipeline.
+#[derive(Debug)]
+pub struct RayTracingShader<'a, B: Backend> {
+    /// Type of the shader.
+    pub stage: RayTracingStage,
+    /// Shader entry point.
+    pub entry: EntryPoint<'a, B>,
+}
+
+/// A shader group of a ray tracing pipeline.
+///
+/// Shaders are referenced by their index in `RayTracingPipelineDesc::shaders`.
+/// Shader binding table records are built from the handles of these groups.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ShaderGroup {
+    /// A single ray generation, miss or callable shader.
+    General(u32),
+    /// Hit group for triangle geometry.
+    Triangles {
+        /// Optional closest hit shader.
+        closest_hit: Option<u32>,
+        /// Optional any hit shader.
+        any_hit: Option<u32>,
+    },
+    /// Hit group for procedural (AABB) geometry.
+    Procedural {
+        /// Intersection shader.
+        intersection: u32,
+        /// Optional closest hit shader.
+        closest_hit: Option<u32>,
+        /// Optional any hit shader.
+        any_hit: Option<u32>,
+    },
+}
+
+/// A description of the data needed to construct a ray tracing pipeline.
+#[derive(Debug)]
+pub struct RayTracingPipelineDesc<'a, B: Backend> {
+    /// Shader stages of the pipeline.
+    pub shaders: &'a [RayTracingShader<'a, B>],
+    /// Shader groups, addressed by the shader binding tables.
+    pub groups: &'a [ShaderGroup],
+    /// Maximum depth of `TraceRay` recursion.
+    pub max_recursion_depth: u32,
+    /// Maximum size of the ray payload, in bytes.
+    pub max_payload_size: u32,
+    /// Maximum size of the hit attributes, in bytes.
+    pub max_attribute_size: u32,
+    /// Pipeline layout.
+    pub layout: &'a B::PipelineLayout,
+    /// Any flags necessary for the pipeline creation.
+    pub flags: PipelineCreationFlags,
+}
+
+impl<'a, B: Backend> RayTracingPipelineDesc<'a, B> {
+    /// Create a new PSO descriptor with a recursion depth of 1 and
+    /// room for the built-in triangle intersection attributes.
+    pub fn new(
+        shaders: &'a [RayTracingShader<'a, B>],
+        groups: &'a [ShaderGroup],
+        layout: &'a B::PipelineLayout,
+    ) -> Self {
+        RayTracingPipelineDesc {
+            shaders,
+            groups,
+            max_recursion_depth: 1,
+            max_payload_size: 16,
+            max_attribute_size: 8,
+            layout,
+            flags: PipelineCreationFlags::empty(),
+        }
+    }
+}
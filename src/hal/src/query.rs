@@ -3,7 +3,10 @@
 //! or other introspection, providing a mechanism for the command buffer to record data about its
 //! operation as it is running.
 
-use Backend;
+use std::any::Any;
+use std::fmt;
+
+use {buffer, Backend};
 
 
 /// A query identifier.
@@ -30,6 +33,45 @@ bitflags!(
     }
 );
 
+bitflags!(
+    /// Query result flags.
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct ResultFlags: u32 {
+        /// Results will be written as an array of 64-bit unsigned integer values.
+        /// If this bit is not set, the results will be written as an array of
+        /// 32-bit unsigned integer values.
+        const BITS_64 = 0x1;
+        /// Wait for each query's status to become available before retrieving its results.
+        const WAIT = 0x2;
+        /// Availability status accompanies the results.
+        const WITH_AVAILABILITY = 0x4;
+        /// Returning partial results is acceptable.
+        const PARTIAL = 0x8;
+    }
+);
+
+bitflags!(
+    /// Query pool creation flags.
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct QueryPoolCreateFlags: u32 {
+        /// Store occlusion results in the format read by predicated rendering:
+        /// a single `u64` per query, which is zero if no samples passed and
+        /// non-zero otherwise. Binary occlusion queries are used where supported.
+        ///
+        /// Only valid for occlusion query pools.
+        const PREDICATION = 0x1;
+    }
+);
+
+/// A query pool stores the results of queries.
+pub trait QueryPool: fmt::Debug + Any + Send + Sync {
+    /// Size in bytes of the results of a single query, as written by
+    /// `copy_query_pool_results` with `ResultFlags::BITS_64`.
+    ///
+    /// Copying with this stride results in tightly packed query results.
+    fn result_stride(&self) -> buffer::Offset;
+}
+
 /// Type of queries in a query pool.
 pub enum QueryType {
     /// Occlusion query. Count the number of drawn samples between
@@ -0,0 +1,291 @@
+//! Deferred destruction of resources.
+//!
+//! Resources must not be destroyed while submitted command buffers referencing
+//! them are still executing. A `RetirementQueue` holds on to retired resources
+//! until the fence of the last submission using them has been signaled:
+//!
+//! ```no_run
+//! # extern crate gfx_backend_empty as empty;
+//! # extern crate gfx_hal as hal;
+//! # fn main() {
+//! use hal::retirement::{Resource, RetirementQueue};
+//!
+//! # let device: empty::Device = return;
+//! # let buffer = ();
+//! let mut retirement = RetirementQueue::<empty::Backend>::new();
+//!
+//! // The buffer is still used by the next submission.
+//! retirement.retire(Resource::Buffer(buffer));
+//! let fence = retirement.fence(&device);
+//! // .. submit the last commands referencing the buffer, signaling `fence` ..
+//!
+//! // Once per frame, destroy the resources of completed submissions.
+//! retirement.cleanup(&device);
+//!
+//! // Wait for all submissions before destroying the device.
+//! retirement.destroy(&device);
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::mem;
+
+use device::Device;
+use Backend;
+
+/// Resource which can be retired.
+///
+/// Descriptor sets are freed by retiring their descriptor pool.
+#[derive(Debug)]
+pub enum Resource<B: Backend> {
+    /// Memory allocation.
+    Memory(B::Memory),
+    /// Buffer.
+    Buffer(B::Buffer),
+    /// Buffer view.
+    BufferView(B::BufferView),
+    /// Image.
+    Image(B::Image),
+    /// Image view.
+    ImageView(B::ImageView),
+    /// Sampler.
+    Sampler(B::Sampler),
+    /// Framebuffer.
+    Framebuffer(B::Framebuffer),
+    /// Descriptor pool, including all descriptor sets allocated from it.
+    DescriptorPool(B::DescriptorPool),
+    /// Graphics pipeline.
+    GraphicsPipeline(B::GraphicsPipeline),
+    /// Compute pipeline.
+    ComputePipeline(B::ComputePipeline),
+    /// Query pool.
+    QueryPool(B::QueryPool),
+    /// Acceleration structure.
+    AccelerationStructure(B::AccelerationStructure),
+}
+
+impl<B: Backend> Resource<B> {
+    /// Destroy the resource.
+    pub fn destroy(self, device: &B::Device) {
+        match self {
+            Resource::Memory(memory) => device.free_memory(memory),
+            Resource::Buffer(buffer) => device.destroy_buffer(buffer),
+            Resource::BufferView(view) => device.destroy_buffer_view(view),
+            Resource::Image(image) => device.destroy_image(image),
+            Resource::ImageView(view) => device.destroy_image_view(view),
+            Resource::Sampler(sampler) => device.destroy_sampler(sampler),
+            Resource::Framebuffer(framebuffer) => device.destroy_framebuffer(framebuffer),
+            Resource::DescriptorPool(pool) => device.destroy_descriptor_pool(pool),
+            Resource::GraphicsPipeline(pipeline) => device.destroy_graphics_pipeline(pipeline),
+            Resource::ComputePipeline(pipeline) => device.destroy_compute_pipeline(pipeline),
+            Resource::QueryPool(pool) => device.destroy_query_pool(pool),
+            Resource::AccelerationStructure(accel) => device.destroy_acceleration_structure(accel),
+        }
+    }
+}
+
+/// Queue of retired resources, which are destroyed once the device has finished using them.
+///
+/// Submissions are expected to complete in order, which holds for submissions
+/// to a single queue. The queue must be emptied with `destroy` before the device
+/// is destroyed, otherwise the remaining resources are leaked.
+#[derive(Debug)]
+pub struct RetirementQueue<B: Backend> {
+    inner: FencedQueue<B::Fence, Resource<B>>,
+}
+
+impl<B: Backend> RetirementQueue<B> {
+    /// Create an empty queue.
+    pub fn new() -> Self {
+        RetirementQueue {
+            inner: FencedQueue::new(),
+        }
+    }
+
+    /// Retire a resource, which may still be used by submitted command buffers or
+    /// by the next submission. Retired resources must not be used in later submissions.
+    pub fn retire(&mut self, resource: Resource<B>) {
+        self.inner.pending.push(resource);
+    }
+
+    /// Get the fence guarding the resources retired since the last call,
+    /// which must be signaled by the next submission.
+    pub fn fence(&mut self, device: &B::Device) -> &B::Fence {
+        self.inner.fence(|| device.create_fence(false))
+    }
+
+    /// Destroy the resources of all completed submissions, without blocking.
+    ///
+    /// Completion is polled with `Device::get_fence_status`, signaled fences are
+    /// reset and reused by later calls to `fence`.
+    pub fn cleanup(&mut self, device: &B::Device) {
+        self.inner.cleanup(
+            |fence| device.get_fence_status(fence),
+            |resource| resource.destroy(device),
+            |fence| device.reset_fence(fence),
+        );
+    }
+
+    /// Wait for all submissions and destroy all retired resources and fences.
+    ///
+    /// Resources retired without requesting a `fence` afterwards are destroyed
+    /// immediately.
+    pub fn destroy(mut self, device: &B::Device) {
+        self.inner.destroy(
+            |fence| { device.wait_for_fence(fence, !0); },
+            |resource| resource.destroy(device),
+            |fence| device.destroy_fence(fence),
+        );
+    }
+}
+
+impl<B: Backend> Default for RetirementQueue<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Items guarded by the fences of the submissions using them last,
+// independent of the device.
+#[derive(Debug)]
+struct FencedQueue<F, T> {
+    // Items retired since the last call to `fence`.
+    pending: Vec<T>,
+    // Retired items and the fence of the submission using them last, oldest first.
+    in_flight: VecDeque<(F, Vec<T>)>,
+    // Unsignaled fences for reuse.
+    free_fences: Vec<F>,
+}
+
+impl<F, T> FencedQueue<F, T> {
+    fn new() -> Self {
+        FencedQueue {
+            pending: Vec::new(),
+            in_flight: VecDeque::new(),
+            free_fences: Vec::new(),
+        }
+    }
+
+    fn fence<C>(&mut self, create: C) -> &F
+    where
+        C: FnOnce() -> F,
+    {
+        let fence = match self.free_fences.pop() {
+            Some(fence) => fence,
+            None => create(),
+        };
+        let items = mem::replace(&mut self.pending, Vec::new());
+        self.in_flight.push_back((fence, items));
+        &self.in_flight.back().unwrap().0
+    }
+
+    // Destroy the items of the completed submissions, stopping at the first
+    // unsignaled fence as submissions complete in order.
+    fn cleanup<S, D, R>(&mut self, mut signaled: S, mut destroy: D, mut reset: R)
+    where
+        S: FnMut(&F) -> bool,
+        D: FnMut(T),
+        R: FnMut(&F),
+    {
+        while self.in_flight.front().map_or(false, |entry| signaled(&entry.0)) {
+            let (fence, items) = self.in_flight.pop_front().unwrap();
+            for item in items {
+                destroy(item);
+            }
+            reset(&fence);
+            self.free_fences.push(fence);
+        }
+    }
+
+    fn destroy<W, D, X>(&mut self, mut wait: W, mut destroy: D, mut destroy_fence: X)
+    where
+        W: FnMut(&F),
+        D: FnMut(T),
+        X: FnMut(F),
+    {
+        for (fence, items) in self.in_flight.drain(..) {
+            wait(&fence);
+            for item in items {
+                destroy(item);
+            }
+            destroy_fence(fence);
+        }
+        for item in self.pending.drain(..) {
+            destroy(item);
+        }
+        for fence in self.free_fences.drain(..) {
+            destroy_fence(fence);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Fences are indices into a list of signaled states.
+    fn fence(queue: &mut FencedQueue<usize, &'static str>, count: &mut usize) -> usize {
+        *queue.fence(|| { *count += 1; *count - 1 })
+    }
+
+    #[test]
+    fn cleanup_order() {
+        let mut queue = FencedQueue::new();
+        let mut count = 0;
+        queue.pending.push("a");
+        assert_eq!(fence(&mut queue, &mut count), 0);
+        queue.pending.push("b");
+        queue.pending.push("c");
+        assert_eq!(fence(&mut queue, &mut count), 1);
+        queue.pending.push("d");
+        assert_eq!(fence(&mut queue, &mut count), 2);
+        queue.pending.push("e");
+
+        let mut signaled = [true, false, true];
+        let mut destroyed = Vec::new();
+        let mut reset = Vec::new();
+        // The third submission can't have completed before the second one.
+        queue.cleanup(|&f| signaled[f], |item| destroyed.push(item), |&f| reset.push(f));
+        assert_eq!(destroyed, ["a"]);
+        assert_eq!(reset, [0]);
+
+        signaled[1] = true;
+        queue.cleanup(|&f| signaled[f], |item| destroyed.push(item), |&f| reset.push(f));
+        assert_eq!(destroyed, ["a", "b", "c", "d"]);
+        assert_eq!(reset, [0, 1, 2]);
+        // Items retired without a fence are kept.
+        assert_eq!(queue.pending, ["e"]);
+
+        // Reset fences are reused before creating new ones.
+        assert_eq!(fence(&mut queue, &mut count), 2);
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn destroy_waits() {
+        let mut queue = FencedQueue::new();
+        let mut count = 0;
+        queue.pending.push("a");
+        fence(&mut queue, &mut count);
+        queue.pending.push("b");
+        fence(&mut queue, &mut count);
+        queue.cleanup(|&f| f == 0, |_| {}, |_| {});
+        queue.pending.push("c");
+
+        let mut events = Vec::new();
+        {
+            let events = ::std::cell::RefCell::new(&mut events);
+            queue.destroy(
+                |&f| events.borrow_mut().push(format!("wait {}", f)),
+                |item| events.borrow_mut().push(format!("destroy {}", item)),
+                |f| events.borrow_mut().push(format!("destroy fence {}", f)),
+            );
+        }
+        assert_eq!(events, [
+            "wait 1", "destroy b", "destroy fence 1",
+            "destroy c",
+            "destroy fence 0",
+        ]);
+        assert!(queue.in_flight.is_empty() && queue.free_fences.is_empty());
+    }
+}
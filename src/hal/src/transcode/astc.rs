@@ -0,0 +1,626 @@
+//! Decoding of ASTC blocks with LDR endpoints.
+//!
+//! Blocks with HDR endpoints or invalid encodings decode to the error color,
+//! as in the LDR profile.
+
+use super::{bits, clamp, Bits};
+
+/// Color of blocks which can't be decoded.
+const ERROR_COLOR: [u8; 4] = [0xFF, 0x00, 0xFF, 0xFF];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Packing {
+    Bits,
+    Trits,
+    Quints,
+}
+
+/// Packing and number of bits of the quantization ranges, from 2 to 256 values.
+///
+/// The first 12 ranges are the ones available for weights.
+const RANGES: [(Packing, usize); 21] = [
+    (Packing::Bits, 1), (Packing::Trits, 0), (Packing::Bits, 2), (Packing::Quints, 0),
+    (Packing::Trits, 1), (Packing::Bits, 3), (Packing::Quints, 1), (Packing::Trits, 2),
+    (Packing::Bits, 4), (Packing::Quints, 2), (Packing::Trits, 3), (Packing::Bits, 5),
+    (Packing::Quints, 3), (Packing::Trits, 4), (Packing::Bits, 6), (Packing::Quints, 4),
+    (Packing::Trits, 5), (Packing::Bits, 7), (Packing::Quints, 5), (Packing::Trits, 6),
+    (Packing::Bits, 8),
+];
+
+/// Smallest range of color endpoint values, from 0 to 5.
+const MIN_COLOR_RANGE: usize = 4;
+
+/// Size in bits of `count` values of the integer sequence encoding in `range`.
+fn sequence_size(count: usize, (packing, bits): (Packing, usize)) -> usize {
+    count * bits + match packing {
+        Packing::Bits => 0,
+        Packing::Trits => (8 * count + 4) / 5,
+        Packing::Quints => (7 * count + 2) / 3,
+    }
+}
+
+fn decode_trits(t: u32) -> [u32; 5] {
+    let (c, t3, t4) = if t >> 2 & 7 == 7 {
+        ((t >> 5 & 7) << 2 | t & 3, 2, 2)
+    } else if t >> 5 & 3 == 3 {
+        (t & 0x1F, t >> 7 & 1, 2)
+    } else {
+        (t & 0x1F, t >> 5 & 3, t >> 7 & 1)
+    };
+    let (t0, t1, t2) = if c & 3 == 3 {
+        ((c >> 3 & 1) << 1 | (c >> 2 & !(c >> 3) & 1), c >> 4 & 1, 2)
+    } else if c >> 2 & 3 == 3 {
+        (c & 3, 2, 2)
+    } else {
+        ((c >> 1 & 1) << 1 | (c & !(c >> 1) & 1), c >> 2 & 3, c >> 4 & 1)
+    };
+    [t0, t1, t2, t3, t4]
+}
+
+fn decode_quints(q: u32) -> [u32; 3] {
+    if q >> 1 & 3 == 3 && q >> 5 & 3 == 0 {
+        let q2 = (q & 1) << 2 | (q >> 4 & !q & 1) << 1 | (q >> 3 & !q & 1);
+        return [4, 4, q2];
+    }
+    let (c, q2) = if q >> 1 & 3 == 3 {
+        ((q >> 3 & 3) << 3 | (!q >> 5 & 3) << 1 | q & 1, 4)
+    } else {
+        (q & 0x1F, q >> 5 & 3)
+    };
+    if c & 7 == 5 {
+        [c >> 3 & 3, 4, q2]
+    } else {
+        [c & 7, c >> 3 & 3, q2]
+    }
+}
+
+/// Decode `count` values of the integer sequence encoding in `range` starting
+/// at bit `start` of `data`, as pairs of trit or quint and low bits.
+///
+/// Bits past the end of the sequence are read as 0.
+fn decode_sequence(data: &[u8], start: usize, count: usize, range: (Packing, usize)) -> Vec<(u32, u32)> {
+    let (packing, bits) = range;
+    let mut reader = Bits::new(data, start, start + sequence_size(count, range));
+    let mut values = Vec::with_capacity(count + 4);
+    match packing {
+        Packing::Bits => {
+            for _ in 0 .. count {
+                values.push((0, reader.read(bits)));
+            }
+        }
+        Packing::Trits => while values.len() < count {
+            // The 8 bits of 5 packed trits are interleaved with the low bits of the values.
+            let mut low = [0; 5];
+            let mut packed = 0;
+            for (i, &(shift, size)) in [(0, 2), (2, 2), (4, 1), (5, 2), (7, 1)].iter().enumerate() {
+                low[i] = reader.read(bits);
+                packed |= reader.read(size) << shift;
+            }
+            let trits = decode_trits(packed);
+            values.extend(trits.iter().cloned().zip(low.iter().cloned()));
+        },
+        Packing::Quints => while values.len() < count {
+            // The 7 bits of 3 packed quints are interleaved with the low bits of the values.
+            let mut low = [0; 3];
+            let mut packed = 0;
+            for (i, &(shift, size)) in [(0, 3), (3, 2), (5, 2)].iter().enumerate() {
+                low[i] = reader.read(bits);
+                packed |= reader.read(size) << shift;
+            }
+            let quints = decode_quints(packed);
+            values.extend(quints.iter().cloned().zip(low.iter().cloned()));
+        },
+    }
+    values.truncate(count);
+    values
+}
+
+/// Repeat the `bits` low bits of `value` to fill `target` bits.
+fn replicate(value: u32, bits: usize, target: usize) -> u32 {
+    let (mut result, mut filled) = (0, 0);
+    while filled < target {
+        result = result << bits | value;
+        filled += bits;
+    }
+    result >> (filled - target)
+}
+
+/// Unquantize a color endpoint value to 8 bits.
+fn unquantize_color((high, low): (u32, u32), (packing, bits): (Packing, usize)) -> i32 {
+    if packing == Packing::Bits {
+        return replicate(low, bits, 8) as i32;
+    }
+    // The low bits above the first one are spread over 9 bits, the first one mirrors the value.
+    let (b, c, d, e, f) = (low >> 1 & 1, low >> 2 & 1, low >> 3 & 1, low >> 4 & 1, low >> 5 & 1);
+    let (spread, scale) = match (packing, bits) {
+        (Packing::Trits, 1) => (0, 204),
+        (Packing::Quints, 1) => (0, 113),
+        (Packing::Trits, 2) => (b * 0x116, 93),
+        (Packing::Quints, 2) => (b * 0x10C, 54),
+        (Packing::Trits, 3) => (c * 0x10A + b * 0x85, 44),
+        (Packing::Quints, 3) => (c * 0x109 + b * 0x84, 26),
+        (Packing::Trits, 4) => (d * 0x104 + c * 0x82 + b * 0x41, 22),
+        (Packing::Quints, 4) => (d * 0x102 + c * 0x81 + b * 0x40, 13),
+        (Packing::Trits, 5) => (e * 0x102 + d * 0x81 + c * 0x40 + b * 0x20, 11),
+        (Packing::Quints, 5) => (e * 0x101 + d * 0x80 + c * 0x40 + b * 0x20, 6),
+        (Packing::Trits, _) => (f * 0x101 + e * 0x80 + d * 0x40 + c * 0x20 + b * 0x10, 5),
+        // Trits and quints without low bits aren't used for color endpoints.
+        _ => (0, 0),
+    };
+    let mirror = if low & 1 != 0 { 0x1FF } else { 0 };
+    let value = (high * scale + spread) ^ mirror;
+    ((mirror & 0x80) | value >> 2) as i32
+}
+
+/// Unquantize a weight to the range from 0 to 64.
+fn unquantize_weight((high, low): (u32, u32), (packing, bits): (Packing, usize)) -> u32 {
+    let value = match (packing, bits) {
+        (Packing::Bits, _) => replicate(low, bits, 6),
+        (Packing::Trits, 0) => [0, 32, 63][high as usize],
+        (Packing::Quints, 0) => [0, 16, 32, 47, 63][high as usize],
+        _ => {
+            let (b, c) = (low >> 1 & 1, low >> 2 & 1);
+            let (spread, scale) = match (packing, bits) {
+                (Packing::Trits, 1) => (0, 50),
+                (Packing::Quints, 1) => (0, 28),
+                (Packing::Trits, 2) => (b * 0x45, 23),
+                (Packing::Quints, 2) => (b * 0x42, 13),
+                _ => (c * 0x42 + b * 0x21, 11),
+            };
+            let mirror = if low & 1 != 0 { 0x7F } else { 0 };
+            let value = (high * scale + spread) ^ mirror;
+            (mirror & 0x20) | value >> 2
+        }
+    };
+    if value > 32 { value + 1 } else { value }
+}
+
+/// Weight grid width and height, dual plane flag and weight range of a block mode.
+fn block_mode(mode: u32) -> Option<(usize, usize, bool, usize)> {
+    let a = (mode >> 5 & 3) as usize;
+    let mut dual_plane = mode >> 10 & 1 != 0;
+    let mut high_precision = mode >> 9 & 1 != 0;
+    let mut range = (mode >> 4 & 1) as usize;
+    let (width, height);
+    if mode & 3 != 0 {
+        range |= (mode as usize & 3) << 1;
+        let b = (mode >> 7 & 3) as usize;
+        match mode >> 2 & 3 {
+            0 => { width = b + 4; height = a + 2; }
+            1 => { width = b + 8; height = a + 2; }
+            2 => { width = a + 2; height = b + 8; }
+            _ if mode & 0x100 != 0 => { width = (b & 1) + 2; height = a + 2; }
+            _ => { width = a + 2; height = (b & 1) + 6; }
+        }
+    } else {
+        if mode >> 2 & 3 == 0 {
+            return None;
+        }
+        range |= (mode as usize >> 2 & 3) << 1;
+        let b = (mode >> 9 & 3) as usize;
+        match mode >> 7 & 3 {
+            0 => { width = 12; height = a + 2; }
+            1 => { width = a + 2; height = 12; }
+            2 => {
+                width = a + 6;
+                height = b + 6;
+                dual_plane = false;
+                high_precision = false;
+            }
+            _ => match a {
+                0 => { width = 6; height = 10; }
+                1 => { width = 10; height = 6; }
+                _ => return None,
+            },
+        }
+    }
+    Some((width, height, dual_plane, range - 2 + if high_precision { 6 } else { 0 }))
+}
+
+/// Transfer the top bit of `a` to `b`, leaving `a` a signed 6 bit offset.
+fn bit_transfer_signed(a: i32, b: i32) -> (i32, i32) {
+    let b = b >> 1 | a & 0x80;
+    let a = a >> 1 & 0x3F;
+    (if a & 0x20 != 0 { a - 0x40 } else { a }, b)
+}
+
+fn blue_contract(color: [i32; 4]) -> [i32; 4] {
+    [(color[0] + color[2]) >> 1, (color[1] + color[2]) >> 1, color[2], color[3]]
+}
+
+/// Decode the endpoints of color endpoint mode `mode` from the values `v`.
+///
+/// Returns `None` for HDR modes.
+fn decode_endpoints(mode: u32, v: &[i32]) -> Option<[[u8; 4]; 2]> {
+    let (e0, e1) = match mode {
+        // Luminance, direct.
+        0 => ([v[0], v[0], v[0], 0xFF], [v[1], v[1], v[1], 0xFF]),
+        // Luminance, base and offset.
+        1 => {
+            let l0 = v[0] >> 2 | v[1] & 0xC0;
+            let l1 = (l0 + (v[1] & 0x3F)).min(0xFF);
+            ([l0, l0, l0, 0xFF], [l1, l1, l1, 0xFF])
+        }
+        // Luminance and alpha, direct.
+        4 => ([v[0], v[0], v[0], v[2]], [v[1], v[1], v[1], v[3]]),
+        // Luminance and alpha, base and offset.
+        5 => {
+            let (o0, l) = bit_transfer_signed(v[1], v[0]);
+            let (o1, a) = bit_transfer_signed(v[3], v[2]);
+            ([l, l, l, a], [l + o0, l + o0, l + o0, a + o1])
+        }
+        // RGB, base and scale.
+        6 => (
+            [(v[0] * v[3]) >> 8, (v[1] * v[3]) >> 8, (v[2] * v[3]) >> 8, 0xFF],
+            [v[0], v[1], v[2], 0xFF],
+        ),
+        // RGB and RGBA, direct.
+        8 | 12 => {
+            let (a0, a1) = if mode == 12 { (v[6], v[7]) } else { (0xFF, 0xFF) };
+            if v[1] + v[3] + v[5] >= v[0] + v[2] + v[4] {
+                ([v[0], v[2], v[4], a0], [v[1], v[3], v[5], a1])
+            } else {
+                (blue_contract([v[1], v[3], v[5], a1]), blue_contract([v[0], v[2], v[4], a0]))
+            }
+        }
+        // RGB and RGBA, base and offset.
+        9 | 13 => {
+            let (r1, r0) = bit_transfer_signed(v[1], v[0]);
+            let (g1, g0) = bit_transfer_signed(v[3], v[2]);
+            let (b1, b0) = bit_transfer_signed(v[5], v[4]);
+            let (a1, a0) = if mode == 13 { bit_transfer_signed(v[7], v[6]) } else { (0, 0xFF) };
+            if r1 + g1 + b1 >= 0 {
+                ([r0, g0, b0, a0], [r0 + r1, g0 + g1, b0 + b1, a0 + a1])
+            } else {
+                (blue_contract([r0 + r1, g0 + g1, b0 + b1, a0 + a1]), blue_contract([r0, g0, b0, a0]))
+            }
+        }
+        // RGB, base and scale, with alpha.
+        10 => (
+            [(v[0] * v[3]) >> 8, (v[1] * v[3]) >> 8, (v[2] * v[3]) >> 8, v[4]],
+            [v[0], v[1], v[2], v[5]],
+        ),
+        _ => return None,
+    };
+    let mut endpoints = [[0; 4]; 2];
+    for c in 0 .. 4 {
+        endpoints[0][c] = clamp(e0[c]);
+        endpoints[1][c] = clamp(e1[c]);
+    }
+    Some(endpoints)
+}
+
+fn hash52(seed: u32) -> u32 {
+    let mut p = seed;
+    p ^= p >> 15;
+    p = p.wrapping_sub(p << 17);
+    p = p.wrapping_add(p << 7);
+    p = p.wrapping_add(p << 4);
+    p ^= p >> 5;
+    p = p.wrapping_add(p << 16);
+    p ^= p >> 7;
+    p ^= p >> 3;
+    p ^= p << 6;
+    p ^= p >> 17;
+    p
+}
+
+/// Partition of the texel at `x`, `y` for partition pattern `seed` of `count` partitions.
+fn select_partition(seed: u32, x: u32, y: u32, count: u32, small_block: bool) -> usize {
+    let (x, y) = if small_block { (x << 1, y << 1) } else { (x, y) };
+    let seed = seed + (count - 1) * 1024;
+    let rnum = hash52(seed);
+    let mut seeds = [0u32; 8];
+    for (i, s) in seeds.iter_mut().enumerate() {
+        let value = rnum >> (4 * i) & 0xF;
+        *s = value * value;
+    }
+    let (sh1, sh2) = if seed & 1 != 0 {
+        (if seed & 2 != 0 { 4 } else { 5 }, if count == 3 { 6 } else { 5 })
+    } else {
+        (if count == 3 { 6 } else { 5 }, if seed & 2 != 0 { 4 } else { 5 })
+    };
+    for (i, s) in seeds.iter_mut().enumerate() {
+        *s >>= if i % 2 == 0 { sh1 } else { sh2 };
+    }
+    // The z coordinate of 3D blocks is always 0 here.
+    let a = (seeds[0] * x + seeds[1] * y + (rnum >> 14)) & 0x3F;
+    let b = (seeds[2] * x + seeds[3] * y + (rnum >> 10)) & 0x3F;
+    let c = if count < 3 { 0 } else { (seeds[4] * x + seeds[5] * y + (rnum >> 6)) & 0x3F };
+    let d = if count < 4 { 0 } else { (seeds[6] * x + seeds[7] * y + (rnum >> 2)) & 0x3F };
+    if a >= b && a >= c && a >= d {
+        0
+    } else if b >= c && b >= d {
+        1
+    } else if c >= d {
+        2
+    } else {
+        3
+    }
+}
+
+/// Decode an ASTC block with a footprint of `width` x `height` texels into `texels`,
+/// in row-major order.
+///
+/// With `srgb`, endpoints are interpolated as specified for sRGB formats.
+pub fn decode(block: &[u8], width: usize, height: usize, srgb: bool, texels: &mut [[u8; 4]]) {
+    if decode_block(block, width, height, srgb, texels).is_none() {
+        for texel in texels.iter_mut() {
+            *texel = ERROR_COLOR;
+        }
+    }
+}
+
+fn decode_block(block: &[u8], width: usize, height: usize, srgb: bool, texels: &mut [[u8; 4]]) -> Option<()> {
+    let mode = bits(block, 0, 11);
+    if mode & 0x1FF == 0x1FC {
+        // Void-extent block of a single color, with UNORM16 channels for LDR.
+        if mode & 0x200 != 0 {
+            return None;
+        }
+        let mut color = [0; 4];
+        for (c, value) in color.iter_mut().enumerate() {
+            *value = block[9 + 2 * c];
+        }
+        for texel in texels.iter_mut() {
+            *texel = color;
+        }
+        return Some(());
+    }
+
+    let (grid_width, grid_height, dual_plane, weight_range) = block_mode(mode)?;
+    let planes = if dual_plane { 2 } else { 1 };
+    let weight_count = grid_width * grid_height * planes;
+    let weight_bits = sequence_size(weight_count, RANGES[weight_range]);
+    let partitions = bits(block, 11, 2) as usize + 1;
+    if grid_width > width || grid_height > height || weight_count > 64 || weight_bits < 24 || weight_bits > 96
+        || (dual_plane && partitions == 4)
+    {
+        return None;
+    }
+
+    // Color endpoint modes, with extra bits of per partition modes below the weights.
+    let mut modes = [0u32; 4];
+    let mut below_weights = 128 - weight_bits;
+    let (color_start, partition_seed) = if partitions == 1 {
+        modes[0] = bits(block, 13, 4);
+        (17, 0)
+    } else {
+        let selector = bits(block, 23, 2);
+        if selector == 0 {
+            let mode = bits(block, 25, 4);
+            for m in modes.iter_mut() {
+                *m = mode;
+            }
+        } else {
+            let extra = 3 * partitions - 4;
+            below_weights = below_weights.checked_sub(extra)?;
+            let encoded = bits(block, 25, 4) | bits(block, below_weights, extra) << 4;
+            for (p, m) in modes[.. partitions].iter_mut().enumerate() {
+                let class = selector - 1 + (encoded >> p & 1);
+                *m = class << 2 | encoded >> (partitions + 2 * p) & 3;
+            }
+        }
+        (29, bits(block, 13, 10))
+    };
+    let plane_component = if dual_plane {
+        below_weights = below_weights.checked_sub(2)?;
+        Some(bits(block, below_weights, 2) as usize)
+    } else {
+        None
+    };
+
+    // Color endpoints use the largest range fitting the available bits.
+    let value_count: usize = modes[.. partitions].iter().map(|&m| ((m >> 2) as usize + 1) * 2).sum();
+    let color_bits = below_weights.checked_sub(color_start)?;
+    if value_count > 18 {
+        return None;
+    }
+    let color_range = (MIN_COLOR_RANGE .. RANGES.len())
+        .rev()
+        .find(|&range| sequence_size(value_count, RANGES[range]) <= color_bits)?;
+    let values: Vec<i32> = decode_sequence(block, color_start, value_count, RANGES[color_range])
+        .into_iter()
+        .map(|value| unquantize_color(value, RANGES[color_range]))
+        .collect();
+    let mut endpoints = [[[0; 4]; 2]; 4];
+    let mut offset = 0;
+    for (p, &m) in modes[.. partitions].iter().enumerate() {
+        endpoints[p] = decode_endpoints(m, &values[offset ..])?;
+        offset += ((m >> 2) as usize + 1) * 2;
+    }
+
+    // Weights are stored in reverse bit order from the end of the block.
+    let mut reversed = [0u8; 16];
+    for (i, byte) in reversed.iter_mut().enumerate() {
+        *byte = block[15 - i].reverse_bits();
+    }
+    let weights: Vec<u32> = decode_sequence(&reversed, 0, weight_count, RANGES[weight_range])
+        .into_iter()
+        .map(|weight| unquantize_weight(weight, RANGES[weight_range]))
+        .collect();
+
+    // Weights of the grid are bilinearly infilled at the texels.
+    let scale_x = (1024 + width / 2) / (width - 1);
+    let scale_y = (1024 + height / 2) / (height - 1);
+    let weight = |index: usize, plane: usize| weights.get(index * planes + plane).cloned().unwrap_or(0);
+    let small_block = width * height < 31;
+    for y in 0 .. height {
+        for x in 0 .. width {
+            let grid_x = (scale_x * x * (grid_width - 1) + 32) >> 6;
+            let grid_y = (scale_y * y * (grid_height - 1) + 32) >> 6;
+            let (fx, fy) = (grid_x & 0xF, grid_y & 0xF);
+            let index = (grid_x >> 4) + (grid_y >> 4) * grid_width;
+            let w11 = (fx * fy + 8) >> 4;
+            let factors = [16 + w11 - fx - fy, fx - w11, fy - w11, w11];
+            let infill = |plane: usize| {
+                let neighbors = [index, index + 1, index + grid_width, index + grid_width + 1];
+                let sum: u32 = neighbors
+                    .iter()
+                    .zip(factors.iter())
+                    .map(|(&n, &f)| if f == 0 { 0 } else { weight(n, plane) * f as u32 })
+                    .sum();
+                (sum + 8) >> 4
+            };
+            let (w0, w1) = (infill(0), if dual_plane { infill(1) } else { 0 });
+
+            let partition = if partitions == 1 {
+                0
+            } else {
+                select_partition(partition_seed, x as u32, y as u32, partitions as u32, small_block)
+            };
+            let (e0, e1) = (endpoints[partition][0], endpoints[partition][1]);
+            let texel = &mut texels[y * width + x];
+            for c in 0 .. 4 {
+                let w = if plane_component == Some(c) { w1 } else { w0 };
+                // Endpoints are expanded to 16 bits before interpolation.
+                let expand = |value: u8| if srgb { (value as u32) << 8 | 0x80 } else { value as u32 * 0x101 };
+                let value = (expand(e0[c]) * (64 - w) + expand(e1[c]) * w + 32) >> 6;
+                texel[c] = (value >> 8) as u8;
+            }
+        }
+    }
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 4x4 weight grid of 2 bit weights.
+    const BLOCK_MODE_4X4: u32 = 0b0_0_00_10_0_00_10;
+
+    /// Write `value` into `count` bits of `block` at `start`.
+    fn write(block: &mut [u8], start: usize, count: usize, value: u32) {
+        for i in 0 .. count {
+            let bit = start + i;
+            block[bit / 8] |= ((value >> i & 1) as u8) << (bit % 8);
+        }
+    }
+
+    #[test]
+    fn integer_sequences() {
+        // Trits 0 to 2 and quints 0 to 4 with their bit patterns.
+        assert_eq!(decode_trits(0), [0; 5]);
+        assert_eq!(decode_quints(0), [0; 3]);
+        for t in 0 .. 256 {
+            assert!(decode_trits(t).iter().all(|&trit| trit < 3));
+        }
+        for q in 0 .. 128 {
+            assert!(decode_quints(q).iter().all(|&quint| quint < 5));
+        }
+        // All 243 combinations of trits are reachable.
+        let mut trits: Vec<_> = (0 .. 256).map(decode_trits).collect();
+        trits.sort();
+        trits.dedup();
+        assert_eq!(trits.len(), 243);
+        let mut quints: Vec<_> = (0 .. 128).map(decode_quints).collect();
+        quints.sort();
+        quints.dedup();
+        assert_eq!(quints.len(), 125);
+    }
+
+    #[test]
+    fn unquantization() {
+        // Weights with 6 values: 0 to 2 trits and one bit.
+        let weights: Vec<_> = (0 .. 6)
+            .map(|v| unquantize_weight((v >> 1, v & 1), (Packing::Trits, 1)))
+            .collect();
+        assert_eq!(weights, [0, 64, 12, 52, 25, 39]);
+        // Colors with 12 values, the mirrored values complement each other.
+        let colors: Vec<_> = (0 .. 12)
+            .map(|v| unquantize_color((v >> 2, v & 3), (Packing::Trits, 2)))
+            .collect();
+        assert_eq!(colors, [0, 255, 69, 186, 23, 232, 92, 163, 46, 209, 116, 139]);
+        assert_eq!(unquantize_color((0, 0x2A), (Packing::Bits, 6)), 0xAA);
+    }
+
+    #[test]
+    fn void_extent() {
+        let mut block = [0xFCu8, 0xFD, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0, 0, 0, 0, 0];
+        for (c, &value) in [0x1234u16, 0x5678, 0x9ABC, 0xFFFF].iter().enumerate() {
+            block[8 + 2 * c] = value as u8;
+            block[9 + 2 * c] = (value >> 8) as u8;
+        }
+        let mut texels = [[0; 4]; 20];
+        decode(&block, 5, 4, false, &mut texels);
+        assert!(texels.iter().all(|texel| *texel == [0x12, 0x56, 0x9A, 0xFF]));
+
+        // HDR void-extent blocks aren't supported.
+        block[1] |= 0x02;
+        decode(&block, 5, 4, false, &mut texels);
+        assert!(texels.iter().all(|texel| *texel == ERROR_COLOR));
+    }
+
+    #[test]
+    fn single_partition() {
+        // 4x4 weight grid with 2 bit weights, in luminance direct mode with
+        // 8 bit endpoints from black to white. Column `x` uses weight `x`.
+        let mut block = [0u8; 16];
+        write(&mut block, 0, 11, BLOCK_MODE_4X4);
+        write(&mut block, 13, 4, 0);
+        write(&mut block, 17, 8, 0x00);
+        write(&mut block, 25, 8, 0xFF);
+        for i in 0 .. 16 {
+            // Weights are stored from the end of the block with reversed bits.
+            let weight = i as u32 % 4;
+            write(&mut block, 126 - 2 * i, 2, (weight & 1) << 1 | weight >> 1);
+        }
+        let mut texels = [[0; 4]; 16];
+        decode(&block, 4, 4, false, &mut texels);
+        // Weights 0, 21, 43 and 64 out of 64.
+        for y in 0 .. 4 {
+            assert_eq!(
+                &texels[y * 4 .. y * 4 + 4],
+                &[[0, 0, 0, 255], [84, 84, 84, 255], [171, 171, 171, 255], [255, 255, 255, 255]],
+            );
+        }
+
+        // The weights are infilled on the larger footprint of a 6x6 block.
+        let mut texels = [[0; 4]; 36];
+        decode(&block, 6, 6, false, &mut texels);
+        assert_eq!(texels[0], [0, 0, 0, 255]);
+        assert_eq!(texels[5], [255, 255, 255, 255]);
+        for x in 1 .. 6 {
+            assert!(texels[x][0] > texels[x - 1][0]);
+            assert_eq!(texels[30 + x], texels[x]);
+        }
+    }
+
+    #[test]
+    fn invalid_blocks() {
+        let mut texels = [[0; 4]; 16];
+        // Reserved block mode.
+        decode(&[0; 16], 4, 4, false, &mut texels);
+        assert!(texels.iter().all(|texel| *texel == ERROR_COLOR));
+
+        // Weight grid larger than the block footprint.
+        let mut block = [0u8; 16];
+        write(&mut block, 0, 11, 0b0_0_00_11_0_00_10);
+        decode(&block, 4, 4, false, &mut texels);
+        assert!(texels.iter().all(|texel| *texel == ERROR_COLOR));
+
+        // HDR endpoint mode.
+        let mut block = [0u8; 16];
+        write(&mut block, 0, 11, BLOCK_MODE_4X4);
+        write(&mut block, 13, 4, 2);
+        decode(&block, 4, 4, false, &mut texels);
+        assert!(texels.iter().all(|texel| *texel == ERROR_COLOR));
+    }
+
+    #[test]
+    fn partitions() {
+        // Texels of a large block are spread over all partitions by some patterns.
+        let mut seen = [false; 4];
+        for seed in 0 .. 1024 {
+            for y in 0 .. 12 {
+                for x in 0 .. 12 {
+                    seen[select_partition(seed, x, y, 4, false)] = true;
+                    assert!(select_partition(seed, x, y, 3, false) < 3);
+                    assert!(select_partition(seed, x, y, 2, false) < 2);
+                }
+            }
+        }
+        assert_eq!(seen, [true; 4]);
+    }
+}
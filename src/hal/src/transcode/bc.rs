@@ -0,0 +1,380 @@
+//! Decoding of BC1 to BC5 and BC7 blocks.
+
+use super::{extend4, unpack565, Bits};
+
+/// Decode the color block of BC1, BC2 and BC3 into `texels`.
+///
+/// With `three_colors`, the first endpoint not greater than the second selects
+/// 3 colors and black, which is transparent with `alpha`. BC2 and BC3 always
+/// use 4 colors.
+pub fn decode_color(block: &[u8], three_colors: bool, alpha: bool, texels: &mut [[u8; 4]]) {
+    let c0 = block[0] as u16 | (block[1] as u16) << 8;
+    let c1 = block[2] as u16 | (block[3] as u16) << 8;
+    let (e0, e1) = (unpack565(c0), unpack565(c1));
+    let palette = if c0 > c1 || !three_colors {
+        [
+            e0,
+            e1,
+            [(2 * e0[0] + e1[0]) / 3, (2 * e0[1] + e1[1]) / 3, (2 * e0[2] + e1[2]) / 3],
+            [(e0[0] + 2 * e1[0]) / 3, (e0[1] + 2 * e1[1]) / 3, (e0[2] + 2 * e1[2]) / 3],
+        ]
+    } else {
+        [e0, e1, [(e0[0] + e1[0]) / 2, (e0[1] + e1[1]) / 2, (e0[2] + e1[2]) / 2], [0; 3]]
+    };
+    let transparent = three_colors && alpha && c0 <= c1;
+
+    let indices = block[4 .. 8].iter().rev().fold(0u32, |bits, &byte| bits << 8 | byte as u32);
+    for (i, texel) in texels.iter_mut().enumerate() {
+        let index = (indices >> (2 * i) & 3) as usize;
+        let color = palette[index];
+        let alpha = if transparent && index == 3 { 0 } else { 0xFF };
+        *texel = [color[0] as u8, color[1] as u8, color[2] as u8, alpha];
+    }
+}
+
+/// Decode the explicit alpha block of BC2 into the alpha channel of `texels`.
+pub fn decode_explicit_alpha(block: &[u8], texels: &mut [[u8; 4]]) {
+    for (i, texel) in texels.iter_mut().enumerate() {
+        texel[3] = extend4(block[i / 2] >> (4 * (i % 2))) as u8;
+    }
+}
+
+/// Decode a BC4 block, or the alpha block of BC3, into `channel` of `texels`.
+///
+/// Signed values are stored as two's complement.
+pub fn decode_channel(block: &[u8], signed: bool, channel: usize, texels: &mut [[u8; 4]]) {
+    let (min, max) = if signed { (-127, 127) } else { (0, 255) };
+    let endpoint = |byte: u8| if signed { (byte as i8 as i32).max(min) } else { byte as i32 };
+    let (a0, a1) = (endpoint(block[0]), endpoint(block[1]));
+    // With the first endpoint greater than the second, 6 values are interpolated,
+    // otherwise 4 and the extremes of the range.
+    let palette = if a0 > a1 {
+        [
+            a0, a1,
+            (6 * a0 + a1) / 7, (5 * a0 + 2 * a1) / 7, (4 * a0 + 3 * a1) / 7,
+            (3 * a0 + 4 * a1) / 7, (2 * a0 + 5 * a1) / 7, (a0 + 6 * a1) / 7,
+        ]
+    } else {
+        [
+            a0, a1,
+            (4 * a0 + a1) / 5, (3 * a0 + 2 * a1) / 5, (2 * a0 + 3 * a1) / 5, (a0 + 4 * a1) / 5,
+            min, max,
+        ]
+    };
+
+    let indices = block[2 .. 8].iter().rev().fold(0u64, |bits, &byte| bits << 8 | byte as u64);
+    for (i, texel) in texels.iter_mut().enumerate() {
+        let value = palette[(indices >> (3 * i) & 7) as usize];
+        texel[channel] = if signed { value as i8 as u8 } else { value as u8 };
+    }
+}
+
+/// Field sizes of a BC7 mode.
+struct Bc7Mode {
+    subsets: usize,
+    partition_bits: usize,
+    rotation_bits: usize,
+    index_selection_bits: usize,
+    color_bits: usize,
+    alpha_bits: usize,
+    /// One P-bit per endpoint.
+    endpoint_pbits: bool,
+    /// One P-bit per subset, shared by its endpoints.
+    shared_pbits: bool,
+    index_bits: usize,
+    secondary_index_bits: usize,
+}
+
+const BC7_MODES: [Bc7Mode; 8] = [
+    Bc7Mode {
+        subsets: 3, partition_bits: 4, rotation_bits: 0, index_selection_bits: 0, color_bits: 4, alpha_bits: 0,
+        endpoint_pbits: true, shared_pbits: false, index_bits: 3, secondary_index_bits: 0,
+    },
+    Bc7Mode {
+        subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 6, alpha_bits: 0,
+        endpoint_pbits: false, shared_pbits: true, index_bits: 3, secondary_index_bits: 0,
+    },
+    Bc7Mode {
+        subsets: 3, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 0,
+        endpoint_pbits: false, shared_pbits: false, index_bits: 2, secondary_index_bits: 0,
+    },
+    Bc7Mode {
+        subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 0,
+        endpoint_pbits: true, shared_pbits: false, index_bits: 2, secondary_index_bits: 0,
+    },
+    Bc7Mode {
+        subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 1, color_bits: 5, alpha_bits: 6,
+        endpoint_pbits: false, shared_pbits: false, index_bits: 2, secondary_index_bits: 3,
+    },
+    Bc7Mode {
+        subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 0, color_bits: 7, alpha_bits: 8,
+        endpoint_pbits: false, shared_pbits: false, index_bits: 2, secondary_index_bits: 2,
+    },
+    Bc7Mode {
+        subsets: 1, partition_bits: 0, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 7,
+        endpoint_pbits: true, shared_pbits: false, index_bits: 4, secondary_index_bits: 0,
+    },
+    Bc7Mode {
+        subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 5,
+        endpoint_pbits: true, shared_pbits: false, index_bits: 2, secondary_index_bits: 0,
+    },
+];
+
+/// Subset of each texel in the partitions of 2 subsets, one bit per texel.
+const BC7_PARTITIONS2: [u16; 64] = [
+    0xCCCC, 0x8888, 0xEEEE, 0xECC8, 0xC880, 0xFEEC, 0xFEC8, 0xEC80,
+    0xC800, 0xFFEC, 0xFE80, 0xE800, 0xFFE8, 0xFF00, 0xFFF0, 0xF000,
+    0xF710, 0x008E, 0x7100, 0x08CE, 0x008C, 0x7310, 0x3100, 0x8CCE,
+    0x088C, 0x3110, 0x6666, 0x366C, 0x17E8, 0x0FF0, 0x718E, 0x399C,
+    0xAAAA, 0xF0F0, 0x5A5A, 0x33CC, 0x3C3C, 0x55AA, 0x9696, 0xA55A,
+    0x73CE, 0x13C8, 0x324C, 0x3BDC, 0x6996, 0xC33C, 0x9966, 0x0660,
+    0x0272, 0x04E4, 0x4E40, 0x2720, 0xC936, 0x936C, 0x39C6, 0x639C,
+    0x9336, 0x9CC6, 0x817E, 0xE718, 0xCCF0, 0x0FCC, 0x7744, 0xEE22,
+];
+
+/// Subset of each texel in the partitions of 3 subsets, two bits per texel.
+const BC7_PARTITIONS3: [u32; 64] = [
+    0xAA685050, 0x6A5A5040, 0x5A5A4200, 0x5450A0A8, 0xA5A50000, 0xA0A05050, 0x5555A0A0, 0x5A5A5050,
+    0xAA550000, 0xAA555500, 0xAAAA5500, 0x90909090, 0x94949494, 0xA4A4A4A4, 0xA9A59450, 0x2A0A4250,
+    0xA5945040, 0x0A425054, 0xA5A5A500, 0x55A0A0A0, 0xA8A85454, 0x6A6A4040, 0xA4A45000, 0x1A1A0500,
+    0x0050A4A4, 0xAAA59090, 0x14696914, 0x69691400, 0xA08585A0, 0xAA821414, 0x50A4A450, 0x6A5A0200,
+    0xA9A58000, 0x5090A0A8, 0xA8A09050, 0x24242424, 0x00AA5500, 0x24924924, 0x24499224, 0x50A50A50,
+    0x500AA550, 0xAAAA4444, 0x66660000, 0xA5A0A5A0, 0x50A050A0, 0x69286928, 0x44AAAA44, 0x66666600,
+    0xAA444444, 0x54A854A8, 0x95809580, 0x96969600, 0xA85454A8, 0x80959580, 0xAA141414, 0x96960000,
+    0xAAAA1414, 0xA05050A0, 0xA0A5A5A0, 0x96000000, 0x40804080, 0xA9A8A9A8, 0xAAAAAA44, 0x2A4A5254,
+];
+
+/// Anchor texel of the second subset in the partitions of 2 subsets.
+const BC7_ANCHORS2: [u8; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15,
+    15, 2, 8, 2, 2, 8, 8, 15, 2, 8, 2, 2, 8, 8, 2, 2,
+    15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6,
+    6, 2, 6, 8, 15, 15, 2, 2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+/// Anchor texels of the second and third subsets in the partitions of 3 subsets.
+const BC7_ANCHORS3: [[u8; 2]; 64] = [
+    [3, 15], [3, 8], [15, 8], [15, 3], [8, 15], [3, 15], [15, 3], [15, 8],
+    [8, 15], [8, 15], [6, 15], [6, 15], [6, 15], [5, 15], [3, 15], [3, 8],
+    [3, 15], [3, 8], [8, 15], [15, 3], [3, 15], [3, 8], [6, 15], [10, 8],
+    [5, 3], [8, 15], [8, 6], [6, 10], [8, 15], [5, 15], [15, 10], [15, 8],
+    [8, 15], [15, 3], [3, 15], [5, 10], [6, 10], [10, 8], [8, 9], [15, 10],
+    [15, 6], [3, 15], [15, 8], [5, 15], [15, 3], [15, 6], [15, 6], [15, 8],
+    [3, 15], [15, 3], [5, 15], [5, 15], [5, 15], [8, 15], [5, 15], [10, 15],
+    [5, 15], [10, 15], [8, 15], [13, 15], [15, 3], [12, 15], [3, 15], [3, 8],
+];
+
+const BC7_WEIGHTS2: [u32; 4] = [0, 21, 43, 64];
+const BC7_WEIGHTS3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const BC7_WEIGHTS4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+fn bc7_interpolate(e0: u32, e1: u32, index: u32, bits: usize) -> u8 {
+    let weight = match bits {
+        2 => BC7_WEIGHTS2[index as usize],
+        3 => BC7_WEIGHTS3[index as usize],
+        _ => BC7_WEIGHTS4[index as usize],
+    };
+    (((64 - weight) * e0 + weight * e1 + 32) >> 6) as u8
+}
+
+/// Decode a BC7 block into `texels`.
+///
+/// Blocks with a reserved mode decode to transparent black.
+pub fn decode_bc7(block: &[u8], texels: &mut [[u8; 4]]) {
+    let mode_index = match (0 .. 8).find(|&bit| block[0] & (1 << bit) != 0) {
+        Some(mode) => mode,
+        None => {
+            for texel in texels.iter_mut() {
+                *texel = [0; 4];
+            }
+            return;
+        }
+    };
+    let mode = &BC7_MODES[mode_index];
+    let mut bits = Bits::new(block, mode_index + 1, 128);
+    let partition = bits.read(mode.partition_bits) as usize;
+    let rotation = bits.read(mode.rotation_bits);
+    let index_selection = bits.read(mode.index_selection_bits);
+
+    // Endpoints are stored channel by channel.
+    let endpoint_count = mode.subsets * 2;
+    let mut endpoints = [[0u32; 4]; 6];
+    for channel in 0 .. 3 {
+        for endpoint in endpoints[.. endpoint_count].iter_mut() {
+            endpoint[channel] = bits.read(mode.color_bits);
+        }
+    }
+    for endpoint in endpoints[.. endpoint_count].iter_mut() {
+        endpoint[3] = bits.read(mode.alpha_bits);
+    }
+    let mut pbits = [0u32; 6];
+    if mode.endpoint_pbits {
+        for pbit in pbits[.. endpoint_count].iter_mut() {
+            *pbit = bits.read(1);
+        }
+    } else if mode.shared_pbits {
+        for subset in 0 .. mode.subsets {
+            let pbit = bits.read(1);
+            pbits[2 * subset] = pbit;
+            pbits[2 * subset + 1] = pbit;
+        }
+    }
+    let has_pbits = mode.endpoint_pbits || mode.shared_pbits;
+    for (endpoint, &pbit) in endpoints[.. endpoint_count].iter_mut().zip(pbits.iter()) {
+        for channel in 0 .. 4 {
+            let field_bits = if channel < 3 { mode.color_bits } else { mode.alpha_bits };
+            if field_bits == 0 {
+                endpoint[channel] = 0xFF;
+                continue;
+            }
+            let (value, count) = if has_pbits {
+                (endpoint[channel] << 1 | pbit, field_bits + 1)
+            } else {
+                (endpoint[channel], field_bits)
+            };
+            // Replicate the high bits into the low bits of the 8 bit value.
+            endpoint[channel] = value << (8 - count) | value >> (2 * count - 8);
+        }
+    }
+
+    let subset = |texel: usize| match mode.subsets {
+        1 => 0,
+        2 => (BC7_PARTITIONS2[partition] >> texel & 1) as usize,
+        _ => (BC7_PARTITIONS3[partition] >> (2 * texel) & 3) as usize,
+    };
+    // The most significant index bit of the anchor texel of each subset is implicitly 0.
+    let is_anchor = |texel: usize| texel == 0 || match mode.subsets {
+        1 => false,
+        2 => BC7_ANCHORS2[partition] as usize == texel,
+        _ => BC7_ANCHORS3[partition].contains(&(texel as u8)),
+    };
+    let mut indices = [0u32; 16];
+    for (texel, index) in indices.iter_mut().enumerate() {
+        *index = bits.read(mode.index_bits - is_anchor(texel) as usize);
+    }
+    let mut secondary_indices = [0u32; 16];
+    if mode.secondary_index_bits != 0 {
+        for (texel, index) in secondary_indices.iter_mut().enumerate() {
+            *index = bits.read(mode.secondary_index_bits - (texel == 0) as usize);
+        }
+    }
+
+    for (texel, value) in texels.iter_mut().enumerate() {
+        let s = subset(texel);
+        let (e0, e1) = (endpoints[2 * s], endpoints[2 * s + 1]);
+        // Colors and alpha are interpolated with separate indices in modes 4 and 5,
+        // swapped if the index selection bit is set.
+        let (color_index, color_bits, alpha_index, alpha_bits) = if mode.secondary_index_bits == 0 {
+            (indices[texel], mode.index_bits, indices[texel], mode.index_bits)
+        } else if index_selection == 0 {
+            (indices[texel], mode.index_bits, secondary_indices[texel], mode.secondary_index_bits)
+        } else {
+            (secondary_indices[texel], mode.secondary_index_bits, indices[texel], mode.index_bits)
+        };
+        for channel in 0 .. 3 {
+            value[channel] = bc7_interpolate(e0[channel], e1[channel], color_index, color_bits);
+        }
+        value[3] = bc7_interpolate(e0[3], e1[3], alpha_index, alpha_bits);
+        if rotation != 0 {
+            let (channel, alpha) = (value[rotation as usize - 1], value[3]);
+            value[rotation as usize - 1] = alpha;
+            value[3] = channel;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_modes() {
+        // Red and blue endpoints in both orders, pixel `i` uses index `i % 4`.
+        let four = [0x00, 0xF8, 0x1F, 0x00, 0xE4, 0xE4, 0xE4, 0xE4];
+        let three = [0x1F, 0x00, 0x00, 0xF8, 0xE4, 0xE4, 0xE4, 0xE4];
+        let mut texels = [[0; 4]; 16];
+
+        decode_color(&four, true, true, &mut texels);
+        assert_eq!(&texels[.. 4], &[[255, 0, 0, 255], [0, 0, 255, 255], [170, 0, 85, 255], [85, 0, 170, 255]]);
+        decode_color(&three, true, true, &mut texels);
+        assert_eq!(&texels[.. 4], &[[0, 0, 255, 255], [255, 0, 0, 255], [127, 0, 127, 255], [0; 4]]);
+        decode_color(&three, true, false, &mut texels);
+        assert_eq!(texels[3], [0, 0, 0, 255]);
+        // BC2 and BC3 always interpolate 4 colors.
+        decode_color(&three, false, false, &mut texels);
+        assert_eq!(texels[3], [170, 0, 85, 255]);
+    }
+
+    #[test]
+    fn channels() {
+        // Pixel `i` uses index `i % 8`.
+        let indices = [0x88, 0xC6, 0xFA, 0x88, 0xC6, 0xFA];
+        let mut texels = [[0; 4]; 16];
+
+        let block = [&[0xFF, 0x00][..], &indices[..]].concat();
+        decode_channel(&block, false, 0, &mut texels);
+        let values: Vec<_> = texels[.. 8].iter().map(|texel| texel[0]).collect();
+        assert_eq!(values, [255, 0, 218, 182, 145, 109, 72, 36]);
+
+        let block = [&[0x00, 0xFF][..], &indices[..]].concat();
+        decode_channel(&block, false, 1, &mut texels);
+        let values: Vec<_> = texels[.. 8].iter().map(|texel| texel[1]).collect();
+        assert_eq!(values, [0, 255, 51, 102, 153, 204, 0, 255]);
+
+        // -128 is clamped to -127.
+        let block = [&[0x80, 0x7F][..], &indices[..]].concat();
+        decode_channel(&block, true, 0, &mut texels);
+        let values: Vec<_> = texels[.. 8].iter().map(|texel| texel[0] as i8).collect();
+        assert_eq!(values, [-127, 127, -76, -25, 25, 76, -127, 127]);
+    }
+
+    #[test]
+    fn explicit_alpha() {
+        let block = [0x10, 0x32, 0x54, 0x76, 0x98, 0xBA, 0xDC, 0xFE];
+        let mut texels = [[0; 4]; 16];
+        decode_explicit_alpha(&block, &mut texels);
+        for (i, texel) in texels.iter().enumerate() {
+            assert_eq!(texel[3], i as u8 * 0x11);
+        }
+    }
+
+    #[test]
+    fn bc7_mode6() {
+        // Endpoints (0, 0, 0, 254) and (255, 255, 255, 255) from 7 bit values
+        // with P-bits 0 and 1, and index `i` for pixel `i`.
+        let mut bits = [0u8; 16];
+        let mut position = 0;
+        let mut write = |value: u32, count: usize| {
+            for i in 0 .. count {
+                let bit = position + i;
+                bits[bit / 8] |= ((value >> i & 1) as u8) << (bit % 8);
+            }
+            position += count;
+        };
+        write(1 << 6, 7);
+        for _ in 0 .. 3 {
+            write(0, 7);
+            write(0x7F, 7);
+        }
+        write(0x7F, 7);
+        write(0x7F, 7);
+        write(0, 1);
+        write(1, 1);
+        write(0, 3);
+        for i in 1 .. 16 {
+            write(i, 4);
+        }
+
+        let mut texels = [[0; 4]; 16];
+        decode_bc7(&bits, &mut texels);
+        for (i, texel) in texels.iter().enumerate() {
+            let color = bc7_interpolate(0, 255, i as u32, 4);
+            assert_eq!(*texel, [color, color, color, bc7_interpolate(254, 255, i as u32, 4)]);
+        }
+        assert_eq!(texels[15], [255, 255, 255, 255]);
+
+        // Blocks without a mode bit are reserved.
+        decode_bc7(&[0; 16], &mut texels);
+        assert!(texels.iter().all(|texel| *texel == [0; 4]));
+    }
+}
@@ -0,0 +1,981 @@
+//! Transcoding of compressed textures into formats supported by the device.
+//!
+//! Asset pipelines can ship textures in one compressed format and transcode them
+//! at upload time on devices lacking support for it, e.g. ETC2 on D3D12.
+//! `format_properties` reports the properties of a format including transcoding,
+//! `upload_format` selects the format to create the image with and `transcode`
+//! converts the texel data of one image level before copying it into a buffer.
+//! With the `ring` feature, `stage_level` does all of this while staging the
+//! level for upload.
+//!
+//! ETC2 and ASTC formats are transcoded into BC1 and BC3, or decompressed into
+//! RGBA8 if no BC format is supported. ASTC blocks with HDR endpoints decode
+//! to the error color, as in the LDR profile.
+//!
+//! BC1 to BC5 and BC7 formats are decompressed into RGBA8, R8 and RG8 on
+//! devices without BC support, e.g. mobile ones. BC6H, EAC R11 and RG11
+//! formats have no 8 bit equivalent and aren't transcoded.
+//!
+//! ```no_run
+//! # extern crate gfx_backend_empty as empty;
+//! # extern crate gfx_hal;
+//! # fn main() {
+//! use gfx_hal::format::Format;
+//! use gfx_hal::transcode;
+//!
+//! # let physical_device: empty::PhysicalDevice = return;
+//! # let level: Vec<u8> = return;
+//! let format = Format::Etc2R8g8b8a8Unorm;
+//! let upload = transcode::upload_format::<empty::Backend>(&physical_device, format)
+//!     .expect("ETC2 textures can't be sampled");
+//! let data = if upload != format {
+//!     transcode::transcode(format, upload, 256, 256, &level).unwrap()
+//! } else {
+//!     level
+//! };
+//! // create an image with the `upload` format and copy `data` into it..
+//! # }
+//! ```
+
+use std::{cmp, mem};
+use std::error::Error;
+use std::fmt;
+
+use Backend;
+use adapter::PhysicalDevice;
+use format::{ChannelType, Format, ImageFeature, Properties};
+#[cfg(feature = "ring")]
+use buffer::DynamicRing;
+#[cfg(feature = "ring")]
+use command::BufferImageCopy;
+#[cfg(feature = "ring")]
+use {format, image};
+
+mod astc;
+mod bc;
+
+/// Error transcoding texel data.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TranscodeError {
+    /// The source format can't be transcoded into the destination format.
+    Unsupported {
+        /// Format of the source data.
+        src: Format,
+        /// Requested destination format.
+        dst: Format,
+    },
+    /// The size of the source data doesn't match the image extent.
+    InvalidSize {
+        /// Size of the blocks covering the image extent.
+        expected: usize,
+        /// Size of the source data.
+        actual: usize,
+    },
+}
+
+impl fmt::Display for TranscodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = self.description();
+        match *self {
+            TranscodeError::Unsupported { src, dst } => write!(f, "{}: {:?} to {:?}", description, src, dst),
+            TranscodeError::InvalidSize { expected, actual } =>
+                write!(f, "{}: expected {} bytes, got {}", description, expected, actual),
+        }
+    }
+}
+
+impl Error for TranscodeError {
+    fn description(&self) -> &str {
+        match *self {
+            TranscodeError::Unsupported { .. } => "Unsupported transcoding",
+            TranscodeError::InvalidSize { .. } => "Source data size doesn't match the extent",
+        }
+    }
+}
+
+/// Error staging an image level for upload.
+#[cfg(feature = "ring")]
+#[derive(Clone, Debug, PartialEq)]
+pub enum StagingError {
+    /// The format can't be sampled by the device, nor transcoded into one which can.
+    Unsupported(Format),
+    /// Transcoding of the texel data failed.
+    Transcode(TranscodeError),
+    /// The ring has no space left for the texel data.
+    OutOfSpace,
+}
+
+#[cfg(feature = "ring")]
+impl fmt::Display for StagingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = self.description();
+        match *self {
+            StagingError::Unsupported(format) => write!(f, "{}: {:?}", description, format),
+            StagingError::Transcode(ref err) => write!(f, "{}: {}", description, err),
+            StagingError::OutOfSpace => write!(f, "{}", description),
+        }
+    }
+}
+
+#[cfg(feature = "ring")]
+impl Error for StagingError {
+    fn description(&self) -> &str {
+        match *self {
+            StagingError::Unsupported(_) => "Format can't be sampled",
+            StagingError::Transcode(_) => "Failed to transcode the texel data",
+            StagingError::OutOfSpace => "No space left in the ring",
+        }
+    }
+}
+
+#[cfg(feature = "ring")]
+impl From<TranscodeError> for StagingError {
+    fn from(err: TranscodeError) -> Self {
+        StagingError::Transcode(err)
+    }
+}
+
+/// Transcoding targets of `format`, in order of preference.
+///
+/// Empty if `format` can't be transcoded.
+pub fn targets(format: Format) -> &'static [Format] {
+    match format {
+        Format::Etc2R8g8b8Unorm => &[Format::Bc1RgbUnorm, Format::Rgba8Unorm],
+        Format::Etc2R8g8b8Srgb => &[Format::Bc1RgbSrgb, Format::Rgba8Srgb],
+        Format::Etc2R8g8b8a1Unorm => &[Format::Bc1RgbaUnorm, Format::Rgba8Unorm],
+        Format::Etc2R8g8b8a1Srgb => &[Format::Bc1RgbaSrgb, Format::Rgba8Srgb],
+        Format::Etc2R8g8b8a8Unorm => &[Format::Bc3Unorm, Format::Rgba8Unorm],
+        Format::Etc2R8g8b8a8Srgb => &[Format::Bc3Srgb, Format::Rgba8Srgb],
+        Format::Bc1RgbUnorm | Format::Bc1RgbaUnorm | Format::Bc2Unorm | Format::Bc3Unorm | Format::Bc7Unorm =>
+            &[Format::Rgba8Unorm],
+        Format::Bc1RgbSrgb | Format::Bc1RgbaSrgb | Format::Bc2Srgb | Format::Bc3Srgb | Format::Bc7Srgb =>
+            &[Format::Rgba8Srgb],
+        Format::Bc4Unorm => &[Format::R8Unorm],
+        Format::Bc4Inorm => &[Format::R8Inorm],
+        Format::Bc5Unorm => &[Format::Rg8Unorm],
+        Format::Bc5Inorm => &[Format::Rg8Inorm],
+        Format::Astc4x4Unorm | Format::Astc5x4Unorm | Format::Astc5x5Unorm | Format::Astc6x5Unorm |
+        Format::Astc6x6Unorm | Format::Astc8x5Unorm | Format::Astc8x6Unorm | Format::Astc8x8Unorm |
+        Format::Astc10x5Unorm | Format::Astc10x6Unorm | Format::Astc10x8Unorm | Format::Astc10x10Unorm |
+        Format::Astc12x10Unorm | Format::Astc12x12Unorm => &[Format::Bc3Unorm, Format::Rgba8Unorm],
+        Format::Astc4x4Srgb | Format::Astc5x4Srgb | Format::Astc5x5Srgb | Format::Astc6x5Srgb |
+        Format::Astc6x6Srgb | Format::Astc8x5Srgb | Format::Astc8x6Srgb | Format::Astc8x8Srgb |
+        Format::Astc10x5Srgb | Format::Astc10x6Srgb | Format::Astc10x8Srgb | Format::Astc10x10Srgb |
+        Format::Astc12x10Srgb | Format::Astc12x12Srgb => &[Format::Bc3Srgb, Format::Rgba8Srgb],
+        _ => &[],
+    }
+}
+
+fn is_sampled(properties: &Properties) -> bool {
+    properties.optimal_tiling.contains(ImageFeature::SAMPLED)
+}
+
+/// Format to create sampled images of `format` with on `physical_device`.
+///
+/// Returns `format` itself if it's supported, otherwise the first supported
+/// transcoding target, or `None` if there is none.
+pub fn upload_format<B: Backend>(physical_device: &B::PhysicalDevice, format: Format) -> Option<Format> {
+    Some(format)
+        .into_iter()
+        .chain(targets(format).iter().cloned())
+        .find(|&format| is_sampled(&physical_device.format_properties(Some(format))))
+}
+
+/// Properties of `format` on `physical_device`, including transcoding.
+///
+/// If `format` can't be sampled, the properties of the transcoding target
+/// selected by `upload_format` are reported instead.
+pub fn format_properties<B: Backend>(physical_device: &B::PhysicalDevice, format: Format) -> Properties {
+    let properties = physical_device.format_properties(Some(format));
+    if is_sampled(&properties) {
+        return properties;
+    }
+    targets(format)
+        .iter()
+        .map(|&target| physical_device.format_properties(Some(target)))
+        .find(is_sampled)
+        .unwrap_or(properties)
+}
+
+/// Image level staged for upload by `stage_level`.
+#[cfg(feature = "ring")]
+#[derive(Clone, Debug)]
+pub struct StagedLevel {
+    /// Format to create the image with, the staged format or its transcoding target.
+    pub format: Format,
+    /// Copy of the staged texel data from the ring buffer into the image level.
+    pub region: BufferImageCopy,
+}
+
+/// Stage one level of an image of `format` with extent `width` x `height` in `ring`,
+/// transcoding it if `physical_device` can't sample `format`.
+///
+/// `data` is laid out as for `transcode`. Rows of blocks are padded to
+/// `Limits::min_buffer_copy_pitch_alignment`, so `ring` needs to be created
+/// with `Usage::TRANSFER_SRC` to align the copy offsets as well.
+///
+/// ```no_run
+/// # extern crate gfx_backend_empty as empty;
+/// # extern crate gfx_hal;
+/// # fn main() {
+/// use gfx_hal::buffer::DynamicRing;
+/// use gfx_hal::format::Format;
+/// use gfx_hal::transcode;
+///
+/// # let physical_device: empty::PhysicalDevice = return;
+/// # let mut ring: DynamicRing<empty::Backend> = return;
+/// # let level: Vec<u8> = return;
+/// let staged = transcode::stage_level(&physical_device, &mut ring, Format::Astc6x6Srgb, 0, 256, 256, &level)
+///     .unwrap();
+/// // create an image with `staged.format`, copy `ring.buffer()` into it with `staged.region`..
+/// # }
+/// ```
+#[cfg(feature = "ring")]
+pub fn stage_level<B: Backend>(
+    physical_device: &B::PhysicalDevice,
+    ring: &mut DynamicRing<B>,
+    format: Format,
+    level: image::Level,
+    width: u32,
+    height: u32,
+    data: &[u8],
+) -> Result<StagedLevel, StagingError> {
+    let upload = upload_format::<B>(physical_device, format).ok_or(StagingError::Unsupported(format))?;
+    let transcoded;
+    let data = if upload != format {
+        transcoded = transcode(format, upload, width, height, data)?;
+        &transcoded[..]
+    } else {
+        data
+    };
+
+    let desc = upload.base_format().0.desc();
+    let (block_width, block_height) = (desc.dim.0 as u32, desc.dim.1 as u32);
+    let (blocks_x, blocks_y) = ((width + block_width - 1) / block_width, (height + block_height - 1) / block_height);
+    let block_size = desc.bits as u64 / 8;
+    let row_size = blocks_x as u64 * block_size;
+    let expected = row_size * blocks_y as u64;
+    if data.len() as u64 != expected {
+        return Err(TranscodeError::InvalidSize { expected: expected as usize, actual: data.len() }.into());
+    }
+
+    // Block sizes and pitch alignments are powers of two, the larger one is a multiple of both.
+    let limits = physical_device.limits();
+    let alignment = cmp::max(limits.min_buffer_copy_pitch_alignment, block_size);
+    let pitch = (row_size + alignment - 1) / alignment * alignment;
+    // Offsets are aligned for copies by the ring, but not necessarily to the block size.
+    let (offset, memory) = ring
+        .allocate(pitch * blocks_y as u64 + block_size - 1)
+        .ok_or(StagingError::OutOfSpace)?;
+    let padding = (block_size - offset % block_size) % block_size;
+    for (row, src) in data.chunks(row_size as usize).enumerate() {
+        let start = (padding + row as u64 * pitch) as usize;
+        memory[start .. start + src.len()].copy_from_slice(src);
+    }
+
+    Ok(StagedLevel {
+        format: upload,
+        region: BufferImageCopy {
+            buffer_offset: offset + padding,
+            buffer_width: (pitch / block_size) as u32 * block_width,
+            buffer_height: blocks_y * block_height,
+            image_layers: image::SubresourceLayers {
+                aspects: format::Aspects::COLOR,
+                level,
+                layers: 0 .. 1,
+            },
+            image_offset: image::Offset { x: 0, y: 0, z: 0 },
+            image_extent: image::Extent { width, height, depth: 1 },
+        },
+    })
+}
+
+/// Transcode one level of an image with extent `width` x `height` from `src`
+/// into `dst`, which needs to be one of the `targets` of `src`.
+///
+/// Blocks of `data` are tightly packed, row by row.
+///
+/// # Examples
+///
+/// ```rust
+/// use gfx_hal::format::Format;
+/// use gfx_hal::transcode::transcode;
+///
+/// // Individual mode block of a single color.
+/// let block = [0xFF, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+/// let texels = transcode(Format::Etc2R8g8b8Unorm, Format::Rgba8Unorm, 4, 4, &block).unwrap();
+/// assert_eq!(texels.len(), 64);
+/// assert_eq!(&texels[.. 4], &[0xFF, 0x02, 0x02, 0xFF]);
+/// ```
+pub fn transcode(src: Format, dst: Format, width: u32, height: u32, data: &[u8]) -> Result<Vec<u8>, TranscodeError> {
+    if !targets(src).contains(&dst) {
+        return Err(TranscodeError::Unsupported { src, dst });
+    }
+
+    let desc = src.base_format().0.desc();
+    let (block_width, block_height) = (desc.dim.0 as usize, desc.dim.1 as usize);
+    let (width, height) = (width as usize, height as usize);
+    let blocks_x = (width + block_width - 1) / block_width;
+    let blocks_y = (height + block_height - 1) / block_height;
+    let block_size = desc.bits as usize / 8;
+    let expected = blocks_x * blocks_y * block_size;
+    if data.len() != expected {
+        return Err(TranscodeError::InvalidSize { expected, actual: data.len() });
+    }
+
+    let mut image = vec![[0u8; 4]; width * height];
+    let mut texels = vec![[0u8; 4]; block_width * block_height];
+    for (i, block) in data.chunks(block_size).enumerate() {
+        decode_block(src, block, block_width, block_height, &mut texels);
+        // Blocks at the right and bottom edges may exceed the extent.
+        let (block_x, block_y) = (i % blocks_x * block_width, i / blocks_x * block_height);
+        for y in block_y .. cmp::min(block_y + block_height, height) {
+            for x in block_x .. cmp::min(block_x + block_width, width) {
+                image[y * width + x] = texels[(y - block_y) * block_width + x - block_x];
+            }
+        }
+    }
+
+    Ok(match dst {
+        Format::Rgba8Unorm | Format::Rgba8Srgb => image.iter().flat_map(|texel| texel.iter().cloned()).collect(),
+        Format::R8Unorm | Format::R8Inorm => image.iter().map(|texel| texel[0]).collect(),
+        Format::Rg8Unorm | Format::Rg8Inorm => image.iter().flat_map(|texel| texel[.. 2].iter().cloned()).collect(),
+        _ => encode_bc(dst, width, height, &image),
+    })
+}
+
+/// Decode one block of `src` with a footprint of `width` x `height` texels
+/// into `texels`, in row-major order.
+fn decode_block(src: Format, block: &[u8], width: usize, height: usize, texels: &mut [[u8; 4]]) {
+    match src {
+        Format::Etc2R8g8b8a8Unorm | Format::Etc2R8g8b8a8Srgb => {
+            decode_etc2_rgb(&block[8 ..], false, texels);
+            decode_eac_alpha(&block[.. 8], texels);
+        }
+        Format::Etc2R8g8b8a1Unorm | Format::Etc2R8g8b8a1Srgb => decode_etc2_rgb(block, true, texels),
+        Format::Etc2R8g8b8Unorm | Format::Etc2R8g8b8Srgb => decode_etc2_rgb(block, false, texels),
+        Format::Bc1RgbUnorm | Format::Bc1RgbSrgb => bc::decode_color(block, true, false, texels),
+        Format::Bc1RgbaUnorm | Format::Bc1RgbaSrgb => bc::decode_color(block, true, true, texels),
+        Format::Bc2Unorm | Format::Bc2Srgb => {
+            bc::decode_color(&block[8 ..], false, false, texels);
+            bc::decode_explicit_alpha(&block[.. 8], texels);
+        }
+        Format::Bc3Unorm | Format::Bc3Srgb => {
+            bc::decode_color(&block[8 ..], false, false, texels);
+            bc::decode_channel(&block[.. 8], false, 3, texels);
+        }
+        Format::Bc4Unorm | Format::Bc4Inorm => bc::decode_channel(block, src == Format::Bc4Inorm, 0, texels),
+        Format::Bc5Unorm | Format::Bc5Inorm => {
+            bc::decode_channel(&block[.. 8], src == Format::Bc5Inorm, 0, texels);
+            bc::decode_channel(&block[8 ..], src == Format::Bc5Inorm, 1, texels);
+        }
+        Format::Bc7Unorm | Format::Bc7Srgb => bc::decode_bc7(block, texels),
+        // ASTC, the remaining sources of `targets`.
+        _ => astc::decode(block, width, height, src.base_format().1 == ChannelType::Srgb, texels),
+    }
+}
+
+/// Encode the texels of an image with extent `width` x `height` into blocks of `dst`.
+fn encode_bc(dst: Format, width: usize, height: usize, image: &[[u8; 4]]) -> Vec<u8> {
+    let (blocks_x, blocks_y) = ((width + 3) / 4, (height + 3) / 4);
+    let block_size = if dst == Format::Bc3Unorm || dst == Format::Bc3Srgb { 16 } else { 8 };
+    let mut output = Vec::with_capacity(blocks_x * blocks_y * block_size);
+    let mut texels = [[0u8; 4]; 16];
+    for block_y in 0 .. blocks_y {
+        for block_x in 0 .. blocks_x {
+            // Texels of blocks exceeding the extent repeat the edges of the image.
+            for (i, texel) in texels.iter_mut().enumerate() {
+                let x = cmp::min(block_x * 4 + i % 4, width - 1);
+                let y = cmp::min(block_y * 4 + i / 4, height - 1);
+                *texel = image[y * width + x];
+            }
+            match dst {
+                Format::Bc1RgbUnorm | Format::Bc1RgbSrgb => output.extend_from_slice(&encode_bc1(&texels, false)),
+                Format::Bc1RgbaUnorm | Format::Bc1RgbaSrgb => output.extend_from_slice(&encode_bc1(&texels, true)),
+                _ => {
+                    output.extend_from_slice(&encode_bc3_alpha(&texels));
+                    output.extend_from_slice(&encode_bc1(&texels, false));
+                }
+            }
+        }
+    }
+    output
+}
+
+/// Reads bit fields of a block, starting with the least significant bit of the first byte.
+///
+/// Bits at or past `end` are read as 0.
+struct Bits<'a> {
+    data: &'a [u8],
+    position: usize,
+    end: usize,
+}
+
+impl<'a> Bits<'a> {
+    fn new(data: &'a [u8], start: usize, end: usize) -> Self {
+        Bits { data, position: start, end }
+    }
+
+    fn read(&mut self, count: usize) -> u32 {
+        let available = cmp::min(count, self.end.saturating_sub(self.position));
+        let value = bits(self.data, self.position, available);
+        self.position += count;
+        value
+    }
+}
+
+/// Read `count` bits of `data` starting at bit `start`.
+fn bits(data: &[u8], start: usize, count: usize) -> u32 {
+    (0 .. count).fold(0, |value, i| {
+        let bit = start + i;
+        value | ((data[bit / 8] >> (bit % 8) & 1) as u32) << i
+    })
+}
+
+const ETC1_MODIFIERS: [[i32; 2]; 8] = [
+    [2, 8], [5, 17], [9, 29], [13, 42], [18, 60], [24, 80], [33, 106], [47, 183],
+];
+const ETC2_DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+const EAC_MODIFIERS: [[i32; 8]; 16] = [
+    [-3, -6, -9, -15, 2, 5, 8, 14],
+    [-3, -7, -10, -13, 2, 6, 9, 12],
+    [-2, -5, -8, -13, 1, 4, 7, 12],
+    [-2, -4, -6, -13, 1, 3, 5, 12],
+    [-3, -6, -8, -12, 2, 5, 7, 11],
+    [-3, -7, -9, -11, 2, 6, 8, 10],
+    [-4, -7, -8, -11, 3, 6, 7, 10],
+    [-3, -5, -8, -11, 2, 4, 7, 10],
+    [-2, -6, -8, -10, 1, 5, 7, 9],
+    [-2, -5, -8, -10, 1, 4, 7, 9],
+    [-2, -4, -8, -10, 1, 3, 7, 9],
+    [-2, -5, -7, -10, 1, 4, 6, 9],
+    [-3, -4, -7, -10, 2, 3, 6, 9],
+    [-1, -2, -3, -10, 0, 1, 2, 9],
+    [-4, -6, -8, -9, 3, 5, 7, 8],
+    [-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+fn clamp(value: i32) -> u8 {
+    cmp::min(cmp::max(value, 0), 255) as u8
+}
+
+fn extend4(value: u8) -> i32 {
+    (value as i32 & 0xF) * 0x11
+}
+
+fn extend5(value: u8) -> i32 {
+    let value = value as i32 & 0x1F;
+    (value << 3) | (value >> 2)
+}
+
+fn extend6(value: u8) -> i32 {
+    let value = value as i32 & 0x3F;
+    (value << 2) | (value >> 4)
+}
+
+fn extend7(value: u8) -> i32 {
+    let value = value as i32 & 0x7F;
+    (value << 1) | (value >> 6)
+}
+
+/// Sign extend the 3 bit delta in the low bits of `value`.
+fn delta3(value: u8) -> i32 {
+    ((value as i32 & 7) << 29) >> 29
+}
+
+/// Decode an ETC2 RGB block into `texels`, in row-major order.
+///
+/// Punch-through blocks use the differential bit as opaque flag.
+fn decode_etc2_rgb(block: &[u8], punch_through: bool, texels: &mut [[u8; 4]]) {
+    let indices = (block[4] as u32) << 24 | (block[5] as u32) << 16 | (block[6] as u32) << 8 | block[7] as u32;
+    // Pixel indices are stored column by column.
+    let index = |x: usize, y: usize| {
+        let i = x * 4 + y;
+        ((indices >> (16 + i) & 1) << 1 | (indices >> i & 1)) as usize
+    };
+    let differential = block[3] & 2 != 0;
+    let opaque = !punch_through || differential;
+
+    if !punch_through && !differential {
+        // Individual mode
+        let base = [
+            [extend4(block[0] >> 4), extend4(block[1] >> 4), extend4(block[2] >> 4)],
+            [extend4(block[0]), extend4(block[1]), extend4(block[2])],
+        ];
+        decode_subblocks(block, base, true, &index, texels);
+        return;
+    }
+
+    let r = (block[0] >> 3) as i32 + delta3(block[0]);
+    let g = (block[1] >> 3) as i32 + delta3(block[1]);
+    let b = (block[2] >> 3) as i32 + delta3(block[2]);
+
+    if r < 0 || r > 31 {
+        // T mode
+        let c1 = [
+            extend4((block[0] >> 1 & 0xC) | (block[0] & 3)),
+            extend4(block[1] >> 4),
+            extend4(block[1]),
+        ];
+        let c2 = [extend4(block[2] >> 4), extend4(block[2]), extend4(block[3] >> 4)];
+        let d = ETC2_DISTANCES[((block[3] >> 1 & 6) | (block[3] & 1)) as usize];
+        let paint = [c1, offset(c2, d), c2, offset(c2, -d)];
+        decode_paint_colors(paint, opaque, &index, texels);
+    } else if g < 0 || g > 31 {
+        // H mode
+        let c1 = [
+            (block[0] >> 3 & 0xF) as i32,
+            ((block[0] & 7) << 1 | (block[1] >> 4 & 1)) as i32,
+            ((block[1] & 8) | (block[1] & 3) << 1 | block[2] >> 7) as i32,
+        ];
+        let c2 = [
+            (block[2] >> 3 & 0xF) as i32,
+            ((block[2] & 7) << 1 | block[3] >> 7) as i32,
+            (block[3] >> 3 & 0xF) as i32,
+        ];
+        let order = if (c1[0] << 8 | c1[1] << 4 | c1[2]) >= (c2[0] << 8 | c2[1] << 4 | c2[2]) { 1 } else { 0 };
+        let d = ETC2_DISTANCES[((block[3] >> 2 & 1) << 2 | (block[3] & 1) << 1) as usize | order];
+        let c1 = [extend4(c1[0] as u8), extend4(c1[1] as u8), extend4(c1[2] as u8)];
+        let c2 = [extend4(c2[0] as u8), extend4(c2[1] as u8), extend4(c2[2] as u8)];
+        let paint = [offset(c1, d), offset(c1, -d), offset(c2, d), offset(c2, -d)];
+        decode_paint_colors(paint, opaque, &index, texels);
+    } else if b < 0 || b > 31 {
+        // Planar mode
+        let bits = block.iter().fold(0u64, |bits, &byte| bits << 8 | byte as u64);
+        let field = |shift: u32, width: u32| (bits >> shift & ((1 << width) - 1)) as u8;
+        let origin = [
+            extend6(field(57, 6)),
+            extend7(field(56, 1) << 6 | field(49, 6)),
+            extend6(field(48, 1) << 5 | field(43, 2) << 3 | field(39, 3)),
+        ];
+        let horizontal = [
+            extend6(field(34, 5) << 1 | field(32, 1)),
+            extend7(field(25, 7)),
+            extend6(field(19, 6)),
+        ];
+        let vertical = [extend6(field(13, 6)), extend7(field(6, 7)), extend6(field(0, 6))];
+        for y in 0 .. 4 {
+            for x in 0 .. 4 {
+                let texel = &mut texels[y * 4 + x];
+                for c in 0 .. 3 {
+                    let value = x as i32 * (horizontal[c] - origin[c]) + y as i32 * (vertical[c] - origin[c]) + 4 * origin[c] + 2;
+                    texel[c] = clamp(value >> 2);
+                }
+                texel[3] = 0xFF;
+            }
+        }
+    } else {
+        // Differential mode
+        let base = [
+            [extend5(block[0] >> 3), extend5(block[1] >> 3), extend5(block[2] >> 3)],
+            [extend5(r as u8), extend5(g as u8), extend5(b as u8)],
+        ];
+        decode_subblocks(block, base, opaque, &index, texels);
+    }
+}
+
+fn offset(color: [i32; 3], d: i32) -> [i32; 3] {
+    [color[0] + d, color[1] + d, color[2] + d]
+}
+
+/// Subblock of the texel at `x`, `y`, depending on the flip bit.
+fn subblock(block: &[u8], x: usize, y: usize) -> usize {
+    if block[3] & 1 != 0 { y / 2 } else { x / 2 }
+}
+
+/// Decode the two subblocks of individual and differential mode blocks.
+///
+/// Non-opaque punch-through blocks use the base color for index 0
+/// and transparent black for index 2.
+fn decode_subblocks<F>(block: &[u8], base: [[i32; 3]; 2], opaque: bool, index: &F, texels: &mut [[u8; 4]])
+where
+    F: Fn(usize, usize) -> usize,
+{
+    let tables = [ETC1_MODIFIERS[(block[3] >> 5) as usize], ETC1_MODIFIERS[(block[3] >> 2 & 7) as usize]];
+    for y in 0 .. 4 {
+        for x in 0 .. 4 {
+            let sub = subblock(block, x, y);
+            let modifier = match index(x, y) {
+                2 if !opaque => {
+                    texels[y * 4 + x] = [0; 4];
+                    continue;
+                }
+                0 if !opaque => 0,
+                0 => tables[sub][0],
+                1 => tables[sub][1],
+                2 => -tables[sub][0],
+                _ => -tables[sub][1],
+            };
+            let color = offset(base[sub], modifier);
+            texels[y * 4 + x] = [clamp(color[0]), clamp(color[1]), clamp(color[2]), 0xFF];
+        }
+    }
+}
+
+fn decode_paint_colors<F>(paint: [[i32; 3]; 4], opaque: bool, index: &F, texels: &mut [[u8; 4]])
+where
+    F: Fn(usize, usize) -> usize,
+{
+    for y in 0 .. 4 {
+        for x in 0 .. 4 {
+            let i = index(x, y);
+            texels[y * 4 + x] = if !opaque && i == 2 {
+                [0; 4]
+            } else {
+                [clamp(paint[i][0]), clamp(paint[i][1]), clamp(paint[i][2]), 0xFF]
+            };
+        }
+    }
+}
+
+/// Decode an EAC alpha block into the alpha channel of `texels`.
+fn decode_eac_alpha(block: &[u8], texels: &mut [[u8; 4]]) {
+    let base = block[0] as i32;
+    let multiplier = (block[1] >> 4) as i32;
+    let modifiers = EAC_MODIFIERS[(block[1] & 0xF) as usize];
+    let indices = block[2 ..].iter().fold(0u64, |bits, &byte| bits << 8 | byte as u64);
+    for y in 0 .. 4 {
+        for x in 0 .. 4 {
+            let index = (indices >> (45 - 3 * (x * 4 + y)) & 7) as usize;
+            texels[y * 4 + x][3] = clamp(base + modifiers[index] * multiplier);
+        }
+    }
+}
+
+fn pack565(color: [u8; 4]) -> u16 {
+    (color[0] as u16 >> 3) << 11 | (color[1] as u16 >> 2) << 5 | color[2] as u16 >> 3
+}
+
+fn unpack565(color: u16) -> [i32; 3] {
+    [
+        extend5((color >> 11) as u8),
+        extend6((color >> 5) as u8),
+        extend5(color as u8),
+    ]
+}
+
+fn distance(a: [i32; 3], texel: &[u8; 4]) -> i32 {
+    (0 .. 3).map(|c| (a[c] - texel[c] as i32) * (a[c] - texel[c] as i32)).sum()
+}
+
+/// Encode `texels` into a BC1 block, using the endpoints of their bounding box.
+///
+/// With `alpha`, texels with an alpha below 128 are encoded as transparent.
+fn encode_bc1(texels: &[[u8; 4]; 16], alpha: bool) -> [u8; 8] {
+    let is_transparent = |texel: &[u8; 4]| alpha && texel[3] < 0x80;
+    let mut min = [0xFFu8; 4];
+    let mut max = [0u8; 4];
+    for texel in texels.iter().filter(|texel| !is_transparent(texel)) {
+        for c in 0 .. 3 {
+            min[c] = min[c].min(texel[c]);
+            max[c] = max[c].max(texel[c]);
+        }
+    }
+    if min[0] > max[0] {
+        // All texels are transparent.
+        min = [0; 4];
+        max = [0; 4];
+    }
+    // Use the diagonal of the bounding box closest to the distribution of the
+    // colors, by flipping channels anti-correlated to the one with the widest range.
+    let axis = (0 .. 3).max_by_key(|&c| max[c] - min[c]).unwrap();
+    let mut mean = [0i32; 3];
+    for c in 0 .. 3 {
+        mean[c] = (min[c] as i32 + max[c] as i32) / 2;
+    }
+    for c in 0 .. 3 {
+        let covariance: i32 = texels
+            .iter()
+            .filter(|texel| !is_transparent(texel))
+            .map(|texel| (texel[axis] as i32 - mean[axis]) * (texel[c] as i32 - mean[c]))
+            .sum();
+        if covariance < 0 {
+            mem::swap(&mut min[c], &mut max[c]);
+        }
+    }
+
+    let transparent = texels.iter().any(|texel| is_transparent(texel));
+    let (mut c0, mut c1) = (pack565(max), pack565(min));
+    // The order of the endpoints selects the mode, with 3 colors and
+    // transparency if the first endpoint isn't greater than the second.
+    if transparent == (c0 > c1) {
+        mem::swap(&mut c0, &mut c1);
+    }
+    let (e0, e1) = (unpack565(c0), unpack565(c1));
+    let palette = if transparent || c0 == c1 {
+        vec![e0, e1, [(e0[0] + e1[0]) / 2, (e0[1] + e1[1]) / 2, (e0[2] + e1[2]) / 2]]
+    } else {
+        vec![
+            e0,
+            e1,
+            [(2 * e0[0] + e1[0]) / 3, (2 * e0[1] + e1[1]) / 3, (2 * e0[2] + e1[2]) / 3],
+            [(e0[0] + 2 * e1[0]) / 3, (e0[1] + 2 * e1[1]) / 3, (e0[2] + 2 * e1[2]) / 3],
+        ]
+    };
+
+    let mut indices = 0u32;
+    for (i, texel) in texels.iter().enumerate() {
+        let index = if is_transparent(texel) {
+            3
+        } else {
+            (0 .. palette.len()).min_by_key(|&p| distance(palette[p], texel)).unwrap()
+        };
+        indices |= (index as u32) << (2 * i);
+    }
+
+    [
+        c0 as u8, (c0 >> 8) as u8,
+        c1 as u8, (c1 >> 8) as u8,
+        indices as u8, (indices >> 8) as u8, (indices >> 16) as u8, (indices >> 24) as u8,
+    ]
+}
+
+/// Encode the alpha channel of `texels` into the alpha block of BC3.
+fn encode_bc3_alpha(texels: &[[u8; 4]; 16]) -> [u8; 8] {
+    let a0 = texels.iter().map(|texel| texel[3]).max().unwrap() as i32;
+    let a1 = texels.iter().map(|texel| texel[3]).min().unwrap() as i32;
+    // With the first endpoint greater than the second, 6 values are interpolated.
+    let palette = [
+        a0, a1,
+        (6 * a0 + a1) / 7, (5 * a0 + 2 * a1) / 7, (4 * a0 + 3 * a1) / 7,
+        (3 * a0 + 4 * a1) / 7, (2 * a0 + 5 * a1) / 7, (a0 + 6 * a1) / 7,
+    ];
+
+    let mut indices = 0u64;
+    if a0 != a1 {
+        for (i, texel) in texels.iter().enumerate() {
+            let alpha = texel[3] as i32;
+            let index = (0 .. 8).min_by_key(|&p| (palette[p] - alpha).abs()).unwrap();
+            indices |= (index as u64) << (3 * i);
+        }
+    }
+
+    [
+        a0 as u8, a1 as u8,
+        indices as u8, (indices >> 8) as u8, (indices >> 16) as u8,
+        (indices >> 24) as u8, (indices >> 32) as u8, (indices >> 40) as u8,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Pixel indices selecting paint color `x` for all texels in column `x`.
+    const COLUMN_INDICES: [u8; 4] = [0xFF, 0x00, 0xF0, 0xF0];
+
+    fn decode(src: Format, width: u32, height: u32, data: &[u8]) -> Vec<[u8; 4]> {
+        transcode(src, Format::Rgba8Unorm, width, height, data)
+            .unwrap()
+            .chunks(4)
+            .map(|texel| [texel[0], texel[1], texel[2], texel[3]])
+            .collect()
+    }
+
+    fn etc2_block(color: [u8; 4], indices: [u8; 4]) -> Vec<u8> {
+        color.iter().chain(indices.iter()).cloned().collect()
+    }
+
+    fn decode_bc1(block: &[u8]) -> [[u8; 4]; 16] {
+        let mut texels = [[0; 4]; 16];
+        bc::decode_color(block, true, true, &mut texels);
+        texels
+    }
+
+    fn decode_bc3_alpha(block: &[u8]) -> [u8; 16] {
+        let mut texels = [[0; 4]; 16];
+        bc::decode_channel(block, false, 3, &mut texels);
+        let mut alpha = [0; 16];
+        for (a, texel) in alpha.iter_mut().zip(texels.iter()) {
+            *a = texel[3];
+        }
+        alpha
+    }
+
+    fn assert_close(expected: &[u8; 4], actual: &[u8; 4], tolerance: i32) {
+        for c in 0 .. 4 {
+            assert!(
+                (expected[c] as i32 - actual[c] as i32).abs() <= tolerance,
+                "expected {:?}, got {:?}", expected, actual,
+            );
+        }
+    }
+
+    #[test]
+    fn t_mode() {
+        // Red overflows: 0 + (-1).
+        let block = etc2_block([0x07, 0x8F, 0x48, 0xC7], COLUMN_INDICES);
+        let texels = decode(Format::Etc2R8g8b8Unorm, 4, 4, &block);
+        let paint = [
+            [51, 136, 255, 255],
+            [84, 152, 220, 255],
+            [68, 136, 204, 255],
+            [52, 120, 188, 255],
+        ];
+        for y in 0 .. 4 {
+            assert_eq!(&texels[y * 4 .. y * 4 + 4], &paint);
+        }
+    }
+
+    #[test]
+    fn h_mode() {
+        // Green overflows: 3 + (-4).
+        let block = etc2_block([0x00, 0x1C, 0xAA, 0xE6], COLUMN_INDICES);
+        let texels = decode(Format::Etc2R8g8b8Unorm, 4, 4, &block);
+        let paint = [
+            [23, 40, 176, 255],
+            [0, 0, 130, 255],
+            [108, 108, 227, 255],
+            [62, 62, 181, 255],
+        ];
+        for y in 0 .. 4 {
+            assert_eq!(&texels[y * 4 .. y * 4 + 4], &paint);
+        }
+    }
+
+    #[test]
+    fn planar_mode() {
+        // Blue overflows: 0 + (-4). Red increases horizontally, blue decreases vertically.
+        let block = [0x01, 0x01, 0x04, 0x7F, 0x81, 0x00, 0x10, 0x00];
+        let texels = decode(Format::Etc2R8g8b8Unorm, 4, 4, &block);
+        let (red, blue) = ([0, 64, 128, 191], [130, 98, 65, 33]);
+        for y in 0 .. 4 {
+            for x in 0 .. 4 {
+                assert_eq!(texels[y * 4 + x], [red[x], 129, blue[y], 255]);
+            }
+        }
+    }
+
+    #[test]
+    fn punch_through() {
+        // Differential mode without the opaque bit, index 0 is the base color and 2 transparent.
+        let block = etc2_block([0x80, 0x80, 0x80, 0x00], COLUMN_INDICES);
+        let texels = decode(Format::Etc2R8g8b8a1Unorm, 4, 4, &block);
+        assert_eq!(texels[0], [132, 132, 132, 255]);
+        assert_eq!(texels[1], [140, 140, 140, 255]);
+        assert_eq!(texels[2], [0; 4]);
+        assert_eq!(texels[3], [124, 124, 124, 255]);
+    }
+
+    #[test]
+    fn eac_alpha() {
+        // Base 128, multiplier 2 and modifier table 13, pixel `i` uses modifier `i % 8`.
+        let mut block = vec![0x80, 0x2D, 0x05, 0x39, 0x77, 0x05, 0x39, 0x77];
+        block.extend(etc2_block([0x88, 0x88, 0x88, 0x00], [0; 4]));
+        let texels = decode(Format::Etc2R8g8b8a8Unorm, 4, 4, &block);
+        let alpha = [126, 124, 122, 108, 128, 130, 132, 146];
+        for y in 0 .. 4 {
+            for x in 0 .. 4 {
+                assert_eq!(texels[y * 4 + x], [138, 138, 138, alpha[(x * 4 + y) % 8]]);
+            }
+        }
+
+        // Base 250, multiplier 1 and modifier 14 of table 0 clamp.
+        let mut block = vec![0xFA, 0x10, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+        block.extend(etc2_block([0x88, 0x88, 0x88, 0x00], [0; 4]));
+        let texels = decode(Format::Etc2R8g8b8a8Unorm, 4, 4, &block);
+        assert!(texels.iter().all(|texel| texel[3] == 255));
+    }
+
+    #[test]
+    fn partial_blocks() {
+        let block = etc2_block([0x07, 0x8F, 0x48, 0xC7], COLUMN_INDICES);
+        let data = [&block[..], &block[..]].concat();
+        let texels = decode(Format::Etc2R8g8b8Unorm, 6, 2, &data);
+        assert_eq!(texels.len(), 12);
+        assert_eq!(texels[4], texels[0]);
+        assert_eq!(texels[6 + 5], [84, 152, 220, 255]);
+    }
+
+    #[test]
+    fn round_trip_bc1() {
+        // Individual mode blocks of gray with different modifiers per column.
+        let data = [
+            etc2_block([0x88, 0x88, 0x88, 0xE0], COLUMN_INDICES),
+            etc2_block([0x44, 0x44, 0x44, 0x48], [0x0F, 0xF0, 0x33, 0xCC]),
+        ].concat();
+        let texels = decode(Format::Etc2R8g8b8Unorm, 8, 4, &data);
+        let bc1 = transcode(Format::Etc2R8g8b8Unorm, Format::Bc1RgbUnorm, 8, 4, &data).unwrap();
+        assert_eq!(bc1.len(), 16);
+        for (b, block) in bc1.chunks(8).enumerate() {
+            let decoded = decode_bc1(block);
+            for y in 0 .. 4 {
+                for x in 0 .. 4 {
+                    // Colors are quantized to 565 and interpolated between the endpoints.
+                    assert_close(&texels[y * 8 + b * 4 + x], &decoded[y * 4 + x], 12);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip_bc3() {
+        let mut data = vec![0x80, 0x2D, 0x05, 0x39, 0x77, 0x05, 0x39, 0x77];
+        data.extend(etc2_block([0x88, 0x88, 0x88, 0x00], [0; 4]));
+        let texels = decode(Format::Etc2R8g8b8a8Unorm, 4, 4, &data);
+        let bc3 = transcode(Format::Etc2R8g8b8a8Unorm, Format::Bc3Unorm, 4, 4, &data).unwrap();
+        assert_eq!(bc3.len(), 16);
+        let alpha = decode_bc3_alpha(&bc3[.. 8]);
+        let colors = decode_bc1(&bc3[8 ..]);
+        for i in 0 .. 16 {
+            assert_close(&[0, 0, 0, texels[i][3]], &[0, 0, 0, alpha[i]], 3);
+            assert_close(&texels[i], &[colors[i][0], colors[i][1], colors[i][2], texels[i][3]], 4);
+        }
+    }
+
+    #[test]
+    fn bc_sources() {
+        // BC1 block of red and blue, pixel `i` uses index `i % 4`.
+        let bc1 = [0x00, 0xF8, 0x1F, 0x00, 0xE4, 0xE4, 0xE4, 0xE4];
+        let texels = decode(Format::Bc1RgbUnorm, 3, 2, &bc1);
+        assert_eq!(texels, [
+            [255, 0, 0, 255], [0, 0, 255, 255], [170, 0, 85, 255],
+            [255, 0, 0, 255], [0, 0, 255, 255], [170, 0, 85, 255],
+        ]);
+
+        // BC5 blocks of red and green ramps, decompressed into two channels.
+        let ramp = [0xFF, 0x00, 0x88, 0xC6, 0xFA, 0x88, 0xC6, 0xFA];
+        let bc5 = [&ramp[..], &ramp[..]].concat();
+        let texels = transcode(Format::Bc5Unorm, Format::Rg8Unorm, 4, 4, &bc5).unwrap();
+        assert_eq!(texels.len(), 32);
+        assert_eq!(&texels[.. 6], &[255, 255, 0, 0, 218, 218]);
+    }
+
+    #[test]
+    fn astc_sources() {
+        // Void-extent blocks of a 5x4 footprint, covering a 6x5 image with 2x2 blocks.
+        let block = |color: [u8; 4]| {
+            let mut block = vec![0xFC, 0xFD, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+            for &c in color.iter() {
+                block.extend_from_slice(&[c, c]);
+            }
+            block
+        };
+        let colors = [[0x10, 0x20, 0x30, 0xFF], [0x40, 0x50, 0x60, 0xFF], [0x70, 0x80, 0x90, 0xFF], [0; 4]];
+        let data: Vec<u8> = colors.iter().flat_map(|&color| block(color)).collect();
+        let texels = decode(Format::Astc5x4Unorm, 6, 5, &data);
+        assert_eq!(texels.len(), 30);
+        assert_eq!(texels[4], colors[0]);
+        assert_eq!(texels[5], colors[1]);
+        assert_eq!(texels[4 * 6], colors[2]);
+        assert_eq!(texels[4 * 6 + 5], colors[3]);
+
+        // Opaque blocks of a single color transcode into BC3 without loss.
+        let data = block([0xFF, 0x00, 0x00, 0xFF]);
+        let bc3 = transcode(Format::Astc4x4Unorm, Format::Bc3Unorm, 4, 4, &data).unwrap();
+        assert_eq!(decode_bc3_alpha(&bc3[.. 8]), [0xFF; 16]);
+        assert!(decode_bc1(&bc3[8 ..]).iter().all(|texel| *texel == [0xFF, 0, 0, 0xFF]));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            transcode(Format::Bc6hUfloat, Format::Rgba8Unorm, 4, 4, &[0; 16]),
+            Err(TranscodeError::Unsupported { src: Format::Bc6hUfloat, dst: Format::Rgba8Unorm }),
+        );
+        assert_eq!(
+            transcode(Format::Etc2R8g8b8Unorm, Format::Bc1RgbUnorm, 8, 4, &[0; 8]),
+            Err(TranscodeError::InvalidSize { expected: 16, actual: 8 }),
+        );
+    }
+}
@@ -49,14 +49,23 @@
 //!
 //! DOC TODO
 
-use Backend;
-use image;
+use {buffer, format, image, mapping, memory};
+use {Backend, Limits, MemoryType};
+use command::{BufferImageCopy, OneShot};
+use device::{BindError, Device, OutOfMemory};
 use format::Format;
-use queue::CommandQueue;
+use offscreen::find_memory_type;
+use pool::CommandPool;
+use pso::PipelineStage;
+use queue::{CommandQueue, Submission};
+use queue::capability::{Supports, Transfer};
 
 use std::any::Any;
 use std::borrow::{Borrow, BorrowMut};
+use std::error::Error;
+use std::fmt;
 use std::ops::Range;
+use std::os::raw::{c_ulong, c_void};
 
 /// An extent describes the size of a rectangle, such as
 /// a window or texture. It is not used for referring to a
@@ -79,6 +88,83 @@ impl From<image::Extent> for Extent2D {
     }
 }
 
+/// Platform specific handle of a native window.
+///
+/// Windowing libraries (e.g. SDL2, GLFW or Qt) expose the handles of their windows,
+/// which allows creating surfaces without backend specific glue code.
+/// Backends only support the handles of the platforms they are running on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RawWindowHandle {
+    /// X11 window, using Xlib.
+    Xlib {
+        /// Pointer to the Xlib `Display`.
+        display: *mut c_void,
+        /// Xlib `Window` id.
+        window: c_ulong,
+    },
+    /// X11 window, using XCB.
+    Xcb {
+        /// Pointer to the `xcb_connection_t`.
+        connection: *mut c_void,
+        /// `xcb_window_t` id.
+        window: u32,
+    },
+    /// Wayland surface.
+    ///
+    /// Wayland surfaces don't have a size, it's decided by the client.
+    Wayland {
+        /// Pointer to the `wl_display`.
+        display: *mut c_void,
+        /// Pointer to the `wl_surface`.
+        surface: *mut c_void,
+        /// Width of the surface.
+        width: image::Size,
+        /// Height of the surface.
+        height: image::Size,
+    },
+    /// Win32 window.
+    Windows {
+        /// Module instance handle (`HINSTANCE`) of the window.
+        hinstance: *mut c_void,
+        /// Window handle (`HWND`).
+        hwnd: *mut c_void,
+    },
+    /// macOS view.
+    MacOS {
+        /// Pointer to the `NSView`.
+        ns_view: *mut c_void,
+    },
+    /// Android window.
+    Android {
+        /// Pointer to the `ANativeWindow`.
+        native_window: *mut c_void,
+        /// Width of the window.
+        width: image::Size,
+        /// Height of the window.
+        height: image::Size,
+    },
+}
+
+/// Windows which can provide the raw handle of their native window.
+///
+/// Backends provide `Instance::create_surface_from_raw_handle`
+/// for creating surfaces from any type implementing this trait.
+///
+/// # Safety
+///
+/// The returned handle must be valid and stay valid until all
+/// surfaces created from it have been destroyed.
+pub unsafe trait HasRawWindowHandle {
+    /// Retrieve the raw handle of the native window.
+    fn raw_window_handle(&self) -> RawWindowHandle;
+}
+
+unsafe impl HasRawWindowHandle for RawWindowHandle {
+    fn raw_window_handle(&self) -> RawWindowHandle {
+        *self
+    }
+}
+
 /// Describes information about what a `Surface`'s properties are.
 /// Fetch this with `surface.capabilities_and_formats(device)`.
 #[derive(Debug, Clone)]
@@ -285,6 +371,191 @@ pub enum Backbuffer<B: Backend> {
     Framebuffer(B::Framebuffer),
 }
 
+/// Presentation timing of a swapchain, see `Swapchain::frame_statistics`.
+///
+/// Refresh counts are the number of vertical blanks of the display and wrap around.
+/// Times are in nanoseconds of a monotonic clock, e.g. `QueryPerformanceCounter`
+/// on Windows, and only comparable to other times of the same swapchain.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameStatistics {
+    /// Number of presents processed by the presentation engine.
+    pub present_count: u32,
+    /// Refresh count at which the last processed present was displayed.
+    pub present_refresh_count: u32,
+    /// Refresh count of the vertical blank at `sync_time`.
+    pub sync_refresh_count: u32,
+    /// Time of the most recent vertical blank.
+    pub sync_time: u64,
+    /// Duration of a refresh cycle, if known.
+    pub refresh_duration: Option<u64>,
+}
+
+impl FrameStatistics {
+    /// Number of refresh cycles missed between the presents of `previous` and `self`,
+    /// if each frame is supposed to be displayed for `interval` refresh cycles.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use gfx_hal::window::FrameStatistics;
+    ///
+    /// let previous = FrameStatistics { present_count: 10, present_refresh_count: 100, ..Default::default() };
+    /// let current = FrameStatistics { present_count: 12, present_refresh_count: 103, ..Default::default() };
+    /// assert_eq!(current.missed_refreshes(&previous, 1), 1);
+    /// ```
+    pub fn missed_refreshes(&self, previous: &FrameStatistics, interval: u32) -> u32 {
+        let presents = self.present_count.wrapping_sub(previous.present_count);
+        let refreshes = self.present_refresh_count.wrapping_sub(previous.present_refresh_count);
+        refreshes.saturating_sub(presents.saturating_mul(interval))
+    }
+
+    /// Predicted display time of the frame presented `frames_ahead` presents after
+    /// the last processed one, displaying each frame for `interval` refresh cycles.
+    ///
+    /// Returns `None` if the refresh duration is unknown.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use gfx_hal::window::FrameStatistics;
+    ///
+    /// let stats = FrameStatistics {
+    ///     present_count: 10,
+    ///     present_refresh_count: 100,
+    ///     sync_refresh_count: 101,
+    ///     sync_time: 1_000_000,
+    ///     refresh_duration: Some(16_000_000),
+    /// };
+    /// assert_eq!(stats.predicted_present_time(2, 1), Some(17_000_000));
+    /// // Frames can't be displayed before the next vertical blank.
+    /// assert_eq!(stats.predicted_present_time(0, 1), Some(17_000_000));
+    /// ```
+    pub fn predicted_present_time(&self, frames_ahead: u32, interval: u32) -> Option<u64> {
+        self.refresh_duration.map(|refresh_duration| {
+            let target = self.present_refresh_count.wrapping_add(frames_ahead.saturating_mul(interval));
+            let refreshes = (target.wrapping_sub(self.sync_refresh_count) as i32).max(1);
+            self.sync_time + refreshes as u64 * refresh_duration
+        })
+    }
+}
+
+/// Error creating a swapchain.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CreationError {
+    /// Out of either host or device memory.
+    OutOfMemory,
+    /// The color format of the swapchain images isn't supported.
+    UnsupportedFormat(Format),
+    /// The surface or presentation engine rejected the swapchain.
+    InitializationFailed,
+}
+
+impl fmt::Display for CreationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = self.description();
+        match *self {
+            CreationError::UnsupportedFormat(format) => write!(f, "{}: {:?}", description, format),
+            _ => write!(f, "{}", description),
+        }
+    }
+}
+
+impl Error for CreationError {
+    fn description(&self) -> &str {
+        match *self {
+            CreationError::OutOfMemory => "Out of memory",
+            CreationError::UnsupportedFormat(_) => "Unsupported swapchain image format",
+            CreationError::InitializationFailed => "Failed to create the swapchain",
+        }
+    }
+}
+
+impl From<OutOfMemory> for CreationError {
+    fn from(_: OutOfMemory) -> Self {
+        CreationError::OutOfMemory
+    }
+}
+
+/// Contents of a backbuffer image read back into host memory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageData {
+    /// Format of the texels.
+    pub format: Format,
+    /// Extent of the image.
+    pub extent: Extent2D,
+    /// Texel data, rows of texels are tightly packed.
+    pub data: Vec<u8>,
+}
+
+/// Error reading back a backbuffer image.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReadbackError {
+    /// The swapchain doesn't expose its backbuffer images.
+    OpaqueBackbuffer,
+    /// Readback buffer creation failed.
+    Buffer(buffer::CreationError),
+    /// No host visible memory type satisfies the requirements of the readback buffer.
+    NoMemoryType,
+    /// Out of memory.
+    OutOfMemory,
+    /// Binding of the readback buffer to memory failed.
+    Bind(BindError),
+    /// Mapping of the readback memory failed.
+    Mapping(mapping::Error),
+    /// Waiting for the copy into the readback buffer failed.
+    Wait,
+}
+
+impl fmt::Display for ReadbackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = self.description();
+        match *self {
+            ReadbackError::Buffer(ref err) => write!(f, "{}: {}", description, err),
+            ReadbackError::Bind(ref err) => write!(f, "{}: {}", description, err),
+            ReadbackError::Mapping(ref err) => write!(f, "{}: {:?}", description, err),
+            _ => write!(f, "{}", description),
+        }
+    }
+}
+
+impl Error for ReadbackError {
+    fn description(&self) -> &str {
+        match *self {
+            ReadbackError::OpaqueBackbuffer => "Backbuffer images of the swapchain are not accessible",
+            ReadbackError::Buffer(_) => "Failed to create a readback buffer",
+            ReadbackError::NoMemoryType => "No memory type satisfies the readback buffer requirements",
+            ReadbackError::OutOfMemory => "Out of memory",
+            ReadbackError::Bind(_) => "Failed to bind the readback buffer to memory",
+            ReadbackError::Mapping(_) => "Failed to map the readback memory",
+            ReadbackError::Wait => "Failed to wait for the readback copy",
+        }
+    }
+}
+
+impl From<buffer::CreationError> for ReadbackError {
+    fn from(err: buffer::CreationError) -> Self {
+        ReadbackError::Buffer(err)
+    }
+}
+
+impl From<OutOfMemory> for ReadbackError {
+    fn from(_: OutOfMemory) -> Self {
+        ReadbackError::OutOfMemory
+    }
+}
+
+impl From<BindError> for ReadbackError {
+    fn from(err: BindError) -> Self {
+        ReadbackError::Bind(err)
+    }
+}
+
+impl From<mapping::Error> for ReadbackError {
+    fn from(err: mapping::Error) -> Self {
+        ReadbackError::Mapping(err)
+    }
+}
+
 /// The `Swapchain` is the backend representation of the surface.
 /// It consists of multiple buffers, which will be presented on the surface.
 pub trait Swapchain<B: Backend>: Any + Send + Sync {
@@ -304,6 +575,31 @@ pub trait Swapchain<B: Backend>: Any + Send + Sync {
     /// ```
     fn acquire_frame(&mut self, sync: FrameSync<B>) -> Frame;
 
+    /// Query the presentation timing of the swapchain, e.g. for pacing frames
+    /// or detecting missed vertical blanks.
+    ///
+    /// Returns `None` if the backend doesn't support it or no frame
+    /// has been displayed yet.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # extern crate gfx_backend_empty as empty;
+    /// # extern crate gfx_hal;
+    /// # fn main() {
+    /// use gfx_hal::Swapchain;
+    ///
+    /// # let swapchain: empty::Swapchain = return;
+    /// if let Some(stats) = swapchain.frame_statistics() {
+    ///     let next_present = stats.predicted_present_time(1, 1);
+    ///     println!("next frame is displayed at {:?}", next_present);
+    /// }
+    /// # }
+    /// ```
+    fn frame_statistics(&self) -> Option<FrameStatistics> {
+        None
+    }
+
     /// Present one acquired frame in FIFO order.
     ///
     /// # Safety
@@ -329,4 +625,188 @@ pub trait Swapchain<B: Backend>: Any + Send + Sync {
     {
         present_queue.present(Some(self), wait_semaphores)
     }
+
+    /// Read back the contents of a presented frame, e.g. for taking screenshots.
+    ///
+    /// Allocates a temporary readback buffer, records the transition of the backbuffer
+    /// image from `Present` layout into `TransferSrcOptimal` and back around the copy,
+    /// submits the commands to `queue` and blocks until they have finished execution.
+    ///
+    /// The backbuffer images must have been created with `TRANSFER_SRC` usage,
+    /// `format` and `extent` must match the configuration of the swapchain.
+    /// The rows of the readback buffer are aligned to the `min_buffer_copy_pitch_alignment`
+    /// of `limits`, the returned rows are tightly packed.
+    /// The command buffer is acquired from `command_pool`, which needs to be reset
+    /// by the caller.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # extern crate gfx_backend_empty as empty;
+    /// # extern crate gfx_hal;
+    /// # fn main() {
+    /// use gfx_hal::{Backbuffer, CommandPool, CommandQueue, Graphics, Limits, MemoryType, Swapchain};
+    /// use gfx_hal::format::Format;
+    /// use gfx_hal::window::Extent2D;
+    ///
+    /// # let swapchain: empty::Swapchain = return;
+    /// # let device: empty::Device = return;
+    /// # let memory_types: Vec<MemoryType> = return;
+    /// # let limits: Limits = return;
+    /// # let mut queue: CommandQueue<empty::Backend, Graphics> = return;
+    /// # let mut command_pool: CommandPool<empty::Backend, Graphics> = return;
+    /// # let backbuffer: Backbuffer<empty::Backend> = return;
+    /// # let frame: gfx_hal::Frame = return;
+    /// let extent = Extent2D { width: 1280, height: 720 };
+    /// let screenshot = swapchain.read_back(
+    ///     &device, &memory_types, &limits, &mut queue, &mut command_pool,
+    ///     &backbuffer, &frame, Format::Bgra8Unorm, extent,
+    /// ).unwrap();
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "cargo-clippy", allow(too_many_arguments))]
+    fn read_back<C>(
+        &self,
+        device: &B::Device,
+        memory_types: &[MemoryType],
+        limits: &Limits,
+        queue: &mut CommandQueue<B, C>,
+        command_pool: &mut CommandPool<B, C>,
+        backbuffer: &Backbuffer<B>,
+        frame: &Frame,
+        format: Format,
+        extent: Extent2D,
+    ) -> Result<ImageData, ReadbackError>
+    where
+        Self: Sized,
+        C: Supports<Transfer>,
+    {
+        let image = match *backbuffer {
+            Backbuffer::Images(ref images) => &images[frame.id()],
+            Backbuffer::Framebuffer(_) => return Err(ReadbackError::OpaqueBackbuffer),
+        };
+
+        let texel_size = format.base_format().0.desc().bits as buffer::Offset / 8;
+        let row_alignment_mask = limits.min_buffer_copy_pitch_alignment - 1;
+        let row_pitch = (extent.width as buffer::Offset * texel_size + row_alignment_mask) & !row_alignment_mask;
+        let size = row_pitch * extent.height as buffer::Offset;
+
+        let unbound = device.create_buffer(size, buffer::Usage::TRANSFER_DST)?;
+        let requirements = device.get_buffer_requirements(&unbound);
+        // Prefer cached memory, as the contents are only read by the host.
+        let memory_type = find_memory_type(
+                memory_types,
+                requirements.type_mask,
+                memory::Properties::CPU_VISIBLE | memory::Properties::CPU_CACHED,
+            )
+            .or_else(|| find_memory_type(memory_types, requirements.type_mask, memory::Properties::CPU_VISIBLE))
+            .ok_or(ReadbackError::NoMemoryType)?;
+        let buffer_memory = device.allocate_memory(memory_type, requirements.size)?;
+        let readback_buffer = match device.bind_buffer_memory(&buffer_memory, 0, unbound) {
+            Ok(buffer) => buffer,
+            Err(err) => {
+                device.free_memory(buffer_memory);
+                return Err(err.into());
+            }
+        };
+
+        let range = image::SubresourceRange {
+            aspects: format::Aspects::COLOR,
+            levels: 0 .. 1,
+            layers: 0 .. 1,
+        };
+        let submit = {
+            let mut cmd_buffer = command_pool.acquire_command_buffer::<OneShot>(false);
+            cmd_buffer.pipeline_barrier(
+                PipelineStage::COLOR_ATTACHMENT_OUTPUT .. PipelineStage::TRANSFER,
+                memory::Dependencies::empty(),
+                &[memory::Barrier::Image {
+                    states: (image::Access::COLOR_ATTACHMENT_WRITE, image::Layout::Present) ..
+                        (image::Access::TRANSFER_READ, image::Layout::TransferSrcOptimal),
+                    target: image,
+                    range: range.clone(),
+                    families: None,
+                }],
+            );
+            cmd_buffer.copy_image_to_buffer(
+                image,
+                image::Layout::TransferSrcOptimal,
+                &readback_buffer,
+                &[BufferImageCopy {
+                    buffer_offset: 0,
+                    buffer_width: (row_pitch / texel_size) as u32,
+                    buffer_height: extent.height,
+                    image_layers: image::SubresourceLayers {
+                        aspects: format::Aspects::COLOR,
+                        level: 0,
+                        layers: 0 .. 1,
+                    },
+                    image_offset: image::Offset { x: 0, y: 0, z: 0 },
+                    image_extent: image::Extent {
+                        width: extent.width,
+                        height: extent.height,
+                        depth: 1,
+                    },
+                }],
+            );
+            // Return the image to the presentable layout for the next acquisition.
+            cmd_buffer.pipeline_barrier(
+                PipelineStage::TRANSFER .. PipelineStage::BOTTOM_OF_PIPE,
+                memory::Dependencies::empty(),
+                &[memory::Barrier::Image {
+                    states: (image::Access::TRANSFER_READ, image::Layout::TransferSrcOptimal) ..
+                        (image::Access::empty(), image::Layout::Present),
+                    target: image,
+                    range,
+                    families: None,
+                }],
+            );
+            cmd_buffer.pipeline_barrier(
+                PipelineStage::TRANSFER .. PipelineStage::HOST,
+                memory::Dependencies::empty(),
+                &[memory::Barrier::Buffer {
+                    states: buffer::Access::TRANSFER_WRITE .. buffer::Access::HOST_READ,
+                    target: &readback_buffer,
+                    families: None,
+                }],
+            );
+            cmd_buffer.finish()
+        };
+
+        let fence = device.create_fence(false);
+        let submission = Submission::new()
+            .promote::<C>()
+            .submit(Some(submit));
+        queue.submit::<C>(submission, Some(&fence));
+        let finished = device.wait_for_fence(&fence, !0);
+        device.destroy_fence(fence);
+
+        let data = if finished {
+            let row_size = (extent.width as buffer::Offset * texel_size) as usize;
+            device
+                .acquire_mapping_reader::<u8>(&buffer_memory, 0 .. size)
+                .map(|reader| {
+                    let mut data = Vec::with_capacity(row_size * extent.height as usize);
+                    for row in reader.chunks(row_pitch as usize) {
+                        data.extend_from_slice(&row[.. row_size]);
+                    }
+                    device.release_mapping_reader(reader);
+                    data
+                })
+                .map_err(ReadbackError::from)
+        } else {
+            Err(ReadbackError::Wait)
+        };
+        // Leak the readback buffer if waiting failed, the device may still write to it.
+        if finished {
+            device.destroy_buffer(readback_buffer);
+            device.free_memory(buffer_memory);
+        }
+
+        Ok(ImageData {
+            format,
+            extent,
+            data: data?,
+        })
+    }
 }
@@ -279,7 +279,11 @@ impl<B: Backend> Device<B> {
             }
         }).collect::<Vec<_>>();
 
-        let mut pool = self.raw.create_descriptor_pool(count, &ranges[..]);
+        let mut pool = self.raw.create_descriptor_pool(
+            count,
+            &ranges[..],
+            hal::pso::DescriptorPoolCreateFlags::empty(),
+        );
         let sets = {
             let layout_refs = (0..count).map(|_| layout.resource());
             pool.allocate_sets(layout_refs)
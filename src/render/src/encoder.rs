@@ -231,6 +231,7 @@ impl<'a, B: Backend, C> Encoder<'a, B, C>
                 levels: 0 .. num_levels,
                 layers: 0 .. num_layers,
             },
+            families: None,
         }
     }
 
@@ -279,6 +280,7 @@ impl<'a, B: Backend, C> Encoder<'a, B, C>
             Some(Barrier::Buffer {
                 states: state..next,
                 target: buffer.resource(),
+                families: None,
             })
         } else {
             None
@@ -302,6 +304,7 @@ impl<'a, B: Backend, C> Encoder<'a, B, C>
                     levels: level .. (level+1),
                     layers: layer .. (layer+1),
                 },
+                families: None,
             })
         } else {
             None
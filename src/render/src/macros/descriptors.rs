@@ -48,6 +48,9 @@ macro_rules! gfx_descriptors {
                             count: <$bind as pso::BindDesc>::COUNT as _,
                             // TODO: specify stage
                             stage_flags: hal::pso::ShaderStageFlags::all(),
+                            update_frequency: None,
+                            rasterizer_ordered: false,
+                            immutable_samplers: Vec::new(),
                         });
                     })*
                     bindings
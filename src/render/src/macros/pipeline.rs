@@ -66,6 +66,8 @@ macro_rules! gfx_graphics_pipeline {
                             depth_stencil: None, //TODO
                             inputs: &[],
                             preserves: &[],
+                            view_mask: 0,
+                            correlation_mask: 0,
                         };
 
                         device.create_render_pass_raw(&attachments[..], &[subpass], &[])
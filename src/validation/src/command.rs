@@ -0,0 +1,719 @@
+use std::borrow::Borrow;
+use std::ops::Range;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use hal::{self, accel_struct, buffer, command as com, image, memory, pass, pso, query};
+use hal::command::RawLevel;
+use hal::pool::CommandPoolCreateFlags;
+
+use conv;
+use error::ValidationError;
+use state::{self, CommandState};
+use {Backend, Buffer, CommandSignature, Image, QueryKind, QueryPool, RenderPass, Reporter};
+
+/// Validating command pool.
+pub struct CommandPool<B: hal::Backend> {
+    pub(crate) raw: B::CommandPool,
+    reset_individual: bool,
+    // Incremented on every pool reset, invalidating previous recordings.
+    epoch: Arc<AtomicUsize>,
+    reporter: Reporter,
+}
+
+impl<B: hal::Backend> CommandPool<B> {
+    pub(crate) fn new(raw: B::CommandPool, flags: CommandPoolCreateFlags, reporter: Reporter) -> Self {
+        CommandPool {
+            raw,
+            reset_individual: flags.contains(CommandPoolCreateFlags::RESET_INDIVIDUAL),
+            epoch: Arc::new(AtomicUsize::new(0)),
+            reporter,
+        }
+    }
+}
+
+impl<B: hal::Backend> hal::pool::RawCommandPool<Backend<B>> for CommandPool<B> {
+    fn reset(&mut self) {
+        self.epoch.fetch_add(1, Ordering::Relaxed);
+        self.raw.reset()
+    }
+
+    fn trim(&mut self) {
+        self.raw.trim()
+    }
+
+    fn allocate(&mut self, num: usize, level: RawLevel) -> Vec<CommandBuffer<B>> {
+        self.raw
+            .allocate(num, level)
+            .into_iter()
+            .map(|raw| CommandBuffer {
+                raw,
+                state: CommandState::new(level, self.reset_individual),
+                epoch: self.epoch.clone(),
+                reporter: self.reporter.clone(),
+            })
+            .collect()
+    }
+
+    unsafe fn free(&mut self, buffers: Vec<CommandBuffer<B>>) {
+        self.raw.free(buffers.into_iter().map(|buffer| buffer.raw).collect())
+    }
+}
+
+/// Validating command buffer.
+#[derive(Clone)]
+pub struct CommandBuffer<B: hal::Backend> {
+    raw: B::CommandBuffer,
+    state: CommandState,
+    epoch: Arc<AtomicUsize>,
+    reporter: Reporter,
+}
+
+impl<B: hal::Backend> CommandBuffer<B> {
+    /// Get the wrapped command buffer.
+    pub fn raw(&self) -> &B::CommandBuffer {
+        &self.raw
+    }
+
+    pub(crate) fn is_executable(&self) -> bool {
+        self.state.is_executable(self.epoch.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn state(&self) -> &CommandState {
+        &self.state
+    }
+
+    // Record a reference to a buffer, returning the wrapped one.
+    fn buffer<'b>(&mut self, buffer: &'b Buffer<B>) -> &'b B::Buffer {
+        self.state.use_resource(buffer.id, "buffer");
+        &buffer.raw
+    }
+
+    fn image<'b>(&mut self, image: &'b Image<B>) -> &'b B::Image {
+        self.state.use_resource(image.id, "image");
+        &image.raw
+    }
+
+    fn check(&self, result: Result<(), ValidationError>) {
+        self.reporter.check(result)
+    }
+
+    fn check_query(&self, command: &'static str, pool: &QueryPool<B>, timestamp: bool) {
+        if (pool.kind == QueryKind::Timestamp) != timestamp {
+            self.check(Err(ValidationError::QueryTypeMismatch { command, pool: pool.kind.name() }));
+        }
+    }
+
+    fn check_clear_layout(&self, command: &'static str, layout: image::Layout) {
+        self.check(self.state.outside_pass(command));
+        match layout {
+            image::Layout::General | image::Layout::TransferDstOptimal => {}
+            _ => self.check(Err(ValidationError::InvalidLayout { command })),
+        }
+    }
+}
+
+impl<B: hal::Backend> com::RawCommandBuffer<Backend<B>> for CommandBuffer<B> {
+    fn begin(&mut self, flags: com::CommandBufferFlags, info: com::CommandBufferInheritanceInfo<Backend<B>>) {
+        let subpass = info.subpass
+            .as_ref()
+            .map(|subpass| (subpass.index, subpass.main_pass.subpasses));
+        let epoch = self.epoch.load(Ordering::Relaxed);
+        let result = self.state.begin(flags, subpass, epoch);
+        self.check(result);
+        self.raw.begin(flags, conv::inheritance_info(&info))
+    }
+
+    fn finish(&mut self) -> Result<(), com::RecordingError> {
+        let result = self.state.finish();
+        self.check(result);
+        self.raw.finish()
+    }
+
+    fn reset(&mut self, release_resources: bool) {
+        let result = self.state.reset();
+        self.check(result);
+        self.raw.reset(release_resources)
+    }
+
+    fn pipeline_barrier<'a, T>(
+        &mut self,
+        stages: Range<pso::PipelineStage>,
+        dependencies: memory::Dependencies,
+        barriers: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<memory::Barrier<'a, Backend<B>>>,
+    {
+        self.check(self.state.recording("pipeline_barrier"));
+        self.check(state::check_barrier_stages(&stages));
+        let barriers = barriers.into_iter().collect::<Vec<_>>();
+        for barrier in &barriers {
+            match *barrier.borrow() {
+                memory::Barrier::Buffer { target, .. } => self.state.use_resource(target.id, "buffer"),
+                memory::Barrier::Image { ref states, target, ref range, .. } => {
+                    let result = self.state.image_barrier(target.id, target.attachment, states, range);
+                    self.check(result);
+                }
+                memory::Barrier::AllBuffers(_) | memory::Barrier::AllImages(_) => {}
+            }
+        }
+        let barriers = barriers
+            .iter()
+            .map(|barrier| conv::barrier(barrier.borrow()))
+            .collect::<Vec<_>>();
+        self.raw.pipeline_barrier(stages, dependencies, barriers)
+    }
+
+    fn fill_buffer(
+        &mut self,
+        buffer: &Buffer<B>,
+        range: Range<buffer::Offset>,
+        data: u32,
+    ) {
+        self.check(self.state.outside_pass("fill_buffer"));
+        if range.start % 4 != 0 {
+            self.check(Err(ValidationError::InvalidRange {
+                command: "fill_buffer",
+                reason: "the offset must be a multiple of 4",
+            }));
+        }
+        if range.start > range.end {
+            self.check(Err(ValidationError::InvalidRange {
+                command: "fill_buffer",
+                reason: "the range must not end before its start",
+            }));
+        }
+        let buffer = self.buffer(buffer);
+        self.raw.fill_buffer(buffer, range, data)
+    }
+
+    fn update_buffer(
+        &mut self,
+        buffer: &Buffer<B>,
+        offset: buffer::Offset,
+        data: &[u8],
+    ) {
+        self.check(self.state.outside_pass("update_buffer"));
+        if offset % 4 != 0 || data.len() % 4 != 0 {
+            self.check(Err(ValidationError::InvalidRange {
+                command: "update_buffer",
+                reason: "the offset and data size must be multiples of 4",
+            }));
+        }
+        let buffer = self.buffer(buffer);
+        self.raw.update_buffer(buffer, offset, data)
+    }
+
+    fn clear_color_image_raw(
+        &mut self,
+        image: &Image<B>,
+        layout: image::Layout,
+        range: image::SubresourceRange,
+        value: com::ClearColorRaw,
+    ) {
+        self.check_clear_layout("clear_color_image", layout);
+        let image = self.image(image);
+        self.raw.clear_color_image_raw(image, layout, range, value)
+    }
+
+    fn clear_depth_stencil_image_raw(
+        &mut self,
+        image: &Image<B>,
+        layout: image::Layout,
+        range: image::SubresourceRange,
+        value: com::ClearDepthStencilRaw,
+    ) {
+        self.check_clear_layout("clear_depth_stencil_image", layout);
+        let image = self.image(image);
+        self.raw.clear_depth_stencil_image_raw(image, layout, range, value)
+    }
+
+    fn clear_attachments<T, U>(&mut self, clears: T, rects: U)
+    where
+        T: IntoIterator,
+        T::Item: Borrow<com::AttachmentClear>,
+        U: IntoIterator,
+        U::Item: Borrow<pso::ClearRect>,
+    {
+        self.check(self.state.inside_pass("clear_attachments"));
+        let rects = rects
+            .into_iter()
+            .map(|rect| rect.borrow().clone())
+            .collect::<Vec<_>>();
+        if rects.iter().any(|rect| rect.layers.start >= rect.layers.end) {
+            self.check(Err(ValidationError::InvalidRange {
+                command: "clear_attachments",
+                reason: "the rects must cover at least one layer",
+            }));
+        }
+        self.raw.clear_attachments(clears, rects)
+    }
+
+    fn resolve_image<T>(
+        &mut self,
+        src: &Image<B>,
+        src_layout: image::Layout,
+        dst: &Image<B>,
+        dst_layout: image::Layout,
+        regions: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<com::ImageResolve>,
+    {
+        self.check(self.state.outside_pass("resolve_image"));
+        let (src, dst) = (self.image(src), self.image(dst));
+        self.raw.resolve_image(src, src_layout, dst, dst_layout, regions)
+    }
+
+    fn blit_image<T>(
+        &mut self,
+        src: &Image<B>,
+        src_layout: image::Layout,
+        dst: &Image<B>,
+        dst_layout: image::Layout,
+        filter: image::Filter,
+        regions: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<com::ImageBlit>,
+    {
+        self.check(self.state.outside_pass("blit_image"));
+        let (src, dst) = (self.image(src), self.image(dst));
+        self.raw.blit_image(src, src_layout, dst, dst_layout, filter, regions)
+    }
+
+    fn bind_index_buffer(&mut self, ibv: buffer::IndexBufferView<Backend<B>>) {
+        self.check(self.state.recording("bind_index_buffer"));
+        self.buffer(ibv.buffer);
+        self.raw.bind_index_buffer(conv::index_buffer_view(ibv))
+    }
+
+    fn bind_vertex_buffers(&mut self, vbs: pso::VertexBufferSet<Backend<B>>) {
+        self.check(self.state.recording("bind_vertex_buffers"));
+        for &(buffer, _) in &vbs.0 {
+            self.buffer(buffer);
+        }
+        self.raw.bind_vertex_buffers(conv::vertex_buffers(&vbs))
+    }
+
+    fn set_viewports<T>(&mut self, first_viewport: u32, viewports: T)
+    where
+        T: IntoIterator,
+        T::Item: Borrow<pso::Viewport>,
+    {
+        self.check(self.state.recording("set_viewports"));
+        self.raw.set_viewports(first_viewport, viewports)
+    }
+
+    fn set_scissors<T>(&mut self, first_scissor: u32, rects: T)
+    where
+        T: IntoIterator,
+        T::Item: Borrow<pso::Rect>,
+    {
+        self.check(self.state.recording("set_scissors"));
+        self.raw.set_scissors(first_scissor, rects)
+    }
+
+    fn set_stencil_reference(&mut self, front: pso::StencilValue, back: pso::StencilValue) {
+        self.check(self.state.recording("set_stencil_reference"));
+        self.raw.set_stencil_reference(front, back)
+    }
+
+    fn set_blend_constants(&mut self, color: pso::ColorValue) {
+        self.check(self.state.recording("set_blend_constants"));
+        self.raw.set_blend_constants(color)
+    }
+
+    fn set_depth_bounds(&mut self, bounds: Range<f32>) {
+        self.check(self.state.recording("set_depth_bounds"));
+        self.raw.set_depth_bounds(bounds)
+    }
+
+    fn set_line_width(&mut self, width: f32) {
+        self.check(self.state.recording("set_line_width"));
+        self.raw.set_line_width(width)
+    }
+
+    fn begin_render_pass_raw<T>(
+        &mut self,
+        render_pass: &RenderPass<B>,
+        framebuffer: &B::Framebuffer,
+        render_area: pso::Rect,
+        clear_values: T,
+        first_subpass: com::SubpassContents,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<com::ClearValueRaw>,
+    {
+        let inline = is_inline(&first_subpass);
+        let result = self.state.begin_render_pass(render_pass.subpasses, inline);
+        self.check(result);
+        self.raw.begin_render_pass_raw(&render_pass.raw, framebuffer, render_area, clear_values, first_subpass)
+    }
+
+    fn next_subpass(&mut self, contents: com::SubpassContents) {
+        let result = self.state.next_subpass(is_inline(&contents));
+        self.check(result);
+        self.raw.next_subpass(contents)
+    }
+
+    fn end_render_pass(&mut self) {
+        let result = self.state.end_render_pass();
+        self.check(result);
+        self.raw.end_render_pass()
+    }
+
+    fn begin_rendering<'a, T>(
+        &mut self,
+        render_area: pso::Rect,
+        colors: T,
+        depth_stencil: Option<pass::RenderingAttachment<'a, Backend<B>>>,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<pass::RenderingAttachment<'a, Backend<B>>>,
+    {
+        let result = self.state.begin_rendering();
+        self.check(result);
+        let colors = colors
+            .into_iter()
+            .map(|attachment| conv::rendering_attachment(attachment.borrow()))
+            .collect::<Vec<_>>();
+        self.raw.begin_rendering(render_area, colors, depth_stencil.as_ref().map(conv::rendering_attachment))
+    }
+
+    fn end_rendering(&mut self) {
+        let result = self.state.end_rendering();
+        self.check(result);
+        self.raw.end_rendering()
+    }
+
+    fn bind_graphics_pipeline(&mut self, pipeline: &B::GraphicsPipeline) {
+        let result = self.state.bind_graphics_pipeline();
+        self.check(result);
+        self.raw.bind_graphics_pipeline(pipeline)
+    }
+
+    fn bind_graphics_descriptor_sets<T>(
+        &mut self,
+        layout: &B::PipelineLayout,
+        first_set: usize,
+        sets: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<B::DescriptorSet>,
+    {
+        self.check(self.state.recording("bind_graphics_descriptor_sets"));
+        self.raw.bind_graphics_descriptor_sets(layout, first_set, sets)
+    }
+
+    fn bind_compute_pipeline(&mut self, pipeline: &B::ComputePipeline) {
+        let result = self.state.bind_compute_pipeline();
+        self.check(result);
+        self.raw.bind_compute_pipeline(pipeline)
+    }
+
+    fn bind_compute_descriptor_sets<T>(
+        &mut self,
+        layout: &B::PipelineLayout,
+        first_set: usize,
+        sets: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<B::DescriptorSet>,
+    {
+        self.check(self.state.recording("bind_compute_descriptor_sets"));
+        self.raw.bind_compute_descriptor_sets(layout, first_set, sets)
+    }
+
+    fn dispatch(&mut self, count: hal::WorkGroupCount) {
+        self.check(self.state.dispatch("dispatch"));
+        self.raw.dispatch(count)
+    }
+
+    fn dispatch_indirect(&mut self, buffer: &Buffer<B>, offset: buffer::Offset, dispatch_count: u32) {
+        self.check(self.state.dispatch("dispatch_indirect"));
+        self.check(state::check_indirect("dispatch_indirect", offset, dispatch_count, 12, 12));
+        let buffer = self.buffer(buffer);
+        self.raw.dispatch_indirect(buffer, offset, dispatch_count)
+    }
+
+    fn build_acceleration_structure<'a>(
+        &mut self,
+        desc: &accel_struct::BuildDesc<'a, Backend<B>>,
+        dst: &B::AccelerationStructure,
+        scratch: (&Buffer<B>, buffer::Offset),
+    ) {
+        self.check(self.state.outside_pass("build_acceleration_structure"));
+        match desc.inputs {
+            accel_struct::Inputs::Bottom(geometries) => for geometry in geometries {
+                match geometry.data {
+                    accel_struct::GeometryData::Triangles(ref triangles) => {
+                        self.buffer(triangles.vertex_buffer);
+                        if let Some((buffer, _, _)) = triangles.index_buffer {
+                            self.buffer(buffer);
+                        }
+                        if let Some((buffer, _)) = triangles.transform {
+                            self.buffer(buffer);
+                        }
+                    }
+                    accel_struct::GeometryData::Aabbs(ref aabbs) => {
+                        self.buffer(aabbs.buffer);
+                    }
+                }
+            },
+            accel_struct::Inputs::Top { buffer, .. } => {
+                self.buffer(buffer);
+            }
+        }
+        let scratch = (self.buffer(scratch.0), scratch.1);
+        let geometries = conv::geometries(desc);
+        self.raw.build_acceleration_structure(&conv::build_desc(desc, &geometries), dst, scratch)
+    }
+
+    fn bind_ray_tracing_pipeline(&mut self, pipeline: &B::RayTracingPipeline) {
+        let result = self.state.bind_ray_tracing_pipeline();
+        self.check(result);
+        self.raw.bind_ray_tracing_pipeline(pipeline)
+    }
+
+    fn bind_ray_tracing_descriptor_sets<T>(
+        &mut self,
+        layout: &B::PipelineLayout,
+        first_set: usize,
+        sets: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<B::DescriptorSet>,
+    {
+        self.check(self.state.recording("bind_ray_tracing_descriptor_sets"));
+        self.raw.bind_ray_tracing_descriptor_sets(layout, first_set, sets)
+    }
+
+    fn trace_rays<'a>(
+        &mut self,
+        tables: &accel_struct::ShaderBindingTables<'a, Backend<B>>,
+        count: [u32; 3],
+    ) {
+        self.check(self.state.trace_rays());
+        for table in [&tables.ray_gen, &tables.miss, &tables.hit_group].iter().chain(tables.callable.as_ref().iter()) {
+            self.buffer(table.buffer);
+        }
+        self.raw.trace_rays(&conv::shader_binding_tables(tables), count)
+    }
+
+    fn copy_buffer<T>(
+        &mut self,
+        src: &Buffer<B>,
+        dst: &Buffer<B>,
+        regions: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<com::BufferCopy>,
+    {
+        self.check(self.state.outside_pass("copy_buffer"));
+        let (src, dst) = (self.buffer(src), self.buffer(dst));
+        self.raw.copy_buffer(src, dst, regions)
+    }
+
+    fn copy_image<T>(
+        &mut self,
+        src: &Image<B>,
+        src_layout: image::Layout,
+        dst: &Image<B>,
+        dst_layout: image::Layout,
+        regions: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<com::ImageCopy>,
+    {
+        self.check(self.state.outside_pass("copy_image"));
+        let (src, dst) = (self.image(src), self.image(dst));
+        self.raw.copy_image(src, src_layout, dst, dst_layout, regions)
+    }
+
+    fn copy_buffer_to_image<T>(
+        &mut self,
+        src: &Buffer<B>,
+        dst: &Image<B>,
+        dst_layout: image::Layout,
+        regions: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<com::BufferImageCopy>,
+    {
+        self.check(self.state.outside_pass("copy_buffer_to_image"));
+        let (src, dst) = (self.buffer(src), self.image(dst));
+        self.raw.copy_buffer_to_image(src, dst, dst_layout, regions)
+    }
+
+    fn copy_image_to_buffer<T>(
+        &mut self,
+        src: &Image<B>,
+        src_layout: image::Layout,
+        dst: &Buffer<B>,
+        regions: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<com::BufferImageCopy>,
+    {
+        self.check(self.state.outside_pass("copy_image_to_buffer"));
+        let (src, dst) = (self.image(src), self.buffer(dst));
+        self.raw.copy_image_to_buffer(src, src_layout, dst, regions)
+    }
+
+    fn draw(
+        &mut self,
+        vertices: Range<hal::VertexCount>,
+        instances: Range<hal::InstanceCount>,
+    ) {
+        self.check(self.state.draw("draw"));
+        self.raw.draw(vertices, instances)
+    }
+
+    fn draw_indexed(
+        &mut self,
+        indices: Range<hal::IndexCount>,
+        base_vertex: hal::VertexOffset,
+        instances: Range<hal::InstanceCount>,
+    ) {
+        self.check(self.state.draw("draw_indexed"));
+        self.raw.draw_indexed(indices, base_vertex, instances)
+    }
+
+    fn draw_indirect(
+        &mut self,
+        buffer: &Buffer<B>,
+        offset: buffer::Offset,
+        draw_count: u32,
+        stride: u32,
+    ) {
+        self.check(self.state.draw("draw_indirect"));
+        self.check(state::check_indirect("draw_indirect", offset, draw_count, stride, 16));
+        let buffer = self.buffer(buffer);
+        self.raw.draw_indirect(buffer, offset, draw_count, stride)
+    }
+
+    fn draw_indexed_indirect(
+        &mut self,
+        buffer: &Buffer<B>,
+        offset: buffer::Offset,
+        draw_count: u32,
+        stride: u32,
+    ) {
+        self.check(self.state.draw("draw_indexed_indirect"));
+        self.check(state::check_indirect("draw_indexed_indirect", offset, draw_count, stride, 20));
+        let buffer = self.buffer(buffer);
+        self.raw.draw_indexed_indirect(buffer, offset, draw_count, stride)
+    }
+
+    fn execute_indirect(
+        &mut self,
+        signature: &CommandSignature<B>,
+        buffer: &Buffer<B>,
+        offset: buffer::Offset,
+        count: u32,
+    ) {
+        if signature.dispatch {
+            self.check(self.state.dispatch("execute_indirect"));
+        } else {
+            self.check(self.state.draw("execute_indirect"));
+        }
+        let buffer = self.buffer(buffer);
+        self.raw.execute_indirect(&signature.raw, buffer, offset, count)
+    }
+
+    fn begin_query(&mut self, query: query::Query<Backend<B>>, flags: query::QueryControl) {
+        self.check_query("begin_query", query.pool, false);
+        let result = self.state.begin_query(query.pool.id, query.id, query.pool.count);
+        self.check(result);
+        self.raw.begin_query(conv::query(&query), flags)
+    }
+
+    fn end_query(&mut self, query: query::Query<Backend<B>>) {
+        self.check_query("end_query", query.pool, false);
+        let result = self.state.end_query(query.pool.id, query.id, query.pool.count);
+        self.check(result);
+        self.raw.end_query(conv::query(&query))
+    }
+
+    fn reset_query_pool(&mut self, pool: &QueryPool<B>, queries: Range<query::QueryId>) {
+        self.check(self.state.reset_query_pool(pool.id, &queries, pool.count));
+        self.raw.reset_query_pool(&pool.raw, queries)
+    }
+
+    fn write_timestamp(&mut self, stage: pso::PipelineStage, query: query::Query<Backend<B>>) {
+        self.check(self.state.recording("write_timestamp"));
+        self.check_query("write_timestamp", query.pool, true);
+        self.check(state::check_query(query.id, query.pool.count));
+        self.raw.write_timestamp(stage, conv::query(&query))
+    }
+
+    fn copy_query_pool_results(
+        &mut self,
+        pool: &QueryPool<B>,
+        queries: Range<query::QueryId>,
+        buffer: &Buffer<B>,
+        offset: buffer::Offset,
+        stride: buffer::Offset,
+        flags: query::ResultFlags,
+    ) {
+        self.check(self.state.copy_query_pool_results(pool.id, &queries, pool.count));
+        let buffer = self.buffer(buffer);
+        self.raw.copy_query_pool_results(&pool.raw, queries, buffer, offset, stride, flags)
+    }
+
+    fn push_graphics_constants(
+        &mut self,
+        layout: &B::PipelineLayout,
+        stages: pso::ShaderStageFlags,
+        offset: u32,
+        constants: &[u32],
+    ) {
+        self.check(self.state.recording("push_graphics_constants"));
+        self.raw.push_graphics_constants(layout, stages, offset, constants)
+    }
+
+    fn push_compute_constants(
+        &mut self,
+        layout: &B::PipelineLayout,
+        offset: u32,
+        constants: &[u32],
+    ) {
+        self.check(self.state.recording("push_compute_constants"));
+        self.raw.push_compute_constants(layout, offset, constants)
+    }
+
+    fn execute_commands<I>(
+        &mut self,
+        buffers: I,
+    ) where
+        I: IntoIterator,
+        I::Item: Borrow<CommandBuffer<B>>,
+    {
+        self.check(self.state.execute_commands());
+        let buffers = buffers.into_iter().collect::<Vec<_>>();
+        for buffer in &buffers {
+            let buffer = buffer.borrow();
+            if buffer.state.level() != RawLevel::Secondary {
+                self.check(Err(ValidationError::WrongLevel {
+                    command: "execute_commands",
+                    level: RawLevel::Secondary,
+                }));
+            }
+            if !buffer.is_executable() {
+                self.check(Err(ValidationError::NotExecutable { command: "execute_commands" }));
+            }
+            let result = self.state.inherit(&buffer.state);
+            self.check(result);
+        }
+        self.raw.execute_commands(buffers.iter().map(|buffer| &buffer.borrow().raw))
+    }
+}
+
+fn is_inline(contents: &com::SubpassContents) -> bool {
+    match *contents {
+        com::SubpassContents::Inline => true,
+        com::SubpassContents::SecondaryBuffers => false,
+    }
+}
@@ -0,0 +1,295 @@
+//! Conversion of backend generic hal structures to the wrapped backend.
+//!
+//! Most resources are passed through unchanged, only references to wrapped
+//! objects like render passes, buffers and images need to be unwrapped.
+
+use std::borrow::Borrow;
+
+use hal::{self, accel_struct, buffer, memory, pass, pso, query, window};
+use hal::command::CommandBufferInheritanceInfo;
+
+use Backend;
+
+pub fn entry_point<'a, B: hal::Backend>(
+    entry: &pso::EntryPoint<'a, Backend<B>>,
+) -> pso::EntryPoint<'a, B> {
+    pso::EntryPoint {
+        entry: entry.entry,
+        module: entry.module,
+        specialization: entry.specialization,
+    }
+}
+
+pub fn subpass<'a, B: hal::Backend>(subpass: &pass::Subpass<'a, Backend<B>>) -> pass::Subpass<'a, B> {
+    pass::Subpass {
+        index: subpass.index,
+        main_pass: &subpass.main_pass.raw,
+    }
+}
+
+fn base_pipeline<'a, P>(parent: &pso::BasePipeline<'a, P>) -> pso::BasePipeline<'a, P> {
+    match *parent {
+        pso::BasePipeline::Pipeline(pipeline) => pso::BasePipeline::Pipeline(pipeline),
+        pso::BasePipeline::Index(index) => pso::BasePipeline::Index(index),
+        pso::BasePipeline::None => pso::BasePipeline::None,
+    }
+}
+
+pub fn graphics_pipeline_desc<'a, B: hal::Backend>(
+    desc: &pso::GraphicsPipelineDesc<'a, Backend<B>>,
+) -> pso::GraphicsPipelineDesc<'a, B> {
+    let shaders = &desc.shaders;
+    pso::GraphicsPipelineDesc {
+        shaders: pso::GraphicsShaderSet {
+            vertex: entry_point(&shaders.vertex),
+            hull: shaders.hull.as_ref().map(entry_point),
+            domain: shaders.domain.as_ref().map(entry_point),
+            geometry: shaders.geometry.as_ref().map(entry_point),
+            fragment: shaders.fragment.as_ref().map(entry_point),
+        },
+        rasterizer: desc.rasterizer.clone(),
+        vertex_buffers: desc.vertex_buffers.clone(),
+        attributes: desc.attributes.clone(),
+        input_assembler: desc.input_assembler.clone(),
+        blender: desc.blender.clone(),
+        multisampling: desc.multisampling.clone(),
+        depth_stencil: desc.depth_stencil,
+        baked_states: desc.baked_states.clone(),
+        viewport_count: desc.viewport_count,
+        layout: desc.layout,
+        subpass: subpass(&desc.subpass),
+        flags: desc.flags,
+        parent: base_pipeline(&desc.parent),
+    }
+}
+
+pub fn compute_pipeline_desc<'a, B: hal::Backend>(
+    desc: &pso::ComputePipelineDesc<'a, Backend<B>>,
+) -> pso::ComputePipelineDesc<'a, B> {
+    pso::ComputePipelineDesc {
+        shader: entry_point(&desc.shader),
+        layout: desc.layout,
+        flags: desc.flags,
+        parent: base_pipeline(&desc.parent),
+    }
+}
+
+pub fn ray_tracing_shaders<'a, B: hal::Backend>(
+    desc: &pso::RayTracingPipelineDesc<'a, Backend<B>>,
+) -> Vec<pso::RayTracingShader<'a, B>> {
+    desc.shaders
+        .iter()
+        .map(|shader| pso::RayTracingShader {
+            stage: shader.stage,
+            entry: entry_point(&shader.entry),
+        })
+        .collect()
+}
+
+/// Convert a ray tracing pipeline description, with shaders converted by `ray_tracing_shaders`.
+pub fn ray_tracing_pipeline_desc<'a, B: hal::Backend>(
+    desc: &pso::RayTracingPipelineDesc<'a, Backend<B>>,
+    shaders: &'a [pso::RayTracingShader<'a, B>],
+) -> pso::RayTracingPipelineDesc<'a, B> {
+    pso::RayTracingPipelineDesc {
+        shaders,
+        groups: desc.groups,
+        max_recursion_depth: desc.max_recursion_depth,
+        max_payload_size: desc.max_payload_size,
+        max_attribute_size: desc.max_attribute_size,
+        layout: desc.layout,
+        flags: desc.flags,
+    }
+}
+
+pub fn geometries<'a, B: hal::Backend>(
+    desc: &accel_struct::BuildDesc<'a, Backend<B>>,
+) -> Vec<accel_struct::Geometry<'a, B>> {
+    let geometries = match desc.inputs {
+        accel_struct::Inputs::Bottom(geometries) => geometries,
+        accel_struct::Inputs::Top { .. } => return Vec::new(),
+    };
+    geometries
+        .iter()
+        .map(|geometry| accel_struct::Geometry {
+            data: match geometry.data {
+                accel_struct::GeometryData::Triangles(ref t) => accel_struct::GeometryData::Triangles(accel_struct::Triangles {
+                    vertex_buffer: &t.vertex_buffer.raw,
+                    vertex_offset: t.vertex_offset,
+                    vertex_stride: t.vertex_stride,
+                    vertex_count: t.vertex_count,
+                    vertex_format: t.vertex_format,
+                    index_buffer: t.index_buffer.map(|(buffer, offset, ty)| (&buffer.raw, offset, ty)),
+                    index_count: t.index_count,
+                    transform: t.transform.map(|(buffer, offset)| (&buffer.raw, offset)),
+                }),
+                accel_struct::GeometryData::Aabbs(ref a) => accel_struct::GeometryData::Aabbs(accel_struct::Aabbs {
+                    buffer: &a.buffer.raw,
+                    offset: a.offset,
+                    stride: a.stride,
+                    count: a.count,
+                }),
+            },
+            flags: geometry.flags,
+        })
+        .collect()
+}
+
+/// Convert an acceleration structure build, with geometries converted by `geometries`.
+pub fn build_desc<'a, B: hal::Backend>(
+    desc: &accel_struct::BuildDesc<'a, Backend<B>>,
+    geometries: &'a [accel_struct::Geometry<'a, B>],
+) -> accel_struct::BuildDesc<'a, B> {
+    accel_struct::BuildDesc {
+        flags: desc.flags,
+        inputs: match desc.inputs {
+            accel_struct::Inputs::Bottom(_) => accel_struct::Inputs::Bottom(geometries),
+            accel_struct::Inputs::Top { buffer, offset, count } => accel_struct::Inputs::Top {
+                buffer: &buffer.raw,
+                offset,
+                count,
+            },
+        },
+    }
+}
+
+pub fn accel_desc<'a, B: hal::Backend>(desc: &accel_struct::Desc<'a, Backend<B>>) -> accel_struct::Desc<'a, B> {
+    accel_struct::Desc {
+        level: desc.level,
+        buffer: &desc.buffer.raw,
+        offset: desc.offset,
+        size: desc.size,
+    }
+}
+
+fn shader_table<'a, B: hal::Backend>(table: &accel_struct::ShaderTable<'a, Backend<B>>) -> accel_struct::ShaderTable<'a, B> {
+    accel_struct::ShaderTable {
+        buffer: &table.buffer.raw,
+        offset: table.offset,
+        size: table.size,
+        stride: table.stride,
+    }
+}
+
+pub fn shader_binding_tables<'a, B: hal::Backend>(
+    tables: &accel_struct::ShaderBindingTables<'a, Backend<B>>,
+) -> accel_struct::ShaderBindingTables<'a, B> {
+    accel_struct::ShaderBindingTables {
+        ray_gen: shader_table(&tables.ray_gen),
+        miss: shader_table(&tables.miss),
+        hit_group: shader_table(&tables.hit_group),
+        callable: tables.callable.as_ref().map(shader_table),
+    }
+}
+
+pub fn descriptor_set_write<'a, B, J>(
+    write: pso::DescriptorSetWrite<'a, Backend<B>, J>,
+) -> pso::DescriptorSetWrite<'a, B, Vec<pso::Descriptor<'a, B>>>
+where
+    B: hal::Backend,
+    J: IntoIterator,
+    J::Item: Borrow<pso::Descriptor<'a, Backend<B>>>,
+{
+    let descriptors = write.descriptors
+        .into_iter()
+        .map(|descriptor| match *descriptor.borrow() {
+            pso::Descriptor::Sampler(sampler) => pso::Descriptor::Sampler(sampler),
+            pso::Descriptor::Image(view, layout) => pso::Descriptor::Image(view, layout),
+            pso::Descriptor::CombinedImageSampler(view, layout, sampler) =>
+                pso::Descriptor::CombinedImageSampler(view, layout, sampler),
+            pso::Descriptor::Buffer(buffer, ref range) => pso::Descriptor::Buffer(&buffer.raw, range.clone()),
+            pso::Descriptor::TexelBuffer(view) => pso::Descriptor::TexelBuffer(view),
+            pso::Descriptor::AccelerationStructure(accel) => pso::Descriptor::AccelerationStructure(accel),
+        })
+        .collect();
+
+    pso::DescriptorSetWrite {
+        set: write.set,
+        binding: write.binding,
+        array_offset: write.array_offset,
+        descriptors,
+    }
+}
+
+pub fn descriptor_set_copy<'a, B: hal::Backend>(
+    copy: &pso::DescriptorSetCopy<'a, Backend<B>>,
+) -> pso::DescriptorSetCopy<'a, B> {
+    pso::DescriptorSetCopy {
+        src_set: copy.src_set,
+        src_binding: copy.src_binding,
+        src_array_offset: copy.src_array_offset,
+        dst_set: copy.dst_set,
+        dst_binding: copy.dst_binding,
+        dst_array_offset: copy.dst_array_offset,
+        count: copy.count,
+    }
+}
+
+pub fn barrier<'a, B: hal::Backend>(barrier: &memory::Barrier<'a, Backend<B>>) -> memory::Barrier<'a, B> {
+    match *barrier {
+        memory::Barrier::AllBuffers(ref access) => memory::Barrier::AllBuffers(access.clone()),
+        memory::Barrier::AllImages(ref access) => memory::Barrier::AllImages(access.clone()),
+        memory::Barrier::Buffer { ref states, target, ref families } => memory::Barrier::Buffer {
+            states: states.clone(),
+            target: &target.raw,
+            families: families.clone(),
+        },
+        memory::Barrier::Image { ref states, target, ref range, ref families } => memory::Barrier::Image {
+            states: states.clone(),
+            target: &target.raw,
+            range: range.clone(),
+            families: families.clone(),
+        },
+    }
+}
+
+pub fn rendering_attachment<'a, B: hal::Backend>(
+    attachment: &pass::RenderingAttachment<'a, Backend<B>>,
+) -> pass::RenderingAttachment<'a, B> {
+    pass::RenderingAttachment {
+        view: attachment.view,
+        format: attachment.format,
+        layout: attachment.layout,
+        ops: attachment.ops,
+        stencil_ops: attachment.stencil_ops,
+        clear_value: attachment.clear_value,
+    }
+}
+
+pub fn index_buffer_view<'a, B: hal::Backend>(view: buffer::IndexBufferView<'a, Backend<B>>) -> buffer::IndexBufferView<'a, B> {
+    buffer::IndexBufferView {
+        buffer: &view.buffer.raw,
+        offset: view.offset,
+        index_type: view.index_type,
+    }
+}
+
+pub fn query<'a, B: hal::Backend>(query: &query::Query<'a, Backend<B>>) -> query::Query<'a, B> {
+    query::Query {
+        pool: &query.pool.raw,
+        id: query.id,
+    }
+}
+
+pub fn inheritance_info<'a, B: hal::Backend>(
+    info: &CommandBufferInheritanceInfo<'a, Backend<B>>,
+) -> CommandBufferInheritanceInfo<'a, B> {
+    CommandBufferInheritanceInfo {
+        subpass: info.subpass.as_ref().map(subpass),
+        framebuffer: info.framebuffer,
+        occlusion_query_enable: info.occlusion_query_enable,
+        occlusion_query_flags: info.occlusion_query_flags,
+        pipeline_statistics: info.pipeline_statistics,
+    }
+}
+
+pub fn frame_sync<'a, B: hal::Backend>(sync: window::FrameSync<'a, Backend<B>>) -> window::FrameSync<'a, B> {
+    match sync {
+        window::FrameSync::Semaphore(semaphore) => window::FrameSync::Semaphore(semaphore),
+        window::FrameSync::Fence(fence) => window::FrameSync::Fence(&fence.raw),
+    }
+}
+
+pub fn vertex_buffers<'a, B: hal::Backend>(vbs: &pso::VertexBufferSet<'a, Backend<B>>) -> pso::VertexBufferSet<'a, B> {
+    pso::VertexBufferSet(vbs.0.iter().map(|&(buffer, offset)| (&buffer.raw, offset)).collect())
+}
@@ -0,0 +1,714 @@
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use hal::{self, accel_struct, buffer, device, format, image, indirect, mapping, memory, pass, pso, query};
+use hal::error::HostExecutionError;
+use hal::memory::Requirements;
+use hal::pool::CommandPoolCreateFlags;
+use hal::queue::QueueFamilyId;
+use hal::range::RangeArg;
+use hal::window::{Backbuffer, SwapchainConfig};
+
+use command::CommandPool;
+use conv;
+use error::ValidationError;
+use track::Tracker;
+use {
+    Backend, Buffer, CommandSignature, DescriptorPool, Fence, Image, QueryKind, QueryPool, RenderPass, Reporter,
+    Surface, Swapchain, UnboundImage,
+};
+
+/// Validating device.
+///
+/// Keeps track of the created objects to report leaks once dropped, and of
+/// the buffers and images used by submissions to report their destruction
+/// while in flight.
+pub struct Device<B: hal::Backend> {
+    raw: B::Device,
+    reporter: Reporter,
+    tracker: Arc<Tracker>,
+    limits: hal::Limits,
+    // Number of live objects by type.
+    objects: Mutex<BTreeMap<&'static str, usize>>,
+    next_query_pool: AtomicUsize,
+}
+
+impl<B: hal::Backend> Device<B> {
+    pub(crate) fn new(raw: B::Device, limits: hal::Limits, reporter: Reporter, tracker: Arc<Tracker>) -> Self {
+        Device {
+            raw,
+            reporter,
+            tracker,
+            limits,
+            objects: Mutex::new(BTreeMap::new()),
+            next_query_pool: AtomicUsize::new(0),
+        }
+    }
+
+    /// Get the wrapped device.
+    pub fn raw(&self) -> &B::Device {
+        &self.raw
+    }
+
+    fn created(&self, object: &'static str) {
+        *self.objects.lock().unwrap().entry(object).or_insert(0) += 1;
+    }
+
+    fn destroyed(&self, object: &'static str) {
+        if let Some(count) = self.objects.lock().unwrap().get_mut(object) {
+            *count = count.saturating_sub(1);
+        }
+    }
+
+    fn track<T, E>(&self, object: &'static str, result: Result<T, E>) -> Result<T, E> {
+        if result.is_ok() {
+            self.created(object);
+        }
+        result
+    }
+
+    fn check_subpass(&self, subpass: &pass::Subpass<Backend<B>>) {
+        let subpasses = subpass.main_pass.subpasses;
+        if subpass.index >= subpasses {
+            self.reporter.check(Err(ValidationError::InvalidSubpass { subpass: subpass.index, subpasses }));
+        }
+    }
+}
+
+impl<B: hal::Backend> Drop for Device<B> {
+    fn drop(&mut self) {
+        // Objects are leaked regularly when unwinding, don't pile up reports.
+        if thread::panicking() {
+            return;
+        }
+        let objects = self.objects.get_mut().unwrap();
+        for (&object, &count) in objects.iter() {
+            if count > 0 {
+                self.reporter.check(Err(ValidationError::Leaked { object, count }));
+            }
+        }
+    }
+}
+
+impl<B: hal::Backend> hal::Device<Backend<B>> for Device<B> {
+    fn allocate_memory(&self, memory_type: hal::MemoryTypeId, size: u64) -> Result<B::Memory, device::OutOfMemory> {
+        self.track("memory", self.raw.allocate_memory(memory_type, size))
+    }
+
+    fn free_memory(&self, memory: B::Memory) {
+        self.destroyed("memory");
+        self.raw.free_memory(memory)
+    }
+
+    fn set_resource_priority<I>(&self, memories: I, priority: memory::ResidencyPriority)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<B::Memory>,
+    {
+        self.raw.set_resource_priority(memories, priority)
+    }
+
+    fn make_resident<I>(&self, memories: I) -> Result<(), device::OutOfMemory>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<B::Memory>,
+    {
+        self.raw.make_resident(memories)
+    }
+
+    fn evict<I>(&self, memories: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<B::Memory>,
+    {
+        self.raw.evict(memories)
+    }
+
+    fn create_command_pool(&self, family: QueueFamilyId, create_flags: CommandPoolCreateFlags) -> CommandPool<B> {
+        self.created("command pool");
+        let raw = self.raw.create_command_pool(family, create_flags);
+        CommandPool::new(raw, create_flags, self.reporter.clone())
+    }
+
+    fn destroy_command_pool(&self, pool: CommandPool<B>) {
+        self.destroyed("command pool");
+        self.raw.destroy_command_pool(pool.raw)
+    }
+
+    fn create_render_pass<'a, IA, IS, ID>(
+        &self,
+        attachments: IA,
+        subpasses: IS,
+        dependencies: ID,
+    ) -> RenderPass<B>
+    where
+        IA: IntoIterator,
+        IA::Item: Borrow<pass::Attachment>,
+        IS: IntoIterator,
+        IS::Item: Borrow<pass::SubpassDesc<'a>>,
+        ID: IntoIterator,
+        ID::Item: Borrow<pass::SubpassDependency>,
+    {
+        let attachments = attachments.into_iter().collect::<Vec<_>>();
+        let subpasses = subpasses.into_iter().collect::<Vec<_>>();
+        let dependencies = dependencies.into_iter().collect::<Vec<_>>();
+        let (num_attachments, num_subpasses) = (attachments.len(), subpasses.len());
+
+        for subpass in &subpasses {
+            let subpass = subpass.borrow();
+            let ids = subpass.colors
+                .iter()
+                .chain(subpass.depth_stencil)
+                .chain(subpass.inputs)
+                .map(|&(id, _)| id)
+                .chain(subpass.preserves.iter().cloned());
+            for attachment in ids {
+                if attachment >= num_attachments {
+                    self.reporter.check(Err(ValidationError::InvalidAttachment {
+                        command: "create_render_pass",
+                        attachment,
+                        attachments: num_attachments,
+                    }));
+                }
+            }
+        }
+        for dependency in &dependencies {
+            let passes = &dependency.borrow().passes;
+            for &pass in &[passes.start, passes.end] {
+                match pass {
+                    pass::SubpassRef::Pass(subpass) if subpass >= num_subpasses => {
+                        self.reporter.check(Err(ValidationError::InvalidSubpass { subpass, subpasses: num_subpasses }));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        self.created("render pass");
+        RenderPass {
+            raw: self.raw.create_render_pass(attachments, subpasses, dependencies),
+            attachments: num_attachments,
+            subpasses: num_subpasses,
+        }
+    }
+
+    fn destroy_render_pass(&self, rp: RenderPass<B>) {
+        self.destroyed("render pass");
+        self.raw.destroy_render_pass(rp.raw)
+    }
+
+    fn create_pipeline_layout<IS, IR>(
+        &self,
+        set_layouts: IS,
+        push_constant: IR,
+    ) -> B::PipelineLayout
+    where
+        IS: IntoIterator,
+        IS::Item: Borrow<B::DescriptorSetLayout>,
+        IR: IntoIterator,
+        IR::Item: Borrow<(pso::ShaderStageFlags, Range<u32>)>,
+    {
+        let set_layouts = set_layouts.into_iter().collect::<Vec<_>>();
+        let push_constant = push_constant.into_iter().collect::<Vec<_>>();
+
+        // Push constant ranges are specified in 32-bit values.
+        let push_constants_size = push_constant
+            .iter()
+            .map(|range| 4 * range.borrow().1.end as usize)
+            .max()
+            .unwrap_or(0);
+        let checks = [
+            ("max_push_constants_size", push_constants_size, self.limits.max_push_constants_size),
+            ("max_bound_descriptor_sets", set_layouts.len(), self.limits.max_bound_descriptor_sets),
+        ];
+        for &(limit, value, max) in &checks {
+            if value > max {
+                self.reporter.check(Err(ValidationError::LimitExceeded {
+                    command: "create_pipeline_layout",
+                    limit,
+                    value,
+                    max,
+                }));
+            }
+        }
+
+        self.created("pipeline layout");
+        self.raw.create_pipeline_layout(set_layouts, push_constant)
+    }
+
+    fn destroy_pipeline_layout(&self, layout: B::PipelineLayout) {
+        self.destroyed("pipeline layout");
+        self.raw.destroy_pipeline_layout(layout)
+    }
+
+    fn create_graphics_pipelines<'a, I>(
+        &self, descs: I
+    ) -> Vec<Result<B::GraphicsPipeline, pso::CreationError>>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<pso::GraphicsPipelineDesc<'a, Backend<B>>>,
+    {
+        let descs = descs
+            .into_iter()
+            .map(|desc| {
+                let desc = desc.borrow();
+                self.check_subpass(&desc.subpass);
+                conv::graphics_pipeline_desc(desc)
+            })
+            .collect::<Vec<_>>();
+        self.raw
+            .create_graphics_pipelines(descs)
+            .into_iter()
+            .map(|pipeline| self.track("graphics pipeline", pipeline))
+            .collect()
+    }
+
+    fn destroy_graphics_pipeline(&self, pipeline: B::GraphicsPipeline) {
+        self.destroyed("graphics pipeline");
+        self.raw.destroy_graphics_pipeline(pipeline)
+    }
+
+    fn create_compute_pipelines<'a, I>(
+        &self, descs: I
+    ) -> Vec<Result<B::ComputePipeline, pso::CreationError>>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<pso::ComputePipelineDesc<'a, Backend<B>>>,
+    {
+        let descs = descs
+            .into_iter()
+            .map(|desc| conv::compute_pipeline_desc(desc.borrow()))
+            .collect::<Vec<_>>();
+        self.raw
+            .create_compute_pipelines(descs)
+            .into_iter()
+            .map(|pipeline| self.track("compute pipeline", pipeline))
+            .collect()
+    }
+
+    fn destroy_compute_pipeline(&self, pipeline: B::ComputePipeline) {
+        self.destroyed("compute pipeline");
+        self.raw.destroy_compute_pipeline(pipeline)
+    }
+
+    fn get_compute_pipeline_statistics(&self, pipeline: &B::ComputePipeline) -> Option<pso::Statistics> {
+        self.raw.get_compute_pipeline_statistics(pipeline)
+    }
+
+    fn create_ray_tracing_pipeline<'a>(
+        &self,
+        desc: &pso::RayTracingPipelineDesc<'a, Backend<B>>,
+    ) -> Result<B::RayTracingPipeline, pso::CreationError> {
+        let shaders = conv::ray_tracing_shaders(desc);
+        let pipeline = self.raw.create_ray_tracing_pipeline(&conv::ray_tracing_pipeline_desc(desc, &shaders));
+        self.track("ray tracing pipeline", pipeline)
+    }
+
+    fn destroy_ray_tracing_pipeline(&self, pipeline: B::RayTracingPipeline) {
+        self.destroyed("ray tracing pipeline");
+        self.raw.destroy_ray_tracing_pipeline(pipeline)
+    }
+
+    fn get_ray_tracing_shader_group_handles(
+        &self,
+        pipeline: &B::RayTracingPipeline,
+        groups: Range<u32>,
+    ) -> Vec<u8> {
+        self.raw.get_ray_tracing_shader_group_handles(pipeline, groups)
+    }
+
+    fn get_acceleration_structure_prebuild_info<'a>(
+        &self,
+        desc: &accel_struct::BuildDesc<'a, Backend<B>>,
+    ) -> accel_struct::PrebuildInfo {
+        let geometries = conv::geometries(desc);
+        self.raw.get_acceleration_structure_prebuild_info(&conv::build_desc(desc, &geometries))
+    }
+
+    fn create_acceleration_structure<'a>(
+        &self,
+        desc: &accel_struct::Desc<'a, Backend<B>>,
+    ) -> Result<B::AccelerationStructure, accel_struct::CreationError> {
+        let accel = self.raw.create_acceleration_structure(&conv::accel_desc(desc));
+        self.track("acceleration structure", accel)
+    }
+
+    fn get_acceleration_structure_address(&self, accel: &B::AccelerationStructure) -> u64 {
+        self.raw.get_acceleration_structure_address(accel)
+    }
+
+    fn destroy_acceleration_structure(&self, accel: B::AccelerationStructure) {
+        self.destroyed("acceleration structure");
+        self.raw.destroy_acceleration_structure(accel)
+    }
+
+    fn create_framebuffer<I>(
+        &self,
+        pass: &RenderPass<B>,
+        attachments: I,
+        extent: image::Extent,
+    ) -> Result<B::Framebuffer, device::FramebufferError>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<B::ImageView>,
+    {
+        let attachments = attachments.into_iter().collect::<Vec<_>>();
+        if attachments.len() != pass.attachments {
+            self.reporter.check(Err(ValidationError::InvalidRange {
+                command: "create_framebuffer",
+                reason: "the number of attachments must match the render pass",
+            }));
+        }
+        let framebuffer = self.raw.create_framebuffer(&pass.raw, attachments, extent);
+        self.track("framebuffer", framebuffer)
+    }
+
+    fn destroy_framebuffer(&self, buf: B::Framebuffer) {
+        self.destroyed("framebuffer");
+        self.raw.destroy_framebuffer(buf)
+    }
+
+    fn create_shader_module(
+        &self, spirv_data: &[u8]
+    ) -> Result<B::ShaderModule, device::ShaderError> {
+        self.track("shader module", self.raw.create_shader_module(spirv_data))
+    }
+
+    fn destroy_shader_module(&self, shader: B::ShaderModule) {
+        self.destroyed("shader module");
+        self.raw.destroy_shader_module(shader)
+    }
+
+    fn create_buffer(
+        &self, size: u64, usage: buffer::Usage,
+    ) -> Result<B::UnboundBuffer, buffer::CreationError> {
+        self.raw.create_buffer(size, usage)
+    }
+
+    fn get_buffer_requirements(&self, buf: &B::UnboundBuffer) -> Requirements {
+        self.raw.get_buffer_requirements(buf)
+    }
+
+    fn bind_buffer_memory(
+        &self, memory: &B::Memory, offset: u64, buf: B::UnboundBuffer
+    ) -> Result<Buffer<B>, device::BindError> {
+        let raw = self.track("buffer", self.raw.bind_buffer_memory(memory, offset, buf))?;
+        Ok(Buffer {
+            raw,
+            id: self.tracker.created("buffer"),
+        })
+    }
+
+    fn get_buffer_address(&self, buffer: &Buffer<B>) -> u64 {
+        self.raw.get_buffer_address(&buffer.raw)
+    }
+
+    fn destroy_buffer(&self, buffer: Buffer<B>) {
+        self.destroyed("buffer");
+        self.tracker.destroyed("destroy_buffer", buffer.id);
+        self.raw.destroy_buffer(buffer.raw)
+    }
+
+    fn create_buffer_view<R: RangeArg<u64>>(
+        &self, buf: &Buffer<B>, fmt: Option<format::Format>, range: R
+    ) -> Result<B::BufferView, buffer::ViewError> {
+        self.track("buffer view", self.raw.create_buffer_view(&buf.raw, fmt, range))
+    }
+
+    fn destroy_buffer_view(&self, view: B::BufferView) {
+        self.destroyed("buffer view");
+        self.raw.destroy_buffer_view(view)
+    }
+
+    fn create_image(
+        &self, kind: image::Kind, mip_levels: image::Level, format: format::Format,
+        tiling: image::Tiling, usage: image::Usage, storage_flags: image::StorageFlags,
+    ) -> Result<UnboundImage<B>, image::CreationError> {
+        let raw = self.raw.create_image(kind, mip_levels, format, tiling, usage, storage_flags)?;
+        Ok(UnboundImage { raw, usage })
+    }
+
+    fn get_image_requirements(&self, image: &UnboundImage<B>) -> Requirements {
+        self.raw.get_image_requirements(&image.raw)
+    }
+
+    fn bind_image_memory(
+        &self, memory: &B::Memory, offset: u64, image: UnboundImage<B>
+    ) -> Result<Image<B>, device::BindError> {
+        let attachments = image::Usage::COLOR_ATTACHMENT
+            | image::Usage::DEPTH_STENCIL_ATTACHMENT
+            | image::Usage::INPUT_ATTACHMENT;
+        let raw = self.track("image", self.raw.bind_image_memory(memory, offset, image.raw))?;
+        Ok(Image {
+            raw,
+            id: self.tracker.created("image"),
+            attachment: image.usage.intersects(attachments),
+        })
+    }
+
+    fn destroy_image(&self, image: Image<B>) {
+        self.destroyed("image");
+        self.tracker.destroyed("destroy_image", image.id);
+        self.raw.destroy_image(image.raw)
+    }
+
+    fn create_image_view(
+        &self,
+        image: &Image<B>,
+        view_kind: image::ViewKind,
+        format: format::Format,
+        swizzle: format::Swizzle,
+        range: image::SubresourceRange,
+    ) -> Result<B::ImageView, image::ViewError> {
+        let view = self.raw.create_image_view(&image.raw, view_kind, format, swizzle, range);
+        self.track("image view", view)
+    }
+
+    fn destroy_image_view(&self, view: B::ImageView) {
+        self.destroyed("image view");
+        self.raw.destroy_image_view(view)
+    }
+
+    fn create_sampler(&self, info: image::SamplerInfo) -> B::Sampler {
+        self.created("sampler");
+        self.raw.create_sampler(info)
+    }
+
+    fn destroy_sampler(&self, sampler: B::Sampler) {
+        self.destroyed("sampler");
+        self.raw.destroy_sampler(sampler)
+    }
+
+    fn create_descriptor_pool<I>(
+        &self,
+        max_sets: usize,
+        descriptor_ranges: I,
+        flags: pso::DescriptorPoolCreateFlags,
+    ) -> DescriptorPool<B>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<pso::DescriptorRangeDesc>,
+    {
+        self.created("descriptor pool");
+        DescriptorPool {
+            raw: self.raw.create_descriptor_pool(max_sets, descriptor_ranges, flags),
+        }
+    }
+
+    fn destroy_descriptor_pool(&self, pool: DescriptorPool<B>) {
+        self.destroyed("descriptor pool");
+        self.raw.destroy_descriptor_pool(pool.raw)
+    }
+
+    fn create_descriptor_set_layout<I>(&self, bindings: I) -> B::DescriptorSetLayout
+    where
+        I: IntoIterator,
+        I::Item: Borrow<pso::DescriptorSetLayoutBinding>,
+    {
+        self.created("descriptor set layout");
+        self.raw.create_descriptor_set_layout(bindings)
+    }
+
+    fn destroy_descriptor_set_layout(&self, layout: B::DescriptorSetLayout) {
+        self.destroyed("descriptor set layout");
+        self.raw.destroy_descriptor_set_layout(layout)
+    }
+
+    fn write_descriptor_sets<'a, I, J>(&self, write_iter: I)
+    where
+        I: IntoIterator<Item = pso::DescriptorSetWrite<'a, Backend<B>, J>>,
+        J: IntoIterator,
+        J::Item: Borrow<pso::Descriptor<'a, Backend<B>>>,
+    {
+        self.raw.write_descriptor_sets(write_iter.into_iter().map(conv::descriptor_set_write))
+    }
+
+    fn copy_descriptor_sets<'a, I>(&self, copy_iter: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<pso::DescriptorSetCopy<'a, Backend<B>>>,
+    {
+        self.raw.copy_descriptor_sets(copy_iter.into_iter().map(|copy| conv::descriptor_set_copy(copy.borrow())))
+    }
+
+    fn map_memory<R>(&self, memory: &B::Memory, range: R) -> Result<*mut u8, mapping::Error>
+    where
+        R: RangeArg<u64>,
+    {
+        self.raw.map_memory(memory, range)
+    }
+
+    fn flush_mapped_memory_ranges<'a, I, R>(&self, ranges: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<(&'a B::Memory, R)>,
+        R: RangeArg<u64>,
+    {
+        self.raw.flush_mapped_memory_ranges(ranges)
+    }
+
+    fn invalidate_mapped_memory_ranges<'a, I, R>(&self, ranges: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<(&'a B::Memory, R)>,
+        R: RangeArg<u64>,
+    {
+        self.raw.invalidate_mapped_memory_ranges(ranges)
+    }
+
+    fn unmap_memory(&self, memory: &B::Memory) {
+        self.raw.unmap_memory(memory)
+    }
+
+    fn create_semaphore(&self) -> B::Semaphore {
+        self.created("semaphore");
+        self.raw.create_semaphore()
+    }
+
+    fn destroy_semaphore(&self, semaphore: B::Semaphore) {
+        self.destroyed("semaphore");
+        self.raw.destroy_semaphore(semaphore)
+    }
+
+    fn create_fence(&self, signaled: bool) -> Fence<B> {
+        self.created("fence");
+        Fence {
+            raw: self.raw.create_fence(signaled),
+            id: self.tracker.next_id(),
+        }
+    }
+
+    fn reset_fences<I>(&self, fences: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<Fence<B>>,
+    {
+        let fences = fences.into_iter().collect::<Vec<_>>();
+        self.raw.reset_fences(fences.iter().map(|fence| &fence.borrow().raw))
+    }
+
+    fn wait_for_fences<I>(&self, fences: I, wait: device::WaitFor, timeout_ms: u32) -> bool
+    where
+        I: IntoIterator,
+        I::Item: Borrow<Fence<B>>,
+    {
+        let fences = fences.into_iter().collect::<Vec<_>>();
+        let signaled = self.raw.wait_for_fences(fences.iter().map(|fence| &fence.borrow().raw), wait, timeout_ms);
+        if signaled {
+            for fence in &fences {
+                let fence = fence.borrow();
+                // Only some of the fences are signaled when waiting for any.
+                let signaled = match wait {
+                    device::WaitFor::All => true,
+                    device::WaitFor::Any => self.raw.get_fence_status(&fence.raw),
+                };
+                if signaled {
+                    self.tracker.signaled(fence.id);
+                }
+            }
+        }
+        signaled
+    }
+
+    fn get_fence_status(&self, fence: &Fence<B>) -> bool {
+        let signaled = self.raw.get_fence_status(&fence.raw);
+        if signaled {
+            self.tracker.signaled(fence.id);
+        }
+        signaled
+    }
+
+    fn destroy_fence(&self, fence: Fence<B>) {
+        self.destroyed("fence");
+        self.raw.destroy_fence(fence.raw)
+    }
+
+    fn create_query_pool(
+        &self,
+        ty: query::QueryType,
+        count: u32,
+        flags: query::QueryPoolCreateFlags,
+    ) -> QueryPool<B> {
+        self.created("query pool");
+        let kind = QueryKind::new(&ty);
+        if flags.contains(query::QueryPoolCreateFlags::PREDICATION) && kind != QueryKind::Occlusion {
+            self.reporter.check(Err(ValidationError::QueryTypeMismatch {
+                command: "create_query_pool",
+                pool: kind.name(),
+            }));
+        }
+        QueryPool {
+            kind,
+            raw: self.raw.create_query_pool(ty, count, flags),
+            id: self.next_query_pool.fetch_add(1, Ordering::Relaxed),
+            count,
+        }
+    }
+
+    fn destroy_query_pool(&self, pool: QueryPool<B>) {
+        self.destroyed("query pool");
+        self.raw.destroy_query_pool(pool.raw)
+    }
+
+    fn create_command_signature(
+        &self,
+        arguments: &[indirect::Argument],
+        stride: u32,
+        layout: Option<&B::PipelineLayout>,
+    ) -> Result<CommandSignature<B>, indirect::CreationError> {
+        let raw = self.track(
+            "command signature",
+            self.raw.create_command_signature(arguments, stride, layout),
+        )?;
+        Ok(CommandSignature {
+            raw,
+            dispatch: arguments.last() == Some(&indirect::Argument::Dispatch),
+        })
+    }
+
+    fn destroy_command_signature(&self, signature: CommandSignature<B>) {
+        self.destroyed("command signature");
+        self.raw.destroy_command_signature(signature.raw)
+    }
+
+    fn create_swapchain(
+        &self,
+        surface: &mut Surface<B>,
+        config: SwapchainConfig,
+    ) -> Result<(Swapchain<B>, Backbuffer<Backend<B>>), hal::window::CreationError> {
+        let (raw, backbuffer) = self.track("swapchain", self.raw.create_swapchain(&mut surface.raw, config))?;
+        let mut images = Vec::new();
+        let backbuffer = match backbuffer {
+            Backbuffer::Images(raw_images) => Backbuffer::Images(
+                raw_images
+                    .into_iter()
+                    .map(|raw| {
+                        let id = self.tracker.created("image");
+                        images.push(id);
+                        Image { raw, id, attachment: true }
+                    })
+                    .collect(),
+            ),
+            Backbuffer::Framebuffer(framebuffer) => Backbuffer::Framebuffer(framebuffer),
+        };
+        Ok((Swapchain { raw, images }, backbuffer))
+    }
+
+    fn destroy_swapchain(&self, swapchain: Swapchain<B>) {
+        self.destroyed("swapchain");
+        for &image in &swapchain.images {
+            self.tracker.destroyed("destroy_swapchain", image);
+        }
+        self.raw.destroy_swapchain(swapchain.raw)
+    }
+
+    fn wait_idle(&self) -> Result<(), HostExecutionError> {
+        self.raw.wait_idle()?;
+        self.tracker.idle(None);
+        Ok(())
+    }
+}
@@ -0,0 +1,210 @@
+//! Validation errors.
+
+use hal::command::RawLevel;
+use hal::image::Layout;
+use hal::query::QueryId;
+
+/// Violation of a usage rule of the hal API.
+///
+/// Commands are named after the `RawCommandBuffer` or `Device` method that
+/// violated the rule.
+#[derive(Fail, Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    /// A command was recorded into a command buffer which isn't recording.
+    #[fail(display = "`{}` requires the command buffer to be recording, call `begin` first.", command)]
+    NotRecording {
+        /// Offending command.
+        command: &'static str,
+    },
+    /// `begin` was called on a command buffer which is already recording.
+    #[fail(display = "Command buffer is already recording, call `finish` first.")]
+    AlreadyRecording,
+    /// A command buffer was reset individually, but its pool doesn't allow it.
+    #[fail(display = "`{}` resets the command buffer, which requires a pool created with `RESET_INDIVIDUAL`.", command)]
+    ResetNotAllowed {
+        /// Offending command.
+        command: &'static str,
+    },
+    /// A command which is only valid inside a render pass was recorded outside of one.
+    #[fail(display = "`{}` must be recorded inside a render pass.", command)]
+    OutsideRenderPass {
+        /// Offending command.
+        command: &'static str,
+    },
+    /// A command which is only valid outside of a render pass was recorded inside one.
+    #[fail(display = "`{}` must be recorded outside of a render pass, call `end_render_pass` first.", command)]
+    InsideRenderPass {
+        /// Offending command.
+        command: &'static str,
+    },
+    /// An inline command was recorded into a subpass with secondary buffer contents.
+    #[fail(display = "`{}` can't be recorded inline in a subpass started with `SubpassContents::SecondaryBuffers`.", command)]
+    SecondaryContentsOnly {
+        /// Offending command.
+        command: &'static str,
+    },
+    /// Secondary command buffers were executed in a subpass with inline contents.
+    #[fail(display = "`{}` requires a subpass started with `SubpassContents::SecondaryBuffers`.", command)]
+    InlineContentsOnly {
+        /// Offending command.
+        command: &'static str,
+    },
+    /// `next_subpass` was called in the last subpass.
+    #[fail(display = "`next_subpass` called in the last subpass {} of the render pass.", subpass)]
+    NoNextSubpass {
+        /// Index of the current subpass.
+        subpass: usize,
+    },
+    /// `end_render_pass` was called before all subpasses were recorded.
+    #[fail(display = "`end_render_pass` called in subpass {} of {}, call `next_subpass` for the remaining ones.", subpass, subpasses)]
+    SubpassesLeft {
+        /// Index of the current subpass.
+        subpass: usize,
+        /// Number of subpasses of the render pass.
+        subpasses: usize,
+    },
+    /// A render pass was ended by the wrong command, `begin_render_pass` has to be
+    /// paired with `end_render_pass` and `begin_rendering` with `end_rendering`.
+    #[fail(display = "`{}` doesn't match the command which began the render pass.", command)]
+    MismatchedEnd {
+        /// Offending command.
+        command: &'static str,
+    },
+    /// A subpass index is out of range of the render pass.
+    #[fail(display = "Subpass {} is out of range, the render pass has {} subpasses.", subpass, subpasses)]
+    InvalidSubpass {
+        /// Referenced subpass.
+        subpass: usize,
+        /// Number of subpasses of the render pass.
+        subpasses: usize,
+    },
+    /// An attachment index is out of range of the render pass or framebuffer.
+    #[fail(display = "`{}` references attachment {}, but there are only {} attachments.", command, attachment, attachments)]
+    InvalidAttachment {
+        /// Offending command.
+        command: &'static str,
+        /// Referenced attachment.
+        attachment: usize,
+        /// Number of attachments.
+        attachments: usize,
+    },
+    /// A draw, dispatch or trace command was recorded without a bound pipeline.
+    #[fail(display = "`{}` requires a bound pipeline, call `{}` first.", command, bind)]
+    NoPipelineBound {
+        /// Offending command.
+        command: &'static str,
+        /// Command binding the required pipeline.
+        bind: &'static str,
+    },
+    /// A pipeline barrier is malformed.
+    #[fail(display = "Invalid pipeline barrier: {}.", _0)]
+    InvalidBarrier(&'static str),
+    /// An image barrier doesn't transition from the current layout of the image.
+    #[fail(display = "Image barrier transitions from `{:?}`, but the image is in `{:?}`.", from, layout)]
+    LayoutMismatch {
+        /// Source layout of the barrier.
+        from: Layout,
+        /// Current layout of the image.
+        layout: Layout,
+    },
+    /// A command has an invalid range or offset.
+    #[fail(display = "`{}`: {}.", command, reason)]
+    InvalidRange {
+        /// Offending command.
+        command: &'static str,
+        /// Description of the violated rule.
+        reason: &'static str,
+    },
+    /// An image is used in a layout which isn't allowed for the command.
+    #[fail(display = "`{}` requires the image layout to be `General` or `TransferDstOptimal`.", command)]
+    InvalidLayout {
+        /// Offending command.
+        command: &'static str,
+    },
+    /// A query index is out of range of its pool.
+    #[fail(display = "Query {} is out of range, the pool has {} queries.", id, count)]
+    QueryOutOfRange {
+        /// Referenced query.
+        id: QueryId,
+        /// Number of queries of the pool.
+        count: u32,
+    },
+    /// `begin_query` was called for a query which is already active.
+    #[fail(display = "Query {} is already active, call `end_query` first.", id)]
+    QueryActive {
+        /// Referenced query.
+        id: QueryId,
+    },
+    /// `end_query` was called for a query which isn't active.
+    #[fail(display = "Query {} isn't active, call `begin_query` first.", id)]
+    QueryNotActive {
+        /// Referenced query.
+        id: QueryId,
+    },
+    /// A query command doesn't match the type of the query pool.
+    #[fail(display = "`{}` can't be used with {} query pools.", command, pool)]
+    QueryTypeMismatch {
+        /// Offending command.
+        command: &'static str,
+        /// Type of the query pool.
+        pool: &'static str,
+    },
+    /// Recording finished or a subpass ended with active queries.
+    #[fail(display = "`{}` called with {} active queries, call `end_query` first.", command, count)]
+    UnfinishedQueries {
+        /// Offending command.
+        command: &'static str,
+        /// Number of active queries.
+        count: usize,
+    },
+    /// A command buffer was submitted or executed without being recorded.
+    #[fail(display = "`{}` requires recorded command buffers, call `finish` first or re-record after a pool reset.", command)]
+    NotExecutable {
+        /// Offending command.
+        command: &'static str,
+    },
+    /// A command buffer of the wrong level was used.
+    #[fail(display = "`{}` requires a {:?} command buffer.", command, level)]
+    WrongLevel {
+        /// Offending command.
+        command: &'static str,
+        /// Required level.
+        level: RawLevel,
+    },
+    /// A device limit was exceeded.
+    #[fail(display = "`{}` exceeds `Limits::{}`: {} > {}.", command, limit, value, max)]
+    LimitExceeded {
+        /// Offending command.
+        command: &'static str,
+        /// Name of the exceeded limit.
+        limit: &'static str,
+        /// Requested value.
+        value: usize,
+        /// Value of the limit.
+        max: usize,
+    },
+    /// A command buffer referencing a destroyed resource was submitted.
+    #[fail(display = "`{}` references an already destroyed {}.", command, object)]
+    Destroyed {
+        /// Offending command.
+        command: &'static str,
+        /// Type of the resource.
+        object: &'static str,
+    },
+    /// A resource was destroyed while a submission using it may still be executing.
+    #[fail(display = "`{}` destroys the {} while it may still be in use, wait for the fence of its last submission first.", command, object)]
+    InUse {
+        /// Offending command.
+        command: &'static str,
+        /// Type of the resource.
+        object: &'static str,
+    },
+    /// Objects were still alive when the device got dropped.
+    #[fail(display = "{} {} objects weren't destroyed before the device.", count, object)]
+    Leaked {
+        /// Type of the objects.
+        object: &'static str,
+        /// Number of objects.
+        count: usize,
+    },
+}
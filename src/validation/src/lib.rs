@@ -0,0 +1,509 @@
+//! Validation layer for gfx-hal backends.
+//!
+//! `Backend<B>` wraps any backend `B` and checks the usage rules of the hal API
+//! before forwarding the calls, independent of the native debug layers:
+//!
+//! * command buffer lifecycle: recording, finishing, resetting and submission,
+//! * render pass and dynamic rendering scoping of commands, subpass progression,
+//! * bound pipelines for draw, dispatch and trace commands,
+//! * pipeline barriers, transfer command ranges and indirect argument offsets,
+//! * query ranges and `begin_query`/`end_query` pairing,
+//! * buffers and images referenced by submitted commands being alive, and not
+//!   destroyed while the submission may still be executing,
+//! * source layouts of image barriers against the previous transitions,
+//! * objects leaked when the device is dropped.
+//!
+//! Violations are reported as `ValidationError` to a callback, which panics
+//! by default. Resources are passed through to the wrapped backend, only the
+//! objects carrying validation state are wrapped. Buffers, images and fences
+//! are wrapped to track them, resources referenced indirectly by descriptor
+//! sets, image views or framebuffers aren't tracked.
+//!
+//! ```no_run
+//! # extern crate gfx_backend_empty as empty;
+//! # extern crate gfx_hal as hal;
+//! # extern crate gfx_hal_validation as validation;
+//! # fn main() {
+//! use hal::Instance;
+//!
+//! # let instance: empty::Instance = return;
+//! let instance = validation::Instance::with_callback(instance, |error| {
+//!     println!("{}", error);
+//! });
+//! let adapters = instance.enumerate_adapters();
+//! # }
+//! ```
+
+#![deny(missing_docs)]
+
+#[macro_use]
+extern crate failure;
+extern crate gfx_hal as hal;
+
+mod command;
+mod conv;
+mod device;
+mod error;
+mod queue;
+mod state;
+mod track;
+
+pub use command::{CommandBuffer, CommandPool};
+pub use device::Device;
+pub use error::ValidationError;
+pub use queue::CommandQueue;
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use hal::{buffer, error as herror, format, image, pso, query};
+use hal::backend::RawQueueGroup;
+use hal::host_memory::AllocationCallbacks;
+use hal::queue::{QueueFamily as HalQueueFamily, QueueFamilyId, Queues};
+
+use track::Tracker;
+
+/// Handler of validation errors.
+pub type Callback = Arc<Fn(&ValidationError) + Send + Sync>;
+
+// Validation state shared by all objects created from an instance.
+#[derive(Clone)]
+pub(crate) struct Reporter {
+    callback: Callback,
+}
+
+impl Reporter {
+    pub(crate) fn check(&self, result: Result<(), ValidationError>) {
+        if let Err(error) = result {
+            (self.callback)(&error);
+        }
+    }
+}
+
+/// Validation backend wrapping the backend `B`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Backend<B>(PhantomData<B>);
+
+impl<B: hal::Backend> hal::Backend for Backend<B> {
+    type PhysicalDevice = PhysicalDevice<B>;
+    type Device = Device<B>;
+
+    type Surface = Surface<B>;
+    type Swapchain = Swapchain<B>;
+
+    type QueueFamily = QueueFamily<B>;
+    type CommandQueue = CommandQueue<B>;
+    type CommandBuffer = CommandBuffer<B>;
+
+    type ShaderModule = B::ShaderModule;
+    type RenderPass = RenderPass<B>;
+    type Framebuffer = B::Framebuffer;
+
+    type Memory = B::Memory;
+    type CommandPool = CommandPool<B>;
+
+    type UnboundBuffer = B::UnboundBuffer;
+    type Buffer = Buffer<B>;
+    type BufferView = B::BufferView;
+    type UnboundImage = UnboundImage<B>;
+    type Image = Image<B>;
+    type ImageView = B::ImageView;
+    type Sampler = B::Sampler;
+
+    type ComputePipeline = B::ComputePipeline;
+    type GraphicsPipeline = B::GraphicsPipeline;
+    type PipelineLayout = B::PipelineLayout;
+    type DescriptorPool = DescriptorPool<B>;
+    type DescriptorSet = B::DescriptorSet;
+    type DescriptorSetLayout = B::DescriptorSetLayout;
+
+    type Fence = Fence<B>;
+    type Semaphore = B::Semaphore;
+    type QueryPool = QueryPool<B>;
+    type CommandSignature = CommandSignature<B>;
+
+    type AccelerationStructure = B::AccelerationStructure;
+    type RayTracingPipeline = B::RayTracingPipeline;
+}
+
+/// Validating instance wrapping an instance of another backend.
+pub struct Instance<I> {
+    raw: I,
+    reporter: Reporter,
+}
+
+impl<I: hal::Instance> Instance<I> {
+    /// Wrap an instance, panicking on validation errors.
+    pub fn new(raw: I) -> Self {
+        Self::with_callback(raw, |error| panic!("Validation error: {}", error))
+    }
+
+    /// Wrap an instance, reporting validation errors to `callback`.
+    pub fn with_callback<F>(raw: I, callback: F) -> Self
+    where
+        F: Fn(&ValidationError) + Send + Sync + 'static,
+    {
+        Instance {
+            raw,
+            reporter: Reporter { callback: Arc::new(callback) },
+        }
+    }
+
+    /// Get the wrapped instance, e.g. for creating surfaces.
+    pub fn raw(&self) -> &I {
+        &self.raw
+    }
+
+    fn wrap_adapters(
+        &self, adapters: Vec<hal::Adapter<I::Backend>>,
+    ) -> Vec<hal::Adapter<Backend<I::Backend>>> {
+        adapters
+            .into_iter()
+            .map(|adapter| hal::Adapter {
+                info: adapter.info,
+                physical_device: PhysicalDevice {
+                    raw: adapter.physical_device,
+                    reporter: self.reporter.clone(),
+                },
+                queue_families: adapter.queue_families
+                    .into_iter()
+                    .map(|family| QueueFamily { raw: Arc::new(family) })
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+impl<I: hal::Instance> hal::Instance for Instance<I> {
+    type Backend = Backend<I::Backend>;
+
+    fn enumerate_adapters(&self) -> Vec<hal::Adapter<Self::Backend>> {
+        self.wrap_adapters(self.raw.enumerate_adapters())
+    }
+
+    fn enumerate_adapters_compute_only(&self) -> Vec<hal::Adapter<Self::Backend>> {
+        self.wrap_adapters(self.raw.enumerate_adapters_compute_only())
+    }
+
+    fn trigger_capture(&self) -> Result<(), herror::CaptureError> {
+        self.raw.trigger_capture()
+    }
+
+    fn begin_capture(&self) -> Result<(), herror::CaptureError> {
+        self.raw.begin_capture()
+    }
+
+    fn end_capture(&self) -> Result<(), herror::CaptureError> {
+        self.raw.end_capture()
+    }
+}
+
+/// Validating physical device.
+pub struct PhysicalDevice<B: hal::Backend> {
+    raw: B::PhysicalDevice,
+    reporter: Reporter,
+}
+
+impl<B: hal::Backend> PhysicalDevice<B> {
+    /// Get the wrapped physical device.
+    pub fn raw(&self) -> &B::PhysicalDevice {
+        &self.raw
+    }
+
+    fn wrap_gpu(
+        &self,
+        families: &[(&QueueFamily<B>, &[hal::QueuePriority])],
+        mut gpu: hal::Gpu<B>,
+    ) -> hal::Gpu<Backend<B>> {
+        let tracker = Arc::new(Tracker::new(self.reporter.clone()));
+        let mut queue_groups = HashMap::new();
+        for &(family, _) in families {
+            let mut group = RawQueueGroup::new(QueueFamily { raw: family.raw.clone() });
+            for queue in gpu.queues.take_raw(family.id()).unwrap_or_default() {
+                group.add_queue(CommandQueue::new(queue, self.reporter.clone(), tracker.clone()));
+            }
+            queue_groups.insert(family.id(), group);
+        }
+
+        hal::Gpu {
+            device: Device::new(gpu.device, hal::PhysicalDevice::limits(&self.raw), self.reporter.clone(), tracker),
+            queues: Queues::new(queue_groups),
+        }
+    }
+}
+
+impl<B: hal::Backend> hal::PhysicalDevice<Backend<B>> for PhysicalDevice<B> {
+    fn open(
+        &self, families: &[(&QueueFamily<B>, &[hal::QueuePriority])]
+    ) -> Result<hal::Gpu<Backend<B>>, herror::DeviceCreationError> {
+        let raw_families = families
+            .iter()
+            .map(|&(family, priorities)| (&*family.raw, priorities))
+            .collect::<Vec<_>>();
+        let gpu = self.raw.open(&raw_families)?;
+        Ok(self.wrap_gpu(families, gpu))
+    }
+
+    fn open_with_callbacks(
+        &self,
+        families: &[(&QueueFamily<B>, &[hal::QueuePriority])],
+        callbacks: Arc<AllocationCallbacks>,
+    ) -> Result<hal::Gpu<Backend<B>>, herror::DeviceCreationError> {
+        let raw_families = families
+            .iter()
+            .map(|&(family, priorities)| (&*family.raw, priorities))
+            .collect::<Vec<_>>();
+        let gpu = self.raw.open_with_callbacks(&raw_families, callbacks)?;
+        Ok(self.wrap_gpu(families, gpu))
+    }
+
+    fn format_properties(&self, format: Option<format::Format>) -> format::Properties {
+        self.raw.format_properties(format)
+    }
+
+    fn image_format_properties(
+        &self, format: format::Format, dimensions: u8, tiling: image::Tiling,
+        usage: image::Usage, storage_flags: image::StorageFlags,
+    ) -> Option<image::FormatProperties> {
+        self.raw.image_format_properties(format, dimensions, tiling, usage, storage_flags)
+    }
+
+    fn memory_properties(&self) -> hal::MemoryProperties {
+        self.raw.memory_properties()
+    }
+
+    fn features(&self) -> hal::Features {
+        self.raw.features()
+    }
+
+    fn limits(&self) -> hal::Limits {
+        self.raw.limits()
+    }
+}
+
+/// Queue family of the wrapped backend.
+#[derive(Debug)]
+pub struct QueueFamily<B: hal::Backend> {
+    // Shared with the queue groups of opened devices.
+    raw: Arc<B::QueueFamily>,
+}
+
+impl<B: hal::Backend> HalQueueFamily for QueueFamily<B> {
+    fn queue_type(&self) -> hal::QueueType {
+        self.raw.queue_type()
+    }
+
+    fn max_queues(&self) -> usize {
+        self.raw.max_queues()
+    }
+
+    fn id(&self) -> QueueFamilyId {
+        self.raw.id()
+    }
+}
+
+/// Surface of the wrapped backend.
+pub struct Surface<B: hal::Backend> {
+    raw: B::Surface,
+}
+
+impl<B: hal::Backend> Surface<B> {
+    /// Wrap a surface created by the wrapped instance.
+    pub fn new(raw: B::Surface) -> Self {
+        Surface { raw }
+    }
+}
+
+impl<B: hal::Backend> hal::Surface<Backend<B>> for Surface<B> {
+    fn kind(&self) -> image::Kind {
+        self.raw.kind()
+    }
+
+    fn supports_queue_family(&self, family: &QueueFamily<B>) -> bool {
+        self.raw.supports_queue_family(&family.raw)
+    }
+
+    fn capabilities_and_formats(
+        &self, physical_device: &PhysicalDevice<B>,
+    ) -> (hal::SurfaceCapabilities, Option<Vec<format::Format>>) {
+        self.raw.capabilities_and_formats(&physical_device.raw)
+    }
+}
+
+/// Swapchain of the wrapped backend.
+pub struct Swapchain<B: hal::Backend> {
+    pub(crate) raw: B::Swapchain,
+    // Ids of the backbuffer images, destroyed with the swapchain.
+    pub(crate) images: Vec<usize>,
+}
+
+impl<B: hal::Backend> hal::Swapchain<Backend<B>> for Swapchain<B> {
+    fn acquire_frame(&mut self, sync: hal::FrameSync<Backend<B>>) -> hal::Frame {
+        self.raw.acquire_frame(conv::frame_sync(sync))
+    }
+
+    fn frame_statistics(&self) -> Option<hal::FrameStatistics> {
+        self.raw.frame_statistics()
+    }
+}
+
+/// Descriptor pool of the wrapped backend.
+#[derive(Debug)]
+pub struct DescriptorPool<B: hal::Backend> {
+    raw: B::DescriptorPool,
+}
+
+impl<B: hal::Backend> pso::DescriptorPool<Backend<B>> for DescriptorPool<B> {
+    fn allocate_sets<I>(&mut self, layouts: I) -> Vec<Result<B::DescriptorSet, pso::AllocationError>>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<B::DescriptorSetLayout>,
+    {
+        self.raw.allocate_sets(layouts)
+    }
+
+    fn free_sets<I>(&mut self, descriptor_sets: I)
+    where
+        I: IntoIterator<Item = B::DescriptorSet>,
+    {
+        self.raw.free_sets(descriptor_sets)
+    }
+
+    fn reset(&mut self) {
+        self.raw.reset()
+    }
+}
+
+/// Buffer of the wrapped backend.
+#[derive(Debug)]
+pub struct Buffer<B: hal::Backend> {
+    raw: B::Buffer,
+    // Unique id of the buffer, used for tracking its lifetime.
+    id: usize,
+}
+
+impl<B: hal::Backend> Buffer<B> {
+    /// Get the wrapped buffer.
+    pub fn raw(&self) -> &B::Buffer {
+        &self.raw
+    }
+}
+
+/// Image of the wrapped backend, not bound to memory yet.
+#[derive(Debug)]
+pub struct UnboundImage<B: hal::Backend> {
+    raw: B::UnboundImage,
+    usage: image::Usage,
+}
+
+/// Image of the wrapped backend.
+#[derive(Debug)]
+pub struct Image<B: hal::Backend> {
+    raw: B::Image,
+    // Unique id of the image, used for tracking its lifetime and layouts.
+    id: usize,
+    // Render passes can change the layout of the image.
+    attachment: bool,
+}
+
+impl<B: hal::Backend> Image<B> {
+    /// Get the wrapped image.
+    pub fn raw(&self) -> &B::Image {
+        &self.raw
+    }
+}
+
+/// Fence of the wrapped backend.
+#[derive(Debug)]
+pub struct Fence<B: hal::Backend> {
+    raw: B::Fence,
+    // Unique id of the fence, used for completing the submissions it signals.
+    id: usize,
+}
+
+impl<B: hal::Backend> Fence<B> {
+    /// Get the wrapped fence.
+    pub fn raw(&self) -> &B::Fence {
+        &self.raw
+    }
+}
+
+/// Render pass of the wrapped backend.
+#[derive(Debug)]
+pub struct RenderPass<B: hal::Backend> {
+    raw: B::RenderPass,
+    attachments: usize,
+    subpasses: usize,
+}
+
+impl<B: hal::Backend> RenderPass<B> {
+    /// Get the wrapped render pass.
+    pub fn raw(&self) -> &B::RenderPass {
+        &self.raw
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum QueryKind {
+    Occlusion,
+    PipelineStatistics,
+    Timestamp,
+}
+
+impl QueryKind {
+    fn new(ty: &query::QueryType) -> Self {
+        match *ty {
+            query::QueryType::Occlusion => QueryKind::Occlusion,
+            query::QueryType::PipelineStatistics(_) => QueryKind::PipelineStatistics,
+            query::QueryType::Timestamp => QueryKind::Timestamp,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            QueryKind::Occlusion => "occlusion",
+            QueryKind::PipelineStatistics => "pipeline statistics",
+            QueryKind::Timestamp => "timestamp",
+        }
+    }
+}
+
+/// Query pool of the wrapped backend.
+#[derive(Debug)]
+pub struct QueryPool<B: hal::Backend> {
+    raw: B::QueryPool,
+    // Unique id of the pool, used for tracking active queries.
+    id: usize,
+    kind: QueryKind,
+    count: u32,
+}
+
+impl<B: hal::Backend> QueryPool<B> {
+    /// Get the wrapped query pool.
+    pub fn raw(&self) -> &B::QueryPool {
+        &self.raw
+    }
+}
+
+impl<B: hal::Backend> query::QueryPool for QueryPool<B> {
+    fn result_stride(&self) -> buffer::Offset {
+        self.raw.result_stride()
+    }
+}
+
+/// Command signature of the wrapped backend.
+#[derive(Debug)]
+pub struct CommandSignature<B: hal::Backend> {
+    raw: B::CommandSignature,
+    // Sequences end with a dispatch instead of a draw.
+    dispatch: bool,
+}
+
+impl<B: hal::Backend> CommandSignature<B> {
+    /// Get the wrapped command signature.
+    pub fn raw(&self) -> &B::CommandSignature {
+        &self.raw
+    }
+}
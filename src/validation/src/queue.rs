@@ -0,0 +1,82 @@
+use std::borrow::{Borrow, BorrowMut};
+use std::sync::Arc;
+
+use hal::{self, queue};
+use hal::error::HostExecutionError;
+
+use command::CommandBuffer;
+use error::ValidationError;
+use track::Tracker;
+use {Backend, Fence, Reporter, Swapchain};
+
+/// Validating command queue.
+pub struct CommandQueue<B: hal::Backend> {
+    raw: B::CommandQueue,
+    reporter: Reporter,
+    tracker: Arc<Tracker>,
+    // Unique id of the queue, used for completing its submissions.
+    id: usize,
+}
+
+impl<B: hal::Backend> CommandQueue<B> {
+    pub(crate) fn new(raw: B::CommandQueue, reporter: Reporter, tracker: Arc<Tracker>) -> Self {
+        CommandQueue {
+            raw,
+            reporter,
+            id: tracker.next_id(),
+            tracker,
+        }
+    }
+
+    /// Get the wrapped command queue.
+    pub fn raw(&self) -> &B::CommandQueue {
+        &self.raw
+    }
+}
+
+impl<B: hal::Backend> queue::RawCommandQueue<Backend<B>> for CommandQueue<B> {
+    unsafe fn submit_raw<IC>(&mut self, submission: queue::RawSubmission<Backend<B>, IC>, fence: Option<&Fence<B>>)
+    where
+        IC: IntoIterator,
+        IC::Item: Borrow<CommandBuffer<B>>,
+    {
+        let cmd_buffers = submission.cmd_buffers.into_iter().collect::<Vec<_>>();
+        for buffer in &cmd_buffers {
+            if !buffer.borrow().is_executable() {
+                self.reporter.check(Err(ValidationError::NotExecutable { command: "submit" }));
+            }
+        }
+        self.tracker.submit(
+            self.id,
+            fence.map(|fence| fence.id),
+            cmd_buffers.iter().map(|buffer| buffer.borrow().state()),
+        );
+
+        let submission = queue::RawSubmission {
+            cmd_buffers: cmd_buffers.iter().map(|buffer| buffer.borrow().raw()),
+            wait_semaphores: submission.wait_semaphores,
+            signal_semaphores: submission.signal_semaphores,
+        };
+        self.raw.submit_raw(submission, fence.map(|fence| &fence.raw))
+    }
+
+    fn present<IS, IW>(&mut self, swapchains: IS, wait_semaphores: IW)
+    where
+        IS: IntoIterator,
+        IS::Item: BorrowMut<Swapchain<B>>,
+        IW: IntoIterator,
+        IW::Item: Borrow<B::Semaphore>,
+    {
+        let mut swapchains = swapchains.into_iter().collect::<Vec<_>>();
+        self.raw.present(
+            swapchains.iter_mut().map(|swapchain| &mut swapchain.borrow_mut().raw),
+            wait_semaphores,
+        )
+    }
+
+    fn wait_idle(&self) -> Result<(), HostExecutionError> {
+        self.raw.wait_idle()?;
+        self.tracker.idle(Some(self.id));
+        Ok(())
+    }
+}
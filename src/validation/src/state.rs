@@ -0,0 +1,596 @@
+//! Backend independent state tracking of command buffers.
+
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::ops::Range;
+
+use hal::{image, pso};
+use hal::command::{CommandBufferFlags, RawLevel};
+use hal::query::QueryId;
+
+use error::ValidationError;
+
+type Result = ::std::result::Result<(), ValidationError>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Lifecycle {
+    Initial,
+    Recording,
+    Executable,
+}
+
+#[derive(Clone, Debug)]
+struct RenderPass {
+    subpass: usize,
+    subpasses: usize,
+    // Commands are recorded inline, not by secondary command buffers.
+    inline: bool,
+    // Begun by `begin_rendering` instead of `begin_render_pass`.
+    dynamic: bool,
+}
+
+/// Layout transition of an image subresource by the barriers of a command buffer.
+#[derive(Clone, Debug)]
+pub(crate) struct Transition {
+    /// Source layout of the first and destination layout of the last barrier.
+    pub layouts: Range<image::Layout>,
+    /// The image can be attached to render passes, which change its layout implicitly.
+    pub attachment: bool,
+}
+
+/// Recording state of a command buffer.
+#[derive(Clone, Debug)]
+pub(crate) struct CommandState {
+    level: RawLevel,
+    reset_individual: bool,
+    recording: Lifecycle,
+    // Pool epoch of the current recording, pool resets invalidate recordings.
+    epoch: usize,
+    render_pass: Option<RenderPass>,
+    // Secondary command buffer continuing the render pass of a primary one.
+    continues_pass: bool,
+    graphics_pipeline: bool,
+    compute_pipeline: bool,
+    ray_tracing_pipeline: bool,
+    // Active queries, identified by pool and query id.
+    active_queries: Vec<(usize, QueryId)>,
+    // Referenced buffers and images by id.
+    resources: HashMap<usize, &'static str>,
+    // Transitioned image subresources, identified by image id, level and layer.
+    transitions: HashMap<(usize, image::Level, image::Layer), Transition>,
+}
+
+impl CommandState {
+    pub(crate) fn new(level: RawLevel, reset_individual: bool) -> Self {
+        CommandState {
+            level,
+            reset_individual,
+            recording: Lifecycle::Initial,
+            epoch: 0,
+            render_pass: None,
+            continues_pass: false,
+            graphics_pipeline: false,
+            compute_pipeline: false,
+            ray_tracing_pipeline: false,
+            active_queries: Vec::new(),
+            resources: HashMap::new(),
+            transitions: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn level(&self) -> RawLevel {
+        self.level
+    }
+
+    pub(crate) fn resources(&self) -> &HashMap<usize, &'static str> {
+        &self.resources
+    }
+
+    pub(crate) fn transitions(&self) -> &HashMap<(usize, image::Level, image::Layer), Transition> {
+        &self.transitions
+    }
+
+    /// Check if the command buffer can be submitted or executed.
+    pub(crate) fn is_executable(&self, epoch: usize) -> bool {
+        self.recording == Lifecycle::Executable && self.epoch == epoch
+    }
+
+    /// Begin recording, `subpass` is the inherited subpass index and number of
+    /// subpasses of secondary command buffers continuing a render pass.
+    pub(crate) fn begin(
+        &mut self,
+        flags: CommandBufferFlags,
+        subpass: Option<(usize, usize)>,
+        epoch: usize,
+    ) -> Result {
+        let result = match self.recording {
+            Lifecycle::Recording => Err(ValidationError::AlreadyRecording),
+            // Beginning an executable command buffer resets it implicitly.
+            Lifecycle::Executable if self.epoch == epoch && !self.reset_individual =>
+                Err(ValidationError::ResetNotAllowed { command: "begin" }),
+            _ => Ok(()),
+        };
+
+        *self = CommandState {
+            recording: Lifecycle::Recording,
+            epoch,
+            .. CommandState::new(self.level, self.reset_individual)
+        };
+
+        if self.level == RawLevel::Secondary && flags.contains(CommandBufferFlags::RENDER_PASS_CONTINUE) {
+            self.continues_pass = true;
+            if let Some((subpass, subpasses)) = subpass {
+                if subpass >= subpasses {
+                    return result.and(Err(ValidationError::InvalidSubpass { subpass, subpasses }));
+                }
+            }
+        }
+        result
+    }
+
+    pub(crate) fn finish(&mut self) -> Result {
+        // Secondary command buffers continuing a render pass end inside of it,
+        // so only check for passes begun by this command buffer.
+        let result = self.recording("finish")
+            .and(match self.render_pass {
+                Some(_) => Err(ValidationError::InsideRenderPass { command: "finish" }),
+                None => Ok(()),
+            })
+            .and_then(|_| self.no_active_queries("finish"));
+        self.recording = Lifecycle::Executable;
+        result
+    }
+
+    pub(crate) fn reset(&mut self) -> Result {
+        let result = if self.reset_individual {
+            Ok(())
+        } else {
+            Err(ValidationError::ResetNotAllowed { command: "reset" })
+        };
+        *self = CommandState::new(self.level, self.reset_individual);
+        result
+    }
+
+    /// Check if the command buffer is recording.
+    pub(crate) fn recording(&self, command: &'static str) -> Result {
+        match self.recording {
+            Lifecycle::Recording => Ok(()),
+            _ => Err(ValidationError::NotRecording { command }),
+        }
+    }
+
+    /// Check for a command which must be recorded outside of render passes.
+    pub(crate) fn outside_pass(&self, command: &'static str) -> Result {
+        self.recording(command)?;
+        if self.render_pass.is_some() || self.continues_pass {
+            Err(ValidationError::InsideRenderPass { command })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Check for a command which must be recorded inline inside of render passes.
+    pub(crate) fn inside_pass(&self, command: &'static str) -> Result {
+        self.recording(command)?;
+        match self.render_pass {
+            Some(RenderPass { inline: false, .. }) =>
+                Err(ValidationError::SecondaryContentsOnly { command }),
+            Some(_) => Ok(()),
+            None if self.continues_pass => Ok(()),
+            None => Err(ValidationError::OutsideRenderPass { command }),
+        }
+    }
+
+    fn primary(&self, command: &'static str) -> Result {
+        match self.level {
+            RawLevel::Primary => Ok(()),
+            RawLevel::Secondary => Err(ValidationError::WrongLevel { command, level: RawLevel::Primary }),
+        }
+    }
+
+    pub(crate) fn begin_render_pass(&mut self, subpasses: usize, inline: bool) -> Result {
+        self.primary("begin_render_pass")?;
+        self.outside_pass("begin_render_pass")?;
+        self.forget_attachments();
+        self.render_pass = Some(RenderPass {
+            subpass: 0,
+            subpasses,
+            inline,
+            dynamic: false,
+        });
+        Ok(())
+    }
+
+    pub(crate) fn begin_rendering(&mut self) -> Result {
+        self.primary("begin_rendering")?;
+        self.outside_pass("begin_rendering")?;
+        self.forget_attachments();
+        self.render_pass = Some(RenderPass {
+            subpass: 0,
+            subpasses: 1,
+            inline: true,
+            dynamic: true,
+        });
+        Ok(())
+    }
+
+    pub(crate) fn next_subpass(&mut self, inline: bool) -> Result {
+        self.recording("next_subpass")?;
+        match self.render_pass {
+            Some(ref mut pass) if pass.subpass + 1 < pass.subpasses => {
+                pass.subpass += 1;
+                pass.inline = inline;
+                Ok(())
+            }
+            Some(ref pass) => Err(ValidationError::NoNextSubpass { subpass: pass.subpass }),
+            None => Err(ValidationError::OutsideRenderPass { command: "next_subpass" }),
+        }
+    }
+
+    pub(crate) fn end_render_pass(&mut self) -> Result {
+        self.end_pass("end_render_pass", false)
+    }
+
+    pub(crate) fn end_rendering(&mut self) -> Result {
+        self.end_pass("end_rendering", true)
+    }
+
+    fn end_pass(&mut self, command: &'static str, dynamic: bool) -> Result {
+        self.recording(command)?;
+        match self.render_pass.take() {
+            Some(ref pass) if pass.dynamic != dynamic => Err(ValidationError::MismatchedEnd { command }),
+            Some(ref pass) if pass.subpass + 1 < pass.subpasses => Err(ValidationError::SubpassesLeft {
+                subpass: pass.subpass,
+                subpasses: pass.subpasses,
+            }),
+            Some(_) => Ok(()),
+            None => Err(ValidationError::OutsideRenderPass { command }),
+        }
+    }
+
+    pub(crate) fn execute_commands(&self) -> Result {
+        self.primary("execute_commands")?;
+        self.recording("execute_commands")?;
+        match self.render_pass {
+            Some(RenderPass { inline: true, .. }) =>
+                Err(ValidationError::InlineContentsOnly { command: "execute_commands" }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Record the resources and transitions of an executed secondary command buffer.
+    pub(crate) fn inherit(&mut self, secondary: &CommandState) -> Result {
+        self.resources.extend(secondary.resources.iter().map(|(&id, &object)| (id, object)));
+        let mut result = Ok(());
+        for (&subresource, transition) in &secondary.transitions {
+            result = result.and(self.transition(subresource, transition.layouts.clone(), transition.attachment));
+        }
+        result
+    }
+
+    /// Record a reference to a buffer or image.
+    pub(crate) fn use_resource(&mut self, id: usize, object: &'static str) {
+        self.resources.insert(id, object);
+    }
+
+    /// Record an image barrier, checking it against the previous transitions
+    /// of the command buffer.
+    pub(crate) fn image_barrier(
+        &mut self,
+        image: usize,
+        attachment: bool,
+        states: &Range<image::State>,
+        range: &image::SubresourceRange,
+    ) -> Result {
+        check_image_barrier(states, range)?;
+        self.use_resource(image, "image");
+        let mut result = Ok(());
+        for level in range.levels.clone() {
+            for layer in range.layers.clone() {
+                let layouts = states.start.1 .. states.end.1;
+                result = result.and(self.transition((image, level, layer), layouts, attachment));
+            }
+        }
+        result
+    }
+
+    fn transition(
+        &mut self,
+        subresource: (usize, image::Level, image::Layer),
+        layouts: Range<image::Layout>,
+        attachment: bool,
+    ) -> Result {
+        match self.transitions.entry(subresource) {
+            Entry::Occupied(mut entry) => {
+                let transition = entry.get_mut();
+                let result = check_layout(layouts.start, transition.layouts.end);
+                transition.layouts.end = layouts.end;
+                result
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(Transition { layouts, attachment });
+                Ok(())
+            }
+        }
+    }
+
+    // Layouts of attachments are unknown after a render pass.
+    fn forget_attachments(&mut self) {
+        self.transitions.retain(|_, transition| !transition.attachment);
+    }
+
+    pub(crate) fn bind_graphics_pipeline(&mut self) -> Result {
+        self.recording("bind_graphics_pipeline")?;
+        self.graphics_pipeline = true;
+        Ok(())
+    }
+
+    pub(crate) fn bind_compute_pipeline(&mut self) -> Result {
+        self.recording("bind_compute_pipeline")?;
+        self.compute_pipeline = true;
+        Ok(())
+    }
+
+    pub(crate) fn bind_ray_tracing_pipeline(&mut self) -> Result {
+        self.recording("bind_ray_tracing_pipeline")?;
+        self.ray_tracing_pipeline = true;
+        Ok(())
+    }
+
+    pub(crate) fn draw(&self, command: &'static str) -> Result {
+        self.inside_pass(command)?;
+        if self.graphics_pipeline {
+            Ok(())
+        } else {
+            Err(ValidationError::NoPipelineBound { command, bind: "bind_graphics_pipeline" })
+        }
+    }
+
+    pub(crate) fn dispatch(&self, command: &'static str) -> Result {
+        self.outside_pass(command)?;
+        if self.compute_pipeline {
+            Ok(())
+        } else {
+            Err(ValidationError::NoPipelineBound { command, bind: "bind_compute_pipeline" })
+        }
+    }
+
+    pub(crate) fn trace_rays(&self) -> Result {
+        self.outside_pass("trace_rays")?;
+        if self.ray_tracing_pipeline {
+            Ok(())
+        } else {
+            Err(ValidationError::NoPipelineBound { command: "trace_rays", bind: "bind_ray_tracing_pipeline" })
+        }
+    }
+
+    pub(crate) fn begin_query(&mut self, pool: usize, id: QueryId, count: u32) -> Result {
+        self.recording("begin_query")?;
+        check_query(id, count)?;
+        if self.active_queries.contains(&(pool, id)) {
+            return Err(ValidationError::QueryActive { id });
+        }
+        self.active_queries.push((pool, id));
+        Ok(())
+    }
+
+    pub(crate) fn end_query(&mut self, pool: usize, id: QueryId, count: u32) -> Result {
+        self.recording("end_query")?;
+        check_query(id, count)?;
+        match self.active_queries.iter().position(|&query| query == (pool, id)) {
+            Some(index) => {
+                self.active_queries.swap_remove(index);
+                Ok(())
+            }
+            None => Err(ValidationError::QueryNotActive { id }),
+        }
+    }
+
+    pub(crate) fn reset_query_pool(&self, pool: usize, queries: &Range<QueryId>, count: u32) -> Result {
+        self.inactive_queries("reset_query_pool", pool, queries, count)
+    }
+
+    pub(crate) fn copy_query_pool_results(&self, pool: usize, queries: &Range<QueryId>, count: u32) -> Result {
+        self.inactive_queries("copy_query_pool_results", pool, queries, count)
+    }
+
+    // Check that the range of queries is valid and none of them is active.
+    fn inactive_queries(&self, command: &'static str, pool: usize, queries: &Range<QueryId>, count: u32) -> Result {
+        self.outside_pass(command)?;
+        if queries.start > queries.end || queries.end > count {
+            return Err(ValidationError::QueryOutOfRange { id: queries.end, count });
+        }
+        match self.active_queries.iter().find(|&&(p, id)| p == pool && queries.start <= id && id < queries.end) {
+            Some(&(_, id)) => Err(ValidationError::QueryActive { id }),
+            None => Ok(()),
+        }
+    }
+
+    fn no_active_queries(&self, command: &'static str) -> Result {
+        match self.active_queries.len() {
+            0 => Ok(()),
+            count => Err(ValidationError::UnfinishedQueries { command, count }),
+        }
+    }
+}
+
+pub(crate) fn check_query(id: QueryId, count: u32) -> Result {
+    if id < count {
+        Ok(())
+    } else {
+        Err(ValidationError::QueryOutOfRange { id, count })
+    }
+}
+
+pub(crate) fn check_barrier_stages(stages: &Range<pso::PipelineStage>) -> Result {
+    if stages.start.is_empty() || stages.end.is_empty() {
+        Err(ValidationError::InvalidBarrier("source and destination stages must not be empty"))
+    } else {
+        Ok(())
+    }
+}
+
+pub(crate) fn check_image_barrier(
+    states: &Range<image::State>,
+    range: &image::SubresourceRange,
+) -> Result {
+    match states.end.1 {
+        image::Layout::Undefined | image::Layout::Preinitialized =>
+            return Err(ValidationError::InvalidBarrier("images can't be transitioned to `Undefined` or `Preinitialized`")),
+        _ => {}
+    }
+    if range.aspects.is_empty() || range.levels.start >= range.levels.end || range.layers.start >= range.layers.end {
+        return Err(ValidationError::InvalidBarrier("the subresource range of an image barrier must not be empty"));
+    }
+    Ok(())
+}
+
+/// Check the source layout of a barrier against the current layout of a subresource.
+pub(crate) fn check_layout(from: image::Layout, layout: image::Layout) -> Result {
+    // Transitions from `Undefined` discard the contents, whatever the layout.
+    if from == image::Layout::Undefined || from == layout {
+        Ok(())
+    } else {
+        Err(ValidationError::LayoutMismatch { from, layout })
+    }
+}
+
+/// Check the offset and stride of an indirect command reading `count`
+/// arguments of `size` bytes each.
+pub(crate) fn check_indirect(
+    command: &'static str,
+    offset: u64,
+    count: u32,
+    stride: u32,
+    size: u32,
+) -> Result {
+    if offset % 4 != 0 {
+        return Err(ValidationError::InvalidRange { command, reason: "the offset must be a multiple of 4" });
+    }
+    if count > 1 && (stride % 4 != 0 || stride < size) {
+        return Err(ValidationError::InvalidRange {
+            command,
+            reason: "the stride must be a multiple of 4 and not smaller than the arguments",
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use hal::format::Aspects;
+    use hal::image::Layout;
+
+    use super::*;
+
+    fn recording(level: RawLevel) -> CommandState {
+        let mut state = CommandState::new(level, false);
+        state.begin(CommandBufferFlags::EMPTY, None, 0).unwrap();
+        state
+    }
+
+    #[test]
+    fn record_outside_begin() {
+        let mut state = CommandState::new(RawLevel::Primary, false);
+        assert_eq!(state.outside_pass("fill_buffer"), Err(ValidationError::NotRecording { command: "fill_buffer" }));
+        assert!(state.begin(CommandBufferFlags::EMPTY, None, 0).is_ok());
+        assert_eq!(state.begin(CommandBufferFlags::EMPTY, None, 0), Err(ValidationError::AlreadyRecording));
+        assert!(state.finish().is_ok());
+        assert!(state.is_executable(0));
+        assert!(!state.is_executable(1));
+        assert_eq!(state.reset(), Err(ValidationError::ResetNotAllowed { command: "reset" }));
+    }
+
+    #[test]
+    fn render_pass_scope() {
+        let mut state = recording(RawLevel::Primary);
+        state.bind_graphics_pipeline().unwrap();
+        assert_eq!(state.draw("draw"), Err(ValidationError::OutsideRenderPass { command: "draw" }));
+        state.begin_render_pass(2, true).unwrap();
+        assert!(state.draw("draw").is_ok());
+        assert_eq!(state.outside_pass("copy_buffer"), Err(ValidationError::InsideRenderPass { command: "copy_buffer" }));
+        assert_eq!(state.end_render_pass(), Err(ValidationError::SubpassesLeft { subpass: 0, subpasses: 2 }));
+
+        state.begin_render_pass(2, true).unwrap();
+        state.next_subpass(false).unwrap();
+        assert_eq!(state.draw("draw"), Err(ValidationError::SecondaryContentsOnly { command: "draw" }));
+        assert!(state.execute_commands().is_ok());
+        assert_eq!(state.next_subpass(true), Err(ValidationError::NoNextSubpass { subpass: 1 }));
+        assert!(state.end_render_pass().is_ok());
+        assert!(state.finish().is_ok());
+    }
+
+    #[test]
+    fn dynamic_rendering_scope() {
+        let mut state = recording(RawLevel::Primary);
+        state.bind_graphics_pipeline().unwrap();
+        state.begin_rendering().unwrap();
+        assert!(state.draw("draw").is_ok());
+        assert_eq!(state.next_subpass(true), Err(ValidationError::NoNextSubpass { subpass: 0 }));
+        assert_eq!(state.end_render_pass(), Err(ValidationError::MismatchedEnd { command: "end_render_pass" }));
+
+        state.begin_render_pass(1, true).unwrap();
+        assert_eq!(state.end_rendering(), Err(ValidationError::MismatchedEnd { command: "end_rendering" }));
+        state.begin_rendering().unwrap();
+        assert!(state.end_rendering().is_ok());
+        assert_eq!(state.end_rendering(), Err(ValidationError::OutsideRenderPass { command: "end_rendering" }));
+        assert!(state.finish().is_ok());
+    }
+
+    #[test]
+    fn secondary_continues_pass() {
+        let mut state = CommandState::new(RawLevel::Secondary, false);
+        state.begin(CommandBufferFlags::RENDER_PASS_CONTINUE, Some((0, 1)), 0).unwrap();
+        state.bind_graphics_pipeline().unwrap();
+        assert!(state.draw("draw").is_ok());
+        assert_eq!(
+            state.begin_render_pass(1, true),
+            Err(ValidationError::WrongLevel { command: "begin_render_pass", level: RawLevel::Primary })
+        );
+        assert!(state.finish().is_ok());
+    }
+
+    #[test]
+    fn query_pairing() {
+        let mut state = recording(RawLevel::Primary);
+        assert!(state.begin_query(0, 1, 2).is_ok());
+        assert_eq!(state.begin_query(0, 1, 2), Err(ValidationError::QueryActive { id: 1 }));
+        assert!(state.begin_query(1, 1, 2).is_ok());
+        assert_eq!(state.begin_query(0, 2, 2), Err(ValidationError::QueryOutOfRange { id: 2, count: 2 }));
+        assert_eq!(state.reset_query_pool(0, &(0 .. 2), 2), Err(ValidationError::QueryActive { id: 1 }));
+        assert!(state.end_query(0, 1, 2).is_ok());
+        assert_eq!(state.end_query(0, 1, 2), Err(ValidationError::QueryNotActive { id: 1 }));
+        assert_eq!(state.finish(), Err(ValidationError::UnfinishedQueries { command: "finish", count: 1 }));
+    }
+
+    #[test]
+    fn image_layouts() {
+        let mut state = recording(RawLevel::Primary);
+        let range = image::SubresourceRange { aspects: Aspects::COLOR, levels: 0 .. 2, layers: 0 .. 1 };
+        let barrier = |from, to| (image::Access::empty(), from) .. (image::Access::empty(), to);
+
+        assert!(state.image_barrier(0, false, &barrier(Layout::Undefined, Layout::TransferDstOptimal), &range).is_ok());
+        assert!(state.image_barrier(0, false, &barrier(Layout::TransferDstOptimal, Layout::General), &range).is_ok());
+        assert_eq!(
+            state.image_barrier(0, false, &barrier(Layout::TransferDstOptimal, Layout::General), &range),
+            Err(ValidationError::LayoutMismatch { from: Layout::TransferDstOptimal, layout: Layout::General })
+        );
+        assert!(state.image_barrier(1, true, &barrier(Layout::Undefined, Layout::ColorAttachmentOptimal), &range).is_ok());
+        state.begin_render_pass(1, true).unwrap();
+        state.end_render_pass().unwrap();
+        assert!(state.image_barrier(1, true, &barrier(Layout::Present, Layout::General), &range).is_ok());
+        assert_eq!(state.transitions()[&(0, 1, 0)].layouts, Layout::Undefined .. Layout::General);
+    }
+
+    #[test]
+    fn indirect_arguments() {
+        assert!(check_indirect("draw_indirect", 16, 4, 16, 16).is_ok());
+        assert!(check_indirect("draw_indirect", 0, 1, 0, 16).is_ok());
+        assert_eq!(
+            check_indirect("draw_indirect", 2, 1, 16, 16),
+            Err(ValidationError::InvalidRange { command: "draw_indirect", reason: "the offset must be a multiple of 4" })
+        );
+        assert!(check_indirect("draw_indexed_indirect", 0, 2, 16, 20).is_err());
+        assert!(check_indirect("draw_indexed_indirect", 0, 2, 22, 20).is_err());
+    }
+}
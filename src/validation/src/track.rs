@@ -0,0 +1,205 @@
+//! Tracking of resources across submissions.
+//!
+//! Buffers and images get an id when they are created. Command buffers record
+//! the ids they reference and the layout transitions of image subresources,
+//! which are checked on submission against the live resources and the last
+//! known layouts. Submissions are in flight until their fence, or the fence of
+//! a later submission to the same queue, is observed as signaled or the queue
+//! or device is waited for.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use hal::image;
+
+use error::ValidationError;
+use state::{self, CommandState};
+use Reporter;
+
+#[derive(Debug)]
+struct Resource {
+    object: &'static str,
+    // Layouts of image subresources after the transitions submitted so far.
+    layouts: HashMap<(image::Level, image::Layer), image::Layout>,
+}
+
+#[derive(Debug)]
+struct Submission {
+    queue: usize,
+    fence: Option<usize>,
+    resources: Vec<usize>,
+}
+
+#[derive(Debug, Default)]
+struct Resources {
+    live: HashMap<usize, Resource>,
+    // Submissions which aren't known to be completed, in submission order.
+    in_flight: Vec<Submission>,
+}
+
+/// Resource tracking shared by a device and its queues.
+pub(crate) struct Tracker {
+    reporter: Reporter,
+    next_id: AtomicUsize,
+    resources: Mutex<Resources>,
+}
+
+impl Tracker {
+    pub(crate) fn new(reporter: Reporter) -> Self {
+        Tracker {
+            reporter,
+            next_id: AtomicUsize::new(0),
+            resources: Mutex::new(Resources::default()),
+        }
+    }
+
+    /// Allocate a unique id, e.g. for queues and fences.
+    pub(crate) fn next_id(&self) -> usize {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Start tracking a new resource, returning its id.
+    pub(crate) fn created(&self, object: &'static str) -> usize {
+        let id = self.next_id();
+        self.resources.lock().unwrap().live.insert(id, Resource {
+            object,
+            layouts: HashMap::new(),
+        });
+        id
+    }
+
+    pub(crate) fn destroyed(&self, command: &'static str, id: usize) {
+        let mut resources = self.resources.lock().unwrap();
+        if let Some(resource) = resources.live.remove(&id) {
+            if resources.in_flight.iter().any(|submission| submission.resources.contains(&id)) {
+                self.reporter.check(Err(ValidationError::InUse { command, object: resource.object }));
+            }
+        }
+    }
+
+    /// Check the command buffers of a submission and apply their layout transitions.
+    pub(crate) fn submit<'a, I>(&self, queue: usize, fence: Option<usize>, command_buffers: I)
+    where
+        I: IntoIterator<Item = &'a CommandState>,
+    {
+        let mut resources = self.resources.lock().unwrap();
+        let mut submitted = Vec::new();
+
+        for state in command_buffers {
+            for (&id, &object) in state.resources() {
+                if resources.live.contains_key(&id) {
+                    submitted.push(id);
+                } else {
+                    self.reporter.check(Err(ValidationError::Destroyed { command: "submit", object }));
+                }
+            }
+            for (&(id, level, layer), transition) in state.transitions() {
+                // Render passes change the layouts of attachments implicitly.
+                if transition.attachment {
+                    continue;
+                }
+                if let Some(resource) = resources.live.get_mut(&id) {
+                    let layout = resource.layouts.insert((level, layer), transition.layouts.end);
+                    if let Some(layout) = layout {
+                        self.reporter.check(state::check_layout(transition.layouts.start, layout));
+                    }
+                }
+            }
+        }
+
+        resources.in_flight.push(Submission { queue, fence, resources: submitted });
+    }
+
+    /// Complete the last submission signaling `fence` and the ones before it on the same queue.
+    pub(crate) fn signaled(&self, fence: usize) {
+        let mut resources = self.resources.lock().unwrap();
+        if let Some(last) = resources.in_flight.iter().rposition(|submission| submission.fence == Some(fence)) {
+            let queue = resources.in_flight[last].queue;
+            let mut index = 0;
+            resources.in_flight.retain(|submission| {
+                index += 1;
+                index > last + 1 || submission.queue != queue
+            });
+        }
+    }
+
+    /// Complete all submissions of `queue`, or of all queues.
+    pub(crate) fn idle(&self, queue: Option<usize>) {
+        let mut resources = self.resources.lock().unwrap();
+        match queue {
+            Some(queue) => resources.in_flight.retain(|submission| submission.queue != queue),
+            None => resources.in_flight.clear(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use hal::command::{CommandBufferFlags, RawLevel};
+    use hal::format::Aspects;
+    use hal::image::{Access, Layout, SubresourceRange};
+
+    use super::*;
+
+    fn tracker() -> (Tracker, Arc<Mutex<Vec<ValidationError>>>) {
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let reported = errors.clone();
+        let reporter = Reporter {
+            callback: Arc::new(move |error: &ValidationError| reported.lock().unwrap().push(error.clone())),
+        };
+        (Tracker::new(reporter), errors)
+    }
+
+    fn transition(image: usize, layouts: ::std::ops::Range<Layout>) -> CommandState {
+        let mut state = CommandState::new(RawLevel::Primary, false);
+        state.begin(CommandBufferFlags::EMPTY, None, 0).unwrap();
+        let range = SubresourceRange { aspects: Aspects::COLOR, levels: 0 .. 1, layers: 0 .. 1 };
+        let states = (Access::empty(), layouts.start) .. (Access::empty(), layouts.end);
+        state.image_barrier(image, false, &states, &range).unwrap();
+        state.finish().unwrap();
+        state
+    }
+
+    #[test]
+    fn destroyed_resources() {
+        let (tracker, errors) = tracker();
+        let queue = tracker.next_id();
+        let fence = tracker.next_id();
+        let image = tracker.created("image");
+        let state = transition(image, Layout::Undefined .. Layout::TransferDstOptimal);
+
+        tracker.submit(queue, Some(fence), Some(&state));
+        tracker.destroyed("destroy_image", image);
+        tracker.submit(queue, None, Some(&state));
+        assert_eq!(*errors.lock().unwrap(), vec![
+            ValidationError::InUse { command: "destroy_image", object: "image" },
+            ValidationError::Destroyed { command: "submit", object: "image" },
+        ]);
+
+        errors.lock().unwrap().clear();
+        let image = tracker.created("image");
+        let state = transition(image, Layout::Undefined .. Layout::General);
+        tracker.submit(queue, Some(fence), Some(&state));
+        tracker.signaled(fence);
+        tracker.destroyed("destroy_image", image);
+        assert!(errors.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn submitted_layouts() {
+        let (tracker, errors) = tracker();
+        let queue = tracker.next_id();
+        let image = tracker.created("image");
+
+        tracker.submit(queue, None, Some(&transition(image, Layout::Undefined .. Layout::TransferDstOptimal)));
+        tracker.submit(queue, None, Some(&transition(image, Layout::TransferDstOptimal .. Layout::ShaderReadOnlyOptimal)));
+        assert!(errors.lock().unwrap().is_empty());
+        tracker.submit(queue, None, Some(&transition(image, Layout::TransferDstOptimal .. Layout::General)));
+        assert_eq!(*errors.lock().unwrap(), vec![
+            ValidationError::LayoutMismatch { from: Layout::TransferDstOptimal, layout: Layout::ShaderReadOnlyOptimal },
+        ]);
+    }
+}
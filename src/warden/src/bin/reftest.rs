@@ -17,7 +17,7 @@ extern crate gfx_backend_vulkan;
 extern crate gfx_backend_dx12;
 #[cfg(feature = "metal")]
 extern crate gfx_backend_metal;
-#[cfg(any(feature = "gl", feature = "gl-headless"))]
+#[cfg(any(feature = "gl", feature = "gl-headless", feature = "gl-software"))]
 extern crate gfx_backend_gl;
 
 use std::collections::HashMap;
@@ -37,9 +37,17 @@ enum Expectation {
 struct Test {
     features: hal::Features,
     jobs: Vec<String>,
+    // Number of times the jobs are submitted before checking the expectation,
+    // replaying the recorded command buffers.
+    #[serde(default = "default_frames")]
+    frames: usize,
     expect: Expectation,
 }
 
+fn default_frames() -> usize {
+    1
+}
+
 type Suite = HashMap<String, HashMap<String, Test>>;
 
 struct TestGroup {
@@ -168,7 +176,9 @@ impl Harness {
                     continue
                 }
 
-                scene.run(test.jobs.iter().map(|x| x.as_str()));
+                for _ in 0 .. test.frames {
+                    scene.run(test.jobs.iter().map(|x| x.as_str()));
+                }
 
                 print!("\tran: ");
                 let (guard, row, data) = match test.expect {
@@ -252,6 +262,12 @@ fn main() {
         let instance = gfx_backend_gl::Headless(context);
         num_failures += harness.run(instance, Disabilities::default());
     }
+    #[cfg(feature = "gl-software")]
+    {
+        println!("Warding GL software:");
+        let instance = gfx_backend_gl::Headless::software(1, 1).unwrap();
+        num_failures += harness.run(instance, Disabilities::default());
+    }
     let _ = harness;
     num_failures += 0; // mark as mutated
     process::exit(num_failures as _);
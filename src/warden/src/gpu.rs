@@ -60,12 +60,14 @@ impl<B: hal::Backend> Buffer<B> {
         memory::Barrier::Buffer {
             states: self.stable_state .. state,
             target: &self.handle,
+            families: None,
         }
     }
     fn barrier_from(&self, state: b::State) -> memory::Barrier<B> {
         memory::Barrier::Buffer {
             states: state .. self.stable_state,
             target: &self.handle,
+            families: None,
         }
     }
 }
@@ -106,12 +108,14 @@ pub struct Job<B: hal::Backend, C> {
 pub struct Scene<B: hal::Backend, C> {
     pub resources: Resources<B>,
     pub jobs: HashMap<String, Job<B, C>>,
-    init_submit: c::Submit<B, C, c::MultiShot, c::Primary>,
+    // Submitted with the first run, jobs are replayed on the initialized resources.
+    init_submit: Option<c::Submit<B, C, c::OneShot, c::Primary>>,
     device: B::Device,
     queue_group: hal::QueueGroup<B, C>,
     command_pool: hal::CommandPool<B, C>,
     upload_buffers: HashMap<String, (B::Buffer, B::Memory)>,
     download_type: hal::MemoryTypeId,
+    memory_types: Vec<hal::MemoryType>,
     limits: hal::Limits,
 }
 
@@ -211,6 +215,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                                 let buffer_barrier = memory::Barrier::Buffer {
                                     states: b::Access::empty() .. access,
                                     target: &buffer,
+                                    families: None,
                                 };
                                 init_cmd.pipeline_barrier(
                                     pso::PipelineStage::TOP_OF_PIPE .. pso::PipelineStage::BOTTOM_OF_PIPE,
@@ -246,6 +251,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                             let pre_barrier = memory::Barrier::Buffer {
                                 states: b::Access::empty() .. b::Access::TRANSFER_WRITE,
                                 target: &buffer,
+                                families: None,
                             };
                             init_cmd.pipeline_barrier(
                                 pso::PipelineStage::TOP_OF_PIPE .. pso::PipelineStage::TRANSFER,
@@ -265,6 +271,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                             let post_barrier = memory::Barrier::Buffer {
                                 states: b::Access::TRANSFER_WRITE .. final_state,
                                 target: &buffer,
+                                families: None,
                             };
                             init_cmd.pipeline_barrier(
                                 pso::PipelineStage::TRANSFER .. pso::PipelineStage::BOTTOM_OF_PIPE,
@@ -318,6 +325,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                                         aspects,
                                         .. COLOR_RANGE.clone()
                                     },
+                                    families: None,
                                 };
                                 init_cmd.pipeline_barrier(
                                     pso::PipelineStage::TOP_OF_PIPE .. pso::PipelineStage::BOTTOM_OF_PIPE,
@@ -372,6 +380,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                                         (i::Access::TRANSFER_WRITE, i::Layout::TransferDstOptimal),
                                 target: &image,
                                 range: COLOR_RANGE.clone(), //TODO
+                                families: None,
                             };
                             init_cmd.pipeline_barrier(
                                 pso::PipelineStage::TOP_OF_PIPE .. pso::PipelineStage::TRANSFER,
@@ -402,6 +411,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                                 states: (i::Access::TRANSFER_WRITE, i::Layout::TransferDstOptimal) .. final_state,
                                 target: &image,
                                 range: COLOR_RANGE.clone(), //TODO
+                                families: None,
                             };
                             init_cmd.pipeline_barrier(
                                 pso::PipelineStage::TRANSFER .. pso::PipelineStage::BOTTOM_OF_PIPE,
@@ -466,6 +476,8 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                                 depth_stencil: t.1.as_ref(),
                                 inputs: &t.2,
                                 preserves: &t.3,
+                                view_mask: 0,
+                                correlation_mask: 0,
                             })
                             .collect::<Vec<_>>();
                         let raw_deps = dependencies
@@ -519,7 +531,11 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                         resources.desc_set_layouts.insert(name.clone(), (binding_indices, layout));
                     }
                     raw::Resource::DescriptorPool { capacity, ref ranges } => {
-                        let pool = device.create_descriptor_pool(capacity, ranges);
+                        let pool = device.create_descriptor_pool(
+                            capacity,
+                            ranges,
+                            pso::DescriptorPoolCreateFlags::empty(),
+                        );
                         resources.desc_pools.insert(name.clone(), pool);
                     }
                     _ => {}
@@ -606,7 +622,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                     }
                     raw::Resource::GraphicsPipeline {
                         ref shaders, ref rasterizer, ref vertex_buffers, ref attributes,
-                        ref input_assembler, ref blender, depth_stencil, ref layout, ref subpass,
+                        ref input_assembler, ref blender, ref multisampling, depth_stencil, ref layout, ref subpass,
                     } => {
                         let reshaders = &resources.shaders;
                         let entry = |shader: &String| -> Option<pso::EntryPoint<B>> {
@@ -641,8 +657,10 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                             attributes: attributes.clone(),
                             input_assembler: input_assembler.clone(),
                             blender: blender.clone(),
+                            multisampling: multisampling.clone(),
                             depth_stencil: depth_stencil.clone(),
                             baked_states: pso::BakedStates::default(), //TODO
+                            viewport_count: 1,
                             layout: &resources.pipeline_layouts[layout],
                             subpass: hal::pass::Subpass {
                                 main_pass: &resources.render_passes[&subpass.parent].handle,
@@ -686,7 +704,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
         // fill up command buffers
         let mut jobs = HashMap::new();
         for (name, job) in &raw.jobs {
-            let mut command_buf = command_pool.acquire_command_buffer(false);
+            let mut command_buf = command_pool.acquire_command_buffer(true);
             match *job {
                 raw::Job::Transfer { ref commands } => {
                     use raw::TransferCommand as Tc;
@@ -819,6 +837,14 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                                 .expect(&format!("Missing descriptor set: {}", name))
                         }),
                     );
+                    // Replayed jobs access the results of the previous submission.
+                    command_buf.pipeline_barrier(
+                        pso::PipelineStage::COMPUTE_SHADER .. pso::PipelineStage::COMPUTE_SHADER,
+                        memory::Dependencies::empty(),
+                        &[memory::Barrier::AllBuffers(
+                            b::Access::SHADER_WRITE .. b::Access::SHADER_READ | b::Access::SHADER_WRITE,
+                        )],
+                    );
                     command_buf.dispatch(dispatch);
                 }
             }
@@ -832,12 +858,13 @@ impl<B: hal::Backend> Scene<B, hal::General> {
         Ok(Scene {
             resources,
             jobs,
-            init_submit,
+            init_submit: Some(init_submit),
             device,
             queue_group,
             command_pool,
             upload_buffers,
             download_type,
+            memory_types,
             limits,
         })
     }
@@ -859,7 +886,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
             });
 
         let submission = hal::queue::Submission::new()
-            .submit(Some(&self.init_submit))
+            .submit(self.init_submit.take())
             .submit(submits);
         self.queue_group.queues[0].submit(submission, None);
     }
@@ -891,6 +918,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
             let pre_barrier = memory::Barrier::Buffer {
                 states: buffer.stable_state .. b::Access::TRANSFER_READ,
                 target: &buffer.handle,
+                families: None,
             };
             cmd_buffer.pipeline_barrier(
                 pso::PipelineStage::TOP_OF_PIPE .. pso::PipelineStage::TRANSFER,
@@ -912,6 +940,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
             let post_barrier = memory::Barrier::Buffer {
                 states: b::Access::TRANSFER_READ .. buffer.stable_state,
                 target: &buffer.handle,
+                families: None,
             };
             cmd_buffer.pipeline_barrier(
                 pso::PipelineStage::TRANSFER .. pso::PipelineStage::BOTTOM_OF_PIPE,
@@ -951,7 +980,6 @@ impl<B: hal::Backend> Scene<B, hal::General> {
         let limits = &self.limits;
 
         let i::Extent { width, height, depth } = image.kind.extent();
-        assert_eq!(image.kind.num_samples(), 1);
 
         // TODO:
         let base_format = image.format.base_format();
@@ -975,6 +1003,36 @@ impl<B: hal::Backend> Scene<B, hal::General> {
         let down_buffer = self.device.bind_buffer_memory(&down_memory, 0, unbound_buffer)
             .unwrap();
 
+        // multisampled images are resolved into a transient image first
+        let resolved = match image.kind {
+            i::Kind::D2(width, height, layers, samples) if samples > 1 => {
+                let unbound = self.device.create_image(
+                    i::Kind::D2(width, height, layers, 1),
+                    1,
+                    image.format,
+                    i::Tiling::Optimal,
+                    i::Usage::TRANSFER_SRC | i::Usage::TRANSFER_DST,
+                    i::StorageFlags::empty(),
+                ).unwrap();
+                let requirements = self.device.get_image_requirements(&unbound);
+                let memory_type = self.memory_types
+                    .iter()
+                    .enumerate()
+                    .position(|(id, mt)| {
+                        requirements.type_mask & (1 << id) != 0 &&
+                        mt.properties.contains(memory::Properties::DEVICE_LOCAL)
+                    })
+                    .unwrap()
+                    .into();
+                let memory = self.device.allocate_memory(memory_type, requirements.size)
+                    .unwrap();
+                let handle = self.device.bind_image_memory(&memory, 0, unbound)
+                    .unwrap();
+                Some((handle, memory))
+            }
+            _ => None,
+        };
+
         let mut command_pool = self.device.create_command_pool_typed(
             &self.queue_group,
             hal::pool::CommandPoolCreateFlags::empty(),
@@ -986,6 +1044,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                 states: image.stable_state .. (i::Access::TRANSFER_READ, i::Layout::TransferSrcOptimal),
                 target: &image.handle,
                 range: COLOR_RANGE.clone(), //TODO
+                families: None,
             };
             cmd_buffer.pipeline_barrier(
                 pso::PipelineStage::TOP_OF_PIPE .. pso::PipelineStage::TRANSFER,
@@ -1009,9 +1068,10 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                     depth: depth as _,
                 },
             };
-            cmd_buffer.copy_image_to_buffer(
+            cmd_buffer.resolve_to_buffer(
                 &image.handle,
                 i::Layout::TransferSrcOptimal,
+                resolved.as_ref().map(|&(ref handle, _)| handle),
                 &down_buffer,
                 &[copy],
             );
@@ -1020,6 +1080,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                 states: (i::Access::TRANSFER_READ, i::Layout::TransferSrcOptimal) .. image.stable_state,
                 target: &image.handle,
                 range: COLOR_RANGE.clone(), //TODO
+                families: None,
             };
             cmd_buffer.pipeline_barrier(
                 pso::PipelineStage::TRANSFER .. pso::PipelineStage::BOTTOM_OF_PIPE,
@@ -1036,6 +1097,10 @@ impl<B: hal::Backend> Scene<B, hal::General> {
         //queue.destroy_command_pool(command_pool);
         self.device.wait_for_fence(&copy_fence, !0);
         self.device.destroy_fence(copy_fence);
+        if let Some((handle, memory)) = resolved {
+            self.device.destroy_image(handle);
+            self.device.free_memory(memory);
+        }
 
         let mapping = self
             .device
@@ -99,6 +99,8 @@ pub enum Resource {
         input_assembler: hal::pso::InputAssemblerDesc,
         blender: hal::pso::BlendDesc,
         #[serde(default)]
+        multisampling: Option<hal::pso::Multisampling>,
+        #[serde(default)]
         depth_stencil: Option<hal::pso::DepthStencilDesc>,
         layout: String,
         subpass: SubpassRef,